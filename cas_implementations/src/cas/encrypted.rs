@@ -0,0 +1,156 @@
+use crate::encryption::StorageKey;
+use holochain_core_types::{
+    cas::{
+        content::{Address, AddressableContent, Content},
+        storage::ContentAddressableStorage,
+    },
+    error::HolochainError,
+    json::RawString,
+};
+use std::sync::{Arc, RwLock};
+
+use uuid::Uuid;
+
+/// Content addressable storage that encrypts content before handing it to another storage.
+/// Content gets stored under the address of its plaintext.
+#[derive(Clone, Debug)]
+pub struct EncryptedStorage {
+    storage: Arc<RwLock<ContentAddressableStorage>>,
+    key: StorageKey,
+    id: Uuid,
+}
+
+impl PartialEq for EncryptedStorage {
+    fn eq(&self, other: &EncryptedStorage) -> bool {
+        self.id == other.id
+    }
+}
+
+impl EncryptedStorage {
+    pub fn new(
+        storage: Arc<RwLock<ContentAddressableStorage>>,
+        key: StorageKey,
+    ) -> EncryptedStorage {
+        EncryptedStorage {
+            storage,
+            key,
+            id: Uuid::new_v4(),
+        }
+    }
+}
+
+/// Sealed content together with the address of its plaintext
+struct SealedContent {
+    address: Address,
+    content: Content,
+}
+
+impl AddressableContent for SealedContent {
+    fn address(&self) -> Address {
+        self.address.clone()
+    }
+
+    fn content(&self) -> Content {
+        self.content.clone()
+    }
+
+    fn try_from_content(_content: &Content) -> Result<Self, HolochainError> {
+        Err(HolochainError::ErrorGeneric(
+            "Sealed content does not know its address".to_string(),
+        ))
+    }
+}
+
+impl ContentAddressableStorage for EncryptedStorage {
+    fn add(&mut self, content: &AddressableContent) -> Result<(), HolochainError> {
+        let address = content.address();
+        let sealed = self.key.seal(
+            String::from(content.content()).as_bytes(),
+            Some(address.to_string().as_bytes()),
+        )?;
+        self.storage.write()?.add(&SealedContent {
+            address,
+            content: RawString::from(base64::encode(&sealed)).into(),
+        })
+    }
+
+    fn contains(&self, address: &Address) -> Result<bool, HolochainError> {
+        self.storage.read()?.contains(address)
+    }
+
+    fn fetch(&self, address: &Address) -> Result<Option<Content>, HolochainError> {
+        let sealed = match self.storage.read()?.fetch(address)? {
+            Some(sealed) => sealed,
+            None => return Ok(None),
+        };
+        let sealed: String = serde_json::from_str(&String::from(sealed))?;
+        let plaintext = self.key.open(
+            &base64::decode(&sealed)?,
+            Some(address.to_string().as_bytes()),
+        )?;
+        let content = String::from_utf8(plaintext).map_err(|_| {
+            HolochainError::ErrorGeneric(format!("Content of {} is not UTF-8", address))
+        })?;
+        Ok(Some(content.into()))
+    }
+
+    fn remove(&mut self, address: &Address) -> Result<(), HolochainError> {
+        self.storage.write()?.remove(address)
+    }
+
+    fn compact(&mut self) -> Result<(), HolochainError> {
+        self.storage.write()?.compact()
+    }
+
+    fn get_id(&self) -> Uuid {
+        self.id
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::{
+        cas::{encrypted::EncryptedStorage, memory::MemoryStorage},
+        encryption::test_storage_key,
+    };
+    use holochain_core_types::{
+        cas::{
+            content::{
+                AddressableContent, ExampleAddressableContent, OtherExampleAddressableContent,
+            },
+            storage::{ContentAddressableStorage, StorageTestSuite},
+        },
+        json::RawString,
+    };
+    use std::sync::{Arc, RwLock};
+
+    pub fn test_encrypted_cas() -> (EncryptedStorage, MemoryStorage) {
+        let memory = MemoryStorage::new();
+        let cas = EncryptedStorage::new(Arc::new(RwLock::new(memory.clone())), test_storage_key());
+        (cas, memory)
+    }
+
+    #[test]
+    /// show that content of different types can round trip through the same storage
+    fn encrypted_content_round_trip_test() {
+        let (cas, _memory) = test_encrypted_cas();
+        let test_suite = StorageTestSuite::new(cas);
+        test_suite.round_trip_test::<ExampleAddressableContent, OtherExampleAddressableContent>(
+            RawString::from("foo").into(),
+            RawString::from("bar").into(),
+        );
+    }
+
+    #[test]
+    /// show that the wrapped storage only sees sealed content under the plaintext address
+    fn encrypted_content_is_sealed_test() {
+        let (mut cas, memory) = test_encrypted_cas();
+        let content =
+            ExampleAddressableContent::try_from_content(&RawString::from("secret").into()).unwrap();
+        cas.add(&content).unwrap();
+
+        let stored = memory.fetch(&content.address()).unwrap().unwrap();
+        assert!(!String::from(stored).contains("secret"));
+        assert_eq!(Ok(Some(content.content())), cas.fetch(&content.address()));
+    }
+}
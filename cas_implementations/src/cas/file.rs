@@ -6,7 +6,7 @@ use holochain_core_types::{
     error::HolochainError,
 };
 use std::{
-    fs::{create_dir_all, read_to_string, write},
+    fs::{create_dir_all, read_to_string, remove_file, write},
     path::{Path, MAIN_SEPARATOR},
     sync::{Arc, RwLock},
 };
@@ -73,6 +73,15 @@ impl ContentAddressableStorage for FilesystemStorage {
         }
     }
 
+    fn remove(&mut self, address: &Address) -> Result<(), HolochainError> {
+        let _guard = self.lock.write()?;
+        let path = self.address_to_path(address);
+        if Path::new(&path).is_file() {
+            remove_file(path)?;
+        }
+        Ok(())
+    }
+
     fn get_id(&self) -> Uuid {
         self.id
     }
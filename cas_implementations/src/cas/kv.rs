@@ -0,0 +1,204 @@
+use crate::kv::KvStore;
+use holochain_core_types::{
+    cas::{
+        content::{Address, AddressableContent, Content},
+        storage::ContentAddressableStorage,
+    },
+    error::HolochainError,
+};
+use std::{
+    path::Path,
+    sync::{Arc, RwLock},
+};
+
+use uuid::Uuid;
+
+/// Prefix of the keys of content in a key-value store it shares with an EAV storage
+pub(crate) const CONTENT_PREFIX: &str = "cas/";
+
+/// Prefix of the keys of the records the persister keeps in the same key-value store
+pub(crate) const STATE_PREFIX: &str = "state/";
+
+/// Content addressable storage in a single-file key-value store.
+#[derive(Clone, Debug)]
+pub struct KvStorage {
+    store: Arc<RwLock<KvStore>>,
+    id: Uuid,
+    /// Keys of this storage start with it
+    prefix: &'static str,
+}
+
+impl PartialEq for KvStorage {
+    fn eq(&self, other: &KvStorage) -> bool {
+        self.id == other.id
+    }
+}
+
+impl KvStorage {
+    /// Uses the given store, which can also be used by an EavKvStorage at the same time.
+    pub fn new(store: Arc<RwLock<KvStore>>) -> KvStorage {
+        KvStorage {
+            store,
+            id: Uuid::new_v4(),
+            prefix: CONTENT_PREFIX,
+        }
+    }
+
+    /// Uses the given store for the records of a persister, apart from the content.
+    /// Unlike content, these records change under the same address, so they get
+    /// overwritten when they are added again.
+    pub fn for_state(store: Arc<RwLock<KvStore>>) -> KvStorage {
+        KvStorage {
+            prefix: STATE_PREFIX,
+            ..KvStorage::new(store)
+        }
+    }
+
+    /// Opens the store in the given file and uses it only for content.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<KvStorage, HolochainError> {
+        Ok(KvStorage::new(Arc::new(RwLock::new(KvStore::open(path)?))))
+    }
+
+    /// Opens the store in the given file and uses it only for the records of a persister.
+    pub fn open_state<P: AsRef<Path>>(path: P) -> Result<KvStorage, HolochainError> {
+        Ok(KvStorage::for_state(Arc::new(RwLock::new(KvStore::open(
+            path,
+        )?))))
+    }
+
+    fn key(&self, address: &Address) -> Vec<u8> {
+        format!("{}{}", self.prefix, address).into_bytes()
+    }
+}
+
+impl ContentAddressableStorage for KvStorage {
+    fn add(&mut self, content: &AddressableContent) -> Result<(), HolochainError> {
+        let key = self.key(&content.address());
+        let mut store = self.store.write()?;
+        // content never changes for an address, so there is nothing to write twice
+        if self.prefix == CONTENT_PREFIX && store.contains(&key) {
+            return Ok(());
+        }
+        store.write(vec![(key, String::from(content.content()).into_bytes())])
+    }
+
+    fn contains(&self, address: &Address) -> Result<bool, HolochainError> {
+        Ok(self.store.read()?.contains(&self.key(address)))
+    }
+
+    fn fetch(&self, address: &Address) -> Result<Option<Content>, HolochainError> {
+        match self.store.read()?.get(&self.key(address))? {
+            Some(bytes) => {
+                let content = String::from_utf8(bytes).map_err(|_| {
+                    HolochainError::ErrorGeneric(format!("Content of {} is not UTF-8", address))
+                })?;
+                Ok(Some(content.into()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn remove(&mut self, address: &Address) -> Result<(), HolochainError> {
+        self.store.write()?.delete(vec![self.key(address)])
+    }
+
+    fn compact(&mut self) -> Result<(), HolochainError> {
+        self.store.write()?.compact()
+    }
+
+    fn get_id(&self) -> Uuid {
+        self.id
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    extern crate tempfile;
+
+    use self::tempfile::{tempdir, TempDir};
+    use crate::cas::kv::KvStorage;
+    use holochain_core_types::{
+        cas::{
+            content::{
+                Address, AddressableContent, Content, ExampleAddressableContent,
+                OtherExampleAddressableContent,
+            },
+            storage::{ContentAddressableStorage, StorageTestSuite},
+        },
+        error::HolochainError,
+        json::RawString,
+    };
+
+    pub fn test_kv_cas() -> (KvStorage, TempDir) {
+        let dir = tempdir().expect("Could not create a tempdir for CAS testing");
+        (KvStorage::open(dir.path().join("storage.db")).unwrap(), dir)
+    }
+
+    #[test]
+    /// show that content of different types can round trip through the same storage
+    fn kv_content_round_trip_test() {
+        let (cas, _dir) = test_kv_cas();
+        let test_suite = StorageTestSuite::new(cas);
+        test_suite.round_trip_test::<ExampleAddressableContent, OtherExampleAddressableContent>(
+            RawString::from("foo").into(),
+            RawString::from("bar").into(),
+        );
+    }
+
+    #[test]
+    /// show that content is still there after opening the store again
+    fn kv_content_persists_test() {
+        let (mut cas, dir) = test_kv_cas();
+        let content =
+            ExampleAddressableContent::try_from_content(&RawString::from("foo").into()).unwrap();
+        cas.add(&content).unwrap();
+        drop(cas);
+
+        let cas = KvStorage::open(dir.path().join("storage.db")).unwrap();
+        assert_eq!(Ok(Some(content.content())), cas.fetch(&content.address()));
+    }
+
+    /// A record of a persister, that keeps its address when it changes
+    struct Record(Address, Content);
+
+    impl AddressableContent for Record {
+        fn address(&self) -> Address {
+            self.0.clone()
+        }
+
+        fn content(&self) -> Content {
+            self.1.clone()
+        }
+
+        fn try_from_content(_content: &Content) -> Result<Self, HolochainError> {
+            Err(HolochainError::ErrorGeneric("no address".to_string()))
+        }
+    }
+
+    #[test]
+    /// show that persisted state lives apart from content and gets overwritten
+    fn kv_state_is_kept_apart_from_content_test() {
+        let (mut cas, dir) = test_kv_cas();
+        let mut state = KvStorage::for_state(cas.store.clone());
+        let address = Address::from("Record");
+        let content = Content::from(RawString::from("foo"));
+        let changed = Content::from(RawString::from("bar"));
+
+        cas.add(&Record(address.clone(), content.clone())).unwrap();
+        assert_eq!(Ok(false), state.contains(&address));
+        // content at an address does not change
+        cas.add(&Record(address.clone(), changed.clone())).unwrap();
+        assert_eq!(Ok(Some(content.clone())), cas.fetch(&address));
+
+        state
+            .add(&Record(address.clone(), content.clone()))
+            .unwrap();
+        state
+            .add(&Record(address.clone(), changed.clone()))
+            .unwrap();
+        drop(state);
+        let state = KvStorage::open_state(dir.path().join("storage.db")).unwrap();
+        assert_eq!(Ok(Some(changed)), state.fetch(&address));
+        assert_eq!(Ok(Some(content)), cas.fetch(&address));
+    }
+}
@@ -49,6 +49,12 @@ impl ContentAddressableStorage for MemoryStorage {
         Ok(map.get(address).cloned())
     }
 
+    fn remove(&mut self, address: &Address) -> Result<(), HolochainError> {
+        let mut map = self.storage.write()?;
+        map.remove(address);
+        Ok(())
+    }
+
     fn get_id(&self) -> Uuid {
         self.id
     }
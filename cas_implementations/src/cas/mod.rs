@@ -1,2 +1,4 @@
+pub mod encrypted;
 pub mod file;
+pub mod kv;
 pub mod memory;
@@ -0,0 +1,237 @@
+use crate::encryption::StorageKey;
+use holochain_core_types::{
+    eav::{
+        Attribute, AttributeFilter, EavQuery, Entity, EntityAttributeValueIndex,
+        EntityAttributeValueStorage, IndexQuery, Value, Versions,
+    },
+    error::HolochainError,
+};
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, RwLock},
+};
+
+use uuid::Uuid;
+
+/// EAV storage that encrypts attributes before handing them to another EAV storage.
+/// Entities and values are addresses and stay in the clear. Attributes get sealed
+/// deterministically, so queries for an attribute still find it.
+#[derive(Clone, Debug)]
+pub struct EncryptedEavStorage {
+    storage: Arc<RwLock<EntityAttributeValueStorage>>,
+    key: StorageKey,
+    id: Uuid,
+}
+
+impl PartialEq for EncryptedEavStorage {
+    fn eq(&self, other: &EncryptedEavStorage) -> bool {
+        self.id == other.id
+    }
+}
+
+impl EncryptedEavStorage {
+    pub fn new(
+        storage: Arc<RwLock<EntityAttributeValueStorage>>,
+        key: StorageKey,
+    ) -> EncryptedEavStorage {
+        EncryptedEavStorage {
+            storage,
+            key,
+            id: Uuid::new_v4(),
+        }
+    }
+
+    /// Sealed attributes are URL safe base64, which passes the attribute validation
+    fn seal_attribute(&self, attribute: &Attribute) -> Result<Attribute, HolochainError> {
+        let sealed = self.key.seal_deterministic(attribute.as_bytes())?;
+        Ok(base64::encode_config(&sealed, base64::URL_SAFE_NO_PAD))
+    }
+
+    fn open_attribute(&self, sealed: &Attribute) -> Result<Attribute, HolochainError> {
+        let sealed = base64::decode_config(sealed, base64::URL_SAFE_NO_PAD)?;
+        String::from_utf8(self.key.open(&sealed, None)?)
+            .map_err(|_| HolochainError::ErrorGeneric("Attribute is not UTF-8".to_string()))
+    }
+
+    fn with_attribute(
+        eavi: &EntityAttributeValueIndex,
+        attribute: Attribute,
+    ) -> Result<EntityAttributeValueIndex, HolochainError> {
+        EntityAttributeValueIndex::new_with_index(
+            &eavi.entity(),
+            &attribute,
+            &eavi.value(),
+            eavi.index(),
+        )
+    }
+}
+
+impl EntityAttributeValueStorage for EncryptedEavStorage {
+    fn add_eavi(
+        &mut self,
+        eav: &EntityAttributeValueIndex,
+    ) -> Result<Option<EntityAttributeValueIndex>, HolochainError> {
+        let sealed = Self::with_attribute(eav, self.seal_attribute(&eav.attribute())?)?;
+        self.storage
+            .write()?
+            .add_eavi(&sealed)?
+            .map(|stored| Self::with_attribute(&stored, eav.attribute()))
+            .transpose()
+    }
+
+    fn fetch_eavi(
+        &self,
+        entity: Option<Entity>,
+        attribute: Option<Attribute>,
+        value: Option<Value>,
+        index_query: IndexQuery,
+    ) -> Result<BTreeSet<EntityAttributeValueIndex>, HolochainError> {
+        let attribute = attribute
+            .map(|attribute| self.seal_attribute(&attribute))
+            .transpose()?;
+        self.storage
+            .read()?
+            .fetch_eavi(entity, attribute, value, index_query)?
+            .iter()
+            .map(|eavi| Self::with_attribute(eavi, self.open_attribute(&eavi.attribute())?))
+            .collect()
+    }
+
+    fn query_eavi(
+        &self,
+        query: &EavQuery,
+    ) -> Result<Vec<EntityAttributeValueIndex>, HolochainError> {
+        // Sealed attributes keep neither prefixes nor order, so only exact attributes can be
+        // looked up in the other storage. The rest of the query runs on the opened EAVIs.
+        let attribute = match &query.attribute {
+            AttributeFilter::Exact(attribute) => {
+                AttributeFilter::Exact(self.seal_attribute(attribute)?)
+            }
+            _ => AttributeFilter::Any,
+        };
+        let sealed_query = EavQuery {
+            attribute,
+            versions: Versions::All,
+            offset: 0,
+            limit: None,
+            ..query.clone()
+        };
+        let opened = self
+            .storage
+            .read()?
+            .query_eavi(&sealed_query)?
+            .iter()
+            .map(|eavi| Self::with_attribute(eavi, self.open_attribute(&eavi.attribute())?))
+            .collect::<Result<Vec<_>, HolochainError>>()?;
+        Ok(query.run(opened))
+    }
+
+    fn remove_eavi(&mut self, eav: &EntityAttributeValueIndex) -> Result<(), HolochainError> {
+        let sealed = Self::with_attribute(eav, self.seal_attribute(&eav.attribute())?)?;
+        self.storage.write()?.remove_eavi(&sealed)
+    }
+
+    fn compact(&mut self) -> Result<(), HolochainError> {
+        self.storage.write()?.compact()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::{
+        eav::{encrypted::EncryptedEavStorage, memory::EavMemoryStorage},
+        encryption::test_storage_key,
+    };
+    use holochain_core_types::{
+        cas::{
+            content::{AddressableContent, ExampleAddressableContent},
+            storage::EavTestSuite,
+        },
+        eav::{EntityAttributeValueIndex, EntityAttributeValueStorage, IndexQuery},
+        json::RawString,
+    };
+    use std::sync::{Arc, RwLock};
+
+    fn test_encrypted_eav() -> (EncryptedEavStorage, EavMemoryStorage) {
+        let memory = EavMemoryStorage::new();
+        let eav =
+            EncryptedEavStorage::new(Arc::new(RwLock::new(memory.clone())), test_storage_key());
+        (eav, memory)
+    }
+
+    #[test]
+    fn encrypted_eav_round_trip() {
+        let entity_content =
+            ExampleAddressableContent::try_from_content(&RawString::from("foo").into()).unwrap();
+        let attribute = "favourite-color".to_string();
+        let value_content =
+            ExampleAddressableContent::try_from_content(&RawString::from("blue").into()).unwrap();
+        EavTestSuite::test_round_trip(
+            test_encrypted_eav().0,
+            entity_content,
+            attribute,
+            value_content,
+        )
+    }
+
+    #[test]
+    fn encrypted_eav_one_to_many() {
+        EavTestSuite::test_one_to_many::<ExampleAddressableContent, EncryptedEavStorage>(
+            test_encrypted_eav().0,
+        )
+    }
+
+    #[test]
+    fn encrypted_eav_many_to_one() {
+        EavTestSuite::test_many_to_one::<ExampleAddressableContent, EncryptedEavStorage>(
+            test_encrypted_eav().0,
+        )
+    }
+
+    #[test]
+    fn encrypted_eav_range() {
+        EavTestSuite::test_range::<ExampleAddressableContent, EncryptedEavStorage>(
+            test_encrypted_eav().0,
+        );
+    }
+
+    #[test]
+    fn encrypted_eav_query() {
+        EavTestSuite::test_query::<ExampleAddressableContent, EncryptedEavStorage>(
+            test_encrypted_eav().0,
+        );
+    }
+
+    #[test]
+    fn encrypted_eav_attributes_are_sealed() {
+        let (mut eav_storage, memory) = test_encrypted_eav();
+        let entity =
+            ExampleAddressableContent::try_from_content(&RawString::from("foo").into()).unwrap();
+        let value =
+            ExampleAddressableContent::try_from_content(&RawString::from("blue").into()).unwrap();
+        let eavi = EntityAttributeValueIndex::new(
+            &entity.address(),
+            &"favourite-color".to_string(),
+            &value.address(),
+        )
+        .unwrap();
+        eav_storage.add_eavi(&eavi).unwrap();
+
+        let stored = memory
+            .fetch_eavi(Some(entity.address()), None, None, IndexQuery::default())
+            .unwrap();
+        assert_eq!(stored.len(), 1);
+        let stored = stored.iter().next().unwrap();
+        assert_ne!(stored.attribute(), eavi.attribute());
+        assert_eq!(stored.value(), eavi.value());
+        assert!(memory
+            .fetch_eavi(
+                None,
+                Some("favourite-color".to_string()),
+                None,
+                IndexQuery::default()
+            )
+            .unwrap()
+            .is_empty());
+    }
+}
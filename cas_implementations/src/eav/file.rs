@@ -1,16 +1,16 @@
-use glob::glob;
+use glob::{glob, Pattern};
 use holochain_core_types::{
     cas::content::AddressableContent,
     eav::{
-        get_latest, increment_key_till_no_collision, Attribute, Entity, EntityAttributeValueIndex,
-        EntityAttributeValueStorage, IndexQuery, Value,
+        get_latest, increment_key_till_no_collision, Attribute, AttributeFilter, EavQuery, Entity,
+        EntityAttributeValueIndex, EntityAttributeValueStorage, IndexQuery, Value,
     },
     error::{HcResult, HolochainError},
     json::JsonString,
 };
 use std::{
     collections::BTreeSet,
-    fs::{create_dir_all, File, OpenOptions},
+    fs::{create_dir_all, remove_file, File, OpenOptions},
     io::prelude::*,
     path::{Path, PathBuf, MAIN_SEPARATOR},
     sync::{Arc, RwLock},
@@ -81,6 +81,22 @@ pub fn read_eav(parent_path: PathBuf) -> HcResult<Vec<String>> {
     }
 }
 
+/// Glob for the attribute directories the filter can match
+fn attribute_glob(attribute: &AttributeFilter) -> String {
+    match attribute {
+        AttributeFilter::Any => "*".to_string(),
+        AttributeFilter::Exact(exact) => Pattern::escape(exact),
+        AttributeFilter::Prefix(prefix) => format!("{}*", Pattern::escape(prefix)),
+        AttributeFilter::Glob(glob) => glob
+            .chars()
+            .map(|c| match c {
+                '*' | '?' => c.to_string(),
+                _ => Pattern::escape(&c.to_string()),
+            })
+            .collect(),
+    }
+}
+
 impl EavFileStorage {
     pub fn new(dir_path: String) -> HcResult<EavFileStorage> {
         Ok(EavFileStorage {
@@ -90,11 +106,8 @@ impl EavFileStorage {
         })
     }
 
-    fn write_to_file(
-        &self,
-        subscript: String,
-        eav: &EntityAttributeValueIndex,
-    ) -> Result<(), HolochainError> {
+    /// Returns the directory and the file the EAV gets stored in below the given subscript
+    fn eav_path(&self, subscript: String, eav: &EntityAttributeValueIndex) -> (String, String) {
         let address: String = match &*subscript {
             ENTITY_DIR => eav.entity().to_string(),
             ATTRIBUTE_DIR => eav.attribute(),
@@ -108,9 +121,19 @@ impl EavFileStorage {
             eav.index().clone().to_string(),
         ]
         .join(&MAIN_SEPARATOR.to_string());
-        create_dir_all(path.clone())?;
-        let address_path = vec![path, eav.address().to_string()].join(&MAIN_SEPARATOR.to_string());
+        let address_path =
+            vec![path.clone(), eav.address().to_string()].join(&MAIN_SEPARATOR.to_string());
         let full_path = vec![address_path.clone(), "txt".to_string()].join(&".".to_string());
+        (path, full_path)
+    }
+
+    fn write_to_file(
+        &self,
+        subscript: String,
+        eav: &EntityAttributeValueIndex,
+    ) -> Result<(), HolochainError> {
+        let (path, full_path) = self.eav_path(subscript, eav);
+        create_dir_all(path)?;
         let mut f = File::create(full_path)?;
         writeln!(f, "{}", eav.content())?;
         Ok(())
@@ -247,6 +270,44 @@ impl EntityAttributeValueStorage for EavFileStorage {
                 .collect::<BTreeSet<EntityAttributeValueIndex>>())
         }
     }
+
+    fn query_eavi(
+        &self,
+        query: &EavQuery,
+    ) -> Result<Vec<EntityAttributeValueIndex>, HolochainError> {
+        let _guard = self.lock.read()?;
+        // every directory holds all EAVs, so reading the narrowest one is enough
+        let contents = match (&query.entity, &query.attribute, &query.values) {
+            (Some(entity), _, _) => self.read_from_dir(ENTITY_DIR.to_string(), Some(entity))?,
+            (None, AttributeFilter::Any, Some(values)) => {
+                let mut contents = BTreeSet::new();
+                for value in values {
+                    contents.extend(self.read_from_dir(VALUE_DIR.to_string(), Some(value))?);
+                }
+                contents
+            }
+            (None, attribute, _) => {
+                self.read_from_dir(ATTRIBUTE_DIR.to_string(), Some(attribute_glob(attribute)))?
+            }
+        };
+        let eavis = contents
+            .into_iter()
+            .map(|content| EntityAttributeValueIndex::try_from_content(&JsonString::from(content)))
+            .collect::<HcResult<Vec<_>>>()
+            .map_err(|_| HolochainError::ErrorGeneric("Error Converting EAVs".to_string()))?;
+        Ok(query.run(eavis))
+    }
+
+    fn remove_eavi(&mut self, eav: &EntityAttributeValueIndex) -> Result<(), HolochainError> {
+        let _guard = self.lock.write()?;
+        for subscript in vec![ENTITY_DIR, ATTRIBUTE_DIR, VALUE_DIR] {
+            let (_, full_path) = self.eav_path(subscript.to_string(), eav);
+            if Path::new(&full_path).is_file() {
+                remove_file(full_path)?;
+            }
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -305,4 +366,12 @@ pub mod tests {
         EavTestSuite::test_range::<ExampleAddressableContent, EavFileStorage>(eav_storage);
     }
 
+    #[test]
+    fn file_eav_query() {
+        let temp = tempdir().expect("test was supposed to create temp dir");
+        let temp_path = String::from(temp.path().to_str().expect("temp dir could not be string"));
+        let eav_storage = EavFileStorage::new(temp_path).unwrap();
+        EavTestSuite::test_query::<ExampleAddressableContent, EavFileStorage>(eav_storage);
+    }
+
 }
@@ -0,0 +1,353 @@
+use crate::kv::KvStore;
+use holochain_core_types::{
+    cas::content::AddressableContent,
+    eav::{
+        Attribute, AttributeFilter, EavQuery, Entity, EntityAttributeValueIndex,
+        EntityAttributeValueStorage, Index, IndexQuery, Value,
+    },
+    error::{HcResult, HolochainError},
+    json::JsonString,
+};
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::Path,
+    sync::{Arc, RwLock},
+};
+use uuid::Uuid;
+
+/// Keys of the EAVIs themselves
+pub(crate) const INDEX_PREFIX: &str = "eav/i\0";
+/// Keys that start with the entity, then attribute and value
+const ENTITY_PREFIX: &str = "eav/e\0";
+/// Keys that start with the attribute, then entity and value
+const ATTRIBUTE_PREFIX: &str = "eav/a\0";
+/// Keys that start with the value, then entity and attribute
+const VALUE_PREFIX: &str = "eav/v\0";
+
+/// EAV storage in a single-file key-value store.
+/// Every EAVI is stored once under its index and gets looked up through keys that start
+/// with its entity, attribute or value, which are all written in the same transaction.
+#[derive(Clone, Debug)]
+pub struct EavKvStorage {
+    store: Arc<RwLock<KvStore>>,
+    id: Uuid,
+}
+
+impl PartialEq for EavKvStorage {
+    fn eq(&self, other: &EavKvStorage) -> bool {
+        self.id == other.id
+    }
+}
+
+fn key(prefix: &str, parts: &[&str]) -> Vec<u8> {
+    let mut key = prefix.to_string();
+    for part in parts {
+        key.push_str(part);
+        key.push('\0');
+    }
+    key.into_bytes()
+}
+
+fn index_key(index: Index) -> Vec<u8> {
+    format!("{}{}", INDEX_PREFIX, index).into_bytes()
+}
+
+/// Reads the EAVI back from one of the keys that look it up, which hold all its parts
+fn eavi_from_key(prefix: &str, key: &[u8]) -> HcResult<EntityAttributeValueIndex> {
+    let invalid = || HolochainError::ErrorGeneric("Invalid EAV key in store".to_string());
+    let parts = String::from_utf8(key[prefix.len()..].to_vec()).map_err(|_| invalid())?;
+    let parts: Vec<&str> = parts.split('\0').collect();
+    let (entity, attribute, value) = match (prefix, &parts[..]) {
+        (ENTITY_PREFIX, [e, a, v, _, ""]) => (e, a, v),
+        (ATTRIBUTE_PREFIX, [a, e, v, _, ""]) => (e, a, v),
+        (VALUE_PREFIX, [v, e, a, _, ""]) => (e, a, v),
+        _ => return Err(invalid()),
+    };
+    let index = parts[3].parse().map_err(|_| invalid())?;
+    EntityAttributeValueIndex::new_with_index(
+        &Entity::from(*entity),
+        &attribute.to_string(),
+        &Value::from(*value),
+        index,
+    )
+}
+
+impl EavKvStorage {
+    /// Uses the given store, which can also be used by a KvStorage at the same time.
+    pub fn new(store: Arc<RwLock<KvStore>>) -> EavKvStorage {
+        EavKvStorage {
+            store,
+            id: Uuid::new_v4(),
+        }
+    }
+
+    /// Opens the store in the given file and uses it only for EAVs.
+    pub fn open<P: AsRef<Path>>(path: P) -> HcResult<EavKvStorage> {
+        Ok(EavKvStorage::new(Arc::new(RwLock::new(KvStore::open(
+            path,
+        )?))))
+    }
+
+    /// Loads the EAVIs whose index is stored under the given keys
+    fn load(store: &KvStore, keys: Vec<Vec<u8>>) -> HcResult<BTreeSet<EntityAttributeValueIndex>> {
+        let mut eavis = BTreeSet::new();
+        for key in keys {
+            let index = store.get(&key)?.ok_or_else(|| {
+                HolochainError::ErrorGeneric("EAV key vanished from store".to_string())
+            })?;
+            let content = store
+                .get(&[INDEX_PREFIX.as_bytes(), &index[..]].concat())?
+                .ok_or_else(|| {
+                    HolochainError::ErrorGeneric("EAV index without EAV in store".to_string())
+                })?;
+            let content = String::from_utf8(content)
+                .map_err(|_| HolochainError::ErrorGeneric("Error Converting EAVs".to_string()))?;
+            eavis.insert(EntityAttributeValueIndex::try_from_content(
+                &JsonString::from(content),
+            )?);
+        }
+        Ok(eavis)
+    }
+}
+
+impl EntityAttributeValueStorage for EavKvStorage {
+    fn add_eavi(
+        &mut self,
+        eav: &EntityAttributeValueIndex,
+    ) -> Result<Option<EntityAttributeValueIndex>, HolochainError> {
+        let mut store = self.store.write()?;
+        let mut new_eav = eav.clone();
+        while store.contains(&index_key(new_eav.index())) {
+            let index = new_eav.index() + 1;
+            new_eav.set_index(index);
+        }
+
+        let entity = new_eav.entity().to_string();
+        let attribute = new_eav.attribute();
+        let value = new_eav.value().to_string();
+        let index = new_eav.index().to_string();
+        let index_bytes = index.clone().into_bytes();
+        store.write(vec![
+            (
+                index_key(new_eav.index()),
+                String::from(new_eav.content()).into_bytes(),
+            ),
+            (
+                key(ENTITY_PREFIX, &[&entity, &attribute, &value, &index]),
+                index_bytes.clone(),
+            ),
+            (
+                key(ATTRIBUTE_PREFIX, &[&attribute, &entity, &value, &index]),
+                index_bytes.clone(),
+            ),
+            (
+                key(VALUE_PREFIX, &[&value, &entity, &attribute, &index]),
+                index_bytes,
+            ),
+        ])?;
+        Ok(Some(new_eav))
+    }
+
+    fn fetch_eavi(
+        &self,
+        entity: Option<Entity>,
+        attribute: Option<Attribute>,
+        value: Option<Value>,
+        index_query: IndexQuery,
+    ) -> Result<BTreeSet<EntityAttributeValueIndex>, HolochainError> {
+        let store = self.store.read()?;
+        // narrow the candidates down with the longest prefix the query allows
+        let keys = match (&entity, &attribute, &value) {
+            (Some(e), Some(a), Some(v)) => {
+                store.keys_with_prefix(&key(ENTITY_PREFIX, &[&e.to_string(), a, &v.to_string()]))
+            }
+            (Some(e), Some(a), None) => {
+                store.keys_with_prefix(&key(ENTITY_PREFIX, &[&e.to_string(), a]))
+            }
+            (Some(e), None, _) => store.keys_with_prefix(&key(ENTITY_PREFIX, &[&e.to_string()])),
+            (None, Some(a), _) => store.keys_with_prefix(&key(ATTRIBUTE_PREFIX, &[a])),
+            (None, None, Some(v)) => store.keys_with_prefix(&key(VALUE_PREFIX, &[&v.to_string()])),
+            (None, None, None) => store.keys_with_prefix(ENTITY_PREFIX.as_bytes()),
+        };
+        let candidates: BTreeSet<EntityAttributeValueIndex> = EavKvStorage::load(&store, keys)?
+            .into_iter()
+            .filter(|e| EntityAttributeValueIndex::filter_on_eav(&e.entity(), entity.as_ref()))
+            .filter(|e| {
+                EntityAttributeValueIndex::filter_on_eav(&e.attribute(), attribute.as_ref())
+            })
+            .filter(|e| EntityAttributeValueIndex::filter_on_eav(&e.value(), value.as_ref()))
+            .collect();
+
+        let mut latest: HashMap<(Entity, Attribute, Value), Index> = HashMap::new();
+        for e in candidates.iter() {
+            let latest_index = latest
+                .entry((e.entity(), e.attribute(), e.value()))
+                .or_insert(e.index());
+            if e.index() > *latest_index {
+                *latest_index = e.index();
+            }
+        }
+        let is_latest = |e: &EntityAttributeValueIndex| {
+            latest[&(e.entity(), e.attribute(), e.value())] == e.index()
+        };
+
+        Ok(candidates
+            .iter()
+            .filter(|e| {
+                index_query
+                    .start()
+                    .map(|start| start <= e.index())
+                    .unwrap_or_else(|| is_latest(e))
+            })
+            .filter(|e| {
+                index_query
+                    .end()
+                    .map(|end| end >= e.index())
+                    .unwrap_or_else(|| is_latest(e))
+            })
+            .cloned()
+            .collect())
+    }
+
+    fn query_eavi(
+        &self,
+        query: &EavQuery,
+    ) -> Result<Vec<EntityAttributeValueIndex>, HolochainError> {
+        let store = self.store.read()?;
+        // the lookup keys hold whole EAVIs, so only the ones with the narrowest prefix get read
+        let attribute_prefix = match &query.attribute {
+            AttributeFilter::Exact(attribute) => key("", &[attribute]),
+            attribute => attribute.prefix().into_bytes(),
+        };
+        let lookups = match (&query.entity, &query.attribute, &query.values) {
+            (Some(entity), _, _) => vec![(
+                ENTITY_PREFIX,
+                [key(ENTITY_PREFIX, &[&entity.to_string()]), attribute_prefix].concat(),
+            )],
+            (None, AttributeFilter::Any, Some(values)) => values
+                .iter()
+                .map(|value| (VALUE_PREFIX, key(VALUE_PREFIX, &[&value.to_string()])))
+                .collect(),
+            (None, AttributeFilter::Any, None) => {
+                vec![(ENTITY_PREFIX, ENTITY_PREFIX.as_bytes().to_vec())]
+            }
+            (None, _, _) => vec![(
+                ATTRIBUTE_PREFIX,
+                [ATTRIBUTE_PREFIX.as_bytes(), &attribute_prefix[..]].concat(),
+            )],
+        };
+        let mut candidates = Vec::new();
+        for (prefix, lookup) in lookups {
+            for key in store.keys_with_prefix(&lookup) {
+                candidates.push(eavi_from_key(prefix, &key)?);
+            }
+        }
+        Ok(query.run(candidates))
+    }
+
+    fn remove_eavi(&mut self, eav: &EntityAttributeValueIndex) -> Result<(), HolochainError> {
+        let mut store = self.store.write()?;
+        // the index might belong to another EAVI
+        let stored = store.get(&index_key(eav.index()))?;
+        if stored != Some(String::from(eav.content()).into_bytes()) {
+            return Ok(());
+        }
+        let entity = eav.entity().to_string();
+        let attribute = eav.attribute();
+        let value = eav.value().to_string();
+        let index = eav.index().to_string();
+        store.delete(vec![
+            index_key(eav.index()),
+            key(ENTITY_PREFIX, &[&entity, &attribute, &value, &index]),
+            key(ATTRIBUTE_PREFIX, &[&attribute, &entity, &value, &index]),
+            key(VALUE_PREFIX, &[&value, &entity, &attribute, &index]),
+        ])
+    }
+
+    fn compact(&mut self) -> Result<(), HolochainError> {
+        self.store.write()?.compact()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    extern crate tempfile;
+
+    use self::tempfile::{tempdir, TempDir};
+    use crate::eav::kv::EavKvStorage;
+    use holochain_core_types::{
+        cas::{
+            content::{AddressableContent, ExampleAddressableContent},
+            storage::EavTestSuite,
+        },
+        eav::{EntityAttributeValueIndex, EntityAttributeValueStorage, IndexQuery},
+        json::RawString,
+    };
+
+    pub fn test_kv_eav() -> (EavKvStorage, TempDir) {
+        let dir = tempdir().expect("Could not create a tempdir for EAV testing");
+        (
+            EavKvStorage::open(dir.path().join("storage.db")).unwrap(),
+            dir,
+        )
+    }
+
+    #[test]
+    fn kv_eav_round_trip() {
+        let (eav_storage, _dir) = test_kv_eav();
+        let entity_content =
+            ExampleAddressableContent::try_from_content(&RawString::from("foo").into()).unwrap();
+        let attribute = "favourite-color".to_string();
+        let value_content =
+            ExampleAddressableContent::try_from_content(&RawString::from("blue").into()).unwrap();
+        EavTestSuite::test_round_trip(eav_storage, entity_content, attribute, value_content)
+    }
+
+    #[test]
+    fn kv_eav_one_to_many() {
+        let (eav_storage, _dir) = test_kv_eav();
+        EavTestSuite::test_one_to_many::<ExampleAddressableContent, EavKvStorage>(eav_storage)
+    }
+
+    #[test]
+    fn kv_eav_many_to_one() {
+        let (eav_storage, _dir) = test_kv_eav();
+        EavTestSuite::test_many_to_one::<ExampleAddressableContent, EavKvStorage>(eav_storage)
+    }
+
+    #[test]
+    fn kv_eav_range() {
+        let (eav_storage, _dir) = test_kv_eav();
+        EavTestSuite::test_range::<ExampleAddressableContent, EavKvStorage>(eav_storage);
+    }
+
+    #[test]
+    fn kv_eav_query() {
+        let (eav_storage, _dir) = test_kv_eav();
+        EavTestSuite::test_query::<ExampleAddressableContent, EavKvStorage>(eav_storage);
+    }
+
+    #[test]
+    fn kv_eav_persists() {
+        let (mut eav_storage, dir) = test_kv_eav();
+        let entity =
+            ExampleAddressableContent::try_from_content(&RawString::from("foo").into()).unwrap();
+        let value =
+            ExampleAddressableContent::try_from_content(&RawString::from("bar").into()).unwrap();
+        let eav = EntityAttributeValueIndex::new_with_index(
+            &entity.address(),
+            &"attribute".to_string(),
+            &value.address(),
+            7,
+        )
+        .unwrap();
+        eav_storage.add_eavi(&eav).unwrap();
+        drop(eav_storage);
+
+        let eav_storage = EavKvStorage::open(dir.path().join("storage.db")).unwrap();
+        let fetched = eav_storage
+            .fetch_eavi(Some(entity.address()), None, None, IndexQuery::default())
+            .unwrap();
+        assert_eq!(fetched.into_iter().collect::<Vec<_>>(), vec![eav]);
+    }
+}
@@ -1,7 +1,7 @@
 use holochain_core_types::{
     eav::{
-        get_latest, increment_key_till_no_collision, Attribute, Entity, EntityAttributeValueIndex,
-        EntityAttributeValueStorage, IndexQuery, Value,
+        get_latest, increment_key_till_no_collision, Attribute, EavQuery, Entity,
+        EntityAttributeValueIndex, EntityAttributeValueStorage, IndexQuery, Value,
     },
     error::HolochainError,
 };
@@ -82,6 +82,22 @@ impl EntityAttributeValueStorage for EavMemoryStorage {
             })
             .collect::<BTreeSet<EntityAttributeValueIndex>>())
     }
+
+    fn query_eavi(
+        &self,
+        query: &EavQuery,
+    ) -> Result<Vec<EntityAttributeValueIndex>, HolochainError> {
+        Ok(query.run(self.storage.read()?.iter().cloned()))
+    }
+
+    fn remove_eavi(&mut self, eav: &EntityAttributeValueIndex) -> Result<(), HolochainError> {
+        let mut map = self.storage.write()?;
+        // EAVIs are ordered by their index only, so make sure it is the same EAVI
+        if map.get(eav) == Some(eav) {
+            map.remove(eav);
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -128,4 +144,11 @@ pub mod tests {
         EavTestSuite::test_range::<ExampleAddressableContent, EavMemoryStorage>(eav_storage);
     }
 
+    #[test]
+    fn memory_eav_query() {
+        EavTestSuite::test_query::<ExampleAddressableContent, EavMemoryStorage>(
+            EavMemoryStorage::new(),
+        )
+    }
+
 }
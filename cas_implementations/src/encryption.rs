@@ -0,0 +1,194 @@
+//! Encryption at rest for the CAS and EAV storages of an instance.
+//!
+//! The encrypted storages wrap any other storage and seal what they hand down to it with
+//! a symmetric key (`aead`, XChaCha20-Poly1305). Addresses stay in the clear, so content can
+//! still be looked up by the address of its plaintext, and the address is authenticated
+//! together with the sealed content so stored values can't be swapped.
+//!
+//! EAV attributes get sealed deterministically, with a nonce derived from the attribute
+//! itself, so the same attribute always results in the same cipher text and queries can
+//! still match on it.
+
+use holochain_core_types::error::{HcResult, HolochainError};
+use holochain_sodium::{
+    aead, error::SodiumError, hash, kdf, random::random_secbuf, secbuf::SecBuf,
+};
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+/// kdf context used to derive the sealing and the nonce key from the storage key
+const KDF_CONTEXT: &[u8; kdf::CONTEXTBYTES] = b"HCSEALKY";
+
+struct SealingKeys {
+    seal: SecBuf,
+    nonce: SecBuf,
+}
+
+// SecBufs are not Send because they might be backed by raw sodium memory.
+// SealingKeys exclusively own their buffers and only lend them to sodium calls,
+// so moving them to another thread is fine.
+unsafe impl Send for SealingKeys {}
+
+/// Key the encrypted storages seal their data with.
+/// Clones share the same key material.
+#[derive(Clone)]
+pub struct StorageKey {
+    keys: Arc<Mutex<SealingKeys>>,
+}
+
+impl fmt::Debug for StorageKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "StorageKey")
+    }
+}
+
+impl StorageKey {
+    /// Derives the keys for sealing values and for deterministic nonces from `key`,
+    /// which should be an agent's storage key.
+    pub fn new(key: &mut SecBuf) -> HcResult<StorageKey> {
+        let mut context = secbuf_from_bytes(KDF_CONTEXT);
+        let mut seal = SecBuf::with_secure(aead::KEYBYTES);
+        let mut nonce = SecBuf::with_secure(aead::KEYBYTES);
+        kdf::derive(&mut seal, 1, &mut context, key).map_err(sodium_error)?;
+        kdf::derive(&mut nonce, 2, &mut context, key).map_err(sodium_error)?;
+        Ok(StorageKey {
+            keys: Arc::new(Mutex::new(SealingKeys { seal, nonce })),
+        })
+    }
+
+    /// Encrypts `plaintext` with a random nonce, authenticating `adata` along with it.
+    /// Returns the nonce followed by the cipher text.
+    pub fn seal(&self, plaintext: &[u8], adata: Option<&[u8]>) -> HcResult<Vec<u8>> {
+        let mut nonce = SecBuf::with_insecure(aead::NONCEBYTES);
+        random_secbuf(&mut nonce);
+        self.seal_with_nonce(plaintext, adata, nonce)
+    }
+
+    /// Encrypts `plaintext` with a nonce derived from it, so sealing the same plaintext
+    /// twice results in the same bytes.
+    pub fn seal_deterministic(&self, plaintext: &[u8]) -> HcResult<Vec<u8>> {
+        let mut digest = SecBuf::with_insecure(hash::BYTES512);
+        {
+            let mut keys = self.keys.lock()?;
+            let mut input = SecBuf::with_insecure(aead::KEYBYTES + plaintext.len());
+            {
+                let nonce_key = keys.nonce.read_lock();
+                let mut input = input.write_lock();
+                input[..aead::KEYBYTES].copy_from_slice(&nonce_key[..]);
+                input[aead::KEYBYTES..].copy_from_slice(plaintext);
+            }
+            hash::sha512(&mut input, &mut digest).map_err(sodium_error)?;
+        }
+        let nonce = secbuf_from_bytes(&digest.read_lock()[..aead::NONCEBYTES]);
+        self.seal_with_nonce(plaintext, None, nonce)
+    }
+
+    /// Decrypts what `seal` or `seal_deterministic` returned.
+    /// Fails if the data got tampered with or `adata` differs from the sealed one.
+    pub fn open(&self, sealed: &[u8], adata: Option<&[u8]>) -> HcResult<Vec<u8>> {
+        if sealed.len() < aead::NONCEBYTES + aead::ABYTES {
+            return Err(HolochainError::ErrorGeneric(
+                "Sealed data is too short".to_string(),
+            ));
+        }
+        let mut nonce = secbuf_from_bytes(&sealed[..aead::NONCEBYTES]);
+        let mut cipher = secbuf_from_bytes(&sealed[aead::NONCEBYTES..]);
+        let mut adata = adata.map(secbuf_from_bytes);
+        let mut plaintext = SecBuf::with_insecure(cipher.len() - aead::ABYTES);
+        aead::dec_verified(
+            &mut plaintext,
+            &mut self.keys.lock()?.seal,
+            adata.as_mut(),
+            &mut nonce,
+            &mut cipher,
+        )
+        .map_err(|_| HolochainError::ErrorGeneric("Could not decrypt sealed data".to_string()))?;
+        let plaintext = plaintext.read_lock();
+        Ok(plaintext.to_vec())
+    }
+
+    fn seal_with_nonce(
+        &self,
+        plaintext: &[u8],
+        adata: Option<&[u8]>,
+        mut nonce: SecBuf,
+    ) -> HcResult<Vec<u8>> {
+        let mut message = secbuf_from_bytes(plaintext);
+        let mut adata = adata.map(secbuf_from_bytes);
+        let mut cipher = SecBuf::with_insecure(plaintext.len() + aead::ABYTES);
+        aead::enc(
+            &mut message,
+            &mut self.keys.lock()?.seal,
+            adata.as_mut(),
+            &mut nonce,
+            &mut cipher,
+        )
+        .map_err(sodium_error)?;
+        let mut sealed = nonce.read_lock().to_vec();
+        sealed.extend_from_slice(&cipher.read_lock()[..]);
+        Ok(sealed)
+    }
+}
+
+fn secbuf_from_bytes(bytes: &[u8]) -> SecBuf {
+    let mut buf = SecBuf::with_insecure(bytes.len());
+    buf.write_lock().copy_from_slice(bytes);
+    buf
+}
+
+fn sodium_error(error: SodiumError) -> HolochainError {
+    HolochainError::ErrorGeneric(format!("sodium error: {:?}", error))
+}
+
+/// A storage key derived from a fixed seed, for tests only
+#[cfg(any(test, feature = "test-keys"))]
+pub fn test_storage_key() -> StorageKey {
+    StorageKey::new(&mut secbuf_from_bytes(&[7u8; 32])).expect("could not derive storage key")
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn sealed_data_can_be_opened() {
+        let key = test_storage_key();
+        let sealed = key.seal(b"some content", Some(&b"address"[..])).unwrap();
+        assert_ne!(&sealed[aead::NONCEBYTES..], b"some content");
+        assert_eq!(
+            key.open(&sealed, Some(&b"address"[..])).unwrap(),
+            b"some content".to_vec()
+        );
+        assert_ne!(
+            sealed,
+            key.seal(b"some content", Some(&b"address"[..])).unwrap()
+        );
+    }
+
+    #[test]
+    fn tampered_data_can_not_be_opened() {
+        let key = test_storage_key();
+        let mut sealed = key.seal(b"some content", Some(&b"address"[..])).unwrap();
+        assert!(key.open(&sealed, Some(&b"other address"[..])).is_err());
+        assert!(key.open(&sealed, None).is_err());
+        assert!(key.open(&sealed[..10], Some(&b"address"[..])).is_err());
+
+        let other_key = StorageKey::new(&mut secbuf_from_bytes(&[8u8; 32])).unwrap();
+        assert!(other_key.open(&sealed, Some(&b"address"[..])).is_err());
+
+        let last = sealed.len() - 1;
+        sealed[last] ^= 1;
+        assert!(key.open(&sealed, Some(&b"address"[..])).is_err());
+    }
+
+    #[test]
+    fn deterministic_sealing_is_deterministic() {
+        let key = test_storage_key();
+        let sealed = key.seal_deterministic(b"attribute").unwrap();
+        assert_eq!(sealed, key.seal_deterministic(b"attribute").unwrap());
+        assert_ne!(sealed, key.seal_deterministic(b"other attribute").unwrap());
+        assert_eq!(key.open(&sealed, None).unwrap(), b"attribute".to_vec());
+    }
+}
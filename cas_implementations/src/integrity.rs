@@ -0,0 +1,508 @@
+//! Integrity checks for the persistent storages of an instance.
+//!
+//! A check reads every item of a file or key-value storage directly, without going through
+//! the storage implementations, so damaged items show up as problems instead of failing
+//! every lookup. It
+//! * re-hashes all content and compares it with the address it is stored under,
+//! * makes sure every EAV can be read and is about content the storage holds, and
+//! * walks the source chain from its newest header back to the first one, checking that
+//!   all headers and their entries are there.
+//!
+//! Corrupt content and EAV files of file storages can be quarantined, i.e. moved into a
+//! `quarantine` directory next to the storage directories, where the storages won't find
+//! them anymore. Key-value storages are append-only, so their problems only get reported.
+//! Checking leaves their file as it is, even a last record that did not get written
+//! completely, which opening the store would cut off.
+//!
+//! Content gets checked one item at a time and only the addresses of intact items are kept,
+//! so checking a storage does not need memory for all of its content.
+
+use crate::{cas::kv::CONTENT_PREFIX, eav::kv::INDEX_PREFIX, encryption::StorageKey, kv::KvStore};
+use glob::glob;
+use holochain_core_types::{
+    cas::content::{Address, AddressableContent, Content},
+    chain_header::ChainHeader,
+    eav::EntityAttributeValueIndex,
+    entry::Entry,
+    error::{HcResult, HolochainError},
+    json::JsonString,
+};
+use std::{
+    collections::BTreeSet,
+    fs::{create_dir_all, read_to_string, rename},
+    path::{Path, PathBuf},
+};
+
+/// Name of the directory corrupt items get moved to
+pub const QUARANTINE_DIR: &str = "quarantine";
+
+/// Something that is wrong with a storage
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum IntegrityProblem {
+    /// Content that can't be read or does not belong to the address it is stored under
+    CorruptContent {
+        address: Address,
+        reason: String,
+        quarantined: bool,
+    },
+    /// A stored EAV that can't be read
+    CorruptEav {
+        location: String,
+        reason: String,
+        quarantined: bool,
+    },
+    /// An EAV about content the storage does not hold
+    DanglingEav {
+        entity: Address,
+        attribute: String,
+        value: Address,
+    },
+    /// A header of the source chain, or its entry, is missing or corrupt
+    BrokenChain { address: Address, reason: String },
+    /// The last record of a key-value storage, at the given position, did not get written
+    /// completely. The instance drops it when it opens the storage the next time.
+    TornRecord { position: u64 },
+}
+
+/// The outcome of an integrity check
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct IntegrityReport {
+    pub content_checked: usize,
+    pub eavs_checked: usize,
+    pub headers_checked: usize,
+    pub problems: Vec<IntegrityProblem>,
+}
+
+impl IntegrityReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Settings of an integrity check
+#[derive(Clone, Debug, Default)]
+pub struct IntegrityCheck {
+    /// Address of the newest header of the source chain. Without it the chain is not walked.
+    pub top_chain_header: Option<Address>,
+    /// Key the storage is encrypted with. Content of encrypted storages can't be checked
+    /// without it.
+    pub storage_key: Option<StorageKey>,
+    /// Move corrupt items of file storages out of the way
+    pub quarantine: bool,
+}
+
+impl IntegrityCheck {
+    /// Checks the file storage in the given directory, which holds the directories of the
+    /// CAS (`cas`) and of the EAV storage (`eav`).
+    pub fn check_file_storage<P: AsRef<Path>>(&self, path: P) -> HcResult<IntegrityReport> {
+        let path = path.as_ref();
+        let mut report = IntegrityReport::default();
+        let mut held = BTreeSet::new();
+
+        for file in glob_files(&path.join("cas"), "*.txt")? {
+            let address = match file.file_stem() {
+                Some(stem) => Address::from(stem.to_string_lossy().to_string()),
+                None => continue,
+            };
+            let checked = read_to_string(&file)
+                .map_err(HolochainError::from)
+                .and_then(|stored| self.check_content(&address, stored));
+            report.content_checked += 1;
+            match checked {
+                Ok(_) => {
+                    held.insert(address);
+                }
+                Err(error) => report.problems.push(IntegrityProblem::CorruptContent {
+                    address,
+                    reason: error.to_string(),
+                    quarantined: self.quarantine_file(path, &file)?,
+                }),
+            }
+        }
+
+        let mut eavs = BTreeSet::new();
+        for file in glob_files(&path.join("eav"), "*/*/*/*.txt")? {
+            match read_to_string(&file)
+                .map_err(HolochainError::from)
+                .and_then(|stored| eav_from_stored(stored.trim_end()))
+            {
+                Ok(eav) => {
+                    eavs.insert(eav);
+                }
+                Err(error) => report.problems.push(IntegrityProblem::CorruptEav {
+                    location: file.to_string_lossy().to_string(),
+                    reason: error.to_string(),
+                    quarantined: self.quarantine_file(path, &file)?,
+                }),
+            }
+        }
+
+        let fetch = |address: &Address| {
+            let file = path.join("cas").join(format!("{}.txt", address));
+            read_to_string(&file)
+                .map_err(HolochainError::from)
+                .and_then(|stored| self.check_content(address, stored))
+                .ok()
+        };
+        self.check_eavs_and_chain(&held, &eavs, fetch, &mut report);
+        Ok(report)
+    }
+
+    /// Checks the key-value storage in the given file, without changing it.
+    pub fn check_kv_storage<P: AsRef<Path>>(&self, path: P) -> HcResult<IntegrityReport> {
+        let store = KvStore::open_read_only(path)?;
+        let mut report = IntegrityReport::default();
+        let mut held = BTreeSet::new();
+
+        for key in store.keys_with_prefix(CONTENT_PREFIX.as_bytes()) {
+            let address =
+                Address::from(String::from_utf8_lossy(&key[CONTENT_PREFIX.len()..]).to_string());
+            let checked = store
+                .get(&key)
+                .and_then(|value| utf8(value.unwrap_or_default()))
+                .and_then(|stored| self.check_content(&address, stored));
+            report.content_checked += 1;
+            match checked {
+                Ok(_) => {
+                    held.insert(address);
+                }
+                Err(error) => report.problems.push(IntegrityProblem::CorruptContent {
+                    address,
+                    reason: error.to_string(),
+                    quarantined: false,
+                }),
+            }
+        }
+
+        let mut eavs = BTreeSet::new();
+        for key in store.keys_with_prefix(INDEX_PREFIX.as_bytes()) {
+            match store
+                .get(&key)
+                .and_then(|value| utf8(value.unwrap_or_default()))
+                .and_then(|stored| eav_from_stored(&stored))
+            {
+                Ok(eav) => {
+                    eavs.insert(eav);
+                }
+                Err(error) => report.problems.push(IntegrityProblem::CorruptEav {
+                    location: String::from_utf8_lossy(&key).replace('\0', "/"),
+                    reason: error.to_string(),
+                    quarantined: false,
+                }),
+            }
+        }
+
+        let fetch = |address: &Address| {
+            store
+                .get(format!("{}{}", CONTENT_PREFIX, address).as_bytes())
+                .and_then(|value| utf8(value.unwrap_or_default()))
+                .and_then(|stored| self.check_content(address, stored))
+                .ok()
+        };
+        self.check_eavs_and_chain(&held, &eavs, fetch, &mut report);
+        if let Some(position) = store.torn_record()? {
+            report
+                .problems
+                .push(IntegrityProblem::TornRecord { position });
+        }
+        Ok(report)
+    }
+
+    /// Decrypts stored content if needed and makes sure it belongs to its address
+    fn check_content(&self, address: &Address, stored: String) -> HcResult<Content> {
+        let content = match self.storage_key {
+            Some(ref key) => {
+                let sealed: String = serde_json::from_str(&stored)?;
+                let plaintext = key.open(
+                    &base64::decode(&sealed)?,
+                    Some(address.to_string().as_bytes()),
+                )?;
+                utf8(plaintext)?
+            }
+            None => stored,
+        };
+        let content = JsonString::from(content);
+        let matches_address = match address.hash_type() {
+            Some(hash_type) => {
+                Address::encode_from_str(&String::from(content.clone()), hash_type) == *address
+            }
+            // Content stored under a name instead of a hash, like persisted state,
+            // can only be checked for being readable
+            None => {
+                serde_json::from_str::<serde_json::Value>(&String::from(content.clone())).is_ok()
+            }
+        };
+        if matches_address {
+            return Ok(content);
+        }
+        // Agent entries are stored under the agent's key
+        match Entry::try_from_content(&content) {
+            Ok(ref entry) if entry.address() == *address => Ok(content),
+            _ => Err(HolochainError::ErrorGeneric(
+                "Content does not match its address".to_string(),
+            )),
+        }
+    }
+
+    /// Checks the EAVs against the addresses of the intact content, and walks the chain
+    /// with `fetch`, which returns intact content.
+    fn check_eavs_and_chain<F>(
+        &self,
+        held: &BTreeSet<Address>,
+        eavs: &BTreeSet<EntityAttributeValueIndex>,
+        fetch: F,
+        report: &mut IntegrityReport,
+    ) where
+        F: Fn(&Address) -> Option<Content>,
+    {
+        // EAVs have to be about held content, or about something that stands for held
+        // content according to another EAV
+        let known: BTreeSet<Address> = held
+            .iter()
+            .cloned()
+            .chain(
+                eavs.iter()
+                    .filter(|eav| held.contains(&eav.value()))
+                    .map(|eav| eav.entity()),
+            )
+            .collect();
+        for eav in eavs {
+            report.eavs_checked += 1;
+            if !known.contains(&eav.entity()) {
+                report.problems.push(IntegrityProblem::DanglingEav {
+                    entity: eav.entity(),
+                    attribute: eav.attribute(),
+                    value: eav.value(),
+                });
+            }
+        }
+
+        let mut next_header = self.top_chain_header.clone();
+        while let Some(address) = next_header {
+            let header = match fetch(&address).as_ref().map(ChainHeader::try_from_content) {
+                Some(Ok(header)) => header,
+                _ => {
+                    report.problems.push(IntegrityProblem::BrokenChain {
+                        address,
+                        reason: "Header is missing or corrupt".to_string(),
+                    });
+                    return;
+                }
+            };
+            report.headers_checked += 1;
+            if !held.contains(header.entry_address()) {
+                report.problems.push(IntegrityProblem::BrokenChain {
+                    address: address.clone(),
+                    reason: format!(
+                        "Entry {} of the header is missing or corrupt",
+                        header.entry_address()
+                    ),
+                });
+            }
+            next_header = header.link();
+        }
+    }
+
+    /// Moves a file of the storage at `storage_path` into its quarantine directory,
+    /// keeping its path relative to the storage. Returns if it got moved.
+    fn quarantine_file(&self, storage_path: &Path, file: &Path) -> HcResult<bool> {
+        if !self.quarantine {
+            return Ok(false);
+        }
+        let relative_path = file.strip_prefix(storage_path).map_err(|_| {
+            HolochainError::ErrorGeneric(format!("{} is not in the storage", file.display()))
+        })?;
+        let target = storage_path.join(QUARANTINE_DIR).join(relative_path);
+        if let Some(parent) = target.parent() {
+            create_dir_all(parent)?;
+        }
+        rename(file, target)?;
+        Ok(true)
+    }
+}
+
+fn glob_files(dir: &Path, pattern: &str) -> HcResult<Vec<PathBuf>> {
+    let pattern = dir.join(pattern);
+    let paths = glob(&pattern.to_string_lossy())
+        .map_err(|_| HolochainError::ErrorGeneric(format!("Invalid path {}", pattern.display())))?;
+    Ok(paths.filter_map(Result::ok).collect())
+}
+
+fn eav_from_stored(stored: &str) -> HcResult<EntityAttributeValueIndex> {
+    EntityAttributeValueIndex::try_from_content(&JsonString::from(stored.to_string()))
+}
+
+fn utf8(bytes: Vec<u8>) -> HcResult<String> {
+    String::from_utf8(bytes).map_err(|_| HolochainError::ErrorGeneric("Not UTF-8".to_string()))
+}
+
+#[cfg(test)]
+pub mod tests {
+    extern crate tempfile;
+
+    use self::tempfile::tempdir;
+    use super::*;
+    use crate::{
+        cas::{encrypted::EncryptedStorage, file::FilesystemStorage, kv::KvStorage},
+        eav::{file::EavFileStorage, kv::EavKvStorage},
+        encryption::test_storage_key,
+    };
+    use holochain_core_types::{
+        cas::storage::ContentAddressableStorage, chain_header::test_chain_header,
+        eav::EntityAttributeValueStorage, entry::test_entry,
+    };
+    use std::{
+        fs::{write, OpenOptions},
+        io::Write,
+        sync::{Arc, RwLock},
+    };
+
+    fn test_eav(entity: &Address) -> EntityAttributeValueIndex {
+        EntityAttributeValueIndex::new(entity, &"crud-status".to_string(), &Address::from("live"))
+            .unwrap()
+    }
+
+    #[test]
+    fn healthy_file_storage_has_no_problems() {
+        let dir = tempdir().unwrap();
+        let mut cas = FilesystemStorage::new(&dir.path().join("cas").to_string_lossy()).unwrap();
+        let mut eav =
+            EavFileStorage::new(dir.path().join("eav").to_string_lossy().to_string()).unwrap();
+        let header = test_chain_header();
+        cas.add(&test_entry()).unwrap();
+        cas.add(&header).unwrap();
+        eav.add_eavi(&test_eav(&test_entry().address())).unwrap();
+
+        let report = IntegrityCheck {
+            top_chain_header: Some(header.address()),
+            ..Default::default()
+        }
+        .check_file_storage(dir.path())
+        .unwrap();
+        assert!(report.is_ok(), "{:?}", report);
+        assert_eq!(report.content_checked, 2);
+        assert_eq!(report.eavs_checked, 1);
+        assert_eq!(report.headers_checked, 1);
+    }
+
+    #[test]
+    fn corrupt_file_storage_gets_reported_and_quarantined() {
+        let dir = tempdir().unwrap();
+        let cas_dir = dir.path().join("cas");
+        let mut cas = FilesystemStorage::new(&cas_dir.to_string_lossy()).unwrap();
+        let mut eav =
+            EavFileStorage::new(dir.path().join("eav").to_string_lossy().to_string()).unwrap();
+        let header = test_chain_header();
+        cas.add(&test_entry()).unwrap();
+        cas.add(&header).unwrap();
+        eav.add_eavi(&test_eav(&Address::from("not held"))).unwrap();
+
+        // truncate the entry
+        let entry_file = cas_dir.join(format!("{}.txt", test_entry().address()));
+        write(&entry_file, "{\"App\":[\"testEntry").unwrap();
+
+        let report = IntegrityCheck {
+            top_chain_header: Some(header.address()),
+            quarantine: true,
+            ..Default::default()
+        }
+        .check_file_storage(dir.path())
+        .unwrap();
+        assert_eq!(
+            report.problems,
+            vec![
+                IntegrityProblem::CorruptContent {
+                    address: test_entry().address(),
+                    reason: "Content does not match its address".to_string(),
+                    quarantined: true,
+                },
+                IntegrityProblem::DanglingEav {
+                    entity: Address::from("not held"),
+                    attribute: "crud-status".to_string(),
+                    value: Address::from("live"),
+                },
+                IntegrityProblem::BrokenChain {
+                    address: header.address(),
+                    reason: format!(
+                        "Entry {} of the header is missing or corrupt",
+                        test_entry().address()
+                    ),
+                },
+            ]
+        );
+        assert!(!entry_file.exists());
+        assert!(dir
+            .path()
+            .join(QUARANTINE_DIR)
+            .join("cas")
+            .join(format!("{}.txt", test_entry().address()))
+            .exists());
+        assert_eq!(cas.fetch(&test_entry().address()), Ok(None));
+    }
+
+    #[test]
+    fn encrypted_storage_needs_its_key() {
+        let dir = tempdir().unwrap();
+        let cas = FilesystemStorage::new(&dir.path().join("cas").to_string_lossy()).unwrap();
+        let mut encrypted = EncryptedStorage::new(Arc::new(RwLock::new(cas)), test_storage_key());
+        encrypted.add(&test_entry()).unwrap();
+
+        let report = IntegrityCheck::default()
+            .check_file_storage(dir.path())
+            .unwrap();
+        assert_eq!(report.problems.len(), 1);
+
+        let report = IntegrityCheck {
+            storage_key: Some(test_storage_key()),
+            ..Default::default()
+        }
+        .check_file_storage(dir.path())
+        .unwrap();
+        assert!(report.is_ok(), "{:?}", report);
+    }
+
+    #[test]
+    fn kv_storage_gets_checked() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("store.kv");
+        {
+            let store = Arc::new(RwLock::new(KvStore::open(&path).unwrap()));
+            let mut cas = KvStorage::new(store.clone());
+            let mut eav = EavKvStorage::new(store);
+            cas.add(&test_entry()).unwrap();
+            eav.add_eavi(&test_eav(&test_entry().address())).unwrap();
+            eav.add_eavi(&test_eav(&Address::from("not held"))).unwrap();
+        }
+        // the start of a record header, as if the process died while writing it
+        let len = path.metadata().unwrap().len();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[1, 2, 3]).unwrap();
+        drop(file);
+
+        let report = IntegrityCheck {
+            top_chain_header: Some(Address::from("missing header")),
+            ..Default::default()
+        }
+        .check_kv_storage(&path)
+        .unwrap();
+        assert_eq!(report.content_checked, 1);
+        assert_eq!(report.eavs_checked, 2);
+        assert_eq!(
+            report.problems,
+            vec![
+                IntegrityProblem::DanglingEav {
+                    entity: Address::from("not held"),
+                    attribute: "crud-status".to_string(),
+                    value: Address::from("live"),
+                },
+                IntegrityProblem::BrokenChain {
+                    address: Address::from("missing header"),
+                    reason: "Header is missing or corrupt".to_string(),
+                },
+                IntegrityProblem::TornRecord { position: len },
+            ]
+        );
+        // checking does not touch the file
+        assert_eq!(path.metadata().unwrap().len(), len + 3);
+    }
+}
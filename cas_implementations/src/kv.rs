@@ -0,0 +1,669 @@
+//! A single-file, append-only key-value store that the CAS and EAV storages of an
+//! instance can share.
+//!
+//! Every write appends one record that holds a whole batch of key-value pairs and syncs
+//! it to disk before the batch becomes visible. Records carry their length and a checksum,
+//! so a record that only got partially written when the process died is detected and cut
+//! off the next time the file is opened. A batch thus shows up either completely or not
+//! at all. Only the last record can be torn like that, a broken record that is followed
+//! by others means the file got corrupted and the store refuses to open it.
+//!
+//! The store keeps an index from keys to the position of their latest value in memory.
+//! Values are only read from disk when they get fetched. Deleting keys appends a record
+//! that marks them as deleted. Once overwritten and deleted values take up more space
+//! than the live ones, the store gets compacted into a new file with only the live values.
+
+use holochain_core_types::error::{HcResult, HolochainError};
+use std::{
+    collections::BTreeMap,
+    fs::{self, create_dir_all, File, OpenOptions},
+    io::{self, BufReader, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Marks a file as a key-value store and versions its format
+const MAGIC: &[u8; 8] = b"HCKV\x00\x00\x00\x01";
+
+/// Every record starts with the length of its payload and the checksum of the payload
+const RECORD_HEADER_LEN: u64 = 8;
+
+/// Value length that marks a key as deleted
+const TOMBSTONE: u32 = ::std::u32::MAX;
+
+/// Stores smaller than this never get compacted
+const COMPACTION_MIN_LEN: u64 = 1 << 20;
+
+/// Compaction writes the live values in records of about this size
+const COMPACTION_RECORD_LEN: usize = 1 << 20;
+
+pub type KvBatch = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// Position and length of a value in the file, None for deleted keys
+type IndexChange = (Vec<u8>, Option<(u64, u32)>);
+
+#[derive(Debug)]
+pub struct KvStore {
+    path: PathBuf,
+    file: Mutex<File>,
+    /// position and length of the latest value of every key
+    index: BTreeMap<Vec<u8>, (u64, u32)>,
+    /// position right after the last complete record
+    end: u64,
+    /// bytes the latest values and their keys take up in the file
+    live: u64,
+    /// opened by `open_read_only`
+    read_only: bool,
+    /// end of the file right after it last got compacted
+    compacted_end: Option<u64>,
+}
+
+/// What reading the records of a store file found
+#[derive(Debug, PartialEq)]
+pub enum ReplayEnd {
+    /// All records are complete
+    Complete,
+    /// The last record, starting at the given position, did not get written completely
+    TornRecord(u64),
+}
+
+impl KvStore {
+    /// Opens the store in the given file and creates the file if it does not exist yet.
+    /// Replays all complete records to build the index and cuts off a trailing record
+    /// that did not get written completely. Fails if any earlier record is broken.
+    pub fn open<P: AsRef<Path>>(path: P) -> HcResult<KvStore> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(&path)?;
+
+        if file.metadata()?.len() == 0 {
+            file.write_all(MAGIC)?;
+            file.sync_all()?;
+        }
+
+        let mut index = BTreeMap::new();
+        let mut live = 0;
+        let end = match replay(&path, &mut file, |changes| {
+            apply(&mut index, &mut live, changes)
+        })? {
+            ReplayEnd::Complete => file.metadata()?.len(),
+            ReplayEnd::TornRecord(end) => {
+                // the last write got interrupted
+                file.set_len(end)?;
+                file.sync_all()?;
+                end
+            }
+        };
+
+        Ok(KvStore {
+            path,
+            file: Mutex::new(file),
+            index,
+            end,
+            live,
+            read_only: false,
+            compacted_end: None,
+        })
+    }
+
+    /// Opens the store in the given file for reading only, e.g. to inspect the storage of
+    /// an instance that is not running. Unlike `open` it leaves the file as it is, a trailing
+    /// record that did not get written completely just gets ignored.
+    pub fn open_read_only<P: AsRef<Path>>(path: P) -> HcResult<KvStore> {
+        let path = path.as_ref().to_path_buf();
+        let mut index = BTreeMap::new();
+        let mut live = 0;
+        let replay_end = KvStore::read_records(&path, |key, position| {
+            apply(&mut index, &mut live, vec![(key, position)])
+        })?;
+        let file = File::open(&path)?;
+        let end = match replay_end {
+            ReplayEnd::Complete => file.metadata()?.len(),
+            ReplayEnd::TornRecord(end) => end,
+        };
+        Ok(KvStore {
+            path,
+            file: Mutex::new(file),
+            index,
+            end,
+            live,
+            read_only: true,
+            compacted_end: None,
+        })
+    }
+
+    /// Reads the keys of all records in the given file in the order they were written,
+    /// without changing the file. Calls `f` with the key and the position and length of
+    /// its value, or None for deleted keys, of every pair.
+    /// Fails if the file is no store or if a record other than the last one is broken.
+    pub fn read_records<P, F>(path: P, mut f: F) -> HcResult<ReplayEnd>
+    where
+        P: AsRef<Path>,
+        F: FnMut(Vec<u8>, Option<(u64, u32)>),
+    {
+        let path = path.as_ref();
+        let mut file = File::open(path)?;
+        replay(path, &mut file, |changes| {
+            for (key, position) in changes {
+                f(key, position)
+            }
+        })
+    }
+
+    /// The file the store lives in
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Position of a trailing record that did not get written completely.
+    /// Only stores opened with `open_read_only` can have one.
+    pub fn torn_record(&self) -> HcResult<Option<u64>> {
+        let len = self.file.lock()?.metadata()?.len();
+        Ok(if len > self.end { Some(self.end) } else { None })
+    }
+
+    pub fn contains(&self, key: &[u8]) -> bool {
+        self.index.contains_key(key)
+    }
+
+    /// Returns the latest value written for the given key
+    pub fn get(&self, key: &[u8]) -> HcResult<Option<Vec<u8>>> {
+        match self.index.get(key) {
+            Some((position, len)) => {
+                let mut file = self.file.lock()?;
+                file.seek(SeekFrom::Start(*position))?;
+                let mut value = vec![0u8; *len as usize];
+                file.read_exact(&mut value)?;
+                Ok(Some(value))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Returns all keys that start with the given prefix, in order
+    pub fn keys_with_prefix(&self, prefix: &[u8]) -> Vec<Vec<u8>> {
+        self.index
+            .range(prefix.to_vec()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// Writes all pairs of the batch in one transaction.
+    /// Keys that already exist get the new value.
+    pub fn write(&mut self, batch: KvBatch) -> HcResult<()> {
+        self.write_record(
+            batch
+                .into_iter()
+                .map(|(key, value)| (key, Some(value)))
+                .collect(),
+        )
+    }
+
+    /// Deletes all given keys in one transaction
+    pub fn delete(&mut self, keys: Vec<Vec<u8>>) -> HcResult<()> {
+        self.write_record(keys.into_iter().map(|key| (key, None)).collect())
+    }
+
+    /// Rewrites the store into a new file that only holds the latest value of every key
+    /// and replaces the old file with it. If that fails, the old file stays as it was.
+    /// Does nothing if the store did not get written to since it got compacted last.
+    pub fn compact(&mut self) -> HcResult<()> {
+        self.check_writable()?;
+        if self.compacted_end == Some(self.end) {
+            return Ok(());
+        }
+        let compacted_path = self.path.with_extension("compacting");
+        let compacted = self.write_compacted(&compacted_path);
+        let (file, index, end, live) = match compacted {
+            Ok(compacted) => compacted,
+            Err(error) => {
+                let _ = fs::remove_file(&compacted_path);
+                return Err(error);
+            }
+        };
+        fs::rename(&compacted_path, &self.path)?;
+        self.file = Mutex::new(file);
+        self.index = index;
+        self.end = end;
+        self.live = live;
+        self.compacted_end = Some(end);
+        // make the rename itself durable, the compacted file is complete either way
+        let dir = match self.path.parent() {
+            Some(parent) if parent != Path::new("") => parent,
+            _ => Path::new("."),
+        };
+        let _ = File::open(dir).and_then(|dir| dir.sync_all());
+        Ok(())
+    }
+
+    fn write_compacted(
+        &self,
+        compacted_path: &Path,
+    ) -> HcResult<(File, BTreeMap<Vec<u8>, (u64, u32)>, u64, u64)> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(compacted_path)?;
+        file.write_all(MAGIC)?;
+        let mut index = BTreeMap::new();
+        let mut live = 0;
+        let mut end = MAGIC.len() as u64;
+        let mut changes = Vec::new();
+        let mut changes_len = 0;
+        for key in self.index.keys() {
+            let value = self.get(key)?.unwrap_or_default();
+            changes_len += key.len() + value.len();
+            changes.push((key.clone(), Some(value)));
+            if changes_len >= COMPACTION_RECORD_LEN {
+                let (record, positions) = encode_record(end, changes)?;
+                append_record(&mut file, end, &record)?;
+                end += record.len() as u64;
+                apply(&mut index, &mut live, positions);
+                changes = Vec::new();
+                changes_len = 0;
+            }
+        }
+        if !changes.is_empty() {
+            let (record, positions) = encode_record(end, changes)?;
+            append_record(&mut file, end, &record)?;
+            end += record.len() as u64;
+            apply(&mut index, &mut live, positions);
+        }
+        file.sync_all()?;
+        Ok((file, index, end, live))
+    }
+
+    /// Compaction pays off once more than half of the file is taken up by values that
+    /// got overwritten or deleted
+    fn needs_compaction(&self) -> bool {
+        self.end > COMPACTION_MIN_LEN && self.end > 2 * self.live
+    }
+
+    fn check_writable(&self) -> HcResult<()> {
+        if self.read_only {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "Key-value store {} is opened read-only",
+                self.path.display()
+            )));
+        }
+        Ok(())
+    }
+
+    fn write_record(&mut self, changes: Vec<(Vec<u8>, Option<Vec<u8>>)>) -> HcResult<()> {
+        self.check_writable()?;
+        if changes.is_empty() {
+            return Ok(());
+        }
+        let record_start = self.end;
+        let (record, positions) = encode_record(record_start, changes)?;
+
+        {
+            let mut file = self.file.lock()?;
+            if let Err(error) = append_record(&mut file, record_start, &record) {
+                // don't leave a partial record behind that later records would follow
+                let _ = file.set_len(record_start);
+                return Err(error.into());
+            }
+        }
+
+        self.end = record_start + record.len() as u64;
+        apply(&mut self.index, &mut self.live, positions);
+        if self.needs_compaction() {
+            // the write went through either way, and a failed compaction leaves the
+            // file as it was and gets tried again with the next write
+            let _ = self.compact();
+        }
+        Ok(())
+    }
+}
+
+/// Builds the record for the given changes that gets written at the given position.
+/// Returns it with the changes to the index it makes.
+fn encode_record(
+    record_start: u64,
+    changes: Vec<(Vec<u8>, Option<Vec<u8>>)>,
+) -> HcResult<(Vec<u8>, Vec<IndexChange>)> {
+    let too_long = |what: &str| {
+        HolochainError::ErrorGeneric(format!("{} is too long for the key-value store", what))
+    };
+    let mut payload = Vec::new();
+    let mut positions: Vec<IndexChange> = Vec::with_capacity(changes.len());
+    for (key, value) in changes.into_iter() {
+        if key.len() >= TOMBSTONE as usize {
+            return Err(too_long("Key"));
+        }
+        payload.extend_from_slice(&(key.len() as u32).to_le_bytes());
+        payload.extend_from_slice(&key);
+        match value {
+            Some(value) => {
+                // a length of TOMBSTONE would read back as a deletion
+                if value.len() >= TOMBSTONE as usize {
+                    return Err(too_long("Value"));
+                }
+                payload.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                let position = record_start + RECORD_HEADER_LEN + payload.len() as u64;
+                payload.extend_from_slice(&value);
+                positions.push((key, Some((position, value.len() as u32))));
+            }
+            None => {
+                payload.extend_from_slice(&TOMBSTONE.to_le_bytes());
+                positions.push((key, None));
+            }
+        }
+    }
+    if payload.len() > ::std::u32::MAX as usize {
+        return Err(too_long("Batch"));
+    }
+    let mut record = Vec::with_capacity(RECORD_HEADER_LEN as usize + payload.len());
+    record.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    record.extend_from_slice(&checksum(&payload).to_le_bytes());
+    record.extend_from_slice(&payload);
+    Ok((record, positions))
+}
+
+/// Applies the changes of a record to the index and keeps track of the live bytes
+fn apply(index: &mut BTreeMap<Vec<u8>, (u64, u32)>, live: &mut u64, changes: Vec<IndexChange>) {
+    for (key, position) in changes {
+        let key_len = key.len() as u64;
+        let replaced = match position {
+            Some(position) => {
+                *live += key_len + u64::from(position.1);
+                index.insert(key, position)
+            }
+            None => index.remove(&key),
+        };
+        if let Some((_, len)) = replaced {
+            *live -= key_len + u64::from(len);
+        }
+    }
+}
+
+fn append_record(file: &mut File, position: u64, record: &[u8]) -> io::Result<()> {
+    file.seek(SeekFrom::Start(position))?;
+    file.write_all(record)?;
+    file.sync_data()
+}
+
+/// Reads the records following the magic bytes and hands the changes of each to `f`.
+/// A broken last record counts as torn, any other broken record is an error.
+fn replay<F>(path: &Path, file: &mut File, mut f: F) -> HcResult<ReplayEnd>
+where
+    F: FnMut(Vec<IndexChange>),
+{
+    let file_len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(0))?;
+    let mut reader = BufReader::new(file);
+    let mut magic = [0u8; 8];
+    if !read_full(&mut reader, &mut magic)? || &magic != MAGIC {
+        return Err(HolochainError::IoError(format!(
+            "{} is not a key-value store",
+            path.display()
+        )));
+    }
+    let mut position = MAGIC.len() as u64;
+    while position < file_len {
+        let mut header = [0u8; RECORD_HEADER_LEN as usize];
+        if !read_full(&mut reader, &mut header)? {
+            return Ok(ReplayEnd::TornRecord(position));
+        }
+        let len = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+        let sum = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        let record_end = position + RECORD_HEADER_LEN + len as u64;
+        if record_end > file_len {
+            return Ok(ReplayEnd::TornRecord(position));
+        }
+        let mut payload = vec![0u8; len as usize];
+        read_full(&mut reader, &mut payload)?;
+        let changes = if checksum(&payload) == sum {
+            parse_payload(&payload, position + RECORD_HEADER_LEN)
+        } else {
+            None
+        };
+        match changes {
+            Some(changes) => f(changes),
+            None if record_end == file_len => return Ok(ReplayEnd::TornRecord(position)),
+            None => {
+                return Err(HolochainError::IoError(format!(
+                    "{} is corrupted: broken record at byte {}",
+                    path.display(),
+                    position
+                )))
+            }
+        }
+        position = record_end;
+    }
+    Ok(ReplayEnd::Complete)
+}
+
+/// Splits a record's payload into its keys and the positions of their values
+fn parse_payload(payload: &[u8], payload_position: u64) -> Option<Vec<IndexChange>> {
+    let mut entries = Vec::new();
+    let mut offset = 0;
+    while offset < payload.len() {
+        let key_len = read_u32(payload, offset)? as usize;
+        offset += 4;
+        let key = payload.get(offset..offset + key_len)?.to_vec();
+        offset += key_len;
+        let value_len = read_u32(payload, offset)?;
+        offset += 4;
+        if value_len == TOMBSTONE {
+            entries.push((key, None));
+            continue;
+        }
+        if offset + value_len as usize > payload.len() {
+            return None;
+        }
+        entries.push((key, Some((payload_position + offset as u64, value_len))));
+        offset += value_len as usize;
+    }
+    Some(entries)
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// Fills the buffer and returns false if the input ends before it is full
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> HcResult<bool> {
+    match reader.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(ref error) if error.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// 32 bit FNV-1a hash, enough to detect torn writes
+fn checksum(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ u32::from(*byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+#[cfg(test)]
+pub mod tests {
+    extern crate tempfile;
+
+    use self::tempfile::{tempdir, TempDir};
+    use super::*;
+
+    pub fn test_kv_store() -> (KvStore, TempDir) {
+        let dir = tempdir().expect("Could not create a tempdir for KV store testing");
+        let store = KvStore::open(dir.path().join("storage.db")).unwrap();
+        (store, dir)
+    }
+
+    fn pair(key: &str, value: &str) -> (Vec<u8>, Vec<u8>) {
+        (key.as_bytes().to_vec(), value.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn kv_store_round_trip() {
+        let (mut store, _dir) = test_kv_store();
+        assert_eq!(store.get(b"a/1").unwrap(), None);
+
+        store
+            .write(vec![
+                pair("a/1", "one"),
+                pair("a/2", "two"),
+                pair("b/1", ""),
+            ])
+            .unwrap();
+        store.write(vec![pair("a/1", "uno")]).unwrap();
+
+        assert!(store.contains(b"b/1"));
+        assert_eq!(store.get(b"a/1").unwrap(), Some(b"uno".to_vec()));
+        assert_eq!(store.get(b"b/1").unwrap(), Some(Vec::new()));
+        assert_eq!(
+            store.keys_with_prefix(b"a/"),
+            vec![b"a/1".to_vec(), b"a/2".to_vec()]
+        );
+    }
+
+    #[test]
+    fn kv_store_survives_reopening() {
+        let (mut store, dir) = test_kv_store();
+        store.write(vec![pair("key", "value")]).unwrap();
+        store.write(vec![pair("key", "new value")]).unwrap();
+        drop(store);
+
+        let store = KvStore::open(dir.path().join("storage.db")).unwrap();
+        assert_eq!(store.get(b"key").unwrap(), Some(b"new value".to_vec()));
+    }
+
+    #[test]
+    fn kv_store_deletes_keys() {
+        let (mut store, dir) = test_kv_store();
+        store
+            .write(vec![pair("gone", "soon"), pair("kept", "yes")])
+            .unwrap();
+        store.delete(vec![b"gone".to_vec()]).unwrap();
+        assert!(!store.contains(b"gone"));
+        assert_eq!(store.get(b"gone").unwrap(), None);
+        drop(store);
+
+        let mut store = KvStore::open(dir.path().join("storage.db")).unwrap();
+        assert!(!store.contains(b"gone"));
+        assert_eq!(store.keys_with_prefix(b""), vec![b"kept".to_vec()]);
+
+        // deleted keys can be written again
+        store.write(vec![pair("gone", "back")]).unwrap();
+        assert_eq!(store.get(b"gone").unwrap(), Some(b"back".to_vec()));
+    }
+
+    #[test]
+    fn kv_store_drops_torn_writes() {
+        let (mut store, dir) = test_kv_store();
+        let path = dir.path().join("storage.db");
+        store.write(vec![pair("complete", "yes")]).unwrap();
+        store
+            .write(vec![pair("torn", "no"), pair("torn too", "no")])
+            .unwrap();
+        drop(store);
+
+        // cut off the end of the last record as if the process died while writing it
+        let len = path.metadata().unwrap().len();
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(len - 3).unwrap();
+        drop(file);
+
+        // inspecting the store leaves the torn record in place
+        let mut read_only = KvStore::open_read_only(&path).unwrap();
+        assert_eq!(read_only.get(b"complete").unwrap(), Some(b"yes".to_vec()));
+        assert!(!read_only.contains(b"torn"));
+        assert!(read_only.torn_record().unwrap().is_some());
+        assert!(read_only.write(vec![pair("after", "no")]).is_err());
+        assert_eq!(path.metadata().unwrap().len(), len - 3);
+        drop(read_only);
+
+        let mut store = KvStore::open(&path).unwrap();
+        assert_eq!(store.torn_record().unwrap(), None);
+        assert_eq!(store.get(b"complete").unwrap(), Some(b"yes".to_vec()));
+        assert!(!store.contains(b"torn"));
+        assert!(!store.contains(b"torn too"));
+
+        // writes after the recovery end up right after the last complete record
+        store.write(vec![pair("after", "yes")]).unwrap();
+        drop(store);
+        let store = KvStore::open(&path).unwrap();
+        assert_eq!(store.get(b"after").unwrap(), Some(b"yes".to_vec()));
+    }
+
+    #[test]
+    fn kv_store_refuses_corrupted_records() {
+        let (mut store, dir) = test_kv_store();
+        let path = dir.path().join("storage.db");
+        store.write(vec![pair("first", "record")]).unwrap();
+        store.write(vec![pair("second", "record")]).unwrap();
+        drop(store);
+
+        // flip a byte in the value of the first record
+        let mut bytes = ::std::fs::read(&path).unwrap();
+        let len = bytes.len();
+        let position = MAGIC.len() + RECORD_HEADER_LEN as usize + 4 + "first".len() + 4;
+        bytes[position] ^= 0xff;
+        ::std::fs::write(&path, &bytes).unwrap();
+
+        // the records after it must not get cut off
+        assert!(KvStore::open(&path).is_err());
+        assert_eq!(path.metadata().unwrap().len(), len as u64);
+        assert!(KvStore::read_records(&path, |_, _| ()).is_err());
+    }
+
+    #[test]
+    fn kv_store_compacts() {
+        let (mut store, dir) = test_kv_store();
+        let path = dir.path().join("storage.db");
+        store
+            .write(vec![pair("kept", "yes"), pair("gone", "soon")])
+            .unwrap();
+        store.write(vec![pair("kept", "still")]).unwrap();
+        store.delete(vec![b"gone".to_vec()]).unwrap();
+        let len = path.metadata().unwrap().len();
+
+        store.compact().unwrap();
+        assert!(path.metadata().unwrap().len() < len);
+        assert!(!path.with_extension("compacting").exists());
+        assert_eq!(store.keys_with_prefix(b""), vec![b"kept".to_vec()]);
+        assert_eq!(store.get(b"kept").unwrap(), Some(b"still".to_vec()));
+
+        // the compacted file takes writes and can be opened again
+        store.write(vec![pair("after", "compaction")]).unwrap();
+        drop(store);
+        let store = KvStore::open(&path).unwrap();
+        assert_eq!(store.get(b"kept").unwrap(), Some(b"still".to_vec()));
+        assert_eq!(store.get(b"after").unwrap(), Some(b"compaction".to_vec()));
+    }
+
+    #[test]
+    fn kv_store_compacts_when_mostly_garbage() {
+        let (mut store, dir) = test_kv_store();
+        let path = dir.path().join("storage.db");
+        let value = vec![7u8; 64 * 1024];
+        for _ in 0..64 {
+            store
+                .write(vec![(b"overwritten".to_vec(), value.clone())])
+                .unwrap();
+        }
+        assert!(path.metadata().unwrap().len() <= COMPACTION_MIN_LEN);
+        assert_eq!(store.get(b"overwritten").unwrap(), Some(value));
+    }
+
+    #[test]
+    fn kv_store_rejects_other_files() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("not-a-store.txt");
+        ::std::fs::write(&path, "some text").unwrap();
+        assert!(KvStore::open(&path).is_err());
+    }
+}
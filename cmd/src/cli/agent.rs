@@ -1,5 +1,232 @@
 use crate::error::DefaultResult;
+use colored::*;
+use holochain_container_api::keystore::{get_passphrase, prompt_passphrase, Keystore};
+use holochain_core_types::{agent::KeyBuffer, cas::content::Address};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use structopt::StructOpt;
 
-pub fn agent() -> DefaultResult<()> {
-    unimplemented!()
+/// File extension of keystore files created by `hc agent keygen`
+pub const KEYSTORE_EXTENSION: &str = "keystore";
+
+#[derive(StructOpt)]
+pub enum AgentCommand {
+    #[structopt(
+        name = "keygen",
+        alias = "k",
+        about = "Generates a new agent keypair and stores it in a keystore file"
+    )]
+    Keygen {
+        #[structopt(
+            long,
+            short,
+            help = "Where to write the keystore file (defaults to ~/.holochain/keys/<address>.keystore)",
+            parse(from_os_str)
+        )]
+        path: Option<PathBuf>,
+    },
+    #[structopt(
+        name = "list",
+        alias = "l",
+        about = "Lists the keystore files in a directory and their public addresses"
+    )]
+    List {
+        #[structopt(
+            help = "The directory to look in (defaults to ~/.holochain/keys)",
+            parse(from_os_str)
+        )]
+        dir: Option<PathBuf>,
+    },
+    #[structopt(
+        name = "inspect",
+        alias = "i",
+        about = "Shows the public keys stored in a keystore file"
+    )]
+    Inspect {
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+        #[structopt(long, help = "Also check that the keystore can be unlocked")]
+        unlock: bool,
+    },
+    #[structopt(
+        name = "passphrase",
+        alias = "p",
+        about = "Changes the passphrase of a keystore file"
+    )]
+    Passphrase {
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+    },
+}
+
+pub fn agent(command: AgentCommand) -> DefaultResult<()> {
+    match command {
+        AgentCommand::Keygen { path } => keygen(path),
+        AgentCommand::List { dir } => list(dir),
+        AgentCommand::Inspect { path, unlock } => inspect(&path, unlock),
+        AgentCommand::Passphrase { path } => passphrase(&path),
+    }
+}
+
+/// Generates a new random keypair, asks for a passphrase and writes the keystore file.
+fn keygen(path: Option<PathBuf>) -> DefaultResult<()> {
+    let passphrase = new_passphrase()?;
+    let keystore = Keystore::new_random(&passphrase)?;
+    let path = match path {
+        Some(path) => path,
+        None => {
+            let dir = default_keys_dir()?;
+            fs::create_dir_all(&dir)?;
+            dir.join(format!(
+                "{}.{}",
+                keystore.public_address(),
+                KEYSTORE_EXTENSION
+            ))
+        }
+    };
+    write_keystore(&keystore, &path)?;
+
+    println!(
+        "{} keystore {}",
+        "Created".green().bold(),
+        path.to_string_lossy()
+    );
+    println!("Public address: {}", keystore.public_address());
+    Ok(())
+}
+
+fn list(dir: Option<PathBuf>) -> DefaultResult<()> {
+    let dir = match dir {
+        Some(dir) => dir,
+        None => default_keys_dir()?,
+    };
+    let keystores = find_keystores(&dir)?;
+    if keystores.is_empty() {
+        println!("No keystores found in {}", dir.to_string_lossy());
+    }
+    for (path, address) in keystores {
+        println!("{}\t{}", address, path.to_string_lossy());
+    }
+    Ok(())
+}
+
+fn inspect(path: &Path, unlock: bool) -> DefaultResult<()> {
+    let keystore = Keystore::load(path)?;
+    let address = keystore.public_address();
+    let key_buffer = KeyBuffer::with_corrected(&address.to_string())?;
+    println!("Keystore:        {}", path.to_string_lossy());
+    println!("Public address:  {}", address);
+    println!("Signing key:     {}", base64::encode(key_buffer.get_sig()));
+    println!("Encryption key:  {}", base64::encode(key_buffer.get_enc()));
+    if unlock {
+        let passphrase = get_passphrase("Passphrase: ")?;
+        keystore.unlock(&passphrase)?;
+        println!("{} keystore", "Unlocked".green().bold());
+    }
+    Ok(())
+}
+
+fn passphrase(path: &Path) -> DefaultResult<()> {
+    let old_passphrase = get_passphrase("Current passphrase: ")?;
+    let new_passphrase = new_passphrase()?;
+    change_passphrase(path, &old_passphrase, &new_passphrase)?;
+    println!(
+        "{} passphrase of {}",
+        "Changed".green().bold(),
+        path.to_string_lossy()
+    );
+    Ok(())
+}
+
+/// Asks for a new passphrase twice and makes sure both inputs match.
+/// `HC_PASSPHRASE` only ever provides the current passphrase, so it gets ignored here.
+pub fn new_passphrase() -> DefaultResult<String> {
+    let passphrase = prompt_passphrase("New passphrase: ")?;
+    let confirmation = prompt_passphrase("Repeat passphrase: ")?;
+    if passphrase != confirmation {
+        bail!("passphrases do not match");
+    }
+    Ok(passphrase)
+}
+
+fn write_keystore(keystore: &Keystore, path: &Path) -> DefaultResult<()> {
+    if path.exists() {
+        bail!("{} already exists", path.to_string_lossy());
+    }
+    keystore.save(path)?;
+    Ok(())
+}
+
+fn change_passphrase(path: &Path, old_passphrase: &str, new_passphrase: &str) -> DefaultResult<()> {
+    let mut keystore = Keystore::load(path)?;
+    keystore.change_passphrase(old_passphrase, new_passphrase)?;
+    keystore.save(path)?;
+    Ok(())
+}
+
+/// Returns all files in `dir` that can be read as a keystore, together with their address.
+fn find_keystores(dir: &Path) -> DefaultResult<Vec<(PathBuf, Address)>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut keystores: Vec<(PathBuf, Address)> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter_map(|path| {
+            Keystore::load(&path)
+                .ok()
+                .map(|keystore| (path, keystore.public_address()))
+        })
+        .collect();
+    keystores.sort();
+    Ok(keystores)
+}
+
+fn default_keys_dir() -> DefaultResult<PathBuf> {
+    match dirs::home_dir() {
+        Some(home) => Ok(home.join(".holochain").join("keys")),
+        None => bail!("no home directory found, please specify a path"),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::cli::init::tests::gen_dir;
+
+    #[test]
+    fn keystores_can_be_listed() {
+        let dir = gen_dir();
+        let keystore = Keystore::new_random("secret").unwrap();
+        let path = dir.path().join("agent.keystore");
+        write_keystore(&keystore, &path).unwrap();
+        fs::write(dir.path().join("not-a-keystore.txt"), "hello").unwrap();
+
+        assert_eq!(
+            find_keystores(dir.path()).unwrap(),
+            vec![(path.clone(), keystore.public_address())]
+        );
+        assert!(write_keystore(&keystore, &path).is_err());
+    }
+
+    #[test]
+    fn passphrase_of_keystore_file_can_be_changed() {
+        let dir = gen_dir();
+        let keystore = Keystore::new_random("old").unwrap();
+        let path = dir.path().join("agent.keystore");
+        write_keystore(&keystore, &path).unwrap();
+
+        assert!(change_passphrase(&path, "wrong", "new").is_err());
+        change_passphrase(&path, "old", "new").unwrap();
+
+        let keystore = Keystore::load(&path).unwrap();
+        assert!(keystore.unlock("old").is_err());
+        assert_eq!(
+            keystore.unlock("new").unwrap().address(),
+            keystore.public_address()
+        );
+    }
 }
@@ -0,0 +1,158 @@
+use crate::{cli::run::LOCAL_STORAGE_PATH, error::DefaultResult};
+use colored::*;
+use holochain_cas_implementations::{
+    cas::{file::FilesystemStorage, kv::KvStorage},
+    kv::KvStore,
+};
+use holochain_core::{
+    agent::{chain_archive::ChainArchive, chain_store::ChainStore},
+    persister::SimplePersister,
+};
+use holochain_core_types::cas::storage::ContentAddressableStorage;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+pub enum ChainCommand {
+    #[structopt(
+        name = "export",
+        alias = "e",
+        about = "Writes the source chain of a stopped instance to an archive file"
+    )]
+    Export {
+        #[structopt(
+            long,
+            short,
+            help = "Storage of the instance (defaults to the storage of `hc run --persist`)",
+            parse(from_os_str)
+        )]
+        storage: Option<PathBuf>,
+        #[structopt(long, help = "The storage is a single key-value database file")]
+        kv: bool,
+        #[structopt(
+            long,
+            short,
+            help = "Where to write the archive (defaults to chain.json)",
+            parse(from_os_str)
+        )]
+        output: Option<PathBuf>,
+    },
+    #[structopt(
+        name = "verify",
+        alias = "v",
+        about = "Checks the header links and signatures of a chain archive"
+    )]
+    Verify {
+        #[structopt(parse(from_os_str))]
+        archive: PathBuf,
+    },
+}
+
+/// Default file name of exported chain archives
+pub const DEFAULT_ARCHIVE_FILE_NAME: &str = "chain.json";
+
+pub fn chain(command: ChainCommand) -> DefaultResult<()> {
+    match command {
+        ChainCommand::Export {
+            storage,
+            kv,
+            output,
+        } => export(
+            &storage.unwrap_or_else(|| PathBuf::from(LOCAL_STORAGE_PATH)),
+            kv,
+            &output.unwrap_or_else(|| PathBuf::from(DEFAULT_ARCHIVE_FILE_NAME)),
+        ),
+        ChainCommand::Verify { archive } => verify(&archive),
+    }
+}
+
+fn export(storage: &Path, kv: bool, output: &Path) -> DefaultResult<()> {
+    let archive = archive_from_storage(storage, kv)?;
+    fs::write(output, serde_json::to_string_pretty(&archive)?)?;
+    println!(
+        "{} chain of {} entries to {}",
+        "Exported".green().bold(),
+        archive.chain().len(),
+        output.to_string_lossy()
+    );
+    println!("DNA:    {}", archive.dna_address());
+    println!("Agent:  {}", archive.agent_id().key);
+    println!(
+        "Import it into a running instance with the admin/instance/import_chain container function"
+    );
+    Ok(())
+}
+
+fn verify(path: &Path) -> DefaultResult<()> {
+    let archive: ChainArchive = serde_json::from_str(&fs::read_to_string(path)?)?;
+    archive.verify()?;
+    println!(
+        "{} chain of {} entries",
+        "Verified".green().bold(),
+        archive.chain().len()
+    );
+    Ok(())
+}
+
+/// Reads the chain of an instance directly from its storage, so the instance must not be running.
+fn archive_from_storage(storage: &Path, kv: bool) -> DefaultResult<ChainArchive> {
+    if !storage.exists() {
+        bail!("{} does not exist", storage.to_string_lossy());
+    }
+    let (cas, state): (
+        Arc<RwLock<ContentAddressableStorage>>,
+        Arc<RwLock<ContentAddressableStorage>>,
+    ) = if kv {
+        let store = Arc::new(RwLock::new(KvStore::open_read_only(storage)?));
+        (
+            Arc::new(RwLock::new(KvStorage::new(store.clone()))),
+            Arc::new(RwLock::new(KvStorage::for_state(store))),
+        )
+    } else {
+        let cas_path = storage.join("cas");
+        let state_path = storage.join("state");
+        (
+            Arc::new(RwLock::new(FilesystemStorage::new(
+                &cas_path.to_string_lossy(),
+            )?)),
+            Arc::new(RwLock::new(FilesystemStorage::new(
+                &state_path.to_string_lossy(),
+            )?)),
+        )
+    };
+    let top_chain_header = SimplePersister::new(state)?.top_chain_header(&cas)?;
+    if top_chain_header.is_none() {
+        bail!("no source chain found in {}", storage.to_string_lossy());
+    }
+    Ok(ChainArchive::from_chain(
+        &ChainStore::new(cas),
+        &top_chain_header,
+    )?)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::cli::init::tests::gen_dir;
+
+    #[test]
+    fn export_fails_without_chain() {
+        let dir = gen_dir();
+        fs::create_dir_all(dir.path().join("cas")).unwrap();
+        assert!(archive_from_storage(dir.path(), false).is_err());
+        assert!(archive_from_storage(&dir.path().join("missing"), false).is_err());
+        assert!(archive_from_storage(&dir.path().join("storage.db"), true).is_err());
+    }
+
+    #[test]
+    fn verify_rejects_invalid_archives() {
+        let dir = gen_dir();
+        let path = dir.path().join(DEFAULT_ARCHIVE_FILE_NAME);
+        fs::write(&path, "{}").unwrap();
+        assert!(verify(&path).is_err());
+    }
+}
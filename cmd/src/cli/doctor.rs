@@ -0,0 +1,156 @@
+use crate::{cli::run::LOCAL_STORAGE_PATH, error::DefaultResult};
+use colored::*;
+use holochain_cas_implementations::{
+    cas::{file::FilesystemStorage, kv::KvStorage},
+    integrity::{IntegrityCheck, IntegrityProblem, IntegrityReport, QUARANTINE_DIR},
+    kv::KvStore,
+};
+use holochain_core::persister::SimplePersister;
+use holochain_core_types::cas::{content::AddressableContent, storage::ContentAddressableStorage};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+/// Checks the storage of a stopped instance and prints what is wrong with it.
+/// Encrypted storages can only be checked by the container, which knows the agent's keys,
+/// through the admin/instance/check_storage container function.
+pub fn doctor(storage: Option<PathBuf>, kv: bool, quarantine: bool) -> DefaultResult<()> {
+    let storage = storage.unwrap_or_else(|| PathBuf::from(LOCAL_STORAGE_PATH));
+    let report = check_storage(&storage, kv, quarantine)?;
+
+    println!(
+        "Checked {} items of content, {} EAVs and {} chain headers",
+        report.content_checked, report.eavs_checked, report.headers_checked
+    );
+    if report.is_ok() {
+        println!("{} no problems found", "Healthy:".green().bold());
+        return Ok(());
+    }
+    for problem in &report.problems {
+        println!("{} {}", "Problem:".red().bold(), describe(problem));
+    }
+    if quarantine && !kv {
+        println!(
+            "Corrupt items got moved to {}",
+            storage.join(QUARANTINE_DIR).to_string_lossy()
+        );
+    }
+    bail!("found {} problems", report.problems.len())
+}
+
+fn check_storage(storage: &Path, kv: bool, quarantine: bool) -> DefaultResult<IntegrityReport> {
+    if !storage.exists() {
+        bail!("{} does not exist", storage.to_string_lossy());
+    }
+    if quarantine && kv {
+        bail!("corrupt items can only be quarantined in file storages");
+    }
+    let top_chain_header = {
+        let (cas, state): (
+            Arc<RwLock<ContentAddressableStorage>>,
+            Arc<RwLock<ContentAddressableStorage>>,
+        ) = if kv {
+            let store = Arc::new(RwLock::new(KvStore::open_read_only(storage)?));
+            (
+                Arc::new(RwLock::new(KvStorage::new(store.clone()))),
+                Arc::new(RwLock::new(KvStorage::for_state(store))),
+            )
+        } else {
+            let cas_path = storage.join("cas");
+            let state_path = storage.join("state");
+            (
+                Arc::new(RwLock::new(FilesystemStorage::new(
+                    &cas_path.to_string_lossy(),
+                )?)),
+                Arc::new(RwLock::new(FilesystemStorage::new(
+                    &state_path.to_string_lossy(),
+                )?)),
+            )
+        };
+        SimplePersister::new(state)?.top_chain_header(&cas)?
+    };
+    let check = IntegrityCheck {
+        top_chain_header: top_chain_header.map(|header| header.address()),
+        storage_key: None,
+        quarantine,
+    };
+    Ok(if kv {
+        check.check_kv_storage(storage)?
+    } else {
+        check.check_file_storage(storage)?
+    })
+}
+
+fn describe(problem: &IntegrityProblem) -> String {
+    match problem {
+        IntegrityProblem::CorruptContent {
+            address,
+            reason,
+            quarantined,
+        } => format!(
+            "content {} is corrupt: {}{}",
+            address,
+            reason,
+            if *quarantined { " (quarantined)" } else { "" }
+        ),
+        IntegrityProblem::CorruptEav {
+            location,
+            reason,
+            quarantined,
+        } => format!(
+            "EAV at {} is corrupt: {}{}",
+            location,
+            reason,
+            if *quarantined { " (quarantined)" } else { "" }
+        ),
+        IntegrityProblem::DanglingEav {
+            entity,
+            attribute,
+            value,
+        } => format!(
+            "EAV ({}, {}, {}) is about missing content",
+            entity, attribute, value
+        ),
+        IntegrityProblem::BrokenChain { address, reason } => {
+            format!("source chain is broken at {}: {}", address, reason)
+        }
+        IntegrityProblem::TornRecord { position } => format!(
+            "last write at byte {} did not complete and gets dropped when the instance starts",
+            position
+        ),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::cli::init::tests::gen_dir;
+    use std::fs;
+
+    #[test]
+    fn empty_storage_is_healthy() {
+        let dir = gen_dir();
+        fs::create_dir_all(dir.path().join("cas")).unwrap();
+        assert!(check_storage(dir.path(), false, true).unwrap().is_ok());
+        assert!(check_storage(&dir.path().join("missing"), false, false).is_err());
+        assert!(check_storage(&dir.path().join("storage.db"), true, true).is_err());
+    }
+
+    #[test]
+    fn corrupt_content_gets_reported() {
+        let dir = gen_dir();
+        fs::create_dir_all(dir.path().join("cas")).unwrap();
+        fs::write(
+            dir.path()
+                .join("cas")
+                .join("QmY8Mzg9F69e5P9AoQPYat655HEhc1TVGs11tmfNSzkqh2.txt"),
+            "not the test data",
+        )
+        .unwrap();
+        assert!(doctor(Some(dir.path().to_path_buf()), false, false).is_err());
+        let report = check_storage(dir.path(), false, true).unwrap();
+        assert_eq!(report.problems.len(), 1);
+        assert!(doctor(Some(dir.path().to_path_buf()), false, false).is_ok());
+    }
+}
@@ -1,4 +1,6 @@
 mod agent;
+mod chain;
+mod doctor;
 mod generate;
 mod init;
 pub mod package;
@@ -8,7 +10,9 @@ pub mod test;
 mod test_context;
 
 pub use self::{
-    agent::agent,
+    agent::{agent, AgentCommand},
+    chain::{chain, ChainCommand},
+    doctor::doctor,
     generate::generate,
     init::init,
     package::{package, unpack},
@@ -1,15 +1,26 @@
-use cli::{self, package};
+use cli::{self, agent::new_passphrase, package};
 use colored::*;
 use error::DefaultResult;
 use holochain_container_api::{
     config::*,
-    container::{mount_container_from_config, CONTAINER},
+    container::{Container, KeyLoader, CONTAINER},
+    keystore::Keystore,
     logger::LogRules,
 };
-use holochain_core_types::agent::AgentId;
-use std::{env, fs};
+use holochain_core::agent::keys::Keypair;
+use holochain_core_types::error::HolochainError;
+use std::{
+    env, fs,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+/// Where `hc run --persist` keeps the storage of its instance
+pub const LOCAL_STORAGE_PATH: &str = ".hc";
 
-const LOCAL_STORAGE_PATH: &str = ".hc";
+/// The keystore file in LOCAL_STORAGE_PATH that `hc run --persist` keeps the keys of its
+/// agent in, as its chain has to be signed by the same agent every time
+pub const LOCAL_KEYSTORE_FILE: &str = "agent.keystore";
 
 const AGENT_CONFIG_ID: &str = "hc-run-agent";
 const DNA_CONFIG_ID: &str = "hc-run-dna";
@@ -29,12 +40,31 @@ pub fn run(
     }
 
     let agent_name = env::var("HC_AGENT").ok();
-    let agent = AgentId::generate_fake(&agent_name.unwrap_or_else(|| String::from("testAgent")));
+    let agent_name = agent_name.unwrap_or_else(|| String::from("testAgent"));
+    // Without --persist nothing the agent does outlives the container,
+    // so it gets fresh random keys that never get stored
+    let (public_address, key_file, maybe_key_loader) = if persist {
+        fs::create_dir_all(LOCAL_STORAGE_PATH)?;
+        let key_file = Path::new(LOCAL_STORAGE_PATH).join(LOCAL_KEYSTORE_FILE);
+        let keystore = local_keystore(&key_file)?;
+        (
+            keystore.public_address(),
+            key_file.to_string_lossy().to_string(),
+            None,
+        )
+    } else {
+        let keys = Keypair::new_random()?;
+        (
+            keys.address(),
+            String::new(),
+            Some(throwaway_key_loader(keys)),
+        )
+    };
     let agent_config = AgentConfiguration {
         id: AGENT_CONFIG_ID.into(),
-        name: agent.nick,
-        public_address: agent.key,
-        key_file: "hc_run.key".into(),
+        public_address: public_address.to_string(),
+        name: agent_name,
+        key_file,
     };
 
     let dna_config = DnaConfiguration {
@@ -44,10 +74,9 @@ pub fn run(
     };
 
     let storage = if persist {
-        fs::create_dir_all(LOCAL_STORAGE_PATH)?;
-
         StorageConfiguration::File {
             path: LOCAL_STORAGE_PATH.into(),
+            encrypted: false,
         }
     } else {
         StorageConfiguration::Memory
@@ -58,6 +87,9 @@ pub fn run(
         dna: DNA_CONFIG_ID.into(),
         agent: AGENT_CONFIG_ID.into(),
         storage,
+        properties: None,
+        migrated_from: None,
+        dht_quota: None,
     };
 
     let interface_type = env::var("HC_INTERFACE").ok().unwrap_or_else(|| interface);
@@ -75,6 +107,7 @@ pub fn run(
         admin: true,
         instances: vec![InstanceReferenceConfiguration {
             id: INSTANCE_CONFIG_ID.into(),
+            cap_token: None,
         }],
     };
 
@@ -86,11 +119,13 @@ pub fn run(
     };
 
     let n3h_path = env::var("HC_N3H_PATH").ok();
+    let tcp_bind_address = env::var("HC_TCP_BIND_ADDRESS").ok();
 
     // create an n3h network config if the --networked flag is set
     // or if a value where to find n3h has been put into the
-    // HC_N3H_PATH environment variable
-    let network_config = if networked || n3h_path.is_some() {
+    // HC_N3H_PATH environment variable.
+    // HC_TCP_BIND_ADDRESS makes the container connect to other nodes itself instead.
+    let network_config = if networked || n3h_path.is_some() || tcp_bind_address.is_some() {
         let n3h_mode = env::var("HC_N3H_MODE").ok();
         let n3h_persistence_path = env::var("HC_N3H_WORK_DIR").ok();
         let n3h_bootstrap_node = env::var("HC_N3H_BOOTSTRAP_NODE").ok();
@@ -107,6 +142,8 @@ pub fn run(
             n3h_persistence_path: n3h_persistence_path
                 .unwrap_or_else(|| default_n3h_persistence_path()),
             n3h_ipc_uri: Default::default(),
+            tcp_bind_address,
+            redundancy: default_redundancy(),
         })
     } else {
         None
@@ -122,7 +159,12 @@ pub fn run(
         ..Default::default()
     };
 
-    mount_container_from_config(base_config);
+    let container = match maybe_key_loader {
+        Some(key_loader) => Container::from_config(base_config).with_key_loader(key_loader),
+        // The default KeyLoader unlocks the keystore
+        None => Container::from_config(base_config),
+    };
+    CONTAINER.lock().unwrap().replace(container);
     let mut container_guard = CONTAINER.lock().unwrap();
     let container = container_guard.as_mut().expect("Container must be mounted");
 
@@ -157,6 +199,35 @@ pub fn run(
     Ok(())
 }
 
+/// Loads the keystore of `hc run --persist` or, on the first run, creates it
+fn local_keystore(path: &Path) -> DefaultResult<Keystore> {
+    if path.exists() {
+        return Ok(Keystore::load(path)?);
+    }
+    println!("Creating a keystore for the agent of the persisted chain");
+    let keystore = Keystore::new_random(&new_passphrase()?)?;
+    keystore.save(path)?;
+    println!(
+        "{} keystore {}",
+        "Created".green().bold(),
+        path.to_string_lossy()
+    );
+    Ok(keystore)
+}
+
+/// KeyLoader that hands out the given keys to the single instance of `hc run`
+fn throwaway_key_loader(keys: Keypair) -> KeyLoader {
+    let keys = Mutex::new(Some(keys));
+    let loader = Box::new(move |_: &AgentConfiguration| {
+        keys.lock()
+            .unwrap()
+            .take()
+            .ok_or_else(|| HolochainError::ErrorGeneric("Keys were already handed out".to_string()))
+    })
+        as Box<FnMut(&AgentConfiguration) -> Result<Keypair, HolochainError> + Send + Sync>;
+    Arc::new(loader)
+}
+
 #[cfg(test)]
 // flagged as broken for:
 // 1. taking 60+ seconds
@@ -14,6 +14,7 @@ extern crate assert_cmd;
 extern crate base64;
 extern crate colored;
 extern crate dir_diff;
+extern crate dirs;
 extern crate semver;
 extern crate toml;
 #[macro_use]
@@ -38,9 +39,41 @@ enum Cli {
     #[structopt(
         name = "agent",
         alias = "a",
-        about = "Starts a Holochain node as an agent"
+        about = "Manages agent keys stored in keystore files"
     )]
-    Agent,
+    Agent {
+        #[structopt(subcommand)]
+        command: cli::AgentCommand,
+    },
+    #[structopt(
+        name = "chain",
+        alias = "c",
+        about = "Exports and verifies source chain archives"
+    )]
+    Chain {
+        #[structopt(subcommand)]
+        command: cli::ChainCommand,
+    },
+    #[structopt(
+        name = "doctor",
+        about = "Checks the storage of a stopped instance for corrupt content and a broken source chain"
+    )]
+    Doctor {
+        #[structopt(
+            long,
+            short,
+            help = "Storage of the instance (defaults to the storage of `hc run --persist`)",
+            parse(from_os_str)
+        )]
+        storage: Option<PathBuf>,
+        #[structopt(long, help = "The storage is a single key-value database file")]
+        kv: bool,
+        #[structopt(
+            long,
+            help = "Moves corrupt items of file storages into a quarantine directory"
+        )]
+        quarantine: bool,
+    },
     #[structopt(
         name = "package",
         alias = "p",
@@ -156,7 +189,13 @@ fn run() -> HolochainResult<()> {
     let args = Cli::from_args();
 
     match args {
-        Cli::Agent => cli::agent().map_err(HolochainError::Default)?,
+        Cli::Agent { command } => cli::agent(command).map_err(HolochainError::Default)?,
+        Cli::Chain { command } => cli::chain(command).map_err(HolochainError::Default)?,
+        Cli::Doctor {
+            storage,
+            kv,
+            quarantine,
+        } => cli::doctor(storage, kv, quarantine).map_err(HolochainError::Default)?,
         Cli::Package { strip_meta, output } => {
             cli::package(strip_meta, output).map_err(HolochainError::Default)?
         }
@@ -98,6 +98,17 @@ impl Configuration {
                     instance.dna, instance.id
                 )
             })?;
+            if let Some(ref old_id) = instance.migrated_from {
+                self.instance_by_id(old_id).is_some().ok_or_else(|| {
+                    format!(
+                        "Instance configuration \"{}\" not found, mentioned in instance \"{}\"",
+                        old_id, instance.id
+                    )
+                })?;
+                (old_id != &instance.id).ok_or_else(|| {
+                    format!("Instance \"{}\" can't be migrated from itself", instance.id)
+                })?;
+            }
         }
         for ref interface in self.interfaces.iter() {
             for ref instance in interface.instances.iter() {
@@ -171,6 +182,16 @@ impl Configuration {
         self.instances.iter().find(|ic| &ic.id == id).cloned()
     }
 
+    /// Returns the IDs of the instances that continue the chain of the instance with the
+    /// given ID after a migration. They need it to stay around and running.
+    pub fn instances_migrated_from(&self, id: &str) -> Vec<String> {
+        self.instances
+            .iter()
+            .filter(|ic| ic.migrated_from.as_ref().map(|old_id| old_id.as_str()) == Some(id))
+            .map(|ic| ic.id.clone())
+            .collect()
+    }
+
     /// Returns the interface configuration with the given ID if present
     pub fn interface_by_id(&self, id: &str) -> Option<InterfaceConfiguration> {
         self.interfaces.iter().find(|ic| &ic.id == id).cloned()
@@ -215,7 +236,7 @@ impl Configuration {
             .collect();
 
         // Create vector of edges (with node indices) from bridges:
-        let edges: Vec<(&NodeIndex<u32>, &NodeIndex<u32>)> = self.bridges
+        let mut edges: Vec<(&NodeIndex<u32>, &NodeIndex<u32>)> = self.bridges
             .iter()
             .map(|bridge| -> Result<(&NodeIndex<u32>, &NodeIndex<u32>), HolochainError> {
                 let start = index_map.get(&bridge.caller_id);
@@ -231,6 +252,19 @@ impl Configuration {
             })
             .collect::<Result<Vec<_>, _>>()?;
 
+        // Migrated instances depend on the instance they got migrated from:
+        for instance in self.instances.iter() {
+            if let Some(ref old_id) = instance.migrated_from {
+                let end = index_map.get(old_id).ok_or_else(|| {
+                    HolochainError::ConfigError(format!(
+                        "Instance configuration not found, mentioned in migration: {} -> {}",
+                        instance.id, old_id,
+                    ))
+                })?;
+                edges.push((index_map.get(&instance.id).unwrap(), end));
+            }
+        }
+
         // Add edges to graph:
         for &(node_a, node_b) in edges.iter() {
             graph.add_edge(node_a.clone(), node_b.clone(), "");
@@ -268,6 +302,12 @@ impl Configuration {
             .instances
             .into_iter()
             .filter(|instance| instance.id != *id)
+            .map(|mut instance| {
+                if instance.migrated_from.as_ref() == Some(id) {
+                    instance.migrated_from = None;
+                }
+                instance
+            })
             .collect();
 
         self.interfaces = self
@@ -324,26 +364,53 @@ impl TryFrom<DnaConfiguration> for Dna {
 
 /// An instance combines a DNA with an agent.
 /// Each instance has its own storage configuration.
+/// `properties` override the DNA's properties of the same name for this instance,
+/// so one DNA file can be run with different settings.
+/// Note that this changes the DNA and thus its hash.
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct InstanceConfiguration {
     pub id: String,
     pub dna: String,
     pub agent: String,
     pub storage: StorageConfiguration,
+    #[serde(default)]
+    pub properties: Option<serde_json::Value>,
+    /// Id of the instance whose source chain got closed and continued by this instance
+    #[serde(default)]
+    pub migrated_from: Option<String>,
+    /// Maximum number of bytes the entries this instance holds in its DHT shard for other
+    /// agents may take up. The least recently requested ones get dropped beyond it.
+    /// Unlimited if not set.
+    #[serde(default)]
+    pub dht_quota: Option<u64>,
 }
 
 /// This configures the Content Addressable Storage (CAS) that
 /// the instance uses to store source chain and DHT shard in.
-/// There are two storage implementations in cas_implementations so far:
+/// There are three storage implementations in cas_implementations so far:
 /// * memory
 /// * file
+/// * kv (a single-file key-value store with transactional writes)
+///
+/// Persistent storages can be encrypted at rest by setting `encrypted = true`.
+/// Everything gets sealed with a key that is derived from the agent's keys,
+/// so the storage can only be read by the instance's agent.
 ///
 /// Projected are various DB adapters.
 #[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum StorageConfiguration {
     Memory,
-    File { path: String },
+    File {
+        path: String,
+        #[serde(default)]
+        encrypted: bool,
+    },
+    Kv {
+        path: String,
+        #[serde(default)]
+        encrypted: bool,
+    },
 }
 
 /// Here, interfaces are user facing and make available zome functions to
@@ -379,6 +446,12 @@ pub enum InterfaceDriver {
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct InstanceReferenceConfiguration {
     pub id: String,
+
+    /// Capability token that gets attached to all zome calls made through this interface.
+    /// If not set, only public functions can be called.
+    /// Interface calls come from unknown callers, so only tokens of transferable grants work.
+    #[serde(default)]
+    pub cap_token: Option<String>,
 }
 
 /// A bridge enables an instance to call zome functions of another instance.
@@ -398,6 +471,13 @@ pub struct Bridge {
     /// by bound dynamically.
     /// Callers reference callees by this arbitrary but unique local name.
     pub handle: String,
+
+    /// Capability token, granted by the callee's agent, that gets attached to all
+    /// calls through this bridge. Calls are made from the caller's agent address,
+    /// so tokens of grants assigned to that agent work too.
+    /// If not set, only public functions of the callee can be called.
+    #[serde(default)]
+    pub cap_token: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
@@ -444,6 +524,21 @@ pub struct NetworkConfig {
     /// configs above. Default is None.
     #[serde(default)]
     pub n3h_ipc_uri: Option<String>,
+    /// Address to listen on for direct TCP connections with other containers,
+    /// like "0.0.0.0:4000".
+    /// If this is set the container does not use n3h but connects to other containers
+    /// itself, starting with the bootstrap nodes, and ignores the n3h configs above.
+    /// Default is None.
+    #[serde(default)]
+    pub tcp_bind_address: Option<String>,
+    /// How many agents hold each entry of the DHT, those closest to its address.
+    /// 0 makes every agent hold everything. Default is 5.
+    #[serde(default = "default_redundancy")]
+    pub redundancy: usize,
+}
+
+pub fn default_redundancy() -> usize {
+    holochain_net::sharding::DEFAULT_REDUNDANCY
 }
 
 pub fn default_n3h_mode() -> String {
@@ -579,6 +674,10 @@ pub mod tests {
     [instances.storage]
     type = "file"
     path = "app_spec_storage"
+    encrypted = true
+    [instances.properties]
+    language = "de"
+    max_posts = 10
 
     [[interfaces]]
     id = "app spec websocket interface"
@@ -595,6 +694,7 @@ pub mod tests {
     port = 4000
     [[interfaces.instances]]
     id = "app spec instance"
+    cap_token = "QmGrantAddress"
 
     [[interfaces]]
     id = "app spec domainsocket interface"
@@ -624,6 +724,27 @@ pub mod tests {
         assert_eq!(instance_config.id, "app spec instance");
         assert_eq!(instance_config.dna, "app spec rust");
         assert_eq!(instance_config.agent, "test agent");
+        assert_eq!(
+            instance_config.properties,
+            Some(json!({"language": "de", "max_posts": 10}))
+        );
+        match instance_config.storage {
+            StorageConfiguration::File {
+                ref path,
+                encrypted,
+            } => {
+                assert_eq!(path, "app_spec_storage");
+                assert!(encrypted);
+            }
+            _ => panic!("expected file storage"),
+        }
+        let websocket_instance = &config.interfaces[0].instances[0];
+        assert_eq!(websocket_instance.cap_token, None);
+        let http_instance = &config.interfaces[1].instances[0];
+        assert_eq!(
+            http_instance.cap_token,
+            Some(String::from("QmGrantAddress"))
+        );
         assert_eq!(config.logger.logger_type, "debug");
         assert_eq!(
             config.network.unwrap(),
@@ -635,6 +756,8 @@ pub mod tests {
                 n3h_mode: String::from("HACK"),
                 n3h_persistence_path: String::from("/Users/cnorris/.holochain/n3h_persistence"),
                 n3h_ipc_uri: None,
+                tcp_bind_address: None,
+                redundancy: default_redundancy(),
             }
         );
     }
@@ -879,7 +1002,9 @@ pub mod tests {
     path = "app_spec_storage"
 
     {}
-    "#, bridges)
+    "#,
+            bridges
+        )
     }
 
     #[test]
@@ -895,11 +1020,17 @@ pub mod tests {
     caller_id = "app2"
     callee_id = "app3"
     handle = "DPKI"
+    cap_token = "QmGrantAddress"
     "#,
         );
         let config = load_configuration::<Configuration>(&toml)
             .expect("Config should be syntactically correct");
         assert_eq!(config.check_consistency(), Ok(()));
+        assert_eq!(config.bridges[0].cap_token, None);
+        assert_eq!(
+            config.bridges[1].cap_token,
+            Some(String::from("QmGrantAddress"))
+        );
 
         // "->": calls
         // app1 -> app2 -> app3
@@ -1,13 +1,40 @@
 use crate::{
     config::{
         AgentConfiguration, Bridge, DnaConfiguration, InstanceConfiguration,
-        InstanceReferenceConfiguration, InterfaceConfiguration,
+        InstanceReferenceConfiguration, InterfaceConfiguration, StorageConfiguration,
     },
     container::{base::notify, Container},
     error::HolochainInstanceError,
 };
-use holochain_core_types::{cas::content::AddressableContent, error::HolochainError};
-use std::{path::PathBuf, sync::Arc};
+use futures::executor::block_on;
+use holochain_cas_implementations::{
+    cas::{encrypted::EncryptedStorage, file::FilesystemStorage, kv::KvStorage},
+    encryption::StorageKey,
+    integrity::{IntegrityCheck, IntegrityProblem, IntegrityReport},
+    kv::KvStore,
+};
+use holochain_core::{
+    agent::chain_archive::ChainArchive,
+    network::{peer_store::PeerInfo, validation_receipt::validation_counts},
+    persister::SimplePersister,
+    workflows::{
+        import_chain::{export_chain, import_chain_workflow},
+        migrate_chain::close_chain_workflow,
+    },
+};
+use holochain_core_types::{
+    cas::{
+        content::{Address, AddressableContent},
+        storage::ContentAddressableStorage,
+    },
+    error::HolochainError,
+};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
 
 pub trait ContainerAdmin {
     fn install_dna_from_file(&mut self, path: PathBuf, id: String) -> Result<(), HolochainError>;
@@ -37,6 +64,21 @@ pub trait ContainerAdmin {
         caller_id: &String,
         callee_id: &String,
     ) -> Result<(), HolochainError>;
+    fn migrate_instance(
+        &mut self,
+        id: &String,
+        new_id: &String,
+        dna_id: &String,
+    ) -> Result<(), HolochainError>;
+    fn export_chain(&self, id: &String) -> Result<ChainArchive, HolochainError>;
+    fn import_chain(&mut self, id: &String, archive: ChainArchive) -> Result<(), HolochainError>;
+    fn check_instance_storage(
+        &mut self,
+        id: &String,
+        quarantine: bool,
+    ) -> Result<IntegrityReport, HolochainError>;
+    fn instance_peers(&self, id: &String) -> Result<Vec<PeerInfo>, HolochainError>;
+    fn validation_counts(&self, id: &String) -> Result<BTreeMap<Address, usize>, HolochainError>;
 }
 
 impl ContainerAdmin for Container {
@@ -86,6 +128,18 @@ impl ContainerAdmin for Container {
             .map(|instance| instance.id.clone())
             .collect();
 
+        for instance_id in instance_ids.iter() {
+            if let Some(new_id) = new_config
+                .instances_migrated_from(instance_id)
+                .into_iter()
+                .find(|new_id| !instance_ids.contains(new_id))
+            {
+                return Err(HolochainError::ErrorGeneric(format!(
+                    "Instance '{}' continues the chain of instance '{}' of DNA '{}', remove it first",
+                    new_id, instance_id, id
+                )));
+            }
+        }
         for id in instance_ids.iter() {
             new_config = new_config.save_remove_instance(id);
         }
@@ -125,6 +179,12 @@ impl ContainerAdmin for Container {
     /// invalid.
     /// Then saves the config.
     fn remove_instance(&mut self, id: &String) -> Result<(), HolochainError> {
+        if let Some(new_id) = self.config.instances_migrated_from(id).first() {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "Instance '{}' continues the chain of instance '{}', remove it first",
+                new_id, id
+            )));
+        }
         let mut new_config = self.config.clone();
 
         new_config = new_config.save_remove_instance(id);
@@ -155,6 +215,23 @@ impl ContainerAdmin for Container {
 
     fn stop_instance(&mut self, id: &String) -> Result<(), HolochainInstanceError> {
         let instance = self.instances.get(id)?;
+        let running_new_id = self
+            .config
+            .instances_migrated_from(id)
+            .into_iter()
+            .find(|new_id| {
+                self.instances
+                    .get(new_id)
+                    .map(|new_instance| new_instance.read().unwrap().active())
+                    .unwrap_or(false)
+            });
+        if let Some(new_id) = running_new_id {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "Instance '{}' continues the chain of instance '{}', stop it first",
+                new_id, id
+            ))
+            .into());
+        }
         notify(format!("Stopping instance \"{}\"...", id));
         instance.write().unwrap().stop()
     }
@@ -242,6 +319,7 @@ impl ContainerAdmin for Container {
                 if interface.id == *interface_id {
                     interface.instances.push(InstanceReferenceConfiguration {
                         id: instance_id.clone(),
+                        cap_token: None,
                     });
                 }
                 interface
@@ -438,6 +516,295 @@ impl ContainerAdmin for Container {
 
         Ok(())
     }
+
+    /// Moves the agent of the instance given by id over to the DNA given by dna_id.
+    /// Adds and starts a new instance with id new_id whose source chain starts with a
+    /// ChainMigrate entry pointing to the chain of the old instance. Then closes the old chain
+    /// with the same entry.
+    /// The old instance stays in the config so the new one can still query its chain.
+    fn migrate_instance(
+        &mut self,
+        id: &String,
+        new_id: &String,
+        dna_id: &String,
+    ) -> Result<(), HolochainError> {
+        let old_instance_config = self.config.instance_by_id(id).ok_or_else(|| {
+            HolochainError::ErrorGeneric(format!("Instance with ID '{}' does not exist", id))
+        })?;
+        let old_instance = self.instances.get(id).cloned().ok_or_else(|| {
+            HolochainError::ErrorGeneric(format!("Instance '{}' is not running", id))
+        })?;
+        let old_context = old_instance.read().unwrap().context().clone();
+        let old_dna = old_context.get_dna().ok_or(HolochainError::DnaMissing)?;
+        let old_agent_state = old_context
+            .state()
+            .ok_or_else(|| HolochainError::ErrorGeneric("State not initialized".to_string()))?
+            .agent();
+        if let Some(migrate) = old_agent_state.closing_migration() {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "Instance '{}' already got migrated to DNA {}",
+                id,
+                migrate.new_dna_address()
+            )));
+        }
+
+        let dna_config = self.config.dna_by_id(dna_id).ok_or_else(|| {
+            HolochainError::ErrorGeneric(format!("DNA with ID '{}' does not exist", dna_id))
+        })?;
+        let new_dna = Arc::get_mut(&mut self.dna_loader).unwrap()(&dna_config.file)?;
+        if new_dna.address() == old_dna.address() {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "Instance '{}' already runs DNA '{}'",
+                id, dna_id
+            )));
+        }
+
+        // File and KV storages of migrated instances live next to the old one
+        // and stay encrypted if the old one was
+        let storage = match old_instance_config.storage {
+            StorageConfiguration::Memory => StorageConfiguration::Memory,
+            StorageConfiguration::File {
+                ref path,
+                encrypted,
+            } => StorageConfiguration::File {
+                path: Path::new(path)
+                    .with_file_name(new_id)
+                    .to_string_lossy()
+                    .to_string(),
+                encrypted,
+            },
+            StorageConfiguration::Kv {
+                ref path,
+                encrypted,
+            } => StorageConfiguration::Kv {
+                path: Path::new(path)
+                    .with_file_name(new_id)
+                    .to_string_lossy()
+                    .to_string(),
+                encrypted,
+            },
+        };
+        let new_storage_path = match storage {
+            StorageConfiguration::Memory => None,
+            StorageConfiguration::File { ref path, .. }
+            | StorageConfiguration::Kv { ref path, .. } => Some(PathBuf::from(path)),
+        };
+        if let Some(ref path) = new_storage_path {
+            if path.exists() {
+                return Err(HolochainError::ErrorGeneric(format!(
+                    "Storage of instance '{}' already exists at {}",
+                    new_id,
+                    path.display()
+                )));
+            }
+        }
+        self.add_instance(InstanceConfiguration {
+            id: new_id.clone(),
+            dna: dna_id.clone(),
+            agent: old_instance_config.agent.clone(),
+            storage,
+            properties: None,
+            migrated_from: Some(id.clone()),
+            dht_quota: old_instance_config.dht_quota,
+        })?;
+
+        // The new chain starts with the migration, but only gets used once the old chain
+        // is closed. If either fails, the new instance and its storage get removed again
+        // so the old instance stays as it was.
+        let config = self.config.clone();
+        let migration = self
+            .instantiate_from_config(new_id, &config, None)
+            .map_err(|error| {
+                HolochainError::ErrorGeneric(format!(
+                    "Error while trying to create instance \"{}\": {}",
+                    new_id, error
+                ))
+            })
+            .and_then(|new_instance| {
+                block_on(close_chain_workflow(new_dna.address(), &old_context))?;
+                Ok(new_instance)
+            });
+        let new_instance = match migration {
+            Ok(new_instance) => new_instance,
+            Err(error) => {
+                self.config = config.save_remove_instance(new_id);
+                self.save_config()?;
+                if let Some(ref path) = new_storage_path.filter(|path| path.exists()) {
+                    let removed = if path.is_dir() {
+                        fs::remove_dir_all(path)
+                    } else {
+                        fs::remove_file(path)
+                    };
+                    removed.map_err(|remove_error| {
+                        HolochainError::ErrorGeneric(format!(
+                            "{}. Could not remove storage of instance '{}' at {}: {}",
+                            error,
+                            new_id,
+                            path.display(),
+                            remove_error
+                        ))
+                    })?;
+                }
+                return Err(error);
+            }
+        };
+
+        self.instances
+            .insert(new_id.clone(), Arc::new(RwLock::new(new_instance)));
+        self.start_instance(new_id)
+            .map_err(|error| HolochainError::ErrorGeneric(error.to_string()))?;
+        notify(format!(
+            "Migrated instance \"{}\" to instance \"{}\" of DNA \"{}\".",
+            id, new_id, dna_id
+        ));
+        Ok(())
+    }
+
+    /// Archives the source chain of the running instance given by id.
+    fn export_chain(&self, id: &String) -> Result<ChainArchive, HolochainError> {
+        let instance = self.instances.get(id).ok_or_else(|| {
+            HolochainError::ErrorGeneric(format!("Instance '{}' is not running", id))
+        })?;
+        let context = instance.read().unwrap().context().clone();
+        export_chain(&context)
+    }
+
+    /// Lists the peers the running instance given by id knows about.
+    fn instance_peers(&self, id: &String) -> Result<Vec<PeerInfo>, HolochainError> {
+        let instance = self.instances.get(id).ok_or_else(|| {
+            HolochainError::ErrorGeneric(format!("Instance '{}' is not running", id))
+        })?;
+        let context = instance.read().unwrap().context().clone();
+        let peers = context
+            .state()
+            .map(|state| state.network().peer_store.peers())
+            .ok_or_else(|| HolochainError::ErrorGeneric("State not initialized".to_string()))?;
+        Ok(peers)
+    }
+
+    /// Counts for every published entry of the running instance given by id how many
+    /// holders sent a receipt saying they found it valid.
+    fn validation_counts(&self, id: &String) -> Result<BTreeMap<Address, usize>, HolochainError> {
+        let instance = self.instances.get(id).ok_or_else(|| {
+            HolochainError::ErrorGeneric(format!("Instance '{}' is not running", id))
+        })?;
+        let context = instance.read().unwrap().context().clone();
+        validation_counts(&context)
+    }
+
+    /// Restores an archived source chain into the running instance given by id.
+    /// The archive gets verified and has to belong to the DNA and agent of the instance.
+    fn import_chain(&mut self, id: &String, archive: ChainArchive) -> Result<(), HolochainError> {
+        let instance = self.instances.get(id).ok_or_else(|| {
+            HolochainError::ErrorGeneric(format!("Instance '{}' is not running", id))
+        })?;
+        let context = instance.read().unwrap().context().clone();
+        let length = archive.chain().len();
+        block_on(import_chain_workflow(archive, &context))?;
+        notify(format!(
+            "Imported chain of {} entries into instance \"{}\".",
+            length, id
+        ));
+        Ok(())
+    }
+    /// Checks the persistent storage of the stopped instance given by id for corrupt items
+    /// and a broken source chain. If quarantine is set, corrupt items of file storages get
+    /// moved out of the storage.
+    fn check_instance_storage(
+        &mut self,
+        id: &String,
+        quarantine: bool,
+    ) -> Result<IntegrityReport, HolochainError> {
+        let instance_config = self.config.instance_by_id(id).ok_or_else(|| {
+            HolochainError::ErrorGeneric(format!("Instance with ID '{}' does not exist", id))
+        })?;
+        if let Some(instance) = self.instances.get(id) {
+            if instance.read().unwrap().active() {
+                return Err(HolochainError::ErrorGeneric(format!(
+                    "Instance '{}' has to be stopped to check its storage",
+                    id
+                )));
+            }
+        }
+        let (path, encrypted, kv) = match instance_config.storage {
+            StorageConfiguration::Memory => {
+                return Err(HolochainError::ErrorGeneric(format!(
+                    "Instance '{}' has no persistent storage",
+                    id
+                )));
+            }
+            StorageConfiguration::File { path, encrypted } => (path, encrypted, false),
+            StorageConfiguration::Kv { path, encrypted } => (path, encrypted, true),
+        };
+        let storage_key = if encrypted {
+            let agent_config = self.config.agent_by_id(&instance_config.agent).ok_or(
+                HolochainError::ErrorGeneric(format!(
+                    "Agent with ID '{}' does not exist",
+                    instance_config.agent
+                )),
+            )?;
+            let mut keys = Arc::get_mut(&mut self.key_loader).unwrap()(&agent_config)?;
+            Some(StorageKey::new(&mut keys.storage_key()?)?)
+        } else {
+            None
+        };
+
+        let top_chain_header = {
+            let (mut cas, mut state): (
+                Arc<RwLock<ContentAddressableStorage>>,
+                Arc<RwLock<ContentAddressableStorage>>,
+            ) = if kv {
+                let store = Arc::new(RwLock::new(KvStore::open_read_only(&path)?));
+                (
+                    Arc::new(RwLock::new(KvStorage::new(store.clone()))),
+                    Arc::new(RwLock::new(KvStorage::for_state(store))),
+                )
+            } else {
+                let cas_path = Path::new(&path).join("cas");
+                let state_path = Path::new(&path).join("state");
+                (
+                    Arc::new(RwLock::new(FilesystemStorage::new(
+                        &cas_path.to_string_lossy(),
+                    )?)),
+                    Arc::new(RwLock::new(FilesystemStorage::new(
+                        &state_path.to_string_lossy(),
+                    )?)),
+                )
+            };
+            if let Some(ref key) = storage_key {
+                cas = Arc::new(RwLock::new(EncryptedStorage::new(cas, key.clone())));
+                state = Arc::new(RwLock::new(EncryptedStorage::new(state, key.clone())));
+            }
+            SimplePersister::new(state).and_then(|persister| persister.top_chain_header(&cas))
+        };
+        let check = IntegrityCheck {
+            top_chain_header: top_chain_header
+                .as_ref()
+                .ok()
+                .and_then(|header| header.as_ref())
+                .map(|header| header.address()),
+            storage_key,
+            quarantine,
+        };
+        let mut report = if kv {
+            check.check_kv_storage(&path)?
+        } else {
+            check.check_file_storage(&path)?
+        };
+        if let Err(error) = top_chain_header {
+            report.problems.push(IntegrityProblem::BrokenChain {
+                address: Default::default(),
+                reason: format!("Could not find the top chain header: {}", error),
+            });
+        }
+
+        notify(format!(
+            "Checked storage of instance \"{}\": {} problems found.",
+            id,
+            report.problems.len()
+        ));
+        Ok(report)
+    }
 }
 
 #[cfg(test)]
@@ -446,9 +813,11 @@ pub mod tests {
     use crate::{
         config::{load_configuration, Configuration, InterfaceConfiguration, InterfaceDriver},
         container::base::{tests::example_dna_string, DnaLoader},
+        keystore::test_key_loader,
     };
-    use holochain_core_types::{agent::AgentId, dna::Dna, json::JsonString};
+    use holochain_core_types::{agent::AgentId, dna::Dna, entry::Entry, json::JsonString};
     use std::{convert::TryFrom, fs::File, io::Read};
+    use tempfile::tempdir;
 
     pub fn test_dna_loader() -> DnaLoader {
         let loader =
@@ -564,6 +933,7 @@ pattern = ".*""#
         let config = load_configuration::<Configuration>(&test_toml(port)).unwrap();
         let mut container = Container::from_config(config.clone());
         container.dna_loader = test_dna_loader();
+        container.key_loader = test_key_loader();
         container.load_config().unwrap();
 
         let mut tmp_config_path = PathBuf::new();
@@ -635,7 +1005,6 @@ id = "new-dna""#,
         assert_eq!(config_contents, toml,);
     }
 
-    use crate::config::StorageConfiguration;
     #[test]
     fn test_add_instance() {
         let mut container = create_test_container("test_add_instance", 3001);
@@ -650,6 +1019,9 @@ id = "new-dna""#,
             dna: String::from("new-dna"),
             agent: String::from("test-agent-1"),
             storage: StorageConfiguration::Memory,
+            properties: None,
+            migrated_from: None,
+            dht_quota: None,
         });
 
         assert_eq!(add_result, Ok(()));
@@ -915,6 +1287,9 @@ ui_interfaces = []"#,
             dna: String::from("test-dna"),
             agent: String::from("test-agent-1"),
             storage: StorageConfiguration::Memory,
+            properties: None,
+            migrated_from: None,
+            dht_quota: None,
         };
 
         assert_eq!(container.add_instance(instance_config.clone()), Ok(()));
@@ -1135,6 +1510,7 @@ type = "websocket""#,
             caller_id: String::from("test-instance-1"),
             callee_id: String::from("test-instance-2"),
             handle: String::from("my favourite instance!"),
+            cap_token: None,
         };
 
         assert_eq!(container.add_bridge(bridge), Ok(()),);
@@ -1197,4 +1573,148 @@ ui_interfaces = []"#,
 
         assert_eq!(config_contents, toml,);
     }
+
+    #[test]
+    fn test_migrate_instance() {
+        let mut container = create_test_container("test_migrate_instance", 3012);
+        let loader = Box::new(|file: &String| {
+            let mut dna = Dna::try_from(JsonString::from(example_dna_string())).unwrap();
+            if file == "new-dna.hcpkg" {
+                dna.version = String::from("2.0.0");
+            }
+            Ok(dna)
+        }) as Box<FnMut(&String) -> Result<Dna, HolochainError> + Send + Sync>;
+        container.dna_loader = Arc::new(loader);
+        container
+            .install_dna_from_file(PathBuf::from("new-dna.hcpkg"), String::from("new-dna"))
+            .expect("Could not install DNA");
+
+        // there is nothing to migrate to if the DNA stays the same
+        assert!(container
+            .migrate_instance(
+                &String::from("test-instance-1"),
+                &String::from("migrated-instance"),
+                &String::from("test-dna"),
+            )
+            .is_err());
+
+        assert_eq!(
+            container.migrate_instance(
+                &String::from("test-instance-1"),
+                &String::from("migrated-instance"),
+                &String::from("new-dna"),
+            ),
+            Ok(())
+        );
+        let instance_config = container
+            .config()
+            .instance_by_id("migrated-instance")
+            .unwrap();
+        assert_eq!(instance_config.agent, String::from("test-agent-1"));
+        assert_eq!(
+            instance_config.migrated_from,
+            Some(String::from("test-instance-1"))
+        );
+
+        let context_of = |id: &str| {
+            container
+                .instances
+                .get(id)
+                .unwrap()
+                .read()
+                .unwrap()
+                .context()
+                .clone()
+        };
+        let old_agent_state = context_of("test-instance-1").state().unwrap().agent();
+        let new_agent_state = context_of("migrated-instance").state().unwrap().agent();
+        let migrate = old_agent_state
+            .closing_migration()
+            .expect("old chain should be closed");
+        assert_eq!(new_agent_state.closing_migration(), None);
+        assert!(new_agent_state
+            .get_header_for_entry(&Entry::ChainMigrate(migrate))
+            .is_some());
+
+        // a closed chain can't be migrated again
+        assert!(container
+            .migrate_instance(
+                &String::from("test-instance-1"),
+                &String::from("another-instance"),
+                &String::from("new-dna"),
+            )
+            .is_err());
+        assert!(container
+            .config()
+            .instance_by_id("another-instance")
+            .is_none());
+
+        // the old instance has to stay while the migrated one continues its chain
+        let old_id = String::from("test-instance-1");
+        assert!(container.remove_instance(&old_id).is_err());
+        assert!(container.uninstall_dna(&String::from("test-dna")).is_err());
+        match container.stop_instance(&old_id) {
+            Err(HolochainInstanceError::InternalFailure(_)) => (),
+            result => panic!("old instance should not stop, got {:?}", result),
+        }
+        assert_eq!(
+            container.stop_instance(&String::from("migrated-instance")),
+            Ok(())
+        );
+        assert_eq!(
+            container.stop_instance(&old_id),
+            Err(HolochainInstanceError::InstanceNotActiveYet)
+        );
+    }
+
+    #[test]
+    fn test_export_and_import_chain() {
+        let mut container = create_test_container("test_export_and_import_chain", 3013);
+        let id = String::from("test-instance-1");
+
+        let archive = container.export_chain(&id).expect("Could not export chain");
+        assert_eq!(archive.chain().len(), 2);
+        assert!(archive.verify().is_ok());
+        assert!(container
+            .export_chain(&String::from("unknown-instance"))
+            .is_err());
+
+        // the running chain is the archived one, so nothing gets added
+        assert_eq!(container.import_chain(&id, archive.clone()), Ok(()));
+        assert_eq!(container.export_chain(&id), Ok(archive));
+    }
+
+    #[test]
+    fn test_check_instance_storage() {
+        let mut container = create_test_container("test_check_instance_storage", 3014);
+        let storage_dir = tempdir().unwrap();
+        let id = String::from("stored-instance");
+        container
+            .add_instance(InstanceConfiguration {
+                id: id.clone(),
+                dna: String::from("test-dna"),
+                agent: String::from("test-agent-1"),
+                storage: StorageConfiguration::File {
+                    path: storage_dir.path().to_string_lossy().to_string(),
+                    encrypted: false,
+                },
+                properties: None,
+                migrated_from: None,
+                dht_quota: None,
+            })
+            .expect("Could not add instance");
+
+        let report = container
+            .check_instance_storage(&id, false)
+            .expect("Could not check storage");
+        assert!(report.is_ok());
+
+        // running instances and memory storages can't be checked
+        assert!(container
+            .check_instance_storage(&String::from("test-instance-1"), false)
+            .is_err());
+        assert!(container
+            .check_instance_storage(&String::from("unknown-instance"), false)
+            .is_err());
+    }
 }
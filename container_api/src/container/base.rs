@@ -1,20 +1,23 @@
 use crate::{
     config::{
-        serialize_configuration, Configuration, InterfaceConfiguration, InterfaceDriver,
-        StorageConfiguration,
+        serialize_configuration, AgentConfiguration, Configuration, InterfaceConfiguration,
+        InterfaceDriver, StorageConfiguration,
     },
     context_builder::ContextBuilder,
     error::HolochainInstanceError,
+    keystore::{self, KeystoreFile},
     logger::DebugLogger,
     Holochain,
 };
+use holochain_cas_implementations::encryption::StorageKey;
 use holochain_core::{
+    agent::keys::Keypair,
     logger::{ChannelLogger, Logger},
     signal::Signal,
 };
 use holochain_core_types::{
-    agent::{AgentId, KeyBuffer},
-    dna::Dna,
+    cas::content::Address,
+    dna::{capabilities::CapabilityCall, Dna},
     error::HolochainError,
     json::JsonString,
 };
@@ -73,6 +76,7 @@ pub fn mount_container_from_config(config: Configuration) {
 /// In order to not bind this code to the assumption that there is a filesystem
 /// and also enable easier testing, a DnaLoader ()which is a closure that returns a
 /// Dna object for a given path string) has to be injected on creation.
+/// The same goes for the agents' keys, which get provided by a KeyLoader.
 pub struct Container {
     pub(in crate::container) instances: InstanceMap,
     pub(in crate::container) config: Configuration,
@@ -80,6 +84,10 @@ pub struct Container {
     static_servers: HashMap<String, StaticServer>,
     pub(in crate::container) interface_threads: HashMap<String, Sender<()>>,
     pub(in crate::container) dna_loader: DnaLoader,
+    pub(in crate::container) key_loader: KeyLoader,
+    /// Whether the agents' keys come from their keystore files, which then also
+    /// store the keys the agents rotate to
+    pub(in crate::container) keys_from_keystores: bool,
     signal_tx: Option<SignalSender>,
     logger: DebugLogger,
     p2p_config: Option<JsonString>,
@@ -96,6 +104,8 @@ impl Drop for Container {
 
 type SignalSender = SyncSender<Signal>;
 pub type DnaLoader = Arc<Box<FnMut(&String) -> Result<Dna, HolochainError> + Send + Sync>>;
+pub type KeyLoader =
+    Arc<Box<FnMut(&AgentConfiguration) -> Result<Keypair, HolochainError> + Send + Sync>>;
 
 // preparing for having container notifiers go to one of the log streams
 pub fn notify(msg: String) {
@@ -103,7 +113,8 @@ pub fn notify(msg: String) {
 }
 
 impl Container {
-    /// Creates a new instance with the default DnaLoader that actually loads files.
+    /// Creates a new instance with the default DnaLoader that actually loads files
+    /// and the default KeyLoader that unlocks the agents' keystore files.
     pub fn from_config(config: Configuration) -> Self {
         let rules = config.logger.rules.clone();
         let config_path = dirs::home_dir()
@@ -117,6 +128,8 @@ impl Container {
             config,
             config_path,
             dna_loader: Arc::new(Box::new(Self::load_dna)),
+            key_loader: Arc::new(Box::new(keystore::load_key)),
+            keys_from_keystores: true,
             signal_tx: None,
             logger: DebugLogger::new(rules),
             p2p_config: None,
@@ -128,6 +141,14 @@ impl Container {
         self.config_path = path;
     }
 
+    /// Replaces the KeyLoader, e.g. with `keystore::test_key_loader()` for setups
+    /// that don't have keystore files. Keys agents rotate to are then only kept in memory.
+    pub fn with_key_loader(mut self, key_loader: KeyLoader) -> Self {
+        self.key_loader = key_loader;
+        self.keys_from_keystores = false;
+        self
+    }
+
     pub fn with_signal_channel(mut self, signal_tx: SyncSender<Signal>) -> Self {
         if !self.instances.is_empty() {
             panic!("Cannot set a signal channel after having run load_config()");
@@ -287,6 +308,15 @@ impl Container {
 
     fn initialize_p2p_config(&mut self) -> JsonString {
         match self.config.network.clone() {
+            // if a TCP address is configured, instances connect to other containers directly
+            Some(ref net_config) if net_config.tcp_bind_address.is_some() => JsonString::from(
+                P2pConfig::new_with_tcp_backend(
+                    net_config.tcp_bind_address.as_ref().unwrap(), // unwrap safe because of guard
+                    &net_config.bootstrap_nodes,
+                )
+                .with_redundancy(net_config.redundancy)
+                .as_str(),
+            ),
             // if there is a config then either we need to spawn a process and get the
             // ipc_uri for it and save it for future calls to `load_config`
             // or we use that uri value that was created from previous calls!
@@ -305,6 +335,7 @@ impl Container {
                     "backend_config": {
                         "socketType": "zmq",
                         "bootstrapNodes": net_config.bootstrap_nodes,
+                        "redundancy": net_config.redundancy,
                             "ipcUri": uri
                     }
                 }
@@ -401,9 +432,41 @@ impl Container {
 
                 // Agent:
                 let agent_config = config.agent_by_id(&instance_config.agent).unwrap();
-                let pub_key = KeyBuffer::with_corrected(&agent_config.public_address)?;
-                context_builder =
-                    context_builder.with_agent(AgentId::new(&agent_config.name, &pub_key));
+                let mut keys = Arc::get_mut(&mut self.key_loader).unwrap()(&agent_config).map_err(
+                    |hc_err| {
+                        format!(
+                            "Could not load keys of agent \"{}\": {}",
+                            agent_config.id, hc_err
+                        )
+                    },
+                )?;
+                let agent_address = keys.address();
+                let encrypted_storage = match instance_config.storage {
+                    StorageConfiguration::Memory => false,
+                    StorageConfiguration::File { encrypted, .. }
+                    | StorageConfiguration::Kv { encrypted, .. } => encrypted,
+                };
+                let storage_key = if encrypted_storage {
+                    let key = keys
+                        .storage_key()
+                        .and_then(|mut key| StorageKey::new(&mut key))
+                        .map_err(|hc_err| {
+                            format!(
+                                "Could not derive storage key of agent \"{}\": {}",
+                                agent_config.id, hc_err
+                            )
+                        })?;
+                    Some(key)
+                } else {
+                    None
+                };
+                context_builder = context_builder
+                    .with_agent(keys.agent_id(&agent_config.name))
+                    .with_agent_keys(keys);
+                if self.keys_from_keystores {
+                    context_builder = context_builder
+                        .with_rotated_key_store(Arc::new(KeystoreFile::new(&agent_config.key_file)));
+                }
 
                 context_builder = context_builder.with_network_config(self.instance_p2p_config()?);
 
@@ -413,11 +476,22 @@ impl Container {
                 };
 
                 // Storage:
-                if let StorageConfiguration::File { path } = instance_config.storage {
-                    context_builder = context_builder.with_file_storage(path).map_err(|hc_err| {
-                        format!("Error creating context: {}", hc_err.to_string())
-                    })?
+                context_builder = match instance_config.storage {
+                    StorageConfiguration::Memory => context_builder,
+                    StorageConfiguration::File { path, .. } => context_builder
+                        .with_file_storage(path)
+                        .map_err(|hc_err| {
+                            format!("Error creating context: {}", hc_err.to_string())
+                        })?,
+                    StorageConfiguration::Kv { path, .. } => {
+                        context_builder.with_kv_storage(path).map_err(|hc_err| {
+                            format!("Error creating context: {}", hc_err.to_string())
+                        })?
+                    }
                 };
+                if let Some(storage_key) = storage_key {
+                    context_builder = context_builder.with_storage_encryption(storage_key);
+                }
 
                 if config.logger.logger_type == "debug" {
                     context_builder = context_builder.with_logger(Arc::new(Mutex::new(
@@ -441,8 +515,16 @@ impl Container {
                             the bridge API"#,
                     );
 
-                    api_builder = api_builder
-                        .with_named_instance(bridge.handle.clone(), callee_instance.clone());
+                    // Bridge calls are made by the caller's agent with the configured token.
+                    // Without one, only public functions can be called.
+                    let cap = bridge.cap_token.clone().map(|cap_token| {
+                        CapabilityCall::new(Address::from(cap_token), Some(agent_address.clone()))
+                    });
+                    api_builder = api_builder.with_named_instance_and_cap(
+                        bridge.handle.clone(),
+                        callee_instance.clone(),
+                        cap,
+                    );
                     api_builder = api_builder
                         .with_named_instance_config(bridge.handle.clone(), callee_config);
                 }
@@ -451,19 +533,38 @@ impl Container {
                     context_builder = context_builder.with_signals(signal_tx);
                 }
 
+                // DHT quota:
+                if let Some(quota) = instance_config.dht_quota {
+                    context_builder = context_builder.with_dht_quota(quota);
+                }
+
+                // Migration:
+                if let Some(ref old_id) = instance_config.migrated_from {
+                    let old_instance = self.instances.get(old_id).ok_or_else(|| {
+                        format!(
+                            "Instance \"{}\" has to be created before instance \"{}\" that continues its chain",
+                            old_id, id
+                        )
+                    })?;
+                    context_builder = context_builder
+                        .with_migrated_chain(old_instance.read().unwrap().context().clone());
+                }
+
                 // Spawn context
-                let context = context_builder.spawn();
+                let context = context_builder.spawn()?;
 
                 // Get DNA
                 let dna_config = config.dna_by_id(&instance_config.dna).unwrap();
-                let dna = Arc::get_mut(&mut self.dna_loader).unwrap()(&dna_config.file).map_err(
-                    |_| {
+                let mut dna = Arc::get_mut(&mut self.dna_loader).unwrap()(&dna_config.file)
+                    .map_err(|_| {
                         HolochainError::ConfigError(format!(
                             "Could not load DNA file \"{}\"",
                             dna_config.file
                         ))
-                    },
-                )?;
+                    })?;
+                if let Some(ref properties) = instance_config.properties {
+                    dna.override_properties(properties);
+                }
 
                 Holochain::new(dna, Arc::new(context)).map_err(|hc_err| hc_err.to_string())
             })
@@ -489,22 +590,24 @@ impl Container {
     }
 
     fn make_interface_handler(&self, interface_config: &InterfaceConfiguration) -> IoHandler {
-        let instance_ids: Vec<String> = interface_config
-            .instances
-            .iter()
-            .map(|i| i.id.clone())
-            .collect();
-
-        let instance_subset: InstanceMap = self
-            .instances
-            .iter()
-            .filter(|(id, _)| instance_ids.contains(&id))
-            .map(|(id, val)| (id.clone(), val.clone()))
-            .collect();
+        let mut container_api_builder = ContainerApiBuilder::new();
+        for instance_ref in interface_config.instances.iter() {
+            if let Some(instance) = self.instances.get(&instance_ref.id) {
+                // Interface calls come from unknown callers, so they carry no caller address
+                let cap = instance_ref
+                    .cap_token
+                    .clone()
+                    .map(|token| CapabilityCall::new(Address::from(token), None));
+                container_api_builder = container_api_builder.with_named_instance_and_cap(
+                    instance_ref.id.clone(),
+                    instance.clone(),
+                    cap,
+                );
+            }
+        }
 
-        let mut container_api_builder = ContainerApiBuilder::new()
-            .with_instances(instance_subset)
-            .with_instance_configs(self.config.instances.clone());
+        container_api_builder =
+            container_api_builder.with_instance_configs(self.config.instances.clone());
 
         if interface_config.admin {
             container_api_builder = container_api_builder.with_admin_dna_functions();
@@ -572,7 +675,7 @@ impl Logger for NullLogger {
 #[cfg(test)]
 pub mod tests {
     use super::*;
-    use crate::config::load_configuration;
+    use crate::{config::load_configuration, keystore::test_key_loader};
     use holochain_core::{action::Action, signal::signal_channel};
     use holochain_core_types::{cas::content::Address, dna, json::RawString};
     use holochain_wasm_utils::wasm_target_dir;
@@ -693,6 +796,7 @@ pub mod tests {
         let config = load_configuration::<Configuration>(&test_toml()).unwrap();
         let mut container = Container::from_config(config.clone());
         container.dna_loader = test_dna_loader();
+        container.key_loader = test_key_loader();
         container.load_config().unwrap();
         container
     }
@@ -701,6 +805,7 @@ pub mod tests {
         let config = load_configuration::<Configuration>(&test_toml()).unwrap();
         let mut container = Container::from_config(config.clone()).with_signal_channel(signal_tx);
         container.dna_loader = test_dna_loader();
+        container.key_loader = test_key_loader();
         container.load_config().unwrap();
         container
     }
@@ -781,6 +886,45 @@ pub mod tests {
         container.stop_all_instances().unwrap();
     }
 
+    #[test]
+    fn test_instance_properties_override_dna_properties() {
+        let mut container = test_container();
+        let mut config = load_configuration::<Configuration>(&test_toml()).unwrap();
+        config.instances[1].properties = Some(json!({"language": "de"}));
+
+        let instance = container
+            .instantiate_from_config(&String::from("test-instance-2"), &config, None)
+            .expect("Could not create instance");
+        let dna = instance.state().unwrap().nucleus().dna().unwrap();
+        assert_eq!(dna.get_property(Some("language")), Some(&json!("de")));
+    }
+
+    #[test]
+    fn test_container_tcp_network_config() {
+        let toml = format!(
+            r#"{}
+    [network]
+    bootstrap_nodes = ["/ip4/127.0.0.1/tcp/4001"]
+    tcp_bind_address = "0.0.0.0:4000"
+    redundancy = 3
+    "#,
+            test_toml()
+        );
+        let config = load_configuration::<Configuration>(&toml).unwrap();
+        let mut container = Container::from_config(config);
+        assert_eq!(
+            container.initialize_p2p_config(),
+            JsonString::from(
+                P2pConfig::new_with_tcp_backend(
+                    "0.0.0.0:4000",
+                    &[String::from("/ip4/127.0.0.1/tcp/4001")]
+                )
+                .with_redundancy(3)
+                .as_str()
+            )
+        );
+    }
+
     //#[test]
     // Default config path ~/.holochain/container-config.toml won't work in CI
     fn _test_container_save_and_load_config_default_location() {
@@ -982,6 +1126,7 @@ pub mod tests {
         let config = load_configuration::<Configuration>(&test_toml()).unwrap();
         let mut container = Container::from_config(config.clone());
         container.dna_loader = test_dna_loader();
+        container.key_loader = test_key_loader();
         container.load_config().expect("Test config must be sane");
         container
             .start_all_instances()
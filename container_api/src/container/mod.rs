@@ -3,7 +3,7 @@ pub mod base;
 
 pub use self::{
     admin::ContainerAdmin,
-    base::{mount_container_from_config, Container, CONTAINER},
+    base::{mount_container_from_config, Container, DnaLoader, KeyLoader, CONTAINER},
 };
 
 #[cfg(test)]
@@ -1,10 +1,18 @@
 use holochain_cas_implementations::{
-    cas::{file::FilesystemStorage, memory::MemoryStorage},
-    eav::{file::EavFileStorage, memory::EavMemoryStorage},
+    cas::{
+        encrypted::EncryptedStorage, file::FilesystemStorage, kv::KvStorage, memory::MemoryStorage,
+    },
+    eav::{
+        encrypted::EncryptedEavStorage, file::EavFileStorage, kv::EavKvStorage,
+        memory::EavMemoryStorage,
+    },
+    encryption::StorageKey,
+    kv::KvStore,
     path::create_path_if_not_exists,
 };
 
 use holochain_core::{
+    agent::keys::{Keypair, RotatedKeyStore},
     context::Context,
     logger::{Logger, SimpleLogger},
     persister::SimplePersister,
@@ -28,29 +36,42 @@ use std::sync::{Arc, Mutex, RwLock};
 /// `spawn()` to retrieve the context.
 pub struct ContextBuilder {
     agent_id: Option<AgentId>,
+    agent_keys: Option<Keypair>,
     logger: Option<Arc<Mutex<Logger>>>,
     // Persister is currently set to a reasonable default in spawn().
     // TODO: add with_persister() function to ContextBuilder.
     //persister: Option<Arc<Mutex<Persister>>>,
+    /// Where the persister keeps snapshots and journal, apart from the content
+    state_storage: Option<Arc<RwLock<ContentAddressableStorage>>>,
     chain_storage: Option<Arc<RwLock<ContentAddressableStorage>>>,
     dht_storage: Option<Arc<RwLock<ContentAddressableStorage>>>,
     eav_storage: Option<Arc<RwLock<EntityAttributeValueStorage>>>,
+    storage_key: Option<StorageKey>,
     network_config: Option<JsonString>,
     container_api: Option<Arc<RwLock<IoHandler>>>,
     signal_tx: Option<SignalSender>,
+    migrated_from: Option<Arc<Context>>,
+    dht_quota: Option<u64>,
+    rotated_key_store: Option<Arc<RotatedKeyStore>>,
 }
 
 impl ContextBuilder {
     pub fn new() -> Self {
         ContextBuilder {
             agent_id: None,
+            agent_keys: None,
             logger: None,
+            state_storage: None,
             chain_storage: None,
             dht_storage: None,
             eav_storage: None,
+            storage_key: None,
             network_config: None,
             container_api: None,
             signal_tx: None,
+            migrated_from: None,
+            dht_quota: None,
+            rotated_key_store: None,
         }
     }
 
@@ -60,11 +81,20 @@ impl ContextBuilder {
         self
     }
 
+    /// Sets the private keys of the agent of the context that gets built.
+    /// They have to belong to the public address of the agent set with `with_agent()`.
+    /// Without keys the instance can't sign the headers of its source chain.
+    pub fn with_agent_keys(mut self, agent_keys: Keypair) -> Self {
+        self.agent_keys = Some(agent_keys);
+        self
+    }
+
     /// Sets all three storages, chain, DHT and EAV storage, to transient memory implementations.
     /// Chain and DHT storages get set to the same memory CAS.
     pub fn with_memory_storage(mut self) -> Self {
         let cas = Arc::new(RwLock::new(MemoryStorage::new()));
         let eav = Arc::new(RwLock::new(EavMemoryStorage::new()));
+        self.state_storage = Some(Arc::new(RwLock::new(MemoryStorage::new())));
         self.chain_storage = Some(cas.clone());
         self.dht_storage = Some(cas);
         self.eav_storage = Some(eav);
@@ -78,8 +108,12 @@ impl ContextBuilder {
         let path: String = path.into();
         let cas_path = format!("{}/cas", path);
         let eav_path = format!("{}/eav", path);
+        let state_path = format!("{}/state", path);
         create_path_if_not_exists(&cas_path)?;
         create_path_if_not_exists(&eav_path)?;
+        create_path_if_not_exists(&state_path)?;
+
+        self.state_storage = Some(Arc::new(RwLock::new(FilesystemStorage::new(&state_path)?)));
 
         let file_storage = Arc::new(RwLock::new(FilesystemStorage::new(&cas_path)?));
         let eav_storage = Arc::new(RwLock::new(EavFileStorage::new(eav_path)?));
@@ -89,12 +123,32 @@ impl ContextBuilder {
         Ok(self)
     }
 
+    /// Sets all three storages, chain, DHT and EAV storage, to one single-file key-value
+    /// store that writes every change transactionally.
+    /// Returns an error if the store could not be opened in the given file.
+    pub fn with_kv_storage<T: Into<String>>(mut self, path: T) -> Result<Self, HolochainError> {
+        let store = Arc::new(RwLock::new(KvStore::open(path.into())?));
+        let kv_storage = Arc::new(RwLock::new(KvStorage::new(store.clone())));
+        self.state_storage = Some(Arc::new(RwLock::new(KvStorage::for_state(store.clone()))));
+        self.chain_storage = Some(kv_storage.clone());
+        self.dht_storage = Some(kv_storage);
+        self.eav_storage = Some(Arc::new(RwLock::new(EavKvStorage::new(store))));
+        Ok(self)
+    }
+
     /// Sets the network config.
     pub fn with_network_config(mut self, network_config: JsonString) -> Self {
         self.network_config = Some(network_config);
         self
     }
 
+    /// Encrypts everything that gets written to the storages of the context with the given key.
+    /// The storages themselves are set with the other `with_*_storage` functions.
+    pub fn with_storage_encryption(mut self, key: StorageKey) -> Self {
+        self.storage_key = Some(key);
+        self
+    }
+
     pub fn with_container_api(mut self, api_handler: IoHandler) -> Self {
         self.container_api = Some(Arc::new(RwLock::new(api_handler)));
         self
@@ -110,11 +164,36 @@ impl ContextBuilder {
         self
     }
 
+    /// Sets the context of the instance whose source chain got migrated to the one of the
+    /// context that gets built. The new instance commits the migration right after its
+    /// agent entry and can query the old chain.
+    pub fn with_migrated_chain(mut self, old_context: Arc<Context>) -> Self {
+        self.migrated_from = Some(old_context);
+        self
+    }
+
+    /// Limits the number of bytes the entries the instance holds in its DHT shard for others
+    /// may take up. Its own source chain does not count.
+    pub fn with_dht_quota(mut self, quota: u64) -> Self {
+        self.dht_quota = Some(quota);
+        self
+    }
+
+    /// Sets where the keys the agent rotates to get stored, e.g. its keystore.
+    pub fn with_rotated_key_store(mut self, store: Arc<RotatedKeyStore>) -> Self {
+        self.rotated_key_store = Some(store);
+        self
+    }
+
     /// Actually creates the context.
     /// Defaults to memory storages, an in-memory network config and a fake agent called "alice".
     /// The logger gets set to SimpleLogger.
-    /// The persister gets set to SimplePersister based on the chain storage.
-    pub fn spawn(self) -> Context {
+    /// The persister gets set to SimplePersister based on a storage next to the chain storage.
+    /// Fails if the persister can't read the journal from that storage.
+    pub fn spawn(self) -> Result<Context, HolochainError> {
+        let state_storage = self
+            .state_storage
+            .unwrap_or(Arc::new(RwLock::new(MemoryStorage::new())));
         let chain_storage = self
             .chain_storage
             .unwrap_or(Arc::new(RwLock::new(MemoryStorage::new())));
@@ -124,10 +203,16 @@ impl ContextBuilder {
         let eav_storage = self
             .eav_storage
             .unwrap_or(Arc::new(RwLock::new(EavMemoryStorage::new())));
-        Context::new(
+        let (state_storage, chain_storage, dht_storage, eav_storage) = match self.storage_key {
+            Some(key) => {
+                encrypt_storages(state_storage, chain_storage, dht_storage, eav_storage, key)
+            }
+            None => (state_storage, chain_storage, dht_storage, eav_storage),
+        };
+        let mut context = Context::new(
             self.agent_id.unwrap_or(AgentId::generate_fake("alice")),
             self.logger.unwrap_or(Arc::new(Mutex::new(SimpleLogger {}))),
-            Arc::new(Mutex::new(SimplePersister::new(chain_storage.clone()))),
+            Arc::new(Mutex::new(SimplePersister::new(state_storage)?)),
             chain_storage,
             dht_storage,
             eav_storage,
@@ -136,18 +221,61 @@ impl ContextBuilder {
             )),
             self.container_api,
             self.signal_tx,
-        )
+        );
+        if let Some(agent_keys) = self.agent_keys {
+            context.set_agent_keys(agent_keys);
+        }
+        if let Some(old_context) = self.migrated_from {
+            context.set_migrated_from(old_context);
+        }
+        if let Some(store) = self.rotated_key_store {
+            context.set_rotated_key_store(store);
+        }
+        context.dht_quota = self.dht_quota;
+        Ok(context)
     }
 }
 
+/// Wraps the storages in their encrypted counterparts.
+/// Chain and DHT storage stay the same storage if they were before.
+fn encrypt_storages(
+    state_storage: Arc<RwLock<ContentAddressableStorage>>,
+    chain_storage: Arc<RwLock<ContentAddressableStorage>>,
+    dht_storage: Arc<RwLock<ContentAddressableStorage>>,
+    eav_storage: Arc<RwLock<EntityAttributeValueStorage>>,
+    key: StorageKey,
+) -> (
+    Arc<RwLock<ContentAddressableStorage>>,
+    Arc<RwLock<ContentAddressableStorage>>,
+    Arc<RwLock<ContentAddressableStorage>>,
+    Arc<RwLock<EntityAttributeValueStorage>>,
+) {
+    let state_storage: Arc<RwLock<ContentAddressableStorage>> = Arc::new(RwLock::new(
+        EncryptedStorage::new(state_storage, key.clone()),
+    ));
+    let shared = Arc::ptr_eq(&chain_storage, &dht_storage);
+    let chain_storage: Arc<RwLock<ContentAddressableStorage>> = Arc::new(RwLock::new(
+        EncryptedStorage::new(chain_storage, key.clone()),
+    ));
+    let dht_storage: Arc<RwLock<ContentAddressableStorage>> = if shared {
+        chain_storage.clone()
+    } else {
+        Arc::new(RwLock::new(EncryptedStorage::new(dht_storage, key.clone())))
+    };
+    let eav_storage = Arc::new(RwLock::new(EncryptedEavStorage::new(eav_storage, key)));
+    (state_storage, chain_storage, dht_storage, eav_storage)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use holochain_core::agent::keys::test_keypair;
+    use holochain_core_types::{cas::content::AddressableContent, entry::Entry, json::RawString};
     use tempfile::tempdir;
 
     #[test]
     fn vanilla() {
-        let context = ContextBuilder::new().spawn();
+        let context = ContextBuilder::new().spawn().unwrap();
         assert_eq!(context.agent_id, AgentId::generate_fake("alice"));
         assert!(context
             .network_config
@@ -158,8 +286,25 @@ mod tests {
     #[test]
     fn with_agent() {
         let agent = AgentId::generate_fake("alice");
-        let context = ContextBuilder::new().with_agent(agent.clone()).spawn();
+        let context = ContextBuilder::new()
+            .with_agent(agent.clone())
+            .spawn()
+            .unwrap();
+        assert_eq!(context.agent_id, agent);
+    }
+
+    #[test]
+    fn with_agent_keys() {
+        let keys = test_keypair("alice");
+        let agent = keys.agent_id("alice");
+        let context = ContextBuilder::new()
+            .with_agent(agent.clone())
+            .with_agent_keys(keys)
+            .spawn()
+            .unwrap();
         assert_eq!(context.agent_id, agent);
+        assert!(context.sign("data").is_ok());
+        assert!(ContextBuilder::new().spawn().unwrap().sign("data").is_err());
     }
 
     #[test]
@@ -167,18 +312,79 @@ mod tests {
         let net = JsonString::from(P2pConfig::new_with_unique_memory_backend().as_str());
         let context = ContextBuilder::new()
             .with_network_config(net.clone())
-            .spawn();
+            .spawn()
+            .unwrap();
         assert_eq!(context.network_config, net);
     }
 
+    #[test]
+    fn with_migrated_chain() {
+        let old_context = Arc::new(ContextBuilder::new().spawn().unwrap());
+        let context = ContextBuilder::new()
+            .with_migrated_chain(old_context.clone())
+            .spawn()
+            .unwrap();
+        assert!(Arc::ptr_eq(&context.migrated_from.unwrap(), &old_context));
+        assert!(ContextBuilder::new()
+            .spawn()
+            .unwrap()
+            .migrated_from
+            .is_none());
+    }
+
+    #[test]
+    fn with_dht_quota() {
+        let context = ContextBuilder::new().with_dht_quota(1024).spawn().unwrap();
+        assert_eq!(context.dht_quota, Some(1024));
+        assert_eq!(ContextBuilder::new().spawn().unwrap().dht_quota, None);
+    }
+
+    #[test]
+    fn with_storage_encryption() {
+        let temp = tempdir().expect("test was supposed to create temp dir");
+        let temp_path = String::from(temp.path().to_str().expect("temp dir could not be string"));
+        let mut keys = test_keypair("alice");
+        let key = StorageKey::new(&mut keys.storage_key().unwrap()).unwrap();
+        let context = ContextBuilder::new()
+            .with_file_storage(temp_path.clone())
+            .unwrap()
+            .with_storage_encryption(key)
+            .spawn()
+            .unwrap();
+        assert!(Arc::ptr_eq(&context.chain_storage, &context.dht_storage));
+
+        let entry = Entry::App("testEntryType".into(), RawString::from("secret").into());
+        context.chain_storage.write().unwrap().add(&entry).unwrap();
+        assert_eq!(
+            context
+                .chain_storage
+                .read()
+                .unwrap()
+                .fetch(&entry.address()),
+            Ok(Some(entry.content()))
+        );
+
+        let plain_storage = FilesystemStorage::new(&format!("{}/cas", temp_path)).unwrap();
+        let stored = plain_storage.fetch(&entry.address()).unwrap().unwrap();
+        assert!(!String::from(stored).contains("secret"));
+    }
+
     #[test]
     fn smoke_tests() {
-        let _ = ContextBuilder::new().with_memory_storage().spawn();
+        let _ = ContextBuilder::new().with_memory_storage().spawn().unwrap();
         let temp = tempdir().expect("test was supposed to create temp dir");
         let temp_path = String::from(temp.path().to_str().expect("temp dir could not be string"));
         let _ = ContextBuilder::new()
             .with_file_storage(temp_path)
             .expect("Filestorage should get instantiated with tempdir")
-            .spawn();
+            .spawn()
+            .unwrap();
+        // persisted state is kept apart from the content
+        assert!(temp.path().join("state").is_dir());
+        let _ = ContextBuilder::new()
+            .with_kv_storage(temp.path().join("storage.db").to_string_lossy())
+            .expect("KV storage should get instantiated with tempdir")
+            .spawn()
+            .unwrap();
     }
 }
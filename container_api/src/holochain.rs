@@ -13,9 +13,9 @@
 //! extern crate holochain_cas_implementations;
 //! extern crate tempfile;
 //! use holochain_container_api::{*, context_builder::ContextBuilder};
+//! use holochain_core::agent::keys::Keypair;
 //! use holochain_core_types::{
 //!     cas::content::Address,
-//!     agent::AgentId,
 //!     dna::{Dna, capabilities::CapabilityCall},
 //!     json::JsonString};
 //! use std::sync::Arc;
@@ -30,12 +30,14 @@
 //! let dna = Dna::new();
 //! let dir = tempdir().unwrap();
 //! let storage_directory_path = dir.path().to_str().unwrap();
-//! let agent = AgentId::generate_fake("bob");
+//! let keys = Keypair::new_random().unwrap();
 //! let context = ContextBuilder::new()
-//!     .with_agent(agent)
+//!     .with_agent(keys.agent_id("bob"))
+//!     .with_agent_keys(keys)
 //!     .with_file_storage(storage_directory_path)
 //!     .expect("Tempdir should be accessible")
-//!     .spawn();
+//!     .spawn()
+//!     .unwrap();
 //! let mut hc = Holochain::new(dna,Arc::new(context)).unwrap();
 //!
 //! // start up the holochain instance
@@ -63,7 +65,6 @@ use holochain_core::{
     context::Context,
     instance::Instance,
     nucleus::{call_and_wait_for_result, ZomeFnCall},
-    persister::{Persister, SimplePersister},
     state::State,
     workflows::application,
 };
@@ -115,9 +116,12 @@ impl Holochain {
         }
     }
 
+    /// Restores an instance from the snapshot and journal its context's persister kept.
+    /// Agent, nucleus and DHT state get restored, the network gets initialized anew.
     pub fn load(_path: String, context: Arc<Context>) -> Result<Self, HolochainError> {
-        let persister = SimplePersister::new(context.dht_storage.clone());
-        let loaded_state = persister
+        let loaded_state = context
+            .persister
+            .lock()?
             .load(context.clone())?
             .unwrap_or(State::new(context.clone()));
         let mut instance = Instance::from_state(loaded_state.clone());
@@ -186,12 +190,13 @@ mod tests {
     use context_builder::ContextBuilder;
     use holochain_core::{
         action::Action,
+        agent::keys::test_keypair,
         context::Context,
         logger::{test_logger, TestLogger},
         nucleus::ribosome::{callback::Callback, Defn},
         signal::{signal_channel, SignalReceiver},
     };
-    use holochain_core_types::{agent::AgentId, cas::content::Address, dna::Dna};
+    use holochain_core_types::{cas::content::Address, dna::Dna};
     use holochain_wasm_utils::wasm_target_dir;
     use std::sync::{Arc, Mutex};
     use tempfile::tempdir;
@@ -201,18 +206,21 @@ mod tests {
     };
 
     fn test_context(agent_name: &str) -> (Arc<Context>, Arc<Mutex<TestLogger>>, SignalReceiver) {
-        let agent = AgentId::generate_fake(agent_name);
+        let keys = test_keypair(agent_name);
+        let agent = keys.agent_id(agent_name);
         let (signal_tx, signal_rx) = signal_channel();
         let logger = test_logger();
         (
             Arc::new(
                 ContextBuilder::new()
                     .with_agent(agent)
+                    .with_agent_keys(keys)
                     .with_logger(logger.clone())
                     .with_signals(signal_tx)
                     .with_file_storage(tempdir().unwrap().path().to_str().unwrap())
                     .unwrap()
-                    .spawn(),
+                    .spawn()
+                    .unwrap(),
             ),
             logger,
             signal_rx,
@@ -278,6 +286,37 @@ mod tests {
         assert!(loaded_holo.instance.state().nucleus().has_initialized());
     }
 
+    #[test]
+    fn can_load_after_restart() {
+        let dir = tempdir().unwrap();
+        let storage_path = dir.path().to_str().unwrap();
+        let context = || {
+            Arc::new(
+                ContextBuilder::new()
+                    .with_agent(test_keypair("bob").agent_id("bob"))
+                    .with_agent_keys(test_keypair("bob"))
+                    .with_file_storage(storage_path)
+                    .unwrap()
+                    .spawn()
+                    .unwrap(),
+            )
+        };
+        let mut dna = Dna::new();
+        dna.name = "TestApp".to_string();
+        let hc = Holochain::new(dna.clone(), context()).unwrap();
+        let state = hc.state().unwrap();
+
+        // a fresh context on the same storage, like after restarting the container
+        let loaded_holo = Holochain::load(storage_path.to_string(), context()).unwrap();
+        let loaded_state = loaded_holo.state().unwrap();
+        assert_eq!(
+            loaded_state.agent().top_chain_header(),
+            state.agent().top_chain_header()
+        );
+        assert_eq!(loaded_state.nucleus().dna(), Some(dna));
+        assert!(loaded_state.nucleus().has_initialized());
+    }
+
     #[test]
     fn fails_instantiate_if_genesis_fails() {
         let dna = create_test_dna_with_wat(
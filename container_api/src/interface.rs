@@ -1,8 +1,8 @@
-use holochain_core::state::State;
-use holochain_core_types::{cas::content::Address, dna::capabilities::CapabilityCall};
+use holochain_core::{agent::chain_archive::ChainArchive, state::State};
+use holochain_core_types::{dna::capabilities::CapabilityCall, error::HolochainError};
 use Holochain;
 
-use jsonrpc_ws_server::jsonrpc_core::{self, types::params::Params, IoHandler, Value};
+use jsonrpc_ws_server::jsonrpc_core::{self, types::params::Params, ErrorCode, IoHandler, Value};
 use serde_json;
 use std::{
     collections::HashMap,
@@ -16,8 +16,13 @@ use config::{
     InterfaceDriver, StorageConfiguration,
 };
 use container::{ContainerAdmin, CONTAINER};
+use error::HolochainInstanceError;
 use serde_json::map::Map;
 
+/// JSON-RPC error code of zome calls that got rejected because the capability token
+/// is missing or revoked, or does not grant access to the caller.
+pub const CAPABILITY_CHECK_FAILED_ERROR_CODE: i64 = -32001;
+
 pub type InterfaceError = String;
 pub type InstanceMap = HashMap<String, Arc<RwLock<Holochain>>>;
 
@@ -140,11 +145,24 @@ impl ContainerApiBuilder {
         self
     }
 
-    /// Add a single instance and register it under the given name
+    /// Add a single instance and register it under the given name.
+    /// Zome calls carry no capability, so only public functions can be called.
     pub fn with_named_instance(
+        self,
+        instance_name: String,
+        instance: Arc<RwLock<Holochain>>,
+    ) -> Self {
+        self.with_named_instance_and_cap(instance_name, instance, None)
+    }
+
+    /// Add a single instance and register it under the given name.
+    /// All zome calls carry the given capability, so only public functions and
+    /// the functions it grants access to can be called.
+    pub fn with_named_instance_and_cap(
         mut self,
         instance_name: String,
         instance: Arc<RwLock<Holochain>>,
+        cap: Option<CapabilityCall>,
     ) -> Self {
         let hc_lock = instance.clone();
         let hc = hc_lock.read().unwrap();
@@ -159,18 +177,14 @@ impl ContainerApiBuilder {
                         let zome_name = zome_name.clone();
                         let method_name = format!("{}/{}/{}", instance_name, zome_name, func_name);
                         let hc_lock_inner = hc_lock.clone();
+                        let cap = cap.clone();
                         self.io.add_method(&method_name, move |params| {
                             let mut hc = hc_lock_inner.write().unwrap();
                             let params_string = serde_json::to_string(&params)
                                 .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?;
                             let response = hc
-                                .call(
-                                    &zome_name,
-                                    Some(CapabilityCall::new(Address::from("fake_token"), None)),
-                                    &func_name,
-                                    &params_string,
-                                )
-                                .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?;
+                                .call(&zome_name, cap.clone(), &func_name, &params_string)
+                                .map_err(Self::zome_call_error)?;
                             Ok(Value::String(response.to_string()))
                         })
                     }
@@ -183,6 +197,21 @@ impl ContainerApiBuilder {
         self
     }
 
+    /// Failed capability checks get their own error code so clients can tell them
+    /// apart from malformed calls.
+    fn zome_call_error(error: HolochainInstanceError) -> jsonrpc_core::Error {
+        match error {
+            HolochainInstanceError::InternalFailure(HolochainError::CapabilityCheckFailed) => {
+                jsonrpc_core::Error {
+                    code: ErrorCode::ServerError(CAPABILITY_CHECK_FAILED_ERROR_CODE),
+                    message: error.to_string(),
+                    data: None,
+                }
+            }
+            _ => jsonrpc_core::Error::invalid_params(error.to_string()),
+        }
+    }
+
     fn unwrap_params_map(params: Params) -> Result<Map<String, Value>, jsonrpc_core::Error> {
         match params {
             Params::Map(map) => Ok(map),
@@ -297,6 +326,49 @@ impl ContainerApiBuilder {
     ///  * `admin/instance/running`
     ///     Returns an array of all instances that are running.
     ///
+    ///  * `admin/instance/migrate`
+    ///     Move the agent of an instance over to another DNA. This creates and starts a new
+    ///     instance whose source chain continues the closed chain of the given instance.
+    ///     Params:
+    ///     * `id`: Which instance to migrate?
+    ///     * `new_id`: ID for the new instance
+    ///     * `dna_id`: DNA of the new instance
+    ///
+    ///  * `admin/instance/export_chain`
+    ///     Returns an archive of all headers and entries of the source chain of a running
+    ///     instance, together with its DNA address and agent id.
+    ///     Params:
+    ///     * `id`: [string] Which instance's chain to export?
+    ///
+    ///  * `admin/instance/import_chain`
+    ///     Verifies the header links and signatures of a chain archive and appends its missing
+    ///     elements to the source chain of a running instance of the same DNA and agent.
+    ///     Params:
+    ///     * `id`: [string] Which instance to import into?
+    ///     * `archive`: [object] The archive as returned by `admin/instance/export_chain`
+    ///
+    ///  * `admin/instance/check_storage`
+    ///     Checks the persistent storage of a stopped instance: re-hashes all content, looks
+    ///     for EAVs about missing content and walks the source chain. Returns a report
+    ///     listing all problems found.
+    ///     Params:
+    ///     * `id`: [string] Which instance's storage to check?
+    ///     * `quarantine`: [bool] Move corrupt items out of file storages? (defaults to false)
+    ///
+    ///  * `admin/instance/peers`
+    ///     Returns the peers a running instance knows about: their agent id, their location
+    ///     on the address space, how far around it they hold entries and when they were
+    ///     last seen (in milliseconds since the Unix epoch).
+    ///     Params:
+    ///     * `id`: [string] Which instance's peers to list?
+    ///
+    ///  * `admin/instance/validation_receipts`
+    ///     Returns an object with the address of every published entry of a running
+    ///     instance as key and the number of holders that sent a receipt saying they
+    ///     validated it as value.
+    ///     Params:
+    ///     * `id`: [string] Which instance's entries to count receipts for?
+    ///
     ///  * `admin/interface/add`
     ///     Adds a new DNA / zome / container interface (that provides access to zome functions
     ///     of selected instances and container functions, depending on the interfaces config).
@@ -355,6 +427,8 @@ impl ContainerApiBuilder {
     ///     * `callee_id`: ID of the instance which's zome functions can be called
     ///     * `handle`: Name that the caller uses to reference this bridge and therefore the other
     ///             instance.
+    ///     * `cap_token`: [optional] Capability token granted by the callee's agent that is
+    ///             used for all calls through this bridge.
     ///
     ///  * `admin/bridge/remove`
     ///     Remove a bridge
@@ -403,6 +477,9 @@ impl ContainerApiBuilder {
                 dna: dna_id.to_string(),
                 agent: agent_id.to_string(),
                 storage: StorageConfiguration::Memory, // TODO: don't actually use this. Have some idea of default store
+                properties: None,
+                migrated_from: None,
+                dht_quota: None,
             };
             container_call!(|c| c.add_instance(new_instance))?;
             Ok(json!({"success": true}))
@@ -429,6 +506,81 @@ impl ContainerApiBuilder {
             Ok(json!({"success": true}))
         });
 
+        self.io.add_method("admin/instance/migrate", move |params| {
+            let params_map = Self::unwrap_params_map(params)?;
+            let id = Self::get_as_string("id", &params_map)?;
+            let new_id = Self::get_as_string("new_id", &params_map)?;
+            let dna_id = Self::get_as_string("dna_id", &params_map)?;
+            container_call!(|c| c.migrate_instance(&id, &new_id, &dna_id))?;
+            Ok(json!({"success": true}))
+        });
+
+        self.io
+            .add_method("admin/instance/export_chain", move |params| {
+                let params_map = Self::unwrap_params_map(params)?;
+                let id = Self::get_as_string("id", &params_map)?;
+                let archive = container_call!(|c| c.export_chain(&id))?;
+                serde_json::to_value(archive).map_err(|e| {
+                    let mut error = jsonrpc_core::Error::internal_error();
+                    error.message = e.to_string();
+                    error
+                })
+            });
+
+        self.io
+            .add_method("admin/instance/import_chain", move |params| {
+                let params_map = Self::unwrap_params_map(params)?;
+                let id = Self::get_as_string("id", &params_map)?;
+                let archive = params_map
+                    .get("archive")
+                    .cloned()
+                    .ok_or(jsonrpc_core::Error::invalid_params(
+                        "`archive` param not provided",
+                    ))
+                    .and_then(|value| {
+                        serde_json::from_value::<ChainArchive>(value)
+                            .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))
+                    })?;
+                container_call!(|c| c.import_chain(&id, archive))?;
+                Ok(json!({"success": true}))
+            });
+
+        self.io
+            .add_method("admin/instance/check_storage", move |params| {
+                let params_map = Self::unwrap_params_map(params)?;
+                let id = Self::get_as_string("id", &params_map)?;
+                let quarantine = Self::get_as_bool("quarantine", &params_map).unwrap_or(false);
+                let report = container_call!(|c| c.check_instance_storage(&id, quarantine))?;
+                serde_json::to_value(report).map_err(|e| {
+                    let mut error = jsonrpc_core::Error::internal_error();
+                    error.message = e.to_string();
+                    error
+                })
+            });
+
+        self.io.add_method("admin/instance/peers", move |params| {
+            let params_map = Self::unwrap_params_map(params)?;
+            let id = Self::get_as_string("id", &params_map)?;
+            let peers = container_call!(|c| c.instance_peers(&id))?;
+            serde_json::to_value(peers).map_err(|e| {
+                let mut error = jsonrpc_core::Error::internal_error();
+                error.message = e.to_string();
+                error
+            })
+        });
+
+        self.io
+            .add_method("admin/instance/validation_receipts", move |params| {
+                let params_map = Self::unwrap_params_map(params)?;
+                let id = Self::get_as_string("id", &params_map)?;
+                let counts = container_call!(|c| c.validation_counts(&id))?;
+                serde_json::to_value(counts).map_err(|e| {
+                    let mut error = jsonrpc_core::Error::internal_error();
+                    error.message = e.to_string();
+                    error
+                })
+            });
+
         self.io.add_method("admin/instance/list", move |_params| {
             let instances = container_call!(
                 |c| Ok(c.config().instances) as Result<Vec<InstanceConfiguration>, String>
@@ -575,11 +727,13 @@ impl ContainerApiBuilder {
             let caller_id = Self::get_as_string("caller_id", &params_map)?;
             let callee_id = Self::get_as_string("callee_id", &params_map)?;
             let handle = Self::get_as_string("handle", &params_map)?;
+            let cap_token = Self::get_as_string("cap_token", &params_map).ok();
 
             let bridge = Bridge {
                 caller_id,
                 callee_id,
                 handle,
+                cap_token,
             };
             container_call!(|c| c.add_bridge(bridge))?;
             Ok(json!({"success": true}))
@@ -692,4 +846,20 @@ pub mod tests {
             r#"[{"id":"test-instance-1","dna":"bridge-callee","agent":"test-agent-1"}]"#
         );
     }
+
+    #[test]
+    fn test_capability_errors_have_own_code() {
+        let error = ContainerApiBuilder::zome_call_error(HolochainInstanceError::InternalFailure(
+            HolochainError::CapabilityCheckFailed,
+        ));
+        assert_eq!(
+            error.code,
+            ErrorCode::ServerError(CAPABILITY_CHECK_FAILED_ERROR_CODE)
+        );
+
+        let error = ContainerApiBuilder::zome_call_error(HolochainInstanceError::InternalFailure(
+            HolochainError::DnaMissing,
+        ));
+        assert_eq!(error.code, ErrorCode::InvalidParams);
+    }
 }
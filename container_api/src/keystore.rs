@@ -0,0 +1,495 @@
+//! Passphrase protected storage of an agent's keys.
+//!
+//! A keystore file holds the seed all of an agent's keys get derived from, encrypted
+//! with a symmetric key that is derived from a passphrase with argon2 (`pwhash`).
+//! The public address is stored in the clear so keystores can be listed and matched
+//! against the container configuration without having to unlock them.
+//! The keystore file referenced by `AgentConfiguration::key_file` gets unlocked by the
+//! container when an instance is created.
+//!
+//! When an agent rotates its keys, the seed of the new keys gets added to the keystore,
+//! encrypted with the agent's storage key, which only the initial seed unlocks.
+
+use crate::{config::AgentConfiguration, container::KeyLoader};
+use holochain_core::agent::keys::{Keypair, RotatedKeyStore, SEED_SIZE};
+use holochain_core_types::{
+    cas::content::Address,
+    error::{HcResult, HolochainError},
+};
+use holochain_sodium::{aead, error::SodiumError, pwhash, random::random_secbuf, secbuf::SecBuf};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::prelude::*,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Version of the keystore file format written by this code
+pub const KEYSTORE_VERSION: u8 = 1;
+
+/// Environment variable that, if set, provides the passphrase for unlocking keystores
+/// so the container can be started without a terminal.
+pub const PASSPHRASE_ENV_VAR: &str = "HC_PASSPHRASE";
+
+/// Serializable, encrypted representation of an agent's key seed.
+/// All binary fields are base64 encoded.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Keystore {
+    version: u8,
+    public_address: String,
+    salt: String,
+    nonce: String,
+    cipher: String,
+    #[serde(default)]
+    rotated_keys: Vec<RotatedKeys>,
+}
+
+/// Seed of keys the agent rotated to, encrypted with the agent's storage key
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+struct RotatedKeys {
+    public_address: String,
+    nonce: String,
+    cipher: String,
+}
+
+impl Keystore {
+    /// Creates a keystore for the keypair derived from `seed`, locked with `passphrase`.
+    pub fn new(seed: &mut SecBuf, passphrase: &str) -> HcResult<Keystore> {
+        let keypair = Keypair::new_from_seed(seed)?;
+        let mut salt = SecBuf::with_insecure(pwhash::SALTBYTES);
+        random_secbuf(&mut salt);
+        let mut nonce = SecBuf::with_insecure(aead::NONCEBYTES);
+        random_secbuf(&mut nonce);
+        let mut secret = passphrase_key(passphrase, &mut salt)?;
+        let mut cipher = SecBuf::with_insecure(SEED_SIZE + aead::ABYTES);
+        aead::enc(seed, &mut secret, None, &mut nonce, &mut cipher).map_err(sodium_error)?;
+
+        Ok(Keystore {
+            version: KEYSTORE_VERSION,
+            public_address: keypair.address().to_string(),
+            salt: encode(&mut salt),
+            nonce: encode(&mut nonce),
+            cipher: encode(&mut cipher),
+            rotated_keys: Vec::new(),
+        })
+    }
+
+    /// Creates a keystore for a freshly generated random seed.
+    pub fn new_random(passphrase: &str) -> HcResult<Keystore> {
+        let mut seed = SecBuf::with_secure(SEED_SIZE);
+        random_secbuf(&mut seed);
+        Keystore::new(&mut seed, passphrase)
+    }
+
+    /// Reads a keystore from the given file.
+    pub fn load<P: AsRef<Path>>(path: P) -> HcResult<Keystore> {
+        let mut file = File::open(path)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        let keystore: Keystore = serde_json::from_str(&contents)?;
+        if keystore.version != KEYSTORE_VERSION {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "Unsupported keystore version {}",
+                keystore.version
+            )));
+        }
+        Ok(keystore)
+    }
+
+    /// Writes this keystore to the given file, replacing its previous content.
+    /// Only the owner of the file may read it.
+    /// The keystore gets written to a temporary file next to it first, which then replaces
+    /// the file, so a failing write never leaves a keystore without the seed behind.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> HcResult<()> {
+        let path = path.as_ref();
+        let temp_path = temp_path(path)?;
+        let result = create_private_file(&temp_path)
+            .and_then(|mut file| {
+                file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+                file.sync_all()?;
+                Ok(())
+            })
+            .and_then(|_| Ok(fs::rename(&temp_path, path)?))
+            .and_then(|_| sync_dir(path));
+        if result.is_err() {
+            let _ = fs::remove_file(&temp_path);
+        }
+        result
+    }
+
+    /// The address of the agent whose keys are stored in here
+    pub fn public_address(&self) -> Address {
+        Address::from(self.public_address.clone())
+    }
+
+    /// Decrypts the stored seed and derives the agent's keys from it.
+    /// Fails if the passphrase is wrong.
+    pub fn unlock(&self, passphrase: &str) -> HcResult<Keypair> {
+        let mut seed = self.decrypt_seed(passphrase)?;
+        Keypair::new_from_seed(&mut seed)
+    }
+
+    /// Re-encrypts the stored seed with a new passphrase, using fresh salt and nonce.
+    pub fn change_passphrase(
+        &mut self,
+        old_passphrase: &str,
+        new_passphrase: &str,
+    ) -> HcResult<()> {
+        let mut seed = self.decrypt_seed(old_passphrase)?;
+        let rotated_keys = self.rotated_keys.clone();
+        *self = Keystore::new(&mut seed, new_passphrase)?;
+        self.rotated_keys = rotated_keys;
+        Ok(())
+    }
+
+    /// Adds the seed of keys the agent rotated to.
+    pub fn add_rotated_keys(&mut self, keys: &mut Keypair, seed: &mut SecBuf) -> HcResult<()> {
+        let mut storage_key = keys.storage_key()?;
+        let mut nonce = SecBuf::with_insecure(aead::NONCEBYTES);
+        random_secbuf(&mut nonce);
+        let mut cipher = SecBuf::with_insecure(SEED_SIZE + aead::ABYTES);
+        aead::enc(seed, &mut storage_key, None, &mut nonce, &mut cipher).map_err(sodium_error)?;
+        self.rotated_keys.push(RotatedKeys {
+            public_address: keys.address().to_string(),
+            nonce: encode(&mut nonce),
+            cipher: encode(&mut cipher),
+        });
+        Ok(())
+    }
+
+    /// Restores all keys the agent rotated to, in the order of the rotations.
+    /// `initial_keys` are the keys `unlock` returns.
+    pub fn unlock_rotated_keys(&self, initial_keys: &mut Keypair) -> HcResult<Vec<Keypair>> {
+        let mut storage_key = initial_keys.storage_key()?;
+        self.rotated_keys
+            .iter()
+            .map(|rotated| {
+                let mut nonce = decode(&rotated.nonce)?;
+                let mut cipher = decode(&rotated.cipher)?;
+                let mut seed = SecBuf::with_secure(SEED_SIZE);
+                if cipher.len() != SEED_SIZE + aead::ABYTES
+                    || aead::dec_verified(
+                        &mut seed,
+                        &mut storage_key,
+                        None,
+                        &mut nonce,
+                        &mut cipher,
+                    )
+                    .is_err()
+                {
+                    return Err(HolochainError::ErrorGeneric(format!(
+                        "Could not unlock rotated keys of {}",
+                        rotated.public_address
+                    )));
+                }
+                let keys = Keypair::new_from_rotated_seed(&mut seed, &mut storage_key)?;
+                if keys.address() != Address::from(rotated.public_address.clone()) {
+                    return Err(HolochainError::ErrorGeneric(format!(
+                        "Keystore does not hold the keys of {}",
+                        rotated.public_address
+                    )));
+                }
+                Ok(keys)
+            })
+            .collect()
+    }
+
+    fn decrypt_seed(&self, passphrase: &str) -> HcResult<SecBuf> {
+        let mut salt = decode(&self.salt)?;
+        let mut nonce = decode(&self.nonce)?;
+        let mut cipher = decode(&self.cipher)?;
+        if cipher.len() != SEED_SIZE + aead::ABYTES {
+            return Err(HolochainError::ErrorGeneric(
+                "Keystore cipher has wrong length".to_string(),
+            ));
+        }
+        let mut secret = passphrase_key(passphrase, &mut salt)?;
+        let mut seed = SecBuf::with_secure(SEED_SIZE);
+        if aead::dec_verified(&mut seed, &mut secret, None, &mut nonce, &mut cipher).is_err() {
+            return Err(HolochainError::ErrorGeneric(
+                "Could not unlock keystore: wrong passphrase or corrupted keystore".to_string(),
+            ));
+        }
+
+        // The public address is stored in the clear, so it could have been replaced.
+        if Keypair::new_from_seed(&mut seed)?.address() != self.public_address() {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "Keystore does not hold the keys of {}",
+                self.public_address
+            )));
+        }
+        Ok(seed)
+    }
+}
+
+/// Returns the passphrase set in `HC_PASSPHRASE` or asks for it on the terminal.
+pub fn get_passphrase(prompt: &str) -> HcResult<String> {
+    match std::env::var(PASSPHRASE_ENV_VAR) {
+        Ok(passphrase) => Ok(passphrase),
+        Err(_) => prompt_passphrase(prompt),
+    }
+}
+
+/// Asks for a passphrase on the terminal, regardless of `HC_PASSPHRASE`.
+/// New passphrases have to be read this way, the environment only ever holds the current one.
+pub fn prompt_passphrase(prompt: &str) -> HcResult<String> {
+    rpassword::prompt_password_stdout(prompt).map_err(HolochainError::from)
+}
+
+/// Default KeyLoader: unlocks the keystore file referenced by the agent configuration
+/// and makes sure it holds the keys of the configured public address.
+pub fn load_key(agent_config: &AgentConfiguration) -> Result<Keypair, HolochainError> {
+    let keystore = Keystore::load(&agent_config.key_file)?;
+    if keystore.public_address() != Address::from(agent_config.public_address.clone()) {
+        return Err(HolochainError::ConfigError(format!(
+            "Keystore \"{}\" does not belong to agent \"{}\"",
+            agent_config.key_file, agent_config.id
+        )));
+    }
+    let passphrase = get_passphrase(&format!(
+        "Passphrase for keystore of agent \"{}\": ",
+        agent_config.id
+    ))?;
+    keystore.unlock(&passphrase)
+}
+
+/// Stores the keys an agent rotates to in the agent's keystore file.
+pub struct KeystoreFile {
+    path: String,
+}
+
+impl KeystoreFile {
+    pub fn new(path: &str) -> KeystoreFile {
+        KeystoreFile {
+            path: path.to_string(),
+        }
+    }
+}
+
+impl RotatedKeyStore for KeystoreFile {
+    fn add(&self, keys: &mut Keypair, seed: &mut SecBuf) -> HcResult<()> {
+        let mut keystore = Keystore::load(&self.path)?;
+        keystore.add_rotated_keys(keys, seed)?;
+        keystore.save(&self.path)
+    }
+
+    fn load(&self, initial_keys: &mut Keypair) -> HcResult<Vec<Keypair>> {
+        Keystore::load(&self.path)?.unlock_rotated_keys(initial_keys)
+    }
+}
+
+/// KeyLoader that ignores the key file and hands out deterministic test keys for the
+/// agent's name. Only meant for tests and development setups like `hc run`,
+/// which is why it needs the `test-keys` feature.
+#[cfg(any(test, feature = "test-keys"))]
+pub fn test_key_loader() -> KeyLoader {
+    use holochain_core::agent::keys::test_keypair;
+    let loader = Box::new(|agent_config: &AgentConfiguration| Ok(test_keypair(&agent_config.name)))
+        as Box<FnMut(&AgentConfiguration) -> Result<Keypair, HolochainError> + Send + Sync>;
+    Arc::new(loader)
+}
+
+/// Where a new version of the file at `path` gets written before it replaces the file
+fn temp_path(path: &Path) -> HcResult<PathBuf> {
+    let file_name = path.file_name().ok_or_else(|| {
+        HolochainError::ErrorGeneric(format!("{} is not a file", path.to_string_lossy()))
+    })?;
+    Ok(path.with_file_name(format!(".{}.tmp", file_name.to_string_lossy())))
+}
+
+/// Makes sure a file that got renamed into the directory of `path` stays there after a crash
+#[cfg(unix)]
+fn sync_dir(path: &Path) -> HcResult<()> {
+    match path.parent() {
+        Some(dir) if dir != Path::new("") => File::open(dir)?.sync_all()?,
+        _ => File::open(".")?.sync_all()?,
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_path: &Path) -> HcResult<()> {
+    Ok(())
+}
+
+/// Creates or truncates the file at `path` with permissions that only allow its owner
+/// to read and write it.
+#[cfg(unix)]
+fn create_private_file(path: &Path) -> HcResult<File> {
+    use std::{
+        fs::Permissions,
+        os::unix::fs::{OpenOptionsExt, PermissionsExt},
+    };
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    // The mode only applies to new files
+    file.set_permissions(Permissions::from_mode(0o600))?;
+    Ok(file)
+}
+
+#[cfg(not(unix))]
+fn create_private_file(path: &Path) -> HcResult<File> {
+    Ok(File::create(path)?)
+}
+
+fn passphrase_key(passphrase: &str, salt: &mut SecBuf) -> HcResult<SecBuf> {
+    let mut password = secbuf_from_bytes(passphrase.as_bytes());
+    let mut key = SecBuf::with_secure(pwhash::HASHBYTES);
+    pwhash::hash(
+        &mut password,
+        pwhash::OPSLIMIT_INTERACTIVE,
+        pwhash::MEMLIMIT_INTERACTIVE,
+        pwhash::ALG_ARGON2ID13,
+        salt,
+        &mut key,
+    )
+    .map_err(sodium_error)?;
+    Ok(key)
+}
+
+fn secbuf_from_bytes(bytes: &[u8]) -> SecBuf {
+    let mut buf = SecBuf::with_insecure(bytes.len());
+    buf.write_lock().copy_from_slice(bytes);
+    buf
+}
+
+fn encode(buf: &mut SecBuf) -> String {
+    base64::encode(&buf.read_lock()[..])
+}
+
+fn decode(data: &str) -> HcResult<SecBuf> {
+    Ok(secbuf_from_bytes(&base64::decode(data)?))
+}
+
+fn sodium_error(error: SodiumError) -> HolochainError {
+    HolochainError::ErrorGeneric(format!("sodium error: {:?}", error))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn keystore_unlocks_with_right_passphrase_only() {
+        let keystore = Keystore::new_random("secret").unwrap();
+        let keys = keystore.unlock("secret").unwrap();
+        assert_eq!(keys.address(), keystore.public_address());
+        assert!(keystore.unlock("wrong").is_err());
+    }
+
+    #[test]
+    fn keystore_roundtrips_through_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("agent.keystore");
+        let keystore = Keystore::new_random("secret").unwrap();
+        keystore.save(&path).unwrap();
+        let loaded = Keystore::load(&path).unwrap();
+        assert_eq!(loaded, keystore);
+        assert_eq!(
+            loaded.unlock("secret").unwrap().address(),
+            keystore.public_address()
+        );
+    }
+
+    #[test]
+    fn keystore_gets_replaced_as_a_whole() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("agent.keystore");
+        Keystore::new_random("secret").unwrap().save(&path).unwrap();
+        let keystore = Keystore::new_random("secret").unwrap();
+        keystore.save(&path).unwrap();
+        assert_eq!(Keystore::load(&path).unwrap(), keystore);
+        assert_eq!(
+            fs::read_dir(dir.path()).unwrap().count(),
+            1,
+            "no temporary file should be left behind"
+        );
+    }
+
+    #[test]
+    fn keystore_detects_tampering() {
+        let keystore = Keystore::new_random("secret").unwrap();
+        let mut cipher = base64::decode(&keystore.cipher).unwrap();
+        cipher[0] ^= 1;
+        let tampered = Keystore {
+            cipher: base64::encode(&cipher),
+            ..keystore.clone()
+        };
+        assert!(tampered.unlock("secret").is_err());
+
+        let other_address = Keystore {
+            public_address: Keystore::new_random("secret").unwrap().public_address,
+            ..keystore
+        };
+        assert!(other_address.unlock("secret").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keystore_file_is_only_readable_by_its_owner() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("agent.keystore");
+        File::create(&path).unwrap();
+        Keystore::new_random("secret").unwrap().save(&path).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn keystore_passphrase_can_be_changed() {
+        let mut keystore = Keystore::new_random("old").unwrap();
+        let address = keystore.public_address();
+        assert!(keystore.change_passphrase("wrong", "new").is_err());
+        keystore.change_passphrase("old", "new").unwrap();
+        assert_eq!(keystore.public_address(), address);
+        assert!(keystore.unlock("old").is_err());
+        assert_eq!(keystore.unlock("new").unwrap().address(), address);
+    }
+
+    #[test]
+    fn keystore_keeps_rotated_keys() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("agent.keystore");
+        Keystore::new_random("old").unwrap().save(&path).unwrap();
+        let store = KeystoreFile::new(path.to_str().unwrap());
+        let mut keys = Keystore::load(&path).unwrap().unlock("old").unwrap();
+        let (mut rotated, mut seed) = keys.rotate().unwrap();
+        store.add(&mut rotated, &mut seed).unwrap();
+
+        let restored = store.load(&mut keys).unwrap();
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].address(), rotated.address());
+
+        // the rotated keys don't depend on the passphrase
+        let mut keystore = Keystore::load(&path).unwrap();
+        keystore.change_passphrase("old", "new").unwrap();
+        let mut keys = keystore.unlock("new").unwrap();
+        let restored = keystore.unlock_rotated_keys(&mut keys).unwrap();
+        assert_eq!(restored[0].address(), rotated.address());
+
+        let mut other_keys = Keystore::new_random("old").unwrap().unlock("old").unwrap();
+        assert!(keystore.unlock_rotated_keys(&mut other_keys).is_err());
+    }
+
+    #[test]
+    fn load_key_checks_public_address() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("agent.keystore");
+        let keystore = Keystore::new_random("secret").unwrap();
+        keystore.save(&path).unwrap();
+        let other_address = Keystore::new_random("secret").unwrap().public_address();
+        let agent_config = AgentConfiguration {
+            id: String::from("agent"),
+            name: String::from("Agent"),
+            public_address: other_address.to_string(),
+            key_file: path.to_str().unwrap().to_string(),
+        };
+        assert!(load_key(&agent_config).is_err());
+    }
+}
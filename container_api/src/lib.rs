@@ -1,5 +1,5 @@
 //! let file_system = Arc::new(RwLock::new(FilesystemStorage::new(tempdir().unwrap().path().to_str().unwrap()).unwrap()));
-//!     Arc::new(Mutex::new(SimplePersister::new(file_system.clone()))),
+//!     Arc::new(Mutex::new(SimplePersister::new(file_system.clone()).unwrap())),
 //!     file_system.clone(),
 
 #![feature(try_from, try_trait, async_await, await_macro)]
@@ -95,6 +95,7 @@ extern crate holochain_core_types;
 extern crate holochain_net;
 extern crate holochain_net_connection;
 extern crate holochain_net_ipc;
+extern crate holochain_sodium;
 
 extern crate chrono;
 extern crate serde;
@@ -127,6 +128,8 @@ extern crate hyper_staticfile;
 #[cfg(test)]
 extern crate reqwest;
 extern crate tokio;
+extern crate base64;
+extern crate rpassword;
 
 pub mod config;
 pub mod container;
@@ -135,6 +138,7 @@ pub mod error;
 pub mod holochain;
 pub mod interface;
 pub mod interface_impls;
+pub mod keystore;
 pub mod logger;
 pub mod static_file_server;
 
@@ -1,7 +1,18 @@
 use crate::{
-    agent::state::AgentState,
+    agent::{
+        bundle::{Bundle, BundleKey, BundledCommit},
+        chain_archive::ChainArchive,
+        state::AgentState,
+    },
     context::Context,
-    network::{direct_message::DirectMessage, state::NetworkState},
+    dht::actions::collect_garbage::GarbageCollection,
+    network::{
+        direct_message::DirectMessage,
+        encrypted_entry::{EncryptedEntry, EncryptedEntryWithMeta},
+        gossip::Aspect,
+        state::NetworkState,
+        validation_receipt::ValidationReceipt,
+    },
     nucleus::{
         state::{NucleusState, ValidationResult},
         ExecuteZomeFnResponse, ZomeFnCall,
@@ -17,12 +28,19 @@ use holochain_core_types::{
     link::Link,
     validation::ValidationPackage,
 };
-use holochain_net_connection::json_protocol::{DhtData, DhtMetaData, GetDhtData, GetDhtMetaData};
+use holochain_net_connection::json_protocol::{
+    DhtData, DhtMetaData, GetDhtData, GetDhtMetaData, PeerChallengeData,
+};
+use holochain_wasm_utils::api_serialization::{
+    bundle::BundleOnClose,
+    get_links::{LinkTagMatch, LinksStatusRequestKind},
+};
 use snowflake;
 use std::{
     hash::{Hash, Hasher},
     sync::Arc,
 };
+use uuid::Uuid;
 
 /// Wrapper for actions that provides a unique ID
 /// The unique ID is needed for state tracking to ensure that we can differentiate between two
@@ -30,30 +48,37 @@ use std::{
 /// The standard approach is to drop the ActionWrapper into the key of a state history HashMap and
 /// use the convenience unwrap_to! macro to extract the action data in a reducer.
 /// All reducer functions must accept an ActionWrapper so all dispatchers take an ActionWrapper.
+/// IDs are UUIDs so they stay unique across restarts, which is needed for the journal
+/// that persists reduced actions.
 #[derive(Clone, Debug)]
 pub struct ActionWrapper {
     action: Action,
-    id: snowflake::ProcessUniqueId,
+    id: Uuid,
 }
 
 impl ActionWrapper {
     /// constructor from &Action
-    /// internal snowflake ID is automatically set
+    /// internal UUID is automatically set
     pub fn new(a: Action) -> Self {
         ActionWrapper {
             action: a,
             // auto generate id
-            id: snowflake::ProcessUniqueId::new(),
+            id: Uuid::new_v4(),
         }
     }
 
+    /// constructor for an action that got reduced before, e.g. when replaying the journal
+    pub fn with_id(a: Action, id: Uuid) -> Self {
+        ActionWrapper { action: a, id }
+    }
+
     /// read only access to action
     pub fn action(&self) -> &Action {
         &self.action
     }
 
     /// read only access to id
-    pub fn id(&self) -> &snowflake::ProcessUniqueId {
+    pub fn id(&self) -> &Uuid {
         &self.id
     }
 }
@@ -67,9 +92,6 @@ impl PartialEq for ActionWrapper {
 impl Eq for ActionWrapper {}
 
 impl Hash for ActionWrapper {
-    /// @TODO dangerous when persisted!
-    /// snowflake only guarantees uniqueness per process
-    /// @see https://github.com/holochain/holochain-rust/issues/203
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.id.hash(state);
     }
@@ -85,6 +107,24 @@ pub enum Action {
     /// Does not validate, assumes entry is valid.
     Commit((Entry, Option<Address>)),
 
+    /// Opens a bundle for the given zome call that collects all following commits
+    /// of that call until it gets closed.
+    StartBundle((BundleKey, Bundle)),
+
+    /// Stages an entry in the open bundle of the given zome call instead of writing it
+    /// to the source chain.
+    /// Does not validate, assumes entry is valid.
+    StageCommit((BundleKey, BundledCommit)),
+
+    /// Closes the open bundle of the given zome call and either moves the chain head to
+    /// the given headers of its staged entries at once or drops them.
+    /// Assumes the headers and entries are stored already.
+    CloseBundle((BundleKey, BundleOnClose, Vec<ChainHeader>)),
+
+    /// Appends the headers and entries of an archived chain that are missing on the
+    /// source chain. Does not verify the archive, assumes it is valid.
+    ImportChain(ChainArchive),
+
     // -------------
     // DHT actions:
     // -------------
@@ -92,10 +132,38 @@ pub enum Action {
     /// Does not validate, assumes entry is valid.
     Hold(Entry),
 
+    /// Adds an entry of an encrypted entry type to the local DHT shard as it is, i.e.
+    /// without the plaintext entry.
+    /// Does not validate, assumes the entry is valid.
+    HoldEncrypted(EncryptedEntry),
+
     /// Adds a link to the local DHT shard's meta/EAV storage
     /// Does not validate, assumes link is valid.
     AddLink(Link),
 
+    /// Marks a link as removed in the local DHT shard's meta/EAV storage
+    /// Does not validate, assumes link removal is valid.
+    RemoveLink(Link),
+
+    /// Drops entries the local DHT shard holds for others until they fit into the quota
+    CollectGarbage(GarbageCollection),
+
+    /// Remembers when another node asked for the entry or the links at the address,
+    /// in nanoseconds, which keeps them from getting garbage collected.
+    RecordRequest((Address, i64)),
+
+    /// Remembers published data that got held in the local DHT shard as an aspect
+    /// to gossip about with other holders.
+    HoldAspect(Aspect),
+
+    /// Remembers published data that we found invalid as a rejected aspect,
+    /// so gossip does not fetch it again.
+    RejectAspect(Aspect),
+
+    /// Stores a validation receipt a holder sent us for one of our entries
+    /// in the local DHT shard's meta/EAV storage.
+    HoldValidationReceipt(ValidationReceipt),
+
     // ----------------
     // Network actions:
     // ----------------
@@ -116,6 +184,10 @@ pub enum Action {
     /// requested entry from our local DHT shard.
     RespondGet((GetDhtData, Option<EntryWithMeta>)),
 
+    /// Lets the network module respond to a GET request for an entry that
+    /// we only hold encrypted.
+    RespondGetEncrypted((GetDhtData, EncryptedEntryWithMeta)),
+
     /// We got a response for our GET request which needs to be
     /// added to the state.
     /// Triggered from the network handler.
@@ -132,8 +204,9 @@ pub enum Action {
     /// Last string is the stringified process unique id of this `hdk::get_links` call.
     GetLinks(GetLinksKey),
     GetLinksTimeout(GetLinksKey),
-    RespondGetLinks((GetDhtMetaData, Vec<Address>)),
-    HandleGetLinksResult((DhtMetaData, String)),
+    RespondGetLinks((GetDhtMetaData, Vec<Link>)),
+    /// The response to a GetLinks request, which it has the msg_id of
+    HandleGetLinksResult(DhtMetaData),
 
     /// Makes the network module send a direct (node-to-node) message
     /// to the address given in [DirectMessageData](struct.DirectMessageData.html)
@@ -162,6 +235,23 @@ pub enum Action {
     /// /// Triggered from the network handler when we get the response.
     HandleCustomSendResponse((String, Result<String, String>)),
 
+    /// Adds the agent with the given ID to the peer store, or refreshes it if known,
+    /// as seen at the given time in milliseconds.
+    /// Triggered from the network handler when a peer of our DNA connects.
+    AddPeer((String, i64)),
+
+    /// Records that we got a message from the agent with the given ID at the given time
+    /// in milliseconds.
+    PeerSeen((String, i64)),
+
+    /// Removes the agent with the given ID from the peer store.
+    /// Triggered when the peer disconnects or did not get in touch for too long.
+    RemovePeer(String),
+
+    /// Sends the signature with which our agent proves to another node that it runs here.
+    /// Triggered from the network handler when that node challenges our agent.
+    RespondPeerChallenge(PeerChallengeData),
+
     // ----------------
     // Nucleus actions:
     // ----------------
@@ -215,6 +305,12 @@ pub struct GetLinksKey {
     /// The link tag
     pub tag: String,
 
+    /// Whether the tag has to match exactly or is a prefix or regex
+    pub tag_match: LinkTagMatch,
+
+    /// Whether live, removed or all links are requested
+    pub status: LinksStatusRequestKind,
+
     /// A unique ID that is used to pair the eventual result to this request
     pub id: String,
 }
@@ -321,7 +417,7 @@ pub mod tests {
         let aw1 = test_action_wrapper();
         let aw2 = test_action_wrapper();
 
-        // snowflake enforces uniqueness
+        // UUIDs enforce uniqueness
         assert_eq!(aw1, aw1);
         assert_ne!(aw1, aw2);
     }
@@ -0,0 +1,119 @@
+use crate::{
+    action::{Action, ActionWrapper},
+    agent::{
+        bundle::{Bundle, BundleKey, BundledCommit},
+        state::ActionResponse,
+    },
+    context::Context,
+    instance::dispatch_action,
+};
+use futures::{
+    future::Future,
+    task::{LocalWaker, Poll},
+};
+use holochain_core_types::{chain_header::ChainHeader, error::HolochainError, json::JsonString};
+use holochain_wasm_utils::api_serialization::bundle::BundleOnClose;
+use std::{pin::Pin, sync::Arc};
+
+/// Start Bundle Action Creator
+/// Opens a bundle that stages all following commits of the given zome call until it
+/// gets closed or `timeout` milliseconds have passed.
+/// Fails if the call has another bundle still open.
+///
+/// Returns a future that resolves once the bundle is open.
+pub async fn start_bundle(
+    key: BundleKey,
+    timeout: usize,
+    user_param: JsonString,
+    context: &Arc<Context>,
+) -> Result<(), HolochainError> {
+    let bundle = Bundle::new(timeout, user_param);
+    let action_wrapper = ActionWrapper::new(Action::StartBundle((key, bundle)));
+    dispatch_action(context.action_channel(), action_wrapper.clone());
+    await!(BundleFuture {
+        context: context.clone(),
+        action: action_wrapper,
+    })
+}
+
+/// Close Bundle Action Creator
+/// Moves the source chain head to the given headers of the staged entries of the open
+/// bundle of the given zome call, or drops the entries.
+/// The headers have to follow the current chain head, otherwise the bundle stays open.
+///
+/// Returns a future that resolves to the commits that were written to the chain.
+pub async fn close_bundle(
+    key: BundleKey,
+    on_close: BundleOnClose,
+    chain_headers: Vec<ChainHeader>,
+    context: &Arc<Context>,
+) -> Result<Vec<BundledCommit>, HolochainError> {
+    let action_wrapper = ActionWrapper::new(Action::CloseBundle((key, on_close, chain_headers)));
+    dispatch_action(context.action_channel(), action_wrapper.clone());
+    await!(CloseBundleFuture {
+        context: context.clone(),
+        action: action_wrapper,
+    })
+}
+
+/// BundleFuture resolves once a bundle got started
+/// Tracks the state for a response to its ActionWrapper
+pub struct BundleFuture {
+    context: Arc<Context>,
+    action: ActionWrapper,
+}
+
+impl Future for BundleFuture {
+    type Output = Result<(), HolochainError>;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<Self::Output> {
+        //
+        // TODO: connect the waker to state updates for performance reasons
+        // See: https://github.com/holochain/holochain-rust/issues/314
+        //
+        lw.wake();
+        match self
+            .context
+            .state()
+            .unwrap()
+            .agent()
+            .actions()
+            .get(&self.action)
+        {
+            Some(ActionResponse::StartBundle(result)) => Poll::Ready(result.clone()),
+            Some(_) => unreachable!(),
+            None => Poll::Pending,
+        }
+    }
+}
+
+/// CloseBundleFuture resolves to the commits of the closed bundle
+/// Tracks the state for a response to its ActionWrapper
+pub struct CloseBundleFuture {
+    context: Arc<Context>,
+    action: ActionWrapper,
+}
+
+impl Future for CloseBundleFuture {
+    type Output = Result<Vec<BundledCommit>, HolochainError>;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<Self::Output> {
+        //
+        // TODO: connect the waker to state updates for performance reasons
+        // See: https://github.com/holochain/holochain-rust/issues/314
+        //
+        lw.wake();
+        match self
+            .context
+            .state()
+            .unwrap()
+            .agent()
+            .actions()
+            .get(&self.action)
+        {
+            Some(ActionResponse::CloseBundle(result)) => Poll::Ready(result.clone()),
+            Some(_) => unreachable!(),
+            None => Poll::Pending,
+        }
+    }
+}
@@ -1,7 +1,10 @@
 extern crate futures;
 use crate::{
     action::{Action, ActionWrapper},
-    agent::state::ActionResponse,
+    agent::{
+        bundle::{BundleKey, BundledCommit},
+        state::ActionResponse,
+    },
     context::Context,
     instance::dispatch_action,
 };
@@ -31,6 +34,25 @@ pub async fn commit_entry(
     })
 }
 
+/// Stage Commit Action Creator
+/// Adds an entry to the open bundle of the given zome call instead of committing it
+/// right away. The entry gets written to the source chain when the bundle is closed with
+/// `BundleOnClose::Commit`.
+///
+/// Returns a future that resolves to the address of the staged entry.
+pub async fn stage_commit(
+    key: BundleKey,
+    commit: BundledCommit,
+    context: &Arc<Context>,
+) -> Result<Address, HolochainError> {
+    let action_wrapper = ActionWrapper::new(Action::StageCommit((key, commit)));
+    dispatch_action(context.action_channel(), action_wrapper.clone());
+    await!(CommitFuture {
+        context: context.clone(),
+        action: action_wrapper,
+    })
+}
+
 /// CommitFuture resolves to ActionResponse
 /// Tracks the state for a response to its ActionWrapper
 pub struct CommitFuture {
@@ -0,0 +1,61 @@
+extern crate futures;
+use crate::{
+    action::{Action, ActionWrapper},
+    agent::{chain_archive::ChainArchive, state::ActionResponse},
+    context::Context,
+    instance::dispatch_action,
+};
+use futures::{
+    future::Future,
+    task::{LocalWaker, Poll},
+};
+use holochain_core_types::{cas::content::Address, error::HolochainError};
+use std::{pin::Pin, sync::Arc};
+
+/// Import Chain Action Creator
+/// Appends the headers and entries of the given archive that are missing on the
+/// source chain. The archive has to be verified before.
+///
+/// Returns a future that resolves to the address of the new top chain header.
+pub async fn import_chain(
+    archive: ChainArchive,
+    context: &Arc<Context>,
+) -> Result<Address, HolochainError> {
+    let action_wrapper = ActionWrapper::new(Action::ImportChain(archive));
+    dispatch_action(context.action_channel(), action_wrapper.clone());
+    await!(ImportChainFuture {
+        context: context.clone(),
+        action: action_wrapper,
+    })
+}
+
+/// ImportChainFuture resolves to the result of the import
+/// Tracks the state for a response to its ActionWrapper
+pub struct ImportChainFuture {
+    context: Arc<Context>,
+    action: ActionWrapper,
+}
+
+impl Future for ImportChainFuture {
+    type Output = Result<Address, HolochainError>;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<Self::Output> {
+        //
+        // TODO: connect the waker to state updates for performance reasons
+        // See: https://github.com/holochain/holochain-rust/issues/314
+        //
+        lw.wake();
+        match self
+            .context
+            .state()
+            .unwrap()
+            .agent()
+            .actions()
+            .get(&self.action)
+        {
+            Some(ActionResponse::ImportChain(result)) => Poll::Ready(result.clone()),
+            Some(_) => unreachable!(),
+            None => Poll::Pending,
+        }
+    }
+}
@@ -1,2 +1,4 @@
+pub mod bundle;
 pub mod commit;
+pub mod import_chain;
 pub mod update_entry;
@@ -0,0 +1,118 @@
+//! Bundles group the commits of a zome function so that they either all show up
+//! or not at all.
+//!
+//! While a bundle is open, authored entries get validated right away but are only
+//! staged in the agent state. Closing the bundle with `BundleOnClose::Commit` appends
+//! all staged entries to the source chain in a single state transition and only then
+//! makes their changes on the DHT. Discarding the bundle, or letting it time out,
+//! drops the staged entries so nothing of them ever reaches the chain or the DHT.
+//!
+//! Every zome call has its own bundle, so concurrent calls don't see each other's
+//! staged commits. A bundle ends with the zome call that started it at the latest.
+//!
+//! Entries are validated against the chain as it was when they were staged,
+//! without the other entries of the bundle.
+
+use holochain_core_types::{cas::content::Address, entry::Entry, json::JsonString};
+use snowflake::ProcessUniqueId;
+use std::time::{Duration, Instant};
+
+/// Bundles are keyed by the id of the zome call that started them.
+pub type BundleKey = ProcessUniqueId;
+
+/// An open bundle and the commits staged in it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bundle {
+    deadline: Instant,
+    user_param: JsonString,
+    commits: Vec<BundledCommit>,
+}
+
+impl Bundle {
+    /// Creates an empty bundle that expires `timeout` milliseconds after it got started.
+    pub fn new(timeout: usize, user_param: JsonString) -> Bundle {
+        Bundle {
+            deadline: Instant::now() + Duration::from_millis(timeout as u64),
+            user_param,
+            commits: Vec::new(),
+        }
+    }
+
+    /// A bundle that is not closed before its timeout counts as discarded.
+    pub fn is_expired(&self) -> bool {
+        Instant::now() >= self.deadline
+    }
+
+    /// The parameter the bundle was started with
+    pub fn user_param(&self) -> JsonString {
+        self.user_param.clone()
+    }
+
+    /// The staged commits in the order they were made
+    pub fn commits(&self) -> Vec<BundledCommit> {
+        self.commits.clone()
+    }
+
+    pub(crate) fn stage(&mut self, commit: BundledCommit) {
+        self.commits.push(commit);
+    }
+}
+
+/// A commit that waits for its bundle to get closed
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BundledCommit {
+    pub entry: Entry,
+    pub crud_link: Option<Address>,
+    pub dht_update: DhtUpdate,
+}
+
+/// The change to the DHT that has to follow a commit, which is what
+/// makes an authored entry visible to others.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DhtUpdate {
+    /// Publish the entry, if its type can be published
+    Publish,
+    /// Publish the entry encrypted for its author and the agents at the given addresses
+    PublishEncrypted(Vec<Address>),
+    /// Mark the entry with the given address as updated by the committed entry
+    Update(Address),
+    /// Mark the entry with the given address as deleted by the committed deletion entry
+    Remove(Address),
+    /// The entry stays private to the source chain
+    None,
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use holochain_core_types::entry::test_entry;
+    use std::thread;
+
+    #[test]
+    fn bundle_stages_commits_in_order() {
+        let mut bundle = Bundle::new(1000, JsonString::from("{}"));
+        assert!(!bundle.is_expired());
+        let first = BundledCommit {
+            entry: test_entry(),
+            crud_link: None,
+            dht_update: DhtUpdate::Publish,
+        };
+        let second = BundledCommit {
+            entry: test_entry(),
+            crud_link: Some(Address::from("old")),
+            dht_update: DhtUpdate::Update(Address::from("old")),
+        };
+        bundle.stage(first.clone());
+        bundle.stage(second.clone());
+        assert_eq!(bundle.commits(), vec![first, second]);
+    }
+
+    #[test]
+    fn bundle_expires_at_its_deadline() {
+        assert!(Bundle::new(0, JsonString::from("{}")).is_expired());
+        let bundle = Bundle::new(50, JsonString::from("{}"));
+        assert!(!bundle.is_expired());
+        thread::sleep(Duration::from_millis(100));
+        assert!(bundle.is_expired());
+    }
+}
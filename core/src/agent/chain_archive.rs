@@ -0,0 +1,309 @@
+//! A ChainArchive holds a whole source chain, i.e. all headers and entries, together with
+//! the DNA and the agent the chain belongs to. It is used to back up a chain and to
+//! restore it into an instance on another machine.
+
+use crate::{
+    agent::{
+        chain_store::ChainStore,
+        keys::{agent_key_after, verify_header},
+    },
+    nucleus::actions::get_entry::get_entry_from_cas,
+};
+use holochain_core_types::{
+    agent::AgentId,
+    cas::content::{Address, AddressableContent},
+    chain_header::ChainHeader,
+    entry::{entry_type::EntryType, Entry},
+    error::{HcResult, HolochainError},
+    json::JsonString,
+    validation::{ValidationPackage, ValidationPackageDefinition},
+};
+use std::collections::HashMap;
+
+/// Identifies the format of an archive, so future formats can be told apart
+pub const CHAIN_ARCHIVE_FORMAT: &str = "holochain-chain-archive/1";
+
+/// A header of the archived chain together with its entry
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChainArchiveElement {
+    pub header: ChainHeader,
+    pub entry: Entry,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, DefaultJson)]
+pub struct ChainArchive {
+    format: String,
+    dna_address: Address,
+    agent_id: AgentId,
+    /// oldest element first
+    chain: Vec<ChainArchiveElement>,
+}
+
+impl ChainArchive {
+    /// Archives the chain of the given store that ends with the given header.
+    /// DNA and agent are the ones of the first DNA and agent entry on the chain.
+    pub fn from_chain(
+        chain_store: &ChainStore,
+        top_chain_header: &Option<ChainHeader>,
+    ) -> HcResult<ChainArchive> {
+        let storage = chain_store.content_storage();
+        let mut chain = chain_store
+            .iter(top_chain_header)
+            .map(|header| {
+                let entry =
+                    get_entry_from_cas(&storage, header.entry_address())?.ok_or_else(|| {
+                        HolochainError::ErrorGeneric(format!(
+                            "Entry {} of the source chain is missing",
+                            header.entry_address()
+                        ))
+                    })?;
+                Ok(ChainArchiveElement { header, entry })
+            })
+            .collect::<HcResult<Vec<_>>>()?;
+        chain.reverse();
+
+        let dna_address = chain
+            .iter()
+            .find(|element| element.header.entry_type() == &EntryType::Dna)
+            .map(|element| element.entry.address())
+            .ok_or_else(|| {
+                HolochainError::ErrorGeneric("Source chain has no DNA entry".to_string())
+            })?;
+        let agent_id = chain
+            .iter()
+            .find_map(|element| match element.entry {
+                Entry::AgentId(ref agent_id) => Some(agent_id.clone()),
+                _ => None,
+            })
+            .ok_or_else(|| {
+                HolochainError::ErrorGeneric("Source chain has no agent entry".to_string())
+            })?;
+
+        Ok(ChainArchive {
+            format: CHAIN_ARCHIVE_FORMAT.to_string(),
+            dna_address,
+            agent_id,
+            chain,
+        })
+    }
+
+    pub fn dna_address(&self) -> &Address {
+        &self.dna_address
+    }
+
+    /// The agent that started the chain, before any key rotation
+    pub fn agent_id(&self) -> &AgentId {
+        &self.agent_id
+    }
+
+    pub fn chain(&self) -> &Vec<ChainArchiveElement> {
+        &self.chain
+    }
+
+    pub fn top_chain_header(&self) -> Option<&ChainHeader> {
+        self.chain.last().map(|element| &element.header)
+    }
+
+    /// Builds the validation package of the given definition for the element at the given
+    /// position, from the archived chain up to and including that element.
+    pub fn validation_package(
+        &self,
+        index: usize,
+        definition: ValidationPackageDefinition,
+    ) -> ValidationPackage {
+        let header = self.chain[index].header.clone();
+        let public_elements = || {
+            self.chain[..=index]
+                .iter()
+                .rev()
+                .filter(|element| element.header.entry_type().can_publish())
+        };
+        let mut package = ValidationPackage::only_header(header);
+        match definition {
+            ValidationPackageDefinition::Entry => {}
+            ValidationPackageDefinition::ChainEntries => {
+                package.source_chain_entries = Some(
+                    public_elements()
+                        .map(|element| element.entry.clone())
+                        .collect(),
+                );
+            }
+            ValidationPackageDefinition::ChainHeaders => {
+                package.source_chain_headers = Some(
+                    public_elements()
+                        .map(|element| element.header.clone())
+                        .collect(),
+                );
+            }
+            ValidationPackageDefinition::ChainFull => {
+                package.source_chain_entries = Some(
+                    public_elements()
+                        .map(|element| element.entry.clone())
+                        .collect(),
+                );
+                package.source_chain_headers = Some(
+                    public_elements()
+                        .map(|element| element.header.clone())
+                        .collect(),
+                );
+            }
+            ValidationPackageDefinition::Custom(string) => package.custom = Some(string),
+        }
+        package.agent_headers = self.chain[..index]
+            .iter()
+            .rev()
+            .filter(|element| *element.header.entry_type() == EntryType::AgentId)
+            .map(|element| element.header.clone())
+            .collect();
+        package
+    }
+
+    /// Checks that the archive holds an unbroken chain of correctly signed headers
+    /// of the given DNA and agent. Every header has to be signed by the agent's key
+    /// in effect at its position, i.e. the initial key or the key it got rotated to last.
+    pub fn verify(&self) -> HcResult<()> {
+        if self.format != CHAIN_ARCHIVE_FORMAT {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "Unknown chain archive format {}",
+                self.format
+            )));
+        }
+        let invalid = |reason: String| HolochainError::ValidationFailed(reason);
+
+        let mut previous: Option<Address> = None;
+        let mut previous_of_type: HashMap<EntryType, Address> = HashMap::new();
+        let mut agent_key = self.agent_id.address();
+        for element in self.chain.iter() {
+            let header = &element.header;
+            verify_header(&element.entry, header, &agent_key)?;
+            agent_key = agent_key_after(&element.entry, &agent_key);
+            if header.link() != previous {
+                return Err(invalid(format!(
+                    "Header of entry {} does not link to the header before it",
+                    header.entry_address()
+                )));
+            }
+            if header.link_same_type() != previous_of_type.get(header.entry_type()).cloned() {
+                return Err(invalid(format!(
+                    "Header of entry {} does not link to the header of the same type before it",
+                    header.entry_address()
+                )));
+            }
+            previous = Some(header.address());
+            previous_of_type.insert(header.entry_type().clone(), header.address());
+        }
+
+        match self.chain.first().map(|element| &element.entry) {
+            Some(Entry::Dna(dna)) if dna.address() == self.dna_address => {}
+            _ => {
+                return Err(invalid(format!(
+                    "Chain does not start with DNA {}",
+                    self.dna_address
+                )))
+            }
+        }
+        let first_agent = self.chain.iter().find_map(|element| match element.entry {
+            Entry::AgentId(ref agent_id) => Some(agent_id),
+            _ => None,
+        });
+        if first_agent != Some(&self.agent_id) {
+            return Err(invalid(format!(
+                "Chain does not belong to agent {}",
+                self.agent_id.address()
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{
+        agent::keys::{header_signed_data, test_keypair, with_signatures, Keypair},
+        nucleus::actions::tests::{instance, test_dna},
+    };
+    use holochain_core_types::{entry::test_entry, signature::Signature, time::Iso8601};
+    use std::convert::TryFrom;
+
+    pub fn test_chain_archive() -> ChainArchive {
+        let (_instance, context) = instance(None);
+        let agent_state = context.state().unwrap().agent();
+        ChainArchive::from_chain(&agent_state.chain(), &agent_state.top_chain_header()).unwrap()
+    }
+
+    #[test]
+    fn can_archive_chain() {
+        let archive = test_chain_archive();
+        assert_eq!(archive.dna_address(), &test_dna().address());
+        assert_eq!(archive.chain().len(), 2);
+        assert_eq!(archive.chain()[0].entry, Entry::Dna(test_dna()));
+        assert_eq!(
+            archive.chain()[1].entry,
+            Entry::AgentId(archive.agent_id().clone())
+        );
+        assert!(archive.verify().is_ok());
+        assert_eq!(
+            ChainArchive::try_from(JsonString::from(archive.clone())).unwrap(),
+            archive
+        );
+    }
+
+    #[test]
+    fn verify_rejects_tampered_chains() {
+        let archive = test_chain_archive();
+
+        let mut forged = archive.clone();
+        let header = forged.chain[1].header.clone();
+        forged.chain[1].header = ChainHeader::new(
+            header.entry_type(),
+            header.entry_address(),
+            header.sources(),
+            &vec![Signature::from("forged")],
+            &header.link(),
+            &header.link_same_type(),
+            &header.link_crud(),
+            &Iso8601::from(""),
+        );
+        assert!(forged.verify().is_err());
+
+        let mut reordered = archive.clone();
+        reordered.chain.swap(0, 1);
+        assert!(reordered.verify().is_err());
+
+        let mut other_dna = archive.clone();
+        other_dna.dna_address = Address::from("other DNA");
+        assert!(other_dna.verify().is_err());
+    }
+
+    #[test]
+    fn verify_rejects_headers_signed_by_other_agents() {
+        let archive = test_chain_archive();
+        let entry = test_entry();
+        let top_header = archive.top_chain_header().unwrap().clone();
+        let signed_by = |keys: &mut Keypair| {
+            let unsigned = ChainHeader::new(
+                &entry.entry_type(),
+                &entry.address(),
+                &vec![keys.address()],
+                &Vec::new(),
+                &Some(top_header.address()),
+                &None,
+                &None,
+                top_header.timestamp(),
+            );
+            let signature = keys.sign(&header_signed_data(&unsigned)).unwrap();
+            let mut extended = archive.clone();
+            extended.chain.push(ChainArchiveElement {
+                header: with_signatures(&unsigned, &vec![signature]),
+                entry: entry.clone(),
+            });
+            extended
+        };
+
+        assert!(signed_by(&mut test_keypair(&archive.agent_id().nick))
+            .verify()
+            .is_ok());
+        assert!(signed_by(&mut test_keypair("mallory")).verify().is_err());
+    }
+}
@@ -0,0 +1,659 @@
+//! The key material of the agent running an instance.
+//!
+//! A `Keypair` holds the private signing and encryption keys whose public halves
+//! together form the agent's `KeyBuffer`, i.e. the public identity that gets rendered
+//! as the agent's address. Secrets are kept in secure `SecBuf`s.
+//!
+//! Signatures are detached Ed25519 signatures, base64 encoded inside a `Signature`.
+//!
+//! Keys can be rotated with `update_agent`. Every rotation moves on to keys derived from
+//! a fresh random seed, so neither the previous keys nor the seed of the initial keys
+//! reveal them. The seeds of rotated keys have to be stored alongside the initial seed,
+//! e.g. in the agent's keystore, to recover the current keys of a chain.
+//! Only the storage key stays the same, as it has to decrypt the agent's storages.
+
+use holochain_core_types::{
+    agent::{AgentId, KeyBuffer},
+    cas::content::{Address, AddressableContent},
+    chain_header::ChainHeader,
+    entry::{entry_type::EntryType, Entry},
+    error::{HcResult, HolochainError},
+    signature::Signature,
+};
+use holochain_sodium::{error::SodiumError, kdf, kx, random::random_secbuf, secbuf::SecBuf, sign};
+
+/// Size of the seed a keypair gets derived from
+pub const SEED_SIZE: usize = 32;
+/// Size of a detached signature
+pub const SIGNATURE_SIZE: usize = 64;
+
+const SIGN_PUBLIC_KEY_SIZE: usize = 32;
+const SIGN_SECRET_KEY_SIZE: usize = 64;
+/// kdf context used to derive the signing and encryption seeds from the root seed
+const KDF_CONTEXT: &[u8; kdf::CONTEXTBYTES] = b"HCAGENTK";
+/// kdf context used to derive the key for encrypting an agent's storages from the root seed
+const STORAGE_KDF_CONTEXT: &[u8; kdf::CONTEXTBYTES] = b"HCSTORAG";
+/// Size of the storage key
+pub const STORAGE_KEY_SIZE: usize = 32;
+/// Prefix of the data the sources of a chain header sign.
+/// Every kind of data agents sign starts with its own prefix, so a signature of one kind
+/// can never be passed off as a signature of another.
+pub const HEADER_SIGNATURE_DOMAIN: &str = "holochain-chain-header:";
+/// Prefix of the payloads zome code signs with `hdk::sign`
+pub const ZOME_SIGNATURE_DOMAIN: &str = "holochain-zome-payload:";
+/// Prefix of what authors of encrypted entries sign
+pub const ENCRYPTED_ENTRY_SIGNATURE_DOMAIN: &str = "holochain-encrypted-entry:";
+
+/// Signing and encryption keys of an agent.
+/// The seed the keys got derived from is not kept.
+pub struct Keypair {
+    public_key: KeyBuffer,
+    sign_secret_key: SecBuf,
+    enc_secret_key: SecBuf,
+    storage_key: SecBuf,
+}
+
+// SecBufs are not Send because they might be backed by raw sodium memory.
+// A Keypair exclusively owns its buffers and never hands out pointers to them,
+// so moving it to another thread is fine.
+unsafe impl Send for Keypair {}
+
+impl Keypair {
+    /// Derives a keypair deterministically from a seed of `SEED_SIZE` bytes.
+    /// The signing and encryption keys as well as the storage key are derived
+    /// from separate sub-seeds.
+    pub fn new_from_seed(seed: &mut SecBuf) -> HcResult<Keypair> {
+        check_seed(seed)?;
+        let mut context = secbuf_from_bytes(STORAGE_KDF_CONTEXT);
+        let mut storage_key = SecBuf::with_secure(STORAGE_KEY_SIZE);
+        kdf::derive(&mut storage_key, 1, &mut context, seed).map_err(sodium_error)?;
+        Keypair::new_from_seed_and_storage_key(seed, storage_key)
+    }
+
+    /// Derives the keys an agent's keys got rotated to from the seed `rotate` created for
+    /// them. The storage key is the one of the agent's initial keys.
+    pub fn new_from_rotated_seed(seed: &mut SecBuf, storage_key: &mut SecBuf) -> HcResult<Keypair> {
+        Keypair::new_from_seed_and_storage_key(seed, copy_secbuf(storage_key))
+    }
+
+    fn new_from_seed_and_storage_key(seed: &mut SecBuf, storage_key: SecBuf) -> HcResult<Keypair> {
+        check_seed(seed)?;
+        let mut context = secbuf_from_bytes(KDF_CONTEXT);
+        let mut sign_seed = SecBuf::with_secure(SEED_SIZE);
+        let mut enc_seed = SecBuf::with_secure(SEED_SIZE);
+        kdf::derive(&mut sign_seed, 1, &mut context, seed).map_err(sodium_error)?;
+        kdf::derive(&mut enc_seed, 2, &mut context, seed).map_err(sodium_error)?;
+
+        let mut sign_public_key = SecBuf::with_insecure(SIGN_PUBLIC_KEY_SIZE);
+        let mut sign_secret_key = SecBuf::with_secure(SIGN_SECRET_KEY_SIZE);
+        sign::seed_keypair(&mut sign_public_key, &mut sign_secret_key, &mut sign_seed)
+            .map_err(sodium_error)?;
+
+        let mut enc_public_key = SecBuf::with_insecure(kx::PUBLICKEYBYTES);
+        let mut enc_secret_key = SecBuf::with_secure(kx::SECRETKEYBYTES);
+        kx::seed_keypair(&mut enc_seed, &mut enc_public_key, &mut enc_secret_key)
+            .map_err(sodium_error)?;
+
+        let mut raw_key = [0u8; SIGN_PUBLIC_KEY_SIZE + kx::PUBLICKEYBYTES];
+        {
+            let sign_public_key = sign_public_key.read_lock();
+            let enc_public_key = enc_public_key.read_lock();
+            raw_key[..SIGN_PUBLIC_KEY_SIZE].copy_from_slice(&sign_public_key[..]);
+            raw_key[SIGN_PUBLIC_KEY_SIZE..].copy_from_slice(&enc_public_key[..]);
+        }
+
+        Ok(Keypair {
+            public_key: KeyBuffer::with_raw(&raw_key),
+            sign_secret_key,
+            enc_secret_key,
+            storage_key,
+        })
+    }
+
+    /// Creates the keys that replace this keypair when the agent's keys get rotated.
+    /// They get derived from a fresh random seed, which is returned along with them
+    /// so it can be stored.
+    pub fn rotate(&mut self) -> HcResult<(Keypair, SecBuf)> {
+        let mut seed = SecBuf::with_secure(SEED_SIZE);
+        random_secbuf(&mut seed);
+        let keys = Keypair::new_from_rotated_seed(&mut seed, &mut self.storage_key)?;
+        Ok((keys, seed))
+    }
+
+    /// The key the agent's storages get encrypted with.
+    /// It stays the same when keys get rotated.
+    pub fn storage_key(&mut self) -> HcResult<SecBuf> {
+        Ok(copy_secbuf(&mut self.storage_key))
+    }
+
+    /// Generates a fresh keypair from a random seed.
+    pub fn new_random() -> HcResult<Keypair> {
+        let mut seed = SecBuf::with_secure(SEED_SIZE);
+        random_secbuf(&mut seed);
+        Keypair::new_from_seed(&mut seed)
+    }
+
+    /// The public identity belonging to this keypair
+    pub fn public_key(&self) -> KeyBuffer {
+        self.public_key.clone()
+    }
+
+    /// The agent address (rendered public key) belonging to this keypair
+    pub fn address(&self) -> Address {
+        Address::from(self.public_key.render())
+    }
+
+    /// Builds an AgentId with the given nick for this keypair
+    pub fn agent_id(&self, nick: &str) -> AgentId {
+        AgentId::new(nick, &self.public_key)
+    }
+
+    /// The secret half of the encryption keypair, to be used for key exchange
+    pub fn enc_secret_key(&mut self) -> &mut SecBuf {
+        &mut self.enc_secret_key
+    }
+
+    /// Creates a detached signature of `data` with the private signing key.
+    pub fn sign(&mut self, data: &str) -> HcResult<Signature> {
+        let mut message = secbuf_from_bytes(data.as_bytes());
+        let mut signature = SecBuf::with_insecure(SIGNATURE_SIZE);
+        sign::sign(&mut message, &mut self.sign_secret_key, &mut signature)
+            .map_err(sodium_error)?;
+        let signature = signature.read_lock();
+        Ok(Signature::from(base64::encode(&signature[..])))
+    }
+}
+
+/// Keeps the seeds of the keys an agent rotated to, so that they are still there
+/// after a restart. The container implements it on top of the agent's keystore.
+pub trait RotatedKeyStore: Send + Sync {
+    /// Stores the seed `keys` got derived from when the agent's keys got rotated to them.
+    fn add(&self, keys: &mut Keypair, seed: &mut SecBuf) -> HcResult<()>;
+
+    /// Restores all keys the agent with the given initial keys rotated to,
+    /// in the order of the rotations.
+    fn load(&self, initial_keys: &mut Keypair) -> HcResult<Vec<Keypair>>;
+}
+
+/// Checks that `signature` is a signature of `data` created by the private key belonging
+/// to `public_key`, which is expected to be a rendered `KeyBuffer` such as an agent address.
+/// Returns an error only if `public_key` can't be parsed.
+pub fn verify(public_key: &Address, data: &str, signature: &Signature) -> HcResult<bool> {
+    let key_buffer = KeyBuffer::with_corrected(&String::from(public_key.clone()))?;
+    let signature_bytes = match base64::decode(&String::from(signature.clone())) {
+        Ok(bytes) => bytes,
+        Err(_) => return Ok(false),
+    };
+    if signature_bytes.len() != SIGNATURE_SIZE {
+        return Ok(false);
+    }
+    let mut signature = secbuf_from_bytes(&signature_bytes);
+    let mut message = secbuf_from_bytes(data.as_bytes());
+    let mut sign_public_key = secbuf_from_bytes(key_buffer.get_sig());
+    Ok(sign::verify(&mut signature, &mut message, &mut sign_public_key) == 0)
+}
+
+/// The data that actually gets signed when zome code signs `payload`.
+/// Zome functions might sign input of their callers, so without the prefix they could
+/// be made to sign chain headers and other data the agent vouches for.
+pub fn zome_signed_data(payload: &str) -> String {
+    format!("{}{}", ZOME_SIGNATURE_DOMAIN, payload)
+}
+
+/// Copy of `header` that carries the given signatures instead of its own
+pub fn with_signatures(header: &ChainHeader, entry_signatures: &Vec<Signature>) -> ChainHeader {
+    ChainHeader::new(
+        header.entry_type(),
+        header.entry_address(),
+        header.sources(),
+        entry_signatures,
+        &header.link(),
+        &header.link_same_type(),
+        &header.link_crud(),
+        header.timestamp(),
+    )
+}
+
+/// The data the sources of `header` sign: the whole header except for the signatures
+/// themselves, so neither the entry nor the position of the header in the chain can be
+/// changed without invalidating them.
+pub fn header_signed_data(header: &ChainHeader) -> String {
+    let unsigned = with_signatures(header, &Vec::new());
+    format!(
+        "{}{}",
+        HEADER_SIGNATURE_DOMAIN,
+        String::from(unsigned.content())
+    )
+}
+
+/// Checks that `header` belongs to `entry` and that it is signed by `agent_key`, the key
+/// the author's agent had at the position of the header in the author's chain.
+/// Agent entries have to be signed by the agent they introduce or, if they replace
+/// a previous agent entry to rotate keys, by the agent whose keys get replaced.
+/// Use `agent_key_after` to follow key rotations when walking a chain.
+pub fn verify_header(entry: &Entry, header: &ChainHeader, agent_key: &Address) -> HcResult<()> {
+    let entry_address = entry.address();
+    verify_header_signatures(&entry_address, header)?;
+    let signing_agent = match entry {
+        Entry::AgentId(_) => header.link_crud().unwrap_or(entry_address.clone()),
+        _ => agent_key.clone(),
+    };
+    if signing_agent != *agent_key || *header.sources() != vec![signing_agent.clone()] {
+        return Err(HolochainError::ValidationFailed(format!(
+            "Header of entry {} must be signed by {}",
+            entry_address, agent_key
+        )));
+    }
+    Ok(())
+}
+
+/// The agent key in effect after `entry` in a chain whose agent key was `agent_key`
+/// before it. Agent entries switch to the key they introduce.
+pub fn agent_key_after(entry: &Entry, agent_key: &Address) -> Address {
+    match entry {
+        Entry::AgentId(_) => entry.address(),
+        _ => agent_key.clone(),
+    }
+}
+
+/// The agent key in effect after the agent entries of the given headers, which have to be
+/// the headers of all agent entries of a chain up to some position, newest first.
+/// Checks that they start with the initial agent entry and that every following agent
+/// entry replaces the one before it and is signed by the key in effect before it.
+/// Returns None if there are no agent entries before that position.
+pub fn agent_key_after_headers(agent_headers: &[ChainHeader]) -> HcResult<Option<Address>> {
+    let mut agent_key: Option<Address> = None;
+    let mut previous: Option<Address> = None;
+    for header in agent_headers.iter().rev() {
+        if *header.entry_type() != EntryType::AgentId
+            || header.link_same_type() != previous
+            || header.link_crud() != agent_key
+        {
+            return Err(HolochainError::ValidationFailed(format!(
+                "Header of agent entry {} does not follow the agent entry before it",
+                header.entry_address()
+            )));
+        }
+        verify_header_signatures(header.entry_address(), header)?;
+        let signing_agent = agent_key.unwrap_or_else(|| header.entry_address().clone());
+        if *header.sources() != vec![signing_agent.clone()] {
+            return Err(HolochainError::ValidationFailed(format!(
+                "Header of agent entry {} must be signed by {}",
+                header.entry_address(),
+                signing_agent
+            )));
+        }
+        previous = Some(header.address());
+        agent_key = Some(header.entry_address().clone());
+    }
+    Ok(agent_key)
+}
+
+/// Checks that `header` belongs to the entry at `entry_address` and that it carries
+/// a valid signature of its content for each of its sources.
+/// This is all that can be checked for an entry that is only known encrypted.
+pub fn verify_header_signatures(entry_address: &Address, header: &ChainHeader) -> HcResult<()> {
+    if header.entry_address() != entry_address {
+        return Err(HolochainError::ValidationFailed(format!(
+            "Header does not belong to entry {}",
+            entry_address
+        )));
+    }
+    if header.sources().is_empty() || header.sources().len() != header.entry_signatures().len() {
+        return Err(HolochainError::ValidationFailed(format!(
+            "Header of entry {} must carry exactly one signature per source",
+            entry_address
+        )));
+    }
+    let signed_data = header_signed_data(header);
+    for (source, signature) in header.sources().iter().zip(header.entry_signatures()) {
+        if !verify(source, &signed_data, signature).unwrap_or(false) {
+            return Err(HolochainError::ValidationFailed(format!(
+                "Invalid signature of {} on header of entry {}",
+                source, entry_address
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn check_seed(seed: &SecBuf) -> HcResult<()> {
+    if seed.len() != SEED_SIZE {
+        return Err(HolochainError::ErrorGeneric(format!(
+            "seed must be {} bytes long",
+            SEED_SIZE
+        )));
+    }
+    Ok(())
+}
+
+/// copies the content of a secure SecBuf into a new secure SecBuf
+fn copy_secbuf(buf: &mut SecBuf) -> SecBuf {
+    let mut copy = SecBuf::with_secure(buf.len());
+    copy.write_lock().copy_from_slice(&buf.read_lock()[..]);
+    copy
+}
+
+/// copies the given bytes into a new insecure SecBuf
+pub(crate) fn secbuf_from_bytes(bytes: &[u8]) -> SecBuf {
+    let mut buf = SecBuf::with_insecure(bytes.len());
+    buf.write_lock().copy_from_slice(bytes);
+    buf
+}
+
+pub(crate) fn sodium_error(error: SodiumError) -> HolochainError {
+    HolochainError::ErrorGeneric(format!("sodium error: {:?}", error))
+}
+
+/// Creates a keypair for tests which is always the same for a given nick.
+/// Never use this outside of tests, the secret keys are trivially guessable.
+/// Only available with the `test-keys` feature.
+#[cfg(any(test, feature = "test-keys"))]
+pub fn test_keypair(nick: &str) -> Keypair {
+    let mut bytes = [0u8; SEED_SIZE];
+    for (byte, nick_byte) in bytes.iter_mut().zip(nick.as_bytes()) {
+        *byte = *nick_byte;
+    }
+    let mut seed = secbuf_from_bytes(&bytes);
+    Keypair::new_from_seed(&mut seed).expect("could not derive test keypair")
+}
+
+/// Creates an AgentId with the given nick that is backed by `test_keypair(nick)`.
+#[cfg(any(test, feature = "test-keys"))]
+pub fn test_agent_id_with_keys(nick: &str) -> AgentId {
+    test_keypair(nick).agent_id(nick)
+}
+
+/// Keeps rotated keys in memory, like a keystore file would on disk.
+/// Only available with the `test-keys` feature.
+#[cfg(any(test, feature = "test-keys"))]
+#[derive(Default)]
+pub struct MemoryRotatedKeyStore {
+    seeds: std::sync::Mutex<Vec<Vec<u8>>>,
+}
+
+#[cfg(any(test, feature = "test-keys"))]
+impl RotatedKeyStore for MemoryRotatedKeyStore {
+    fn add(&self, _keys: &mut Keypair, seed: &mut SecBuf) -> HcResult<()> {
+        self.seeds.lock()?.push(seed.read_lock().to_vec());
+        Ok(())
+    }
+
+    fn load(&self, initial_keys: &mut Keypair) -> HcResult<Vec<Keypair>> {
+        let mut storage_key = initial_keys.storage_key()?;
+        self.seeds
+            .lock()?
+            .iter()
+            .map(|seed| {
+                let mut seed = secbuf_from_bytes(seed);
+                Keypair::new_from_rotated_seed(&mut seed, &mut storage_key)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use holochain_core_types::{
+        chain_header::test_chain_header,
+        entry::{test_entry, test_entry_b},
+        time::test_iso_8601,
+    };
+
+    #[test]
+    fn keypair_from_same_seed_is_the_same() {
+        assert_eq!(
+            test_keypair("alice").address(),
+            test_keypair("alice").address()
+        );
+        assert_ne!(
+            test_keypair("alice").address(),
+            test_keypair("bob").address()
+        );
+    }
+
+    #[test]
+    fn address_can_be_parsed_as_key_buffer() {
+        let keys = test_keypair("alice");
+        let key_buffer = KeyBuffer::with_corrected(&String::from(keys.address())).unwrap();
+        assert_eq!(key_buffer.render(), keys.public_key().render());
+        assert_eq!(keys.agent_id("alice").key, String::from(keys.address()));
+    }
+
+    #[test]
+    fn signature_can_be_verified() {
+        let mut keys = test_keypair("alice");
+        let signature = keys.sign("some data").unwrap();
+        assert!(verify(&keys.address(), "some data", &signature).unwrap());
+    }
+
+    #[test]
+    fn signature_of_other_data_or_agent_fails() {
+        let mut keys = test_keypair("alice");
+        let signature = keys.sign("some data").unwrap();
+        assert!(!verify(&keys.address(), "other data", &signature).unwrap());
+        assert!(!verify(&test_keypair("bob").address(), "some data", &signature).unwrap());
+        assert!(!verify(&keys.address(), "some data", &Signature::from("")).unwrap());
+    }
+
+    /// Builds a header for `entry` signed by `keys`
+    fn signed_header(
+        keys: &mut Keypair,
+        entry: &Entry,
+        link: Option<Address>,
+        crud_link: Option<Address>,
+    ) -> ChainHeader {
+        let unsigned = ChainHeader::new(
+            &entry.entry_type(),
+            &entry.address(),
+            &vec![keys.address()],
+            &Vec::new(),
+            &link,
+            &None,
+            &crud_link,
+            &test_iso_8601(),
+        );
+        let signature = keys.sign(&header_signed_data(&unsigned)).unwrap();
+        with_signatures(&unsigned, &vec![signature])
+    }
+
+    #[test]
+    fn verify_header_checks_signature_and_entry() {
+        let mut keys = test_keypair("alice");
+        let entry = test_entry();
+        let header = signed_header(&mut keys, &entry, None, None);
+        let agent_key = keys.address();
+        assert_eq!(verify_header(&entry, &header, &agent_key), Ok(()));
+        assert!(verify_header(&test_entry_b(), &header, &agent_key).is_err());
+
+        let forged_header = ChainHeader::new(
+            &entry.entry_type(),
+            &entry.address(),
+            &vec![test_keypair("bob").address()],
+            header.entry_signatures(),
+            &None,
+            &None,
+            &None,
+            &test_iso_8601(),
+        );
+        assert!(verify_header(&entry, &forged_header, &agent_key).is_err());
+        assert!(verify_header(&entry, &test_chain_header(), &agent_key).is_err());
+
+        // the signature does not carry over to the entry address alone
+        let bare_signature = keys.sign(&entry.address().to_string()).unwrap();
+        let bare_header = with_signatures(&header, &vec![bare_signature]);
+        assert!(verify_header(&entry, &bare_header, &agent_key).is_err());
+    }
+
+    #[test]
+    fn verify_header_checks_the_agent_key_in_effect() {
+        let mut keys = test_keypair("alice");
+        let entry = test_entry();
+        let header = signed_header(&mut keys, &entry, None, None);
+        assert!(verify_header(&entry, &header, &test_keypair("bob").address()).is_err());
+
+        // a header signed with a key that got rotated away is not accepted any more
+        let (rotated_keys, _) = keys.rotate().unwrap();
+        assert!(verify_header(&entry, &header, &rotated_keys.address()).is_err());
+    }
+
+    #[test]
+    fn verify_header_covers_the_links_of_the_header() {
+        let mut keys = test_keypair("alice");
+        let entry = test_entry();
+        let header = signed_header(&mut keys, &entry, None, None);
+        let relinked_header = ChainHeader::new(
+            &entry.entry_type(),
+            &entry.address(),
+            header.sources(),
+            header.entry_signatures(),
+            &Some(Address::from("some other header")),
+            &None,
+            &None,
+            &test_iso_8601(),
+        );
+        assert!(verify_header(&entry, &relinked_header, &keys.address()).is_err());
+        assert!(header_signed_data(&header).starts_with(HEADER_SIGNATURE_DOMAIN));
+    }
+
+    #[test]
+    fn verify_header_checks_signer_of_agent_entries() {
+        let mut keys = test_keypair("alice");
+        let (mut next_keys, _) = keys.rotate().unwrap();
+        let header_signed_by = |keys: &mut Keypair, entry: &Entry, crud_link: Option<Address>| {
+            signed_header(keys, entry, None, crud_link)
+        };
+
+        let agent_entry = Entry::AgentId(keys.agent_id("alice"));
+        let old_address = keys.address();
+        let header = header_signed_by(&mut keys, &agent_entry, None);
+        assert_eq!(verify_header(&agent_entry, &header, &old_address), Ok(()));
+
+        let rotated_agent_entry = Entry::AgentId(next_keys.agent_id("alice"));
+        let header = header_signed_by(&mut keys, &rotated_agent_entry, Some(old_address.clone()));
+        assert_eq!(
+            verify_header(&rotated_agent_entry, &header, &old_address),
+            Ok(())
+        );
+        assert_eq!(
+            agent_key_after(&rotated_agent_entry, &old_address),
+            next_keys.address()
+        );
+        assert_eq!(agent_key_after(&test_entry(), &old_address), old_address);
+
+        let header = header_signed_by(
+            &mut next_keys,
+            &rotated_agent_entry,
+            Some(old_address.clone()),
+        );
+        assert!(verify_header(&rotated_agent_entry, &header, &old_address).is_err());
+        let header = header_signed_by(&mut keys, &rotated_agent_entry, None);
+        assert!(verify_header(&rotated_agent_entry, &header, &old_address).is_err());
+    }
+
+    fn signed_agent_header(
+        keys: &mut Keypair,
+        agent_entry: &Entry,
+        previous: Option<&ChainHeader>,
+        crud_link: Option<Address>,
+    ) -> ChainHeader {
+        let previous = previous.map(|header| header.address());
+        let unsigned = ChainHeader::new(
+            &agent_entry.entry_type(),
+            &agent_entry.address(),
+            &vec![keys.address()],
+            &Vec::new(),
+            &previous,
+            &previous,
+            &crud_link,
+            &test_iso_8601(),
+        );
+        let signature = keys.sign(&header_signed_data(&unsigned)).unwrap();
+        with_signatures(&unsigned, &vec![signature])
+    }
+
+    #[test]
+    fn agent_key_after_headers_follows_rotations() {
+        let mut keys = test_keypair("alice");
+        let (mut next_keys, _) = keys.rotate().unwrap();
+        let initial_key = keys.address();
+        let initial_entry = Entry::AgentId(keys.agent_id("alice"));
+        let rotated_entry = Entry::AgentId(next_keys.agent_id("alice"));
+        let initial = signed_agent_header(&mut keys, &initial_entry, None, None);
+        let rotated = signed_agent_header(
+            &mut keys,
+            &rotated_entry,
+            Some(&initial),
+            Some(initial_key.clone()),
+        );
+
+        assert_eq!(agent_key_after_headers(&[]), Ok(None));
+        assert_eq!(
+            agent_key_after_headers(&[initial.clone()]),
+            Ok(Some(initial_key.clone()))
+        );
+        assert_eq!(
+            agent_key_after_headers(&[rotated.clone(), initial.clone()]),
+            Ok(Some(next_keys.address()))
+        );
+
+        // the rotations have to start at the initial agent entry and be in order
+        assert!(agent_key_after_headers(&[rotated.clone()]).is_err());
+        assert!(agent_key_after_headers(&[initial.clone(), rotated.clone()]).is_err());
+
+        // a rotation has to be signed by the key it replaces
+        let forged = signed_agent_header(
+            &mut next_keys,
+            &rotated_entry,
+            Some(&initial),
+            Some(initial_key),
+        );
+        assert!(agent_key_after_headers(&[forged, initial]).is_err());
+    }
+
+    #[test]
+    fn rotated_keys_are_fresh() {
+        let mut keys = test_keypair("alice");
+        let (mut next, mut seed) = keys.rotate().unwrap();
+        assert_ne!(next.address(), keys.address());
+        assert_ne!(
+            next.address(),
+            test_keypair("alice").rotate().unwrap().0.address()
+        );
+
+        // the returned seed is all it takes to restore them
+        let mut storage_key = keys.storage_key().unwrap();
+        let restored = Keypair::new_from_rotated_seed(&mut seed, &mut storage_key).unwrap();
+        assert_eq!(restored.address(), next.address());
+
+        let signature = next.sign("some data").unwrap();
+        assert!(verify(&next.address(), "some data", &signature).unwrap());
+        assert!(!verify(&keys.address(), "some data", &signature).unwrap());
+    }
+
+    #[test]
+    fn storage_key_survives_key_rotation() {
+        let mut keys = test_keypair("alice");
+        let mut storage_key = keys.storage_key().unwrap();
+        let mut next_storage_key = keys.rotate().unwrap().0.storage_key().unwrap();
+        let mut other_storage_key = test_keypair("bob").storage_key().unwrap();
+        assert_eq!(storage_key.len(), STORAGE_KEY_SIZE);
+        assert_eq!(
+            &storage_key.read_lock()[..],
+            &next_storage_key.read_lock()[..]
+        );
+        assert_ne!(
+            &storage_key.read_lock()[..],
+            &other_storage_key.read_lock()[..]
+        );
+    }
+
+    #[test]
+    fn random_keypairs_differ() {
+        assert_ne!(
+            Keypair::new_random().unwrap().address(),
+            Keypair::new_random().unwrap().address()
+        );
+    }
+}
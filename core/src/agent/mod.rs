@@ -1,7 +1,10 @@
 /// Agent is the module that handles the user’s identity and source chain for every Phenotype.
 ///
 pub mod actions;
+pub mod bundle;
+pub mod chain_archive;
 pub mod chain_store;
+pub mod keys;
 pub mod state;
 
 use crate::context::Context;
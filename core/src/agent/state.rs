@@ -1,7 +1,13 @@
 use crate::{
     action::{Action, ActionWrapper, AgentReduceFn},
-    agent::chain_store::ChainStore,
+    agent::{
+        bundle::{Bundle, BundleKey, BundledCommit},
+        chain_archive::ChainArchive,
+        chain_store::ChainStore,
+        keys::{header_signed_data, with_signatures},
+    },
     context::Context,
+    nucleus::actions::get_entry::get_entry_from_cas,
     state::State,
     workflows::get_entry_result::get_entry_result_workflow,
 };
@@ -9,15 +15,19 @@ use holochain_core_types::{
     agent::AgentId,
     cas::content::{Address, AddressableContent, Content},
     chain_header::ChainHeader,
-    entry::{entry_type::EntryType, Entry},
+    chain_migrate::ChainMigrate,
+    entry::{cap_entries::CapTokenGrant, entry_type::EntryType, Entry},
     error::{HcResult, HolochainError},
     json::*,
-    signature::Signature,
     time::Iso8601,
 };
-use holochain_wasm_utils::api_serialization::get_entry::*;
+use holochain_wasm_utils::api_serialization::{bundle::BundleOnClose, get_entry::*};
 use serde_json;
-use std::{collections::HashMap, convert::TryFrom, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    sync::Arc,
+};
 
 /// The state-slice for the Agent.
 /// Holds the agent's source chain and keys.
@@ -29,6 +39,9 @@ pub struct AgentState {
     actions: HashMap<ActionWrapper, ActionResponse>,
     chain: ChainStore,
     top_chain_header: Option<ChainHeader>,
+    /// commits waiting for the bundle they were made in to get closed,
+    /// by the zome call that started the bundle
+    bundles: HashMap<BundleKey, Bundle>,
 }
 
 impl AgentState {
@@ -38,6 +51,7 @@ impl AgentState {
             actions: HashMap::new(),
             chain,
             top_chain_header: None,
+            bundles: HashMap::new(),
         }
     }
 
@@ -46,6 +60,7 @@ impl AgentState {
             actions: HashMap::new(),
             chain,
             top_chain_header: Some(chain_header),
+            bundles: HashMap::new(),
         }
     }
 
@@ -63,6 +78,48 @@ impl AgentState {
         self.top_chain_header.clone()
     }
 
+    /// The bundle new commits of the given zome call get staged in,
+    /// if the call opened one and it has not timed out yet
+    pub fn open_bundle(&self, key: &BundleKey) -> Option<Bundle> {
+        self.bundles
+            .get(key)
+            .filter(|bundle| !bundle.is_expired())
+            .cloned()
+    }
+
+    /// Copy of this state in which the given action that moves the chain head failed with
+    /// `error` instead. Returns None for actions that can't fail that way.
+    pub(crate) fn with_failed_action(
+        &self,
+        action_wrapper: &ActionWrapper,
+        error: HolochainError,
+    ) -> Option<AgentState> {
+        let mut failed = self.clone();
+        let response = match action_wrapper.action() {
+            Action::Commit(_) => ActionResponse::Commit(Err(error)),
+            // a bundle that could not be committed is closed all the same
+            Action::CloseBundle((key, _, _)) => {
+                failed.bundles.remove(key);
+                ActionResponse::CloseBundle(Err(error))
+            }
+            Action::ImportChain(_) => ActionResponse::ImportChain(Err(error)),
+            _ => return None,
+        };
+        failed.actions.insert(action_wrapper.clone(), response);
+        Some(failed)
+    }
+
+    /// Moves the chain head of this copy of the state, so that further headers can get
+    /// built on top of the given one before the chain head moves for real
+    pub(crate) fn set_top_chain_header(&mut self, chain_header: ChainHeader) {
+        self.top_chain_header = Some(chain_header);
+    }
+
+    /// Whether any zome call has a bundle open whose commits have not reached the chain yet
+    pub fn has_open_bundles(&self) -> bool {
+        self.bundles.values().any(|bundle| !bundle.is_expired())
+    }
+
     pub fn get_agent_address(&self) -> HcResult<Address> {
         self.chain()
             .iter_type(&self.top_chain_header, &EntryType::AgentId)
@@ -95,6 +152,96 @@ impl AgentState {
             .iter_type(&self.top_chain_header(), &entry.entry_type())
             .find(|h| h.entry_address() == &entry.address())
     }
+
+    /// Returns all capability grants on the chain that have not been revoked, newest first.
+    /// A grant is revoked by a deletion entry that got committed after it.
+    pub fn get_grants(&self) -> Vec<CapTokenGrant> {
+        let mut seen = HashSet::new();
+        let mut grants = Vec::new();
+        for header in self.chain.iter(&self.top_chain_header) {
+            match header.entry_type() {
+                EntryType::Deletion => {
+                    if let Some(Entry::Deletion(deletion)) = self.get_chain_entry(&header) {
+                        seen.insert(deletion.deleted_entry_address());
+                    }
+                }
+                EntryType::CapTokenGrant => {
+                    if seen.insert(header.entry_address().clone()) {
+                        if let Some(Entry::CapTokenGrant(grant)) = self.get_chain_entry(&header) {
+                            grants.push(grant);
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+        grants
+    }
+
+    /// Returns the grant a capability token refers to, i.e. the grant with the token as address,
+    /// if it is on the chain and has not been revoked.
+    pub fn get_grant(&self, token: &Address) -> Option<CapTokenGrant> {
+        for header in self.chain.iter(&self.top_chain_header) {
+            match (header.entry_type(), self.get_chain_entry(&header)) {
+                (EntryType::CapTokenGrant, Some(Entry::CapTokenGrant(grant))) => {
+                    if header.entry_address() == token {
+                        return Some(grant);
+                    }
+                }
+                (EntryType::Deletion, Some(Entry::Deletion(deletion))) => {
+                    if deletion.deleted_entry_address() == *token {
+                        return None;
+                    }
+                }
+                _ => (),
+            }
+        }
+        None
+    }
+
+    /// Returns the migration that closed this chain, if any.
+    /// A chain is closed when its last entry is a ChainMigrate entry that names the DNA
+    /// of the chain as the old one. The same entry on the new chain does not close it.
+    pub fn closing_migration(&self) -> Option<ChainMigrate> {
+        let top_chain_header = self.top_chain_header.as_ref()?;
+        if top_chain_header.entry_type() != &EntryType::ChainMigrate {
+            return None;
+        }
+        let dna_header = self
+            .chain
+            .iter_type(&self.top_chain_header, &EntryType::Dna)
+            .nth(0)?;
+        match (
+            self.get_chain_entry(&dna_header),
+            self.get_chain_entry(top_chain_header),
+        ) {
+            (Some(Entry::Dna(dna)), Some(Entry::ChainMigrate(migrate))) => {
+                if migrate.old_dna_address() == &dna.address() {
+                    Some(migrate)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// A closed chain does not take any more commits.
+    pub(crate) fn check_not_closed(&self) -> Result<(), HolochainError> {
+        match self.closing_migration() {
+            Some(migrate) => Err(HolochainError::ErrorGeneric(format!(
+                "Source chain is closed: it got migrated to DNA {}",
+                migrate.new_dna_address()
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    fn get_chain_entry(&self, header: &ChainHeader) -> Option<Entry> {
+        get_entry_from_cas(&self.chain.content_storage(), header.entry_address())
+            .ok()
+            .and_then(|maybe_entry| maybe_entry)
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, DefaultJson)]
@@ -154,26 +301,39 @@ pub enum ActionResponse {
     GetEntry(Option<Entry>),
     GetLinks(Result<Vec<Address>, HolochainError>),
     LinkEntries(Result<Entry, HolochainError>),
+    StartBundle(Result<(), HolochainError>),
+    CloseBundle(Result<Vec<BundledCommit>, HolochainError>),
+    ImportChain(Result<Address, HolochainError>),
 }
 
 pub fn create_new_chain_header(
     entry: &Entry,
     context: Arc<Context>,
     crud_link: &Option<Address>,
-) -> ChainHeader {
+) -> Result<ChainHeader, HolochainError> {
     let agent_state = context
         .state()
         .expect("create_new_chain_header called without state")
         .agent();
+    chain_header_on_top_of(&agent_state, entry, &context, crud_link)
+}
+
+/// Builds the header for `entry` as the next one on the chain of the given agent state.
+/// Fails if our agent can not sign it, as other nodes would refuse to hold it.
+pub(crate) fn chain_header_on_top_of(
+    agent_state: &AgentState,
+    entry: &Entry,
+    context: &Arc<Context>,
+    crud_link: &Option<Address>,
+) -> Result<ChainHeader, HolochainError> {
     let agent_address = agent_state
         .get_agent_address()
         .unwrap_or(context.agent_id.address());
-    ChainHeader::new(
+    let unsigned_header = ChainHeader::new(
         &entry.entry_type(),
         &entry.address(),
         &vec![agent_address],
-        // @TODO signatures
-        &vec![Signature::from("")],
+        &Vec::new(),
         &agent_state
             .top_chain_header
             .clone()
@@ -186,7 +346,18 @@ pub fn create_new_chain_header(
         crud_link,
         // @TODO timestamp
         &Iso8601::from(""),
-    )
+    );
+    // The signature covers the whole header, i.e. the entry and the position in the chain.
+    let signature = context
+        .sign(&header_signed_data(&unsigned_header))
+        .map_err(|error| {
+            HolochainError::ErrorGeneric(format!(
+                "Could not sign header for {}: {}",
+                unsigned_header.entry_address(),
+                error
+            ))
+        })?;
+    Ok(with_signatures(&unsigned_header, &vec![signature]))
 }
 
 /// Do a Commit Action against an agent state.
@@ -195,8 +366,7 @@ pub fn create_new_chain_header(
 /// action reduction to hang
 /// @TODO is there a way to reduce that doesn't block indefinitely on callback fns?
 /// @see https://github.com/holochain/holochain-rust/issues/222
-/// @TODO Better error handling in the state persister section
-/// https://github.com/holochain/holochain-rust/issues/555
+/// The new chain head gets persisted by the instance after reduction.
 fn reduce_commit_entry(
     context: Arc<Context>,
     state: &mut AgentState,
@@ -204,7 +374,21 @@ fn reduce_commit_entry(
 ) {
     let action = action_wrapper.action();
     let (entry, maybe_crud_link) = unwrap_to!(action => Action::Commit);
-    let chain_header = create_new_chain_header(&entry, context.clone(), &maybe_crud_link);
+    if let Err(error) = state.check_not_closed() {
+        state
+            .actions
+            .insert(action_wrapper.clone(), ActionResponse::Commit(Err(error)));
+        return;
+    }
+    let chain_header = match create_new_chain_header(&entry, context.clone(), &maybe_crud_link) {
+        Ok(chain_header) => chain_header,
+        Err(error) => {
+            state
+                .actions
+                .insert(action_wrapper.clone(), ActionResponse::Commit(Err(error)));
+            return;
+        }
+    };
 
     fn response(
         state: &mut AgentState,
@@ -218,24 +402,184 @@ fn reduce_commit_entry(
     }
     let result = response(state, &entry, &chain_header);
     state.top_chain_header = Some(chain_header);
-    let con = context.clone();
 
-    #[allow(unused_must_use)]
-    con.state().map(|global_state_lock| {
-        let persis_lock = context.clone().persister.clone();
-        let persister = &mut *persis_lock.lock().unwrap();
-        persister.save(global_state_lock.clone());
-    });
+    state
+        .actions
+        .insert(action_wrapper.clone(), ActionResponse::Commit(result));
+}
 
+/// Opens a new bundle for a zome call, replacing an expired one.
+/// Fails if the call already has an open bundle.
+fn reduce_start_bundle(
+    _context: Arc<Context>,
+    state: &mut AgentState,
+    action_wrapper: &ActionWrapper,
+) {
+    let action = action_wrapper.action();
+    let (key, bundle) = unwrap_to!(action => Action::StartBundle);
+    let result = if state.open_bundle(key).is_some() {
+        Err(HolochainError::ErrorGeneric(
+            "A bundle is already open".to_string(),
+        ))
+    } else {
+        state.bundles.insert(*key, bundle.clone());
+        Ok(())
+    };
+    state
+        .actions
+        .insert(action_wrapper.clone(), ActionResponse::StartBundle(result));
+}
+
+/// Adds a validated entry to the open bundle of a zome call instead of the chain.
+fn reduce_stage_commit(
+    _context: Arc<Context>,
+    state: &mut AgentState,
+    action_wrapper: &ActionWrapper,
+) {
+    let action = action_wrapper.action();
+    let (key, commit) = unwrap_to!(action => Action::StageCommit);
+    let result = match state.bundles.get_mut(key) {
+        Some(ref mut bundle) if !bundle.is_expired() => {
+            bundle.stage(commit.clone());
+            Ok(commit.entry.address())
+        }
+        _ => Err(HolochainError::ErrorGeneric("No open bundle".to_string())),
+    };
     state
         .actions
         .insert(action_wrapper.clone(), ActionResponse::Commit(result));
 }
 
+/// Closes the open bundle of a zome call, committing or dropping its staged entries.
+/// Committing moves the chain head to the stored headers of the staged entries in one go.
+/// The response holds the commits that made it to the chain.
+fn reduce_close_bundle(
+    _context: Arc<Context>,
+    state: &mut AgentState,
+    action_wrapper: &ActionWrapper,
+) {
+    let action = action_wrapper.action();
+    let (key, on_close, chain_headers) = unwrap_to!(action => Action::CloseBundle);
+    let result = match (state.bundles.remove(key), on_close) {
+        (None, _) => Err(HolochainError::ErrorGeneric("No open bundle".to_string())),
+        (Some(_), BundleOnClose::Discard) => Ok(Vec::new()),
+        (Some(ref bundle), BundleOnClose::Commit) if bundle.is_expired() => {
+            Err(HolochainError::Timeout)
+        }
+        (Some(bundle), BundleOnClose::Commit) => {
+            let commits = bundle.commits();
+            if let Err(error) = state.check_not_closed() {
+                Err(error)
+            } else if !follows_chain_head(state, &commits, chain_headers) {
+                // the headers have to be built again on top of the new chain head
+                state.bundles.insert(*key, bundle);
+                Err(HolochainError::ErrorGeneric(
+                    "Source chain moved on while closing the bundle".to_string(),
+                ))
+            } else {
+                if let Some(chain_header) = chain_headers.last() {
+                    state.top_chain_header = Some(chain_header.clone());
+                }
+                Ok(commits)
+            }
+        }
+    };
+    state
+        .actions
+        .insert(action_wrapper.clone(), ActionResponse::CloseBundle(result));
+}
+
+/// A bundle does not outlive the zome call that started it.
+/// If the call returns without closing it, the bundle is discarded.
+fn reduce_return_zome_function_result(
+    _context: Arc<Context>,
+    state: &mut AgentState,
+    action_wrapper: &ActionWrapper,
+) {
+    let action = action_wrapper.action();
+    let response = unwrap_to!(action => Action::ReturnZomeFunctionResult);
+    state.bundles.remove(&response.call().id());
+}
+
+/// Whether the given headers put the given commits, in order, on top of the chain head.
+fn follows_chain_head(
+    state: &AgentState,
+    commits: &[BundledCommit],
+    chain_headers: &[ChainHeader],
+) -> bool {
+    let mut previous = state
+        .top_chain_header
+        .as_ref()
+        .map(|chain_header| chain_header.address());
+    commits.len() == chain_headers.len()
+        && commits
+            .iter()
+            .zip(chain_headers)
+            .all(|(commit, chain_header)| {
+                let follows = chain_header.link() == previous
+                    && chain_header.entry_address() == &commit.entry.address();
+                previous = Some(chain_header.address());
+                follows
+            })
+}
+
+/// Appends the part of an archived chain that is not on the source chain yet.
+/// The source chain has to be the beginning of the archived chain.
+fn reduce_import_chain(
+    _context: Arc<Context>,
+    state: &mut AgentState,
+    action_wrapper: &ActionWrapper,
+) {
+    let action = action_wrapper.action();
+    let archive = unwrap_to!(action => Action::ImportChain);
+    let result = import_chain(state, archive);
+    state
+        .actions
+        .insert(action_wrapper.clone(), ActionResponse::ImportChain(result));
+}
+
+fn import_chain(state: &mut AgentState, archive: &ChainArchive) -> HcResult<Address> {
+    state.check_not_closed()?;
+    let top_chain_header = archive
+        .top_chain_header()
+        .ok_or_else(|| HolochainError::ErrorGeneric("Chain archive is empty".to_string()))?
+        .clone();
+    // Headers link to each other by hash, so finding our top header in the
+    // archive means that our whole chain is part of it.
+    let known = match state.top_chain_header {
+        None => 0,
+        Some(ref header) => {
+            let address = header.address();
+            archive
+                .chain()
+                .iter()
+                .position(|element| element.header.address() == address)
+                .map(|position| position + 1)
+                .ok_or_else(|| {
+                    HolochainError::ErrorGeneric(
+                        "Source chain is not part of the archived chain".to_string(),
+                    )
+                })?
+        }
+    };
+    let storage = state.chain.content_storage();
+    for element in archive.chain()[known..].iter() {
+        storage.write()?.add(&element.entry)?;
+        storage.write()?.add(&element.header)?;
+    }
+    state.top_chain_header = Some(top_chain_header.clone());
+    Ok(top_chain_header.address())
+}
+
 /// maps incoming action to the correct handler
 fn resolve_reducer(action_wrapper: &ActionWrapper) -> Option<AgentReduceFn> {
     match action_wrapper.action() {
         Action::Commit(_) => Some(reduce_commit_entry),
+        Action::StartBundle(_) => Some(reduce_start_bundle),
+        Action::StageCommit(_) => Some(reduce_stage_commit),
+        Action::CloseBundle(_) => Some(reduce_close_bundle),
+        Action::ReturnZomeFunctionResult(_) => Some(reduce_return_zome_function_result),
+        Action::ImportChain(_) => Some(reduce_import_chain),
         _ => None,
     }
 }
@@ -260,15 +604,23 @@ pub fn reduce(
 #[cfg(test)]
 pub mod tests {
     extern crate tempfile;
+    extern crate test_utils;
     use super::{reduce_commit_entry, ActionResponse, AgentState, AgentStateSnapshot};
     use crate::{
-        action::tests::test_action_wrapper_commit, agent::chain_store::tests::test_chain_store,
-        instance::tests::test_context, state::State,
+        action::tests::test_action_wrapper_commit,
+        agent::{actions::commit::commit_entry, chain_store::tests::test_chain_store},
+        instance::tests::{test_context, test_instance_and_context},
+        state::State,
     };
+    use futures::executor::block_on;
     use holochain_core_types::{
-        cas::content::AddressableContent,
+        cas::content::{Address, AddressableContent},
         chain_header::test_chain_header,
-        entry::{expected_entry_address, test_entry, Entry},
+        dna::capabilities::CapabilityType,
+        entry::{
+            cap_entries::CapTokenGrant, deletion_entry::DeletionEntry, expected_entry_address,
+            test_entry, Entry,
+        },
         error::HolochainError,
         json::JsonString,
     };
@@ -300,6 +652,45 @@ pub mod tests {
         assert_eq!(HashMap::new(), test_agent_state().actions());
     }
 
+    #[test]
+    /// test that grants are listed until they get revoked by a deletion entry, for good
+    fn test_get_grants_honours_revocation() {
+        let dna = test_utils::create_test_dna_with_wat("test_zome", "test_cap", None);
+        let (_instance, context) = test_instance_and_context(dna, None).unwrap();
+        let grant_a =
+            CapTokenGrant::create("test_cap", CapabilityType::Transferable, None).unwrap();
+        let grant_b = CapTokenGrant::create(
+            "test_cap",
+            CapabilityType::Assigned,
+            Some(vec![Address::from("bob")]),
+        )
+        .unwrap();
+        let commit = |entry: Entry| block_on(commit_entry(entry, None, &context)).unwrap();
+        let token_a = commit(Entry::CapTokenGrant(grant_a.clone()));
+        let token_b = commit(Entry::CapTokenGrant(grant_b.clone()));
+        assert_eq!(token_a, grant_a.token());
+
+        let agent = context.state().unwrap().agent();
+        assert_eq!(agent.get_grants(), vec![grant_b.clone(), grant_a.clone()]);
+        assert_eq!(agent.get_grant(&token_a), Some(grant_a.clone()));
+
+        commit(Entry::Deletion(DeletionEntry::new(token_a.clone())));
+        let agent = context.state().unwrap().agent();
+        assert_eq!(agent.get_grants(), vec![grant_b.clone()]);
+        assert_eq!(agent.get_grant(&token_a), None);
+        assert_eq!(agent.get_grant(&token_b), Some(grant_b.clone()));
+
+        // granting the same capability again issues a new token, the revoked one stays dead
+        let grant_c =
+            CapTokenGrant::create("test_cap", CapabilityType::Transferable, None).unwrap();
+        let token_c = commit(Entry::CapTokenGrant(grant_c.clone()));
+        assert_ne!(token_c, token_a);
+        let agent = context.state().unwrap().agent();
+        assert_eq!(agent.get_grants(), vec![grant_c.clone(), grant_b]);
+        assert_eq!(agent.get_grant(&token_a), None);
+        assert_eq!(agent.get_grant(&token_c), Some(grant_c));
+    }
+
     #[test]
     /// test for reducing commit entry
     fn test_reduce_commit_entry() {
@@ -321,6 +712,30 @@ pub mod tests {
         );
     }
 
+    #[test]
+    /// test that entries our agent can not sign for do not make it to the chain
+    fn test_reduce_commit_entry_without_keys() {
+        let mut agent_state = test_agent_state();
+        let netname = Some("test_reduce_commit_entry_without_keys");
+        let context = test_context("bob", netname);
+        let state = State::new_with_agent(context, Arc::new(agent_state.clone()));
+        let mut context = test_context("bob", netname);
+        {
+            let context = Arc::get_mut(&mut context).unwrap();
+            context.set_state(Arc::new(RwLock::new(state)));
+            context.agent_keys = None;
+        }
+        let action_wrapper = test_action_wrapper_commit();
+
+        reduce_commit_entry(context, &mut agent_state, &action_wrapper);
+
+        match agent_state.actions().get(&action_wrapper) {
+            Some(ActionResponse::Commit(Err(_))) => {}
+            response => panic!("unexpected commit response {:?}", response),
+        }
+        assert_eq!(agent_state.top_chain_header(), None);
+    }
+
     #[test]
     /// test response to json
     fn test_commit_response_to_json() {
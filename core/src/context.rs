@@ -1,5 +1,6 @@
 use crate::{
     action::ActionWrapper,
+    agent::keys::{Keypair, RotatedKeyStore},
     instance::Observer,
     logger::Logger,
     persister::Persister,
@@ -16,6 +17,7 @@ use holochain_core_types::{
     eav::EntityAttributeValueStorage,
     error::{HcResult, HolochainError},
     json::JsonString,
+    signature::Signature,
 };
 use holochain_net::p2p_config::P2pConfig;
 use jsonrpc_ws_server::jsonrpc_core::IoHandler;
@@ -32,6 +34,7 @@ use std::{
 #[derive(Clone)]
 pub struct Context {
     pub agent_id: AgentId,
+    pub agent_keys: Option<Arc<Mutex<Keypair>>>,
     pub logger: Arc<Mutex<Logger>>,
     pub persister: Arc<Mutex<Persister>>,
     state: Option<Arc<RwLock<State>>>,
@@ -43,6 +46,14 @@ pub struct Context {
     pub network_config: JsonString,
     pub container_api: Option<Arc<RwLock<IoHandler>>>,
     pub signal_tx: Option<SyncSender<Signal>>,
+    /// Context of the instance whose source chain got migrated to this one, if any
+    pub migrated_from: Option<Arc<Context>>,
+    /// Where the keys the agent rotated to get stored. Without it, rotated keys
+    /// only live in memory.
+    pub rotated_key_store: Option<Arc<RotatedKeyStore>>,
+    /// Number of bytes the entries held in the DHT shard for others may take up.
+    /// Garbage collection drops the least recently requested ones beyond it.
+    pub dht_quota: Option<u64>,
 }
 
 impl Context {
@@ -63,6 +74,7 @@ impl Context {
     ) -> Self {
         Context {
             agent_id,
+            agent_keys: None,
             logger,
             persister,
             state: None,
@@ -74,6 +86,9 @@ impl Context {
             eav_storage: eav,
             network_config,
             container_api,
+            migrated_from: None,
+            rotated_key_store: None,
+            dht_quota: None,
         }
     }
 
@@ -90,6 +105,7 @@ impl Context {
     ) -> Result<Context, HolochainError> {
         Ok(Context {
             agent_id,
+            agent_keys: None,
             logger,
             persister,
             state: None,
@@ -101,6 +117,9 @@ impl Context {
             eav_storage: eav,
             network_config,
             container_api: None,
+            migrated_from: None,
+            rotated_key_store: None,
+            dht_quota: None,
         })
     }
 
@@ -118,6 +137,57 @@ impl Context {
         self.state = Some(state);
     }
 
+    /// Sets the private keys of this context's agent.
+    /// Without keys, headers can't be signed and other nodes will refuse to hold our entries.
+    pub fn set_agent_keys(&mut self, keys: Keypair) {
+        self.agent_keys = Some(Arc::new(Mutex::new(keys)));
+    }
+
+    /// Sets the context of the instance whose closed source chain this context's chain continues.
+    /// Zome code can query the old chain through it.
+    pub fn set_migrated_from(&mut self, old_context: Arc<Context>) {
+        self.migrated_from = Some(old_context);
+    }
+
+    /// Signs the given data with the private key of this context's agent.
+    pub fn sign(&self, data: &str) -> HcResult<Signature> {
+        let keys = self.agent_keys.as_ref().ok_or(HolochainError::ErrorGeneric(
+            "Agent has no keys to sign with".to_string(),
+        ))?;
+        let mut keys = keys.lock()?;
+        keys.sign(data)
+    }
+
+    /// Sets where the keys this context's agent rotates to get stored.
+    pub fn set_rotated_key_store(&mut self, store: Arc<RotatedKeyStore>) {
+        self.rotated_key_store = Some(store);
+    }
+
+    /// Creates the keys that will replace the current keys of this context's agent
+    /// on the next key rotation and stores them, without switching to them yet.
+    /// Fails without a rotated key store, as the keys would be lost on restart.
+    pub fn next_agent_keys(&self) -> HcResult<Keypair> {
+        let keys = self.agent_keys.as_ref().ok_or(HolochainError::ErrorGeneric(
+            "Agent has no keys to rotate".to_string(),
+        ))?;
+        let store = self.rotated_key_store.as_ref().ok_or(HolochainError::ErrorGeneric(
+            "Agent has no store for rotated keys, they would be lost on restart".to_string(),
+        ))?;
+        let (mut next_keys, mut seed) = keys.lock()?.rotate()?;
+        store.add(&mut next_keys, &mut seed)?;
+        Ok(next_keys)
+    }
+
+    /// Switches to new keys for this context's agent. Since the keys are shared, this
+    /// affects all contexts derived from the same context.
+    pub fn replace_agent_keys(&self, new_keys: Keypair) -> HcResult<()> {
+        let keys = self.agent_keys.as_ref().ok_or(HolochainError::ErrorGeneric(
+            "Agent has no keys to rotate".to_string(),
+        ))?;
+        *keys.lock()? = new_keys;
+        Ok(())
+    }
+
     pub fn state(&self) -> Option<RwLockReadGuard<State>> {
         match self.state {
             None => None,
@@ -125,6 +195,14 @@ impl Context {
         }
     }
 
+    /// Address of the agent entry currently on top of this context's source chain.
+    /// Falls back to the agent the context was created for before genesis.
+    pub fn agent_address(&self) -> Address {
+        self.state()
+            .and_then(|state| state.agent().get_agent_address().ok())
+            .unwrap_or_else(|| self.agent_id.address())
+    }
+
     pub fn get_dna(&self) -> Option<Dna> {
         // In the case of genesis we encounter race conditions with regards to setting the DNA.
         // Genesis gets called asynchronously right after dispatching an action that sets the DNA in
@@ -228,8 +306,8 @@ pub mod tests {
     use self::tempfile::tempdir;
     use super::*;
     use crate::{
-        context::unique_memory_network_config, logger::test_logger, persister::SimplePersister,
-        state::State,
+        context::unique_memory_network_config, instance::tests::test_state_storage,
+        logger::test_logger, persister::SimplePersister, state::State,
     };
     use holochain_cas_implementations::{cas::file::FilesystemStorage, eav::file::EavFileStorage};
     use holochain_core_types::agent::AgentId;
@@ -248,7 +326,9 @@ pub mod tests {
         let mut maybe_context = Context::new(
             AgentId::generate_fake("Terence"),
             test_logger(),
-            Arc::new(Mutex::new(SimplePersister::new(file_storage.clone()))),
+            Arc::new(Mutex::new(
+                SimplePersister::new(test_state_storage()).unwrap(),
+            )),
             file_storage.clone(),
             file_storage.clone(),
             Arc::new(RwLock::new(
@@ -281,7 +361,9 @@ pub mod tests {
         let mut context = Context::new(
             AgentId::generate_fake("Terence"),
             test_logger(),
-            Arc::new(Mutex::new(SimplePersister::new(file_storage.clone()))),
+            Arc::new(Mutex::new(
+                SimplePersister::new(test_state_storage()).unwrap(),
+            )),
             file_storage.clone(),
             file_storage.clone(),
             Arc::new(RwLock::new(
@@ -0,0 +1,57 @@
+use crate::{
+    action::{Action, ActionWrapper},
+    context::Context,
+    instance::dispatch_action_and_wait,
+};
+use holochain_core_types::{
+    cas::content::{Address, AddressableContent},
+    error::HolochainError,
+};
+use std::{collections::BTreeSet, sync::Arc};
+
+/// Tells the DHT reducer how much it may keep and what it must never drop
+#[derive(Clone, Debug, PartialEq)]
+pub struct GarbageCollection {
+    /// Number of bytes the entries held for others, with their meta data, may take up
+    pub quota: u64,
+    /// Headers and entries of the agent's own source chain
+    pub keep: BTreeSet<Address>,
+}
+
+/// Collect Garbage Action Creator
+/// Drops the least recently requested entries the local DHT shard holds for others,
+/// together with their meta data, until the rest fits into the instance's DHT quota.
+/// Entries of the agent's own source chain are never dropped.
+/// The collection only runs once the held data outgrows the quota and then makes room
+/// for another quarter of it, so that it is not due again with the next entry.
+/// Does nothing if the instance has no DHT quota.
+pub fn collect_garbage(context: &Arc<Context>) -> Result<(), HolochainError> {
+    let quota = match context.dht_quota {
+        Some(quota) => quota,
+        None => return Ok(()),
+    };
+    let keep = {
+        let state = context
+            .state()
+            .ok_or_else(|| HolochainError::ErrorGeneric("State not initialized".to_string()))?;
+        if !state.dht().needs_garbage_collection(quota) {
+            return Ok(());
+        }
+        let agent = state.agent();
+        let mut keep = BTreeSet::new();
+        for header in agent.chain().iter(&agent.top_chain_header()) {
+            keep.insert(header.entry_address().clone());
+            keep.insert(header.address());
+        }
+        keep
+    };
+    dispatch_action_and_wait(
+        context.action_channel(),
+        context.observer_channel(),
+        ActionWrapper::new(Action::CollectGarbage(GarbageCollection {
+            quota: quota / 4 * 3,
+            keep,
+        })),
+    );
+    Ok(())
+}
@@ -4,6 +4,7 @@ use crate::{
     action::{Action, ActionWrapper},
     context::Context,
     instance::dispatch_action,
+    network::encrypted_entry::EncryptedEntry,
 };
 use futures::{
     future::Future,
@@ -29,6 +30,22 @@ pub async fn hold_entry<'a>(
     })
 }
 
+/// Holds an entry of an encrypted entry type without its plaintext.
+/// Resolves to the address of the plaintext entry.
+pub async fn hold_encrypted_entry<'a>(
+    encrypted_entry: &'a EncryptedEntry,
+    context: &'a Arc<Context>,
+) -> Result<Address, HolochainError> {
+    let action_wrapper = ActionWrapper::new(Action::HoldEncrypted(encrypted_entry.clone()));
+    dispatch_action(context.action_channel(), action_wrapper.clone());
+
+    await!(HoldEntryFuture {
+        context: context.clone(),
+        address: encrypted_entry.address(),
+    })?;
+    Ok(encrypted_entry.entry_address().clone())
+}
+
 pub struct HoldEntryFuture {
     context: Arc<Context>,
     address: Address,
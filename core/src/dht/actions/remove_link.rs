@@ -0,0 +1,110 @@
+extern crate futures;
+extern crate serde_json;
+use crate::{
+    action::{Action, ActionWrapper},
+    context::Context,
+    instance::dispatch_action,
+};
+use futures::{
+    future::Future,
+    task::{LocalWaker, Poll},
+};
+use holochain_core_types::{error::HolochainError, link::Link};
+use std::{pin::Pin, sync::Arc};
+
+/// RemoveLink Action Creator
+/// This action creator dispatches a RemoveLink action which is consumed by the DHT reducer.
+/// Note that this function does not include any validation checks for the link removal.
+/// Like with AddLink, the DHT reducer only tombstones links of a base that it has in its
+/// local storage and will return an error that the RemoveLinkFuture resolves to
+/// if that is not the case.
+///
+/// Returns a future that resolves to an Ok(()) or an Err(HolochainError).
+pub fn remove_link(link: &Link, context: &Arc<Context>) -> RemoveLinkFuture {
+    let action_wrapper = ActionWrapper::new(Action::RemoveLink(link.clone()));
+    dispatch_action(context.action_channel(), action_wrapper.clone());
+
+    RemoveLinkFuture {
+        context: context.clone(),
+        action: action_wrapper,
+    }
+}
+
+pub struct RemoveLinkFuture {
+    context: Arc<Context>,
+    action: ActionWrapper,
+}
+
+impl Future for RemoveLinkFuture {
+    type Output = Result<(), HolochainError>;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<Self::Output> {
+        //
+        // TODO: connect the waker to state updates for performance reasons
+        // See: https://github.com/holochain/holochain-rust/issues/314
+        //
+        lw.wake();
+        if let Some(state) = self.context.state() {
+            match state.dht().actions().get(&self.action) {
+                Some(Ok(_)) => Poll::Ready(Ok(())),
+                Some(Err(e)) => Poll::Ready(Err(e.clone())),
+                None => Poll::Pending,
+            }
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dht::actions::add_link::add_link, nucleus};
+
+    use futures::executor::block_on;
+    use holochain_core_types::{cas::content::AddressableContent, link::Link};
+    use holochain_wasm_utils::api_serialization::get_links::{
+        LinkTagMatch, LinksStatusRequestKind,
+    };
+
+    #[test]
+    fn can_remove_link() {
+        let (_instance, context) = nucleus::actions::tests::instance(None);
+
+        let base = nucleus::actions::tests::test_entry_package_entry();
+        nucleus::actions::tests::commit(base.clone(), &context);
+
+        let link = Link::new(&base.address(), &base.address(), "test-tag");
+        block_on(add_link(&link, &context)).unwrap();
+
+        let result = block_on(remove_link(&link, &context));
+        assert!(result.is_ok(), "result = {:?}", result);
+
+        let links = context
+            .state()
+            .unwrap()
+            .dht()
+            .get_links(
+                base.address(),
+                String::from("test-tag"),
+                LinkTagMatch::Exact,
+                LinksStatusRequestKind::Live,
+            )
+            .unwrap();
+        assert!(links.is_empty());
+    }
+
+    #[test]
+    fn errors_when_link_base_not_present() {
+        let (_instance, context) = nucleus::actions::tests::instance(None);
+
+        let base = nucleus::actions::tests::test_entry_package_entry();
+        let link = Link::new(&base.address(), &base.address(), "test-tag");
+
+        let result = block_on(remove_link(&link, &context));
+        assert_eq!(
+            result.err().unwrap(),
+            HolochainError::ErrorGeneric(String::from("Base for link not found"))
+        );
+    }
+}
@@ -3,7 +3,12 @@
 use crate::{
     action::{Action, ActionWrapper},
     context::Context,
-    dht::dht_store::DhtStore,
+    dht::dht_store::{DhtStore, LINK_PREFIX, REMOVED_LINK_PREFIX},
+    network::{
+        encrypted_entry::ENCRYPTED_ENTRY_NAME,
+        gossip::{ASPECT_NAME, REJECTED_ASPECT_NAME},
+        validation_receipt::VALIDATION_RECEIPT_NAME,
+    },
 };
 use holochain_core_types::{
     cas::content::{Address, AddressableContent},
@@ -11,6 +16,7 @@ use holochain_core_types::{
     eav::{EntityAttributeValueIndex, IndexQuery},
     entry::Entry,
     error::HolochainError,
+    link::Link,
 };
 use std::{collections::BTreeSet, convert::TryFrom, str::FromStr, sync::Arc};
 
@@ -44,9 +50,16 @@ fn resolve_reducer(action_wrapper: &ActionWrapper) -> Option<DhtReducer> {
     match action_wrapper.action() {
         Action::Commit(_) => Some(reduce_hold_entry),
         Action::Hold(_) => Some(reduce_hold_entry),
+        Action::HoldEncrypted(_) => Some(reduce_hold_encrypted_entry),
         Action::UpdateEntry(_) => Some(reduce_update_entry),
         Action::RemoveEntry(_) => Some(reduce_remove_entry),
         Action::AddLink(_) => Some(reduce_add_link),
+        Action::RemoveLink(_) => Some(reduce_remove_link),
+        Action::CollectGarbage(_) => Some(reduce_collect_garbage),
+        Action::HoldAspect(_) => Some(reduce_hold_aspect),
+        Action::RejectAspect(_) => Some(reduce_reject_aspect),
+        Action::HoldValidationReceipt(_) => Some(reduce_hold_validation_receipt),
+        Action::RecordRequest(_) => Some(reduce_record_request),
         _ => None,
     }
 }
@@ -66,7 +79,7 @@ pub(crate) fn reduce_hold_entry(
     };
 
     // Add it to local storage
-    let new_store = (*old_store).clone();
+    let mut new_store = (*old_store).clone();
     let content_storage = &new_store.content_storage().clone();
     let res = (*content_storage.write().unwrap()).add(entry).ok();
     if res.is_some() {
@@ -75,7 +88,14 @@ pub(crate) fn reduce_hold_entry(
             .map(|status_eav| {
                 let meta_res = (*meta_storage.write().unwrap()).add_eavi(&status_eav);
                 meta_res
-                    .map(|_| Some(new_store))
+                    .map(|_| {
+                        // committed entries belong to the own chain and never get collected
+                        if let Action::Hold(_) = action {
+                            new_store.count_held(entry);
+                            new_store.count_held(&status_eav);
+                        }
+                        Some(new_store)
+                    })
                     .map_err(|err| {
                         context.log(format!(
                             "err/dht: reduce_hold_entry: meta_storage write failed!: {:?}",
@@ -97,6 +117,173 @@ pub(crate) fn reduce_hold_entry(
     }
 }
 
+// Encrypted entries get stored under their own address, so they never replace the
+// plaintext entry on nodes that have both, and get linked to the plaintext address.
+pub(crate) fn reduce_hold_encrypted_entry(
+    context: Arc<Context>,
+    old_store: &DhtStore,
+    action_wrapper: &ActionWrapper,
+) -> Option<DhtStore> {
+    let action = action_wrapper.action();
+    let encrypted_entry = unwrap_to!(action => Action::HoldEncrypted);
+
+    let mut new_store = (*old_store).clone();
+    let content_storage = &new_store.content_storage().clone();
+    let meta_storage = &new_store.meta_storage().clone();
+    let entry_address = encrypted_entry.entry_address();
+    let res = (*content_storage.write().unwrap())
+        .add(encrypted_entry)
+        .and_then(|_| {
+            EntityAttributeValueIndex::new(
+                entry_address,
+                &ENCRYPTED_ENTRY_NAME.to_string(),
+                &encrypted_entry.address(),
+            )
+        })
+        .and_then(|eavi| {
+            (*meta_storage.write().unwrap()).add_eavi(&eavi)?;
+            let status_eav = create_crud_status_eav(entry_address, CrudStatus::Live)?;
+            (*meta_storage.write().unwrap()).add_eavi(&status_eav)?;
+            Ok((eavi, status_eav))
+        });
+    match res {
+        Ok((eavi, status_eav)) => {
+            new_store.count_held(encrypted_entry);
+            new_store.count_held(&eavi);
+            new_store.count_held(&status_eav);
+            Some(new_store)
+        }
+        Err(err) => {
+            context.log(format!(
+                "err/dht: dht::reduce_hold_encrypted_entry() FAILED {:?}",
+                err
+            ));
+            None
+        }
+    }
+}
+
+//
+pub(crate) fn reduce_hold_aspect(
+    context: Arc<Context>,
+    old_store: &DhtStore,
+    action_wrapper: &ActionWrapper,
+) -> Option<DhtStore> {
+    let action = action_wrapper.action();
+    let aspect = unwrap_to!(action => Action::HoldAspect);
+
+    let mut new_store = (*old_store).clone();
+    let content_storage = &new_store.content_storage().clone();
+    let meta_storage = &new_store.meta_storage().clone();
+    let res = (*content_storage.write().unwrap())
+        .add(aspect)
+        .and_then(|_| {
+            EntityAttributeValueIndex::new(
+                &aspect.held_at(),
+                &ASPECT_NAME.to_string(),
+                &aspect.address(),
+            )
+        })
+        .and_then(|eavi| {
+            (*meta_storage.write().unwrap()).add_eavi(&eavi)?;
+            Ok(eavi)
+        });
+    match res {
+        Ok(eavi) => {
+            new_store.count_held(aspect);
+            new_store.count_held(&eavi);
+            Some(new_store)
+        }
+        Err(err) => {
+            context.log(format!(
+                "err/dht: dht::reduce_hold_aspect() FAILED {:?}",
+                err
+            ));
+            None
+        }
+    }
+}
+
+//
+pub(crate) fn reduce_reject_aspect(
+    context: Arc<Context>,
+    old_store: &DhtStore,
+    action_wrapper: &ActionWrapper,
+) -> Option<DhtStore> {
+    let action = action_wrapper.action();
+    let aspect = unwrap_to!(action => Action::RejectAspect);
+
+    let mut new_store = (*old_store).clone();
+    let meta_storage = &new_store.meta_storage().clone();
+    let res = EntityAttributeValueIndex::new(
+        &aspect.held_at(),
+        &REJECTED_ASPECT_NAME.to_string(),
+        &aspect.address(),
+    )
+    .and_then(|eavi| {
+        (*meta_storage.write().unwrap()).add_eavi(&eavi)?;
+        Ok(eavi)
+    });
+    match res {
+        Ok(eavi) => {
+            new_store.count_held(&eavi);
+            Some(new_store)
+        }
+        Err(err) => {
+            context.log(format!(
+                "err/dht: dht::reduce_reject_aspect() FAILED {:?}",
+                err
+            ));
+            None
+        }
+    }
+}
+
+//
+pub(crate) fn reduce_hold_validation_receipt(
+    context: Arc<Context>,
+    old_store: &DhtStore,
+    action_wrapper: &ActionWrapper,
+) -> Option<DhtStore> {
+    let action = action_wrapper.action();
+    let receipt = unwrap_to!(action => Action::HoldValidationReceipt);
+
+    let mut new_store = (*old_store).clone();
+    let content_storage = &new_store.content_storage().clone();
+    let meta_storage = &new_store.meta_storage().clone();
+    // Holders may send the same receipt more than once
+    if (*content_storage.read().unwrap()).contains(&receipt.address()) == Ok(true) {
+        return None;
+    }
+    let res = (*content_storage.write().unwrap())
+        .add(receipt)
+        .and_then(|_| {
+            EntityAttributeValueIndex::new(
+                &receipt.entry_address,
+                &VALIDATION_RECEIPT_NAME.to_string(),
+                &receipt.address(),
+            )
+        })
+        .and_then(|eavi| {
+            (*meta_storage.write().unwrap()).add_eavi(&eavi)?;
+            Ok(eavi)
+        });
+    match res {
+        Ok(eavi) => {
+            new_store.count_held(receipt);
+            new_store.count_held(&eavi);
+            Some(new_store)
+        }
+        Err(err) => {
+            context.log(format!(
+                "err/dht: dht::reduce_hold_validation_receipt() FAILED {:?}",
+                err
+            ));
+            None
+        }
+    }
+}
+
 //
 pub(crate) fn reduce_add_link(
     _context: Arc<Context>,
@@ -106,7 +293,87 @@ pub(crate) fn reduce_add_link(
     // Get Action's input data
     let action = action_wrapper.action();
     let link = unwrap_to!(action => Action::AddLink);
+    let mut new_store = reduce_link_meta(
+        old_store,
+        action_wrapper,
+        link,
+        format!("{}{}", LINK_PREFIX, link.tag()),
+    )?;
+    let link_added = new_store
+        .actions()
+        .get(action_wrapper)
+        .map(|result| result.is_ok())
+        .unwrap_or(false);
+    if link_added && link.payload().is_some() {
+        if let Err(error) = new_store.add_link_payload(link) {
+            new_store
+                .actions_mut()
+                .insert(action_wrapper.clone(), Err(error));
+        }
+    }
+    Some(new_store)
+}
+
+// Removed links stay in the meta storage and get tombstoned by an EAV, whether it
+// arrives before or after the one that added the link.
+pub(crate) fn reduce_remove_link(
+    _context: Arc<Context>,
+    old_store: &DhtStore,
+    action_wrapper: &ActionWrapper,
+) -> Option<DhtStore> {
+    // Get Action's input data
+    let action = action_wrapper.action();
+    let link = unwrap_to!(action => Action::RemoveLink);
+    reduce_link_meta(
+        old_store,
+        action_wrapper,
+        link,
+        format!("{}{}", REMOVED_LINK_PREFIX, link.tag()),
+    )
+}
+
+pub(crate) fn reduce_collect_garbage(
+    context: Arc<Context>,
+    old_store: &DhtStore,
+    action_wrapper: &ActionWrapper,
+) -> Option<DhtStore> {
+    let action = action_wrapper.action();
+    let collection = unwrap_to!(action => Action::CollectGarbage);
+    let mut new_store = (*old_store).clone();
+    match new_store.collect_garbage(collection) {
+        Ok(ref dropped) if dropped.is_empty() => (),
+        Ok(dropped) => context.log(format!(
+            "debug/dht: garbage collection dropped {} entries: {:?}",
+            dropped.len(),
+            dropped
+        )),
+        Err(err) => context.log(format!(
+            "err/dht: dht::reduce_collect_garbage() FAILED {:?}",
+            err
+        )),
+    }
+    Some(new_store)
+}
+
+// Answering other nodes' requests keeps the requested data from getting garbage collected
+pub(crate) fn reduce_record_request(
+    _context: Arc<Context>,
+    old_store: &DhtStore,
+    action_wrapper: &ActionWrapper,
+) -> Option<DhtStore> {
+    let action = action_wrapper.action();
+    let (address, requested_at) = unwrap_to!(action => Action::RecordRequest);
+    let mut new_store = (*old_store).clone();
+    new_store.record_request(address.clone(), *requested_at);
+    Some(new_store)
+}
 
+fn reduce_link_meta(
+    old_store: &DhtStore,
+    action_wrapper: &ActionWrapper,
+    link: &Link,
+    attribute: String,
+) -> Option<DhtStore> {
     let mut new_store = (*old_store).clone();
     let storage = &old_store.content_storage().clone();
     if !(*storage.read().unwrap()).contains(link.base()).unwrap() {
@@ -118,14 +385,13 @@ pub(crate) fn reduce_add_link(
         );
         Some(new_store)
     } else {
-        let eav = EntityAttributeValueIndex::new(
-            link.base(),
-            &format!("link__{}", link.tag()),
-            link.target(),
-        );
+        let eav = EntityAttributeValueIndex::new(link.base(), &attribute, link.target());
         eav.map(|e| {
             let storage = new_store.meta_storage();
             let result = storage.write().unwrap().add_eavi(&e);
+            if result.is_ok() {
+                new_store.count_held(&e);
+            }
             new_store
                 .actions_mut()
                 .insert(action_wrapper.clone(), result.map(|_| link.base().clone()));
@@ -296,20 +562,32 @@ pub mod tests {
 
     use crate::{
         action::{Action, ActionWrapper},
+        agent::keys::test_keypair,
         dht::{
-            dht_reducers::{reduce, reduce_hold_entry},
+            actions::collect_garbage::GarbageCollection,
+            dht_reducers::{reduce, reduce_hold_encrypted_entry, reduce_hold_entry},
             dht_store::DhtStore,
         },
         instance::tests::test_context,
+        network::{
+            encrypted_entry::{EncryptedEntry, ENCRYPTED_ENTRY_NAME},
+            validation_receipt::sign_validation_receipt,
+        },
         state::test_store,
     };
+    use chrono::Utc;
     use holochain_core_types::{
         cas::content::AddressableContent,
-        eav::IndexQuery,
-        entry::{test_entry, test_sys_entry, Entry},
+        eav::{EntityAttributeValueIndex, IndexQuery},
+        entry::{test_entry, test_entry_a, test_entry_b, test_entry_c, test_sys_entry, Entry},
+        json::JsonString,
         link::Link,
     };
+    use holochain_wasm_utils::api_serialization::get_links::{
+        LinkTagMatch, LinksStatusRequestKind,
+    };
     use std::{
+        collections::BTreeSet,
         convert::TryFrom,
         sync::{Arc, RwLock},
     };
@@ -349,6 +627,143 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn reduce_hold_encrypted_entry_test() {
+        let context = test_context("bob", None);
+        let store = test_store(context.clone());
+        let entry = test_entry();
+        let encrypted_entry =
+            EncryptedEntry::seal(&entry, &[], &mut test_keypair("alice")).unwrap();
+
+        let new_dht_store = reduce_hold_encrypted_entry(
+            Arc::clone(&context),
+            &store.dht(),
+            &ActionWrapper::new(Action::HoldEncrypted(encrypted_entry.clone())),
+        )
+        .expect("there should be a new store for holding an encrypted entry");
+
+        let content_storage = &new_dht_store.content_storage().clone();
+        assert_eq!(
+            (*content_storage.read().unwrap())
+                .fetch(&encrypted_entry.address())
+                .unwrap()
+                .map(|s| EncryptedEntry::try_from_content(&s).unwrap()),
+            Some(encrypted_entry.clone())
+        );
+        assert_eq!(
+            (*content_storage.read().unwrap()).contains(&entry.address()),
+            Ok(false)
+        );
+
+        let meta_storage = &new_dht_store.meta_storage().clone();
+        let encrypted_entry_eavs = (*meta_storage.read().unwrap())
+            .fetch_eavi(
+                Some(entry.address()),
+                Some(ENCRYPTED_ENTRY_NAME.to_string()),
+                None,
+                IndexQuery::default(),
+            )
+            .unwrap();
+        assert_eq!(encrypted_entry_eavs.len(), 1);
+        assert_eq!(
+            encrypted_entry_eavs.iter().next().unwrap().value(),
+            encrypted_entry.address()
+        );
+    }
+
+    fn all_meta(store: &DhtStore, entry: &Entry) -> BTreeSet<EntityAttributeValueIndex> {
+        store
+            .meta_storage()
+            .read()
+            .unwrap()
+            .fetch_eavi(
+                Some(entry.address()),
+                None,
+                None,
+                IndexQuery::new(std::i64::MIN, std::i64::MAX),
+            )
+            .unwrap()
+    }
+
+    /// bytes an entry and its meta data take up in the DHT shard
+    fn held_size(store: &DhtStore, entry: &Entry) -> u64 {
+        all_meta(store, entry)
+            .iter()
+            .map(|eavi| String::from(eavi.content()).len() as u64)
+            .sum::<u64>()
+            + String::from(entry.content()).len() as u64
+    }
+
+    #[test]
+    fn reduce_collect_garbage_test() {
+        let context = test_context("bob", None);
+        let store = test_store(context.clone());
+        let (requested, unrequested, own) = (test_entry_a(), test_entry_b(), test_entry_c());
+
+        let mut dht = store.dht();
+        for entry in vec![&requested, &unrequested, &own] {
+            let action_wrapper = ActionWrapper::new(Action::Hold(entry.clone()));
+            dht = reduce(Arc::clone(&context), dht, &action_wrapper);
+        }
+        let mut dht = (*dht).clone();
+        dht.record_request(requested.address(), Utc::now().timestamp_nanos());
+
+        // only one of the entries held for others fits into the quota
+        let mut keep = BTreeSet::new();
+        keep.insert(own.address());
+        let quota = held_size(&dht, &requested);
+        assert!(dht.needs_garbage_collection(quota));
+        let collection = GarbageCollection { quota, keep };
+        let action_wrapper = ActionWrapper::new(Action::CollectGarbage(collection));
+        let dht = reduce(Arc::clone(&context), Arc::new(dht), &action_wrapper);
+        assert!(!dht.needs_garbage_collection(quota));
+
+        let content_storage = dht.content_storage();
+        let content_storage = content_storage.read().unwrap();
+        assert_eq!(content_storage.contains(&requested.address()), Ok(true));
+        assert_eq!(content_storage.contains(&unrequested.address()), Ok(false));
+        assert_eq!(content_storage.contains(&own.address()), Ok(true));
+        assert!(all_meta(&dht, &unrequested).is_empty());
+        assert!(!all_meta(&dht, &requested).is_empty());
+        assert!(!all_meta(&dht, &own).is_empty());
+        drop(content_storage);
+
+        // holding more for others makes the next collection due
+        let action_wrapper = ActionWrapper::new(Action::Hold(unrequested.clone()));
+        let dht = reduce(Arc::clone(&context), dht, &action_wrapper);
+        assert!(dht.needs_garbage_collection(quota));
+    }
+
+    #[test]
+    fn reduce_hold_validation_receipt_counts_as_held() {
+        let context = test_context("bob", None);
+        let store = test_store(context.clone());
+        let entry = test_entry();
+        let action_wrapper = ActionWrapper::new(Action::Hold(entry.clone()));
+        let dht = reduce(Arc::clone(&context), store.dht(), &action_wrapper);
+        let quota = held_size(&dht, &entry);
+        let collection = GarbageCollection {
+            quota,
+            keep: BTreeSet::new(),
+        };
+        let action_wrapper = ActionWrapper::new(Action::CollectGarbage(collection.clone()));
+        let dht = reduce(Arc::clone(&context), dht, &action_wrapper);
+        assert!(!dht.needs_garbage_collection(quota));
+
+        let receipt = sign_validation_receipt(entry.address(), Ok(()), 1, &context).unwrap();
+        let action_wrapper = ActionWrapper::new(Action::HoldValidationReceipt(receipt.clone()));
+        let dht = reduce(Arc::clone(&context), dht, &action_wrapper);
+        assert!(dht.needs_garbage_collection(quota));
+
+        // receipts get dropped along with the entry they are about
+        let action_wrapper = ActionWrapper::new(Action::CollectGarbage(collection));
+        let dht = reduce(Arc::clone(&context), dht, &action_wrapper);
+        let content_storage = dht.content_storage();
+        let content_storage = content_storage.read().unwrap();
+        assert_eq!(content_storage.contains(&entry.address()), Ok(false));
+        assert_eq!(content_storage.contains(&receipt.address()), Ok(false));
+    }
+
     #[test]
     fn can_add_links() {
         let context = test_context("bob", None);
@@ -389,6 +804,140 @@ pub mod tests {
         assert_eq!(eav.attribute(), format!("link__{}", link.tag()));
     }
 
+    #[test]
+    fn can_remove_links() {
+        let context = test_context("bob", None);
+        let store = test_store(context.clone());
+        let entry = test_entry();
+
+        let locked_state = Arc::new(RwLock::new(store));
+
+        let mut context = (*context).clone();
+        context.set_state(locked_state.clone());
+        let storage = context.dht_storage.clone();
+        let _ = (storage.write().unwrap()).add(&entry);
+        let context = Arc::new(context);
+
+        let link = Link::new(&entry.address(), &entry.address(), "test-tag");
+        let add_action = ActionWrapper::new(Action::AddLink(link.clone()));
+        let remove_action = ActionWrapper::new(Action::RemoveLink(link.clone()));
+
+        let new_dht_store: DhtStore;
+        {
+            let state = locked_state.read().unwrap();
+            let dht_store = reduce(Arc::clone(&context), state.dht(), &add_action);
+            new_dht_store = (*reduce(Arc::clone(&context), dht_store, &remove_action)).clone();
+        }
+        assert!(new_dht_store.actions().get(&remove_action).unwrap().is_ok());
+
+        let get_links = |status| {
+            new_dht_store
+                .get_links(
+                    entry.address(),
+                    link.tag().clone(),
+                    LinkTagMatch::Exact,
+                    status,
+                )
+                .unwrap()
+                .into_iter()
+                .map(|link| link.target().clone())
+                .collect::<Vec<_>>()
+        };
+        assert!(get_links(LinksStatusRequestKind::Live).is_empty());
+        assert_eq!(
+            get_links(LinksStatusRequestKind::Deleted),
+            vec![link.target().clone()]
+        );
+        assert_eq!(
+            get_links(LinksStatusRequestKind::All),
+            vec![link.target().clone()]
+        );
+
+        // a removal that arrives before the link was added still removes it
+        let reordered_dht_store: DhtStore;
+        {
+            let state = locked_state.read().unwrap();
+            let dht_store = reduce(Arc::clone(&context), state.dht(), &remove_action);
+            reordered_dht_store = (*reduce(Arc::clone(&context), dht_store, &add_action)).clone();
+        }
+        assert!(reordered_dht_store
+            .get_links(
+                entry.address(),
+                link.tag().clone(),
+                LinkTagMatch::Exact,
+                LinksStatusRequestKind::Live,
+            )
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn can_get_links_by_tag_pattern_with_payloads() {
+        let context = test_context("bob", None);
+        let store = test_store(context.clone());
+        let entry = test_entry();
+
+        let locked_state = Arc::new(RwLock::new(store));
+
+        let mut context = (*context).clone();
+        context.set_state(locked_state.clone());
+        let storage = context.dht_storage.clone();
+        let _ = (storage.write().unwrap()).add(&entry);
+        let context = Arc::new(context);
+
+        let base = entry.address();
+        let (target_a, target_b) = (test_entry_a().address(), test_entry_b().address());
+        let january = Link::new(&base, &target_a, "date-2026-01");
+        let february = Link::new_with_payload(
+            &base,
+            &target_b,
+            "date-2026-02",
+            JsonString::from("{\"title\":\"February\"}"),
+        );
+        let december = Link::new(&base, &target_a, "date-2025-12");
+
+        let mut dht = locked_state.read().unwrap().dht();
+        for link in vec![&january, &february, &december] {
+            let action_wrapper = ActionWrapper::new(Action::AddLink(link.clone()));
+            dht = reduce(Arc::clone(&context), dht, &action_wrapper);
+            assert!(dht.actions().get(&action_wrapper).unwrap().is_ok());
+        }
+
+        let get_links = |tag: &str, tag_match| {
+            dht.get_links(
+                base.clone(),
+                tag.to_string(),
+                tag_match,
+                LinksStatusRequestKind::Live,
+            )
+            .unwrap()
+        };
+        let mut in_2026 = get_links("date-2026-", LinkTagMatch::Prefix);
+        in_2026.sort_by_key(|link| link.tag().clone());
+        assert_eq!(in_2026, vec![january.clone(), february.clone()]);
+        assert_eq!(
+            in_2026[1].payload(),
+            Some(JsonString::from("{\"title\":\"February\"}"))
+        );
+        assert_eq!(
+            get_links("^date-\\d+-12$", LinkTagMatch::Regex),
+            vec![december.clone()]
+        );
+        assert_eq!(
+            get_links("date-2026-02", LinkTagMatch::Exact),
+            vec![february.clone()]
+        );
+        assert!(get_links("date-2026", LinkTagMatch::Exact).is_empty());
+        assert!(dht
+            .get_links(
+                base.clone(),
+                "(".to_string(),
+                LinkTagMatch::Regex,
+                LinksStatusRequestKind::Live
+            )
+            .is_err());
+    }
+
     #[test]
     fn does_not_add_link_for_missing_base() {
         let context = test_context("bob", None);
@@ -1,15 +1,42 @@
-use crate::action::ActionWrapper;
+use crate::{
+    action::ActionWrapper,
+    dht::actions::collect_garbage::GarbageCollection,
+    network::{
+        encrypted_entry::ENCRYPTED_ENTRY_NAME, gossip::ASPECT_NAME,
+        validation_receipt::VALIDATION_RECEIPT_NAME,
+    },
+};
 use holochain_core_types::{
-    cas::{content::Address, storage::ContentAddressableStorage},
-    eav::{EntityAttributeValueIndex, EntityAttributeValueStorage, IndexQuery},
+    cas::{
+        content::{Address, AddressableContent},
+        storage::ContentAddressableStorage,
+    },
+    crud_status::STATUS_NAME,
+    eav::{
+        AttributeFilter, EavQuery, EntityAttributeValueIndex, EntityAttributeValueStorage,
+        IndexOrder, IndexQuery, Versions,
+    },
+    entry::Entry,
     error::HolochainError,
+    link::{link_add::LinkAdd, Link},
 };
+use holochain_wasm_utils::api_serialization::get_links::{LinkTagMatch, LinksStatusRequestKind};
+use regex::Regex;
 
 use std::{
-    collections::{BTreeSet, HashMap},
+    cmp::max,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    convert::TryFrom,
     sync::{Arc, RwLock},
 };
 
+/// Attributes of the EAVs that add or remove links start with these, followed by the tag
+pub(crate) const LINK_PREFIX: &str = "link__";
+pub(crate) const REMOVED_LINK_PREFIX: &str = "removed_link__";
+/// Attribute prefix of the EAVs that point from the base to the LinkAdd entry of a link
+/// with a payload
+const LINK_PAYLOAD_PREFIX: &str = "link_payload__";
+
 /// The state-slice for the DHT.
 /// Holds the agent's local shard and interacts with the network module
 #[derive(Clone, Debug)]
@@ -19,6 +46,22 @@ pub struct DhtStore {
     meta_storage: Arc<RwLock<EntityAttributeValueStorage>>,
 
     actions: HashMap<ActionWrapper, Result<Address, HolochainError>>,
+
+    /// When other nodes last asked us for an entry or its links, in nanoseconds
+    last_requested: HashMap<Address, i64>,
+
+    /// Bytes the data held for others took up after the last garbage collection, plus
+    /// what got held since. None as long as there was no collection.
+    held_size: Option<u64>,
+}
+
+/// An entry held for others, with what it takes up in the storages
+struct HeldEntry {
+    address: Address,
+    last_used: i64,
+    size: u64,
+    content: Vec<Address>,
+    meta: BTreeSet<EntityAttributeValueIndex>,
 }
 
 impl PartialEq for DhtStore {
@@ -45,20 +88,238 @@ impl DhtStore {
             content_storage,
             meta_storage,
             actions: HashMap::new(),
+            last_requested: HashMap::new(),
+            held_size: None,
         }
     }
 
+    /// Returns the links from the entry with the given address whose tag matches the given one,
+    /// filtered by their status. A removal tombstones the link for good, so every holder
+    /// ends up with the same links no matter in which order adds and removals reach it.
     pub fn get_links(
         &self,
         address: Address,
         tag: String,
-    ) -> Result<BTreeSet<EntityAttributeValueIndex>, HolochainError> {
-        self.meta_storage.read()?.fetch_eavi(
-            Some(address),
-            Some(format!("link__{}", tag)),
+        tag_match: LinkTagMatch,
+        status: LinksStatusRequestKind,
+    ) -> Result<Vec<Link>, HolochainError> {
+        let regex = match tag_match {
+            LinkTagMatch::Regex => Some(Regex::new(&tag).map_err(|_| {
+                HolochainError::ErrorGeneric(format!("Invalid link tag regex: {}", tag))
+            })?),
+            _ => None,
+        };
+        // (tag, target, index) of the latest EAV of every link
+        let links = |prefix: &str| -> Result<Vec<(String, Address, i64)>, HolochainError> {
+            let attribute = match tag_match {
+                LinkTagMatch::Exact => AttributeFilter::Exact(format!("{}{}", prefix, tag)),
+                LinkTagMatch::Prefix => AttributeFilter::Prefix(format!("{}{}", prefix, tag)),
+                LinkTagMatch::Regex => AttributeFilter::Prefix(prefix.to_string()),
+            };
+            let eavis = self.meta_storage.read()?.query_eavi(
+                &EavQuery::new()
+                    .with_entity(address.clone())
+                    .with_attribute(attribute)
+                    .with_versions(Versions::LatestPerEav),
+            )?;
+            Ok(eavis
+                .into_iter()
+                .map(|eavi| {
+                    let tag = eavi.attribute()[prefix.len()..].to_string();
+                    (tag, eavi.value(), eavi.index())
+                })
+                .filter(|(tag, _, _)| {
+                    regex
+                        .as_ref()
+                        .map(|regex| regex.is_match(tag))
+                        .unwrap_or(true)
+                })
+                .collect())
+        };
+        let added = links(LINK_PREFIX)?;
+        let removed = match status {
+            LinksStatusRequestKind::All => Vec::new(),
+            _ => links(REMOVED_LINK_PREFIX)?,
+        };
+        added
+            .into_iter()
+            .filter(|(tag, target, _)| {
+                let is_removed = removed.iter().any(|(removed_tag, removed_target, _)| {
+                    removed_tag == tag && removed_target == target
+                });
+                match status {
+                    LinksStatusRequestKind::All => true,
+                    LinksStatusRequestKind::Deleted => is_removed,
+                    _ => !is_removed,
+                }
+            })
+            .map(|(tag, target, index)| self.link_with_payload(&address, &tag, &target, index))
+            .collect()
+    }
+
+    /// Stores the LinkAdd entry of a link that carries a payload, so that get_links can
+    /// return the link with its payload
+    pub(crate) fn add_link_payload(&self, link: &Link) -> Result<(), HolochainError> {
+        let entry = Entry::LinkAdd(LinkAdd::from_link(link));
+        self.content_storage.write()?.add(&entry)?;
+        let eavi = EntityAttributeValueIndex::new(
+            link.base(),
+            &format!("{}{}", LINK_PAYLOAD_PREFIX, link.tag()),
+            &entry.address(),
+        )?;
+        self.meta_storage.write()?.add_eavi(&eavi)?;
+        Ok(())
+    }
+
+    /// Looks for the payload the link got when it was last added at the given index
+    fn link_with_payload(
+        &self,
+        base: &Address,
+        tag: &str,
+        target: &Address,
+        index: i64,
+    ) -> Result<Link, HolochainError> {
+        let link_adds = self.meta_storage.read()?.query_eavi(
+            &EavQuery::new()
+                .with_entity(base.clone())
+                .with_attribute(AttributeFilter::Exact(format!(
+                    "{}{}",
+                    LINK_PAYLOAD_PREFIX, tag
+                )))
+                .with_index_range(Some(index), None)
+                .with_order(IndexOrder::Descending),
+        )?;
+        for eavi in link_adds {
+            let content = self.content_storage.read()?.fetch(&eavi.value())?;
+            if let Some(Entry::LinkAdd(link_add)) = content.map(Entry::try_from).transpose()? {
+                if link_add.link().target() == target {
+                    return Ok(link_add.link().clone());
+                }
+            }
+        }
+        Ok(Link::new(base, target, tag))
+    }
+
+    /// Remembers that another node asked for the entry or the links at the given address
+    /// at the given time, in nanoseconds
+    pub(crate) fn record_request(&mut self, address: Address, requested_at: i64) {
+        self.last_requested.insert(address, requested_at);
+    }
+
+    /// Adds data that got held to the size the garbage collection gets measured against
+    pub(crate) fn count_held(&mut self, content: &AddressableContent) {
+        if let Some(held_size) = self.held_size.as_mut() {
+            *held_size += String::from(content.content()).len() as u64;
+        }
+    }
+
+    /// Garbage collection is due once the data held for others might not fit into the
+    /// given quota anymore
+    pub(crate) fn needs_garbage_collection(&self, quota: u64) -> bool {
+        self.held_size
+            .map(|held_size| held_size > quota)
+            .unwrap_or(true)
+    }
+
+    /// Drops the entries held for others that got requested or held the longest time ago,
+    /// together with their meta data, until the rest fits into the quota of the collection.
+    /// Returns the addresses of the dropped entries.
+    pub(crate) fn collect_garbage(
+        &mut self,
+        collection: &GarbageCollection,
+    ) -> Result<Vec<Address>, HolochainError> {
+        let mut held = self.entries_held_for_others(&collection.keep)?;
+        let held_addresses: BTreeSet<Address> =
+            held.iter().map(|entry| entry.address.clone()).collect();
+        self.last_requested
+            .retain(|address, _| held_addresses.contains(address));
+
+        let mut total: u64 = held.iter().map(|entry| entry.size).sum();
+        held.sort_by_key(|entry| entry.last_used);
+        let mut dropped = Vec::new();
+        for entry in held {
+            if total <= collection.quota {
+                break;
+            }
+            for address in entry.content.iter() {
+                self.content_storage.write()?.remove(address)?;
+            }
+            for eavi in entry.meta.iter() {
+                self.meta_storage.write()?.remove_eavi(eavi)?;
+            }
+            self.last_requested.remove(&entry.address);
+            total -= entry.size;
+            dropped.push(entry.address);
+        }
+        if !dropped.is_empty() {
+            self.content_storage.write()?.compact()?;
+            self.meta_storage.write()?.compact()?;
+        }
+        self.held_size = Some(total);
+        Ok(dropped)
+    }
+
+    /// Entries with a CRUD status are held, either plain or as encrypted entries
+    fn entries_held_for_others(
+        &self,
+        keep: &BTreeSet<Address>,
+    ) -> Result<Vec<HeldEntry>, HolochainError> {
+        let content_storage = self.content_storage.read()?;
+        let meta_storage = self.meta_storage.read()?;
+        let mut last_held: BTreeMap<Address, i64> = BTreeMap::new();
+        for status in meta_storage.fetch_eavi(
+            None,
+            Some(STATUS_NAME.to_string()),
             None,
             IndexQuery::default(),
-        )
+        )? {
+            if !keep.contains(&status.entity()) {
+                let held = last_held.entry(status.entity()).or_insert(status.index());
+                *held = max(*held, status.index());
+            }
+        }
+
+        let mut held = Vec::new();
+        for (address, last_held) in last_held {
+            let meta = meta_storage.fetch_eavi(
+                Some(address.clone()),
+                None,
+                None,
+                IndexQuery::new(std::i64::MIN, std::i64::MAX),
+            )?;
+            let mut content = vec![address.clone()];
+            content.extend(
+                meta.iter()
+                    .filter(|eavi| {
+                        eavi.attribute() == ENCRYPTED_ENTRY_NAME
+                            || eavi.attribute() == ASPECT_NAME
+                            || eavi.attribute() == VALIDATION_RECEIPT_NAME
+                    })
+                    .map(|eavi| eavi.value()),
+            );
+            let mut size: u64 = meta
+                .iter()
+                .map(|eavi| String::from(eavi.content()).len() as u64)
+                .sum();
+            for address in content.iter() {
+                if let Some(stored) = content_storage.fetch(address)? {
+                    size += String::from(stored).len() as u64;
+                }
+            }
+            let last_used = self
+                .last_requested
+                .get(&address)
+                .map(|requested| max(*requested, last_held))
+                .unwrap_or(last_held);
+            held.push(HeldEntry {
+                address,
+                last_used,
+                size,
+                content,
+                meta,
+            });
+        }
+        Ok(held)
     }
 
     // Getters (for reducers)
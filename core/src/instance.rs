@@ -1,4 +1,8 @@
-use crate::{action::ActionWrapper, context::Context, signal::Signal, state::State};
+use crate::{
+    action::ActionWrapper, context::Context, persister::JournaledAction, signal::Signal,
+    state::State,
+};
+use holochain_core_types::error::HolochainError;
 use std::{
     sync::{
         mpsc::{sync_channel, Receiver, SyncSender},
@@ -141,7 +145,7 @@ impl Instance {
     ) -> Vec<Observer> {
         // Mutate state
         {
-            let new_state: State;
+            let mut new_state: State;
 
             {
                 // Only get a read lock first so code in reducers can read state as well
@@ -152,6 +156,30 @@ impl Instance {
 
                 // Create new state by reducing the action on old state
                 new_state = state.reduce(context.clone(), action_wrapper.clone());
+
+                // Persist the effect of the action before anyone can observe it
+                if let Some(journaled) =
+                    JournaledAction::from_reduced(&action_wrapper, &state, &new_state)
+                {
+                    let result = context
+                        .persister
+                        .lock()
+                        .map_err(|_| {
+                            HolochainError::ErrorGeneric("persister lock is poisoned".to_string())
+                        })
+                        .and_then(|mut persister| {
+                            persister.journal(action_wrapper.id().clone(), journaled, &new_state)
+                        });
+                    if let Err(error) = result {
+                        context.log(format!("err/instance: could not journal action: {}", error));
+                        // A chain head that would be lost on restart must not be used,
+                        // so the action fails instead
+                        if let Some(failed_state) = state.with_failed_action(&action_wrapper, error)
+                        {
+                            new_state = failed_state;
+                        }
+                    }
+                }
             }
 
             // Get write lock
@@ -308,16 +336,19 @@ pub mod tests {
         action::{tests::test_action_wrapper_commit, Action, ActionWrapper},
         agent::{
             chain_store::ChainStore,
+            keys::{test_keypair, MemoryRotatedKeyStore},
             state::{ActionResponse, AgentState},
         },
         context::{test_memory_network_config, Context},
         logger::{test_logger, TestLogger},
     };
     use futures::executor::block_on;
-    use holochain_cas_implementations::{cas::file::FilesystemStorage, eav::file::EavFileStorage};
+    use holochain_cas_implementations::{
+        cas::{file::FilesystemStorage, memory::MemoryStorage},
+        eav::file::EavFileStorage,
+    };
     use holochain_core_types::{
-        agent::AgentId,
-        cas::content::AddressableContent,
+        cas::{content::AddressableContent, storage::ContentAddressableStorage},
         chain_header::test_chain_header,
         dna::{zome::Zome, Dna},
         entry::{entry_type::EntryType, test_entry},
@@ -330,7 +361,7 @@ pub mod tests {
             actions::initialize::initialize_application,
             ribosome::{callback::Callback, Defn},
         },
-        persister::SimplePersister,
+        persister::{Persister, SimplePersister},
         state::State,
     };
 
@@ -344,6 +375,12 @@ pub mod tests {
     };
 
     use holochain_core_types::entry::Entry;
+    use uuid::Uuid;
+
+    /// Storage for the persister of a test context, apart from its chain storage
+    pub fn test_state_storage() -> Arc<RwLock<ContentAddressableStorage>> {
+        Arc::new(RwLock::new(MemoryStorage::new()))
+    }
 
     /// create a test context and TestLogger pair so we can use the logger in assertions
     #[cfg_attr(tarpaulin, skip)]
@@ -351,28 +388,31 @@ pub mod tests {
         agent_name: &str,
         network_name: Option<&str>,
     ) -> (Arc<Context>, Arc<Mutex<TestLogger>>) {
-        let agent = AgentId::generate_fake(agent_name);
+        let keys = test_keypair(agent_name);
+        let agent = keys.agent_id(agent_name);
         let file_storage = Arc::new(RwLock::new(
             FilesystemStorage::new(tempdir().unwrap().path().to_str().unwrap()).unwrap(),
         ));
         let logger = test_logger();
-        (
-            Arc::new(Context::new(
-                agent,
-                logger.clone(),
-                Arc::new(Mutex::new(SimplePersister::new(file_storage.clone()))),
-                file_storage.clone(),
-                file_storage.clone(),
-                Arc::new(RwLock::new(
-                    EavFileStorage::new(tempdir().unwrap().path().to_str().unwrap().to_string())
-                        .unwrap(),
-                )),
-                test_memory_network_config(network_name),
-                None,
-                None,
+        let mut context = Context::new(
+            agent,
+            logger.clone(),
+            Arc::new(Mutex::new(
+                SimplePersister::new(test_state_storage()).unwrap(),
             )),
-            logger,
-        )
+            file_storage.clone(),
+            file_storage.clone(),
+            Arc::new(RwLock::new(
+                EavFileStorage::new(tempdir().unwrap().path().to_str().unwrap().to_string())
+                    .unwrap(),
+            )),
+            test_memory_network_config(network_name),
+            None,
+            None,
+        );
+        context.set_agent_keys(keys);
+        context.set_rotated_key_store(Arc::new(MemoryRotatedKeyStore::default()));
+        (Arc::new(context), logger)
     }
 
     /// create a test context
@@ -390,28 +430,31 @@ pub mod tests {
         observer_channel: &SyncSender<Observer>,
         network_name: Option<&str>,
     ) -> Arc<Context> {
-        let agent = AgentId::generate_fake(agent_name);
+        let keys = test_keypair(agent_name);
+        let agent = keys.agent_id(agent_name);
         let logger = test_logger();
         let file_storage = Arc::new(RwLock::new(
             FilesystemStorage::new(tempdir().unwrap().path().to_str().unwrap()).unwrap(),
         ));
-        Arc::new(
-            Context::new_with_channels(
-                agent,
-                logger.clone(),
-                Arc::new(Mutex::new(SimplePersister::new(file_storage.clone()))),
-                Some(action_channel.clone()),
-                None,
-                Some(observer_channel.clone()),
-                file_storage.clone(),
-                Arc::new(RwLock::new(
-                    EavFileStorage::new(tempdir().unwrap().path().to_str().unwrap().to_string())
-                        .unwrap(),
-                )),
-                test_memory_network_config(network_name),
-            )
-            .unwrap(),
+        let mut context = Context::new_with_channels(
+            agent,
+            logger.clone(),
+            Arc::new(Mutex::new(
+                SimplePersister::new(test_state_storage()).unwrap(),
+            )),
+            Some(action_channel.clone()),
+            None,
+            Some(observer_channel.clone()),
+            file_storage.clone(),
+            Arc::new(RwLock::new(
+                EavFileStorage::new(tempdir().unwrap().path().to_str().unwrap().to_string())
+                    .unwrap(),
+            )),
+            test_memory_network_config(network_name),
         )
+        .unwrap();
+        context.set_agent_keys(keys);
+        Arc::new(context)
     }
 
     #[cfg_attr(tarpaulin, skip)]
@@ -420,9 +463,11 @@ pub mod tests {
             FilesystemStorage::new(tempdir().unwrap().path().to_str().unwrap()).unwrap(),
         ));
         let mut context = Context::new(
-            AgentId::generate_fake("Florence"),
+            test_keypair("Florence").agent_id("Florence"),
             test_logger(),
-            Arc::new(Mutex::new(SimplePersister::new(file_storage.clone()))),
+            Arc::new(Mutex::new(
+                SimplePersister::new(test_state_storage()).unwrap(),
+            )),
             file_storage.clone(),
             file_storage.clone(),
             Arc::new(RwLock::new(
@@ -433,6 +478,7 @@ pub mod tests {
             None,
             None,
         );
+        context.set_agent_keys(test_keypair("Florence"));
         let global_state = Arc::new(RwLock::new(State::new(Arc::new(context.clone()))));
         context.set_state(global_state.clone());
         Arc::new(context)
@@ -444,9 +490,11 @@ pub mod tests {
             FilesystemStorage::new(tempdir().unwrap().path().to_str().unwrap()).unwrap();
         let cas = Arc::new(RwLock::new(file_system.clone()));
         let mut context = Context::new(
-            AgentId::generate_fake("Florence"),
+            test_keypair("Florence").agent_id("Florence"),
             test_logger(),
-            Arc::new(Mutex::new(SimplePersister::new(cas.clone()))),
+            Arc::new(Mutex::new(
+                SimplePersister::new(test_state_storage()).unwrap(),
+            )),
             cas.clone(),
             cas.clone(),
             Arc::new(RwLock::new(
@@ -457,6 +505,7 @@ pub mod tests {
             None,
             None,
         );
+        context.set_agent_keys(test_keypair("Florence"));
         let chain_store = ChainStore::new(cas.clone());
         let chain_header = test_chain_header();
         let agent_state = AgentState::new_with_top_chain_header(chain_store, chain_header);
@@ -623,6 +672,52 @@ pub mod tests {
         );
     }
 
+    /// Persister that can't write its journal, like on a full disk
+    struct FailingPersister;
+
+    impl Persister for FailingPersister {
+        fn save(&mut self, _state: State) -> Result<(), HolochainError> {
+            Ok(())
+        }
+
+        fn journal(
+            &mut self,
+            _id: Uuid,
+            _action: JournaledAction,
+            _state: &State,
+        ) -> Result<(), HolochainError> {
+            Err(HolochainError::ErrorGeneric("disk is full".to_string()))
+        }
+
+        fn load(&self, _context: Arc<Context>) -> Result<Option<State>, HolochainError> {
+            Ok(None)
+        }
+    }
+
+    #[test]
+    /// A commit whose new chain head can't be journaled fails and leaves the chain as it was
+    pub fn commit_fails_if_it_can_not_be_journaled() {
+        let netname = Some("commit_fails_if_it_can_not_be_journaled");
+        let mut instance = Instance::new(test_context("jason", netname));
+        let mut context = (*test_context("jane", netname)).clone();
+        context.persister = Arc::new(Mutex::new(FailingPersister));
+        let context = instance.initialize_context(Arc::new(context));
+        let (_, rx_observer) = instance.initialize_channels();
+
+        let action_wrapper = test_action_wrapper_commit();
+        instance.process_action(action_wrapper.clone(), Vec::new(), &rx_observer, &context);
+
+        let state = instance.state();
+        assert!(state.history.contains(&action_wrapper));
+        assert_eq!(state.agent().top_chain_header(), None);
+        assert_eq!(
+            state.agent().actions().get(&action_wrapper),
+            Some(&ActionResponse::Commit(Err(HolochainError::ErrorGeneric(
+                "disk is full".to_string()
+            ))))
+        );
+    }
+
     #[test]
     /// This test shows how to call dispatch with a closure that should run
     /// when the action results in a state change.  Note that the observer closure
@@ -12,6 +12,7 @@ extern crate serde;
 #[macro_use]
 extern crate serde_json;
 extern crate snowflake;
+extern crate uuid;
 #[cfg(test)]
 extern crate test_utils;
 extern crate wasmi;
@@ -33,6 +34,7 @@ extern crate holochain_core_types_derive;
 extern crate base64;
 extern crate globset;
 extern crate holochain_net_connection;
+extern crate holochain_sodium;
 #[macro_use]
 extern crate lazy_static;
 
@@ -8,22 +8,29 @@ use futures::{
     future::Future,
     task::{LocalWaker, Poll},
 };
-use holochain_core_types::{cas::content::Address, error::HcResult, time::Timeout};
+use holochain_core_types::{cas::content::Address, error::HcResult, link::Link, time::Timeout};
+use holochain_wasm_utils::api_serialization::get_links::{LinkTagMatch, LinksStatusRequestKind};
 use snowflake::ProcessUniqueId;
 use std::{pin::Pin, sync::Arc, thread};
 
 /// GetLinks Action Creator
 /// This is the network version of get_links that makes the network module start
 /// a look-up process.
+/// Depending on the given status, only live links, only removed links or all links get returned.
+/// The tag either has to match exactly or gets used as prefix or regex, see LinkTagMatch.
 pub async fn get_links(
     context: Arc<Context>,
     address: Address,
     tag: String,
+    tag_match: LinkTagMatch,
+    status: LinksStatusRequestKind,
     timeout: Timeout,
-) -> HcResult<Vec<Address>> {
+) -> HcResult<Vec<Link>> {
     let key = GetLinksKey {
         base_address: address.clone(),
         tag: tag.clone(),
+        tag_match,
+        status,
         id: ProcessUniqueId::new().to_string(),
     };
     let action_wrapper = ActionWrapper::new(Action::GetLinks(key.clone()));
@@ -43,7 +50,7 @@ pub async fn get_links(
     })
 }
 
-/// GetLinksFuture resolves to a HcResult<Vec<Address>>.
+/// GetLinksFuture resolves to a HcResult<Vec<Link>>.
 /// Tracks the state of the network module
 pub struct GetLinksFuture {
     context: Arc<Context>,
@@ -51,7 +58,7 @@ pub struct GetLinksFuture {
 }
 
 impl Future for GetLinksFuture {
-    type Output = HcResult<Vec<Address>>;
+    type Output = HcResult<Vec<Link>>;
 
     fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<Self::Output> {
         let state = self.context.state().unwrap().network();
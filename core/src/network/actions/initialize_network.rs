@@ -4,7 +4,10 @@ use crate::{
     action::{Action, ActionWrapper, NetworkSettings},
     context::{get_dna_and_agent, Context},
     instance::dispatch_action,
-    network::actions::publish::publish,
+    network::{
+        actions::publish::publish,
+        gossip::{gossip_interval, start_gossip},
+    },
 };
 use futures::{
     task::{LocalWaker, Poll},
@@ -13,7 +16,8 @@ use futures::{
 #[cfg(test)]
 use holochain_core_types::cas::content::Address;
 use holochain_core_types::error::HcResult;
-use std::{pin::Pin, sync::Arc};
+use holochain_net::p2p_config::P2pConfig;
+use std::{pin::Pin, str::FromStr, sync::Arc};
 
 /// Creates a network proxy object and stores DNA and agent hash in the network state.
 pub async fn initialize_network(context: &Arc<Context>) -> HcResult<()> {
@@ -32,6 +36,9 @@ pub async fn initialize_network(context: &Arc<Context>) -> HcResult<()> {
 
     await!(publish(agent_id.clone().into(), context))?;
 
+    let p2p_config = P2pConfig::from_str(&context.network_config.to_string())?;
+    start_gossip(context, gossip_interval(&p2p_config.backend_config));
+
     Ok(())
 }
 
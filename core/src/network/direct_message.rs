@@ -1,3 +1,7 @@
+use crate::network::{
+    gossip::{Aspect, GossipDigest},
+    validation_receipt::ValidationReceipt,
+};
 use holochain_core_types::{
     cas::content::Address, error::HolochainError, json::JsonString, validation::ValidationPackage,
 };
@@ -32,4 +36,24 @@ pub enum DirectMessage {
     /// Option<> since there has to be a way to respond saying
     /// "I can't"
     ValidationPackage(Option<ValidationPackage>),
+
+    /// Holders gossip with this message about the aspects they both hold.
+    /// The receiver responds with a GossipAddresses message.
+    Gossip(GossipDigest),
+
+    /// With this message a holder is responding to a Gossip message with the
+    /// addresses of the aspects it holds in the buckets whose digests differ.
+    GossipAddresses(Vec<Address>),
+
+    /// With this message a holder asks the holder it gossiped with for the aspects
+    /// it does not know yet. The receiver responds with a GossipAspects message.
+    FetchAspects(Vec<Address>),
+
+    /// With this message a holder is responding to a FetchAspects message with the
+    /// requested aspects.
+    GossipAspects(Vec<Aspect>),
+
+    /// With this message a holder tells the author of an entry that got published
+    /// to it whether it found the entry valid. It does not get answered.
+    ValidationReceipt(ValidationReceipt),
 }
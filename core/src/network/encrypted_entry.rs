@@ -0,0 +1,381 @@
+//! Entries of entry types with `Sharing::Encrypted` get published as `EncryptedEntry`s.
+//!
+//! An encrypted entry is sealed (`aead`) with a random entry key. That key gets wrapped for
+//! the author and for every recipient the author committed the entry for, each time with a
+//! session key that comes from a key exchange (`kx`) between the author's and the recipient's
+//! encryption keys. Both public keys are part of the agent addresses, so nothing but the
+//! addresses needs to be known up front.
+//! The author signs the hash of the whole encrypted entry, so holders that can't open it
+//! can still tell that it is what the author published.
+//!
+//! DHT holders store encrypted entries as they are, next to the address of the plaintext
+//! entry they stand for. Only recipients can open them, so only recipients get to validate
+//! and read the plaintext.
+//! Entry keys are wrapped for agent addresses, so an agent that rotated its keys can't open
+//! entries that got encrypted for its previous address.
+
+use crate::{
+    agent::keys::{
+        secbuf_from_bytes, sodium_error, verify, Keypair, ENCRYPTED_ENTRY_SIGNATURE_DOMAIN,
+    },
+    context::Context,
+};
+use holochain_core_types::{
+    agent::KeyBuffer,
+    cas::content::{Address, AddressableContent, Content},
+    chain_header::ChainHeader,
+    crud_status::CrudStatus,
+    dna::entry_types::Sharing,
+    entry::{entry_type::EntryType, Entry, EntryWithMeta},
+    error::{HcResult, HolochainError},
+    json::JsonString,
+    signature::Signature,
+};
+use holochain_sodium::{aead, kx, random::random_secbuf, secbuf::SecBuf};
+use std::{collections::BTreeMap, convert::TryInto, iter, sync::Arc};
+
+/// EAV attribute linking the address of an entry to the encrypted entries standing for it
+pub const ENCRYPTED_ENTRY_NAME: &str = "encrypted-entry";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, DefaultJson)]
+pub struct EncryptedEntry {
+    /// address of the plaintext entry
+    entry_address: Address,
+    entry_type: EntryType,
+    /// agent that encrypted the entry
+    author: Address,
+    /// the entry key wrapped for each recipient, base64 encoded nonce and cipher text
+    keys: BTreeMap<Address, String>,
+    /// the sealed entry, base64 encoded nonce and cipher text
+    entry: String,
+    /// signature of the author over the hash of all of the above
+    signature: Signature,
+}
+
+impl AddressableContent for EncryptedEntry {
+    fn content(&self) -> Content {
+        self.to_owned().into()
+    }
+
+    fn try_from_content(content: &Content) -> HcResult<Self> {
+        content.to_owned().try_into()
+    }
+}
+
+impl EncryptedEntry {
+    /// Encrypts `entry` for the agent with the given keys, who is the author,
+    /// and for the agents at the given addresses.
+    pub fn seal(
+        entry: &Entry,
+        recipients: &[Address],
+        author_keys: &mut Keypair,
+    ) -> HcResult<EncryptedEntry> {
+        let entry_address = entry.address();
+        let author = author_keys.address();
+        let mut entry_key = SecBuf::with_secure(aead::KEYBYTES);
+        random_secbuf(&mut entry_key);
+        let sealed_entry = seal_with_key(
+            String::from(entry.content()).as_bytes(),
+            &mut entry_key,
+            &entry_address,
+        )?;
+
+        let mut author_public_key = secbuf_from_bytes(author_keys.public_key().get_enc());
+        let mut keys = BTreeMap::new();
+        for recipient in iter::once(&author).chain(recipients.iter()) {
+            let mut recipient_public_key = enc_public_key(recipient)?;
+            let mut rx = SecBuf::with_secure(kx::SESSIONKEYBYTES);
+            let mut tx = SecBuf::with_secure(kx::SESSIONKEYBYTES);
+            kx::client_session(
+                &mut author_public_key,
+                author_keys.enc_secret_key(),
+                &mut recipient_public_key,
+                &mut rx,
+                &mut tx,
+            )
+            .map_err(sodium_error)?;
+            let wrapped_key = seal_with_key(&entry_key.read_lock()[..], &mut tx, recipient)?;
+            keys.insert(recipient.clone(), wrapped_key);
+        }
+
+        let mut encrypted_entry = EncryptedEntry {
+            entry_address,
+            entry_type: entry.entry_type(),
+            author,
+            keys,
+            entry: sealed_entry,
+            signature: Signature::from(""),
+        };
+        encrypted_entry.signature = author_keys.sign(&encrypted_entry.signed_data())?;
+        Ok(encrypted_entry)
+    }
+
+    /// Checks that the author signed the encrypted entry as it is.
+    pub fn verify_signature(&self) -> HcResult<()> {
+        if !verify(&self.author, &self.signed_data(), &self.signature)? {
+            return Err(HolochainError::ValidationFailed(format!(
+                "Encrypted entry {} is not signed by its author",
+                self.entry_address
+            )));
+        }
+        Ok(())
+    }
+
+    /// The data the author signs: the address of the encrypted entry without its signature,
+    /// which is the hash of the cipher texts, the wrapped keys and everything else
+    fn signed_data(&self) -> String {
+        let unsigned = EncryptedEntry {
+            signature: Signature::from(""),
+            ..self.clone()
+        };
+        format!("{}{}", ENCRYPTED_ENTRY_SIGNATURE_DOMAIN, unsigned.address())
+    }
+
+    /// Decrypts the entry with the keys of one of its recipients.
+    /// Returns None if the entry was not encrypted for them.
+    pub fn open(&self, recipient_keys: &mut Keypair) -> HcResult<Option<Entry>> {
+        let recipient = recipient_keys.address();
+        let wrapped_key = match self.keys.get(&recipient) {
+            Some(wrapped_key) => wrapped_key,
+            None => return Ok(None),
+        };
+
+        let mut recipient_public_key = secbuf_from_bytes(recipient_keys.public_key().get_enc());
+        let mut author_public_key = enc_public_key(&self.author)?;
+        let mut rx = SecBuf::with_secure(kx::SESSIONKEYBYTES);
+        let mut tx = SecBuf::with_secure(kx::SESSIONKEYBYTES);
+        kx::server_session(
+            &mut recipient_public_key,
+            recipient_keys.enc_secret_key(),
+            &mut author_public_key,
+            &mut rx,
+            &mut tx,
+        )
+        .map_err(sodium_error)?;
+        let mut entry_key = secbuf_from_bytes(&open_with_key(wrapped_key, &mut rx, &recipient)?);
+        let plaintext = open_with_key(&self.entry, &mut entry_key, &self.entry_address)?;
+
+        let content = String::from_utf8(plaintext).map_err(|_| {
+            HolochainError::ErrorGeneric(format!(
+                "Encrypted entry {} is not UTF-8",
+                self.entry_address
+            ))
+        })?;
+        let entry = Entry::try_from_content(&JsonString::from(content))?;
+        if entry.address() != self.entry_address || entry.entry_type() != self.entry_type {
+            return Err(HolochainError::ValidationFailed(format!(
+                "Encrypted entry does not hold entry {}",
+                self.entry_address
+            )));
+        }
+        Ok(Some(entry))
+    }
+
+    pub fn entry_address(&self) -> &Address {
+        &self.entry_address
+    }
+
+    pub fn entry_type(&self) -> &EntryType {
+        &self.entry_type
+    }
+
+    pub fn author(&self) -> &Address {
+        &self.author
+    }
+
+    /// All agents that can open the entry, including its author
+    pub fn recipients(&self) -> Vec<Address> {
+        self.keys.keys().cloned().collect()
+    }
+}
+
+/// An encrypted entry as it gets published, i.e. together with the header of the
+/// plaintext entry.
+#[derive(Serialize, Deserialize)]
+pub struct EncryptedEntryWithHeader {
+    pub encrypted_entry: EncryptedEntry,
+    pub header: ChainHeader,
+}
+
+impl EncryptedEntryWithHeader {
+    pub fn new(encrypted_entry: EncryptedEntry, header: ChainHeader) -> EncryptedEntryWithHeader {
+        EncryptedEntryWithHeader {
+            encrypted_entry,
+            header,
+        }
+    }
+}
+
+/// An encrypted entry as it gets returned for a GET request, i.e. together with the
+/// metadata of the plaintext entry.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EncryptedEntryWithMeta {
+    pub encrypted_entry: EncryptedEntry,
+    pub crud_status: CrudStatus,
+    pub maybe_crud_link: Option<Address>,
+}
+
+/// Whether the DNA defines the given entry type as an encrypted entry type
+pub fn is_encrypted_entry_type(entry_type: &EntryType, context: &Arc<Context>) -> bool {
+    let app_entry_type = match entry_type {
+        EntryType::App(app_entry_type) => app_entry_type,
+        _ => return false,
+    };
+    let dna = match context.state().and_then(|state| state.nucleus().dna()) {
+        Some(dna) => dna,
+        None => return false,
+    };
+    dna.get_entry_type_def(&app_entry_type.to_string())
+        .map(|entry_type_def| entry_type_def.sharing == Sharing::Encrypted)
+        .unwrap_or(false)
+}
+
+/// Encrypts `entry` as authored by the agent of the given context.
+pub fn seal_entry(
+    entry: &Entry,
+    recipients: &[Address],
+    context: &Arc<Context>,
+) -> HcResult<EncryptedEntry> {
+    let keys = context
+        .agent_keys
+        .as_ref()
+        .ok_or(HolochainError::ErrorGeneric(
+            "Agent has no keys to encrypt with".to_string(),
+        ))?;
+    let mut keys = keys.lock()?;
+    EncryptedEntry::seal(entry, recipients, &mut keys)
+}
+
+/// Decrypts `encrypted_entry` if the agent of the given context is one of its recipients.
+pub fn open_entry(
+    encrypted_entry: &EncryptedEntry,
+    context: &Arc<Context>,
+) -> HcResult<Option<Entry>> {
+    match context.agent_keys {
+        Some(ref keys) => encrypted_entry.open(&mut *keys.lock()?),
+        None => Ok(None),
+    }
+}
+
+/// Decrypts the entry of `encrypted_entry_with_meta` if the agent of the given context
+/// is one of its recipients.
+pub fn open_entry_with_meta(
+    encrypted_entry_with_meta: &EncryptedEntryWithMeta,
+    context: &Arc<Context>,
+) -> HcResult<Option<EntryWithMeta>> {
+    Ok(
+        open_entry(&encrypted_entry_with_meta.encrypted_entry, context)?.map(|entry| {
+            EntryWithMeta {
+                entry,
+                crud_status: encrypted_entry_with_meta.crud_status.clone(),
+                maybe_crud_link: encrypted_entry_with_meta.maybe_crud_link.clone(),
+            }
+        }),
+    )
+}
+
+/// The public key for key exchange that is part of the given agent address
+fn enc_public_key(agent_address: &Address) -> HcResult<SecBuf> {
+    let key_buffer = KeyBuffer::with_corrected(&String::from(agent_address.clone()))?;
+    Ok(secbuf_from_bytes(key_buffer.get_enc()))
+}
+
+/// Seals `plaintext` with `key` and a random nonce, authenticating `address` along with it.
+/// Returns the nonce followed by the cipher text, base64 encoded.
+fn seal_with_key(plaintext: &[u8], key: &mut SecBuf, address: &Address) -> HcResult<String> {
+    let mut message = secbuf_from_bytes(plaintext);
+    let mut adata = secbuf_from_bytes(address.to_string().as_bytes());
+    let mut nonce = SecBuf::with_insecure(aead::NONCEBYTES);
+    random_secbuf(&mut nonce);
+    let mut cipher = SecBuf::with_insecure(plaintext.len() + aead::ABYTES);
+    aead::enc(&mut message, key, Some(&mut adata), &mut nonce, &mut cipher)
+        .map_err(sodium_error)?;
+    let mut sealed = nonce.read_lock().to_vec();
+    sealed.extend_from_slice(&cipher.read_lock()[..]);
+    Ok(base64::encode(&sealed))
+}
+
+/// Opens what `seal_with_key` returned.
+/// Fails if it got tampered with or was sealed with another key or address.
+fn open_with_key(sealed: &str, key: &mut SecBuf, address: &Address) -> HcResult<Vec<u8>> {
+    let sealed = base64::decode(sealed)?;
+    if sealed.len() < aead::NONCEBYTES + aead::ABYTES {
+        return Err(HolochainError::ErrorGeneric(
+            "Sealed data is too short".to_string(),
+        ));
+    }
+    let mut nonce = secbuf_from_bytes(&sealed[..aead::NONCEBYTES]);
+    let mut cipher = secbuf_from_bytes(&sealed[aead::NONCEBYTES..]);
+    let mut adata = secbuf_from_bytes(address.to_string().as_bytes());
+    let mut plaintext = SecBuf::with_insecure(cipher.len() - aead::ABYTES);
+    aead::dec_verified(
+        &mut plaintext,
+        key,
+        Some(&mut adata),
+        &mut nonce,
+        &mut cipher,
+    )
+    .map_err(|_| HolochainError::ErrorGeneric("Could not decrypt entry".to_string()))?;
+    let plaintext = plaintext.read_lock();
+    Ok(plaintext.to_vec())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::agent::keys::test_keypair;
+    use holochain_core_types::entry::test_entry;
+
+    #[test]
+    fn recipients_can_open_encrypted_entries() {
+        let entry = test_entry();
+        let mut alice = test_keypair("alice");
+        let mut bob = test_keypair("bob");
+        let mut carol = test_keypair("carol");
+
+        let encrypted_entry = EncryptedEntry::seal(&entry, &[bob.address()], &mut alice).unwrap();
+        assert_eq!(encrypted_entry.entry_address(), &entry.address());
+        assert_eq!(encrypted_entry.author(), &alice.address());
+        assert!(encrypted_entry.verify_signature().is_ok());
+        assert!(!String::from(encrypted_entry.content()).contains("test entry value"));
+        assert_ne!(encrypted_entry.address(), entry.address());
+
+        assert_eq!(encrypted_entry.open(&mut alice), Ok(Some(entry.clone())));
+        assert_eq!(encrypted_entry.open(&mut bob), Ok(Some(entry)));
+        assert_eq!(encrypted_entry.open(&mut carol), Ok(None));
+    }
+
+    #[test]
+    fn tampered_encrypted_entries_can_not_be_opened() {
+        let mut alice = test_keypair("alice");
+        let mut bob = test_keypair("bob");
+        let encrypted_entry =
+            EncryptedEntry::seal(&test_entry(), &[bob.address()], &mut alice).unwrap();
+
+        // a key wrapped for someone else does not work
+        let mut swapped_keys = encrypted_entry.clone();
+        let alice_key = swapped_keys.keys[&alice.address()].clone();
+        swapped_keys.keys.insert(bob.address(), alice_key);
+        assert!(swapped_keys.open(&mut bob).is_err());
+        assert!(swapped_keys.verify_signature().is_err());
+
+        // the entry is bound to its address
+        let mut other_address = encrypted_entry.clone();
+        other_address.entry_address = Address::from("other address");
+        assert!(other_address.open(&mut bob).is_err());
+        assert!(other_address.verify_signature().is_err());
+
+        // only the author's key exchange yields the right session key
+        let mut other_author = encrypted_entry.clone();
+        other_author.author = test_keypair("carol").address();
+        assert!(other_author.open(&mut bob).is_err());
+        assert!(other_author.verify_signature().is_err());
+
+        // holders can tell that the cipher text got replaced
+        let mut other_cipher_text = encrypted_entry.clone();
+        other_cipher_text.entry = EncryptedEntry::seal(&test_entry(), &[bob.address()], &mut alice)
+            .unwrap()
+            .entry;
+        assert!(other_cipher_text.verify_signature().is_err());
+    }
+}
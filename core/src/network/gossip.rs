@@ -0,0 +1,540 @@
+//! Anti-entropy between the holders of the DHT.
+//! Holders only get data through the publishes of its authors, so a holder that is
+//! offline at that moment would miss it for good. That is why holders remember what got
+//! published to them as aspects and regularly gossip with one of their peers: they send
+//! digests of the aspects they share with the peer, bucketed by location on the address
+//! space, and the peer responds with the addresses of its aspects in the buckets whose
+//! digests differ. Holders then fetch the aspects they do not know yet, which get
+//! validated and held just like published data. Aspects found invalid get remembered as
+//! rejected, so they do not get fetched again.
+
+use crate::{
+    action::{Action, ActionWrapper, DirectMessageData},
+    context::Context,
+    instance::dispatch_action,
+    network::{
+        direct_message::DirectMessage,
+        handler::{
+            remove_peer,
+            store::{handle_store_dht, handle_store_dht_meta},
+        },
+    },
+};
+use chrono::Utc;
+use holochain_core_types::{
+    cas::content::{Address, AddressableContent, Content},
+    eav::IndexQuery,
+    error::HolochainError,
+    json::JsonString,
+};
+use holochain_net::sharding::location;
+use holochain_net_connection::json_protocol::{DhtData, DhtMetaData};
+use multihash::Hash;
+use snowflake::ProcessUniqueId;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    convert::TryFrom,
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+/// Attribute of the EAVs that point from an address to the aspects held at it
+pub const ASPECT_NAME: &str = "gossip-aspect";
+
+/// Attribute of the EAVs that point from an address to the aspects published at it that
+/// we found invalid. They count for our digests like the aspects we hold.
+pub const REJECTED_ASPECT_NAME: &str = "gossip-rejected-aspect";
+
+/// How many milliseconds pass between two gossip rounds, unless configured otherwise
+pub const DEFAULT_GOSSIP_INTERVAL: u64 = 10_000;
+
+/// How many rounds of gossip with every peer may pass without hearing from one before
+/// it counts as gone. Peers answer our gossip and gossip with us themselves.
+const SILENT_ROUNDS_UNTIL_GONE: u32 = 3;
+
+/// The address space gets split into 2^BUCKET_BITS buckets that get compared separately
+const BUCKET_BITS: u32 = 6;
+
+/// How many addresses of aspects a holder lists at most in response to a digest
+const MAX_GOSSIP_ADDRESSES: usize = 1024;
+
+/// How many bytes of aspects a holder sends at most in response to a fetch,
+/// unless a single aspect is bigger than that
+const MAX_GOSSIP_RESPONSE_LEN: usize = 1024 * 1024;
+
+/// Something that got published to us and that we hold, as it got published.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, DefaultJson)]
+pub enum Aspect {
+    Entry(DhtData),
+    Meta(DhtMetaData),
+}
+
+impl Aspect {
+    /// Message IDs differ between holders, so they get dropped to give the aspects of the
+    /// same publish the same address everywhere.
+    pub fn entry(dht_data: DhtData) -> Self {
+        Aspect::Entry(DhtData {
+            msg_id: String::new(),
+            ..dht_data
+        })
+    }
+
+    pub fn meta(dht_meta_data: DhtMetaData) -> Self {
+        Aspect::Meta(DhtMetaData {
+            msg_id: String::new(),
+            ..dht_meta_data
+        })
+    }
+
+    /// The address the aspect is held at
+    pub fn held_at(&self) -> Address {
+        match self {
+            Aspect::Entry(dht_data) => Address::from(dht_data.address.clone()),
+            Aspect::Meta(dht_meta_data) => Address::from(dht_meta_data.address.clone()),
+        }
+    }
+}
+
+impl AddressableContent for Aspect {
+    fn content(&self) -> Content {
+        self.to_owned().into()
+    }
+
+    fn try_from_content(content: &Content) -> Result<Self, HolochainError> {
+        Aspect::try_from(content.to_owned())
+    }
+}
+
+/// Digests of the aspects a node holds at addresses the receiver holds too, by bucket.
+/// Buckets without aspects are left out.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct GossipDigest {
+    pub buckets: BTreeMap<u32, Address>,
+}
+
+/// Read the gossip interval from a backend config
+pub fn gossip_interval(backend_config: &serde_json::Value) -> Duration {
+    Duration::from_millis(
+        backend_config["gossipInterval"]
+            .as_u64()
+            .unwrap_or(DEFAULT_GOSSIP_INTERVAL),
+    )
+}
+
+fn bucket(address: &Address) -> u32 {
+    location(&address.to_string()) >> (32 - BUCKET_BITS)
+}
+
+fn digest(aspects: &BTreeSet<Address>) -> Address {
+    let aspects: Vec<String> = aspects.iter().map(Address::to_string).collect();
+    Address::encode_from_str(&aspects.join(","), Hash::SHA2256)
+}
+
+/// The addresses of the aspects we hold or rejected at addresses the given agent holds
+/// too, by bucket. Agents that gossip with us are peers, even if they did not connect to
+/// us yet.
+fn shared_aspects(
+    agent_id: &str,
+    context: &Arc<Context>,
+) -> Result<BTreeMap<u32, BTreeSet<Address>>, HolochainError> {
+    let (mut peer_store, meta_storage) = {
+        let state = context
+            .state()
+            .ok_or_else(|| HolochainError::ErrorGeneric("State not initialized".to_string()))?;
+        (
+            state.network().peer_store.clone(),
+            state.dht().meta_storage(),
+        )
+    };
+    peer_store.add_peer(agent_id, 0);
+
+    let mut shared: BTreeMap<u32, BTreeSet<Address>> = BTreeMap::new();
+    for attribute in &[ASPECT_NAME, REJECTED_ASPECT_NAME] {
+        for eavi in meta_storage.read()?.fetch_eavi(
+            None,
+            Some(attribute.to_string()),
+            None,
+            IndexQuery::new(std::i64::MIN, std::i64::MAX),
+        )? {
+            if peer_store.holds(agent_id, &eavi.entity()) {
+                shared
+                    .entry(bucket(&eavi.entity()))
+                    .or_insert_with(BTreeSet::new)
+                    .insert(eavi.value());
+            }
+        }
+    }
+    Ok(shared)
+}
+
+/// The digest we send to the given agent
+pub fn gossip_digest(
+    agent_id: &str,
+    context: &Arc<Context>,
+) -> Result<GossipDigest, HolochainError> {
+    Ok(GossipDigest {
+        buckets: shared_aspects(agent_id, context)?
+            .iter()
+            .map(|(bucket, aspects)| (*bucket, digest(aspects)))
+            .collect(),
+    })
+}
+
+/// The addresses of the aspects the agent that sent us the given digest might miss:
+/// the ones we hold in buckets where its digest differs from ours, at most
+/// MAX_GOSSIP_ADDRESSES of them.
+pub fn differing_aspects(
+    agent_id: &str,
+    gossip_digest: &GossipDigest,
+    context: &Arc<Context>,
+) -> Result<Vec<Address>, HolochainError> {
+    let content_storage = context
+        .state()
+        .ok_or_else(|| HolochainError::ErrorGeneric("State not initialized".to_string()))?
+        .dht()
+        .content_storage();
+    let mut differing = Vec::new();
+    for (bucket, aspects) in shared_aspects(agent_id, context)? {
+        if gossip_digest.buckets.get(&bucket) == Some(&digest(&aspects)) {
+            continue;
+        }
+        for address in aspects {
+            if differing.len() == MAX_GOSSIP_ADDRESSES {
+                return Ok(differing);
+            }
+            if content_storage.read()?.contains(&address)? {
+                differing.push(address);
+            }
+        }
+    }
+    Ok(differing)
+}
+
+/// The aspects with the given addresses that we neither hold nor rejected
+pub fn unknown_aspects(
+    addresses: Vec<Address>,
+    context: &Arc<Context>,
+) -> Result<Vec<Address>, HolochainError> {
+    let (content_storage, meta_storage) = {
+        let state = context
+            .state()
+            .ok_or_else(|| HolochainError::ErrorGeneric("State not initialized".to_string()))?;
+        (state.dht().content_storage(), state.dht().meta_storage())
+    };
+    let mut unknown = Vec::new();
+    for address in addresses.into_iter().take(MAX_GOSSIP_ADDRESSES) {
+        if content_storage.read()?.contains(&address)? {
+            continue;
+        }
+        let rejections = meta_storage.read()?.fetch_eavi(
+            None,
+            Some(REJECTED_ASPECT_NAME.to_string()),
+            Some(address.clone()),
+            IndexQuery::new(std::i64::MIN, std::i64::MAX),
+        )?;
+        if rejections.is_empty() {
+            unknown.push(address);
+        }
+    }
+    Ok(unknown)
+}
+
+/// The aspects with the given addresses that we hold at addresses the given agent holds
+/// too, as many as fit into MAX_GOSSIP_RESPONSE_LEN bytes.
+/// The agent asks for the others again after its next round of gossip.
+pub fn requested_aspects(
+    agent_id: &str,
+    addresses: &[Address],
+    context: &Arc<Context>,
+) -> Result<Vec<Aspect>, HolochainError> {
+    let content_storage = context
+        .state()
+        .ok_or_else(|| HolochainError::ErrorGeneric("State not initialized".to_string()))?
+        .dht()
+        .content_storage();
+    let shared: BTreeSet<Address> = shared_aspects(agent_id, context)?
+        .into_iter()
+        .flat_map(|(_, aspects)| aspects)
+        .collect();
+    let mut aspects = Vec::new();
+    let mut len = 0;
+    for address in addresses.iter().filter(|address| shared.contains(address)) {
+        if let Some(content) = content_storage.read()?.fetch(address)? {
+            len += String::from(content.clone()).len();
+            if len > MAX_GOSSIP_RESPONSE_LEN && !aspects.is_empty() {
+                break;
+            }
+            aspects.push(Aspect::try_from_content(&content)?);
+        }
+    }
+    Ok(aspects)
+}
+
+/// Remembers an aspect after we held what got published
+pub fn hold_aspect(aspect: Aspect, context: &Arc<Context>) {
+    dispatch_action(
+        context.action_channel(),
+        ActionWrapper::new(Action::HoldAspect(aspect)),
+    );
+}
+
+/// Remembers an aspect we found invalid, so we do not fetch it again
+pub fn reject_aspect(aspect: Aspect, context: &Arc<Context>) {
+    dispatch_action(
+        context.action_channel(),
+        ActionWrapper::new(Action::RejectAspect(aspect)),
+    );
+}
+
+/// Holds the aspects we got through gossip that we do not have yet.
+/// They get validated like any other published data before we hold them.
+pub fn hold_missing_aspects(aspects: Vec<Aspect>, context: &Arc<Context>) {
+    let content_storage = context.state().unwrap().dht().content_storage();
+    for aspect in aspects {
+        if content_storage.read().unwrap().contains(&aspect.address()) == Ok(true) {
+            continue;
+        }
+        context.log(format!(
+            "debug/net/gossip: got missing aspect at {}",
+            aspect.held_at()
+        ));
+        match aspect {
+            Aspect::Entry(dht_data) => handle_store_dht(dht_data, context.clone()),
+            Aspect::Meta(dht_meta_data) => handle_store_dht_meta(dht_meta_data, context.clone()),
+        }
+    }
+}
+
+fn send_message(
+    agent_id: Address,
+    message: DirectMessage,
+    msg_id: String,
+    is_response: bool,
+    context: &Arc<Context>,
+) {
+    let direct_message_data = DirectMessageData {
+        address: agent_id,
+        message,
+        msg_id,
+        is_response,
+    };
+    dispatch_action(
+        context.action_channel(),
+        ActionWrapper::new(Action::SendDirectMessage(direct_message_data)),
+    );
+}
+
+/// Sends our digest to the given agent, which responds with the addresses of the aspects
+/// we might miss
+pub fn gossip_with(agent_id: &str, context: &Arc<Context>) -> Result<(), HolochainError> {
+    send_message(
+        Address::from(agent_id),
+        DirectMessage::Gossip(gossip_digest(agent_id, context)?),
+        ProcessUniqueId::new().to_string(),
+        false,
+        context,
+    );
+    Ok(())
+}
+
+/// Responds to the digest of another holder with the addresses of the aspects it might miss
+pub fn respond_gossip(
+    to_agent_id: Address,
+    msg_id: String,
+    gossip_digest: GossipDigest,
+    context: Arc<Context>,
+) {
+    let addresses = differing_aspects(&to_agent_id.to_string(), &gossip_digest, &context)
+        .unwrap_or_else(|error| {
+            context.log(format!("err/net/gossip: {}", error));
+            Vec::new()
+        });
+    send_message(
+        to_agent_id,
+        DirectMessage::GossipAddresses(addresses),
+        msg_id,
+        true,
+        &context,
+    );
+}
+
+/// Asks the holder that responded to our gossip for the aspects we do not know yet
+pub fn fetch_unknown_aspects(
+    from_agent_id: Address,
+    addresses: Vec<Address>,
+    context: &Arc<Context>,
+) {
+    match unknown_aspects(addresses, context) {
+        Ok(ref unknown) if unknown.is_empty() => {}
+        Ok(unknown) => send_message(
+            from_agent_id,
+            DirectMessage::FetchAspects(unknown),
+            ProcessUniqueId::new().to_string(),
+            false,
+            context,
+        ),
+        Err(error) => context.log(format!("err/net/gossip: {}", error)),
+    }
+}
+
+/// Responds to another holder with the aspects it asked for
+pub fn respond_fetch_aspects(
+    to_agent_id: Address,
+    msg_id: String,
+    addresses: Vec<Address>,
+    context: Arc<Context>,
+) {
+    let aspects =
+        requested_aspects(&to_agent_id.to_string(), &addresses, &context).unwrap_or_else(|error| {
+            context.log(format!("err/net/gossip: {}", error));
+            Vec::new()
+        });
+    send_message(
+        to_agent_id,
+        DirectMessage::GossipAspects(aspects),
+        msg_id,
+        true,
+        &context,
+    );
+}
+
+/// Removes the peers we did not hear from for `SILENT_ROUNDS_UNTIL_GONE` rounds of
+/// gossip with every peer, in case the network did not tell us they went away.
+fn expire_peers(context: &Arc<Context>, interval: Duration) {
+    let peer_store = match context.state() {
+        Some(state) => state.network().peer_store.clone(),
+        None => return,
+    };
+    let silence = interval * SILENT_ROUNDS_UNTIL_GONE * peer_store.peers().len() as u32;
+    let since = Utc::now().timestamp_millis() - silence.as_millis() as i64;
+    for agent_id in peer_store.peers_not_seen_since(since) {
+        context.log(format!(
+            "debug/net/gossip: did not hear from {} for {:?}",
+            agent_id, silence
+        ));
+        remove_peer(&agent_id, context);
+    }
+}
+
+/// Gossips with one known peer after the other, every `interval`,
+/// for as long as the context is around. Peers that stay silent get removed.
+pub fn start_gossip(context: &Arc<Context>, interval: Duration) {
+    let context = Arc::downgrade(context);
+    thread::spawn(move || {
+        let mut round = 0;
+        loop {
+            thread::sleep(interval);
+            let context = match context.upgrade() {
+                Some(context) => context,
+                None => break,
+            };
+            expire_peers(&context, interval);
+            let peers = match context.state() {
+                Some(state) => state.network().peer_store.peers(),
+                // the instance is not initialized yet, there is nobody to gossip with
+                None => continue,
+            };
+            if peers.is_empty() {
+                continue;
+            }
+            let peer = &peers[round % peers.len()];
+            round += 1;
+            if let Err(error) = gossip_with(&peer.agent_id, &context) {
+                context.log(format!("err/net/gossip: {}", error));
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{
+        instance::dispatch_action_and_wait,
+        nucleus::actions::tests::{instance_by_name, test_dna},
+    };
+    use holochain_core_types::entry::test_entry;
+
+    fn test_aspect(msg_id: &str) -> Aspect {
+        Aspect::entry(DhtData {
+            msg_id: msg_id.to_string(),
+            address: test_entry().address().to_string(),
+            agent_id: "jill".to_string(),
+            content: json!({ "entry": "content" }),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn holders_send_the_aspects_others_miss() {
+        let netname = Some("holders_send_the_aspects_others_miss");
+        let (_instance, context) = instance_by_name("jill", test_dna(), netname);
+        let aspect = test_aspect("some message");
+        assert_eq!(aspect.address(), test_aspect("other message").address());
+        assert_eq!(aspect.held_at(), test_entry().address());
+        dispatch_action_and_wait(
+            context.action_channel(),
+            context.observer_channel(),
+            ActionWrapper::new(Action::HoldAspect(aspect.clone())),
+        );
+
+        let empty_digest = GossipDigest::default();
+        assert_eq!(
+            differing_aspects("jack", &empty_digest, &context),
+            Ok(vec![aspect.address()])
+        );
+        assert_eq!(
+            requested_aspects("jack", &[aspect.address()], &context),
+            Ok(vec![aspect.clone()])
+        );
+        assert_eq!(
+            unknown_aspects(vec![aspect.address()], &context),
+            Ok(Vec::new())
+        );
+
+        let our_digest = gossip_digest("jack", &context).unwrap();
+        assert_eq!(our_digest.buckets.len(), 1);
+        assert_eq!(
+            differing_aspects("jack", &our_digest, &context),
+            Ok(Vec::new())
+        );
+    }
+    #[test]
+    fn rejected_aspects_count_for_digests_but_do_not_get_sent() {
+        let netname = Some("rejected_aspects_count_for_digests_but_do_not_get_sent");
+        let (_instance, context) = instance_by_name("jill", test_dna(), netname);
+        let aspect = test_aspect("some message");
+        assert_eq!(
+            unknown_aspects(vec![aspect.address()], &context),
+            Ok(vec![aspect.address()])
+        );
+        dispatch_action_and_wait(
+            context.action_channel(),
+            context.observer_channel(),
+            ActionWrapper::new(Action::RejectAspect(aspect.clone())),
+        );
+
+        assert_eq!(
+            unknown_aspects(vec![aspect.address()], &context),
+            Ok(Vec::new())
+        );
+        let mut aspects = BTreeSet::new();
+        aspects.insert(aspect.address());
+        assert_eq!(
+            gossip_digest("jack", &context)
+                .unwrap()
+                .buckets
+                .get(&bucket(&aspect.held_at())),
+            Some(&digest(&aspects))
+        );
+        assert_eq!(
+            differing_aspects("jack", &GossipDigest::default(), &context),
+            Ok(Vec::new())
+        );
+        assert_eq!(
+            requested_aspects("jack", &[aspect.address()], &context),
+            Ok(Vec::new())
+        );
+    }
+}
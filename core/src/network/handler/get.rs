@@ -2,29 +2,74 @@ use crate::{
     action::{Action, ActionWrapper},
     context::Context,
     instance::dispatch_action,
+    network::encrypted_entry::is_encrypted_entry_type,
     nucleus,
 };
+use chrono::Utc;
 use holochain_core_types::cas::content::Address;
 use holochain_net_connection::json_protocol::{DhtData, DhtMetaData, GetDhtData, GetDhtMetaData};
+use holochain_wasm_utils::api_serialization::get_links::LinksStatusRequestKind;
 use regex::Regex;
-use std::{collections::BTreeSet, sync::Arc};
+use std::sync::Arc;
 
 lazy_static! {
-    static ref LINK: Regex =
-        Regex::new(r"^link__(.*)$").expect("This string literal is a valid regex");
+    static ref LINK: Regex = Regex::new(r"^(link|removed_link|any_link)__(.*)$")
+        .expect("This string literal is a valid regex");
+}
+
+/// Splits the attribute of a GET META request for links into the link tag and the
+/// requested link status, see get_links_attribute().
+fn parse_links_attribute(attribute: &str) -> Option<(String, LinksStatusRequestKind)> {
+    LINK.captures(attribute).map(|captures| {
+        let status = match &captures[1] {
+            "removed_link" => LinksStatusRequestKind::Deleted,
+            "any_link" => LinksStatusRequestKind::All,
+            _ => LinksStatusRequestKind::Live,
+        };
+        (captures[2].to_string(), status)
+    })
+}
+
+/// Keeps data other nodes ask for from getting garbage collected
+fn record_request(address: Address, context: &Arc<Context>) {
+    let action_wrapper = ActionWrapper::new(Action::RecordRequest((
+        address,
+        Utc::now().timestamp_nanos(),
+    )));
+    dispatch_action(context.action_channel(), action_wrapper);
 }
 
 /// The network has requested a DHT entry from us.
 /// Lets try to get it and trigger a response.
 pub fn handle_get_dht(get_dht_data: GetDhtData, context: Arc<Context>) {
-    let maybe_entry_with_meta = nucleus::actions::get_entry::get_entry_with_meta(
-        &context,
-        Address::from(get_dht_data.address.clone()),
-    )
-    .unwrap_or_else(|error| {
-        context.log(format!("err/net: Error trying to find entry {:?}", error));
-        None
-    });
+    let address = Address::from(get_dht_data.address.clone());
+    record_request(address.clone(), &context);
+
+    // Entries of encrypted entry types only ever leave this node encrypted
+    match nucleus::actions::get_entry::get_encrypted_entry_with_meta(&context, address.clone()) {
+        Ok(Some(encrypted_entry_with_meta)) => {
+            let action_wrapper = ActionWrapper::new(Action::RespondGetEncrypted((
+                get_dht_data,
+                encrypted_entry_with_meta,
+            )));
+            dispatch_action(context.action_channel(), action_wrapper.clone());
+            return;
+        }
+        Ok(None) => {}
+        Err(error) => context.log(format!(
+            "err/net: Error trying to find encrypted entry {:?}",
+            error
+        )),
+    }
+
+    let maybe_entry_with_meta = nucleus::actions::get_entry::get_entry_with_meta(&context, address)
+        .unwrap_or_else(|error| {
+            context.log(format!("err/net: Error trying to find entry {:?}", error));
+            None
+        })
+        .filter(|entry_with_meta| {
+            !is_encrypted_entry_type(&entry_with_meta.entry.entry_type(), &context)
+        });
 
     let action_wrapper =
         ActionWrapper::new(Action::RespondGet((get_dht_data, maybe_entry_with_meta)));
@@ -38,14 +83,8 @@ pub fn handle_get_dht_result(dht_data: DhtData, context: Arc<Context>) {
 }
 
 pub fn handle_get_dht_meta(get_dht_meta_data: GetDhtMetaData, context: Arc<Context>) {
-    if LINK.is_match(&get_dht_meta_data.attribute) {
-        let tag = LINK
-            .captures(&get_dht_meta_data.attribute)
-            .unwrap()
-            .get(1)
-            .unwrap()
-            .as_str()
-            .to_string();
+    if let Some((tag, status)) = parse_links_attribute(&get_dht_meta_data.attribute) {
+        record_request(Address::from(get_dht_meta_data.address.clone()), &context);
         let links = context
             .state()
             .unwrap()
@@ -53,11 +92,13 @@ pub fn handle_get_dht_meta(get_dht_meta_data: GetDhtMetaData, context: Arc<Conte
             .get_links(
                 Address::from(get_dht_meta_data.address.clone()),
                 tag.clone(),
+                get_dht_meta_data.tag_match.clone(),
+                status,
             )
-            .unwrap_or(BTreeSet::new())
-            .into_iter()
-            .map(|eav| eav.value())
-            .collect::<Vec<_>>();
+            .unwrap_or_else(|error| {
+                context.log(format!("err/net: Error trying to find links {:?}", error));
+                Vec::new()
+            });
         let action_wrapper =
             ActionWrapper::new(Action::RespondGetLinks((get_dht_meta_data, links)));
         dispatch_action(context.action_channel(), action_wrapper.clone());
@@ -66,15 +107,32 @@ pub fn handle_get_dht_meta(get_dht_meta_data: GetDhtMetaData, context: Arc<Conte
 
 /// The network comes back with a result to our previous GET META request.
 pub fn handle_get_dht_meta_result(dht_meta_data: DhtMetaData, context: Arc<Context>) {
-    if LINK.is_match(&dht_meta_data.attribute) {
-        let tag = LINK
-            .captures(&dht_meta_data.attribute)
-            .unwrap()
-            .get(1)
-            .unwrap()
-            .as_str()
-            .to_string();
-        let action_wrapper = ActionWrapper::new(Action::HandleGetLinksResult((dht_meta_data, tag)));
+    if parse_links_attribute(&dht_meta_data.attribute).is_some() {
+        let action_wrapper = ActionWrapper::new(Action::HandleGetLinksResult(dht_meta_data));
         dispatch_action(context.action_channel(), action_wrapper.clone());
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::network::reducers::get_links::get_links_attribute;
+
+    #[test]
+    fn links_attribute_round_trip() {
+        for status in vec![
+            LinksStatusRequestKind::Live,
+            LinksStatusRequestKind::Deleted,
+            LinksStatusRequestKind::All,
+        ] {
+            for tag in vec!["test-tag", "test-", "^test-[a-z]+$"] {
+                let attribute = get_links_attribute(tag, &status);
+                assert_eq!(
+                    parse_links_attribute(&attribute),
+                    Some((String::from(tag), status.clone()))
+                );
+            }
+        }
+        assert_eq!(parse_links_attribute("crud-status"), None);
+    }
+}
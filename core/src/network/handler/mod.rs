@@ -3,18 +3,26 @@ pub mod send;
 pub mod store;
 
 use crate::{
+    action::{Action, ActionWrapper},
     context::Context,
+    instance::{dispatch_action, dispatch_action_and_wait},
     network::{
         actions::publish::publish,
+        entry_with_header::fetch_entry_with_header,
         handler::{get::*, send::*, store::*},
+        peer_store::PeerStore,
     },
 };
+use chrono::Utc;
 use futures::executor::block_on;
 use holochain_core_types::{
     cas::content::{Address, AddressableContent},
-    hash::HashString,
+    entry::Entry,
+};
+use holochain_net_connection::{
+    json_protocol::{JsonProtocol, PeerChallengeData},
+    net_connection::NetHandler,
 };
-use holochain_net_connection::{json_protocol::JsonProtocol, net_connection::NetHandler};
 use std::{convert::TryFrom, sync::Arc};
 
 // FIXME: Temporary hack to ignore messages incorrectly sent to us by the networking
@@ -56,6 +64,7 @@ pub fn create_handler(c: &Arc<Context>) -> NetHandler {
                     return Ok(());
                 }
                 context.log(format!("debug/net/handle: StoreDht: {:?}", dht_data));
+                peer_seen(&context, &dht_data.agent_id);
                 handle_store_dht(dht_data, context.clone())
             }
             Ok(JsonProtocol::HandleStoreDhtMeta(dht_meta_data)) => {
@@ -70,6 +79,7 @@ pub fn create_handler(c: &Arc<Context>) -> NetHandler {
                     ));
                     return Ok(());
                 }
+                peer_seen(&context, &dht_meta_data.from_agent_id);
                 handle_store_dht_meta(dht_meta_data, context.clone())
             }
             Ok(JsonProtocol::HandleGetDhtData(get_dht_data)) => {
@@ -78,6 +88,7 @@ pub fn create_handler(c: &Arc<Context>) -> NetHandler {
                     return Ok(());
                 }
                 context.log(format!("debug/net/handle: GetDht: {:?}", get_dht_data));
+                peer_seen(&context, &get_dht_data.from_agent_id);
                 handle_get_dht(get_dht_data, context.clone())
             }
             Ok(JsonProtocol::GetDhtDataResult(dht_data)) => {
@@ -93,6 +104,7 @@ pub fn create_handler(c: &Arc<Context>) -> NetHandler {
                         "debug/net/handle: GetDhtMeta: {:?}",
                         get_dht_meta_data
                     ));
+                    peer_seen(&context, &get_dht_meta_data.from_agent_id);
                     handle_get_dht_meta(get_dht_meta_data, context.clone())
                 }
             }
@@ -133,6 +145,7 @@ pub fn create_handler(c: &Arc<Context>) -> NetHandler {
                 ) {
                     return Ok(());
                 }
+                peer_seen(&context, &message_data.from_agent_id);
                 handle_send(message_data, context.clone())
             }
             Ok(JsonProtocol::SendMessageResult(message_data)) => {
@@ -143,6 +156,7 @@ pub fn create_handler(c: &Arc<Context>) -> NetHandler {
                 ) {
                     return Ok(());
                 }
+                peer_seen(&context, &message_data.from_agent_id);
                 handle_send_result(message_data, context.clone())
             }
             Ok(JsonProtocol::PeerConnected(peer_data)) => {
@@ -154,9 +168,28 @@ pub fn create_handler(c: &Arc<Context>) -> NetHandler {
                 if is_me(&context, &peer_data.dna_address, &peer_data.agent_id) {
                     return Ok(());
                 }
-                // Total hack in lieu of a world-model.  Just republish everything
-                // when a new person comes on-line!!
-                republish_all_public_chain_entries(&context);
+                context.log(format!("debug/net/handle: PeerConnected: {:?}", peer_data));
+                add_peer(&peer_data.agent_id, &context);
+            }
+            Ok(JsonProtocol::PeerDisconnected(peer_data)) => {
+                if !is_me(&context, &peer_data.dna_address, "") {
+                    return Ok(());
+                }
+                context.log(format!(
+                    "debug/net/handle: PeerDisconnected: {:?}",
+                    peer_data
+                ));
+                remove_peer(&peer_data.agent_id, &context);
+            }
+            Ok(JsonProtocol::HandleSignPeerChallenge(challenge_data)) => {
+                if !is_me(
+                    &context,
+                    &challenge_data.dna_address,
+                    &challenge_data.agent_id,
+                ) {
+                    return Ok(());
+                }
+                sign_peer_challenge(challenge_data, &context);
             }
             _ => {}
         }
@@ -164,20 +197,123 @@ pub fn create_handler(c: &Arc<Context>) -> NetHandler {
     })
 }
 
-fn republish_all_public_chain_entries(context: &Arc<Context>) {
-    let chain = context.state().unwrap().agent().chain();
-    let top_header = context.state().unwrap().agent().top_chain_header();
+/// Adds a connecting agent to the peer store.
+/// Agents we did not know yet get the entries of our chain they are now responsible for.
+fn add_peer(agent_id: &str, context: &Arc<Context>) {
+    let old_peer_store = context.state().unwrap().network().peer_store.clone();
+    dispatch_action_and_wait(
+        context.action_channel(),
+        context.observer_channel(),
+        ActionWrapper::new(Action::AddPeer((
+            agent_id.to_string(),
+            Utc::now().timestamp_millis(),
+        ))),
+    );
+    if !old_peer_store.contains(agent_id) {
+        publish_to_new_holders(&old_peer_store, context);
+    }
+}
+
+/// Removes an agent that went away from the peer store.
+/// The agents that take over its part of the address space get the entries of our chain
+/// they are now responsible for.
+pub fn remove_peer(agent_id: &str, context: &Arc<Context>) {
+    let old_peer_store = context.state().unwrap().network().peer_store.clone();
+    if !old_peer_store.contains(agent_id) {
+        return;
+    }
+    dispatch_action_and_wait(
+        context.action_channel(),
+        context.observer_channel(),
+        ActionWrapper::new(Action::RemovePeer(agent_id.to_string())),
+    );
+    publish_to_new_holders(&old_peer_store, context);
+}
+
+/// Signs the challenge of another node, which only believes that our agent runs on
+/// this node once it has the signature.
+fn sign_peer_challenge(mut challenge_data: PeerChallengeData, context: &Arc<Context>) {
+    match context.sign(&challenge_data.signed_data()) {
+        Ok(signature) => {
+            challenge_data.signature = String::from(signature);
+            dispatch_action(
+                context.action_channel(),
+                ActionWrapper::new(Action::RespondPeerChallenge(challenge_data)),
+            );
+        }
+        Err(error) => context.log(format!(
+            "err/net/handle: could not sign peer challenge: {}",
+            error
+        )),
+    }
+}
+
+/// Refreshes the last seen time of a known peer
+fn peer_seen(context: &Arc<Context>, agent_id: &str) {
+    let is_known = context
+        .state()
+        .unwrap()
+        .network()
+        .peer_store
+        .contains(agent_id);
+    if is_known {
+        dispatch_action(
+            context.action_channel(),
+            ActionWrapper::new(Action::PeerSeen((
+                agent_id.to_string(),
+                Utc::now().timestamp_millis(),
+            ))),
+        );
+    }
+}
+
+/// The addresses the DHT stores an entry of our chain at:
+/// the entry itself and, for links, the link meta on the base.
+fn published_addresses(address: &Address, context: &Arc<Context>) -> Vec<Address> {
+    let mut addresses = vec![address.clone()];
+    match fetch_entry_with_header(address, context).map(|entry_with_header| entry_with_header.entry)
+    {
+        Ok(Entry::LinkAdd(link_add)) => addresses.push(link_add.link().base().clone()),
+        Ok(Entry::LinkRemove(link_remove)) => addresses.push(link_remove.link().base().clone()),
+        _ => {}
+    }
+    addresses
+}
+
+/// Publishes the public entries of our chain that got holders which they did not have
+/// with the peers of `old_peer_store`.
+fn publish_to_new_holders(old_peer_store: &PeerStore, context: &Arc<Context>) {
+    let (chain, top_header, peer_store) = {
+        let state = context.state().unwrap();
+        (
+            state.agent().chain(),
+            state.agent().top_chain_header(),
+            state.network().peer_store.clone(),
+        )
+    };
+    let has_new_holders = |address: &Address| {
+        let old_holders = old_peer_store.holders(address);
+        peer_store
+            .holders(address)
+            .iter()
+            .any(|holder| !old_holders.contains(holder))
+    };
     chain
         .iter(&top_header)
-        .filter(|ref chain_header| chain_header.entry_type().can_publish())
-        .for_each(|chain_header| {
-            let hash = HashString::from(chain_header.entry_address().to_string());
-            match block_on(publish(hash.clone(), context)) {
+        .filter(|chain_header| chain_header.entry_type().can_publish())
+        .map(|chain_header| chain_header.entry_address().clone())
+        .filter(|address| {
+            published_addresses(address, context)
+                .iter()
+                .any(|published| has_new_holders(published))
+        })
+        .for_each(
+            |address| match block_on(publish(address.clone(), context)) {
                 Err(e) => context.log(format!(
                     "err/net/handle: unable to publish {:?}, got error: {:?}",
-                    hash, e
+                    address, e
                 )),
                 _ => {}
-            }
-        });
+            },
+        );
 }
@@ -2,14 +2,20 @@ use crate::{
     action::{Action, ActionWrapper},
     context::Context,
     instance::dispatch_action,
-    network::direct_message::DirectMessage,
+    network::{
+        direct_message::DirectMessage,
+        gossip::{
+            fetch_unknown_aspects, hold_missing_aspects, respond_fetch_aspects, respond_gossip,
+        },
+        validation_receipt::handle_validation_receipt,
+    },
     workflows::{
         handle_custom_direct_message::handle_custom_direct_message,
         respond_validation_package_request::respond_validation_package_request,
     },
 };
 use futures::executor::block_on;
-use holochain_core_types::cas::content::Address;
+use holochain_core_types::cas::content::{Address, AddressableContent};
 use std::{sync::Arc, thread};
 
 use holochain_net_connection::json_protocol::MessageData;
@@ -50,6 +56,37 @@ pub fn handle_send(message_data: MessageData, context: Arc<Context>) {
         DirectMessage::ValidationPackage(_) => context.log(
             "err/net: Got DirectMessage::ValidationPackage as initial message. This should not happen.",
         ),
+        DirectMessage::Gossip(gossip_digest) => {
+            thread::spawn(move || {
+                respond_gossip(
+                    Address::from(message_data.from_agent_id),
+                    message_data.msg_id,
+                    gossip_digest,
+                    context.clone(),
+                );
+            });
+        }
+        DirectMessage::GossipAddresses(_) => context.log(
+            "err/net: Got DirectMessage::GossipAddresses as initial message. This should not happen.",
+        ),
+        DirectMessage::FetchAspects(addresses) => {
+            thread::spawn(move || {
+                respond_fetch_aspects(
+                    Address::from(message_data.from_agent_id),
+                    message_data.msg_id,
+                    addresses,
+                    context.clone(),
+                );
+            });
+        }
+        DirectMessage::GossipAspects(_) => context.log(
+            "err/net: Got DirectMessage::GossipAspects as initial message. This should not happen.",
+        ),
+        DirectMessage::ValidationReceipt(receipt) => handle_validation_receipt(
+            Address::from(message_data.from_agent_id),
+            receipt,
+            context.clone(),
+        ),
     };
 }
 
@@ -107,5 +144,54 @@ pub fn handle_send_result(message_data: MessageData, context: Arc<Context>) {
                 ActionWrapper::new(Action::ResolveDirectConnection(message_data.msg_id));
             dispatch_action(context.action_channel(), action_wrapper.clone());
         }
+        DirectMessage::Gossip(_) => context.log(
+            "err/net: Got DirectMessage::Gossip as a response. This should not happen.",
+        ),
+        DirectMessage::FetchAspects(_) => context.log(
+            "err/net: Got DirectMessage::FetchAspects as a response. This should not happen.",
+        ),
+        DirectMessage::ValidationReceipt(_) => context.log(
+            "err/net: Got DirectMessage::ValidationReceipt as a response. This should not happen.",
+        ),
+        DirectMessage::GossipAddresses(addresses) => {
+            match initial_message {
+                Some(DirectMessage::Gossip(_)) => {}
+                _ => {
+                    context.log("err/net: Received gossip but could not find our digest in history. Not able to process.");
+                    return;
+                }
+            }
+
+            let action_wrapper =
+                ActionWrapper::new(Action::ResolveDirectConnection(message_data.msg_id));
+            dispatch_action(context.action_channel(), action_wrapper.clone());
+
+            thread::spawn(move || {
+                fetch_unknown_aspects(
+                    Address::from(message_data.from_agent_id),
+                    addresses,
+                    &context,
+                );
+            });
+        }
+        DirectMessage::GossipAspects(aspects) => {
+            let requested = match initial_message {
+                Some(DirectMessage::FetchAspects(requested)) => requested,
+                _ => {
+                    context.log("err/net: Received gossip but could not find our fetch in history. Not able to process.");
+                    return;
+                }
+            };
+            let aspects = aspects
+                .into_iter()
+                .filter(|aspect| requested.contains(&aspect.address()))
+                .collect();
+
+            let action_wrapper =
+                ActionWrapper::new(Action::ResolveDirectConnection(message_data.msg_id));
+            dispatch_action(context.action_channel(), action_wrapper.clone());
+
+            hold_missing_aspects(aspects, &context);
+        }
     };
 }
@@ -1,62 +1,117 @@
 use crate::{
     context::Context,
-    network::entry_with_header::EntryWithHeader,
-    workflows::{hold_entry::hold_entry_workflow, hold_link::hold_link_workflow},
+    network::{
+        encrypted_entry::EncryptedEntryWithHeader,
+        entry_with_header::EntryWithHeader,
+        gossip::{hold_aspect, reject_aspect, Aspect},
+        validation_receipt::send_validation_receipt,
+    },
+    workflows::{
+        hold_encrypted_entry::hold_encrypted_entry_workflow, hold_entry::hold_entry_workflow,
+        hold_link::hold_link_workflow,
+    },
 };
 use futures::executor::block_on;
 use holochain_core_types::{
     cas::content::Address,
     crud_status::{CrudStatus, LINK_NAME, STATUS_NAME},
+    error::HolochainError,
 };
 use holochain_net_connection::json_protocol::{DhtData, DhtMetaData};
 use std::{sync::Arc, thread};
 
 /// The network requests us to store (i.e. hold) the given entry.
+/// Once held, the published data gets remembered as an aspect we gossip about.
+/// The sources of plain entries get a receipt telling them whether we found it valid.
 pub fn handle_store_dht(dht_data: DhtData, context: Arc<Context>) {
-    let entry_with_header: EntryWithHeader =
-        serde_json::from_str(&serde_json::to_string(&dht_data.content).unwrap()).unwrap();
-    thread::spawn(move || {
-        match block_on(hold_entry_workflow(&entry_with_header, &context.clone())) {
-            Err(error) => context.log(format!("err/net/dht: {}", error)),
-            _ => (),
+    let content = dht_data.content.to_string();
+    let aspect = Aspect::entry(dht_data);
+    // Entries of encrypted entry types get published encrypted
+    if let Ok(encrypted_entry_with_header) =
+        serde_json::from_str::<EncryptedEntryWithHeader>(&content)
+    {
+        thread::spawn(move || {
+            let result = block_on(hold_encrypted_entry_workflow(
+                &encrypted_entry_with_header,
+                &context.clone(),
+            ));
+            remember_aspect(aspect, &result, &context);
+        });
+        return;
+    }
+    let entry_with_header: EntryWithHeader = match serde_json::from_str(&content) {
+        Ok(entry_with_header) => entry_with_header,
+        Err(error) => {
+            context.log(format!(
+                "err/net/dht: malformed entry at {}: {}",
+                aspect.held_at(),
+                error
+            ));
+            reject_aspect(aspect, &context);
+            return;
         }
+    };
+    thread::spawn(move || {
+        let result = block_on(hold_entry_workflow(&entry_with_header, &context.clone()));
+        remember_aspect(aspect, &result, &context);
+        send_validation_receipt(&entry_with_header.header, &result, &context);
     });
 }
 
+/// Remembers the aspect as held if holding it worked, or as rejected if it was invalid.
+/// Other errors, like an unreachable source, leave it to be fetched again.
+fn remember_aspect<T>(aspect: Aspect, result: &Result<T, HolochainError>, context: &Arc<Context>) {
+    match result {
+        Ok(_) => hold_aspect(aspect, context),
+        Err(HolochainError::ValidationFailed(error)) => {
+            context.log(format!("err/net/dht: invalid: {}", error));
+            reject_aspect(aspect, context);
+        }
+        Err(error) => context.log(format!("err/net/dht: {}", error)),
+    }
+}
+
 /// The network requests us to store meta information (links/CRUD/etc) for an
 /// entry that we hold.
 pub fn handle_store_dht_meta(dht_meta_data: DhtMetaData, context: Arc<Context>) {
     match dht_meta_data.attribute.as_ref() {
         "link" => {
             context.log("debug/net/handle: HandleStoreDhtMeta: got LINK. processing...");
-            let entry_with_header: EntryWithHeader = serde_json::from_str(
-                &serde_json::to_string(&dht_meta_data.content)
-                    .expect("dht_meta_data should be EntryWithHader"),
-            )
-            .expect("dht_meta_data should be EntryWithHader");
-            thread::spawn(move || {
-                match block_on(hold_link_workflow(&entry_with_header, &context.clone())) {
-                    Err(error) => context.log(format!("err/net/dht: {}", error)),
-                    _ => (),
+            let maybe_entry_with_header =
+                serde_json::from_str::<EntryWithHeader>(&dht_meta_data.content.to_string());
+            let aspect = Aspect::meta(dht_meta_data);
+            let entry_with_header = match maybe_entry_with_header {
+                Ok(entry_with_header) => entry_with_header,
+                Err(error) => {
+                    context.log(format!(
+                        "err/net/dht: malformed link at {}: {}",
+                        aspect.held_at(),
+                        error
+                    ));
+                    reject_aspect(aspect, &context);
+                    return;
                 }
+            };
+            thread::spawn(move || {
+                let result = block_on(hold_link_workflow(&entry_with_header, &context.clone()));
+                remember_aspect(aspect, &result, &context);
             });
         }
         STATUS_NAME => {
             context.log("debug/net/handle: HandleStoreDhtMeta: got CRUD status. processing...");
-            let _crud_status: CrudStatus = serde_json::from_str(
-                &serde_json::to_string(&dht_meta_data.content)
-                    .expect("dht_meta_data should be crud_status"),
-            )
-            .expect("dht_meta_data should be crud_status");
+            if let Err(error) =
+                serde_json::from_str::<CrudStatus>(&dht_meta_data.content.to_string())
+            {
+                context.log(format!("err/net/dht: malformed crud status: {}", error));
+            }
             // FIXME: block_on hold crud_status metadata in DHT?
         }
         LINK_NAME => {
             context.log("debug/net/handle: HandleStoreDhtMeta: got CRUD LINK. processing...");
-            let _crud_link: Address = serde_json::from_str(
-                &serde_json::to_string(&dht_meta_data.content)
-                    .expect("dht_meta_data should be crud_link"),
-            )
-            .expect("dht_meta_data should be crud_link");
+            if let Err(error) = serde_json::from_str::<Address>(&dht_meta_data.content.to_string())
+            {
+                context.log(format!("err/net/dht: malformed crud link: {}", error));
+            }
             // FIXME: block_on hold crud_link metadata in DHT?
         }
         _ => {}
@@ -1,9 +1,13 @@
 pub mod actions;
 pub mod direct_message;
+pub mod encrypted_entry;
 pub mod entry_with_header;
+pub mod gossip;
 pub mod handler;
+pub mod peer_store;
 pub mod reducers;
 pub mod state;
+pub mod validation_receipt;
 #[cfg(test)]
 pub mod test_utils;
 
@@ -29,6 +33,7 @@ pub mod tests {
         entry::{entry_type::test_app_entry_type, test_entry, Entry},
         link::Link,
     };
+    use std::{thread, time::Duration};
     use test_utils::*;
 
     #[test]
@@ -60,6 +65,37 @@ pub mod tests {
         assert_eq!(entry_with_meta.crud_status, CrudStatus::Live);
     }
 
+    #[test]
+    fn peers_get_added_when_they_connect() {
+        let netname = Some("peers_get_added_when_they_connect");
+        let mut dna = create_test_dna_with_wat("test_zome", "test_cap", None);
+        dna.uuid = String::from("peers_get_added_when_they_connect");
+        let (_, context1) =
+            test_instance_and_context_by_name(dna.clone(), "alice5", netname).unwrap();
+        let (_, context2) =
+            test_instance_and_context_by_name(dna.clone(), "bob5", netname).unwrap();
+
+        let knows_bob = || {
+            context1
+                .state()
+                .unwrap()
+                .network()
+                .peer_store
+                .contains(&context2.agent_id.key)
+        };
+        for _ in 0..50 {
+            if knows_bob() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        assert!(knows_bob());
+        let peers = context1.state().unwrap().network().peer_store.peers();
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].agent_id, context2.agent_id.key);
+        assert!(peers[0].last_seen > 0);
+    }
+
     #[test]
     fn get_non_existant_entry() {
         let netname = Some("get_non_existant_entry");
@@ -104,7 +140,7 @@ pub mod tests {
             test_instance_and_context_by_name(dna.clone(), "alice1", netname).unwrap();
 
         let entry = test_entry();
-        block_on(author_entry(&entry, None, &context1)).expect("Could not author entry");
+        block_on(author_entry(&entry, None, None, &context1)).expect("Could not author entry");
 
         let agent1_state = context1.state().unwrap().agent();
         let header = agent1_state
@@ -154,10 +190,16 @@ pub mod tests {
             entry_addresses[0].clone(),
             String::from("test-tag"),
             Default::default(),
+            Default::default(),
+            Default::default(),
         ));
 
         assert!(maybe_links.is_ok());
-        let links = maybe_links.unwrap();
+        let links: Vec<Address> = maybe_links
+            .unwrap()
+            .iter()
+            .map(|link| link.target().clone())
+            .collect();
         // can be in any order
         assert!(
             (links[0] == entry_addresses[1] || links[0] == entry_addresses[2])
@@ -0,0 +1,185 @@
+use holochain_core_types::cas::content::Address;
+use holochain_net::sharding::{distance, holders, location};
+pub use holochain_wasm_utils::api_serialization::peers::PeerInfo;
+use std::collections::BTreeMap;
+
+/// The agents of our DNA we know about, and which part of the address space each of
+/// them is responsible for.
+/// Responsibilities follow the same neighborhoods the network backends shard the DHT
+/// by (see holochain_net::sharding), so we can tell which of our entries a peer holds.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PeerStore {
+    agent_id: String,
+    redundancy: usize,
+    peers: BTreeMap<String, PeerInfo>,
+}
+
+impl PeerStore {
+    pub fn new(agent_id: String, redundancy: usize) -> Self {
+        PeerStore {
+            agent_id,
+            redundancy,
+            peers: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a peer, or refreshes it if we already know it.
+    /// Returns true if the peer is new to us.
+    pub fn add_peer(&mut self, agent_id: &str, now: i64) -> bool {
+        if agent_id == self.agent_id {
+            return false;
+        }
+        if let Some(peer) = self.peers.get_mut(agent_id) {
+            peer.last_seen = now;
+            return false;
+        }
+        self.peers.insert(
+            agent_id.to_string(),
+            PeerInfo {
+                agent_id: agent_id.to_string(),
+                location: location(agent_id),
+                arc_radius: std::u32::MAX,
+                last_seen: now,
+            },
+        );
+        self.update_arcs();
+        true
+    }
+
+    /// Records that we just heard from a peer. Unknown agents get ignored.
+    pub fn peer_seen(&mut self, agent_id: &str, now: i64) {
+        if let Some(peer) = self.peers.get_mut(agent_id) {
+            peer.last_seen = now;
+        }
+    }
+
+    /// Forgets a peer that went away.
+    /// Returns true if we knew the peer.
+    pub fn remove_peer(&mut self, agent_id: &str) -> bool {
+        if self.peers.remove(agent_id).is_none() {
+            return false;
+        }
+        self.update_arcs();
+        true
+    }
+
+    /// The peers we did not hear from since the given time
+    pub fn peers_not_seen_since(&self, since: i64) -> Vec<String> {
+        self.peers
+            .values()
+            .filter(|peer| peer.last_seen < since)
+            .map(|peer| peer.agent_id.clone())
+            .collect()
+    }
+
+    pub fn contains(&self, agent_id: &str) -> bool {
+        self.peers.contains_key(agent_id)
+    }
+
+    pub fn peer(&self, agent_id: &str) -> Option<&PeerInfo> {
+        self.peers.get(agent_id)
+    }
+
+    /// All known peers, ordered by agent id
+    pub fn peers(&self) -> Vec<PeerInfo> {
+        self.peers.values().cloned().collect()
+    }
+
+    /// The agents, including ourselves, that are responsible for holding `address`
+    pub fn holders(&self, address: &Address) -> Vec<String> {
+        holders(&address.to_string(), self.agent_ids(), self.redundancy)
+            .into_iter()
+            .map(String::from)
+            .collect()
+    }
+
+    /// Whether the given agent is one of the holders of `address`
+    pub fn holds(&self, agent_id: &str, address: &Address) -> bool {
+        self.holders(address)
+            .iter()
+            .any(|holder| holder == agent_id)
+    }
+
+    fn agent_ids(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.agent_id.as_str()).chain(self.peers.keys().map(String::as_str))
+    }
+
+    /// Estimates the arc of every peer from the distances to its closest neighbors.
+    /// An address is held by the `redundancy` agents closest to it, so an agent holds
+    /// roughly as far as its `redundancy`-th closest neighbor, spread over both sides
+    /// of the ring. Agents hold everything while there are no more agents than that.
+    fn update_arcs(&mut self) {
+        let redundancy = self.redundancy;
+        let locations: Vec<(String, u32)> = self
+            .agent_ids()
+            .map(|agent_id| (agent_id.to_string(), location(agent_id)))
+            .collect();
+        for peer in self.peers.values_mut() {
+            let mut distances: Vec<u32> = locations
+                .iter()
+                .filter(|(agent_id, _)| *agent_id != peer.agent_id)
+                .map(|(_, other)| distance(peer.location, *other))
+                .collect();
+            distances.sort();
+            peer.arc_radius = match redundancy {
+                0 => std::u32::MAX,
+                _ if distances.len() < redundancy => std::u32::MAX,
+                _ => {
+                    let neighbor = u64::from(distances[redundancy - 1]);
+                    let sides = (redundancy + redundancy % 2) as u64;
+                    (neighbor * redundancy as u64 / sides) as u32
+                }
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn it_tracks_peers_and_when_they_were_seen() {
+        let mut peer_store = PeerStore::new("me".to_string(), 2);
+        assert!(peer_store.add_peer("alice", 10));
+        assert!(!peer_store.add_peer("alice", 20));
+        assert!(!peer_store.add_peer("me", 20));
+        peer_store.peer_seen("alice", 30);
+        peer_store.peer_seen("bob", 30);
+
+        assert!(peer_store.contains("alice"));
+        assert!(!peer_store.contains("bob"));
+        assert_eq!(peer_store.peers().len(), 1);
+        let alice = peer_store.peer("alice").unwrap();
+        assert_eq!(alice.last_seen, 30);
+        assert_eq!(alice.location, location("alice"));
+        // two agents with a redundancy of two hold everything
+        assert_eq!(alice.arc_radius, std::u32::MAX);
+
+        assert!(peer_store.add_peer("bob", 40));
+        assert_eq!(
+            peer_store.peers_not_seen_since(35),
+            vec!["alice".to_string()]
+        );
+        assert!(peer_store.remove_peer("alice"));
+        assert!(!peer_store.remove_peer("alice"));
+        assert!(!peer_store.contains("alice"));
+        assert!(peer_store.peers_not_seen_since(35).is_empty());
+    }
+
+    #[test]
+    fn it_knows_which_peers_hold_an_address() {
+        let mut peer_store = PeerStore::new("me".to_string(), 1);
+        for i in 0..5 {
+            peer_store.add_peer(&format!("agent-{}", i), 0);
+        }
+        let address = Address::from("QmAddress");
+        let holders_of_address = peer_store.holders(&address);
+        assert_eq!(holders_of_address.len(), 1);
+        assert!(peer_store.holds(&holders_of_address[0], &address));
+        assert!(peer_store
+            .peers()
+            .iter()
+            .all(|peer| peer.arc_radius < std::u32::MAX));
+    }
+}
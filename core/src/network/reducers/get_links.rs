@@ -5,8 +5,20 @@ use crate::{
 };
 use holochain_core_types::error::HolochainError;
 use holochain_net_connection::json_protocol::{GetDhtMetaData, JsonProtocol};
+use holochain_wasm_utils::api_serialization::get_links::LinksStatusRequestKind;
 use std::sync::Arc;
 
+/// The attribute of a GET META request for links encodes the tag and the status of the
+/// requested links, since the request has no other place for them.
+pub fn get_links_attribute(tag: &str, status: &LinksStatusRequestKind) -> String {
+    let status = match status {
+        LinksStatusRequestKind::Live => "link",
+        LinksStatusRequestKind::Deleted => "removed_link",
+        LinksStatusRequestKind::All => "any_link",
+    };
+    format!("{}__{}", status, tag)
+}
+
 fn inner(network_state: &mut NetworkState, key: &GetLinksKey) -> Result<(), HolochainError> {
     network_state.initialized()?;
 
@@ -17,7 +29,8 @@ fn inner(network_state: &mut NetworkState, key: &GetLinksKey) -> Result<(), Holo
             dna_address: network_state.dna_address.clone().unwrap(),
             from_agent_id: network_state.agent_id.clone().unwrap(),
             address: key.base_address.to_string(),
-            attribute: format!("link__{}", key.tag),
+            attribute: get_links_attribute(&key.tag, &key.status),
+            tag_match: key.tag_match.clone(),
         }),
     )
 }
@@ -66,6 +79,9 @@ mod tests {
         state::test_store,
     };
     use holochain_core_types::error::HolochainError;
+    use holochain_wasm_utils::api_serialization::get_links::{
+        LinkTagMatch, LinksStatusRequestKind,
+    };
     //use std::sync::{Arc, RwLock};
 
     #[test]
@@ -78,6 +94,8 @@ mod tests {
         let key = GetLinksKey {
             base_address: entry.address(),
             tag: tag.clone(),
+            tag_match: LinkTagMatch::Exact,
+            status: LinksStatusRequestKind::Live,
             id: snowflake::ProcessUniqueId::new().to_string(),
         };
         let action_wrapper = ActionWrapper::new(Action::GetLinks(key.clone()));
@@ -120,6 +138,8 @@ mod tests {
         let key = GetLinksKey {
             base_address: entry.address(),
             tag: tag.clone(),
+            tag_match: LinkTagMatch::Exact,
+            status: LinksStatusRequestKind::Live,
             id: snowflake::ProcessUniqueId::new().to_string(),
         };
         let action_wrapper = ActionWrapper::new(Action::GetLinks(key.clone()));
@@ -162,6 +182,8 @@ mod tests {
         let key = GetLinksKey {
             base_address: entry.address(),
             tag: tag.clone(),
+            tag_match: LinkTagMatch::Exact,
+            status: LinksStatusRequestKind::Live,
             id: snowflake::ProcessUniqueId::new().to_string(),
         };
         let action_wrapper = ActionWrapper::new(Action::GetLinks(key.clone()));
@@ -1,22 +1,18 @@
-use crate::{
-    action::{ActionWrapper, GetLinksKey},
-    context::Context,
-    network::state::NetworkState,
-};
-use holochain_core_types::{cas::content::Address, error::HolochainError};
+use crate::{action::ActionWrapper, context::Context, network::state::NetworkState};
+use holochain_core_types::{cas::content::Address, error::HolochainError, link::Link};
 use holochain_net_connection::json_protocol::DhtMetaData;
 use std::sync::Arc;
 
 fn inner(
     network_state: &mut NetworkState,
     dht_meta_data: &DhtMetaData,
-) -> Result<Vec<Address>, HolochainError> {
+) -> Result<Vec<Link>, HolochainError> {
     network_state.initialized()?;
 
     let res = serde_json::from_str(&serde_json::to_string(&dht_meta_data.content).unwrap());
     if let Err(_) = res {
         return Err(HolochainError::ErrorGeneric(
-            "Failed to deserialize Vec<Address> from HandleGetLinkResult DhtMetaData content"
+            "Failed to deserialize Vec<Link> from HandleGetLinkResult DhtMetaData content"
                 .to_string(),
         ));
     }
@@ -29,19 +25,31 @@ pub fn reduce_handle_get_links_result(
     action_wrapper: &ActionWrapper,
 ) {
     let action = action_wrapper.action();
-    let (dht_meta_data, tag) = unwrap_to!(action => crate::action::Action::HandleGetLinksResult);
+    let dht_meta_data = unwrap_to!(action => crate::action::Action::HandleGetLinksResult);
 
     context.log(format!(
         "debug/reduce/handle_get_links_result: Got response from {}: {}",
         dht_meta_data.from_agent_id, dht_meta_data.content,
     ));
 
-    let result = inner(network_state, dht_meta_data);
-    let key = GetLinksKey {
-        base_address: Address::from(dht_meta_data.address.clone()),
-        tag: tag.clone(),
-        id: dht_meta_data.msg_id.clone(),
+    // the response only says which request it belongs to, not how the tag had to match
+    let base_address = Address::from(dht_meta_data.address.clone());
+    let key = network_state
+        .get_links_results
+        .keys()
+        .find(|key| key.id == dht_meta_data.msg_id && key.base_address == base_address)
+        .cloned();
+    let key = match key {
+        Some(key) => key,
+        None => {
+            context.log(format!(
+                "warn/reduce/handle_get_links_result: No request {} for links of {}",
+                dht_meta_data.msg_id, base_address
+            ));
+            return;
+        }
     };
 
+    let result = inner(network_state, dht_meta_data);
     network_state.get_links_results.insert(key, Some(result));
 }
@@ -1,19 +1,31 @@
 use crate::{
     action::{ActionWrapper, GetEntryKey},
     context::Context,
-    network::state::NetworkState,
+    network::{
+        encrypted_entry::{open_entry_with_meta, EncryptedEntryWithMeta},
+        state::NetworkState,
+    },
 };
 use holochain_core_types::{cas::content::Address, entry::EntryWithMeta, error::HolochainError};
 use holochain_net_connection::json_protocol::DhtData;
 use std::sync::Arc;
 
 fn inner(
+    context: &Arc<Context>,
     network_state: &mut NetworkState,
     dht_data: &DhtData,
 ) -> Result<Option<EntryWithMeta>, HolochainError> {
     network_state.initialized()?;
 
-    let res = serde_json::from_str(&serde_json::to_string(&dht_data.content).unwrap());
+    let content = serde_json::to_string(&dht_data.content).unwrap();
+    // Entries of encrypted entry types come back encrypted and are only
+    // readable if we are one of their recipients
+    if let Ok(encrypted_entry_with_meta) = serde_json::from_str::<EncryptedEntryWithMeta>(&content)
+    {
+        return open_entry_with_meta(&encrypted_entry_with_meta, context);
+    }
+
+    let res = serde_json::from_str(&content);
     if let Err(_) = res {
         return Err(HolochainError::ErrorGeneric(
             "Failed to deserialize EntryWithMeta from HandleGetResult action argument".to_string(),
@@ -23,14 +35,14 @@ fn inner(
 }
 
 pub fn reduce_handle_get_result(
-    _context: Arc<Context>,
+    context: Arc<Context>,
     network_state: &mut NetworkState,
     action_wrapper: &ActionWrapper,
 ) {
     let action = action_wrapper.action();
     let dht_data = unwrap_to!(action => crate::action::Action::HandleGetResult);
 
-    let result = inner(network_state, dht_data);
+    let result = inner(&context, network_state, dht_data);
 
     let key = GetEntryKey {
         address: Address::from(dht_data.address.clone()),
@@ -1,9 +1,9 @@
 use crate::{
     action::{Action, ActionWrapper},
     context::Context,
-    network::{handler::create_handler, state::NetworkState},
+    network::{handler::create_handler, peer_store::PeerStore, state::NetworkState},
 };
-use holochain_net::{p2p_config::P2pConfig, p2p_network::P2pNetwork};
+use holochain_net::{p2p_config::P2pConfig, p2p_network::P2pNetwork, sharding::redundancy};
 use holochain_net_connection::{
     json_protocol::{JsonProtocol, TrackDnaData},
     net_connection::NetSend,
@@ -36,6 +36,10 @@ pub fn reduce_init(
             state.network = Some(Arc::new(Mutex::new(network)));
             state.dna_address = Some(network_settings.dna_address.clone());
             state.agent_id = Some(network_settings.agent_id.clone());
+            state.peer_store = PeerStore::new(
+                network_settings.agent_id.clone(),
+                redundancy(&p2p_config.backend_config),
+            );
             Ok(())
         });
 }
@@ -6,6 +6,7 @@ pub mod handle_get_links_result;
 pub mod handle_get_result;
 pub mod handle_get_validation_package;
 pub mod init;
+pub mod peers;
 pub mod publish;
 pub mod resolve_direct_connection;
 pub mod respond_get;
@@ -26,9 +27,13 @@ use crate::{
             handle_get_result::reduce_handle_get_result,
             handle_get_validation_package::reduce_handle_get_validation_package,
             init::reduce_init,
+            peers::{
+                reduce_add_peer, reduce_peer_seen, reduce_remove_peer,
+                reduce_respond_peer_challenge,
+            },
             publish::reduce_publish,
             resolve_direct_connection::reduce_resolve_direct_connection,
-            respond_get::reduce_respond_get,
+            respond_get::{reduce_respond_get, reduce_respond_get_encrypted},
             respond_get_links::reduce_respond_get_links,
             send_direct_message::{reduce_send_direct_message, reduce_send_direct_message_timeout},
         },
@@ -46,6 +51,7 @@ use std::sync::Arc;
 /// maps incoming action to the correct handler
 fn resolve_reducer(action_wrapper: &ActionWrapper) -> Option<NetworkReduceFn> {
     match action_wrapper.action() {
+        Action::AddPeer(_) => Some(reduce_add_peer),
         Action::GetEntry(_) => Some(reduce_get_entry),
         Action::GetEntryTimeout(_) => Some(reduce_get_entry_timeout),
         Action::GetLinks(_) => Some(reduce_get_links),
@@ -56,10 +62,14 @@ fn resolve_reducer(action_wrapper: &ActionWrapper) -> Option<NetworkReduceFn> {
         Action::HandleGetLinksResult(_) => Some(reduce_handle_get_links_result),
         Action::HandleGetValidationPackage(_) => Some(reduce_handle_get_validation_package),
         Action::InitNetwork(_) => Some(reduce_init),
+        Action::PeerSeen(_) => Some(reduce_peer_seen),
         Action::Publish(_) => Some(reduce_publish),
+        Action::RemovePeer(_) => Some(reduce_remove_peer),
         Action::ResolveDirectConnection(_) => Some(reduce_resolve_direct_connection),
         Action::RespondGet(_) => Some(reduce_respond_get),
+        Action::RespondGetEncrypted(_) => Some(reduce_respond_get_encrypted),
         Action::RespondGetLinks(_) => Some(reduce_respond_get_links),
+        Action::RespondPeerChallenge(_) => Some(reduce_respond_peer_challenge),
         Action::SendDirectMessage(_) => Some(reduce_send_direct_message),
         Action::SendDirectMessageTimeout(_) => Some(reduce_send_direct_message_timeout),
         _ => None,
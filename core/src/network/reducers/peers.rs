@@ -0,0 +1,55 @@
+use crate::{
+    action::{Action, ActionWrapper},
+    context::Context,
+    network::{reducers::send, state::NetworkState},
+};
+use holochain_net_connection::json_protocol::JsonProtocol;
+use std::sync::Arc;
+
+pub fn reduce_add_peer(
+    _context: Arc<Context>,
+    network_state: &mut NetworkState,
+    action_wrapper: &ActionWrapper,
+) {
+    let action = action_wrapper.action();
+    let (agent_id, now) = unwrap_to!(action => Action::AddPeer);
+    network_state.peer_store.add_peer(agent_id, *now);
+}
+
+pub fn reduce_peer_seen(
+    _context: Arc<Context>,
+    network_state: &mut NetworkState,
+    action_wrapper: &ActionWrapper,
+) {
+    let action = action_wrapper.action();
+    let (agent_id, now) = unwrap_to!(action => Action::PeerSeen);
+    network_state.peer_store.peer_seen(agent_id, *now);
+}
+
+pub fn reduce_remove_peer(
+    _context: Arc<Context>,
+    network_state: &mut NetworkState,
+    action_wrapper: &ActionWrapper,
+) {
+    let action = action_wrapper.action();
+    let agent_id = unwrap_to!(action => Action::RemovePeer);
+    network_state.peer_store.remove_peer(agent_id);
+}
+
+pub fn reduce_respond_peer_challenge(
+    context: Arc<Context>,
+    network_state: &mut NetworkState,
+    action_wrapper: &ActionWrapper,
+) {
+    let action = action_wrapper.action();
+    let challenge_data = unwrap_to!(action => Action::RespondPeerChallenge);
+    if let Err(error) = send(
+        network_state,
+        JsonProtocol::HandleSignPeerChallengeResult(challenge_data.clone()),
+    ) {
+        context.log(format!(
+            "err/net: Error responding to peer challenge: {:?}",
+            error
+        ));
+    }
+}
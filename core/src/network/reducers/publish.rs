@@ -3,11 +3,12 @@ use crate::{
     context::Context,
     network::{
         actions::ActionResponse,
+        encrypted_entry::{is_encrypted_entry_type, EncryptedEntryWithHeader},
         entry_with_header::{fetch_entry_with_header, EntryWithHeader},
         reducers::send,
         state::NetworkState,
     },
-    nucleus::actions::get_entry::get_entry_crud_meta_from_dht,
+    nucleus::actions::get_entry::{get_encrypted_entries_from_dht, get_entry_crud_meta_from_dht},
 };
 use holochain_core_types::{
     cas::content::{Address, AddressableContent},
@@ -37,6 +38,50 @@ fn publish_entry(
     )
 }
 
+/// Publishes an entry of an encrypted entry type in the encrypted form its author sealed
+/// it in when committing it, which the author holds in the local DHT shard.
+fn publish_encrypted_entry(
+    context: &Arc<Context>,
+    network_state: &mut NetworkState,
+    entry_with_header: &EntryWithHeader,
+) -> Result<(), HolochainError> {
+    let address = entry_with_header.entry.address();
+    let encrypted_entries = get_encrypted_entries_from_dht(context, &address)?
+        .into_iter()
+        .filter(|encrypted_entry| {
+            entry_with_header
+                .header
+                .sources()
+                .contains(encrypted_entry.author())
+        })
+        .collect::<Vec<_>>();
+    if encrypted_entries.is_empty() {
+        return Err(HolochainError::ErrorGeneric(format!(
+            "Entry {} did not get sealed for publishing",
+            address
+        )));
+    }
+
+    for encrypted_entry in encrypted_entries {
+        let encrypted_entry_with_header =
+            EncryptedEntryWithHeader::new(encrypted_entry, entry_with_header.header.clone());
+        send(
+            network_state,
+            JsonProtocol::PublishDhtData(DhtData {
+                msg_id: "?".to_string(),
+                dna_address: network_state.dna_address.clone().unwrap(),
+                agent_id: network_state.agent_id.clone().unwrap(),
+                address: address.to_string(),
+                content: serde_json::from_str(
+                    &serde_json::to_string(&encrypted_entry_with_header).unwrap(),
+                )
+                .unwrap(),
+            }),
+        )?;
+    }
+    Ok(())
+}
+
 fn publish_crud_meta(
     network_state: &mut NetworkState,
     entry_address: Address,
@@ -82,16 +127,16 @@ fn publish_link_meta(
     network_state: &mut NetworkState,
     entry_with_header: &EntryWithHeader,
 ) -> Result<(), HolochainError> {
-    let link_add_entry = match entry_with_header.entry.clone() {
-        Entry::LinkAdd(link_add_entry) => link_add_entry,
+    let link = match entry_with_header.entry.clone() {
+        Entry::LinkAdd(link_add_entry) => link_add_entry.link().clone(),
+        Entry::LinkRemove(link_remove_entry) => link_remove_entry.link().clone(),
         _ => {
             return Err(HolochainError::ErrorGeneric(format!(
-                "Received bad entry type. Expected Entry::LinkAdd received {:?}",
+                "Received bad entry type. Expected Entry::LinkAdd or Entry::LinkRemove received {:?}",
                 entry_with_header.entry,
             )));
         }
     };
-    let link = link_add_entry.link().clone();
 
     context.log(format!(
         "debug/reduce/link_meta: Publishing link meta for link: {:?}",
@@ -132,16 +177,25 @@ fn reduce_publish_inner(
                 maybe_crud_link,
             )
         }),
-        EntryType::App(_) => publish_entry(network_state, &entry_with_header).and_then(|_| {
-            publish_crud_meta(
-                network_state,
-                entry_with_header.entry.address(),
-                crud_status,
-                maybe_crud_link,
-            )
-        }),
-        EntryType::LinkAdd => publish_entry(network_state, &entry_with_header)
-            .and_then(|_| publish_link_meta(context, network_state, &entry_with_header)),
+        EntryType::App(_) => {
+            if is_encrypted_entry_type(&entry_with_header.entry.entry_type(), context) {
+                publish_encrypted_entry(context, network_state, &entry_with_header)
+            } else {
+                publish_entry(network_state, &entry_with_header)
+            }
+            .and_then(|_| {
+                publish_crud_meta(
+                    network_state,
+                    entry_with_header.entry.address(),
+                    crud_status,
+                    maybe_crud_link,
+                )
+            })
+        }
+        EntryType::LinkAdd | EntryType::LinkRemove => {
+            publish_entry(network_state, &entry_with_header)
+                .and_then(|_| publish_link_meta(context, network_state, &entry_with_header))
+        }
         EntryType::Deletion => publish_entry(network_state, &entry_with_header).and_then(|_| {
             publish_crud_meta(
                 network_state,
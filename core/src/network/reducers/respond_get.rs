@@ -1,16 +1,20 @@
 use crate::{
     action::ActionWrapper,
     context::Context,
-    network::{actions::ActionResponse, reducers::send, state::NetworkState},
+    network::{
+        actions::ActionResponse, encrypted_entry::EncryptedEntryWithMeta, reducers::send,
+        state::NetworkState,
+    },
 };
 use holochain_core_types::{entry::EntryWithMeta, error::HolochainError};
 use holochain_net_connection::json_protocol::{DhtData, GetDhtData, JsonProtocol};
+use serde::Serialize;
 use std::sync::Arc;
 
-fn reduce_respond_get_inner(
+fn reduce_respond_get_inner<T: Serialize>(
     network_state: &mut NetworkState,
     get_dht_data: &GetDhtData,
-    maybe_entry: &Option<EntryWithMeta>,
+    maybe_entry: &T,
 ) -> Result<(), HolochainError> {
     network_state.initialized()?;
 
@@ -42,3 +46,21 @@ pub fn reduce_respond_get(
         }),
     );
 }
+
+pub fn reduce_respond_get_encrypted(
+    _context: Arc<Context>,
+    network_state: &mut NetworkState,
+    action_wrapper: &ActionWrapper,
+) {
+    let action = action_wrapper.action();
+    let (get_dht_data, encrypted_entry) =
+        unwrap_to!(action => crate::action::Action::RespondGetEncrypted);
+    let result = reduce_respond_get_inner(network_state, get_dht_data, encrypted_entry);
+    network_state.actions.insert(
+        action_wrapper.clone(),
+        ActionResponse::RespondGet(match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(HolochainError::ErrorGeneric(e.to_string())),
+        }),
+    );
+}
@@ -3,14 +3,14 @@ use crate::{
     context::Context,
     network::{actions::ActionResponse, reducers::send, state::NetworkState},
 };
-use holochain_core_types::{cas::content::Address, error::HolochainError};
+use holochain_core_types::{error::HolochainError, link::Link};
 use holochain_net_connection::json_protocol::{DhtMetaData, GetDhtMetaData, JsonProtocol};
 use std::sync::Arc;
 
 fn reduce_respond_get_links_inner(
     network_state: &mut NetworkState,
     get_dht_meta_data: &GetDhtMetaData,
-    links: &Vec<Address>,
+    links: &Vec<Link>,
 ) -> Result<(), HolochainError> {
     network_state.initialized()?;
 
@@ -1,10 +1,10 @@
 use crate::{
     action::{ActionWrapper, GetEntryKey, GetLinksKey},
-    network::{actions::ActionResponse, direct_message::DirectMessage},
+    network::{actions::ActionResponse, direct_message::DirectMessage, peer_store::PeerStore},
 };
 use boolinator::*;
 use holochain_core_types::{
-    cas::content::Address, entry::EntryWithMeta, error::HolochainError,
+    cas::content::Address, entry::EntryWithMeta, error::HolochainError, link::Link,
     validation::ValidationPackage,
 };
 use holochain_net::p2p_network::P2pNetwork;
@@ -27,7 +27,7 @@ type GetEntryWithMetaResult = Option<Result<Option<EntryWithMeta>, HolochainErro
 /// None: process started, but no response yet from the network
 /// Some(Err(_)): there was a problem at some point
 /// Some(Ok(_)): we got the list of links
-type GetLinksResult = Option<Result<Vec<Address>, HolochainError>>;
+type GetLinksResult = Option<Result<Vec<Link>, HolochainError>>;
 
 /// This represents the state of a get_validation_package network process:
 /// None: process started, but no response yet from the network
@@ -67,6 +67,11 @@ pub struct NetworkState {
 
     pub custom_direct_message_replys: HashMap<String, Result<String, HolochainError>>,
 
+    /// The agents of our DNA we know about.
+    /// Filled from the PeerConnected messages of the network and kept fresh by the
+    /// messages we get from them. Peers that disconnect or stay silent get removed.
+    pub peer_store: PeerStore,
+
     id: snowflake::ProcessUniqueId,
 }
 
@@ -89,6 +94,7 @@ impl NetworkState {
             get_validation_package_results: HashMap::new(),
             direct_message_connections: HashMap::new(),
             custom_direct_message_replys: HashMap::new(),
+            peer_store: PeerStore::default(),
 
             id: snowflake::ProcessUniqueId::new(),
         }
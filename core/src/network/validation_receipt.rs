@@ -0,0 +1,311 @@
+use crate::{
+    action::{Action, ActionWrapper, DirectMessageData},
+    agent::keys::verify,
+    context::Context,
+    instance::dispatch_action,
+    network::direct_message::DirectMessage,
+};
+use chrono::Utc;
+use holochain_core_types::{
+    cas::content::{Address, AddressableContent},
+    chain_header::ChainHeader,
+    eav::IndexQuery,
+    error::{HcResult, HolochainError},
+};
+pub use holochain_wasm_utils::api_serialization::validation_receipt::ValidationReceipt;
+use snowflake::ProcessUniqueId;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+
+/// Attribute of the EAVs that point from an entry to the validation receipts we got for it
+pub const VALIDATION_RECEIPT_NAME: &str = "validation-receipt";
+
+/// Creates a receipt about the entry at `entry_address`, signed by our agent at the given
+/// time in milliseconds
+pub fn sign_validation_receipt(
+    entry_address: Address,
+    result: Result<(), String>,
+    signed_at: i64,
+    context: &Arc<Context>,
+) -> HcResult<ValidationReceipt> {
+    let holder = Address::from(context.agent_id.key.clone());
+    let signature = context.sign(&ValidationReceipt::signed_data(
+        &entry_address,
+        &holder,
+        signed_at,
+        &result,
+    ))?;
+    Ok(ValidationReceipt {
+        entry_address,
+        holder,
+        result,
+        signed_at,
+        signature,
+    })
+}
+
+/// Checks that the receipt carries a valid signature of its holder
+pub fn verify_validation_receipt(receipt: &ValidationReceipt) -> HcResult<()> {
+    let signed_data = ValidationReceipt::signed_data(
+        &receipt.entry_address,
+        &receipt.holder,
+        receipt.signed_at,
+        &receipt.result,
+    );
+    if verify(&receipt.holder, &signed_data, &receipt.signature)? {
+        Ok(())
+    } else {
+        Err(HolochainError::ValidationFailed(format!(
+            "Invalid signature of {} on validation receipt for {}",
+            receipt.holder, receipt.entry_address
+        )))
+    }
+}
+
+/// Tells the sources of an entry that got published to us whether we found it valid.
+/// Errors other than validation failures say nothing about the entry, so they do not
+/// get a receipt.
+pub fn send_validation_receipt(
+    header: &ChainHeader,
+    hold_result: &Result<Address, HolochainError>,
+    context: &Arc<Context>,
+) {
+    let result = match hold_result {
+        Ok(_) => Ok(()),
+        Err(HolochainError::ValidationFailed(reason)) => Err(reason.clone()),
+        Err(_) => return,
+    };
+    let signed_at = Utc::now().timestamp_millis();
+    let receipt =
+        match sign_validation_receipt(header.entry_address().clone(), result, signed_at, context) {
+            Ok(receipt) => receipt,
+            Err(error) => {
+                context.log(format!(
+                    "err/net: could not sign validation receipt: {}",
+                    error
+                ));
+                return;
+            }
+        };
+    for source in header.sources() {
+        if *source == receipt.holder {
+            continue;
+        }
+        let msg_id = ProcessUniqueId::new().to_string();
+        let direct_message_data = DirectMessageData {
+            address: source.clone(),
+            message: DirectMessage::ValidationReceipt(receipt.clone()),
+            msg_id: msg_id.clone(),
+            is_response: false,
+        };
+        dispatch_action(
+            context.action_channel(),
+            ActionWrapper::new(Action::SendDirectMessage(direct_message_data)),
+        );
+        // Receipts do not get answered
+        dispatch_action(
+            context.action_channel(),
+            ActionWrapper::new(Action::ResolveDirectConnection(msg_id)),
+        );
+    }
+}
+
+/// Whether the given agent is one of the holders of `entry_address`.
+/// Agents that send us receipts are peers, even if they did not connect to us yet.
+fn is_holder(agent_id: &Address, entry_address: &Address, context: &Arc<Context>) -> bool {
+    let mut peer_store = context.state().unwrap().network().peer_store.clone();
+    peer_store.add_peer(&agent_id.to_string(), 0);
+    peer_store.holds(&agent_id.to_string(), entry_address)
+}
+
+/// Stores a receipt another agent sent us, if it is about one of our entries, signed by
+/// the agent that sent it and that agent is one of the entry's holders.
+pub fn handle_validation_receipt(
+    from_agent_id: Address,
+    receipt: ValidationReceipt,
+    context: Arc<Context>,
+) {
+    if receipt.holder != from_agent_id {
+        context.log(format!(
+            "err/net: got validation receipt of {} from {}",
+            receipt.holder, from_agent_id
+        ));
+        return;
+    }
+    if let Err(error) = verify_validation_receipt(&receipt) {
+        context.log(format!("err/net: {}", error));
+        return;
+    }
+    let is_ours = context
+        .state()
+        .unwrap()
+        .agent()
+        .chain()
+        .content_storage()
+        .read()
+        .unwrap()
+        .contains(&receipt.entry_address)
+        .unwrap_or(false);
+    if !is_ours {
+        context.log(format!(
+            "debug/net: ignoring validation receipt for {}, which is not ours",
+            receipt.entry_address
+        ));
+        return;
+    }
+    if !is_holder(&receipt.holder, &receipt.entry_address, &context) {
+        context.log(format!(
+            "debug/net: ignoring validation receipt of {}, which does not hold {}",
+            receipt.holder, receipt.entry_address
+        ));
+        return;
+    }
+    dispatch_action(
+        context.action_channel(),
+        ActionWrapper::new(Action::HoldValidationReceipt(receipt)),
+    );
+}
+
+/// The latest receipt of every holder that sent us one for the given entry
+pub fn validation_receipts(
+    entry_address: &Address,
+    context: &Arc<Context>,
+) -> HcResult<Vec<ValidationReceipt>> {
+    let (content_storage, meta_storage) = {
+        let state = context
+            .state()
+            .ok_or_else(|| HolochainError::ErrorGeneric("State not initialized".to_string()))?;
+        (state.dht().content_storage(), state.dht().meta_storage())
+    };
+    let mut latest: BTreeMap<Address, ValidationReceipt> = BTreeMap::new();
+    for eavi in meta_storage.read()?.fetch_eavi(
+        Some(entry_address.clone()),
+        Some(VALIDATION_RECEIPT_NAME.to_string()),
+        None,
+        IndexQuery::new(std::i64::MIN, std::i64::MAX),
+    )? {
+        if let Some(content) = content_storage.read()?.fetch(&eavi.value())? {
+            let receipt = ValidationReceipt::try_from_content(&content)?;
+            let is_newer = latest
+                .get(&receipt.holder)
+                .map(|held| receipt.signed_at > held.signed_at)
+                .unwrap_or(true);
+            if is_newer {
+                latest.insert(receipt.holder.clone(), receipt);
+            }
+        }
+    }
+    Ok(latest.into_iter().map(|(_, receipt)| receipt).collect())
+}
+
+/// How many holders found each of the published entries of our source chain valid.
+/// Only the receipts of the agents that currently hold an entry count.
+pub fn validation_counts(context: &Arc<Context>) -> HcResult<BTreeMap<Address, usize>> {
+    let (chain, top_header, peer_store) = {
+        let state = context
+            .state()
+            .ok_or_else(|| HolochainError::ErrorGeneric("State not initialized".to_string()))?;
+        (
+            state.agent().chain(),
+            state.agent().top_chain_header(),
+            state.network().peer_store.clone(),
+        )
+    };
+    let published: BTreeSet<Address> = chain
+        .iter(&top_header)
+        .filter(|header| header.entry_type().can_publish())
+        .map(|header| header.entry_address().clone())
+        .collect();
+    let mut counts = BTreeMap::new();
+    for entry_address in published {
+        let valid = validation_receipts(&entry_address, context)?
+            .iter()
+            .filter(|receipt| receipt.result.is_ok())
+            .filter(|receipt| peer_store.holds(&receipt.holder.to_string(), &entry_address))
+            .count();
+        counts.insert(entry_address, valid);
+    }
+    Ok(counts)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{
+        agent::actions::commit::commit_entry,
+        instance::dispatch_action_and_wait,
+        nucleus::actions::tests::{instance_by_name, test_dna},
+    };
+    use futures::executor::block_on;
+    use holochain_core_types::entry::test_entry;
+
+    #[test]
+    fn authors_keep_the_receipts_of_their_holders() {
+        let netname = Some("authors_keep_the_receipts_of_their_holders");
+        let (_author_instance, author) = instance_by_name("jill", test_dna(), netname);
+        let (_holder_instance, holder) = instance_by_name("jack", test_dna(), netname);
+        let entry = test_entry();
+        block_on(commit_entry(entry.clone(), None, &author)).unwrap();
+
+        let valid = sign_validation_receipt(entry.address(), Ok(()), 2, &holder).unwrap();
+        let invalid =
+            sign_validation_receipt(entry.address(), Err("not valid".to_string()), 1, &holder)
+                .unwrap();
+        assert_eq!(verify_validation_receipt(&valid), Ok(()));
+        let forged = ValidationReceipt {
+            result: Ok(()),
+            ..invalid.clone()
+        };
+        assert!(verify_validation_receipt(&forged).is_err());
+        let replayed = ValidationReceipt {
+            signed_at: 3,
+            ..invalid.clone()
+        };
+        assert!(verify_validation_receipt(&replayed).is_err());
+
+        for receipt in vec![valid.clone(), invalid.clone(), valid.clone()] {
+            dispatch_action_and_wait(
+                author.action_channel(),
+                author.observer_channel(),
+                ActionWrapper::new(Action::HoldValidationReceipt(receipt)),
+            );
+        }
+        // receipts that got sent again are only held once
+        let receipt_eavis = author
+            .state()
+            .unwrap()
+            .dht()
+            .meta_storage()
+            .read()
+            .unwrap()
+            .fetch_eavi(
+                Some(entry.address()),
+                Some(VALIDATION_RECEIPT_NAME.to_string()),
+                None,
+                IndexQuery::new(std::i64::MIN, std::i64::MAX),
+            )
+            .unwrap();
+        assert_eq!(receipt_eavis.len(), 2);
+        // only the latest receipt of each holder counts
+        assert_eq!(
+            validation_receipts(&entry.address(), &author),
+            Ok(vec![valid])
+        );
+        // and only if the holder is one of the entry's holders
+        assert_eq!(
+            validation_counts(&author).unwrap().get(&entry.address()),
+            Some(&0)
+        );
+        dispatch_action_and_wait(
+            author.action_channel(),
+            author.observer_channel(),
+            ActionWrapper::new(Action::AddPeer((holder.agent_id.key.clone(), 0))),
+        );
+        assert_eq!(
+            validation_counts(&author).unwrap().get(&entry.address()),
+            Some(&1)
+        );
+    }
+}
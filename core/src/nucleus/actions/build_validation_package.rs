@@ -16,7 +16,7 @@ use holochain_core_types::{
     chain_header::ChainHeader,
     entry::{entry_type::EntryType, Entry},
     error::HolochainError,
-    validation::{ValidationPackage, ValidationPackageDefinition::*},
+    validation::{ValidationPackage, ValidationPackageDefinition, ValidationPackageDefinition::*},
 };
 use snowflake;
 use std::{convert::TryInto, pin::Pin, sync::Arc, thread};
@@ -46,8 +46,8 @@ pub fn build_validation_package(entry: &Entry, context: &Arc<Context>) -> Valida
             }
         }
 
-        EntryType::LinkAdd => {
-            // LinkAdd can always be validated
+        EntryType::LinkAdd | EntryType::LinkRemove => {
+            // Links can always be validated
         }
 
         EntryType::Deletion => {
@@ -55,7 +55,7 @@ pub fn build_validation_package(entry: &Entry, context: &Arc<Context>) -> Valida
         }
 
         EntryType::CapTokenGrant => {
-            // FIXME
+            // Grants are validated by the system and only need the entry itself
         }
 
         EntryType::AgentId => {
@@ -77,7 +77,8 @@ pub fn build_validation_package(entry: &Entry, context: &Arc<Context>) -> Valida
         let id = id.clone();
         let entry = entry.clone();
         let context = context.clone();
-        let entry_header = find_chain_header(&entry.clone(), &context).unwrap_or(
+        let entry_header = match find_chain_header(&entry.clone(), &context) {
+            Some(entry_header) => entry_header,
             // TODO: make sure that we don't run into race conditions with respect to the chain
             // We need the source chain header as part of the validation package.
             // For an already committed entry (when asked to deliver the validation package to
@@ -90,26 +91,21 @@ pub fn build_validation_package(entry: &Entry, context: &Arc<Context>) -> Valida
             // and just used for the validation, I don't see why it would be a problem.
             // If it was a problem, we would have to make sure that the whole commit process
             // (including validtion) is atomic.
-            agent::state::create_new_chain_header(&entry, context.clone(), &None),
-        );
+            None => match agent::state::create_new_chain_header(&entry, context.clone(), &None) {
+                Ok(entry_header) => entry_header,
+                Err(error) => {
+                    return ValidationPackageFuture {
+                        context: context.clone(),
+                        key: id,
+                        error: Some(error),
+                    };
+                }
+            },
+        };
 
         thread::spawn(move || {
-            let maybe_callback_result = get_validation_package_definition(&entry, context.clone());
-            let maybe_validation_package = maybe_callback_result
-                .and_then(|callback_result| match callback_result {
-                    CallbackResult::Fail(error_string) => {
-                        Err(HolochainError::ErrorGeneric(error_string))
-                    }
-                    CallbackResult::ValidationPackageDefinition(def) => Ok(def),
-                    CallbackResult::NotImplemented(reason) => {
-                        Err(HolochainError::ErrorGeneric(format!(
-                            "ValidationPackage callback not implemented for {:?} ({})",
-                            entry.entry_type().clone(),
-                            reason
-                        )))
-                    }
-                    _ => unreachable!(),
-                })
+            let agent_headers = agent_headers_before(&entry_header, &context);
+            let maybe_validation_package = validation_package_definition(&entry, context.clone())
                 .and_then(|package_definition| {
                     Ok(match package_definition {
                         Entry => ValidationPackage::only_header(entry_header),
@@ -135,6 +131,10 @@ pub fn build_validation_package(entry: &Entry, context: &Arc<Context>) -> Valida
                             package
                         }
                     })
+                })
+                .map(|mut package| {
+                    package.agent_headers = agent_headers;
+                    package
                 });
 
             context
@@ -154,6 +154,23 @@ pub fn build_validation_package(entry: &Entry, context: &Arc<Context>) -> Valida
     }
 }
 
+/// Asks the app which validation package the given entry needs.
+pub(crate) fn validation_package_definition(
+    entry: &Entry,
+    context: Arc<Context>,
+) -> Result<ValidationPackageDefinition, HolochainError> {
+    match get_validation_package_definition(entry, context)? {
+        CallbackResult::Fail(error_string) => Err(HolochainError::ErrorGeneric(error_string)),
+        CallbackResult::ValidationPackageDefinition(def) => Ok(def),
+        CallbackResult::NotImplemented(reason) => Err(HolochainError::ErrorGeneric(format!(
+            "ValidationPackage callback not implemented for {:?} ({})",
+            entry.entry_type().clone(),
+            reason
+        ))),
+        _ => unreachable!(),
+    }
+}
+
 fn all_public_chain_entries(context: &Arc<Context>) -> Vec<Entry> {
     let chain = context.state().unwrap().agent().chain();
     let top_header = context.state().unwrap().agent().top_chain_header();
@@ -181,6 +198,16 @@ fn all_public_chain_headers(context: &Arc<Context>) -> Vec<ChainHeader> {
         .collect::<Vec<_>>()
 }
 
+/// Headers of the agent entries before `header` in the chain, newest first.
+fn agent_headers_before(header: &ChainHeader, context: &Arc<Context>) -> Vec<ChainHeader> {
+    let chain = context.state().unwrap().agent().chain();
+    chain
+        .iter(&Some(header.clone()))
+        .skip(1)
+        .filter(|chain_header| *chain_header.entry_type() == EntryType::AgentId)
+        .collect::<Vec<_>>()
+}
+
 /// ValidationPackageFuture resolves to the ValidationPackage or a HolochainError.
 pub struct ValidationPackageFuture {
     context: Arc<Context>,
@@ -218,7 +245,7 @@ mod tests {
     use crate::nucleus::actions::tests::*;
 
     use futures::executor::block_on;
-    use holochain_core_types::validation::ValidationPackage;
+    use holochain_core_types::{cas::content::AddressableContent, validation::ValidationPackage};
 
     #[test]
     fn test_building_validation_package_entry() {
@@ -238,11 +265,20 @@ mod tests {
         println!("{:?}", maybe_validation_package);
         assert!(maybe_validation_package.is_ok());
 
+        // the package tells which agent key was in effect at the position of the entry
+        let agent_headers = agent_headers_before(&chain_header, &context);
+        assert_eq!(agent_headers.len(), 1);
+        assert_eq!(
+            agent_headers[0].entry_address(),
+            &context.agent_id.address()
+        );
+
         let expected = ValidationPackage {
-            chain_header: Some(chain_header),
+            chain_header: Some(chain_header.clone()),
             source_chain_entries: None,
             source_chain_headers: None,
             custom: None,
+            agent_headers: agent_headers_before(&chain_header, &context),
         };
 
         assert_eq!(maybe_validation_package.unwrap(), expected);
@@ -266,10 +302,11 @@ mod tests {
         assert!(maybe_validation_package.is_ok());
 
         let expected = ValidationPackage {
-            chain_header: Some(chain_header),
+            chain_header: Some(chain_header.clone()),
             source_chain_entries: Some(all_public_chain_entries(&context)),
             source_chain_headers: None,
             custom: None,
+            agent_headers: agent_headers_before(&chain_header, &context),
         };
 
         assert_eq!(maybe_validation_package.unwrap(), expected);
@@ -293,10 +330,11 @@ mod tests {
         assert!(maybe_validation_package.is_ok());
 
         let expected = ValidationPackage {
-            chain_header: Some(chain_header),
+            chain_header: Some(chain_header.clone()),
             source_chain_entries: None,
             source_chain_headers: Some(all_public_chain_headers(&context)),
             custom: None,
+            agent_headers: agent_headers_before(&chain_header, &context),
         };
 
         assert_eq!(maybe_validation_package.unwrap(), expected);
@@ -320,10 +358,11 @@ mod tests {
         assert!(maybe_validation_package.is_ok());
 
         let expected = ValidationPackage {
-            chain_header: Some(chain_header),
+            chain_header: Some(chain_header.clone()),
             source_chain_entries: Some(all_public_chain_entries(&context)),
             source_chain_headers: Some(all_public_chain_headers(&context)),
             custom: None,
+            agent_headers: agent_headers_before(&chain_header, &context),
         };
 
         assert_eq!(maybe_validation_package.unwrap(), expected);
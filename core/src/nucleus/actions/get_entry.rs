@@ -1,7 +1,13 @@
 extern crate serde_json;
-use crate::context::Context;
+use crate::{
+    context::Context,
+    network::encrypted_entry::{EncryptedEntry, EncryptedEntryWithMeta, ENCRYPTED_ENTRY_NAME},
+};
 use holochain_core_types::{
-    cas::{content::Address, storage::ContentAddressableStorage},
+    cas::{
+        content::{Address, AddressableContent},
+        storage::ContentAddressableStorage,
+    },
     crud_status::{CrudStatus, LINK_NAME, STATUS_NAME},
     eav::{EntityAttributeValueIndex, IndexQuery},
     entry::{Entry, EntryWithMeta},
@@ -42,6 +48,41 @@ pub(crate) fn get_entry_from_dht(
     get_entry_from_cas(&cas.clone(), address)
 }
 
+/// Looks up an encrypted entry that stands for the entry at `address` in the local DHT shard.
+pub(crate) fn get_encrypted_entry_from_dht(
+    context: &Arc<Context>,
+    address: &Address,
+) -> Result<Option<EncryptedEntry>, HolochainError> {
+    // Every encrypted entry holds the same entry
+    Ok(get_encrypted_entries_from_dht(context, address)?
+        .into_iter()
+        .next())
+}
+
+/// Looks up all encrypted entries that stand for the entry at `address` in the local DHT
+/// shard. Authors seal an entry anew for every commit of it.
+pub(crate) fn get_encrypted_entries_from_dht(
+    context: &Arc<Context>,
+    address: &Address,
+) -> Result<Vec<EncryptedEntry>, HolochainError> {
+    let dht = context.state().unwrap().dht();
+    let encrypted_entry_eavs = (*dht.meta_storage().read().unwrap()).fetch_eavi(
+        Some(address.clone()),
+        Some(ENCRYPTED_ENTRY_NAME.to_string()),
+        None,
+        IndexQuery::default(),
+    )?;
+    let content_storage = dht.content_storage();
+    let content_storage = content_storage.read().unwrap();
+    let mut encrypted_entries = Vec::new();
+    for eav in encrypted_entry_eavs.iter() {
+        if let Some(content) = content_storage.fetch(&eav.value())? {
+            encrypted_entries.push(EncryptedEntry::try_from_content(&content)?);
+        }
+    }
+    Ok(encrypted_entries)
+}
+
 pub(crate) fn get_entry_crud_meta_from_dht(
     context: &Arc<Context>,
     address: Address,
@@ -105,6 +146,15 @@ pub(crate) fn get_entry_crud_meta_from_dht(
     Ok(Some((crud_status, maybe_crud_link)))
 }
 
+/// Entries get held together with their crud-status, so if it is missing the local DHT
+/// shard is inconsistent.
+fn missing_crud_meta(address: &Address) -> HolochainError {
+    HolochainError::ErrorGeneric(format!(
+        "Entry {} is held without crud-status metadata",
+        address
+    ))
+}
+
 /// GetEntry Action Creator
 ///
 /// Returns a future that resolves to an Ok(ActionWrapper) or an Err(error_message:String).
@@ -119,13 +169,8 @@ pub fn get_entry_with_meta<'a>(
         Ok(Some(entry)) => entry,
     };
     // 2. try to get the entry's metadata
-    let maybe_meta = get_entry_crud_meta_from_dht(context, address);
-    if let Err(err) = maybe_meta {
-        return Err(err);
-    }
-    let (crud_status, maybe_crud_link) = maybe_meta
-        .unwrap()
-        .expect("Entry should have crud-status metadata");
+    let (crud_status, maybe_crud_link) = get_entry_crud_meta_from_dht(context, address.clone())?
+        .ok_or_else(|| missing_crud_meta(&address))?;
     let item = EntryWithMeta {
         entry,
         crud_status,
@@ -134,6 +179,25 @@ pub fn get_entry_with_meta<'a>(
     Ok(Some(item))
 }
 
+/// Gets an entry of an encrypted entry type together with its metadata from the local
+/// DHT shard, in the encrypted form it is held in.
+pub fn get_encrypted_entry_with_meta(
+    context: &Arc<Context>,
+    address: Address,
+) -> Result<Option<EncryptedEntryWithMeta>, HolochainError> {
+    let encrypted_entry = match get_encrypted_entry_from_dht(context, &address)? {
+        Some(encrypted_entry) => encrypted_entry,
+        None => return Ok(None),
+    };
+    let (crud_status, maybe_crud_link) = get_entry_crud_meta_from_dht(context, address.clone())?
+        .ok_or_else(|| missing_crud_meta(&address))?;
+    Ok(Some(EncryptedEntryWithMeta {
+        encrypted_entry,
+        crud_status,
+        maybe_crud_link,
+    }))
+}
+
 #[cfg(test)]
 pub mod tests {
     use crate::instance::tests::test_context_with_state;
@@ -150,4 +214,13 @@ pub mod tests {
         let result = super::get_entry_from_dht(&context, &entry.address());
         assert_eq!(Ok(Some(entry.clone())), result);
     }
+
+    #[test]
+    fn get_entry_with_meta_fails_without_crud_status() {
+        let entry = test_entry();
+        let context = test_context_with_state(None);
+        let storage = &context.state().unwrap().dht().content_storage().clone();
+        (*storage.write().unwrap()).add(&entry).unwrap();
+        assert!(super::get_entry_with_meta(&context, entry.address()).is_err());
+    }
 }
@@ -8,12 +8,15 @@ use crate::{
         ribosome::callback::{genesis::genesis, CallbackParams, CallbackResult},
         state::NucleusStatus,
     },
+    workflows::migrate_chain::chain_migrate_from,
 };
 use futures::{
     future::Future,
     task::{LocalWaker, Poll},
 };
-use holochain_core_types::{dna::Dna, entry::Entry, error::HolochainError};
+use holochain_core_types::{
+    cas::content::AddressableContent, dna::Dna, entry::Entry, error::HolochainError,
+};
 use std::{pin::Pin, sync::Arc, time::*};
 
 /// Timeout in seconds for initialization process.
@@ -83,6 +86,28 @@ pub async fn initialize_application(
         return Err(HolochainError::new("error committing Agent"));
     }
 
+    // Commit the migration from the closed chain this chain continues, if any,
+    // so that genesis can already refer to it
+    if let Some(ref old_context) = context_clone.migrated_from {
+        let migrate_commit = match chain_migrate_from(old_context, dna.address()) {
+            Ok(migrate) => await!(commit_entry(
+                Entry::ChainMigrate(migrate),
+                None,
+                &context_clone
+            )),
+            Err(error) => Err(error),
+        };
+        if let Err(error) = migrate_commit {
+            context_clone
+                .action_channel()
+                .send(ActionWrapper::new(Action::ReturnInitializationResult(
+                    Some(error.to_string()),
+                )))
+                .expect("Action channel not usable in initialize_application()");
+            return Err(HolochainError::new("error committing chain migration"));
+        }
+    }
+
     // TODO: Question: genesis is called AFTER dna and agent entries committed??
 
     // map genesis across every zome
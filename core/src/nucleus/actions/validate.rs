@@ -52,8 +52,8 @@ pub fn validate_entry<'a>(
             }
         }
 
-        EntryType::LinkAdd => {
-            // LinkAdd can always be validated
+        EntryType::LinkAdd | EntryType::LinkRemove => {
+            // Links can always be validated
         }
 
         EntryType::Deletion => {
@@ -61,7 +61,7 @@ pub fn validate_entry<'a>(
         }
 
         EntryType::CapTokenGrant => {
-            // FIXME
+            // Grants are validated against the DNA's capabilities by the system
         }
 
         EntryType::AgentId => {
@@ -60,6 +60,11 @@ impl ZomeFnCall {
         }
     }
 
+    /// Unique id of this call, which e.g. the bundle started by the call is keyed by
+    pub fn id(&self) -> snowflake::ProcessUniqueId {
+        self.id
+    }
+
     pub fn same_fn_as(&self, fn_call: &ZomeFnCall) -> bool {
         self.zome_name == fn_call.zome_name
             && self.cap == fn_call.cap
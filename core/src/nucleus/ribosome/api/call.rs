@@ -10,10 +10,7 @@ use crate::{
     },
 };
 use holochain_core_types::{
-    dna::{capabilities::CapabilityCall, Dna},
-    entry::cap_entries::CapTokenGrant,
-    error::HolochainError,
-    json::JsonString,
+    dna::Dna, entry::cap_entries::CapTokenGrant, error::HolochainError, json::JsonString,
 };
 use holochain_wasm_utils::api_serialization::{ZomeFnCallArgs, THIS_INSTANCE};
 use jsonrpc_lite::JsonRpc;
@@ -165,7 +162,7 @@ pub fn validate_call(
         .map_err(|e| HolochainError::Dna(e))?;
 
     let public = is_fn_public(&dna, &fn_call)?;
-    if !public && !check_capability(context.clone(), &fn_call.clone()) {
+    if !public && !check_capability(context.clone(), &dna, &fn_call.clone()) {
         return Err(HolochainError::CapabilityCheckFailed);
     }
     Ok(dna)
@@ -204,36 +201,36 @@ pub(crate) fn reduce_call(
     launch_zome_fn_call(context, fn_call, &code, state.dna.clone().unwrap().name);
 }
 
-// TODO: check the signature too
-fn is_token_the_agent(context: Arc<Context>, cap: &Option<CapabilityCall>) -> bool {
-    match cap {
-        None => false,
-        Some(call) => context.agent_id.key == call.cap_token.to_string(),
-    }
-}
-
 /// checks to see if a given function call is allowable according to the capabilities
 /// that have been registered to callers in the chain.
-fn check_capability(context: Arc<Context>, fn_call: &ZomeFnCall) -> bool {
-    // the agent can always do everything
-    if is_token_the_agent(context.clone(), &fn_call.cap) {
-        return true;
-    }
-
-    match fn_call.cap.clone() {
-        None => false,
-        Some(call) => {
-            let chain = &context.chain_storage;
-            let maybe_json = chain.read().unwrap().fetch(&call.cap_token).unwrap();
-            let grant = match maybe_json {
-                Some(content) => CapTokenGrant::try_from(content).unwrap(),
-                None => return false,
-            };
-            grant.verify(call.cap_token.clone(), call.caller, &call.signature)
+/// Every call of a function that is not public needs a token of a grant for it,
+/// even calls made on behalf of the agent itself: there is no master token.
+fn check_capability(context: Arc<Context>, dna: &Dna, fn_call: &ZomeFnCall) -> bool {
+    let call = match fn_call.cap.clone() {
+        Some(call) => call,
+        None => return false,
+    };
+    let maybe_grant = context
+        .state()
+        .and_then(|state| state.agent().get_grant(&call.cap_token));
+    match maybe_grant {
+        Some(grant) => {
+            grant_covers_fn(dna, &grant, fn_call)
+                && grant.verify(call.cap_token.clone(), call.caller, &call.signature)
         }
+        None => false,
     }
 }
 
+/// A grant only gives access to the functions of the capability it was created for.
+fn grant_covers_fn(dna: &Dna, grant: &CapTokenGrant, fn_call: &ZomeFnCall) -> bool {
+    dna.get_zome(&fn_call.zome_name)
+        .ok()
+        .and_then(|zome| zome.capabilities.get(&grant.cap_name()))
+        .map(|capability| capability.functions.contains(&fn_call.fn_name))
+        .unwrap_or(false)
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::*;
@@ -241,6 +238,7 @@ pub mod tests {
     extern crate wabt;
 
     use crate::{
+        agent::actions::commit::commit_entry,
         context::Context,
         instance::{tests::test_instance_and_context, Instance, Observer, RECV_DEFAULT_TIMEOUT_MS},
         nucleus::{
@@ -266,7 +264,7 @@ pub mod tests {
             fn_declarations::FnDeclaration,
             Dna,
         },
-        entry::Entry,
+        entry::{deletion_entry::DeletionEntry, test_entry, Entry},
         error::{DnaError, HolochainError},
         json::JsonString,
     };
@@ -330,12 +328,12 @@ pub mod tests {
     fn test_reduce_call(
         test_setup: &TestSetup,
         token_str: &str,
-        _caller: Address,
+        caller: Address,
         expected: Result<Result<JsonString, HolochainError>, RecvTimeoutError>,
     ) {
         let zome_call = ZomeFnCall::new(
             "test_zome",
-            Some(CapabilityCall::new(Address::from(token_str), None)),
+            Some(CapabilityCall::new(Address::from(token_str), Some(caller))),
             "test",
             "{}",
         );
@@ -422,21 +420,30 @@ pub mod tests {
         let dna = setup_dna_for_cap_test(CapabilityType::Transferable);
         let test_setup = setup_test(dna);
         let expected_failure = Ok(Err(HolochainError::CapabilityCheckFailed));
-        test_reduce_call(&test_setup, "", Address::from("caller"), expected_failure);
+        test_reduce_call(
+            &test_setup,
+            "",
+            Address::from("caller"),
+            expected_failure.clone(),
+        );
 
-        // Expecting timeout since there is no function in wasm to call
-        let expected = Err(RecvTimeoutError::Disconnected);
+        // the agent's address is no master token
         let agent_token_str = test_setup.context.agent_id.key.clone();
         test_reduce_call(
             &test_setup,
             &agent_token_str,
             Address::from(agent_token_str.clone()),
-            expected.clone(),
+            expected_failure,
         );
 
-        let grant = CapTokenGrant::create(CapabilityType::Transferable, None).unwrap();
+        // Expecting timeout since there is no function in wasm to call
+        let expected = Err(RecvTimeoutError::Disconnected);
+
+        let grant =
+            CapTokenGrant::create(&test_capability_name(), CapabilityType::Transferable, None)
+                .unwrap();
         let grant_entry = Entry::CapTokenGrant(grant);
-        let addr = block_on(author_entry(&grant_entry, None, &test_setup.context)).unwrap();
+        let addr = block_on(author_entry(&grant_entry, None, None, &test_setup.context)).unwrap();
         test_reduce_call(
             &test_setup,
             &String::from(addr),
@@ -457,21 +464,25 @@ pub mod tests {
             expected_failure.clone(),
         );
 
-        // Expecting timeout since there is no function in wasm to call
-        let expected = Err(RecvTimeoutError::Disconnected);
         let agent_token_str = test_setup.context.agent_id.key.clone();
         test_reduce_call(
             &test_setup,
             &agent_token_str,
             Address::from(agent_token_str.clone()),
-            expected.clone(),
+            expected_failure.clone(),
         );
 
+        // Expecting timeout since there is no function in wasm to call
+        let expected = Err(RecvTimeoutError::Disconnected);
         let someone = Address::from("somoeone");
-        let grant =
-            CapTokenGrant::create(CapabilityType::Assigned, Some(vec![someone.clone()])).unwrap();
+        let grant = CapTokenGrant::create(
+            &test_capability_name(),
+            CapabilityType::Assigned,
+            Some(vec![someone.clone()]),
+        )
+        .unwrap();
         let grant_entry = Entry::CapTokenGrant(grant);
-        let addr = block_on(author_entry(&grant_entry, None, &test_setup.context)).unwrap();
+        let addr = block_on(author_entry(&grant_entry, None, None, &test_setup.context)).unwrap();
         test_reduce_call(
             &test_setup,
             &String::from(addr.clone()),
@@ -479,21 +490,75 @@ pub mod tests {
             expected.clone(),
         );
 
-        /* function call doesn't know who the caller is yet so can't do the check in reduce
-                let someone_else = Address::from("somoeone_else");
-                test_reduce_call(&test_setup,&String::from(addr),someone_else, expected_failure.clone());
-        */
+        let someone_else = Address::from("somoeone_else");
+        test_reduce_call(
+            &test_setup,
+            &String::from(addr),
+            someone_else,
+            expected_failure.clone(),
+        );
     }
 
     #[test]
-    fn test_agent_as_token() {
-        let dna = test_utils::create_test_dna_with_wat("bad_zome", "test_cap", None);
+    fn test_grant_only_covers_its_capability() {
+        let dna = setup_dna_for_cap_test(CapabilityType::Transferable);
         let test_setup = setup_test(dna);
-        let agent_token = Address::from(test_setup.context.agent_id.key.clone());
-        let context = test_setup.context.clone();
-        let cap_call = CapabilityCall::new(agent_token, None);
-        assert!(is_token_the_agent(context.clone(), &Some(cap_call)));
-        let cap_call = CapabilityCall::new(Address::from(""), None);
-        assert!(!is_token_the_agent(context, &Some(cap_call)));
+        let grant = CapTokenGrant::create("other_cap", CapabilityType::Transferable, None).unwrap();
+        let grant_entry = Entry::CapTokenGrant(grant);
+        let addr = block_on(commit_entry(grant_entry, None, &test_setup.context)).unwrap();
+        let expected_failure = Ok(Err(HolochainError::CapabilityCheckFailed));
+        test_reduce_call(
+            &test_setup,
+            &String::from(addr),
+            Address::from("any caller"),
+            expected_failure,
+        );
+    }
+
+    #[test]
+    fn test_call_with_revoked_grant() {
+        let dna = setup_dna_for_cap_test(CapabilityType::Transferable);
+        let test_setup = setup_test(dna);
+        let grant =
+            CapTokenGrant::create(&test_capability_name(), CapabilityType::Transferable, None)
+                .unwrap();
+        let grant_entry = Entry::CapTokenGrant(grant);
+        let addr = block_on(author_entry(&grant_entry, None, None, &test_setup.context)).unwrap();
+
+        // Expecting timeout since there is no function in wasm to call
+        let expected = Err(RecvTimeoutError::Disconnected);
+        test_reduce_call(
+            &test_setup,
+            &String::from(addr.clone()),
+            Address::from("any caller"),
+            expected,
+        );
+
+        let deletion_entry = Entry::Deletion(DeletionEntry::new(addr.clone()));
+        block_on(commit_entry(deletion_entry, None, &test_setup.context)).unwrap();
+        let expected_failure = Ok(Err(HolochainError::CapabilityCheckFailed));
+        test_reduce_call(
+            &test_setup,
+            &String::from(addr),
+            Address::from("any caller"),
+            expected_failure,
+        );
+    }
+
+    #[test]
+    fn test_token_must_point_to_grant() {
+        let dna = setup_dna_for_cap_test(CapabilityType::Transferable);
+        let test_setup = setup_test(dna);
+        let entry = test_entry();
+        let addr = block_on(commit_entry(entry, None, &test_setup.context)).unwrap();
+        let state = test_setup.context.state().unwrap();
+        assert_eq!(state.agent().get_grant(&addr), None);
+        let expected_failure = Ok(Err(HolochainError::CapabilityCheckFailed));
+        test_reduce_call(
+            &test_setup,
+            &String::from(addr),
+            Address::from("any caller"),
+            expected_failure,
+        );
     }
 }
@@ -0,0 +1,58 @@
+use crate::{
+    nucleus::ribosome::{api::ZomeApiResult, Runtime},
+    workflows::close_bundle::close_bundle_workflow,
+};
+use futures::executor::block_on;
+use holochain_wasm_utils::api_serialization::bundle::BundleOnClose;
+use std::convert::TryFrom;
+use wasmi::{RuntimeArgs, RuntimeValue};
+
+/// ZomeApiFunction::CloseBundle function code
+/// args: [0] encoded MemoryAllocation as u64
+/// Expected complex argument: BundleOnClose
+/// Stores/returns a RibosomeEncodedValue
+pub fn invoke_close_bundle(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
+    // deserialize args
+    let args_str = runtime.load_json_string_from_args(&args);
+    let on_close = match BundleOnClose::try_from(args_str.clone()) {
+        Ok(input) => input,
+        Err(..) => {
+            runtime.context.log(format!(
+                "err/zome: invoke_close_bundle failed to deserialize BundleOnClose: {:?}",
+                args_str
+            ));
+            return ribosome_error_code!(ArgumentDeserializationFailed);
+        }
+    };
+
+    let result = block_on(close_bundle_workflow(
+        runtime.zome_call.id(),
+        on_close,
+        &runtime.context,
+    ));
+
+    runtime.store_result(result)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::nucleus::ribosome::{
+        api::{tests::test_zome_api_function, ZomeApiFunction},
+        Defn,
+    };
+    use holochain_core_types::{error::ZomeApiInternalResult, json::JsonString};
+    use holochain_wasm_utils::api_serialization::bundle::BundleOnClose;
+    use std::convert::TryFrom;
+
+    #[test]
+    /// test that closing fails if no bundle was started
+    fn test_close_bundle_without_bundle() {
+        let (call_result, _) = test_zome_api_function(
+            ZomeApiFunction::CloseBundle.as_str(),
+            JsonString::from(BundleOnClose::Commit).into_bytes(),
+        );
+
+        let result = ZomeApiInternalResult::try_from(call_result).unwrap();
+        assert!(!result.ok);
+    }
+}
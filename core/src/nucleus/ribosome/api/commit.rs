@@ -1,20 +1,26 @@
 use crate::{
+    agent::bundle::DhtUpdate,
+    network::encrypted_entry::is_encrypted_entry_type,
     nucleus::ribosome::{api::ZomeApiResult, Runtime},
-    workflows::author_entry::author_entry,
+    workflows::author_entry::author_entry_with_dht_update,
 };
 use futures::executor::block_on;
 use holochain_core_types::{cas::content::Address, entry::Entry, error::HolochainError};
+use holochain_wasm_utils::api_serialization::CommitEntryArgs;
 use std::convert::TryFrom;
 use wasmi::{RuntimeArgs, RuntimeValue};
 
 /// ZomeApiFunction::CommitAppEntry function code
 /// args: [0] encoded MemoryAllocation as u64
-/// Expected complex argument: CommitArgs
+/// Expected complex argument: Entry, or CommitEntryArgs for an entry of an encrypted entry type
 /// Returns an HcApiReturnCode as I64
 pub fn invoke_commit_app_entry(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
     // deserialize args
     let args_str = runtime.load_json_string_from_args(&args);
-    let entry = match Entry::try_from(args_str.clone()) {
+    let (entry, dht_update) = match CommitEntryArgs::try_from(args_str.clone())
+        .map(|args| (args.entry, DhtUpdate::PublishEncrypted(args.recipients)))
+        .or_else(|_| Entry::try_from(args_str.clone()).map(|entry| (entry, DhtUpdate::Publish)))
+    {
         Ok(entry_input) => entry_input,
         // Exit on error
         Err(_) => {
@@ -26,8 +32,23 @@ pub fn invoke_commit_app_entry(runtime: &mut Runtime, args: &RuntimeArgs) -> Zom
         }
     };
     // Wait for future to be resolved
-    let task_result: Result<Address, HolochainError> =
-        block_on(author_entry(&entry, None, &runtime.context));
+    let task_result: Result<Address, HolochainError> = match dht_update {
+        DhtUpdate::PublishEncrypted(_)
+            if !is_encrypted_entry_type(&entry.entry_type(), &runtime.context) =>
+        {
+            Err(HolochainError::ErrorGeneric(format!(
+                "Entry type {} is not encrypted, so there are no recipients to commit for",
+                entry.entry_type()
+            )))
+        }
+        dht_update => block_on(author_entry_with_dht_update(
+            &entry,
+            None,
+            dht_update,
+            Some(runtime.zome_call.id()),
+            &runtime.context,
+        )),
+    };
 
     runtime.store_result(task_result)
 }
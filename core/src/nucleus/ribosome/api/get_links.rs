@@ -3,9 +3,7 @@ use crate::{
     nucleus::ribosome::{api::ZomeApiResult, Runtime},
 };
 use futures::executor::block_on;
-use holochain_wasm_utils::api_serialization::get_links::{
-    GetLinksArgs, GetLinksResult, LinksStatusRequestKind,
-};
+use holochain_wasm_utils::api_serialization::get_links::{GetLinksArgs, GetLinksResult};
 use std::convert::TryFrom;
 use wasmi::{RuntimeArgs, RuntimeValue};
 
@@ -27,13 +25,6 @@ pub fn invoke_get_links(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiRes
         }
     };
 
-    if input.options.status_request != LinksStatusRequestKind::Live {
-        runtime
-            .context
-            .log("get links status request other than Live not implemented!");
-        return ribosome_error_code!(Unspecified);
-    }
-
     if input.options.sources {
         runtime
             .context
@@ -46,11 +37,13 @@ pub fn invoke_get_links(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiRes
         runtime.context.clone(),
         input.entry_address,
         input.tag,
+        input.options.tag_match,
+        input.options.status_request,
         input.options.timeout,
     ));
 
     runtime.store_result(match maybe_links {
-        Ok(links) => Ok(GetLinksResult::new(links)),
+        Ok(links) => Ok(GetLinksResult::from_links(links)),
         Err(hc_err) => Err(hc_err),
     })
 }
@@ -79,7 +72,7 @@ pub mod tests {
         json::JsonString,
         link::Link,
     };
-    use holochain_wasm_utils::api_serialization::get_links::GetLinksArgs;
+    use holochain_wasm_utils::api_serialization::get_links::{GetLinksArgs, GetLinksResult};
     use serde_json;
 
     /// dummy link_entries args from standard test entry
@@ -132,19 +125,17 @@ pub mod tests {
             test_get_links_args_bytes(&entry_addresses[0], "test-tag"),
         );
 
-        let expected_1 = JsonString::from(
-            format!(
-                r#"{{"ok":true,"value":"{{\"addresses\":[\"{}\",\"{}\"]}}","error":"null"}}"#,
-                entry_addresses[1], entry_addresses[2]
-            ) + "\u{0}",
-        );
-
-        let expected_2 = JsonString::from(
-            format!(
-                r#"{{"ok":true,"value":"{{\"addresses\":[\"{}\",\"{}\"]}}","error":"null"}}"#,
-                entry_addresses[2], entry_addresses[1]
-            ) + "\u{0}",
-        );
+        let expected = |links: Vec<Link>| {
+            let value = String::from(JsonString::from(GetLinksResult::from_links(links)));
+            JsonString::from(
+                format!(
+                    r#"{{"ok":true,"value":{},"error":"null"}}"#,
+                    serde_json::to_string(&value).unwrap()
+                ) + "\u{0}",
+            )
+        };
+        let expected_1 = expected(vec![link1.clone(), link2.clone()]);
+        let expected_2 = expected(vec![link2.clone(), link1.clone()]);
 
         assert!(
             call_result == expected_1 || call_result == expected_2,
@@ -0,0 +1,46 @@
+use crate::nucleus::ribosome::{api::ZomeApiResult, Runtime};
+use holochain_core_types::error::HolochainError;
+use holochain_wasm_utils::api_serialization::peers::GetPeersResult;
+use wasmi::{RuntimeArgs, RuntimeValue};
+
+/// ZomeApiFunction::GetPeers function code
+/// args: [0] encoded MemoryAllocation as u64
+/// Expected argument: none
+/// Returns a GetPeersResult with all peers of this DNA the node knows about
+pub fn invoke_get_peers(runtime: &mut Runtime, _args: &RuntimeArgs) -> ZomeApiResult {
+    let result = runtime
+        .context
+        .state()
+        .map(|state| GetPeersResult {
+            peers: state.network().peer_store.peers(),
+        })
+        .ok_or_else(|| HolochainError::ErrorGeneric("State not initialized".to_string()));
+
+    runtime.store_result(result)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::nucleus::ribosome::{
+        api::{tests::test_zome_api_function, ZomeApiFunction},
+        Defn,
+    };
+    use holochain_core_types::{error::ZomeApiInternalResult, json::JsonString};
+    use holochain_wasm_utils::api_serialization::peers::GetPeersResult;
+
+    #[test]
+    /// test that a node without peers lists none
+    fn test_get_peers_round_trip() {
+        let (call_result, _) =
+            test_zome_api_function(ZomeApiFunction::GetPeers.as_str(), Vec::new());
+
+        assert_eq!(
+            call_result,
+            JsonString::from(
+                String::from(JsonString::from(ZomeApiInternalResult::success(
+                    GetPeersResult { peers: Vec::new() }
+                ))) + "\u{0}"
+            ),
+        );
+    }
+}
@@ -0,0 +1,66 @@
+use crate::{
+    network::validation_receipt::validation_receipts,
+    nucleus::ribosome::{api::ZomeApiResult, Runtime},
+};
+use holochain_core_types::cas::content::Address;
+use holochain_wasm_utils::api_serialization::validation_receipt::GetValidationReceiptsResult;
+use std::convert::TryFrom;
+use wasmi::{RuntimeArgs, RuntimeValue};
+
+/// ZomeApiFunction::GetValidationReceipts function code
+/// args: [0] encoded MemoryAllocation as u64
+/// Expected Address argument: the address of one of our entries
+/// Returns a GetValidationReceiptsResult with the latest receipt of every holder
+/// that validated the entry
+pub fn invoke_get_validation_receipts(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
+    // deserialize args
+    let args_str = runtime.load_json_string_from_args(&args);
+    let entry_address = match Address::try_from(args_str.clone()) {
+        Ok(entry_address) => entry_address,
+        Err(..) => {
+            runtime.context.log(format!(
+                "err/zome: invoke_get_validation_receipts failed to deserialize Address: {:?}",
+                args_str
+            ));
+            return ribosome_error_code!(ArgumentDeserializationFailed);
+        }
+    };
+
+    let result = validation_receipts(&entry_address, &runtime.context)
+        .map(|receipts| GetValidationReceiptsResult { receipts });
+
+    runtime.store_result(result)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::nucleus::ribosome::{
+        api::{tests::test_zome_api_function, ZomeApiFunction},
+        Defn,
+    };
+    use holochain_core_types::{
+        cas::content::AddressableContent, entry::test_entry, error::ZomeApiInternalResult,
+        json::JsonString,
+    };
+    use holochain_wasm_utils::api_serialization::validation_receipt::GetValidationReceiptsResult;
+
+    #[test]
+    /// test that an entry nobody validated yet has no receipts
+    fn test_get_validation_receipts_round_trip() {
+        let (call_result, _) = test_zome_api_function(
+            ZomeApiFunction::GetValidationReceipts.as_str(),
+            JsonString::from(test_entry().address()).into_bytes(),
+        );
+
+        assert_eq!(
+            call_result,
+            JsonString::from(
+                String::from(JsonString::from(ZomeApiInternalResult::success(
+                    GetValidationReceiptsResult {
+                        receipts: Vec::new()
+                    }
+                ))) + "\u{0}"
+            ),
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use crate::{
+    nucleus::ribosome::{api::ZomeApiResult, Runtime},
+    workflows::author_entry::author_entry,
+};
+use futures::executor::block_on;
+use holochain_core_types::{
+    cas::content::Address,
+    entry::{cap_entries::CapTokenGrant, Entry},
+    error::HolochainError,
+};
+use holochain_wasm_utils::api_serialization::capabilities::GrantCapabilityArgs;
+use std::convert::TryFrom;
+use wasmi::{RuntimeArgs, RuntimeValue};
+
+/// ZomeApiFunction::GrantCapability function code
+/// args: [0] encoded MemoryAllocation as u64
+/// Expected complex argument: GrantCapabilityArgs
+/// Commits a CapTokenGrant entry and returns its address, which is the token to hand out.
+/// Returns an HcApiReturnCode as I64
+pub fn invoke_grant_capability(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
+    // deserialize args
+    let args_str = runtime.load_json_string_from_args(&args);
+    let grant_args = match GrantCapabilityArgs::try_from(args_str.clone()) {
+        Ok(input) => input,
+        Err(..) => {
+            runtime.context.log(format!(
+                "err/zome: invoke_grant_capability failed to deserialize: {:?}",
+                args_str
+            ));
+            return ribosome_error_code!(ArgumentDeserializationFailed);
+        }
+    };
+
+    let result: Result<Address, HolochainError> = CapTokenGrant::create(
+        &grant_args.cap_name,
+        grant_args.cap_type,
+        grant_args.assignees,
+    )
+    .and_then(|grant| {
+        block_on(author_entry(
+            &Entry::CapTokenGrant(grant),
+            None,
+            Some(runtime.zome_call.id()),
+            &runtime.context,
+        ))
+    });
+
+    runtime.store_result(result)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::nucleus::{
+        ribosome::{
+            api::{tests::test_zome_api_function, ZomeApiFunction},
+            Defn,
+        },
+        tests::test_capability_name,
+    };
+    use holochain_core_types::{
+        dna::capabilities::CapabilityType, entry::cap_entries::CapTokenGrant,
+        error::ZomeApiInternalResult, json::JsonString,
+    };
+    use holochain_wasm_utils::api_serialization::capabilities::GrantCapabilityArgs;
+    use std::convert::TryFrom;
+
+    pub fn test_grant_capability_args_bytes(cap_name: &str, cap_type: CapabilityType) -> Vec<u8> {
+        let args = GrantCapabilityArgs {
+            cap_name: cap_name.to_string(),
+            cap_type,
+            assignees: None,
+        };
+        JsonString::from(args).into_bytes()
+    }
+
+    #[test]
+    /// test that a grant for a declared capability gets committed and its token returned
+    fn test_grant_capability_round_trip() {
+        let (call_result, context) = test_zome_api_function(
+            ZomeApiFunction::GrantCapability.as_str(),
+            test_grant_capability_args_bytes(&test_capability_name(), CapabilityType::Public),
+        );
+
+        let grant =
+            CapTokenGrant::create(&test_capability_name(), CapabilityType::Public, None).unwrap();
+        assert_eq!(
+            call_result,
+            JsonString::from(
+                String::from(JsonString::from(ZomeApiInternalResult::success(
+                    grant.token()
+                ))) + "\u{0}"
+            ),
+        );
+        let state = context.state().unwrap();
+        assert_eq!(state.agent().get_grants(), vec![grant]);
+    }
+
+    #[test]
+    /// test that grants are validated against the capabilities of the DNA
+    fn test_grant_capability_validation() {
+        for (cap_name, cap_type) in vec![
+            (test_capability_name(), CapabilityType::Transferable),
+            (String::from("undeclared_cap"), CapabilityType::Public),
+        ] {
+            let (call_result, _) = test_zome_api_function(
+                ZomeApiFunction::GrantCapability.as_str(),
+                test_grant_capability_args_bytes(&cap_name, cap_type),
+            );
+            let result = ZomeApiInternalResult::try_from(call_result).unwrap();
+            assert!(!result.ok);
+        }
+    }
+}
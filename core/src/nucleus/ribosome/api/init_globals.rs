@@ -54,11 +54,14 @@ pub fn invoke_init_globals(runtime: &mut Runtime, _args: &RuntimeArgs) -> ZomeAp
 
 #[cfg(test)]
 pub mod tests {
-    use crate::nucleus::ribosome::{
-        api::{tests::test_zome_api_function, ZomeApiFunction},
-        Defn,
+    use crate::{
+        agent::keys::test_agent_id_with_keys,
+        nucleus::ribosome::{
+            api::{tests::test_zome_api_function, ZomeApiFunction},
+            Defn,
+        },
     };
-    use holochain_core_types::{agent::AgentId, error::ZomeApiInternalResult, json::JsonString};
+    use holochain_core_types::{error::ZomeApiInternalResult, json::JsonString};
     use holochain_wasm_utils::api_serialization::ZomeApiGlobals;
     use std::convert::TryFrom;
 
@@ -73,7 +76,7 @@ pub mod tests {
             ZomeApiGlobals::try_from(JsonString::from(zome_api_internal_result.value)).unwrap();
 
         assert_eq!(globals.dna_name, "TestApp");
-        let expected_agent = AgentId::generate_fake("jane");
+        let expected_agent = test_agent_id_with_keys("jane");
         assert_eq!(globals.agent_address.to_string(), expected_agent.key);
         // TODO (david.b) this should work:
         //assert_eq!(globals.agent_id_str, String::from(AgentId::generate_fake("jane")));
@@ -31,8 +31,13 @@ pub fn invoke_link_entries(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApi
     let entry = Entry::LinkAdd(link_add);
 
     // Wait for future to be resolved
-    let result: Result<(), HolochainError> =
-        block_on(author_entry(&entry, None, &runtime.context)).map(|_| ());
+    let result: Result<(), HolochainError> = block_on(author_entry(
+        &entry,
+        None,
+        Some(runtime.zome_call.id()),
+        &runtime.context,
+    ))
+    .map(|_| ());
 
     runtime.store_result(result)
 }
@@ -80,6 +85,7 @@ pub mod tests {
             base: entry.address(),
             target: entry.address(),
             tag,
+            payload: None,
         };
         serde_json::to_string(&args)
             .expect("args should serialize")
@@ -94,6 +100,7 @@ pub mod tests {
             base: base.address(),
             target: target.address(),
             tag,
+            payload: None,
         };
         serde_json::to_string(&args)
             .expect("args should serialize")
@@ -0,0 +1,46 @@
+use crate::nucleus::ribosome::{api::ZomeApiResult, Runtime};
+use holochain_core_types::error::HolochainError;
+use holochain_wasm_utils::api_serialization::capabilities::ListGrantsResult;
+use wasmi::{RuntimeArgs, RuntimeValue};
+
+/// ZomeApiFunction::ListGrants function code
+/// args: [0] encoded MemoryAllocation as u64
+/// Expected argument: none
+/// Returns a ListGrantsResult with all grants on the local chain that have not been revoked
+pub fn invoke_list_grants(runtime: &mut Runtime, _args: &RuntimeArgs) -> ZomeApiResult {
+    let result = runtime
+        .context
+        .state()
+        .map(|state| ListGrantsResult {
+            grants: state.agent().get_grants(),
+        })
+        .ok_or_else(|| HolochainError::ErrorGeneric("State not initialized".to_string()));
+
+    runtime.store_result(result)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::nucleus::ribosome::{
+        api::{tests::test_zome_api_function, ZomeApiFunction},
+        Defn,
+    };
+    use holochain_core_types::{error::ZomeApiInternalResult, json::JsonString};
+    use holochain_wasm_utils::api_serialization::capabilities::ListGrantsResult;
+
+    #[test]
+    /// test that the grants of a fresh chain can be listed
+    fn test_list_grants_round_trip() {
+        let (call_result, _) =
+            test_zome_api_function(ZomeApiFunction::ListGrants.as_str(), Vec::new());
+
+        assert_eq!(
+            call_result,
+            JsonString::from(
+                String::from(JsonString::from(ZomeApiInternalResult::success(
+                    ListGrantsResult { grants: Vec::new() }
+                ))) + "\u{0}"
+            ),
+        );
+    }
+}
@@ -2,25 +2,45 @@
 //! ZomeApiFunctions are the functions provided by the ribosome that are callable by Zomes.
 
 pub mod call;
+pub mod close_bundle;
 pub mod commit;
 pub mod debug;
 pub mod entry_address;
 pub mod get_entry;
 pub mod get_links;
+pub mod get_peers;
+pub mod get_validation_receipts;
+pub mod grant_capability;
 pub mod init_globals;
 pub mod link_entries;
+pub mod list_grants;
+pub mod property;
 pub mod query;
+pub mod query_migrated_chain;
 pub mod remove_entry;
+pub mod remove_link;
+pub mod revoke_capability;
 pub mod send;
+pub mod sign;
+pub mod start_bundle;
+pub mod update_agent;
 pub mod update_entry;
+pub mod verify_signature;
 
 use crate::nucleus::ribosome::{
     api::{
-        call::invoke_call, commit::invoke_commit_app_entry, debug::invoke_debug,
-        entry_address::invoke_entry_address, get_entry::invoke_get_entry,
-        get_links::invoke_get_links, init_globals::invoke_init_globals,
-        link_entries::invoke_link_entries, query::invoke_query, remove_entry::invoke_remove_entry,
-        send::invoke_send, update_entry::invoke_update_entry,
+        call::invoke_call, close_bundle::invoke_close_bundle, commit::invoke_commit_app_entry,
+        debug::invoke_debug, entry_address::invoke_entry_address, get_entry::invoke_get_entry,
+        get_links::invoke_get_links, get_peers::invoke_get_peers,
+        get_validation_receipts::invoke_get_validation_receipts,
+        grant_capability::invoke_grant_capability, init_globals::invoke_init_globals,
+        link_entries::invoke_link_entries, list_grants::invoke_list_grants,
+        property::invoke_property, query::invoke_query,
+        query_migrated_chain::invoke_query_migrated_chain, remove_entry::invoke_remove_entry,
+        remove_link::invoke_remove_link, revoke_capability::invoke_revoke_capability,
+        send::invoke_send, sign::invoke_sign, start_bundle::invoke_start_bundle,
+        update_agent::invoke_update_agent, update_entry::invoke_update_entry,
+        verify_signature::invoke_verify_signature,
     },
     runtime::Runtime,
     Defn,
@@ -88,6 +108,58 @@ pub enum ZomeApiFunction {
     EntryAddress,
 
     Send,
+
+    /// Sign a payload with the private key of the agent running the instance
+    /// hc_sign(payload: String) -> Signature
+    Sign,
+
+    /// Check a signature of a payload against the public key it supposedly belongs to
+    /// hc_verify_signature(pub_key: Address, payload: String, signature: Signature) -> bool
+    VerifySignature,
+
+    /// Commit a CapTokenGrant for a capability of this DNA and return its token
+    /// hc_grant_capability(cap_name: String, cap_type: CapabilityType, assignees: Option<Vec<Address>>) -> Address
+    GrantCapability,
+
+    /// List all grants on the local chain that have not been revoked
+    /// hc_list_grants() -> Vec<CapTokenGrant>
+    ListGrants,
+
+    /// Revoke a grant by committing a deletion of it
+    /// hc_revoke_capability(token: Address)
+    RevokeCapability,
+
+    /// Get a named property of the DNA or the whole properties object
+    /// hc_property(name: Option<String>) -> JsonString
+    Property,
+
+    /// Start a bundle in which all following commits get staged until it is closed
+    /// hc_start_bundle(timeout: usize, user_param: serde_json::Value)
+    StartBundle,
+
+    /// Commit or discard the staged commits of the open bundle
+    /// hc_close_bundle(action: BundleOnClose)
+    CloseBundle,
+
+    /// Commit a new agent entry with rotated keys that replaces the current one
+    /// hc_update_agent() -> Address
+    UpdateAgent,
+
+    /// Query the closed source chain this instance's chain got migrated from
+    /// hc_query_migrated_chain(query: QueryArgs) -> QueryResult
+    QueryMigratedChain,
+
+    /// Mark a link between two entries as removed
+    /// hc_remove_link(base: Address, target: Address, tag: String)
+    RemoveLink,
+
+    /// Lists the peers of this DNA the node knows about
+    /// hc_get_peers() -> Vec<PeerInfo>
+    GetPeers,
+
+    /// Lists the validation receipts holders sent us for one of our entries
+    /// hc_get_validation_receipts(entry_address: Address) -> Vec<ValidationReceipt>
+    GetValidationReceipts,
 }
 
 impl Defn for ZomeApiFunction {
@@ -107,6 +179,19 @@ impl Defn for ZomeApiFunction {
             ZomeApiFunction::Query => "hc_query",
             ZomeApiFunction::EntryAddress => "hc_entry_address",
             ZomeApiFunction::Send => "hc_send",
+            ZomeApiFunction::Sign => "hc_sign",
+            ZomeApiFunction::VerifySignature => "hc_verify_signature",
+            ZomeApiFunction::GrantCapability => "hc_grant_capability",
+            ZomeApiFunction::ListGrants => "hc_list_grants",
+            ZomeApiFunction::RevokeCapability => "hc_revoke_capability",
+            ZomeApiFunction::Property => "hc_property",
+            ZomeApiFunction::StartBundle => "hc_start_bundle",
+            ZomeApiFunction::CloseBundle => "hc_close_bundle",
+            ZomeApiFunction::UpdateAgent => "hc_update_agent",
+            ZomeApiFunction::QueryMigratedChain => "hc_query_migrated_chain",
+            ZomeApiFunction::RemoveLink => "hc_remove_link",
+            ZomeApiFunction::GetPeers => "hc_get_peers",
+            ZomeApiFunction::GetValidationReceipts => "hc_get_validation_receipts",
         }
     }
 
@@ -145,6 +230,19 @@ impl FromStr for ZomeApiFunction {
             "hc_query" => Ok(ZomeApiFunction::Query),
             "hc_entry_address" => Ok(ZomeApiFunction::EntryAddress),
             "hc_send" => Ok(ZomeApiFunction::Send),
+            "hc_sign" => Ok(ZomeApiFunction::Sign),
+            "hc_verify_signature" => Ok(ZomeApiFunction::VerifySignature),
+            "hc_grant_capability" => Ok(ZomeApiFunction::GrantCapability),
+            "hc_list_grants" => Ok(ZomeApiFunction::ListGrants),
+            "hc_revoke_capability" => Ok(ZomeApiFunction::RevokeCapability),
+            "hc_property" => Ok(ZomeApiFunction::Property),
+            "hc_start_bundle" => Ok(ZomeApiFunction::StartBundle),
+            "hc_close_bundle" => Ok(ZomeApiFunction::CloseBundle),
+            "hc_update_agent" => Ok(ZomeApiFunction::UpdateAgent),
+            "hc_query_migrated_chain" => Ok(ZomeApiFunction::QueryMigratedChain),
+            "hc_remove_link" => Ok(ZomeApiFunction::RemoveLink),
+            "hc_get_peers" => Ok(ZomeApiFunction::GetPeers),
+            "hc_get_validation_receipts" => Ok(ZomeApiFunction::GetValidationReceipts),
             _ => Err("Cannot convert string to ZomeApiFunction"),
         }
     }
@@ -177,6 +275,19 @@ impl ZomeApiFunction {
             ZomeApiFunction::Query => invoke_query,
             ZomeApiFunction::EntryAddress => invoke_entry_address,
             ZomeApiFunction::Send => invoke_send,
+            ZomeApiFunction::Sign => invoke_sign,
+            ZomeApiFunction::VerifySignature => invoke_verify_signature,
+            ZomeApiFunction::GrantCapability => invoke_grant_capability,
+            ZomeApiFunction::ListGrants => invoke_list_grants,
+            ZomeApiFunction::RevokeCapability => invoke_revoke_capability,
+            ZomeApiFunction::Property => invoke_property,
+            ZomeApiFunction::StartBundle => invoke_start_bundle,
+            ZomeApiFunction::CloseBundle => invoke_close_bundle,
+            ZomeApiFunction::UpdateAgent => invoke_update_agent,
+            ZomeApiFunction::QueryMigratedChain => invoke_query_migrated_chain,
+            ZomeApiFunction::RemoveLink => invoke_remove_link,
+            ZomeApiFunction::GetPeers => invoke_get_peers,
+            ZomeApiFunction::GetValidationReceipts => invoke_get_validation_receipts,
         }
     }
 }
@@ -432,6 +543,25 @@ pub mod tests {
             ("hc_query", ZomeApiFunction::Query),
             ("hc_entry_address", ZomeApiFunction::EntryAddress),
             ("hc_send", ZomeApiFunction::Send),
+            ("hc_sign", ZomeApiFunction::Sign),
+            ("hc_verify_signature", ZomeApiFunction::VerifySignature),
+            ("hc_grant_capability", ZomeApiFunction::GrantCapability),
+            ("hc_list_grants", ZomeApiFunction::ListGrants),
+            ("hc_revoke_capability", ZomeApiFunction::RevokeCapability),
+            ("hc_property", ZomeApiFunction::Property),
+            ("hc_start_bundle", ZomeApiFunction::StartBundle),
+            ("hc_close_bundle", ZomeApiFunction::CloseBundle),
+            ("hc_update_agent", ZomeApiFunction::UpdateAgent),
+            (
+                "hc_query_migrated_chain",
+                ZomeApiFunction::QueryMigratedChain,
+            ),
+            ("hc_remove_link", ZomeApiFunction::RemoveLink),
+            ("hc_get_peers", ZomeApiFunction::GetPeers),
+            (
+                "hc_get_validation_receipts",
+                ZomeApiFunction::GetValidationReceipts,
+            ),
         ] {
             assert_eq!(ZomeApiFunction::from_str(input).unwrap(), output);
         }
@@ -461,6 +591,25 @@ pub mod tests {
             (ZomeApiFunction::Query, "hc_query"),
             (ZomeApiFunction::EntryAddress, "hc_entry_address"),
             (ZomeApiFunction::Send, "hc_send"),
+            (ZomeApiFunction::Sign, "hc_sign"),
+            (ZomeApiFunction::VerifySignature, "hc_verify_signature"),
+            (ZomeApiFunction::GrantCapability, "hc_grant_capability"),
+            (ZomeApiFunction::ListGrants, "hc_list_grants"),
+            (ZomeApiFunction::RevokeCapability, "hc_revoke_capability"),
+            (ZomeApiFunction::Property, "hc_property"),
+            (ZomeApiFunction::StartBundle, "hc_start_bundle"),
+            (ZomeApiFunction::CloseBundle, "hc_close_bundle"),
+            (ZomeApiFunction::UpdateAgent, "hc_update_agent"),
+            (
+                ZomeApiFunction::QueryMigratedChain,
+                "hc_query_migrated_chain",
+            ),
+            (ZomeApiFunction::RemoveLink, "hc_remove_link"),
+            (ZomeApiFunction::GetPeers, "hc_get_peers"),
+            (
+                ZomeApiFunction::GetValidationReceipts,
+                "hc_get_validation_receipts",
+            ),
         ] {
             assert_eq!(output, input.as_str());
         }
@@ -481,6 +630,19 @@ pub mod tests {
             ("hc_query", 11),
             ("hc_entry_address", 12),
             ("hc_send", 13),
+            ("hc_sign", 14),
+            ("hc_verify_signature", 15),
+            ("hc_grant_capability", 16),
+            ("hc_list_grants", 17),
+            ("hc_revoke_capability", 18),
+            ("hc_property", 19),
+            ("hc_start_bundle", 20),
+            ("hc_close_bundle", 21),
+            ("hc_update_agent", 22),
+            ("hc_query_migrated_chain", 23),
+            ("hc_remove_link", 24),
+            ("hc_get_peers", 25),
+            ("hc_get_validation_receipts", 26),
         ] {
             assert_eq!(output, ZomeApiFunction::str_to_index(input));
         }
@@ -501,6 +663,19 @@ pub mod tests {
             (11, ZomeApiFunction::Query),
             (12, ZomeApiFunction::EntryAddress),
             (13, ZomeApiFunction::Send),
+            (14, ZomeApiFunction::Sign),
+            (15, ZomeApiFunction::VerifySignature),
+            (16, ZomeApiFunction::GrantCapability),
+            (17, ZomeApiFunction::ListGrants),
+            (18, ZomeApiFunction::RevokeCapability),
+            (19, ZomeApiFunction::Property),
+            (20, ZomeApiFunction::StartBundle),
+            (21, ZomeApiFunction::CloseBundle),
+            (22, ZomeApiFunction::UpdateAgent),
+            (23, ZomeApiFunction::QueryMigratedChain),
+            (24, ZomeApiFunction::RemoveLink),
+            (25, ZomeApiFunction::GetPeers),
+            (26, ZomeApiFunction::GetValidationReceipts),
         ] {
             assert_eq!(output, ZomeApiFunction::from_index(input));
         }
@@ -0,0 +1,122 @@
+use crate::nucleus::ribosome::{api::ZomeApiResult, Runtime};
+use holochain_core_types::{error::HolochainError, json::JsonString};
+use holochain_wasm_utils::api_serialization::property::PropertyArgs;
+use std::convert::TryFrom;
+use wasmi::{RuntimeArgs, RuntimeValue};
+
+/// ZomeApiFunction::Property function code
+/// args: [0] encoded MemoryAllocation as u64
+/// Expected complex argument: PropertyArgs
+/// Returns the JSON value of the requested property, or of all properties if no name is given
+pub fn invoke_property(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
+    // deserialize args
+    let args_str = runtime.load_json_string_from_args(&args);
+    let property_args = match PropertyArgs::try_from(args_str.clone()) {
+        Ok(input) => input,
+        Err(..) => {
+            runtime.context.log(format!(
+                "err/zome: invoke_property failed to deserialize PropertyArgs: {:?}",
+                args_str
+            ));
+            return ribosome_error_code!(ArgumentDeserializationFailed);
+        }
+    };
+
+    let result = match runtime.context.get_dna() {
+        Some(dna) => dna
+            .get_property(property_args.name.as_ref().map(String::as_str))
+            .map(|value| JsonString::from(value.clone()))
+            .ok_or_else(|| {
+                HolochainError::ErrorGeneric(format!(
+                    "DNA property \"{}\" not found",
+                    property_args.name.unwrap_or_default()
+                ))
+            }),
+        None => Err(HolochainError::DnaMissing),
+    };
+
+    runtime.store_result(result)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::{
+        instance::tests::test_instance_and_context,
+        nucleus::{
+            ribosome::{
+                api::{
+                    tests::{
+                        test_zome_api_function_call, test_zome_api_function_wasm, test_zome_name,
+                    },
+                    ZomeApiFunction,
+                },
+                Defn,
+            },
+            tests::test_capability_name,
+        },
+    };
+    use holochain_core_types::{error::ZomeApiInternalResult, json::JsonString};
+    use holochain_wasm_utils::api_serialization::property::PropertyArgs;
+    use std::convert::TryFrom;
+
+    fn test_property_args_bytes(name: Option<&str>) -> Vec<u8> {
+        let args = PropertyArgs {
+            name: name.map(String::from),
+        };
+        JsonString::from(args).into_bytes()
+    }
+
+    fn call_property(name: Option<&str>) -> JsonString {
+        let wasm = test_zome_api_function_wasm(ZomeApiFunction::Property.as_str());
+        let mut dna = test_utils::create_test_dna_with_wasm(
+            &test_zome_name(),
+            &test_capability_name(),
+            wasm.clone(),
+        );
+        dna.properties = json!({"language": "en", "limits": {"posts": 10}});
+        let dna_name = &dna.name.to_string().clone();
+        let (instance, context) =
+            test_instance_and_context(dna, None).expect("Could not create test instance");
+
+        test_zome_api_function_call(
+            &dna_name,
+            context,
+            &instance,
+            &wasm,
+            test_property_args_bytes(name),
+        )
+    }
+
+    #[test]
+    /// test that a named property can be read
+    fn test_property_round_trip() {
+        assert_eq!(
+            call_property(Some("limits")),
+            JsonString::from(
+                String::from(JsonString::from(ZomeApiInternalResult::success(
+                    JsonString::from(json!({"posts": 10}))
+                ))) + "\u{0}"
+            ),
+        );
+    }
+
+    #[test]
+    /// test that all properties get returned if no name is given
+    fn test_all_properties() {
+        assert_eq!(
+            call_property(None),
+            JsonString::from(
+                String::from(JsonString::from(ZomeApiInternalResult::success(
+                    JsonString::from(json!({"language": "en", "limits": {"posts": 10}}))
+                ))) + "\u{0}"
+            ),
+        );
+    }
+
+    #[test]
+    /// test that asking for an undefined property fails
+    fn test_missing_property() {
+        let result = ZomeApiInternalResult::try_from(call_property(Some("missing")));
+        assert!(!result.unwrap().ok);
+    }
+}
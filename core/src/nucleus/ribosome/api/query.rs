@@ -65,10 +65,31 @@ pub fn invoke_query(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult
     };
 
     // Perform query
-    let agent = runtime.context.state().unwrap().agent();
+    let result = match query_chain(&runtime.context, query) {
+        Ok(result) => Ok(result),
+        // TODO #793: the Err(_code) is the RibosomeErrorCode, but we can't import that type here.
+        // Perhaps return chain().query should return Some(result)/None instead, and the fixed
+        // UnknownEntryType code here, rather than trying to return a specific error code.
+        Err(_e) => return ribosome_error_code!(UnknownEntryType), // TODO: return actual error?
+    };
+
+    runtime.store_result(result)
+}
+
+/// Runs the given query against the source chain of the given context
+pub(crate) fn query_chain(
+    context: &Arc<Context>,
+    query: QueryArgs,
+) -> Result<QueryResult, HolochainError> {
+    let agent = context.state().unwrap().agent();
     let top = agent
         .top_chain_header()
         .expect("Should have genesis entries.");
+    let options = ChainStoreQueryOptions {
+        start: query.options.start,
+        limit: query.options.limit,
+        headers: query.options.headers,
+    };
     let maybe_result = match query.entry_type_names {
         // Result<ChainStoreQueryResult,...>
         QueryArgsNames::QueryList(pats) => {
@@ -76,11 +97,7 @@ pub fn invoke_query(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult
             agent.chain().query(
                 &Some(top),
                 refs.as_slice(), // Vec<&str> -> Vec[&str]
-                ChainStoreQueryOptions {
-                    start: query.options.start,
-                    limit: query.options.limit,
-                    headers: query.options.headers,
-                },
+                options,
             )
         }
         QueryArgsNames::QueryName(name) => {
@@ -88,53 +105,32 @@ pub fn invoke_query(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult
             agent.chain().query(
                 &Some(top),
                 refs.as_slice(), // Vec<&str> -> &[&str]
-                ChainStoreQueryOptions {
-                    start: query.options.start,
-                    limit: query.options.limit,
-                    headers: query.options.headers,
-                },
+                options,
             )
         }
     };
-    let result = match maybe_result {
-        // TODO #793: the Err(_code) is the RibosomeErrorCode, but we can't import that type here.
-        // Perhaps return chain().query should return Some(result)/None instead, and the fixed
-        // UnknownEntryType code here, rather than trying to return a specific error code.
-        Ok(result) => Ok(match (query.options.entries, result) {
-            (false, ChainStoreQueryResult::Addresses(addresses)) => {
-                QueryResult::Addresses(addresses)
-            }
-            (false, ChainStoreQueryResult::Headers(headers)) => QueryResult::Headers(headers),
-            (true, ChainStoreQueryResult::Addresses(addresses)) => {
-                let maybe_entries: Result<Vec<(Address, Entry)>, HolochainError> = addresses
-                    .iter()
-                    .map(|address| // -> Result<Entry, HolochainError>
-                         Ok((address.to_owned(), get_entry_from_chain(&runtime.context, address)?)))
-                    .collect();
-
-                match maybe_entries {
-                    Ok(entries) => QueryResult::Entries(entries),
-                    Err(_e) => return ribosome_error_code!(UnknownEntryType), // TODO: return actual error?
-                }
-            }
-            (true, ChainStoreQueryResult::Headers(headers)) => {
-                let maybe_headers_with_entries: Result<Vec<(ChainHeader,Entry)>,HolochainError> = headers
-                    .iter()
-                    .map(|header| // -> Result<Entry, HolochainError>
-                         Ok((header.to_owned(), get_entry_from_chain(&runtime.context,header.entry_address())?)))
-                    .collect();
-                match maybe_headers_with_entries {
-                    Ok(headers_with_entries) => {
-                        QueryResult::HeadersWithEntries(headers_with_entries)
-                    }
-                    Err(_e) => return ribosome_error_code!(UnknownEntryType), // TODO: return actual error?
-                }
-            }
-        }),
-        Err(_code) => return ribosome_error_code!(UnknownEntryType),
-    };
-
-    runtime.store_result(result)
+    let result = maybe_result
+        .map_err(|_code| HolochainError::ErrorGeneric("Unknown entry type in query".to_string()))?;
+    Ok(match (query.options.entries, result) {
+        (false, ChainStoreQueryResult::Addresses(addresses)) => QueryResult::Addresses(addresses),
+        (false, ChainStoreQueryResult::Headers(headers)) => QueryResult::Headers(headers),
+        (true, ChainStoreQueryResult::Addresses(addresses)) => {
+            let entries: Result<Vec<(Address, Entry)>, HolochainError> = addresses
+                .iter()
+                .map(|address| // -> Result<Entry, HolochainError>
+                     Ok((address.to_owned(), get_entry_from_chain(context, address)?)))
+                .collect();
+            QueryResult::Entries(entries?)
+        }
+        (true, ChainStoreQueryResult::Headers(headers)) => {
+            let headers_with_entries: Result<Vec<(ChainHeader, Entry)>, HolochainError> = headers
+                .iter()
+                .map(|header| // -> Result<Entry, HolochainError>
+                     Ok((header.to_owned(), get_entry_from_chain(context, header.entry_address())?)))
+                .collect();
+            QueryResult::HeadersWithEntries(headers_with_entries?)
+        }
+    })
 }
 
 /// Get an local-chain Entry via the provided context, returning Entry or HolochainError on failure
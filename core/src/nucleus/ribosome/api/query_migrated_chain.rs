@@ -0,0 +1,58 @@
+use crate::nucleus::ribosome::{
+    api::{query::query_chain, ZomeApiResult},
+    Runtime,
+};
+use holochain_core_types::error::HolochainError;
+use holochain_wasm_utils::api_serialization::QueryArgs;
+use std::convert::TryFrom;
+use wasmi::{RuntimeArgs, RuntimeValue};
+
+/// ZomeApiFunction::QueryMigratedChain function code
+/// args: [0] encoded MemoryAllocation as u64
+/// Expected complex argument: QueryArgs
+/// Returns the QueryResult of the query against the closed source chain this instance's chain
+/// got migrated from. See invoke_query for the supported patterns.
+pub fn invoke_query_migrated_chain(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
+    // deserialize args
+    let args_str = runtime.load_json_string_from_args(&args);
+    let query = match QueryArgs::try_from(args_str.clone()) {
+        Ok(input) => input,
+        Err(..) => {
+            runtime.context.log(format!(
+                "err/zome: invoke_query_migrated_chain failed to deserialize QueryArgs: {:?}",
+                args_str
+            ));
+            return ribosome_error_code!(ArgumentDeserializationFailed);
+        }
+    };
+
+    let result = match runtime.context.migrated_from {
+        Some(ref old_context) => query_chain(old_context, query),
+        None => Err(HolochainError::ErrorGeneric(
+            "Source chain was not migrated from another chain".to_string(),
+        )),
+    };
+
+    runtime.store_result(result)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::nucleus::ribosome::{
+        api::{tests::test_zome_api_function, ZomeApiFunction},
+        Defn,
+    };
+    use holochain_core_types::{error::ZomeApiInternalResult, json::JsonString};
+    use holochain_wasm_utils::api_serialization::QueryArgs;
+    use std::convert::TryFrom;
+
+    #[test]
+    /// test that querying fails for an instance that was not migrated
+    fn test_query_migrated_chain_without_migration() {
+        let args = JsonString::from(QueryArgs::default()).into_bytes();
+        let (call_result, _) =
+            test_zome_api_function(ZomeApiFunction::QueryMigratedChain.as_str(), args);
+        let result = ZomeApiInternalResult::try_from(call_result).unwrap();
+        assert!(!result.ok);
+    }
+}
@@ -1,11 +1,10 @@
 use crate::{
-    agent::actions::commit::commit_entry,
-    dht::actions::remove_entry::remove_entry,
+    agent::bundle::DhtUpdate,
     nucleus::{
         actions::{build_validation_package::*, validate::*},
         ribosome::{api::ZomeApiResult, Runtime},
     },
-    workflows::get_entry_result::get_entry_result_workflow,
+    workflows::{author_entry::commit_and_update_dht, get_entry_result::get_entry_result_workflow},
 };
 use futures::{
     executor::block_on,
@@ -75,23 +74,17 @@ pub fn invoke_remove_entry(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApi
             .and_then(|validation_data| {
                 validate_entry(deletion_entry.clone(), validation_data, &runtime.context)
             })
-            // 3. Commit the valid entry to chain and DHT
+            // 3. Commit the valid entry to chain and remove the entry in DHT metadata
             .and_then(|_| {
-                commit_entry(
+                commit_and_update_dht(
                     deletion_entry.clone(),
                     Some(deleted_entry_address.clone()),
+                    DhtUpdate::Remove(deleted_entry_address.clone()),
+                    Some(runtime.zome_call.id()),
                     &runtime.context,
                 )
             })
-            // 4. Remove the entry in DHT metadata
-            .and_then(|_| {
-                remove_entry(
-                    &runtime.context,
-                    runtime.context.action_channel(),
-                    deleted_entry_address.clone(),
-                    deletion_entry.address().clone(),
-                )
-            }),
+            .map_ok(|_| ()),
     );
 
     runtime.store_result(result)
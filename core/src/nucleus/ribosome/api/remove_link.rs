@@ -0,0 +1,130 @@
+use crate::{
+    agent::bundle::DhtUpdate,
+    nucleus::{
+        actions::{build_validation_package::build_validation_package, validate::validate_entry},
+        ribosome::{api::ZomeApiResult, Runtime},
+    },
+    workflows::author_entry::commit_and_update_dht,
+};
+use futures::{
+    executor::block_on,
+    future::{self, TryFutureExt},
+};
+use holochain_core_types::{
+    entry::Entry,
+    error::HolochainError,
+    link::link_remove::LinkRemove,
+    validation::{EntryAction, EntryLifecycle, ValidationData},
+};
+use holochain_wasm_utils::api_serialization::link_entries::LinkEntriesArgs;
+use std::convert::TryFrom;
+use wasmi::{RuntimeArgs, RuntimeValue};
+
+/// ZomeApiFunction::RemoveLink function code
+/// args: [0] encoded MemoryAllocation as u64
+/// Expected complex argument: LinkEntriesArgs
+/// Commits a LinkRemove entry, which the link's validation callback validates as a deletion,
+/// and publishes it so that DHT nodes stop returning the link as live.
+pub fn invoke_remove_link(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
+    // deserialize args
+    let args_str = runtime.load_json_string_from_args(&args);
+    let input = match LinkEntriesArgs::try_from(args_str.clone()) {
+        Ok(entry_input) => entry_input,
+        // Exit on error
+        Err(_) => {
+            runtime.context.log(format!(
+                "err/zome: invoke_remove_link failed to deserialize LinkEntriesArgs: {:?}",
+                args_str
+            ));
+            return ribosome_error_code!(ArgumentDeserializationFailed);
+        }
+    };
+
+    let link = input.to_link();
+    let entry = Entry::LinkRemove(LinkRemove::from_link(&link));
+
+    // Resolve future
+    let result: Result<(), HolochainError> = block_on(
+        // 1. Build the context needed for validation of the entry
+        build_validation_package(&entry, &runtime.context)
+            .and_then(|validation_package| {
+                future::ready(Ok(ValidationData {
+                    package: validation_package,
+                    sources: vec![runtime.context.agent_address()],
+                    lifecycle: EntryLifecycle::Chain,
+                    action: EntryAction::Delete,
+                }))
+            })
+            // 2. Validate the entry
+            .and_then(|validation_data| {
+                validate_entry(entry.clone(), validation_data, &runtime.context)
+            })
+            // 3. Commit the valid entry to chain and publish it to the DHT
+            .and_then(|_| {
+                commit_and_update_dht(
+                    entry.clone(),
+                    None,
+                    DhtUpdate::Publish,
+                    Some(runtime.zome_call.id()),
+                    &runtime.context,
+                )
+            })
+            .map_ok(|_| ()),
+    );
+
+    runtime.store_result(result)
+}
+
+#[cfg(test)]
+pub mod tests {
+    extern crate test_utils;
+
+    use crate::{
+        agent::actions::commit::commit_entry,
+        instance::tests::{test_context_and_logger, test_instance},
+        nucleus::{
+            ribosome::{
+                api::{link_entries::tests::test_link_args_bytes, tests::*, ZomeApiFunction},
+                Defn,
+            },
+            tests::*,
+        },
+    };
+    use futures::executor::block_on;
+    use holochain_core_types::{
+        entry::test_entry,
+        error::{CoreError, ZomeApiInternalResult},
+        json::JsonString,
+    };
+    use std::convert::TryFrom;
+
+    #[test]
+    fn remove_link_checks_link_definition() {
+        let wasm = test_zome_api_function_wasm(ZomeApiFunction::RemoveLink.as_str());
+        let dna = test_utils::create_test_dna_with_wasm(
+            &test_zome_name(),
+            &test_capability_name(),
+            wasm.clone(),
+        );
+        let netname = Some("remove_link_checks_link_definition");
+        let instance = test_instance(dna, netname).expect("Could not create test instance");
+        let (context, _) = test_context_and_logger("joan", netname);
+        let context = instance.initialize_context(context);
+
+        block_on(commit_entry(test_entry(), None, &context))
+            .expect("Could not commit entry for testing");
+
+        let call_result = test_zome_api_function_call(
+            &context.get_dna().unwrap().name.to_string(),
+            context.clone(),
+            &instance,
+            &wasm,
+            test_link_args_bytes(String::from("wrong-tag")),
+        );
+
+        let result = ZomeApiInternalResult::try_from(call_result)
+            .expect("valid ZomeApiInternalResult JsonString");
+        let core_err = CoreError::try_from(result).expect("valid CoreError JsonString");
+        assert_eq!("Unknown entry type", core_err.kind.to_string(),);
+    }
+}
@@ -0,0 +1,163 @@
+use crate::{
+    agent::bundle::DhtUpdate,
+    nucleus::{
+        actions::{build_validation_package::*, validate::*},
+        ribosome::{api::ZomeApiResult, Runtime},
+    },
+    workflows::author_entry::commit_and_update_dht,
+};
+use futures::{
+    executor::block_on,
+    future::{self, TryFutureExt},
+};
+use holochain_core_types::{
+    cas::content::Address,
+    entry::{deletion_entry::DeletionEntry, Entry},
+    error::HolochainError,
+    validation::{EntryAction, EntryLifecycle, ValidationData},
+};
+use std::convert::TryFrom;
+use wasmi::{RuntimeArgs, RuntimeValue};
+
+/// ZomeApiFunction::RevokeCapability function code
+/// args: [0] encoded MemoryAllocation as u64
+/// Expected Address argument: the token of the grant to revoke
+/// Commits a DeletionEntry for the grant. Grants are private, so unlike remove_entry
+/// this does not touch the DHT.
+/// Stores/returns a RibosomeEncodedValue
+pub fn invoke_revoke_capability(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
+    // deserialize args
+    let args_str = runtime.load_json_string_from_args(&args);
+    let token = match Address::try_from(args_str.clone()) {
+        Ok(token) => token,
+        Err(..) => {
+            runtime.context.log(format!(
+                "err/zome: invoke_revoke_capability failed to deserialize Address: {:?}",
+                args_str
+            ));
+            return ribosome_error_code!(ArgumentDeserializationFailed);
+        }
+    };
+
+    let maybe_grant = runtime
+        .context
+        .state()
+        .and_then(|state| state.agent().get_grant(&token));
+    if maybe_grant.is_none() {
+        return runtime.store_result::<()>(Err(HolochainError::ErrorGeneric(format!(
+            "No active grant with token {}",
+            token
+        ))));
+    }
+
+    let deletion_entry = Entry::Deletion(DeletionEntry::new(token.clone()));
+
+    // Resolve future
+    let result: Result<(), HolochainError> = block_on(
+        // 1. Build the context needed for validation of the entry
+        build_validation_package(&deletion_entry, &runtime.context)
+            .and_then(|validation_package| {
+                future::ready(Ok(ValidationData {
+                    package: validation_package,
+                    sources: vec![runtime.context.agent_address()],
+                    lifecycle: EntryLifecycle::Chain,
+                    action: EntryAction::Delete,
+                }))
+            })
+            // 2. Validate the entry
+            .and_then(|validation_data| {
+                validate_entry(deletion_entry.clone(), validation_data, &runtime.context)
+            })
+            // 3. Commit the valid entry to the chain
+            .and_then(|_| {
+                commit_and_update_dht(
+                    deletion_entry.clone(),
+                    Some(token.clone()),
+                    DhtUpdate::None,
+                    Some(runtime.zome_call.id()),
+                    &runtime.context,
+                )
+            })
+            .map_ok(|_| ()),
+    );
+
+    runtime.store_result(result)
+}
+
+#[cfg(test)]
+pub mod tests {
+    extern crate test_utils;
+    use crate::{
+        instance::tests::test_instance_and_context,
+        nucleus::{
+            ribosome::{
+                api::{
+                    tests::{
+                        test_zome_api_function_call, test_zome_api_function_wasm, test_zome_name,
+                    },
+                    ZomeApiFunction,
+                },
+                Defn,
+            },
+            tests::test_capability_name,
+        },
+        workflows::author_entry::author_entry,
+    };
+    use futures::executor::block_on;
+    use holochain_core_types::{
+        dna::capabilities::CapabilityType,
+        entry::{cap_entries::CapTokenGrant, Entry},
+        error::ZomeApiInternalResult,
+        json::JsonString,
+    };
+    use std::convert::TryFrom;
+
+    #[test]
+    /// test that a revoked grant can't be found anymore and can't be revoked twice
+    fn test_revoke_capability() {
+        let wasm = test_zome_api_function_wasm(ZomeApiFunction::RevokeCapability.as_str());
+        let dna = test_utils::create_test_dna_with_wasm(
+            &test_zome_name(),
+            &test_capability_name(),
+            wasm.clone(),
+        );
+        let dna_name = dna.name.clone();
+        let (instance, context) =
+            test_instance_and_context(dna, None).expect("Could not create test instance");
+
+        let grant =
+            CapTokenGrant::create(&test_capability_name(), CapabilityType::Public, None).unwrap();
+        let token = block_on(author_entry(
+            &Entry::CapTokenGrant(grant),
+            None,
+            None,
+            &context,
+        ))
+        .unwrap();
+
+        let call_result = test_zome_api_function_call(
+            &dna_name,
+            context.clone(),
+            &instance,
+            &wasm,
+            JsonString::from(token.clone()).into_bytes(),
+        );
+        assert_eq!(
+            call_result,
+            JsonString::from(
+                String::from(JsonString::from(ZomeApiInternalResult::success(()))) + "\u{0}"
+            ),
+        );
+        assert_eq!(context.state().unwrap().agent().get_grant(&token), None);
+
+        let call_result = test_zome_api_function_call(
+            &dna_name,
+            context.clone(),
+            &instance,
+            &wasm,
+            JsonString::from(token).into_bytes(),
+        );
+        let result = ZomeApiInternalResult::try_from(call_result).unwrap();
+        assert!(!result.ok);
+    }
+}
@@ -0,0 +1,73 @@
+use crate::{
+    agent::keys::zome_signed_data,
+    nucleus::ribosome::{api::ZomeApiResult, Runtime},
+};
+use holochain_core_types::json::RawString;
+use holochain_wasm_utils::api_serialization::sign::SignArgs;
+use std::convert::TryFrom;
+use wasmi::{RuntimeArgs, RuntimeValue};
+
+/// ZomeApiFunction::Sign function code
+/// args: [0] encoded MemoryAllocation as u64
+/// Expected complex argument: SignArgs
+/// Returns an HcApiReturnCode as I64
+/// The payload gets signed with the zome signature prefix, see `keys::zome_signed_data`.
+pub fn invoke_sign(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
+    // deserialize args
+    let args_str = runtime.load_json_string_from_args(&args);
+    let sign_args = match SignArgs::try_from(args_str) {
+        Ok(input) => input,
+        Err(..) => return ribosome_error_code!(ArgumentDeserializationFailed),
+    };
+
+    let result = runtime
+        .context
+        .sign(&zome_signed_data(&sign_args.payload))
+        .map(|signature| RawString::from(String::from(signature)));
+
+    runtime.store_result(result)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::{
+        agent::keys::{test_keypair, zome_signed_data},
+        nucleus::ribosome::{
+            api::{tests::test_zome_api_function, ZomeApiFunction},
+            Defn,
+        },
+    };
+    use holochain_core_types::{
+        error::ZomeApiInternalResult,
+        json::{JsonString, RawString},
+    };
+    use holochain_wasm_utils::api_serialization::sign::SignArgs;
+
+    /// dummy sign args
+    pub fn test_sign_args_bytes() -> Vec<u8> {
+        let args = SignArgs {
+            payload: String::from("test payload"),
+        };
+        JsonString::from(args).into_bytes()
+    }
+
+    #[test]
+    /// test that the payload gets signed with the key of the test agent
+    fn test_sign_round_trip() {
+        let (call_result, _) =
+            test_zome_api_function(ZomeApiFunction::Sign.as_str(), test_sign_args_bytes());
+
+        // Ed25519 signatures are deterministic
+        let expected_signature = test_keypair("jane")
+            .sign(&zome_signed_data("test payload"))
+            .unwrap();
+        assert_eq!(
+            call_result,
+            JsonString::from(
+                String::from(JsonString::from(ZomeApiInternalResult::success(
+                    RawString::from(String::from(expected_signature))
+                ))) + "\u{0}"
+            ),
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use crate::{
+    agent::actions::bundle::start_bundle,
+    nucleus::ribosome::{api::ZomeApiResult, Runtime},
+};
+use futures::executor::block_on;
+use holochain_core_types::json::JsonString;
+use holochain_wasm_utils::api_serialization::bundle::StartBundleArgs;
+use std::convert::TryFrom;
+use wasmi::{RuntimeArgs, RuntimeValue};
+
+/// ZomeApiFunction::StartBundle function code
+/// args: [0] encoded MemoryAllocation as u64
+/// Expected complex argument: StartBundleArgs
+/// Stores/returns a RibosomeEncodedValue
+pub fn invoke_start_bundle(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
+    // deserialize args
+    let args_str = runtime.load_json_string_from_args(&args);
+    let bundle_args = match StartBundleArgs::try_from(args_str.clone()) {
+        Ok(input) => input,
+        Err(..) => {
+            runtime.context.log(format!(
+                "err/zome: invoke_start_bundle failed to deserialize StartBundleArgs: {:?}",
+                args_str
+            ));
+            return ribosome_error_code!(ArgumentDeserializationFailed);
+        }
+    };
+
+    let result = block_on(start_bundle(
+        runtime.zome_call.id(),
+        bundle_args.timeout,
+        JsonString::from(bundle_args.user_param),
+        &runtime.context,
+    ));
+
+    runtime.store_result(result)
+}
+
+#[cfg(test)]
+pub mod tests {
+    extern crate test_utils;
+    use crate::{
+        instance::tests::test_instance_and_context,
+        nucleus::{
+            ribosome::{
+                self,
+                api::{
+                    tests::{test_function_name, test_zome_api_function_wasm, test_zome_name},
+                    ZomeApiFunction,
+                },
+                Defn,
+            },
+            tests::{test_capability_call, test_capability_name},
+            ZomeFnCall,
+        },
+    };
+    use holochain_core_types::{error::ZomeApiInternalResult, json::JsonString};
+    use holochain_wasm_utils::api_serialization::bundle::StartBundleArgs;
+
+    pub fn test_start_bundle_args_bytes() -> Vec<u8> {
+        let args = StartBundleArgs {
+            timeout: 60000,
+            user_param: json!({"reason": "test"}),
+        };
+        JsonString::from(args).into_bytes()
+    }
+
+    #[test]
+    /// test that a bundle gets opened for the calling zome function only
+    fn test_start_bundle_round_trip() {
+        let wasm = test_zome_api_function_wasm(ZomeApiFunction::StartBundle.as_str());
+        let dna = test_utils::create_test_dna_with_wasm(
+            &test_zome_name(),
+            &test_capability_name(),
+            wasm.clone(),
+        );
+        let dna_name = dna.name.clone();
+        let (_instance, context) =
+            test_instance_and_context(dna, None).expect("Could not create test instance");
+        let zome_call = ZomeFnCall::new(
+            &test_zome_name(),
+            Some(test_capability_call()),
+            &test_function_name(),
+            "",
+        );
+
+        let call_result = ribosome::run_dna(
+            &dna_name,
+            context.clone(),
+            wasm,
+            &zome_call,
+            Some(test_start_bundle_args_bytes()),
+        )
+        .expect("test should be callable");
+
+        assert_eq!(
+            call_result,
+            JsonString::from(
+                String::from(JsonString::from(ZomeApiInternalResult::success(()))) + "\u{0}"
+            ),
+        );
+        let agent = context.state().unwrap().agent();
+        let bundle = agent.open_bundle(&zome_call.id()).unwrap();
+        assert_eq!(
+            bundle.user_param(),
+            JsonString::from(json!({"reason": "test"}))
+        );
+        let other_call = ZomeFnCall::new(
+            &test_zome_name(),
+            Some(test_capability_call()),
+            &test_function_name(),
+            "",
+        );
+        assert_eq!(agent.open_bundle(&other_call.id()), None);
+    }
+}
@@ -0,0 +1,51 @@
+use crate::{
+    nucleus::ribosome::{api::ZomeApiResult, Runtime},
+    workflows::update_agent::update_agent_workflow,
+};
+use futures::executor::block_on;
+use wasmi::{RuntimeArgs, RuntimeValue};
+
+/// ZomeApiFunction::UpdateAgent function code
+/// args: [0] encoded MemoryAllocation as u64
+/// Expected argument: none
+/// Returns the address of the new agent entry, i.e. the agent's new public key
+pub fn invoke_update_agent(runtime: &mut Runtime, _args: &RuntimeArgs) -> ZomeApiResult {
+    let result = block_on(update_agent_workflow(&runtime.context));
+
+    runtime.store_result(result)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::{
+        agent::keys::test_keypair,
+        nucleus::ribosome::{
+            api::{tests::test_zome_api_function, ZomeApiFunction},
+            Defn,
+        },
+    };
+    use holochain_core_types::{error::ZomeApiInternalResult, json::JsonString};
+
+    #[test]
+    /// test that the agent address changes to freshly generated keys
+    fn test_update_agent_round_trip() {
+        let (call_result, context) =
+            test_zome_api_function(ZomeApiFunction::UpdateAgent.as_str(), Vec::new());
+        let new_address = context
+            .state()
+            .unwrap()
+            .agent()
+            .get_agent_address()
+            .unwrap();
+        assert_ne!(new_address, test_keypair("jane").address());
+
+        assert_eq!(
+            call_result,
+            JsonString::from(
+                String::from(JsonString::from(ZomeApiInternalResult::success(
+                    new_address.clone()
+                ))) + "\u{0}"
+            ),
+        );
+    }
+}
@@ -1,10 +1,10 @@
 use crate::{
-    agent::actions::{commit::commit_entry, update_entry::update_entry},
+    agent::bundle::DhtUpdate,
     nucleus::{
         actions::{build_validation_package::*, validate::*},
         ribosome::{api::ZomeApiResult, Runtime},
     },
-    workflows::get_entry_result::get_entry_result_workflow,
+    workflows::{author_entry::commit_and_update_dht, get_entry_result::get_entry_result_workflow},
 };
 use futures::{
     executor::block_on,
@@ -83,15 +83,14 @@ pub fn invoke_update_entry(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApi
             .and_then(|validation_data| {
                 validate_entry(entry.clone(), validation_data, &runtime.context)
             })
-            // 3. Commit the valid entry to chain and DHT
-            .and_then(|_| commit_entry(entry.clone(), Some(chain_header_address), &runtime.context))
-            // 4. Update the entry in DHT metadata
-            .and_then(|new_address| {
-                update_entry(
+            // 3. Commit the valid entry to chain and update the entry in DHT metadata
+            .and_then(|_| {
+                commit_and_update_dht(
+                    entry.clone(),
+                    Some(chain_header_address),
+                    DhtUpdate::Update(latest_entry.address()),
+                    Some(runtime.zome_call.id()),
                     &runtime.context,
-                    runtime.context.action_channel(),
-                    latest_entry.address().clone(),
-                    new_address,
                 )
             }),
     );
@@ -0,0 +1,94 @@
+use crate::{
+    agent::keys::{verify, zome_signed_data},
+    nucleus::ribosome::{api::ZomeApiResult, Runtime},
+};
+use holochain_wasm_utils::api_serialization::sign::VerifySignatureArgs;
+use std::convert::TryFrom;
+use wasmi::{RuntimeArgs, RuntimeValue};
+
+/// ZomeApiFunction::VerifySignature function code
+/// args: [0] encoded MemoryAllocation as u64
+/// Expected complex argument: VerifySignatureArgs
+/// Returns an HcApiReturnCode as I64
+/// Only signatures created by zome code with `hc_sign` pass, see `keys::zome_signed_data`.
+pub fn invoke_verify_signature(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
+    // deserialize args
+    let args_str = runtime.load_json_string_from_args(&args);
+    let verify_args = match VerifySignatureArgs::try_from(args_str) {
+        Ok(input) => input,
+        Err(..) => return ribosome_error_code!(ArgumentDeserializationFailed),
+    };
+
+    let result = verify(
+        &verify_args.pub_key,
+        &zome_signed_data(&verify_args.payload),
+        &verify_args.signature,
+    );
+
+    runtime.store_result(result)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::{
+        agent::keys::{test_keypair, zome_signed_data},
+        nucleus::ribosome::{
+            api::{tests::test_zome_api_function, ZomeApiFunction},
+            Defn,
+        },
+    };
+    use holochain_core_types::{error::ZomeApiInternalResult, json::JsonString};
+    use holochain_wasm_utils::api_serialization::sign::VerifySignatureArgs;
+
+    /// verify args checking a signature of "test payload" by `signer` against the key of alice
+    pub fn test_verify_signature_args_bytes(signer: &str, payload: &str) -> Vec<u8> {
+        let args = VerifySignatureArgs {
+            pub_key: test_keypair("alice").address(),
+            payload: String::from(payload),
+            signature: test_keypair(signer)
+                .sign(&zome_signed_data("test payload"))
+                .unwrap(),
+        };
+        JsonString::from(args).into_bytes()
+    }
+
+    fn verify_signature_result(valid: bool) -> JsonString {
+        JsonString::from(
+            String::from(JsonString::from(ZomeApiInternalResult::success(valid))) + "\u{0}",
+        )
+    }
+
+    #[test]
+    /// test that valid signatures are accepted and all others are not
+    fn test_verify_signature_round_trip() {
+        let (call_result, _) = test_zome_api_function(
+            ZomeApiFunction::VerifySignature.as_str(),
+            test_verify_signature_args_bytes("alice", "test payload"),
+        );
+        assert_eq!(call_result, verify_signature_result(true));
+
+        let (call_result, _) = test_zome_api_function(
+            ZomeApiFunction::VerifySignature.as_str(),
+            test_verify_signature_args_bytes("bob", "test payload"),
+        );
+        assert_eq!(call_result, verify_signature_result(false));
+
+        let (call_result, _) = test_zome_api_function(
+            ZomeApiFunction::VerifySignature.as_str(),
+            test_verify_signature_args_bytes("alice", "other payload"),
+        );
+        assert_eq!(call_result, verify_signature_result(false));
+
+        // signatures of anything but zome payloads don't pass
+        let args = VerifySignatureArgs {
+            pub_key: test_keypair("alice").address(),
+            payload: String::from("test payload"),
+            signature: test_keypair("alice").sign("test payload").unwrap(),
+        };
+        let (call_result, _) = test_zome_api_function(
+            ZomeApiFunction::VerifySignature.as_str(),
+            JsonString::from(args).into_bytes(),
+        );
+        assert_eq!(call_result, verify_signature_result(false));
+    }
+}
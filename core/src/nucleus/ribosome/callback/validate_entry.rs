@@ -10,7 +10,7 @@ use crate::{
     },
 };
 use holochain_core_types::{
-    dna::wasm::DnaWasm,
+    dna::{capabilities::CapabilityType, wasm::DnaWasm},
     entry::{
         entry_type::{AppEntryType, EntryType},
         Entry,
@@ -48,7 +48,8 @@ pub fn validate_entry(
             context,
         )?),
 
-        EntryType::LinkAdd => Ok(validate_link_entry(
+        // Link removals are validated by the same callback as the link they remove
+        EntryType::LinkAdd | EntryType::LinkRemove => Ok(validate_link_entry(
             entry.clone(),
             validation_data,
             context,
@@ -58,8 +59,8 @@ pub fn validate_entry(
         // TODO: Specify how Deletion can be commited to chain.
         EntryType::Deletion => Ok(CallbackResult::Pass),
 
-        // a grant should always be private, so it should always pass
-        EntryType::CapTokenGrant => Ok(CallbackResult::Pass),
+        // grants are private and checked against the DNA by the system, there is no app callback
+        EntryType::CapTokenGrant => Ok(validate_cap_token_grant(entry.clone(), context)?),
 
         // TODO: actually check agent against app specific membrane validation rule
         // like for instance: validate_agent_id(
@@ -78,15 +79,16 @@ fn validate_link_entry(
     validation_data: ValidationData,
     context: Arc<Context>,
 ) -> Result<CallbackResult, HolochainError> {
-    let link_add = match entry {
-        Entry::LinkAdd(link_add) => link_add,
+    let link = match entry {
+        Entry::LinkAdd(link_add) => link_add.link().clone(),
+        Entry::LinkRemove(link_remove) => link_remove.link().clone(),
         _ => {
             return Err(HolochainError::ValidationFailed(
-                "Could not extract link_add from entry".into(),
+                "Could not extract link from entry".into(),
             ));
         }
     };
-    let link = link_add.link().clone();
+    link.validate_payload()?;
     let (base, target) = links_utils::get_link_entries(&link, &context)?;
     let link_definition_path = links_utils::find_link_definition_in_dna(
         &base.entry_type(),
@@ -120,6 +122,44 @@ fn validate_link_entry(
     ))
 }
 
+/// A grant has to refer to a capability declared in the DNA
+/// and has to be of the same type as that capability.
+fn validate_cap_token_grant(
+    entry: Entry,
+    context: Arc<Context>,
+) -> Result<CallbackResult, HolochainError> {
+    let grant = match entry {
+        Entry::CapTokenGrant(grant) => grant,
+        _ => {
+            return Err(HolochainError::ValidationFailed(
+                "Could not extract grant from entry".into(),
+            ));
+        }
+    };
+    let dna = context.get_dna().expect("Callback called without DNA set!");
+    let declared_types: Vec<CapabilityType> = dna
+        .zomes
+        .values()
+        .filter_map(|zome| zome.capabilities.get(&grant.cap_name()))
+        .map(|capability| capability.cap_type.clone())
+        .collect();
+
+    if declared_types.is_empty() {
+        return Ok(CallbackResult::Fail(format!(
+            "Capability '{}' is not declared in the DNA",
+            grant.cap_name()
+        )));
+    }
+    if !declared_types.contains(&grant.cap_type()) {
+        return Ok(CallbackResult::Fail(format!(
+            "Grant of type {:?} does not match the type of capability '{}'",
+            grant.cap_type(),
+            grant.cap_name()
+        )));
+    }
+    Ok(CallbackResult::Pass)
+}
+
 fn validate_app_entry(
     entry: Entry,
     app_entry_type: AppEntryType,
@@ -50,20 +50,21 @@ pub fn get_validation_package_definition(
                 Some(app_entry_type.to_string().into_bytes()),
             )?
         }
-        EntryType::LinkAdd => {
-            let link_add = match entry {
-                Entry::LinkAdd(link_add) => link_add,
+        EntryType::LinkAdd | EntryType::LinkRemove => {
+            let link = match entry {
+                Entry::LinkAdd(link_add) => link_add.link().clone(),
+                Entry::LinkRemove(link_remove) => link_remove.link().clone(),
                 _ => {
                     return Err(HolochainError::ValidationFailed(
-                        "Failed to extract LinkAdd".into(),
+                        "Failed to extract link".into(),
                     ));
                 }
             };
-            let (base, target) = links_utils::get_link_entries(link_add.link(), &context)?;
+            let (base, target) = links_utils::get_link_entries(&link, &context)?;
 
             let link_definition_path = links_utils::find_link_definition_in_dna(
                 &base.entry_type(),
-                link_add.link().tag(),
+                link.tag(),
                 &target.entry_type(),
                 &context,
             )?;
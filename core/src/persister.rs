@@ -1,6 +1,24 @@
+//! Persistence of the parts of the state that don't live in the storages already.
+//!
+//! Entries, headers and EAVs get written to the chain, DHT and EAV storages while actions
+//! get reduced. What is left is the head of the source chain and the status of the nucleus.
+//! Every reduced action that changes these gets appended to a journal, and every now and then
+//! a snapshot of the whole state gets taken. Loading restores the last snapshot and replays
+//! the journal entries that followed it. Journal entries that made it into a snapshot get
+//! dropped.
+//!
+//! Snapshot and journal head change under fixed addresses, so they can't live in the content
+//! addressable chain storage. The persister keeps them in a storage of its own that
+//! overwrites what gets added under an address that is already taken.
+
 use crate::{
-    agent::state::{AgentStateSnapshot, AGENT_SNAPSHOT_ADDRESS},
+    action::{Action, ActionWrapper},
+    agent::{
+        chain_store::ChainStore,
+        state::{AgentState, AgentStateSnapshot, AGENT_SNAPSHOT_ADDRESS},
+    },
     context::Context,
+    nucleus::state::{NucleusState, NucleusStatus},
     state::State,
 };
 use holochain_core_types::{
@@ -8,26 +26,177 @@ use holochain_core_types::{
         content::{Address, AddressableContent, Content},
         storage::ContentAddressableStorage,
     },
+    chain_header::ChainHeader,
+    dna::Dna,
     error::HolochainError,
+    json::JsonString,
 };
 use std::{
     convert::TryFrom,
     sync::{Arc, RwLock},
 };
+use uuid::Uuid;
+
+/// Number of journal entries after which a new snapshot gets taken
+pub const SNAPSHOT_INTERVAL: usize = 50;
+
+pub static STATE_SNAPSHOT_ADDRESS: &'static str = "StateSnapshot";
+pub static JOURNAL_HEAD_ADDRESS: &'static str = "JournalHead";
 
 /// trait that defines the persistence functionality that holochain_core requires
 pub trait Persister: Send {
-    // @TODO how does save/load work with snowflake IDs?
-    // snowflake is only unique across a single process, not a reboot save/load round trip
-    // we'd need real UUIDs for persistant uniqueness
-    // @see https://github.com/holochain/holochain-rust/issues/203
+    /// Takes a snapshot of the given state
     fn save(&mut self, state: State) -> Result<(), HolochainError>;
+    /// Appends the effect of a reduced action to the journal.
+    /// The given state already contains that effect.
+    fn journal(
+        &mut self,
+        id: Uuid,
+        action: JournaledAction,
+        state: &State,
+    ) -> Result<(), HolochainError>;
+    /// Restores the last snapshot and replays the journal entries that followed it
     fn load(&self, context: Arc<Context>) -> Result<Option<State>, HolochainError>;
 }
 
+/// The effect of a reduced action on state that does not live in the storages
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum JournaledAction {
+    /// The source chain got the given header as its new head
+    ChainHead(ChainHeader),
+    InitApplication(Dna),
+    ReturnInitializationResult(Option<String>),
+}
+
+impl JournaledAction {
+    /// Returns what has to be journaled about the given action,
+    /// which turned the old state into the new one.
+    pub fn from_reduced(
+        action_wrapper: &ActionWrapper,
+        old_state: &State,
+        new_state: &State,
+    ) -> Option<JournaledAction> {
+        let new_head = new_state.agent().top_chain_header();
+        if new_head != old_state.agent().top_chain_header() {
+            return new_head.map(JournaledAction::ChainHead);
+        }
+        match action_wrapper.action() {
+            Action::InitApplication(dna) => Some(JournaledAction::InitApplication(dna.clone())),
+            Action::ReturnInitializationResult(result) => {
+                Some(JournaledAction::ReturnInitializationResult(result.clone()))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// An entry of the journal. Entries are chained backwards, starting from the journal head.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, DefaultJson)]
+pub struct JournalEntry {
+    /// ID of the action wrapper that got reduced
+    id: Uuid,
+    action: JournaledAction,
+    previous: Option<Address>,
+}
+
+impl JournalEntry {
+    pub fn id(&self) -> &Uuid {
+        &self.id
+    }
+
+    pub fn action(&self) -> &JournaledAction {
+        &self.action
+    }
+
+    pub fn previous(&self) -> Option<&Address> {
+        self.previous.as_ref()
+    }
+}
+
+impl AddressableContent for JournalEntry {
+    fn content(&self) -> Content {
+        self.to_owned().into()
+    }
+
+    fn try_from_content(content: &Content) -> Result<Self, HolochainError> {
+        Self::try_from(content.to_owned())
+    }
+
+    fn address(&self) -> Address {
+        Address::from(format!("JournalEntry:{}", self.id))
+    }
+}
+
+/// Points to the latest journal entry
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, DefaultJson)]
+struct JournalHead {
+    entry: Address,
+}
+
+impl AddressableContent for JournalHead {
+    fn content(&self) -> Content {
+        self.to_owned().into()
+    }
+
+    fn try_from_content(content: &Content) -> Result<Self, HolochainError> {
+        Self::try_from(content.to_owned())
+    }
+
+    fn address(&self) -> Address {
+        JOURNAL_HEAD_ADDRESS.into()
+    }
+}
+
+/// Everything of the state that does not live in the storages
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, DefaultJson)]
+pub struct StateSnapshot {
+    top_chain_header: Option<ChainHeader>,
+    dna: Option<Dna>,
+    nucleus_status: NucleusStatus,
+    /// The last journal entry that is contained in this snapshot
+    journal_position: Option<Address>,
+}
+
+impl StateSnapshot {
+    pub fn new(state: &State, journal_position: Option<Address>) -> StateSnapshot {
+        let nucleus = state.nucleus();
+        StateSnapshot {
+            top_chain_header: state.agent().top_chain_header(),
+            dna: nucleus.dna(),
+            nucleus_status: nucleus.status(),
+            journal_position,
+        }
+    }
+
+    pub fn top_chain_header(&self) -> Option<&ChainHeader> {
+        self.top_chain_header.as_ref()
+    }
+
+    pub fn journal_position(&self) -> Option<&Address> {
+        self.journal_position.as_ref()
+    }
+}
+
+impl AddressableContent for StateSnapshot {
+    fn content(&self) -> Content {
+        self.to_owned().into()
+    }
+
+    fn try_from_content(content: &Content) -> Result<Self, HolochainError> {
+        Self::try_from(content.to_owned())
+    }
+
+    fn address(&self) -> Address {
+        STATE_SNAPSHOT_ADDRESS.into()
+    }
+}
+
 #[derive(Clone)]
 pub struct SimplePersister {
     storage: Arc<RwLock<ContentAddressableStorage>>,
+    /// latest journal entry
+    journal_head: Option<Address>,
+    entries_since_snapshot: usize,
 }
 
 impl PartialEq for SimplePersister {
@@ -38,28 +207,217 @@ impl PartialEq for SimplePersister {
 
 impl Persister for SimplePersister {
     fn save(&mut self, state: State) -> Result<(), HolochainError> {
-        let lock = &*self.storage.clone();
-        let mut store = lock.write().unwrap();
-        let snapshot = AgentStateSnapshot::try_from(state)?;
-        Ok(store.add(&snapshot)?)
+        let snapshot = StateSnapshot::new(&state, self.journal_head.clone());
+        let mut store = self.storage.write()?;
+        store.add(&snapshot)?;
+        self.entries_since_snapshot = 0;
+
+        // The snapshot contains the whole journal now. Oldest entries go first, so an
+        // interrupted pruning leaves a journal that is still connected to its head.
+        let mut journal = Vec::new();
+        let mut next = self.journal_head.clone();
+        while let Some(address) = next {
+            next = match store.fetch(&address)? {
+                Some(content) => JournalEntry::try_from_content(&content)?.previous,
+                None => break,
+            };
+            journal.push(address);
+        }
+        for address in journal.iter().rev() {
+            store.remove(address)?;
+        }
+        Ok(())
+    }
+
+    fn journal(
+        &mut self,
+        id: Uuid,
+        action: JournaledAction,
+        state: &State,
+    ) -> Result<(), HolochainError> {
+        let entry = JournalEntry {
+            id,
+            action,
+            previous: self.journal_head.clone(),
+        };
+        {
+            // The entry has to be stored before the head points to it, so an interrupted
+            // write leaves the journal as it was before.
+            let mut store = self.storage.write()?;
+            store.add(&entry)?;
+            store.add(&JournalHead {
+                entry: entry.address(),
+            })?;
+        }
+        self.journal_head = Some(entry.address());
+        self.entries_since_snapshot += 1;
+        if self.entries_since_snapshot >= SNAPSHOT_INTERVAL {
+            self.save(state.clone())?;
+        }
+        Ok(())
     }
+
     fn load(&self, context: Arc<Context>) -> Result<Option<State>, HolochainError> {
-        let lock = &*self.storage.clone();
-        let store = lock.write().unwrap();
-        let address = Address::from(AGENT_SNAPSHOT_ADDRESS);
-        let snapshot: Option<AgentStateSnapshot> = store.fetch(&address)?.map(|s: Content| {
-            AgentStateSnapshot::try_from_content(&s)
-                .expect("could not load AgentStateSnapshot from content")
-        });
-        let state = snapshot.map(|snap| State::try_from_agent_snapshot(context, snap).ok());
-        Ok(state.unwrap_or(None))
+        let store = self.storage.read()?;
+        let snapshot = fetch_snapshot(&*store)?;
+        let journal = self.journal_since(&*store, snapshot.as_ref())?;
+
+        let snapshot = match snapshot {
+            Some(snapshot) => snapshot,
+            None => {
+                // Stores from before the journal only have a snapshot of the agent,
+                // in the chain storage
+                let agent_snapshot = fetch_agent_snapshot(&context.chain_storage)?;
+                if agent_snapshot.is_none() && journal.is_empty() {
+                    return Ok(None);
+                }
+                if journal.is_empty() {
+                    return Ok(agent_snapshot.and_then(|snapshot| {
+                        State::try_from_agent_snapshot(context, snapshot).ok()
+                    }));
+                }
+                StateSnapshot {
+                    top_chain_header: agent_snapshot
+                        .map(|snapshot| snapshot.top_chain_header().clone()),
+                    dna: None,
+                    nucleus_status: NucleusStatus::New,
+                    journal_position: None,
+                }
+            }
+        };
+        Ok(Some(replay(context, snapshot, journal)))
     }
 }
 
 impl SimplePersister {
-    pub fn new(storage: Arc<RwLock<ContentAddressableStorage>>) -> Self {
-        SimplePersister { storage: storage }
+    /// Keeps snapshot and journal in the given storage. It must not be the chain storage
+    /// and has to overwrite content added under an address that is already taken.
+    pub fn new(storage: Arc<RwLock<ContentAddressableStorage>>) -> Result<Self, HolochainError> {
+        // A restarted instance continues the journal it left
+        let journal_head = storage
+            .read()?
+            .fetch(&Address::from(JOURNAL_HEAD_ADDRESS))?
+            .map(|content| JournalHead::try_from_content(&content))
+            .transpose()?
+            .map(|head| head.entry);
+        Ok(SimplePersister {
+            storage,
+            journal_head,
+            entries_since_snapshot: 0,
+        })
+    }
+
+    /// Returns the persisted top chain header without building a state.
+    /// This lets tools read the source chain of an instance that is not running,
+    /// from the given chain storage.
+    pub fn top_chain_header(
+        &self,
+        chain_storage: &Arc<RwLock<ContentAddressableStorage>>,
+    ) -> Result<Option<ChainHeader>, HolochainError> {
+        let store = self.storage.read()?;
+        let snapshot = fetch_snapshot(&*store)?;
+        let journaled_header = self
+            .journal_since(&*store, snapshot.as_ref())?
+            .into_iter()
+            .rev()
+            .find_map(|entry| match entry.action {
+                JournaledAction::ChainHead(header) => Some(header),
+                _ => None,
+            });
+        if journaled_header.is_some() {
+            return Ok(journaled_header);
+        }
+        match snapshot {
+            Some(snapshot) => Ok(snapshot.top_chain_header),
+            None => Ok(fetch_agent_snapshot(chain_storage)?
+                .map(|snapshot| snapshot.top_chain_header().clone())),
+        }
+    }
+
+    /// Returns the journal entries that followed the given snapshot, oldest first
+    fn journal_since(
+        &self,
+        store: &ContentAddressableStorage,
+        snapshot: Option<&StateSnapshot>,
+    ) -> Result<Vec<JournalEntry>, HolochainError> {
+        let position = snapshot.and_then(|snapshot| snapshot.journal_position());
+        let mut journal = Vec::new();
+        let mut next = store
+            .fetch(&Address::from(JOURNAL_HEAD_ADDRESS))?
+            .map(|content| JournalHead::try_from_content(&content))
+            .transpose()?
+            .map(|head| head.entry);
+        while let Some(address) = next {
+            if Some(&address) == position {
+                break;
+            }
+            let entry = store
+                .fetch(&address)?
+                .map(|content| JournalEntry::try_from_content(&content))
+                .transpose()?
+                .ok_or_else(|| {
+                    HolochainError::ErrorGeneric(format!("Journal entry {} is missing", address))
+                })?;
+            next = entry.previous.clone();
+            journal.push(entry);
+        }
+        journal.reverse();
+        Ok(journal)
+    }
+}
+
+fn fetch_snapshot(
+    store: &ContentAddressableStorage,
+) -> Result<Option<StateSnapshot>, HolochainError> {
+    store
+        .fetch(&Address::from(STATE_SNAPSHOT_ADDRESS))?
+        .map(|content| StateSnapshot::try_from_content(&content))
+        .transpose()
+}
+
+fn fetch_agent_snapshot(
+    chain_storage: &Arc<RwLock<ContentAddressableStorage>>,
+) -> Result<Option<AgentStateSnapshot>, HolochainError> {
+    chain_storage
+        .read()?
+        .fetch(&Address::from(AGENT_SNAPSHOT_ADDRESS))?
+        .map(|content| AgentStateSnapshot::try_from_content(&content))
+        .transpose()
+}
+
+/// Builds the state of the given snapshot with the given journal entries applied on top
+fn replay(context: Arc<Context>, snapshot: StateSnapshot, journal: Vec<JournalEntry>) -> State {
+    let mut top_chain_header = snapshot.top_chain_header.clone();
+    let mut nucleus = Arc::new(NucleusState {
+        dna: snapshot.dna.clone(),
+        status: snapshot.nucleus_status.clone(),
+        ..NucleusState::new()
+    });
+    for entry in journal {
+        match entry.action {
+            JournaledAction::ChainHead(header) => top_chain_header = Some(header),
+            JournaledAction::InitApplication(dna) => {
+                let action_wrapper = ActionWrapper::with_id(Action::InitApplication(dna), entry.id);
+                nucleus = crate::nucleus::reduce(context.clone(), nucleus, &action_wrapper);
+            }
+            JournaledAction::ReturnInitializationResult(result) => {
+                let action_wrapper =
+                    ActionWrapper::with_id(Action::ReturnInitializationResult(result), entry.id);
+                nucleus = crate::nucleus::reduce(context.clone(), nucleus, &action_wrapper);
+            }
+        }
+    }
+
+    let chain = ChainStore::new(context.chain_storage.clone());
+    let agent = match top_chain_header {
+        Some(header) => AgentState::new_with_top_chain_header(chain, header),
+        None => AgentState::new(chain),
+    };
+    let mut state = State::new_with_agent(context, Arc::new(agent));
+    if nucleus.dna.is_some() {
+        state = state.with_nucleus(nucleus);
     }
+    state
 }
 
 #[cfg(test)]
@@ -67,10 +425,13 @@ mod tests {
 
     extern crate tempfile;
     use self::tempfile::tempdir;
+    use super::*;
     use crate::{
-        instance::tests::test_context_with_agent_state,
-        persister::{Persister, SimplePersister},
+        action::tests::test_action_wrapper_commit,
+        instance::tests::{test_context_with_agent_state, test_state_storage},
+        nucleus::actions::tests::instance,
     };
+    use holochain_core_types::{chain_header::test_chain_header, json::RawString};
     use std::fs::File;
 
     #[test]
@@ -80,7 +441,7 @@ mod tests {
         let _tempfile = temp_path.to_str().unwrap();
         let context = test_context_with_agent_state(None);
         File::create(temp_path.clone()).unwrap();
-        let mut persistance = SimplePersister::new(context.dht_storage.clone());
+        let mut persistance = SimplePersister::new(test_state_storage()).unwrap();
         let state = context.state().unwrap().clone();
         persistance.save(state.clone()).unwrap();
         let state_from_file = persistance.load(context).unwrap().unwrap();
@@ -92,4 +453,154 @@ mod tests {
         // need to fix this so `persitance.load()` takes a networks or something
         assert_ne!(state.network(), state_from_file.network());
     }
+
+    #[test]
+    fn journal_entry_json_round_trip() {
+        let entry = JournalEntry {
+            id: Uuid::new_v4(),
+            action: JournaledAction::ChainHead(test_chain_header()),
+            previous: Some(Address::from("JournalEntry:previous")),
+        };
+        assert_eq!(
+            JournalEntry::try_from_content(&entry.content()).unwrap(),
+            entry
+        );
+    }
+
+    #[test]
+    fn journaled_action_from_reduced() {
+        let (_instance, context) = instance(None);
+        let state = context.state().unwrap().clone();
+        let action_wrapper = test_action_wrapper_commit();
+        let new_state = state.reduce(context.clone(), action_wrapper.clone());
+
+        assert_eq!(
+            JournaledAction::from_reduced(&action_wrapper, &state, &new_state),
+            Some(JournaledAction::ChainHead(
+                new_state.agent().top_chain_header().unwrap()
+            ))
+        );
+        assert_eq!(
+            JournaledAction::from_reduced(&action_wrapper, &new_state, &new_state),
+            None
+        );
+    }
+
+    #[test]
+    fn load_replays_journal_after_snapshot() {
+        let (_instance, context) = instance(None);
+        let storage = test_state_storage();
+        let mut persister = SimplePersister::new(storage.clone()).unwrap();
+        let state = context.state().unwrap().clone();
+        persister.save(state.clone()).unwrap();
+
+        // a commit that only made it into the journal, not into a snapshot
+        let action_wrapper = test_action_wrapper_commit();
+        let new_state = state.reduce(context.clone(), action_wrapper.clone());
+        let journaled = JournaledAction::from_reduced(&action_wrapper, &state, &new_state).unwrap();
+        persister
+            .journal(*action_wrapper.id(), journaled, &new_state)
+            .unwrap();
+
+        // a new persister on the same storage continues where the old one stopped
+        let persister = SimplePersister::new(storage).unwrap();
+        assert_eq!(
+            persister.journal_head,
+            Some(Address::from(format!(
+                "JournalEntry:{}",
+                action_wrapper.id()
+            )))
+        );
+        let loaded = persister.load(context.clone()).unwrap().unwrap();
+        assert_eq!(
+            loaded.agent().top_chain_header(),
+            new_state.agent().top_chain_header()
+        );
+        assert_eq!(
+            persister.top_chain_header(&context.chain_storage).unwrap(),
+            new_state.agent().top_chain_header()
+        );
+        assert_eq!(loaded.nucleus().dna(), state.nucleus().dna());
+        assert_eq!(loaded.nucleus().status(), state.nucleus().status());
+    }
+
+    /// Content under the address of the journal head that is not a journal head
+    struct BrokenJournalHead;
+
+    impl AddressableContent for BrokenJournalHead {
+        fn content(&self) -> Content {
+            RawString::from("not a journal head").into()
+        }
+
+        fn try_from_content(_content: &Content) -> Result<Self, HolochainError> {
+            Ok(BrokenJournalHead)
+        }
+
+        fn address(&self) -> Address {
+            JOURNAL_HEAD_ADDRESS.into()
+        }
+    }
+
+    #[test]
+    fn new_fails_on_broken_journal_head() {
+        let storage = test_state_storage();
+        storage.write().unwrap().add(&BrokenJournalHead).unwrap();
+        assert!(SimplePersister::new(storage).is_err());
+    }
+
+    #[test]
+    fn journal_takes_snapshots() {
+        let (_instance, context) = instance(None);
+        let storage = test_state_storage();
+        let mut persister = SimplePersister::new(storage.clone()).unwrap();
+        let state = context.state().unwrap().clone();
+        let mut journaled = Vec::new();
+        for _ in 0..SNAPSHOT_INTERVAL + 1 {
+            let action_wrapper = test_action_wrapper_commit();
+            persister
+                .journal(
+                    *action_wrapper.id(),
+                    JournaledAction::ChainHead(test_chain_header()),
+                    &state,
+                )
+                .unwrap();
+            journaled.push(Address::from(format!(
+                "JournalEntry:{}",
+                action_wrapper.id()
+            )));
+        }
+        let store = storage.read().unwrap();
+        let snapshot = StateSnapshot::try_from_content(
+            &store
+                .fetch(&Address::from(STATE_SNAPSHOT_ADDRESS))
+                .unwrap()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            snapshot.journal_position(),
+            journaled.get(SNAPSHOT_INTERVAL - 1)
+        );
+        assert_eq!(
+            persister
+                .journal_since(&*store, Some(&snapshot))
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // the entries in the snapshot got dropped, the one after it is kept
+        let (in_snapshot, after_snapshot) = journaled.split_at(SNAPSHOT_INTERVAL);
+        for address in in_snapshot {
+            assert_eq!(store.contains(address), Ok(false));
+        }
+        assert_eq!(store.contains(&after_snapshot[0]), Ok(true));
+
+        // nothing of it ends up in the chain storage
+        let chain_storage = context.chain_storage.read().unwrap();
+        assert_eq!(
+            chain_storage.contains(&Address::from(STATE_SNAPSHOT_ADDRESS)),
+            Ok(false)
+        );
+    }
 }
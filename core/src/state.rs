@@ -96,6 +96,26 @@ impl State {
         }
     }
 
+    /// The state in which the given action failed with `error` instead of changing anything,
+    /// or None if the action can't fail that way. See `AgentState::with_failed_action`.
+    pub fn with_failed_action(
+        &self,
+        action_wrapper: &ActionWrapper,
+        error: HolochainError,
+    ) -> Option<Self> {
+        let agent = self.agent.with_failed_action(action_wrapper, error)?;
+        let mut failed = self.clone();
+        failed.agent = Arc::new(agent);
+        failed.history.insert(action_wrapper.clone());
+        Some(failed)
+    }
+
+    /// Replaces the nucleus state, e.g. with one that got restored by the persister
+    pub fn with_nucleus(mut self, nucleus: Arc<NucleusState>) -> Self {
+        self.nucleus = nucleus;
+        self
+    }
+
     pub fn reduce(&self, context: Arc<Context>, action_wrapper: ActionWrapper) -> Self {
         // context.log(format!("debug/reduce: {:?}", action_wrapper));
         let mut new_state = State {
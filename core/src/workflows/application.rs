@@ -5,9 +5,11 @@ use crate::{
 
 use std::sync::Arc;
 
-use crate::{instance::Instance, nucleus::actions::initialize::initialize_application};
-use futures::TryFutureExt;
-use holochain_core_types::{dna::Dna, error::HcResult};
+use crate::{
+    instance::Instance, nucleus::actions::initialize::initialize_application,
+    workflows::update_agent::catch_up_agent_keys,
+};
+use holochain_core_types::{cas::content::Address, dna::Dna, error::HcResult};
 
 pub async fn initialize(
     instance: &Instance,
@@ -15,11 +17,16 @@ pub async fn initialize(
     context: Arc<Context>,
 ) -> HcResult<Arc<Context>> {
     let instance_context = instance.initialize_context(context.clone());
-    await!(get_dna_and_agent(&instance_context)
-        .map_ok(|_| ())
-        .or_else(
-            |_| initialize_application(dna.unwrap_or(Dna::new()), &instance_context).map_ok(|_| ())
-        ))?;
+    match await!(get_dna_and_agent(&instance_context)) {
+        // The chain exists already and might have gotten its keys rotated
+        Ok((_, agent_key)) => catch_up_agent_keys(&Address::from(agent_key), &instance_context)?,
+        Err(_) => {
+            await!(initialize_application(
+                dna.unwrap_or(Dna::new()),
+                &instance_context
+            ))?;
+        }
+    }
     await!(initialize_network::initialize_network(&instance_context))?;
     Ok(instance_context)
 }
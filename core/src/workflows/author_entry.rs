@@ -1,7 +1,17 @@
 use crate::{
-    agent::actions::commit::commit_entry,
+    agent::{
+        actions::{
+            commit::{commit_entry, stage_commit},
+            update_entry::update_entry,
+        },
+        bundle::{BundleKey, BundledCommit, DhtUpdate},
+    },
     context::Context,
-    network::actions::publish::publish,
+    dht::actions::{hold::hold_encrypted_entry, remove_entry::remove_entry},
+    network::{
+        actions::publish::publish,
+        encrypted_entry::{is_encrypted_entry_type, seal_entry},
+    },
     nucleus::actions::{
         build_validation_package::build_validation_package, validate::validate_entry,
     },
@@ -18,6 +28,25 @@ use std::sync::Arc;
 pub async fn author_entry<'a>(
     entry: &'a Entry,
     maybe_crud_link: Option<Address>,
+    maybe_bundle: Option<BundleKey>,
+    context: &'a Arc<Context>,
+) -> Result<Address, HolochainError> {
+    await!(author_entry_with_dht_update(
+        entry,
+        maybe_crud_link,
+        DhtUpdate::Publish,
+        maybe_bundle,
+        context
+    ))
+}
+
+/// Authors an entry like `author_entry` does, but makes the given change to the DHT after
+/// committing it.
+pub async fn author_entry_with_dht_update<'a>(
+    entry: &'a Entry,
+    maybe_crud_link: Option<Address>,
+    dht_update: DhtUpdate,
+    maybe_bundle: Option<BundleKey>,
     context: &'a Arc<Context>,
 ) -> Result<Address, HolochainError> {
     let address = entry.address();
@@ -42,45 +71,134 @@ pub async fn author_entry<'a>(
     await!(validate_entry(entry.clone(), validation_data, &context))?;
     context.log(format!("Authoring entry {}: is valid!", address));
 
-    // 3. Commit the entry
+    // 3. Commit the entry and publish it
+    await!(commit_and_update_dht(
+        entry.clone(),
+        maybe_crud_link,
+        dht_update,
+        maybe_bundle,
+        context
+    ))
+}
+
+/// Commits a valid entry and then makes the given change to the DHT.
+/// If the zome call the entry is authored in has a bundle open, both only get staged
+/// in it and happen when the bundle gets committed.
+pub async fn commit_and_update_dht<'a>(
+    entry: Entry,
+    maybe_crud_link: Option<Address>,
+    dht_update: DhtUpdate,
+    maybe_bundle: Option<BundleKey>,
+    context: &'a Arc<Context>,
+) -> Result<Address, HolochainError> {
+    let address = entry.address();
+    let open_bundle = maybe_bundle.filter(|key| {
+        context
+            .state()
+            .map(|state| state.agent().open_bundle(key).is_some())
+            .unwrap_or(false)
+    });
+    if let Some(key) = open_bundle {
+        context.log(format!(
+            "debug/workflow/authoring_entry/{}: staging in bundle",
+            address
+        ));
+        return await!(stage_commit(
+            key,
+            BundledCommit {
+                entry,
+                crud_link: maybe_crud_link,
+                dht_update,
+            },
+            context
+        ));
+    }
+
     context.log(format!(
         "debug/workflow/authoring_entry/{}: committing...",
         address
     ));
-    let addr = await!(commit_entry(entry.clone(), maybe_crud_link, &context))?;
+    let addr = await!(commit_entry(entry.clone(), maybe_crud_link, context))?;
     context.log(format!(
         "debug/workflow/authoring_entry/{}: committed",
         address
     ));
 
-    // 4. Publish the valid entry to DHT. This will call Hold to itself
-    //TODO: missing a general public/private sharing check here, for now just
-    // using the entry_type can_publish() function which isn't enough
-    if entry.entry_type().can_publish() {
-        context.log(format!(
-            "debug/workflow/authoring_entry/{}: publishing...",
-            address
-        ));
-        await!(publish(entry.address(), &context))?;
-        context.log(format!(
-            "debug/workflow/authoring_entry/{}: published!",
-            address
-        ));
-    } else {
-        context.log(format!(
-            "debug/workflow/authoring_entry/{}: entry is private, no publishing",
-            address
-        ));
-    }
+    await!(update_dht(&entry, &dht_update, context))?;
     Ok(addr)
 }
 
+/// Makes the change to the DHT that has to follow the commit of `entry`.
+pub async fn update_dht<'a>(
+    entry: &'a Entry,
+    dht_update: &'a DhtUpdate,
+    context: &'a Arc<Context>,
+) -> Result<(), HolochainError> {
+    let address = entry.address();
+    match dht_update {
+        // Publish the valid entry to DHT. This will call Hold to itself
+        //TODO: missing a general public/private sharing check here, for now just
+        // using the entry_type can_publish() function which isn't enough
+        DhtUpdate::Publish | DhtUpdate::PublishEncrypted(_) => {
+            if entry.entry_type().can_publish() {
+                if is_encrypted_entry_type(&entry.entry_type(), context) {
+                    // Publishing sends what we hold sealed, also when it happens again later
+                    let recipients = match dht_update {
+                        DhtUpdate::PublishEncrypted(recipients) => recipients.clone(),
+                        _ => Vec::new(),
+                    };
+                    let encrypted_entry = seal_entry(entry, &recipients, context)?;
+                    await!(hold_encrypted_entry(&encrypted_entry, context))?;
+                }
+                context.log(format!(
+                    "debug/workflow/authoring_entry/{}: publishing...",
+                    address
+                ));
+                await!(publish(address.clone(), context))?;
+                context.log(format!(
+                    "debug/workflow/authoring_entry/{}: published!",
+                    address
+                ));
+            } else {
+                context.log(format!(
+                    "debug/workflow/authoring_entry/{}: entry is private, no publishing",
+                    address
+                ));
+            }
+        }
+        DhtUpdate::Update(old_address) => {
+            await!(update_entry(
+                context,
+                context.action_channel(),
+                old_address.clone(),
+                address
+            ))?;
+        }
+        DhtUpdate::Remove(deleted_address) => {
+            await!(remove_entry(
+                context,
+                context.action_channel(),
+                deleted_address.clone(),
+                address
+            ))?;
+        }
+        DhtUpdate::None => (),
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 pub mod tests {
-    use super::author_entry;
-    use crate::nucleus::actions::tests::*;
+    use super::{author_entry, author_entry_with_dht_update};
+    use crate::{
+        agent::{bundle::DhtUpdate, keys::test_keypair},
+        nucleus::actions::{get_entry::get_encrypted_entry_from_dht, tests::*},
+        workflows::hold_encrypted_entry::tests::encrypted_dna,
+    };
     use futures::executor::block_on;
-    use holochain_core_types::{entry::test_entry, json::JsonString};
+    use holochain_core_types::{
+        cas::content::AddressableContent, entry::test_entry, json::JsonString,
+    };
     use std::{thread, time};
 
     #[test]
@@ -93,7 +211,7 @@ pub mod tests {
         let (_instance1, context1) = instance_by_name("jill", dna.clone(), netname);
         let (_instance2, context2) = instance_by_name("jack", dna, netname);
 
-        let entry_address = block_on(author_entry(&test_entry(), None, &context1)).unwrap();
+        let entry_address = block_on(author_entry(&test_entry(), None, None, &context1)).unwrap();
         thread::sleep(time::Duration::from_millis(500));
 
         let mut json: Option<JsonString> = None;
@@ -122,4 +240,30 @@ pub mod tests {
             "{\"App\":[\"testEntryType\",\"\\\"test entry value\\\"\"]}".to_string(),
         );
     }
+
+    #[test]
+    fn encrypted_entries_get_sealed_for_their_recipients() {
+        let (_instance, context) = instance_by_name("jill", encrypted_dna("jill"), None);
+        let entry = test_entry();
+        let recipient = test_keypair("jack").address();
+
+        block_on(author_entry_with_dht_update(
+            &entry,
+            None,
+            DhtUpdate::PublishEncrypted(vec![recipient.clone()]),
+            None,
+            &context,
+        ))
+        .unwrap();
+
+        // the author holds the sealed entry to publish it
+        let encrypted_entry = get_encrypted_entry_from_dht(&context, &entry.address())
+            .unwrap()
+            .unwrap();
+        assert_eq!(encrypted_entry.author(), &context.agent_address());
+        let recipients = encrypted_entry.recipients();
+        assert_eq!(recipients.len(), 2);
+        assert!(recipients.contains(&recipient));
+        assert!(encrypted_entry.verify_signature().is_ok());
+    }
 }
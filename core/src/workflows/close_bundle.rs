@@ -0,0 +1,201 @@
+use crate::{
+    agent::{actions::bundle::close_bundle, bundle::BundleKey, state::chain_header_on_top_of},
+    context::Context,
+    workflows::author_entry::update_dht,
+};
+use holochain_core_types::{chain_header::ChainHeader, error::HolochainError};
+use holochain_wasm_utils::api_serialization::bundle::BundleOnClose;
+use std::sync::Arc;
+
+/// Closes the open bundle of a zome call. When committing, the staged entries get
+/// written to the source chain all at once and only after that to the DHT, in the
+/// order they were made.
+pub async fn close_bundle_workflow<'a>(
+    key: BundleKey,
+    on_close: BundleOnClose,
+    context: &'a Arc<Context>,
+) -> Result<(), HolochainError> {
+    let commits = loop {
+        let chain_headers = match on_close {
+            BundleOnClose::Commit => store_bundle(key, context)?,
+            BundleOnClose::Discard => Vec::new(),
+        };
+        match await!(close_bundle(key, on_close.clone(), chain_headers, context)) {
+            // other commits got in first, the bundle goes on top of them
+            Err(_) if is_open(key, context) => continue,
+            result => break result?,
+        }
+    };
+    for commit in commits.iter() {
+        await!(update_dht(&commit.entry, &commit.dht_update, context))?;
+    }
+    Ok(())
+}
+
+/// Stores the staged entries of the open bundle of a zome call together with headers
+/// that put them on top of the source chain as it is now.
+/// The chain head only moves to them once the bundle gets closed.
+fn store_bundle(
+    key: BundleKey,
+    context: &Arc<Context>,
+) -> Result<Vec<ChainHeader>, HolochainError> {
+    let mut agent_state = context
+        .state()
+        .ok_or_else(|| HolochainError::ErrorGeneric("Context has no state".to_string()))?
+        .agent()
+        .as_ref()
+        .clone();
+    let commits = match agent_state.open_bundle(&key) {
+        Some(bundle) => bundle.commits(),
+        // closing reports bundles that are missing or timed out
+        None => return Ok(Vec::new()),
+    };
+    agent_state.check_not_closed()?;
+    let storage = agent_state.chain().content_storage();
+    let mut chain_headers = Vec::new();
+    for commit in commits.iter() {
+        let chain_header =
+            chain_header_on_top_of(&agent_state, &commit.entry, context, &commit.crud_link)?;
+        storage.write()?.add(&commit.entry)?;
+        storage.write()?.add(&chain_header)?;
+        agent_state.set_top_chain_header(chain_header.clone());
+        chain_headers.push(chain_header);
+    }
+    Ok(chain_headers)
+}
+
+/// Whether the zome call still has its bundle open
+fn is_open(key: BundleKey, context: &Arc<Context>) -> bool {
+    context
+        .state()
+        .map(|state| state.agent().open_bundle(&key).is_some())
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::close_bundle_workflow;
+    use crate::{
+        agent::actions::bundle::{close_bundle, start_bundle},
+        context::Context,
+        nucleus::actions::tests::instance,
+        workflows::author_entry::author_entry,
+    };
+    use futures::executor::block_on;
+    use holochain_core_types::{
+        cas::content::Address,
+        entry::{test_entry, Entry},
+        error::HolochainError,
+        json::{JsonString, RawString},
+    };
+    use holochain_wasm_utils::api_serialization::bundle::BundleOnClose;
+    use snowflake::ProcessUniqueId;
+    use std::{sync::Arc, thread, time::Duration};
+
+    fn is_on_chain(context: &Arc<Context>, address: &Address) -> bool {
+        let agent = context.state().unwrap().agent();
+        let top_chain_header = agent.top_chain_header();
+        let found = agent
+            .chain()
+            .iter(&top_chain_header)
+            .any(|header| header.entry_address() == address);
+        found
+    }
+
+    #[test]
+    fn test_bundle_commits_on_close() {
+        let (_instance, context) = instance(None);
+        let key = ProcessUniqueId::new();
+        block_on(start_bundle(key, 60000, JsonString::from("{}"), &context)).unwrap();
+        let address = block_on(author_entry(&test_entry(), None, Some(key), &context)).unwrap();
+
+        assert!(!is_on_chain(&context, &address));
+        let bundle = context.state().unwrap().agent().open_bundle(&key).unwrap();
+        assert_eq!(bundle.commits().len(), 1);
+
+        block_on(close_bundle_workflow(key, BundleOnClose::Commit, &context)).unwrap();
+        assert!(is_on_chain(&context, &address));
+        assert_eq!(context.state().unwrap().agent().open_bundle(&key), None);
+    }
+
+    #[test]
+    fn test_bundle_goes_on_top_of_later_commits() {
+        let (_instance, context) = instance(None);
+        let key = ProcessUniqueId::new();
+        block_on(start_bundle(key, 60000, JsonString::from("{}"), &context)).unwrap();
+        let staged = block_on(author_entry(&test_entry(), None, Some(key), &context)).unwrap();
+        let entry = Entry::App("testEntryType".into(), RawString::from("later").into());
+        let committed = block_on(author_entry(&entry, None, None, &context)).unwrap();
+        assert!(is_on_chain(&context, &committed));
+
+        // headers that don't follow the chain head leave the bundle open
+        assert!(block_on(close_bundle(
+            key,
+            BundleOnClose::Commit,
+            Vec::new(),
+            &context
+        ))
+        .is_err());
+        assert!(context.state().unwrap().agent().open_bundle(&key).is_some());
+
+        block_on(close_bundle_workflow(key, BundleOnClose::Commit, &context)).unwrap();
+        assert!(is_on_chain(&context, &staged));
+        let top_chain_header = context.state().unwrap().agent().top_chain_header().unwrap();
+        assert_eq!(top_chain_header.entry_address(), &staged);
+    }
+
+    #[test]
+    fn test_discarded_bundle_leaves_no_trace() {
+        let (_instance, context) = instance(None);
+        let key = ProcessUniqueId::new();
+        block_on(start_bundle(key, 60000, JsonString::from("{}"), &context)).unwrap();
+        assert!(block_on(start_bundle(key, 60000, JsonString::from("{}"), &context)).is_err());
+        let address = block_on(author_entry(&test_entry(), None, Some(key), &context)).unwrap();
+
+        block_on(close_bundle_workflow(key, BundleOnClose::Discard, &context)).unwrap();
+        assert!(!is_on_chain(&context, &address));
+        assert!(block_on(close_bundle_workflow(key, BundleOnClose::Commit, &context)).is_err());
+    }
+
+    #[test]
+    fn test_bundles_of_concurrent_calls_are_separate() {
+        let (_instance, context) = instance(None);
+        let (key1, key2) = (ProcessUniqueId::new(), ProcessUniqueId::new());
+        block_on(start_bundle(key1, 60000, JsonString::from("{}"), &context)).unwrap();
+        block_on(start_bundle(key2, 60000, JsonString::from("{}"), &context)).unwrap();
+        let entry1 = test_entry();
+        let address1 = block_on(author_entry(&entry1, None, Some(key1), &context)).unwrap();
+
+        block_on(close_bundle_workflow(key2, BundleOnClose::Commit, &context)).unwrap();
+        assert!(!is_on_chain(&context, &address1));
+        let bundle = context.state().unwrap().agent().open_bundle(&key1).unwrap();
+        assert_eq!(bundle.commits().len(), 1);
+
+        block_on(close_bundle_workflow(
+            key1,
+            BundleOnClose::Discard,
+            &context,
+        ))
+        .unwrap();
+        assert!(!is_on_chain(&context, &address1));
+    }
+
+    #[test]
+    fn test_bundle_times_out() {
+        let (_instance, context) = instance(None);
+        let key = ProcessUniqueId::new();
+        block_on(start_bundle(key, 2000, JsonString::from("{}"), &context)).unwrap();
+        let address = block_on(author_entry(&test_entry(), None, Some(key), &context)).unwrap();
+        let bundle = context.state().unwrap().agent().open_bundle(&key).unwrap();
+        assert_eq!(bundle.commits().len(), 1);
+        thread::sleep(Duration::from_millis(2500));
+
+        // the staged commits got dropped without waiting for the bundle to be closed
+        assert_eq!(context.state().unwrap().agent().open_bundle(&key), None);
+        assert_eq!(
+            block_on(close_bundle_workflow(key, BundleOnClose::Commit, &context)),
+            Err(HolochainError::Timeout)
+        );
+        assert!(!is_on_chain(&context, &address));
+    }
+}
@@ -1,4 +1,8 @@
-use crate::{context::Context, network, nucleus};
+use crate::{
+    context::Context,
+    network::{self, encrypted_entry::open_entry_with_meta},
+    nucleus,
+};
 use holochain_core_types::time::Timeout;
 
 use holochain_core_types::{
@@ -21,7 +25,13 @@ pub async fn get_entry_with_meta_workflow<'a>(
     if maybe_entry_with_meta.is_some() {
         return Ok(maybe_entry_with_meta);
     }
-    // 2. No result, so try on the network
+    // 2. Entries of encrypted entry types are held encrypted
+    if let Some(encrypted_entry_with_meta) =
+        nucleus::actions::get_entry::get_encrypted_entry_with_meta(context, address.clone())?
+    {
+        return open_entry_with_meta(&encrypted_entry_with_meta, context);
+    }
+    // 3. No result, so try on the network
     await!(network::actions::get_entry::get_entry(
         context.clone(),
         address.clone(),
@@ -0,0 +1,148 @@
+use crate::{
+    agent::keys::verify_header_signatures,
+    context::Context,
+    dht::actions::{collect_garbage::collect_garbage, hold::hold_encrypted_entry},
+    network::encrypted_entry::{open_entry, EncryptedEntryWithHeader},
+    workflows::hold_entry::validate_entry_from_source,
+};
+
+use holochain_core_types::{cas::content::Address, error::HolochainError};
+use std::sync::Arc;
+
+/// Holds an entry that got published encrypted.
+/// Recipients decrypt the entry and validate it like any other entry. All other nodes can
+/// only make sure the header is valid and that a source of it sealed the encrypted entry.
+/// Either way only the encrypted entry gets stored.
+pub async fn hold_encrypted_entry_workflow<'a>(
+    encrypted_entry_with_header: &'a EncryptedEntryWithHeader,
+    context: &'a Arc<Context>,
+) -> Result<Address, HolochainError> {
+    let EncryptedEntryWithHeader {
+        encrypted_entry,
+        header,
+    } = &encrypted_entry_with_header;
+
+    // 0. Make sure the header was signed by its sources
+    verify_header_signatures(encrypted_entry.entry_address(), header)?;
+    if encrypted_entry.entry_type() != header.entry_type() {
+        return Err(HolochainError::ValidationFailed(format!(
+            "Header does not belong to an entry of type {}",
+            encrypted_entry.entry_type()
+        )));
+    }
+    if !header.sources().contains(encrypted_entry.author()) {
+        return Err(HolochainError::ValidationFailed(format!(
+            "Encrypted entry {} is not sealed by a source of its header",
+            encrypted_entry.entry_address()
+        )));
+    }
+    encrypted_entry.verify_signature()?;
+
+    // 1. Validate the plaintext if we are a recipient
+    match open_entry(encrypted_entry, &context)? {
+        Some(entry) => await!(validate_entry_from_source(&entry, header, &context))?,
+        None => context.log(format!(
+            "debug/workflow/hold_encrypted_entry: {} is not encrypted for us, holding it unvalidated",
+            encrypted_entry.entry_address()
+        )),
+    }
+
+    // 2. Store the encrypted entry in the local DHT shard
+    let address = await!(hold_encrypted_entry(encrypted_entry, &context))?;
+
+    // 3. Make room for it if the shard outgrew its quota
+    collect_garbage(&context)?;
+    Ok(address)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{
+        agent::{actions::commit::commit_entry, keys::test_keypair},
+        network::encrypted_entry::seal_entry,
+        nucleus::actions::{
+            get_entry::{get_encrypted_entry_with_meta, get_entry_from_dht},
+            tests::{instance_by_name, test_dna},
+        },
+        workflows::get_entry_result::get_entry_with_meta_workflow,
+    };
+    use futures::executor::block_on;
+    use holochain_core_types::{
+        cas::content::AddressableContent,
+        dna::{entry_types::Sharing, Dna},
+        entry::test_entry,
+        time::Timeout,
+    };
+
+    pub fn encrypted_dna(name: &str) -> Dna {
+        let mut dna = test_dna();
+        dna.uuid = format!("encrypted for {}", name);
+        let entry_type_def = dna
+            .zomes
+            .get_mut("test_zome")
+            .unwrap()
+            .entry_types
+            .get_mut(&test_entry().entry_type())
+            .unwrap();
+        entry_type_def.sharing = Sharing::Encrypted;
+        dna
+    }
+
+    #[test]
+    fn only_recipients_can_read_encrypted_entries() {
+        let dna = encrypted_dna("jack");
+        let netname = Some("only_recipients_can_read_encrypted_entries, the network");
+        let (_author_instance, author) = instance_by_name("jill", dna.clone(), netname);
+        let (_recipient_instance, recipient) = instance_by_name("jack", dna.clone(), netname);
+        let (_other_instance, other) = instance_by_name("joan", dna, netname);
+
+        let entry = test_entry();
+        let recipients = vec![test_keypair("jack").address()];
+
+        block_on(commit_entry(entry.clone(), None, &author)).unwrap();
+        let header = author.state().unwrap().agent().top_chain_header().unwrap();
+        let encrypted_entry_with_header = EncryptedEntryWithHeader::new(
+            seal_entry(&entry, &recipients, &author).unwrap(),
+            header.clone(),
+        );
+
+        // only sources of the header can seal the entry
+        let sealed_by_other =
+            EncryptedEntryWithHeader::new(seal_entry(&entry, &recipients, &other).unwrap(), header);
+        assert!(block_on(hold_encrypted_entry_workflow(&sealed_by_other, &other)).is_err());
+
+        for context in vec![&recipient, &other] {
+            assert_eq!(
+                block_on(hold_encrypted_entry_workflow(
+                    &encrypted_entry_with_header,
+                    context
+                )),
+                Ok(entry.address())
+            );
+            // holders never store the plaintext
+            assert_eq!(get_entry_from_dht(context, &entry.address()), Ok(None));
+            assert!(get_encrypted_entry_with_meta(context, entry.address())
+                .unwrap()
+                .is_some());
+        }
+
+        let timeout = Timeout::default();
+        let entry_with_meta = block_on(get_entry_with_meta_workflow(
+            &recipient,
+            &entry.address(),
+            &timeout,
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(entry_with_meta.entry, entry);
+        assert_eq!(
+            block_on(get_entry_with_meta_workflow(
+                &other,
+                &entry.address(),
+                &timeout
+            )),
+            Ok(None)
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{
+    agent::keys::{agent_key_after_headers, verify_header},
     context::Context,
-    dht::actions::hold::hold_entry,
+    dht::actions::{collect_garbage::collect_garbage, hold::hold_entry},
     network::{
         actions::get_validation_package::get_validation_package, entry_with_header::EntryWithHeader,
     },
@@ -8,9 +9,11 @@ use crate::{
 };
 
 use holochain_core_types::{
-    cas::content::Address,
+    cas::content::{Address, AddressableContent},
+    chain_header::ChainHeader,
+    entry::Entry,
     error::HolochainError,
-    validation::{EntryAction, EntryLifecycle, ValidationData},
+    validation::{EntryAction, EntryLifecycle, ValidationData, ValidationPackage},
 };
 use std::sync::Arc;
 
@@ -20,12 +23,80 @@ pub async fn hold_entry_workflow<'a>(
 ) -> Result<Address, HolochainError> {
     let EntryWithHeader { entry, header } = &entry_with_header;
 
-    // 1. Get validation package from source
+    // 0. Get validation package from source
+    let validation_package = await!(validation_package_from_source(header, &context))?;
+
+    // 1. Make sure the header was signed by the author's agent key in effect at its position
+    let agent_key = agent_key_in_effect(entry, header, &validation_package)?;
+    verify_header(entry, header, &agent_key)?;
+
+    // 2. Validate the entry
+    await!(validate_entry_with_package(
+        entry,
+        header,
+        validation_package,
+        &context
+    ))?;
+
+    // 3. If valid store the entry in the local DHT shard
+    let address = await!(hold_entry(entry, &context))?;
+
+    // 4. Make room for it if the shard outgrew its quota
+    collect_garbage(&context)?;
+    Ok(address)
+}
+
+/// The agent key that has to sign `header`, i.e. the key the author's agent had at the
+/// position of `header` in its chain. The validation package carries the headers of the
+/// agent entries before that position, which tell the initial key and all rotations since.
+/// Agent entries that rotate keys have to be signed by the key they replace, so the
+/// package has to end with the agent entry they replace.
+pub(crate) fn agent_key_in_effect(
+    entry: &Entry,
+    header: &ChainHeader,
+    validation_package: &ValidationPackage,
+) -> Result<Address, HolochainError> {
+    let agent_headers = &validation_package.agent_headers;
+    if let Entry::AgentId(_) = entry {
+        if header.link_same_type() != agent_headers.first().map(|header| header.address()) {
+            return Err(HolochainError::ValidationFailed(format!(
+                "Header of agent entry {} does not follow the agent entry before it",
+                entry.address()
+            )));
+        }
+    }
+    match agent_key_after_headers(agent_headers)? {
+        Some(agent_key) => Ok(agent_key),
+        // Only the initial agent entry of a chain has no agent entry before it
+        None => match entry {
+            Entry::AgentId(_) => Ok(entry.address()),
+            _ => Err(HolochainError::ValidationFailed(format!(
+                "No agent entry before entry {}",
+                entry.address()
+            ))),
+        },
+    }
+}
+
+/// Gets the validation package of the entry of `header` from its source.
+pub async fn validation_package_from_source<'a>(
+    header: &'a ChainHeader,
+    context: &'a Arc<Context>,
+) -> Result<ValidationPackage, HolochainError> {
     let maybe_validation_package = await!(get_validation_package(header.clone(), &context))?;
-    let validation_package = maybe_validation_package
-        .ok_or("Could not get validation package from source".to_string())?;
+    maybe_validation_package.ok_or_else(|| {
+        HolochainError::ErrorGeneric("Could not get validation package from source".to_string())
+    })
+}
 
-    // 2. Create validation data struct
+/// Validates an entry we are asked to hold, with the given validation package of its source.
+pub async fn validate_entry_with_package<'a>(
+    entry: &'a Entry,
+    header: &'a ChainHeader,
+    validation_package: ValidationPackage,
+    context: &'a Arc<Context>,
+) -> Result<(), HolochainError> {
+    // 1. Create validation data struct
     let validation_data = ValidationData {
         package: validation_package,
         sources: header.sources().clone(),
@@ -33,11 +104,24 @@ pub async fn hold_entry_workflow<'a>(
         action: EntryAction::Create,
     };
 
-    // 3. Validate the entry
+    // 2. Validate the entry
     await!(validate_entry(entry.clone(), validation_data, &context))?;
+    Ok(())
+}
 
-    // 3. If valid store the entry in the local DHT shard
-    await!(hold_entry(entry, &context))
+/// Validates an entry we are asked to hold, with the validation package of its source.
+pub async fn validate_entry_from_source<'a>(
+    entry: &'a Entry,
+    header: &'a ChainHeader,
+    context: &'a Arc<Context>,
+) -> Result<(), HolochainError> {
+    let validation_package = await!(validation_package_from_source(header, &context))?;
+    await!(validate_entry_with_package(
+        entry,
+        header,
+        validation_package,
+        &context
+    ))
 }
 
 #[cfg(test)]
@@ -77,7 +161,7 @@ pub mod tests {
 
         // Commit entry on attackers node
         let entry = test_entry();
-        let _entry_address = block_on(author_entry(&entry, None, &context1)).unwrap();
+        let _entry_address = block_on(author_entry(&entry, None, None, &context1)).unwrap();
 
         // Get header which we need to trigger hold_entry_workflow
         let agent1_state = context1.state().unwrap().agent();
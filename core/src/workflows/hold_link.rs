@@ -1,10 +1,12 @@
 use crate::{
+    agent::keys::verify_header,
     context::Context,
-    dht::actions::add_link::add_link,
+    dht::actions::{add_link::add_link, remove_link::remove_link},
     network::{
         actions::get_validation_package::get_validation_package, entry_with_header::EntryWithHeader,
     },
     nucleus::actions::validate::validate_entry,
+    workflows::hold_entry::agent_key_in_effect,
 };
 
 use holochain_core_types::{
@@ -20,16 +22,17 @@ pub async fn hold_link_workflow<'a>(
 ) -> Result<(), HolochainError> {
     let EntryWithHeader { entry, header } = &entry_with_header;
 
-    let link_add = match entry {
-        Entry::LinkAdd(link_add) => link_add,
+    let (link, action) = match entry {
+        Entry::LinkAdd(link_add) => (link_add.link().clone(), EntryAction::Create),
+        Entry::LinkRemove(link_remove) => (link_remove.link().clone(), EntryAction::Delete),
         _ => Err(HolochainError::ErrorGeneric(
-            "hold_link_workflow expects entry to be an Entry::LinkAdd".to_string(),
+            "hold_link_workflow expects entry to be an Entry::LinkAdd or Entry::LinkRemove"
+                .to_string(),
         ))?,
     };
-    let link = link_add.link().clone();
 
     context.log(format!("debug/workflow/hold_link: {:?}", link));
-    // 1. Get validation package from source
+    // 0. Get validation package from source
     context.log(format!(
         "debug/workflow/hold_link: getting validation package..."
     ));
@@ -38,12 +41,16 @@ pub async fn hold_link_workflow<'a>(
         .ok_or("Could not get validation package from source".to_string())?;
     context.log(format!("debug/workflow/hold_link: got validation package!"));
 
+    // 1. Make sure the header was signed by the author's agent key in effect at its position
+    let agent_key = agent_key_in_effect(entry, header, &validation_package)?;
+    verify_header(entry, header, &agent_key)?;
+
     // 2. Create validation data struct
     let validation_data = ValidationData {
         package: validation_package,
         sources: header.sources().clone(),
         lifecycle: EntryLifecycle::Meta,
-        action: EntryAction::Create,
+        action: action.clone(),
     };
 
     // 3. Validate the entry
@@ -54,9 +61,17 @@ pub async fn hold_link_workflow<'a>(
     })?;
     context.log(format!("debug/workflow/hold_link: is valid!"));
 
-    // 3. If valid store the entry in the local DHT shard
-    await!(add_link(&link, &context))?;
-    context.log(format!("debug/workflow/hold_link: added! {:?}", link));
+    // 3. If valid store the link, or its removal, in the local DHT shard
+    match action {
+        EntryAction::Delete => {
+            await!(remove_link(&link, &context))?;
+            context.log(format!("debug/workflow/hold_link: removed! {:?}", link));
+        }
+        _ => {
+            await!(add_link(&link, &context))?;
+            context.log(format!("debug/workflow/hold_link: added! {:?}", link));
+        }
+    }
     Ok(())
 }
 
@@ -97,12 +112,12 @@ pub mod tests {
 
         // Commit entry on attackers node
         let entry = test_entry();
-        let entry_address = block_on(author_entry(&entry, None, &context1)).unwrap();
+        let entry_address = block_on(author_entry(&entry, None, None, &context1)).unwrap();
 
         let link_add = LinkAdd::new(&entry_address, &entry_address, "test-tag");
         let link_entry = Entry::LinkAdd(link_add);
 
-        let _ = block_on(author_entry(&link_entry, None, &context1)).unwrap();
+        let _ = block_on(author_entry(&link_entry, None, None, &context1)).unwrap();
 
         // Get header which we need to trigger hold_entry_workflow
         let agent1_state = context1.state().unwrap().agent();
@@ -0,0 +1,176 @@
+use crate::{
+    agent::{actions::import_chain::import_chain, chain_archive::ChainArchive},
+    context::Context,
+    nucleus::actions::{
+        build_validation_package::validation_package_definition, validate::validate_entry,
+    },
+    workflows::update_agent::catch_up_agent_keys,
+};
+
+use holochain_core_types::{
+    cas::content::{Address, AddressableContent},
+    entry::entry_type::EntryType,
+    error::HolochainError,
+    validation::{EntryAction, EntryLifecycle, ValidationData},
+};
+use std::sync::Arc;
+
+/// Archives the whole source chain of the given context.
+pub fn export_chain(context: &Arc<Context>) -> Result<ChainArchive, HolochainError> {
+    let agent_state = context
+        .state()
+        .ok_or_else(|| HolochainError::ErrorGeneric("State not initialized".to_string()))?
+        .agent();
+    ChainArchive::from_chain(&agent_state.chain(), &agent_state.top_chain_header())
+}
+
+/// Restores an archived chain into the source chain of the given context.
+/// The archive has to be a valid chain of the same DNA and agent, and the source chain has
+/// to be the beginning of it, which is the case for a freshly created instance.
+/// The app validates the archived entries again, just like when they got committed.
+/// Imported entries are not published again since the DHT got them from the original
+/// instance already.
+pub async fn import_chain_workflow<'a>(
+    archive: ChainArchive,
+    context: &'a Arc<Context>,
+) -> Result<Address, HolochainError> {
+    archive.verify()?;
+    let dna = context.get_dna().ok_or(HolochainError::DnaMissing)?;
+    if dna.address() != *archive.dna_address() {
+        return Err(HolochainError::ErrorGeneric(format!(
+            "Chain archive belongs to DNA {}, not to DNA {}",
+            archive.dna_address(),
+            dna.address()
+        )));
+    }
+    if archive.agent_id().key != context.agent_id.key {
+        return Err(HolochainError::ErrorGeneric(format!(
+            "Chain archive belongs to agent {}, not to agent {}",
+            archive.agent_id().address(),
+            context.agent_id.address()
+        )));
+    }
+    let agent_state = context
+        .state()
+        .ok_or_else(|| HolochainError::ErrorGeneric("State not initialized".to_string()))?
+        .agent();
+    if agent_state.has_open_bundles() {
+        return Err(HolochainError::ErrorGeneric(
+            "Can't import a chain while a bundle is open".to_string(),
+        ));
+    }
+
+    await!(validate_archived_entries(&archive, context))?;
+    let top_header_address = await!(import_chain(archive, context))?;
+    context.log(format!(
+        "debug/workflow/import_chain: source chain continues at header {}",
+        top_header_address
+    ));
+
+    // The archived chain might have rotated keys
+    let agent_address = context
+        .state()
+        .ok_or_else(|| HolochainError::ErrorGeneric("State not initialized".to_string()))?
+        .agent()
+        .get_agent_address()?;
+    catch_up_agent_keys(&agent_address, context)?;
+    Ok(top_header_address)
+}
+
+/// Runs the app's validation of every archived entry the app defines, with the validation
+/// package built from the archived chain up to that entry.
+async fn validate_archived_entries<'a>(
+    archive: &'a ChainArchive,
+    context: &'a Arc<Context>,
+) -> Result<(), HolochainError> {
+    for (index, element) in archive.chain().iter().enumerate() {
+        let action = match element.entry.entry_type() {
+            EntryType::App(_) if element.header.link_crud().is_some() => EntryAction::Modify,
+            EntryType::App(_) | EntryType::LinkAdd => EntryAction::Create,
+            EntryType::LinkRemove => EntryAction::Delete,
+            _ => continue,
+        };
+        let definition = validation_package_definition(&element.entry, context.clone())?;
+        let validation_data = ValidationData {
+            package: archive.validation_package(index, definition),
+            sources: element.header.sources().clone(),
+            lifecycle: EntryLifecycle::Chain,
+            action,
+        };
+        await!(validate_entry(
+            element.entry.clone(),
+            validation_data,
+            context
+        ))
+        .map_err(|error| {
+            HolochainError::ValidationFailed(format!(
+                "Archived entry {} is not valid: {}",
+                element.entry.address(),
+                error
+            ))
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{
+        agent::actions::commit::commit_entry,
+        nucleus::actions::tests::{instance_by_name, test_dna},
+    };
+    use futures::executor::block_on;
+    use holochain_core_types::{
+        entry::{test_entry, Entry},
+        json::RawString,
+    };
+
+    #[test]
+    fn can_export_and_import_chain() {
+        let (_instance, context) = instance_by_name("jane", test_dna(), None);
+        block_on(commit_entry(test_entry(), None, &context)).unwrap();
+        let archive = export_chain(&context).unwrap();
+        assert_eq!(archive.chain().len(), 3);
+
+        let (_new_instance, new_context) = instance_by_name("jane", test_dna(), None);
+        let top_header_address =
+            block_on(import_chain_workflow(archive.clone(), &new_context)).unwrap();
+
+        let new_agent_state = new_context.state().unwrap().agent();
+        let top_header = new_agent_state.top_chain_header().unwrap();
+        assert_eq!(top_header.address(), top_header_address);
+        assert_eq!(Some(&top_header), archive.top_chain_header());
+        assert_eq!(export_chain(&new_context).unwrap(), archive);
+
+        // importing the same chain again does not change anything
+        assert_eq!(
+            block_on(import_chain_workflow(archive, &new_context)),
+            Ok(top_header_address)
+        );
+    }
+
+    #[test]
+    fn import_chain_rejects_other_agents() {
+        let (_instance, context) = instance_by_name("jane", test_dna(), None);
+        let archive = export_chain(&context).unwrap();
+
+        let (_other_instance, other_context) = instance_by_name("joe", test_dna(), None);
+        assert!(block_on(import_chain_workflow(archive, &other_context)).is_err());
+    }
+
+    #[test]
+    fn import_chain_validates_entries() {
+        let (_instance, context) = instance_by_name("jane", test_dna(), None);
+        // committing directly skips the validation the app would do
+        let invalid_entry = Entry::App("testEntryType".into(), RawString::from("FAIL").into());
+        block_on(commit_entry(invalid_entry, None, &context)).unwrap();
+        let archive = export_chain(&context).unwrap();
+        assert!(archive.verify().is_ok());
+
+        let (_new_instance, new_context) = instance_by_name("jane", test_dna(), None);
+        assert!(block_on(import_chain_workflow(archive, &new_context)).is_err());
+        // nothing got imported
+        assert_eq!(export_chain(&new_context).unwrap().chain().len(), 2);
+    }
+}
@@ -0,0 +1,122 @@
+use crate::{agent::actions::commit::commit_entry, context::Context};
+
+use holochain_core_types::{
+    cas::content::{Address, AddressableContent},
+    chain_migrate::ChainMigrate,
+    entry::Entry,
+    error::HolochainError,
+};
+use std::sync::Arc;
+
+/// Describes the migration of the source chain of the given context to the DNA with the
+/// given address. The same entry gets committed on both chains.
+pub fn chain_migrate_from(
+    old_context: &Arc<Context>,
+    new_dna_address: Address,
+) -> Result<ChainMigrate, HolochainError> {
+    let state = old_context
+        .state()
+        .ok_or_else(|| HolochainError::ErrorGeneric("State not initialized".to_string()))?;
+    let old_dna = state.nucleus().dna().ok_or(HolochainError::DnaMissing)?;
+    Ok(ChainMigrate::new(
+        old_dna.address(),
+        new_dna_address,
+        state.agent().get_agent_address()?,
+    ))
+}
+
+/// Closes the source chain of the given context by committing a ChainMigrate entry
+/// that points to the chain of the DNA with the given address.
+/// The entry stays private to the chain and no further commits are accepted after it.
+pub async fn close_chain_workflow<'a>(
+    new_dna_address: Address,
+    context: &'a Arc<Context>,
+) -> Result<Address, HolochainError> {
+    let agent_state = context
+        .state()
+        .ok_or_else(|| HolochainError::ErrorGeneric("State not initialized".to_string()))?
+        .agent();
+    if let Some(migrate) = agent_state.closing_migration() {
+        return Err(HolochainError::ErrorGeneric(format!(
+            "Source chain is already closed: it got migrated to DNA {}",
+            migrate.new_dna_address()
+        )));
+    }
+    let migrate = chain_migrate_from(context, new_dna_address)?;
+    context.log(format!(
+        "debug/workflow/migrate_chain: closing chain of DNA {} in favour of DNA {}",
+        migrate.old_dna_address(),
+        migrate.new_dna_address()
+    ));
+    await!(commit_entry(Entry::ChainMigrate(migrate), None, context))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{
+        instance::{tests::test_context, Instance},
+        nucleus::actions::{
+            initialize::initialize_application,
+            tests::{instance, test_dna},
+        },
+    };
+    use futures::executor::block_on;
+    use holochain_core_types::{
+        dna::Dna,
+        entry::{entry_type::EntryType, test_entry},
+    };
+
+    /// Creates an instance of the given DNA that continues the chain of the given context.
+    pub fn migrated_instance(dna: Dna, old_context: Arc<Context>) -> (Instance, Arc<Context>) {
+        let mut context = (*test_context("jane", None)).clone();
+        context.set_migrated_from(old_context);
+        let context = Arc::new(context);
+        let mut instance = Instance::new(context.clone());
+        instance.start_action_loop(context.clone());
+        let context = instance.initialize_context(context);
+        block_on(initialize_application(dna, &context)).unwrap();
+        (instance, context)
+    }
+
+    #[test]
+    fn test_migrate_chain() {
+        let (_old_instance, old_context) = instance(None);
+        let mut new_dna = test_dna();
+        new_dna.version = String::from("2.0.0");
+        let migrate = chain_migrate_from(&old_context, new_dna.address()).unwrap();
+
+        let (_new_instance, new_context) = migrated_instance(new_dna.clone(), old_context.clone());
+        let new_agent_state = new_context.state().unwrap().agent();
+        let header = new_agent_state
+            .get_header_for_entry(&Entry::ChainMigrate(migrate.clone()))
+            .expect("new chain should start with the migration");
+        assert_eq!(header.entry_type(), &EntryType::ChainMigrate);
+        assert_eq!(new_agent_state.closing_migration(), None);
+
+        block_on(close_chain_workflow(new_dna.address(), &old_context)).unwrap();
+        let old_agent_state = old_context.state().unwrap().agent();
+        assert_eq!(old_agent_state.closing_migration(), Some(migrate));
+
+        // a closed chain does not take any commits, not even another migration
+        assert!(block_on(commit_entry(test_entry(), None, &old_context)).is_err());
+        assert!(block_on(close_chain_workflow(new_dna.address(), &old_context)).is_err());
+    }
+
+    #[test]
+    fn test_chain_migrate_from() {
+        let (_instance, context) = instance(None);
+        let migrate = chain_migrate_from(&context, Address::from("new_dna")).unwrap();
+        assert_eq!(migrate.old_dna_address(), &test_dna().address());
+        assert_eq!(
+            migrate.agent(),
+            &context
+                .state()
+                .unwrap()
+                .agent()
+                .get_agent_address()
+                .unwrap()
+        );
+        assert!(chain_migrate_from(&test_context("jane", None), Address::from("new_dna")).is_err());
+    }
+}
@@ -1,7 +1,12 @@
 pub mod application;
 pub mod author_entry;
+pub mod close_bundle;
 pub mod get_entry_result;
 pub mod handle_custom_direct_message;
+pub mod hold_encrypted_entry;
 pub mod hold_entry;
 pub mod hold_link;
+pub mod import_chain;
+pub mod migrate_chain;
 pub mod respond_validation_package_request;
+pub mod update_agent;
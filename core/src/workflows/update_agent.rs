@@ -0,0 +1,217 @@
+use crate::{
+    agent::bundle::DhtUpdate,
+    context::Context,
+    nucleus::actions::{
+        build_validation_package::build_validation_package, validate::validate_entry,
+    },
+    workflows::author_entry::{commit_and_update_dht, update_dht},
+};
+
+use holochain_core_types::{
+    agent::AgentId,
+    cas::content::{Address, AddressableContent},
+    entry::{entry_type::EntryType, Entry},
+    error::HolochainError,
+    validation::{EntryAction, EntryLifecycle, ValidationData},
+};
+use std::sync::Arc;
+
+/// Rotates the keys of the agent.
+/// Commits a new agent entry with freshly generated keys that replaces the current
+/// agent entry. The new keys get stored before anything is committed, so this fails
+/// if the context has no rotated key store. The header of the new agent entry still
+/// gets signed with the current keys, which proves that the new keys belong to the
+/// same agent. All following headers get signed with the new keys.
+/// On the DHT, the current agent entry gets marked as updated by the new one.
+pub async fn update_agent_workflow<'a>(
+    context: &'a Arc<Context>,
+) -> Result<Address, HolochainError> {
+    let agent_state = context
+        .state()
+        .ok_or_else(|| HolochainError::ErrorGeneric("State not initialized".to_string()))?
+        .agent();
+    // Bundled headers only get created when the bundle is closed, so they could not
+    // be signed with the right keys.
+    if agent_state.has_open_bundles() {
+        return Err(HolochainError::ErrorGeneric(
+            "Can't update the agent while a bundle is open".to_string(),
+        ));
+    }
+    let old_agent = await!(agent_state.get_agent(context))?;
+    let old_address = old_agent.address();
+    let new_keys = context.next_agent_keys()?;
+    let entry = Entry::AgentId(AgentId::new(&old_agent.nick, &new_keys.public_key()));
+    let address = entry.address();
+
+    // 1. Build the context needed for validation of the entry
+    let validation_package = await!(build_validation_package(&entry, &context))?;
+    let validation_data = ValidationData {
+        package: validation_package,
+        sources: vec![old_address.clone()],
+        lifecycle: EntryLifecycle::Chain,
+        action: EntryAction::Modify,
+    };
+
+    // 2. Validate the entry
+    await!(validate_entry(entry.clone(), validation_data, &context))?;
+
+    // 3. Commit the entry, signed with the current keys
+    await!(commit_and_update_dht(
+        entry.clone(),
+        Some(old_address.clone()),
+        DhtUpdate::None,
+        None,
+        context
+    ))?;
+
+    // 4. Switch to the new keys
+    context.replace_agent_keys(new_keys)?;
+    context.log(format!(
+        "debug/workflow/update_agent: rotated keys from {} to {}",
+        old_address, address
+    ));
+
+    // 5. Publish the new agent entry and mark the old one as updated
+    await!(update_dht(&entry, &DhtUpdate::Publish, context))?;
+    await!(update_dht(&entry, &DhtUpdate::Update(old_address), context))?;
+    Ok(address)
+}
+
+/// Makes the agent keys of the context match the latest agent entry on the chain.
+/// Contexts get created with the initial keys of the agent, so after loading a chain
+/// with rotated keys they have to be replaced by the keys the agent rotated to last,
+/// which the context's rotated key store keeps.
+pub fn catch_up_agent_keys(
+    agent_address: &Address,
+    context: &Arc<Context>,
+) -> Result<(), HolochainError> {
+    let keys = match context.agent_keys {
+        Some(ref keys) => keys.clone(),
+        None => return Ok(()),
+    };
+    let mut keys = keys.lock()?;
+    if keys.address() == *agent_address {
+        return Ok(());
+    }
+    let rotated_keys = match context.rotated_key_store {
+        Some(ref store) => store.load(&mut keys)?,
+        None => Vec::new(),
+    };
+    match rotated_keys
+        .into_iter()
+        .find(|rotated| rotated.address() == *agent_address)
+    {
+        Some(rotated) => {
+            *keys = rotated;
+            Ok(())
+        }
+        None => Err(HolochainError::ErrorGeneric(format!(
+            "Agent keys don't belong to agent {} of the source chain",
+            agent_address
+        ))),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{
+        agent::keys::{test_keypair, verify},
+        nucleus::actions::tests::instance,
+    };
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_update_agent_rotates_keys() {
+        let (_instance, context) = instance(None);
+        let old_address = context
+            .state()
+            .unwrap()
+            .agent()
+            .get_agent_address()
+            .unwrap();
+
+        let new_address = block_on(update_agent_workflow(&context)).unwrap();
+        assert_ne!(new_address, old_address);
+
+        let agent_state = context.state().unwrap().agent();
+        assert_eq!(agent_state.get_agent_address().unwrap(), new_address);
+        let header = agent_state.top_chain_header().unwrap();
+        assert_eq!(header.sources(), &vec![old_address.clone()]);
+        assert_eq!(header.link_crud(), Some(old_address));
+
+        let signature = context.sign("some data").unwrap();
+        assert!(verify(&new_address, "some data", &signature).unwrap());
+    }
+
+    #[test]
+    fn test_catch_up_agent_keys() {
+        let (_instance, context) = instance(None);
+        let initial_address = context
+            .state()
+            .unwrap()
+            .agent()
+            .get_agent_address()
+            .unwrap();
+        let new_address = block_on(update_agent_workflow(&context)).unwrap();
+
+        // Go back to the initial keys, like after loading the chain with the keystore
+        context
+            .replace_agent_keys(test_keypair(&context.agent_id.nick))
+            .unwrap();
+        assert_eq!(
+            context
+                .agent_keys
+                .as_ref()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .address(),
+            initial_address
+        );
+
+        catch_up_agent_keys(&new_address, &context).unwrap();
+        assert_eq!(
+            context
+                .agent_keys
+                .as_ref()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .address(),
+            new_address
+        );
+        assert!(catch_up_agent_keys(&Address::from("unknown agent"), &context).is_err());
+    }
+
+    #[test]
+    fn test_update_agent_fails_without_key_store() {
+        let (_instance, context) = instance(None);
+        let mut context = (*context).clone();
+        context.rotated_key_store = None;
+        let context = Arc::new(context);
+        let agent_state = context.state().unwrap().agent();
+
+        assert!(block_on(update_agent_workflow(&context)).is_err());
+
+        let new_agent_state = context.state().unwrap().agent();
+        assert_eq!(
+            new_agent_state.top_chain_header(),
+            agent_state.top_chain_header()
+        );
+        assert_eq!(
+            new_agent_state.get_agent_address().unwrap(),
+            agent_state.get_agent_address().unwrap()
+        );
+        assert_eq!(
+            context
+                .agent_keys
+                .as_ref()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .address(),
+            agent_state.get_agent_address().unwrap()
+        );
+    }
+}
@@ -6,13 +6,13 @@ extern crate holochain_core_types;
 extern crate holochain_net;
 
 use holochain_container_api::{context_builder::ContextBuilder, Holochain};
-use holochain_core::context::Context;
+use holochain_core::{agent::keys::Keypair, context::Context};
 use holochain_core_types::{cas::content::Address, dna::Dna, error::HolochainError};
 
 use std::sync::Arc;
 
 use holochain_core::logger::Logger;
-use holochain_core_types::{agent::AgentId, dna::capabilities::CapabilityCall};
+use holochain_core_types::dna::capabilities::CapabilityCall;
 use std::{
     ffi::{CStr, CString},
     os::raw::c_char,
@@ -57,11 +57,12 @@ pub unsafe extern "C" fn holochain_load(storage_path: CStrPtr) -> *mut Holochain
 }
 
 fn get_context(path: &String) -> Result<Context, HolochainError> {
-    let agent = AgentId::generate_fake("c_bob");
-    Ok(ContextBuilder::new()
-        .with_agent(agent)
+    let keys = Keypair::new_random()?;
+    ContextBuilder::new()
+        .with_agent(keys.agent_id("c_bob"))
+        .with_agent_keys(keys)
         .with_file_storage(path.clone())?
-        .spawn())
+        .spawn()
 }
 
 #[no_mangle]
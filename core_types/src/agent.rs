@@ -32,6 +32,12 @@ impl KeyBuffer {
     pub fn with_corrected(s: &str) -> Result<KeyBuffer, HolochainError> {
         let s = s.replace("-", "+").replace("_", "/");
         let base64 = base64::decode(&s)?;
+        if base64.len() != KeyBuffer::KEY_LEN + KeyBuffer::PARITY_LEN {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "public key has wrong length: {}",
+                base64.len()
+            )));
+        }
         let dec = Decoder::new(KeyBuffer::PARITY_LEN);
         let dec = *dec.correct(base64.as_slice(), None)?;
         Ok(KeyBuffer::with_raw(array_ref![dec, 0, KeyBuffer::KEY_LEN]))
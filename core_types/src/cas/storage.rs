@@ -5,7 +5,10 @@
 
 use crate::{
     cas::content::{Address, AddressableContent, Content},
-    eav::{EntityAttributeValueIndex, EntityAttributeValueStorage, IndexQuery},
+    eav::{
+        AttributeFilter, EavQuery, EntityAttributeValueIndex, EntityAttributeValueStorage,
+        IndexOrder, IndexQuery, Versions,
+    },
     entry::{test_entry_unique, Entry},
     error::HolochainError,
     json::RawString,
@@ -23,7 +26,7 @@ use uuid::Uuid;
 /// content addressable store (CAS)
 /// implements storage in memory or persistently
 /// anything implementing AddressableContent can be added and fetched by address
-/// CAS is append only
+/// CAS is append only, apart from dropping content that is held for others
 pub trait ContentAddressableStorage: objekt::Clone + Send + Sync + Debug {
     /// adds AddressableContent to the ContentAddressableStorage by its Address as Content
     fn add(&mut self, content: &AddressableContent) -> Result<(), HolochainError>;
@@ -34,6 +37,12 @@ pub trait ContentAddressableStorage: objekt::Clone + Send + Sync + Debug {
     /// AddressableContent::from_content() can be used to allow the compiler to infer the type
     /// @see the fetch implementation for ExampleCas in the cas module tests
     fn fetch(&self, address: &Address) -> Result<Option<Content>, HolochainError>;
+    /// removes the Content at the given Address, if there is any
+    fn remove(&mut self, address: &Address) -> Result<(), HolochainError>;
+    /// frees the space removed content still takes up, for implementations that keep it around
+    fn compact(&mut self) -> Result<(), HolochainError> {
+        Ok(())
+    }
     //needed to find a way to compare two different CAS for partialord derives.
     //easiest solution was to just compare two ids which are based on uuids
     fn get_id(&self) -> Uuid;
@@ -83,6 +92,10 @@ impl ContentAddressableStorage for ExampleContentAddressableStorage {
         Ok(self.content.read()?.unthreadable_fetch(address)?)
     }
 
+    fn remove(&mut self, address: &Address) -> Result<(), HolochainError> {
+        self.content.write()?.unthreadable_remove(address)
+    }
+
     fn get_id(&self) -> Uuid {
         Uuid::new_v4()
     }
@@ -117,6 +130,11 @@ impl ExampleContentAddressableStorageContent {
     fn unthreadable_fetch(&self, address: &Address) -> Result<Option<Content>, HolochainError> {
         Ok(self.storage.get(address).cloned())
     }
+
+    fn unthreadable_remove(&mut self, address: &Address) -> Result<(), HolochainError> {
+        self.storage.remove(address);
+        Ok(())
+    }
 }
 
 // A struct for our test suite that infers a type of ContentAddressableStorage
@@ -191,6 +209,15 @@ where
             );
         }
 
+        // removed content is gone for all clones, other content stays
+        assert_eq!(Ok(()), self.cas.remove(&other_content.address()));
+
+        for cas in both_cas.iter() {
+            assert_eq!(Ok(true), cas.contains(&content.address()));
+            assert_eq!(Ok(false), cas.contains(&other_content.address()));
+            assert_eq!(Ok(None), cas.fetch(&other_content.address()));
+        }
+
         // show consistent view on data across threads
 
         let entry = test_entry_unique();
@@ -308,6 +335,17 @@ impl EavTestSuite {
                 );
             }
         }
+
+        eav_storage.remove_eavi(&eav).expect("could not remove eav");
+        let two_stores = vec![eav_storage.clone(), eav_storage.clone()];
+        for eav_storage in two_stores.iter() {
+            assert_eq!(
+                BTreeSet::new(),
+                eav_storage
+                    .fetch_eavi(None, None, None, IndexQuery::default())
+                    .expect("could not fetch eav")
+            );
+        }
     }
     pub fn test_one_to_many<A, S>(mut eav_storage: S)
     where
@@ -489,6 +527,106 @@ impl EavTestSuite {
         );
     }
 
+    pub fn test_query<A, S>(mut eav_storage: S)
+    where
+        A: AddressableContent + Clone,
+        S: EntityAttributeValueStorage,
+    {
+        let content = |s: &str| {
+            A::try_from_content(&Content::from(RawString::from(s)))
+                .expect("could not create AddressableContent from Content")
+                .address()
+        };
+        let (foo, bar, baz, qux) = (
+            content("foo"),
+            content("bar"),
+            content("baz"),
+            content("qux"),
+        );
+        for (entity, attribute, value, index) in vec![
+            (&foo, "link__a", &baz, 1),
+            (&foo, "link__b", &qux, 2),
+            (&foo, "removed_link__a", &baz, 3),
+            (&bar, "link__a", &baz, 4),
+            (&foo, "link__a", &baz, 5),
+        ] {
+            eav_storage
+                .add_eavi(
+                    &EntityAttributeValueIndex::new_with_index(
+                        entity,
+                        &attribute.to_string(),
+                        value,
+                        index,
+                    )
+                    .expect("could not create EAV"),
+                )
+                .expect("could not add eav");
+        }
+        let indices = |query: EavQuery| -> Vec<i64> {
+            eav_storage
+                .query_eavi(&query)
+                .expect("could not query eavs")
+                .iter()
+                .map(|eavi| eavi.index())
+                .collect()
+        };
+
+        let links_of_foo = EavQuery::new()
+            .with_entity(foo.clone())
+            .with_attribute(AttributeFilter::Prefix("link__".to_string()));
+        assert_eq!(indices(links_of_foo.clone()), vec![1, 2, 5]);
+        assert_eq!(
+            indices(links_of_foo.clone().with_versions(Versions::LatestPerEav)),
+            vec![2, 5]
+        );
+        assert_eq!(
+            indices(
+                links_of_foo
+                    .clone()
+                    .with_versions(Versions::LatestPerEav)
+                    .with_order(IndexOrder::Descending)
+            ),
+            vec![5, 2]
+        );
+        assert_eq!(indices(links_of_foo.with_offset(1).with_limit(1)), vec![2]);
+
+        assert_eq!(
+            indices(EavQuery::new().with_attribute(AttributeFilter::Glob("*link__a".to_string()))),
+            vec![1, 3, 4, 5]
+        );
+        assert_eq!(
+            indices(
+                EavQuery::new()
+                    .with_entity(foo.clone())
+                    .with_attribute(AttributeFilter::Glob("link__?".to_string()))
+                    .with_value(qux.clone())
+            ),
+            vec![2]
+        );
+        assert_eq!(
+            indices(
+                EavQuery::new()
+                    .with_attribute(AttributeFilter::Prefix("link__".to_string()))
+                    .with_versions(Versions::LatestPerEntity)
+            ),
+            vec![4, 5]
+        );
+        assert_eq!(
+            indices(
+                EavQuery::new()
+                    .with_attribute(AttributeFilter::Exact("link__a".to_string()))
+                    .with_value(baz.clone())
+                    .with_value(qux.clone())
+                    .with_index_range(Some(2), Some(4))
+            ),
+            vec![4]
+        );
+        assert!(indices(
+            EavQuery::new().with_attribute(AttributeFilter::Exact("link__".to_string()))
+        )
+        .is_empty());
+    }
+
     pub fn test_many_to_one<A, S>(mut eav_storage: S)
     where
         A: AddressableContent + Clone,
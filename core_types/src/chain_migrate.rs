@@ -1,4 +1,69 @@
-//! NOT YET AVAILABLE
+//! A ChainMigrate entry links two source chains of the same agent: the chain of an old DNA
+//! that gets closed and the chain of a new DNA that continues it.
+//! The same entry is the last entry of the old chain and follows the agent entry on the new
+//! chain, so both chains can be recognized as the two sides of the same migration.
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
-pub struct ChainMigrate {}
+use crate::{cas::content::Address, error::HolochainError, json::JsonString};
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, DefaultJson)]
+pub struct ChainMigrate {
+    old_dna_address: Address,
+    new_dna_address: Address,
+    agent: Address,
+}
+
+impl ChainMigrate {
+    pub fn new(old_dna_address: Address, new_dna_address: Address, agent: Address) -> Self {
+        ChainMigrate {
+            old_dna_address,
+            new_dna_address,
+            agent,
+        }
+    }
+
+    /// Address of the DNA whose chain got closed
+    pub fn old_dna_address(&self) -> &Address {
+        &self.old_dna_address
+    }
+
+    /// Address of the DNA whose chain continues the closed chain
+    pub fn new_dna_address(&self) -> &Address {
+        &self.new_dna_address
+    }
+
+    /// Address of the agent on the closed chain
+    pub fn agent(&self) -> &Address {
+        &self.agent
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    pub fn test_chain_migrate() -> ChainMigrate {
+        ChainMigrate::new(
+            Address::from("old_dna"),
+            Address::from("new_dna"),
+            Address::from("agent"),
+        )
+    }
+
+    #[test]
+    fn chain_migrate_smoke_test() {
+        let migrate = test_chain_migrate();
+        assert_eq!(migrate.old_dna_address(), &Address::from("old_dna"));
+        assert_eq!(migrate.new_dna_address(), &Address::from("new_dna"));
+        assert_eq!(migrate.agent(), &Address::from("agent"));
+    }
+
+    #[test]
+    fn chain_migrate_json_round_trip() {
+        let migrate = test_chain_migrate();
+        assert_eq!(
+            ChainMigrate::try_from(JsonString::from(migrate.clone())).unwrap(),
+            migrate
+        );
+    }
+}
@@ -227,6 +227,28 @@ impl Dna {
         None
     }
 
+    /// Return the property with the given name, or the whole properties object
+    /// if no name is given.
+    pub fn get_property(&self, name: Option<&str>) -> Option<&Value> {
+        match name {
+            Some(name) => self.properties.get(name),
+            None => Some(&self.properties),
+        }
+    }
+
+    /// Replace the top-level properties that are set in `overrides`, keeping all others.
+    /// If either side is not an object, `overrides` replaces the properties completely.
+    pub fn override_properties(&mut self, overrides: &Value) {
+        match (self.properties.as_object_mut(), overrides.as_object()) {
+            (Some(properties), Some(overrides)) => {
+                for (name, value) in overrides {
+                    properties.insert(name.clone(), value.clone());
+                }
+            }
+            _ => self.properties = overrides.clone(),
+        }
+    }
+
     pub fn multihash(&self) -> Result<Vec<u8>, HolochainError> {
         let s = String::from(JsonString::from(self.to_owned()));
         multihash::encode(multihash::Hash::SHA2256, &s.into_bytes())
@@ -325,6 +347,30 @@ pub mod tests {
         assert_eq!(format!("{:?}",dna.to_json_pretty()),"Ok(\"{\\n  \\\"name\\\": \\\"\\\",\\n  \\\"description\\\": \\\"\\\",\\n  \\\"version\\\": \\\"\\\",\\n  \\\"uuid\\\": \\\"00000000-0000-0000-0000-000000000000\\\",\\n  \\\"dna_spec_version\\\": \\\"2.0\\\",\\n  \\\"properties\\\": {},\\n  \\\"zomes\\\": {}\\n}\")")
     }
 
+    #[test]
+    fn test_dna_get_property() {
+        let dna = test_dna();
+        assert_eq!(dna.get_property(Some("test")), Some(&json!("test")));
+        assert_eq!(dna.get_property(Some("missing")), None);
+        assert_eq!(dna.get_property(None), Some(&json!({"test": "test"})));
+    }
+
+    #[test]
+    fn test_dna_override_properties() {
+        let mut dna = test_dna();
+        dna.override_properties(&json!({"other": 42}));
+        assert_eq!(dna.properties, json!({"test": "test", "other": 42}));
+
+        dna.override_properties(&json!({"test": {"nested": true}}));
+        assert_eq!(
+            dna.properties,
+            json!({"test": {"nested": true}, "other": 42})
+        );
+
+        dna.override_properties(&json!("not an object"));
+        assert_eq!(dna.properties, json!("not an object"));
+    }
+
     #[test]
     fn test_dna_get_zome() {
         let dna = test_dna();
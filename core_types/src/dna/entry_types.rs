@@ -158,6 +158,7 @@ mod tests {
     fn can_publish() {
         assert!(Sharing::Public.can_publish());
         assert!(!Sharing::Private.can_publish());
+        assert!(Sharing::Encrypted.can_publish());
     }
 
     #[test]
@@ -13,7 +13,7 @@ use chrono::offset::Utc;
 use objekt;
 use std::{
     cmp::Ordering,
-    collections::BTreeSet,
+    collections::{BTreeSet, HashMap},
     convert::TryInto,
     sync::{Arc, RwLock},
 };
@@ -93,6 +93,224 @@ impl Default for IndexQuery {
     }
 }
 
+/// Which attributes an EavQuery matches
+#[derive(Clone, Debug, PartialEq)]
+pub enum AttributeFilter {
+    Any,
+    Exact(Attribute),
+    /// Attributes starting with the given string, like all `link__` attributes
+    Prefix(String),
+    /// `*` stands for any number of characters and `?` for exactly one.
+    /// Attributes can contain neither of them.
+    Glob(String),
+}
+
+impl AttributeFilter {
+    pub fn matches(&self, attribute: &Attribute) -> bool {
+        match self {
+            AttributeFilter::Any => true,
+            AttributeFilter::Exact(exact) => exact == attribute,
+            AttributeFilter::Prefix(prefix) => attribute.starts_with(prefix.as_str()),
+            AttributeFilter::Glob(glob) => glob_matches(
+                &glob.chars().collect::<Vec<_>>(),
+                &attribute.chars().collect::<Vec<_>>(),
+            ),
+        }
+    }
+
+    /// The part all matching attributes start with, which storages can look up directly
+    pub fn prefix(&self) -> String {
+        match self {
+            AttributeFilter::Any => String::new(),
+            AttributeFilter::Exact(exact) => exact.clone(),
+            AttributeFilter::Prefix(prefix) => prefix.clone(),
+            AttributeFilter::Glob(glob) => glob
+                .chars()
+                .take_while(|c| *c != '*' && *c != '?')
+                .collect(),
+        }
+    }
+}
+
+/// Only ever backtracks to the latest `*`, so matching takes at most glob length times
+/// attribute length steps
+fn glob_matches(glob: &[char], attribute: &[char]) -> bool {
+    let (mut g, mut a) = (0, 0);
+    // position of the latest star in the glob and of what it stands for in the attribute
+    let mut star: Option<(usize, usize)> = None;
+    while a < attribute.len() {
+        match glob.get(g) {
+            Some('*') => {
+                star = Some((g, a));
+                g += 1;
+            }
+            Some(c) if *c == '?' || *c == attribute[a] => {
+                g += 1;
+                a += 1;
+            }
+            _ => match star {
+                Some((star_g, star_a)) => {
+                    star = Some((star_g, star_a + 1));
+                    g = star_g + 1;
+                    a = star_a + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    glob[g..].iter().all(|c| *c == '*')
+}
+
+/// Which of the EAVIs that match an EavQuery it returns
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Versions {
+    All,
+    /// The newest one of every entity, like the current CRUD status of entries
+    LatestPerEntity,
+    /// The newest one of every entity, attribute and value, which is what `fetch_eavi`
+    /// returns without index range
+    LatestPerEav,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IndexOrder {
+    Ascending,
+    Descending,
+}
+
+/// A query for EAVIs that storages can answer without handing out all EAVIs that
+/// share its entity or attribute: EAVIs get filtered, reduced to the requested versions,
+/// ordered by their index and paged, in that order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EavQuery {
+    pub entity: Option<Entity>,
+    pub attribute: AttributeFilter,
+    /// The EAVI has to have one of these values, if given
+    pub values: Option<BTreeSet<Value>>,
+    pub start: Option<Index>,
+    pub end: Option<Index>,
+    pub versions: Versions,
+    pub order: IndexOrder,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+impl Default for EavQuery {
+    fn default() -> EavQuery {
+        EavQuery {
+            entity: None,
+            attribute: AttributeFilter::Any,
+            values: None,
+            start: None,
+            end: None,
+            versions: Versions::All,
+            order: IndexOrder::Ascending,
+            offset: 0,
+            limit: None,
+        }
+    }
+}
+
+impl EavQuery {
+    pub fn new() -> EavQuery {
+        EavQuery::default()
+    }
+
+    pub fn with_entity(mut self, entity: Entity) -> Self {
+        self.entity = Some(entity);
+        self
+    }
+
+    pub fn with_attribute(mut self, attribute: AttributeFilter) -> Self {
+        self.attribute = attribute;
+        self
+    }
+
+    /// Adds a value the EAVIs may have
+    pub fn with_value(mut self, value: Value) -> Self {
+        self.values.get_or_insert_with(BTreeSet::new).insert(value);
+        self
+    }
+
+    pub fn with_index_range(mut self, start: Option<Index>, end: Option<Index>) -> Self {
+        self.start = start;
+        self.end = end;
+        self
+    }
+
+    pub fn with_versions(mut self, versions: Versions) -> Self {
+        self.versions = versions;
+        self
+    }
+
+    pub fn with_order(mut self, order: IndexOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    pub fn with_offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Whether the EAVI passes the filters of this query
+    pub fn matches(&self, eavi: &EntityAttributeValueIndex) -> bool {
+        EntityAttributeValueIndex::filter_on_eav(&eavi.entity(), self.entity.as_ref())
+            && self.attribute.matches(&eavi.attribute())
+            && self
+                .values
+                .as_ref()
+                .map(|values| values.contains(&eavi.value()))
+                .unwrap_or(true)
+            && self
+                .start
+                .map(|start| start <= eavi.index())
+                .unwrap_or(true)
+            && self.end.map(|end| end >= eavi.index()).unwrap_or(true)
+    }
+
+    /// Runs the query on the given candidates. Storages narrow the candidates down with
+    /// their own indices and leave the rest to this.
+    pub fn run<I>(&self, candidates: I) -> Vec<EntityAttributeValueIndex>
+    where
+        I: IntoIterator<Item = EntityAttributeValueIndex>,
+    {
+        let mut eavis: Vec<EntityAttributeValueIndex> = candidates
+            .into_iter()
+            .filter(|eavi| self.matches(eavi))
+            .collect();
+        eavis.sort();
+        eavis.dedup();
+
+        if self.versions != Versions::All {
+            let mut latest: HashMap<(Entity, Option<(Attribute, Value)>), Index> = HashMap::new();
+            let version_key = |eavi: &EntityAttributeValueIndex| match self.versions {
+                Versions::LatestPerEav => (eavi.entity(), Some((eavi.attribute(), eavi.value()))),
+                _ => (eavi.entity(), None),
+            };
+            // sorted by index, so later ones are newer
+            for eavi in eavis.iter() {
+                latest.insert(version_key(eavi), eavi.index());
+            }
+            eavis.retain(|eavi| latest[&version_key(eavi)] == eavi.index());
+        }
+
+        if self.order == IndexOrder::Descending {
+            eavis.reverse();
+        }
+        eavis
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(std::usize::MAX))
+            .collect()
+    }
+}
+
 impl AddressableContent for EntityAttributeValueIndex {
     fn content(&self) -> Content {
         self.to_owned().into()
@@ -197,6 +415,18 @@ pub trait EntityAttributeValueStorage: objekt::Clone + Send + Sync + Debug {
         value: Option<Value>,
         index_query: IndexQuery,
     ) -> Result<BTreeSet<EntityAttributeValueIndex>, HolochainError>;
+    /// Fetch the EntityAttributeValues that match the query, ordered and paged as it asks
+    fn query_eavi(
+        &self,
+        query: &EavQuery,
+    ) -> Result<Vec<EntityAttributeValueIndex>, HolochainError>;
+    /// Removes the given EntityAttributeValueIndex, if it is stored.
+    /// Only meant for dropping meta data that is held for others.
+    fn remove_eavi(&mut self, eav: &EntityAttributeValueIndex) -> Result<(), HolochainError>;
+    /// Frees the space removed EAVs still take up, for implementations that keep it around.
+    fn compact(&mut self) -> Result<(), HolochainError> {
+        Ok(())
+    }
 }
 
 clone_trait_object!(EntityAttributeValueStorage);
@@ -291,6 +521,22 @@ impl EntityAttributeValueStorage for ExampleEntityAttributeValueStorage {
 
         Ok(filtered)
     }
+
+    fn query_eavi(
+        &self,
+        query: &EavQuery,
+    ) -> Result<Vec<EntityAttributeValueIndex>, HolochainError> {
+        Ok(query.run(self.storage.read()?.iter().cloned()))
+    }
+
+    fn remove_eavi(&mut self, eav: &EntityAttributeValueIndex) -> Result<(), HolochainError> {
+        let mut map = self.storage.write()?;
+        // EAVIs are ordered by their index only, so make sure it is the same EAVI
+        if map.get(eav) == Some(eav) {
+            map.remove(eav);
+        }
+        Ok(())
+    }
 }
 
 pub fn get_latest(
@@ -465,6 +711,35 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn example_eav_query() {
+        EavTestSuite::test_query::<ExampleAddressableContent, ExampleEntityAttributeValueStorage>(
+            test_eav_storage(),
+        );
+    }
+
+    #[test]
+    fn attribute_filter_globs() {
+        let glob = |glob: &str, attribute: &str| {
+            AttributeFilter::Glob(glob.to_string()).matches(&attribute.to_string())
+        };
+        assert!(glob("link__*", "link__"));
+        assert!(glob("link__*", "link__friends"));
+        assert!(glob("*__friends", "link__friends"));
+        assert!(glob("link__fr?ends", "link__friends"));
+        assert!(!glob("link__fr?ends", "link__frends"));
+        assert!(!glob("link__*", "removed_link__friends"));
+        assert!(glob("*link__*s", "removed_link__friends"));
+        assert!(!glob("link__*s?", "link__friends"));
+        // a glob full of stars that can't match doesn't take exponential time
+        let attribute = "a".repeat(100);
+        assert!(!glob(&format!("{}b", "*a".repeat(20)), &attribute));
+        assert_eq!(
+            AttributeFilter::Glob("link__f*s".to_string()).prefix(),
+            "link__f".to_string()
+        );
+    }
+
     #[test]
     /// show AddressableContent implementation
     fn addressable_content_test() {
@@ -5,6 +5,7 @@ use crate::{
     error::HolochainError,
     json::JsonString,
 };
+use uuid::Uuid;
 
 pub type CapTokenValue = Address;
 
@@ -23,23 +24,32 @@ impl CapToken {
     }
 }
 
-/// System entry to hold a capabilities granted by the callee
+/// System entry to hold a capabilities granted by the callee.
+/// The random nonce makes every grant, and so its token, unique: tokens can't be guessed
+/// from the capability name and re-granting a revoked capability issues a fresh token.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, DefaultJson)]
 pub struct CapTokenGrant {
+    cap_name: String,
     assignees: Option<Vec<Address>>,
+    nonce: String,
 }
 
 impl CapTokenGrant {
-    fn new(assignees: Option<Vec<Address>>) -> Self {
-        CapTokenGrant { assignees }
+    fn new(cap_name: &str, assignees: Option<Vec<Address>>) -> Self {
+        CapTokenGrant {
+            cap_name: cap_name.to_string(),
+            assignees,
+            nonce: Uuid::new_v4().to_string(),
+        }
     }
 
     pub fn create(
+        cap_name: &str,
         cap_type: CapabilityType,
         assignees: Option<Vec<Address>>,
     ) -> Result<Self, HolochainError> {
         let assignees = CapTokenGrant::valid(cap_type, assignees)?;
-        Ok(CapTokenGrant::new(assignees))
+        Ok(CapTokenGrant::new(cap_name, assignees))
     }
 
     // internal check that type and assignees are valid for create
@@ -69,12 +79,17 @@ impl CapTokenGrant {
     }
 
     // the token value is address of the entry, so we can just build it
-    // and take the address.
+    // and take the address. The nonce makes it unique per grant.
     pub fn token(&self) -> CapTokenValue {
         let addr: Address = Entry::CapTokenGrant((*self).clone()).address();
         addr
     }
 
+    /// name of the capability, as declared in the zome, that this grant gives access to
+    pub fn cap_name(&self) -> String {
+        self.cap_name.clone()
+    }
+
     pub fn cap_type(&self) -> CapabilityType {
         match self.assignees() {
             None => CapabilityType::Public,
@@ -92,7 +107,9 @@ impl CapTokenGrant {
         self.assignees.clone()
     }
 
-    /// verifies that this grant is valid for a given requester and token value
+    /// verifies that this grant is valid for a given requester and token value.
+    /// Transferable tokens work for whoever presents them, assigned ones only for calls
+    /// from one of the assignees, so calls from unknown callers fail.
     pub fn verify(
         &self,
         token: CapTokenValue,
@@ -103,9 +120,6 @@ impl CapTokenGrant {
         if cap_type == CapabilityType::Public {
             return true;
         }
-        if !from.is_some() {
-            return false;
-        }
 
         if self.token() != token {
             return false;
@@ -113,17 +127,13 @@ impl CapTokenGrant {
 
         // TODO: CallSignature check against Address
 
-        match self.cap_type() {
+        match cap_type {
             CapabilityType::Public => true,
             CapabilityType::Transferable => true,
-            CapabilityType::Assigned => {
-                // unwraps are safe because type comes from the shape of
-                // the assignee, and the from must some by the check above.
-                if !self.assignees().unwrap().contains(&from.unwrap()) {
-                    return false;
-                }
-                true
-            }
+            CapabilityType::Assigned => match from {
+                Some(from) => self.assignees().unwrap_or_default().contains(&from),
+                None => false,
+            },
         }
     }
 }
@@ -134,16 +144,25 @@ pub mod tests {
 
     #[test]
     fn test_new_cap_token_grant_entry() {
-        let grant = CapTokenGrant::new(None);
+        let grant = CapTokenGrant::new("foo", None);
         assert_eq!(grant.cap_type(), CapabilityType::Public);
-        let grant = CapTokenGrant::new(Some(Vec::new()));
+        assert_eq!(grant.cap_name(), String::from("foo"));
+        let grant = CapTokenGrant::new("foo", Some(Vec::new()));
         assert_eq!(grant.cap_type(), CapabilityType::Transferable);
         let test_address = Address::new();
-        let grant = CapTokenGrant::new(Some(vec![test_address.clone()]));
+        let grant = CapTokenGrant::new("foo", Some(vec![test_address.clone()]));
         assert_eq!(grant.cap_type(), CapabilityType::Assigned);
         assert_eq!(grant.assignees().unwrap()[0], test_address)
     }
 
+    #[test]
+    fn test_cap_token_grants_get_unique_tokens() {
+        let grant1 = CapTokenGrant::create("foo", CapabilityType::Transferable, None).unwrap();
+        let grant2 = CapTokenGrant::create("foo", CapabilityType::Transferable, None).unwrap();
+        assert_ne!(grant1.token(), grant2.token());
+        assert_eq!(grant1.token(), grant1.clone().token());
+    }
+
     #[test]
     fn test_cap_grant_valid() {
         assert!(CapTokenGrant::valid(CapabilityType::Public, None).is_ok());
@@ -161,28 +180,35 @@ pub mod tests {
 
     #[test]
     fn test_create_cap_token_grant_entry() {
-        let maybe_grant = CapTokenGrant::create(CapabilityType::Public, None);
+        let maybe_grant = CapTokenGrant::create("foo", CapabilityType::Public, None);
         assert!(maybe_grant.is_ok());
         let grant = maybe_grant.unwrap();
         assert_eq!(grant.cap_type(), CapabilityType::Public);
 
-        let maybe_grant = CapTokenGrant::create(CapabilityType::Transferable, Some(Vec::new()));
+        let maybe_grant =
+            CapTokenGrant::create("foo", CapabilityType::Transferable, Some(Vec::new()));
         assert!(maybe_grant.is_ok());
         let grant = maybe_grant.unwrap();
         assert_eq!(grant.cap_type(), CapabilityType::Transferable);
 
         let test_address = Address::new();
 
-        let maybe_grant =
-            CapTokenGrant::create(CapabilityType::Public, Some(vec![test_address.clone()]));
+        let maybe_grant = CapTokenGrant::create(
+            "foo",
+            CapabilityType::Public,
+            Some(vec![test_address.clone()]),
+        );
         assert!(maybe_grant.is_err());
-        let maybe_grant = CapTokenGrant::create(CapabilityType::Transferable, None);
+        let maybe_grant = CapTokenGrant::create("foo", CapabilityType::Transferable, None);
         assert!(maybe_grant.is_ok());
         let grant = maybe_grant.unwrap();
         assert_eq!(grant.cap_type(), CapabilityType::Transferable);
 
-        let maybe_grant =
-            CapTokenGrant::create(CapabilityType::Assigned, Some(vec![test_address.clone()]));
+        let maybe_grant = CapTokenGrant::create(
+            "foo",
+            CapabilityType::Assigned,
+            Some(vec![test_address.clone()]),
+        );
         assert!(maybe_grant.is_ok());
         let grant = maybe_grant.unwrap();
         assert_eq!(grant.cap_type(), CapabilityType::Assigned);
@@ -195,7 +221,7 @@ pub mod tests {
         let test_address2 = Address::from("some other identity");
         let test_call_signature = &CallSignature {};
 
-        let grant = CapTokenGrant::create(CapabilityType::Public, None).unwrap();
+        let grant = CapTokenGrant::create("foo", CapabilityType::Public, None).unwrap();
         let token = grant.token();
         assert!(grant.verify(token.clone(), None, test_call_signature));
         assert!(grant.verify(
@@ -205,9 +231,9 @@ pub mod tests {
         ));
         assert!(grant.verify(Address::from("Bad Token"), None, test_call_signature));
 
-        let grant = CapTokenGrant::create(CapabilityType::Transferable, None).unwrap();
+        let grant = CapTokenGrant::create("foo", CapabilityType::Transferable, None).unwrap();
         let token = grant.token();
-        assert!(!grant.verify(token.clone(), None, test_call_signature));
+        assert!(grant.verify(token.clone(), None, test_call_signature));
         assert!(grant.verify(
             token.clone(),
             Some(test_address1.clone()),
@@ -224,9 +250,12 @@ pub mod tests {
             test_call_signature
         ));
 
-        let grant =
-            CapTokenGrant::create(CapabilityType::Assigned, Some(vec![test_address1.clone()]))
-                .unwrap();
+        let grant = CapTokenGrant::create(
+            "foo",
+            CapabilityType::Assigned,
+            Some(vec![test_address1.clone()]),
+        )
+        .unwrap();
         let token = grant.token();
         assert!(!grant.verify(token.clone(), None, test_call_signature));
         assert!(grant.verify(
@@ -6,8 +6,8 @@ use crate::{
     error::error::HolochainError,
     json::{default_try_from_json, JsonString},
 };
-use multihash::{encode, Hash};
-use rust_base58::ToBase58;
+use multihash::{decode, encode, Hash};
+use rust_base58::{FromBase58, ToBase58};
 use std::{convert::TryFrom, fmt};
 
 // HashString newtype for String
@@ -66,6 +66,14 @@ impl HashString {
     pub fn encode_from_json_string(json_string: JsonString, hash_type: Hash) -> HashString {
         HashString::encode_from_str(&String::from(json_string), hash_type)
     }
+
+    /// the hash function this string was made with, None if it is not a b58 multihash
+    pub fn hash_type(&self) -> Option<Hash> {
+        self.0
+            .from_base58()
+            .ok()
+            .and_then(|bytes| decode(&bytes).ok().map(|multihash| multihash.alg))
+    }
 }
 
 #[cfg(test)]
@@ -129,6 +137,18 @@ pub mod tests {
         );
     }
 
+    #[test]
+    /// show that the hash function can be read back from a hash
+    fn hash_type_test() {
+        assert_eq!(test_hash().hash_type(), Some(Hash::SHA2256));
+        assert_eq!(
+            HashString::encode_from_str("test data", Hash::SHA3512).hash_type(),
+            Some(Hash::SHA3512)
+        );
+        assert_eq!(HashString::from("JournalHead").hash_type(), None);
+        assert_eq!(HashString::new().hash_type(), None);
+    }
+
     #[test]
     /// known hash for a serializable something
     fn can_serialize_to_b58_hash() {
@@ -65,6 +65,19 @@ impl TryFrom<JsonString> for u32 {
     }
 }
 
+impl From<bool> for JsonString {
+    fn from(b: bool) -> JsonString {
+        default_to_json(b)
+    }
+}
+
+impl TryFrom<JsonString> for bool {
+    type Error = HolochainError;
+    fn try_from(j: JsonString) -> Result<Self, Self::Error> {
+        default_try_from_json(j)
+    }
+}
+
 impl From<serde_json::Value> for JsonString {
     fn from(v: serde_json::Value) -> JsonString {
         JsonString::from(v.to_string())
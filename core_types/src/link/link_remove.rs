@@ -1,2 +1,101 @@
-#[derive(Clone, Debug, Deserialize, Serialize)]
-pub struct LinkRemove {}
+use crate::{
+    cas::content::Address,
+    error::HolochainError,
+    json::JsonString,
+    link::{Link, LinkActionKind},
+};
+
+//-------------------------------------------------------------------------------------------------
+// LinkRemove
+//-------------------------------------------------------------------------------------------------
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, DefaultJson)]
+pub struct LinkRemove {
+    action_kind: LinkActionKind,
+    link: Link,
+}
+
+impl LinkRemove {
+    pub fn new(base: &Address, target: &Address, tag: &str) -> Self {
+        LinkRemove {
+            action_kind: LinkActionKind::DELETE,
+            link: Link::new(base, target, tag),
+        }
+    }
+
+    pub fn action_kind(&self) -> &LinkActionKind {
+        &self.action_kind
+    }
+
+    pub fn link(&self) -> &Link {
+        &self.link
+    }
+
+    pub fn from_link(link: &Link) -> Self {
+        LinkRemove {
+            action_kind: LinkActionKind::DELETE,
+            link: link.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use crate::{
+        cas::content::AddressableContent,
+        entry::{test_entry_a, test_entry_b, Entry},
+        json::JsonString,
+        link::{link_remove::LinkRemove, tests::example_link, LinkActionKind},
+    };
+    use std::convert::TryFrom;
+
+    pub fn example_link_remove() -> LinkRemove {
+        let link = example_link();
+        LinkRemove::new(link.base(), link.target(), link.tag())
+    }
+
+    pub fn test_link_remove_entry() -> Entry {
+        Entry::LinkRemove(example_link_remove())
+    }
+
+    pub fn test_link_remove_entry_json_string() -> JsonString {
+        JsonString::from(format!(
+            "{{\"LinkRemove\":{{\"action_kind\":\"DELETE\",\"link\":{{\"base\":\"{}\",\"target\":\"{}\",\"tag\":\"foo-tag\"}}}}}}",
+            test_entry_a().address(),
+            test_entry_b().address(),
+        ))
+    }
+
+    #[test]
+    fn link_remove_action_kind_test() {
+        assert_eq!(&LinkActionKind::DELETE, example_link_remove().action_kind(),);
+    }
+
+    #[test]
+    fn link_remove_link_test() {
+        assert_eq!(&example_link(), example_link_remove().link(),);
+        assert_eq!(
+            LinkRemove::from_link(&example_link()),
+            example_link_remove(),
+        );
+    }
+
+    #[test]
+    /// show ToString for LinkRemove
+    fn link_remove_entry_to_string_test() {
+        assert_eq!(
+            test_link_remove_entry_json_string(),
+            JsonString::from(test_link_remove_entry()),
+        );
+    }
+
+    #[test]
+    /// show From<String> for LinkRemove
+    fn link_remove_entry_from_string_test() {
+        assert_eq!(
+            Entry::try_from(test_link_remove_entry_json_string()).unwrap(),
+            test_link_remove_entry(),
+        );
+    }
+}
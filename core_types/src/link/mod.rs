@@ -10,11 +10,17 @@ use crate::{cas::content::Address, error::HolochainError, json::JsonString};
 
 type LinkTag = String;
 
+/// Number of bytes the JSON payload of a link may have
+pub const MAX_LINK_PAYLOAD_SIZE: usize = 1024;
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash, DefaultJson)]
 pub struct Link {
     base: Address,
     target: Address,
     tag: LinkTag,
+    /// JSON the link carries itself, so that apps don't need an entry for small data
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    payload: Option<String>,
 }
 
 impl Link {
@@ -23,6 +29,19 @@ impl Link {
             base: base.to_owned(),
             target: target.to_owned(),
             tag: tag.to_owned(),
+            payload: None,
+        }
+    }
+
+    pub fn new_with_payload(
+        base: &Address,
+        target: &Address,
+        tag: &str,
+        payload: JsonString,
+    ) -> Self {
+        Link {
+            payload: Some(String::from(payload)),
+            ..Link::new(base, target, tag)
         }
     }
 
@@ -38,6 +57,43 @@ impl Link {
     pub fn tag(&self) -> &LinkTag {
         &self.tag
     }
+
+    pub fn payload(&self) -> Option<JsonString> {
+        self.payload.clone().map(JsonString::from)
+    }
+
+    /// Makes sure the payload is JSON that is not bigger than MAX_LINK_PAYLOAD_SIZE
+    pub fn validate_payload(&self) -> Result<(), HolochainError> {
+        let payload = match &self.payload {
+            Some(payload) => payload,
+            None => return Ok(()),
+        };
+        if payload.len() > MAX_LINK_PAYLOAD_SIZE {
+            return Err(HolochainError::ValidationFailed(format!(
+                "Link payload has {} bytes, more than the {} allowed",
+                payload.len(),
+                MAX_LINK_PAYLOAD_SIZE
+            )));
+        }
+        serde_json::from_str::<serde_json::Value>(payload)
+            .map(|_| ())
+            .map_err(|_| HolochainError::ValidationFailed("Link payload is not JSON".to_string()))
+    }
+}
+
+/// How the tag given to get_links selects the links
+#[derive(Deserialize, Debug, Serialize, DefaultJson, Clone, PartialEq, Eq, Hash)]
+pub enum LinkTagMatch {
+    Exact,
+    /// Links whose tag starts with the given one
+    Prefix,
+    /// Links whose tag matches the given regular expression
+    Regex,
+}
+impl Default for LinkTagMatch {
+    fn default() -> Self {
+        LinkTagMatch::Exact
+    }
 }
 
 // HC.LinkAction sync with hdk-rust
@@ -53,8 +109,10 @@ pub mod tests {
     use crate::{
         cas::content::AddressableContent,
         entry::{test_entry_a, test_entry_b},
-        link::{Link, LinkActionKind, LinkTag},
+        json::JsonString,
+        link::{Link, LinkActionKind, LinkTag, MAX_LINK_PAYLOAD_SIZE},
     };
+    use std::convert::TryFrom;
 
     pub fn example_link_tag() -> LinkTag {
         LinkTag::from("foo-tag")
@@ -71,4 +129,38 @@ pub mod tests {
     pub fn example_link_action_kind() -> LinkActionKind {
         LinkActionKind::ADD
     }
+
+    #[test]
+    fn link_payload_test() {
+        assert_eq!(example_link().payload(), None);
+        assert!(example_link().validate_payload().is_ok());
+
+        let link = Link::new_with_payload(
+            &test_entry_a().address(),
+            &test_entry_b().address(),
+            &example_link_tag(),
+            JsonString::from("{\"date\":\"2026-10-15\"}"),
+        );
+        assert_eq!(
+            link.payload(),
+            Some(JsonString::from("{\"date\":\"2026-10-15\"}"))
+        );
+        assert!(link.validate_payload().is_ok());
+        assert_eq!(Link::try_from(JsonString::from(link.clone())), Ok(link));
+
+        let not_json = Link::new_with_payload(
+            &test_entry_a().address(),
+            &test_entry_b().address(),
+            &example_link_tag(),
+            JsonString::from("{date"),
+        );
+        assert!(not_json.validate_payload().is_err());
+        let too_big = Link::new_with_payload(
+            &test_entry_a().address(),
+            &test_entry_b().address(),
+            &example_link_tag(),
+            JsonString::from(format!("\"{}\"", "x".repeat(MAX_LINK_PAYLOAD_SIZE))),
+        );
+        assert!(too_big.validate_payload().is_err());
+    }
 }
@@ -14,6 +14,10 @@ pub struct ValidationPackage {
     pub source_chain_entries: Option<Vec<Entry>>,
     pub source_chain_headers: Option<Vec<ChainHeader>>,
     pub custom: Option<String>,
+    /// Headers of the source's agent entries before `chain_header`, newest first.
+    /// They tell holders which agent key was in effect at the position of the entry.
+    #[serde(default)]
+    pub agent_headers: Vec<ChainHeader>,
 }
 
 impl ValidationPackage {
@@ -23,6 +27,7 @@ impl ValidationPackage {
             source_chain_entries: None,
             source_chain_headers: None,
             custom: None,
+            agent_headers: Vec::new(),
         }
     }
 }
@@ -8,22 +8,32 @@ use crate::{
 };
 use holochain_core_types::{
     cas::content::Address,
-    dna::capabilities::CapabilityCall,
-    entry::Entry,
+    dna::capabilities::{CapabilityCall, CapabilityType},
+    entry::{cap_entries::CapTokenGrant, Entry},
     error::{RibosomeEncodedAllocation, RibosomeEncodingBits, ZomeApiInternalResult},
+    signature::Signature,
     time::Timeout,
 };
-pub use holochain_wasm_utils::api_serialization::validation::*;
+pub use holochain_wasm_utils::api_serialization::{
+    bundle::BundleOnClose, peers::PeerInfo, validation::*, validation_receipt::ValidationReceipt,
+};
 use holochain_wasm_utils::{
     api_serialization::{
+        bundle::StartBundleArgs,
+        capabilities::{GrantCapabilityArgs, ListGrantsResult},
         get_entry::{
             EntryHistory, GetEntryArgs, GetEntryOptions, GetEntryResult, GetEntryResultType,
             StatusRequestKind,
         },
         get_links::{GetLinksArgs, GetLinksOptions, GetLinksResult},
         link_entries::LinkEntriesArgs,
+        peers::GetPeersResult,
+        property::PropertyArgs,
         send::{SendArgs, SendOptions},
-        QueryArgs, QueryArgsNames, QueryArgsOptions, QueryResult, UpdateEntryArgs, ZomeFnCallArgs,
+        sign::{SignArgs, VerifySignatureArgs},
+        validation_receipt::GetValidationReceiptsResult,
+        CommitEntryArgs, QueryArgs, QueryArgsNames, QueryArgsOptions, QueryResult, UpdateEntryArgs,
+        ZomeFnCallArgs,
     },
     holochain_core_types::{
         hash::HashString,
@@ -173,12 +183,6 @@ impl Default for GetEntryMask {
 //    }
 //}
 
-/// Allowed input for close_bundle()
-pub enum BundleOnClose {
-    Commit,
-    Discard,
-}
-
 //--------------------------------------------------------------------------------------------------
 // API FUNCTIONS
 //--------------------------------------------------------------------------------------------------
@@ -196,6 +200,19 @@ pub enum Dispatch {
     RemoveEntry,
     Query,
     Send,
+    Sign,
+    VerifySignature,
+    GrantCapability,
+    ListGrants,
+    RevokeCapability,
+    Property,
+    StartBundle,
+    CloseBundle,
+    UpdateAgent,
+    QueryMigratedChain,
+    RemoveLink,
+    GetPeers,
+    GetValidationReceipts,
 }
 
 impl Dispatch {
@@ -231,6 +248,19 @@ impl Dispatch {
                 Dispatch::RemoveEntry => hc_remove_entry,
                 Dispatch::Query => hc_query,
                 Dispatch::Send => hc_send,
+                Dispatch::Sign => hc_sign,
+                Dispatch::VerifySignature => hc_verify_signature,
+                Dispatch::GrantCapability => hc_grant_capability,
+                Dispatch::ListGrants => hc_list_grants,
+                Dispatch::RevokeCapability => hc_revoke_capability,
+                Dispatch::Property => hc_property,
+                Dispatch::StartBundle => hc_start_bundle,
+                Dispatch::CloseBundle => hc_close_bundle,
+                Dispatch::UpdateAgent => hc_update_agent,
+                Dispatch::QueryMigratedChain => hc_query_migrated_chain,
+                Dispatch::RemoveLink => hc_remove_link,
+                Dispatch::GetPeers => hc_get_peers,
+                Dispatch::GetValidationReceipts => hc_get_validation_receipts,
             })(encoded_input)
         };
 
@@ -302,6 +332,32 @@ impl Dispatch {
 /// # #[no_mangle]
 /// # pub fn hc_send(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
 /// # #[no_mangle]
+/// # pub fn hc_sign(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_verify_signature(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_grant_capability(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_list_grants(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_revoke_capability(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_property(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_start_bundle(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_close_bundle(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_update_agent(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_query_migrated_chain(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_remove_link(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_get_peers(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_get_validation_receipts(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
 /// # pub fn hc_debug(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
 /// # #[no_mangle]
 /// # pub fn hc_get_links(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
@@ -379,6 +435,32 @@ impl Dispatch {
 /// # #[no_mangle]
 /// # pub fn hc_send(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
 /// # #[no_mangle]
+/// # pub fn hc_sign(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_verify_signature(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_grant_capability(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_list_grants(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_revoke_capability(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_property(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_start_bundle(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_close_bundle(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_update_agent(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_query_migrated_chain(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_remove_link(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_get_peers(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_get_validation_receipts(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
 /// # pub fn hc_debug(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
 /// # #[no_mangle]
 /// # pub fn hc_get_links(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
@@ -515,6 +597,18 @@ pub fn commit_entry(entry: &Entry) -> ZomeApiResult<Address> {
     Dispatch::CommitEntry.with_input(entry)
 }
 
+/// Commits an entry of an entry type defined with `Sharing::Encrypted`, like
+/// [commit_entry](fn.commit_entry.html) does.
+/// The entry only gets published encrypted, and only the author and the agents at the
+/// given addresses can decrypt it. Entries of encrypted types committed with `commit_entry`
+/// can only be decrypted by their author.
+pub fn commit_encrypted_entry(entry: &Entry, recipients: Vec<Address>) -> ZomeApiResult<Address> {
+    Dispatch::CommitEntry.with_input(CommitEntryArgs {
+        entry: entry.to_owned(),
+        recipients,
+    })
+}
+
 /// Retrieves latest version of an entry from the local chain or the DHT, by looking it up using
 /// the specified address.
 /// Returns None if no entry exists at the specified address or
@@ -666,16 +760,96 @@ pub fn link_entries<S: Into<String>>(
         base: base.clone(),
         target: target.clone(),
         tag: tag.into(),
+        payload: None,
     })
 }
 
-/// NOT YET AVAILABLE
-// Returns a DNA property, which are defined by the DNA developer.
-// They are custom values that are defined in the DNA file
-// that can be used in the zome code for defining configurable behaviors.
-// (e.g. Name, Language, Description, Author, etc.).
-pub fn property<S: Into<String>>(_name: S) -> ZomeApiResult<String> {
-    Err(ZomeApiError::FunctionNotImplemented)
+/// Works like [link_entries](fn.link_entries.html) but puts a small JSON payload on the link,
+/// which [get_links](fn.get_links.html) returns together with the link.
+/// Payloads bigger than `MAX_LINK_PAYLOAD_SIZE` bytes make the link invalid.
+/// # Examples
+/// ```rust
+/// # extern crate hdk;
+/// # extern crate holochain_core_types;
+/// # use holochain_core_types::json::JsonString;
+/// # use holochain_core_types::cas::content::Address;
+/// # use hdk::error::ZomeApiResult;
+///
+/// # fn main() {
+/// pub fn handle_tag_post(post: Address, date: String) -> ZomeApiResult<()> {
+///     hdk::link_entries_with_payload(
+///         &post,
+///         &post,
+///         format!("date-{}", date),
+///         JsonString::from(format!("{{\"date\":\"{}\"}}", date)),
+///     )
+/// }
+/// # }
+/// ```
+pub fn link_entries_with_payload<S: Into<String>>(
+    base: &Address,
+    target: &Address,
+    tag: S,
+    payload: JsonString,
+) -> Result<(), ZomeApiError> {
+    Dispatch::LinkEntries.with_input(LinkEntriesArgs {
+        base: base.clone(),
+        target: target.clone(),
+        tag: tag.into(),
+        payload: Some(String::from(payload)),
+    })
+}
+
+/// Removes a link between two entries that was created with [link_entries](fn.link_entries.html).
+/// Commits a LinkRemove entry to your local source chain that gets validated through the
+/// `validation` callback of the link's definition, with `EntryAction::Delete` as the action.
+/// DHT nodes keep the link but mark it as removed, so that [get_links](fn.get_links.html) no
+/// longer returns it unless removed links are requested with `LinksStatusRequestKind::Deleted`
+/// or `LinksStatusRequestKind::All`. Removing a link is final: linking the same entries
+/// with the same tag again does not bring it back.
+/// # Examples
+/// ```rust
+/// # extern crate hdk;
+/// # extern crate holochain_core_types;
+/// # use holochain_core_types::cas::content::Address;
+/// # use hdk::error::ZomeApiResult;
+///
+/// # fn main() {
+/// pub fn handle_unlink_post(agent: Address, post: Address) -> ZomeApiResult<()> {
+///     hdk::remove_link(&agent, &post, "authored_posts")
+/// }
+/// # }
+/// ```
+pub fn remove_link<S: Into<String>>(
+    base: &Address,
+    target: &Address,
+    tag: S,
+) -> Result<(), ZomeApiError> {
+    Dispatch::RemoveLink.with_input(LinkEntriesArgs {
+        base: base.clone(),
+        target: target.clone(),
+        tag: tag.into(),
+        payload: None,
+    })
+}
+
+/// Returns a DNA property, which are defined by the DNA developer.
+/// They are custom values that are defined in the DNA file
+/// that can be used in the zome code for defining configurable behaviors.
+/// (e.g. Name, Language, Description, Author, etc.).
+/// Properties can be overridden per instance in the container configuration.
+/// The property is returned as JSON and can be converted into the expected type with `try_into()`.
+/// Fails if the DNA does not define the property.
+pub fn property<S: Into<String>>(name: S) -> ZomeApiResult<JsonString> {
+    Dispatch::Property.with_input(PropertyArgs {
+        name: Some(name.into()),
+    })
+}
+
+/// Returns all DNA properties as one JSON object.
+/// See [property](fn.property.html).
+pub fn properties() -> ZomeApiResult<JsonString> {
+    Dispatch::Property.with_input(PropertyArgs { name: None })
 }
 
 /// Reconstructs an address of the given entry data.
@@ -722,18 +896,61 @@ pub fn entry_address(entry: &Entry) -> ZomeApiResult<Address> {
     Dispatch::EntryAddress.with_input(entry)
 }
 
-/// NOT YET AVAILABLE
-pub fn sign<S: Into<String>>(_doc: S) -> ZomeApiResult<String> {
-    Err(ZomeApiError::FunctionNotImplemented)
+/// Signs the given payload with the private key of the agent running this instance
+/// and returns the (base64 encoded) signature.
+/// This can be used to sign data that does not live on the source chain,
+/// e.g. to hand out receipts that others can check with [verify_signature](fn.verify_signature.html).
+/// What gets signed is the payload behind a prefix that marks it as signed by zome code,
+/// so these signatures can never pass as signatures of chain headers.
+pub fn sign<S: Into<String>>(payload: S) -> ZomeApiResult<String> {
+    Dispatch::Sign
+        .with_input(SignArgs {
+            payload: payload.into(),
+        })
+        .map(|signature: RawString| String::from(signature))
+}
+
+/// Checks that `signature` is a signature of `data` that was created with [sign](fn.sign.html)
+/// and the private key belonging to `pub_key`. The public key is expected in its rendered form, as used for
+/// agent addresses (e.g. [AGENT_ADDRESS](struct.AGENT_ADDRESS.html)).
+/// Returns `Ok(false)` for invalid signatures and an error if `pub_key` can not be parsed.
+pub fn verify_signature<S: Into<String>>(signature: S, data: S, pub_key: S) -> ZomeApiResult<bool> {
+    Dispatch::VerifySignature.with_input(VerifySignatureArgs {
+        pub_key: Address::from(pub_key.into()),
+        payload: data.into(),
+        signature: Signature::from(signature.into()),
+    })
+}
+
+/// Grants access to the functions of the capability `cap_name` of this zome by committing
+/// a [CapTokenGrant](../holochain_core_types/entry/cap_entries/struct.CapTokenGrant.html)
+/// to the local source chain. The capability has to be declared in the DNA with the given
+/// `cap_type`. `Assigned` and `Transferable` grants need a list of `assignees`.
+/// Returns the token, i.e. the address of the grant, which callers have to present
+/// in their [CapabilityCall](../holochain_core_types/dna/capabilities/struct.CapabilityCall.html).
+pub fn grant_capability<S: Into<String>>(
+    cap_name: S,
+    cap_type: CapabilityType,
+    assignees: Option<Vec<Address>>,
+) -> ZomeApiResult<Address> {
+    Dispatch::GrantCapability.with_input(GrantCapabilityArgs {
+        cap_name: cap_name.into(),
+        cap_type,
+        assignees,
+    })
+}
+
+/// Returns all capability grants on the local source chain that have not been revoked.
+pub fn list_grants() -> ZomeApiResult<Vec<CapTokenGrant>> {
+    Dispatch::ListGrants
+        .with_input(JsonString::empty_object())
+        .map(|result: ListGrantsResult| result.grants)
 }
 
-/// NOT YET AVAILABLE
-pub fn verify_signature<S: Into<String>>(
-    _signature: S,
-    _data: S,
-    _pub_key: S,
-) -> ZomeApiResult<bool> {
-    Err(ZomeApiError::FunctionNotImplemented)
+/// Revokes the grant with the given token by committing a deletion of it.
+/// Calls presenting this token will fail from now on.
+pub fn revoke_capability(token: &Address) -> ZomeApiResult<()> {
+    Dispatch::RevokeCapability.with_input(token.to_owned())
 }
 
 /// Commit an entry to your local source chain that "updates" a previous entry, meaning when getting
@@ -749,9 +966,16 @@ pub fn update_entry(new_entry: Entry, address: &Address) -> ZomeApiResult<Addres
     })
 }
 
-/// NOT YET AVAILABLE
+/// Rotates the keys of the agent by committing a new identity entry that replaces the current one.
+/// The new keys are freshly generated from a random seed, which the container keeps in the
+/// agent's keystore. Rotating fails if there is no keystore to keep them in.
+/// The new identity entry gets signed with the current keys so others can verify that it
+/// belongs to the same agent.
+/// All entries committed afterwards get signed with the new keys.
+/// Returns the new agent address, which AGENT_ADDRESS and AGENT_LATEST_HASH will hold from
+/// the next zome call on.
 pub fn update_agent() -> ZomeApiResult<Address> {
-    Err(ZomeApiError::FunctionNotImplemented)
+    Dispatch::UpdateAgent.with_input(JsonString::empty_object())
 }
 
 /// Commit a DeletionEntry to your local source chain that marks an entry as 'deleted' by setting
@@ -766,6 +990,10 @@ pub fn remove_entry(address: &Address) -> ZomeApiResult<()> {
 /// Note: the tag is intended to describe the relationship between the `base` and other entries you wish to lookup.
 /// This function returns a list of addresses of other entries which matched as being linked by the given `tag`.
 /// Links are created using the Zome API function [link_entries](fn.link_entries.html).
+/// With `tag_match` set to `LinkTagMatch::Prefix` or `LinkTagMatch::Regex` in the options,
+/// the tag selects all links whose tag starts with it or matches it, like the tags
+/// `date-2026-` or `date-2026-.*` do for all posts linked with a date in 2026.
+/// The links of the result hold their actual tags and payloads.
 /// If you also need the content of the entry consider using one of the helper functions:
 /// [get_links_result](fn.get_links_result) or [get_links_and_load](fn._get_links_and_load)
 /// # Examples
@@ -954,6 +1182,20 @@ pub fn query_result(
         options,
     })
 }
+
+/// Like [query_result](fn.query_result.html), but queries the closed source chain this
+/// instance's chain got migrated from with a ChainMigrate entry, which belongs to an older
+/// DNA of the same agent. Genesis of the new DNA can use it to re-validate and carry over
+/// data from the old chain. Fails if the instance was not migrated from another chain.
+pub fn query_migrated_chain(
+    entry_type_names: QueryArgsNames,
+    options: QueryArgsOptions,
+) -> ZomeApiResult<QueryResult> {
+    Dispatch::QueryMigratedChain.with_input(QueryArgs {
+        entry_type_names,
+        options,
+    })
+}
 /// Sends a node-to-node message to the given agent, specified by their address.
 /// Addresses of agents can be accessed using [hdk::AGENT_ADDRESS](struct.AGENT_ADDRESS.html).
 /// This works in conjunction with the `receive` callback that has to be defined in the
@@ -999,6 +1241,32 @@ pub fn query_result(
 /// # #[no_mangle]
 /// # pub fn hc_send(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
 /// # #[no_mangle]
+/// # pub fn hc_sign(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_verify_signature(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_grant_capability(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_list_grants(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_revoke_capability(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_property(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_start_bundle(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_close_bundle(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_update_agent(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_query_migrated_chain(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_remove_link(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_get_peers(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_get_validation_receipts(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
 /// # pub fn hc_debug(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
 /// # #[no_mangle]
 /// # pub fn hc_get_links(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
@@ -1044,12 +1312,43 @@ pub fn send(to_agent: Address, payload: String, timeout: Timeout) -> ZomeApiResu
     })
 }
 
-/// NOT YET AVAILABLE
-pub fn start_bundle(_timeout: usize, _user_param: serde_json::Value) -> ZomeApiResult<()> {
-    Err(ZomeApiError::FunctionNotImplemented)
+/// Returns the peers of this DNA the node knows about, with their location on the
+/// address space, how far around it they hold entries and when we last heard from them.
+pub fn get_peers() -> ZomeApiResult<Vec<PeerInfo>> {
+    Dispatch::GetPeers
+        .with_input(JsonString::empty_object())
+        .map(|result: GetPeersResult| result.peers)
+}
+
+/// Returns the validation receipts the holders of one of our published entries sent us,
+/// the latest one of each holder. A receipt carries the holder's verdict on the entry,
+/// signed by the holder, so counting the receipts with an `Ok` result tells how many
+/// holders validated the entry.
+pub fn get_validation_receipts(entry_address: &Address) -> ZomeApiResult<Vec<ValidationReceipt>> {
+    Dispatch::GetValidationReceipts
+        .with_input(entry_address.to_owned())
+        .map(|result: GetValidationReceiptsResult| result.receipts)
+}
+
+/// Opens a bundle that groups all following commits of the current zome function call
+/// until [close_bundle](fn.close_bundle.html) gets called.
+/// Entries committed while the bundle is open get validated right away but only reach
+/// the source chain and the DHT once the bundle is closed with `BundleOnClose::Commit`.
+/// If the bundle is not closed within `timeout` milliseconds, or before the zome function
+/// returns, its commits get discarded.
+/// Each zome function call can have one bundle open at a time; other calls running
+/// at the same time are not affected by it.
+pub fn start_bundle(timeout: usize, user_param: serde_json::Value) -> ZomeApiResult<()> {
+    Dispatch::StartBundle.with_input(StartBundleArgs {
+        timeout,
+        user_param,
+    })
 }
 
-/// NOT YET AVAILABLE
-pub fn close_bundle(_action: BundleOnClose) -> ZomeApiResult<()> {
-    Err(ZomeApiError::FunctionNotImplemented)
+/// Closes the open bundle of the current zome function call. `BundleOnClose::Commit` writes
+/// all of its commits to the source chain in one go and publishes them,
+/// `BundleOnClose::Discard` drops them.
+/// Fails if no bundle is open or if the bundle timed out before it could be committed.
+pub fn close_bundle(action: BundleOnClose) -> ZomeApiResult<()> {
+    Dispatch::CloseBundle.with_input(action)
 }
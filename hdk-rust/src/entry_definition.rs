@@ -69,7 +69,9 @@ pub struct ValidatingLinkDefinition {
 ///      It is what must be given as the `entry_type_name` argument when calling [commit_entry](fn.commit_entry.html) and the other data read/write functions.
 /// 2. description: `description` is something that is primarily for human readers of your code, just describe this entry type
 /// 3. sharing: `sharing` defines what distribution over the DHT, or not, occurs with entries of this type, possible values
-///      are defined in the [Sharing](../core_types/entry/dna/zome/entry_types/enum.Sharing.html) enum
+///      are defined in the [Sharing](../core_types/entry/dna/zome/entry_types/enum.Sharing.html) enum.
+///      Entries of `Sharing::Encrypted` types only get published encrypted, for the agents given to
+///      [commit_encrypted_entry](fn.commit_encrypted_entry.html).
 /// 4. native_type: `native_type` references a given Rust struct, which provides a clear schema for entries of this type.
 /// 5. validation_package: `validation_package` is a special identifier, which declares which data is required from peers
 ///      when attempting to validate entries of this type.
@@ -249,7 +251,7 @@ macro_rules! entry {
 ///         when attempting to validate entries of this type.
 ///         Possible values are found within [ValidationPackageDefinition](enum.ValidationPackageDefinition.html)
 /// 5. validation: `validation` is a callback function which will be called any time that a
-///         (DHT) node processes or stores a link of this kind, triggered through the link actions [link_entries](fn.commit_entry.html) and [remove_link]().
+///         (DHT) node processes or stores a link of this kind, triggered through the link actions [link_entries](fn.commit_entry.html) and [remove_link](fn.remove_link.html).
 ///         It always expects three arguments, the first being the base and the second the target of the link.
 ///         The third is the validation `context`, which offers a variety of metadata useful for validation.
 ///         See [ValidationData](struct.ValidationData.html) for more details.
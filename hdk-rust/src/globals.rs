@@ -44,6 +44,12 @@ extern "C" {
 
     pub(crate) fn hc_verify_signature(_: RibosomeEncodingBits) -> RibosomeEncodingBits;
 
+    pub(crate) fn hc_grant_capability(_: RibosomeEncodingBits) -> RibosomeEncodingBits;
+
+    pub(crate) fn hc_list_grants(_: RibosomeEncodingBits) -> RibosomeEncodingBits;
+
+    pub(crate) fn hc_revoke_capability(_: RibosomeEncodingBits) -> RibosomeEncodingBits;
+
     pub(crate) fn hc_link_entries(_: RibosomeEncodingBits) -> RibosomeEncodingBits;
 
     pub(crate) fn hc_get_links(_: RibosomeEncodingBits) -> RibosomeEncodingBits;
@@ -51,4 +57,11 @@ extern "C" {
     pub(crate) fn hc_start_bundle(_: RibosomeEncodingBits) -> RibosomeEncodingBits;
 
     pub(crate) fn hc_close_bundle(_: RibosomeEncodingBits) -> RibosomeEncodingBits;
+
+    pub(crate) fn hc_update_agent(_: RibosomeEncodingBits) -> RibosomeEncodingBits;
+
+    pub(crate) fn hc_query_migrated_chain(_: RibosomeEncodingBits) -> RibosomeEncodingBits;
+    pub(crate) fn hc_remove_link(_: RibosomeEncodingBits) -> RibosomeEncodingBits;
+    pub(crate) fn hc_get_peers(_: RibosomeEncodingBits) -> RibosomeEncodingBits;
+    pub(crate) fn hc_get_validation_receipts(_: RibosomeEncodingBits) -> RibosomeEncodingBits;
 }
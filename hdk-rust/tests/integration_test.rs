@@ -15,7 +15,7 @@ extern crate holochain_core_types_derive;
 
 use hdk::error::{ZomeApiError, ZomeApiResult};
 use holochain_container_api::{error::HolochainResult, *};
-use holochain_core::logger::TestLogger;
+use holochain_core::{agent::keys::test_keypair, logger::TestLogger};
 use holochain_core_types::{
     cas::content::Address,
     crud_status::CrudStatus,
@@ -32,6 +32,7 @@ use holochain_core_types::{
     error::{CoreError, HolochainError, RibosomeEncodedValue, RibosomeEncodingBits},
     hash::HashString,
     json::JsonString,
+    link::Link,
 };
 use holochain_wasm_utils::{
     api_serialization::{
@@ -113,6 +114,21 @@ pub fn hc_verify_signature(_: RibosomeEncodingBits) -> RibosomeEncodingBits {
     RibosomeEncodedValue::Success.into()
 }
 
+#[no_mangle]
+pub fn hc_grant_capability(_: RibosomeEncodingBits) -> RibosomeEncodingBits {
+    RibosomeEncodedValue::Success.into()
+}
+
+#[no_mangle]
+pub fn hc_list_grants(_: RibosomeEncodingBits) -> RibosomeEncodingBits {
+    RibosomeEncodedValue::Success.into()
+}
+
+#[no_mangle]
+pub fn hc_revoke_capability(_: RibosomeEncodingBits) -> RibosomeEncodingBits {
+    RibosomeEncodedValue::Success.into()
+}
+
 #[no_mangle]
 pub fn hc_link_entries(_: RibosomeEncodingBits) -> RibosomeEncodingBits {
     RibosomeEncodedValue::Success.into()
@@ -133,6 +149,31 @@ pub fn hc_close_bundle(_: RibosomeEncodingBits) -> RibosomeEncodingBits {
     RibosomeEncodedValue::Success.into()
 }
 
+#[no_mangle]
+pub fn hc_update_agent(_: RibosomeEncodingBits) -> RibosomeEncodingBits {
+    RibosomeEncodedValue::Success.into()
+}
+
+#[no_mangle]
+pub fn hc_query_migrated_chain(_: RibosomeEncodingBits) -> RibosomeEncodingBits {
+    RibosomeEncodedValue::Success.into()
+}
+
+#[no_mangle]
+pub fn hc_remove_link(_: RibosomeEncodingBits) -> RibosomeEncodingBits {
+    RibosomeEncodedValue::Success.into()
+}
+
+#[no_mangle]
+pub fn hc_get_peers(_: RibosomeEncodingBits) -> RibosomeEncodingBits {
+    RibosomeEncodedValue::Success.into()
+}
+
+#[no_mangle]
+pub fn hc_get_validation_receipts(_: RibosomeEncodingBits) -> RibosomeEncodingBits {
+    RibosomeEncodedValue::Success.into()
+}
+
 #[no_mangle]
 pub fn zome_setup(_: RibosomeEncodingBits) -> RibosomeEncodingBits {
     RibosomeEncodedValue::Success.into()
@@ -221,6 +262,7 @@ fn start_holochain_instance<T: Into<String>>(
         "send_tweet",
         "commit_validation_package_tester",
         "link_two_entries",
+        "remove_link",
         "links_roundtrip_create",
         "links_roundtrip_get",
         "links_roundtrip_get_and_load",
@@ -234,9 +276,16 @@ fn start_holochain_instance<T: Into<String>>(
         "remove_entry_ok",
         "remove_modified_entry_ok",
         "send_message",
+        "sign_and_verify",
+        "grant_and_revoke_capability",
+        "get_property",
+        "commit_in_bundles",
+        "update_agent",
+        "query_migrated_chain",
     ]);
     let mut dna = create_test_dna_with_defs("test_zome", defs, &wasm);
     dna.uuid = uuid.into();
+    dna.properties = json!({"language": "en"});
 
     // TODO: construct test DNA using the auto-generated JSON feature
     // The code below is fragile!
@@ -502,6 +551,15 @@ fn can_link_entries() {
     assert_eq!(result.unwrap(), JsonString::from(r#"{"Ok":null}"#));
 }
 
+#[test]
+fn can_remove_link() {
+    let (mut hc, _) = start_holochain_instance("can_remove_link", "alice");
+
+    let result = make_test_call(&mut hc, "remove_link", r#"{}"#);
+    assert!(result.is_ok(), "\t result = {:?}", result);
+    assert_eq!(result.unwrap(), JsonString::from(r#"{"Ok":null}"#));
+}
+
 #[test]
 #[cfg(not(windows))]
 fn can_roundtrip_links() {
@@ -558,10 +616,14 @@ fn can_roundtrip_links() {
             .into(),
         );
 
-        let expected: Result<GetLinksResult, HolochainError> = Ok(GetLinksResult::new(vec![
-            address_1.clone(),
-            address_2.clone(),
-        ]));
+        let link_1 = Link::new(&address, &address_1, "test-tag");
+        let link_2 = Link::new(&address, &address_2, "test-tag");
+
+        let expected: Result<GetLinksResult, HolochainError> =
+            Ok(GetLinksResult::from_links(vec![
+                link_1.clone(),
+                link_2.clone(),
+            ]));
         let expected_entries: ZomeApiResult<Vec<ZomeApiResult<Entry>>> =
             Ok(vec![Ok(entry_1.clone()), Ok(entry_2.clone())]);
 
@@ -574,7 +636,7 @@ fn can_roundtrip_links() {
         let entries_ordering1: bool = entries_result_string == JsonString::from(expected_entries);
 
         let expected: Result<GetLinksResult, HolochainError> =
-            Ok(GetLinksResult::new(vec![address_2, address_1]));
+            Ok(GetLinksResult::from_links(vec![link_2, link_1]));
 
         let expected_entries: ZomeApiResult<Vec<ZomeApiResult<Entry>>> =
             Ok(vec![Ok(entry_2.clone()), Ok(entry_1.clone())]);
@@ -741,3 +803,75 @@ fn can_send_and_receive() {
     let expected: ZomeApiResult<String> = Ok(String::from("Received: TEST"));
     assert_eq!(result.unwrap(), JsonString::from(expected),);
 }
+
+#[test]
+fn can_sign_and_verify() {
+    let (mut hc, _) = start_holochain_instance("can_sign_and_verify", "alice");
+    let result = make_test_call(&mut hc, "sign_and_verify", r#"{"payload": "receipt"}"#);
+    assert!(result.is_ok(), "result = {:?}", result);
+
+    let expected: ZomeApiResult<bool> = Ok(true);
+    assert_eq!(result.unwrap(), JsonString::from(expected));
+}
+
+#[test]
+fn can_grant_and_revoke_capabilities() {
+    let (mut hc, _) = start_holochain_instance("can_grant_and_revoke_capabilities", "alice");
+    let result = make_test_call(&mut hc, "grant_and_revoke_capability", r#"{}"#);
+    assert!(result.is_ok(), "result = {:?}", result);
+
+    let expected: ZomeApiResult<bool> = Ok(true);
+    assert_eq!(result.unwrap(), JsonString::from(expected));
+}
+
+#[test]
+fn can_get_dna_property() {
+    let (mut hc, _) = start_holochain_instance("can_get_dna_property", "alice");
+    let result = make_test_call(&mut hc, "get_property", r#"{"name": "language"}"#);
+    assert!(result.is_ok(), "result = {:?}", result);
+
+    let expected: ZomeApiResult<JsonString> = Ok(JsonString::from(json!("en")));
+    assert_eq!(result.unwrap(), JsonString::from(expected));
+
+    let result = make_test_call(&mut hc, "get_property", r#"{"name": "missing"}"#);
+    assert!(result.unwrap().to_string().contains("not found"));
+}
+
+#[test]
+fn can_commit_in_bundles() {
+    let (mut hc, _) = start_holochain_instance("can_commit_in_bundles", "alice");
+    let result = make_test_call(&mut hc, "commit_in_bundles", r#"{}"#);
+    assert!(result.is_ok(), "result = {:?}", result);
+
+    let expected: ZomeApiResult<bool> = Ok(true);
+    assert_eq!(result.unwrap(), JsonString::from(expected));
+}
+
+#[test]
+fn can_update_agent() {
+    let (mut hc, _) = start_holochain_instance("can_update_agent", "alice");
+    let result = make_test_call(&mut hc, "update_agent", r#"{}"#);
+    assert!(result.is_ok(), "result = {:?}", result);
+
+    // rotated keys are freshly generated, so all we know is that the address changed
+    let result = result.unwrap().to_string();
+    assert!(result.contains("\"Ok\""), "result = {:?}", result);
+    assert!(!result.contains(&String::from(test_keypair("alice").address())));
+
+    // the next call signs its commits with the new keys
+    let result = make_test_call(&mut hc, "commit_in_bundles", r#"{}"#);
+    let expected: ZomeApiResult<bool> = Ok(true);
+    assert_eq!(result.unwrap(), JsonString::from(expected));
+}
+
+#[test]
+fn query_migrated_chain_fails_without_migration() {
+    let (mut hc, _) =
+        start_holochain_instance("query_migrated_chain_fails_without_migration", "alice");
+    let result = make_test_call(&mut hc, "query_migrated_chain", r#"{}"#);
+    assert!(result.is_ok(), "result = {:?}", result);
+    assert!(result
+        .unwrap()
+        .to_string()
+        .contains("not migrated from another chain"));
+}
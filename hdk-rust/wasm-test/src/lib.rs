@@ -18,6 +18,7 @@ use handle_crud::{
 };
 use hdk::{
     error::{ZomeApiError, ZomeApiResult},
+    BundleOnClose,
 };
 use holochain_wasm_utils::{
     api_serialization::{
@@ -27,7 +28,7 @@ use holochain_wasm_utils::{
     },
     holochain_core_types::{
         cas::content::{Address, AddressableContent},
-        dna::entry_types::Sharing,
+        dna::{capabilities::CapabilityType, entry_types::Sharing},
         entry::{
             entry_type::{AppEntryType, EntryType},
             AppEntryValue, Entry,
@@ -152,6 +153,29 @@ fn handle_link_two_entries() -> ZomeApiResult<()> {
     hdk::link_entries(&entry_1.address(), &entry_2.address(), "test-tag")
 }
 
+fn handle_remove_link() -> ZomeApiResult<()> {
+    let entry_1 = Entry::App(
+        "testEntryType".into(),
+        EntryStruct {
+            stuff: "entry1".into(),
+        }
+        .into(),
+    );
+    hdk::commit_entry(&entry_1)?;
+
+    let entry_2 = Entry::App(
+        "testEntryType".into(),
+        EntryStruct {
+            stuff: "entry2".into(),
+        }
+        .into(),
+    );
+    hdk::commit_entry(&entry_2)?;
+
+    hdk::link_entries(&entry_1.address(), &entry_2.address(), "test-tag")?;
+    hdk::remove_link(&entry_1.address(), &entry_2.address(), "test-tag")
+}
+
 fn handle_links_roundtrip_create() -> ZomeApiResult<Address> {
     let entry_1 = Entry::App(
         "testEntryType".into(),
@@ -418,6 +442,63 @@ fn handle_send_message(to_agent: Address, message: String) -> ZomeApiResult<Stri
     hdk::send(to_agent, message, 60000.into())
 }
 
+fn handle_sign_and_verify(payload: String) -> ZomeApiResult<bool> {
+    let signature = hdk::sign(payload.clone())?;
+    let valid = hdk::verify_signature(
+        signature.clone(),
+        payload,
+        hdk::AGENT_ADDRESS.to_string(),
+    )?;
+    let forged = hdk::verify_signature(
+        signature,
+        "other".to_string(),
+        hdk::AGENT_ADDRESS.to_string(),
+    )?;
+    Ok(valid && !forged)
+}
+
+fn handle_grant_and_revoke_capability() -> ZomeApiResult<bool> {
+    let token = hdk::grant_capability("test_cap", CapabilityType::Public, None)?;
+    let listed = hdk::list_grants()?.iter().any(|grant| grant.token() == token);
+    hdk::revoke_capability(&token)?;
+    let revoked = hdk::list_grants()?.iter().all(|grant| grant.token() != token);
+    Ok(listed && revoked)
+}
+
+fn handle_get_property(name: String) -> ZomeApiResult<JsonString> {
+    hdk::property(name)
+}
+
+fn handle_commit_in_bundles() -> ZomeApiResult<bool> {
+    let entry = Entry::App(
+        "testEntryType".into(),
+        TestEntryType {
+            stuff: "bundled".into(),
+        }
+        .into(),
+    );
+
+    hdk::start_bundle(10000, serde_json::Value::Null)?;
+    let address = hdk::commit_entry(&entry)?;
+    hdk::close_bundle(BundleOnClose::Discard)?;
+    let discarded = hdk::get_entry(&address)?.is_none();
+
+    hdk::start_bundle(10000, serde_json::Value::Null)?;
+    hdk::commit_entry(&entry)?;
+    hdk::close_bundle(BundleOnClose::Commit)?;
+    let committed = hdk::get_entry(&address)?.is_some();
+
+    Ok(discarded && committed)
+}
+
+fn handle_update_agent() -> ZomeApiResult<Address> {
+    hdk::update_agent()
+}
+
+fn handle_query_migrated_chain() -> ZomeApiResult<QueryResult> {
+    hdk::query_migrated_chain("testEntryType".into(), QueryArgsOptions::default())
+}
+
 define_zome! {
     entries: [
         entry!(
@@ -554,6 +635,12 @@ define_zome! {
             handler: handle_link_two_entries
         }
 
+        remove_link: {
+            inputs: | |,
+            outputs: |result: ZomeApiResult<()>|,
+            handler: handle_remove_link
+        }
+
         links_roundtrip_create: {
             inputs: | |,
             outputs: |result: ZomeApiResult<Address>|,
@@ -637,6 +724,42 @@ define_zome! {
             outputs: |response: ZomeApiResult<String>|,
             handler: handle_send_message
         }
+
+        sign_and_verify: {
+            inputs: |payload: String|,
+            outputs: |result: ZomeApiResult<bool>|,
+            handler: handle_sign_and_verify
+        }
+
+        grant_and_revoke_capability: {
+            inputs: | |,
+            outputs: |result: ZomeApiResult<bool>|,
+            handler: handle_grant_and_revoke_capability
+        }
+
+        get_property: {
+            inputs: |name: String|,
+            outputs: |result: ZomeApiResult<JsonString>|,
+            handler: handle_get_property
+        }
+
+        commit_in_bundles: {
+            inputs: | |,
+            outputs: |result: ZomeApiResult<bool>|,
+            handler: handle_commit_in_bundles
+        }
+
+        update_agent: {
+            inputs: | |,
+            outputs: |result: ZomeApiResult<Address>|,
+            handler: handle_update_agent
+        }
+
+        query_migrated_chain: {
+            inputs: | |,
+            outputs: |result: ZomeApiResult<QueryResult>|,
+            handler: handle_query_migrated_chain
+        }
     ]
 
     capabilities: {}
@@ -10,6 +10,7 @@ extern crate failure;
 extern crate holochain_core_types;
 extern crate holochain_net_connection;
 extern crate holochain_net_ipc;
+extern crate holochain_sodium;
 #[macro_use]
 extern crate lazy_static;
 extern crate regex;
@@ -30,3 +31,6 @@ pub mod memory_server;
 pub mod memory_worker;
 pub mod p2p_config;
 pub mod p2p_network;
+pub mod sharding;
+pub mod tcp_server;
+pub mod tcp_worker;
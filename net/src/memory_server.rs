@@ -1,5 +1,6 @@
 //! provides fake in-memory p2p worker for use in scenario testing
 
+use crate::sharding::{holders, is_miss, request_holders, PendingRequests};
 use holochain_core_types::cas::content::Address;
 use holochain_net_connection::{
     json_protocol::{
@@ -10,7 +11,7 @@ use holochain_net_connection::{
     NetResult,
 };
 use std::{
-    collections::{hash_map::Entry, HashMap},
+    collections::HashMap,
     convert::TryFrom,
     sync::{mpsc, Mutex, RwLock},
 };
@@ -34,6 +35,12 @@ pub(crate) struct InMemoryServer {
     senders: HashMap<String, mpsc::Sender<Protocol>>,
     // keep track of senders as arrays by dna_address
     senders_by_dna: HashMap<Address, Vec<mpsc::Sender<Protocol>>>,
+    // keep track of agent ids by dna_address, for finding the holders of an address
+    agents_by_dna: HashMap<Address, Vec<String>>,
+    // requests that go to the next holder if theirs misses
+    pending_requests: PendingRequests<JsonProtocol>,
+    // how many agents hold each address
+    redundancy: usize,
     // Unique identifier
     name: String,
     // Keep track of connected clients
@@ -42,11 +49,14 @@ pub(crate) struct InMemoryServer {
 
 impl InMemoryServer {
     /// create a new in-memory network server
-    pub fn new(name: String) -> Self {
+    pub fn new(name: String, redundancy: usize) -> Self {
         //println!("NEW InMemoryServer '{}'", name.clone());
         Self {
             senders: HashMap::new(),
             senders_by_dna: HashMap::new(),
+            agents_by_dna: HashMap::new(),
+            pending_requests: PendingRequests::default(),
+            redundancy,
             name,
             client_count: 0,
         }
@@ -70,6 +80,7 @@ impl InMemoryServer {
             //println!("--- InMemoryServer '{}' CLEAR CHANNELS", self.name.clone());
             self.senders.clear();
             self.senders_by_dna.clear();
+            self.agents_by_dna.clear();
         }
     }
 
@@ -81,18 +92,29 @@ impl InMemoryServer {
         sender: mpsc::Sender<Protocol>,
     ) -> NetResult<()> {
         self.senders
-            .insert(cat_dna_agent(dna_address, agent_id), sender.clone());
-        match self.senders_by_dna.entry(dna_address.to_owned()) {
-            Entry::Occupied(mut e) => {
-                e.get_mut().push(sender.clone());
-            }
-            Entry::Vacant(e) => {
-                e.insert(vec![sender.clone()]);
-            }
-        };
+            .insert(cat_dna_agent(dna_address, agent_id), sender);
+        let agents = self
+            .agents_by_dna
+            .entry(dna_address.to_owned())
+            .or_insert_with(Vec::new);
+        // an agent that registers again only gets its sender replaced
+        if !agents.iter().any(|agent| agent == agent_id) {
+            agents.push(agent_id.to_string());
+        }
+        self.priv_collect_senders(dna_address);
         Ok(())
     }
 
+    /// remove an agent that went away, so that it neither holds data
+    /// nor gets messages any more
+    pub fn unregister(&mut self, dna_address: &Address, agent_id: &str) {
+        self.senders.remove(&cat_dna_agent(dna_address, agent_id));
+        if let Some(agents) = self.agents_by_dna.get_mut(dna_address) {
+            agents.retain(|agent| agent != agent_id);
+        }
+        self.priv_collect_senders(dna_address);
+    }
+
     /// process an incoming message
     pub fn handle(&mut self, data: Protocol) -> NetResult<()> {
         // Debugging code (do not remove)
@@ -163,6 +185,19 @@ impl InMemoryServer {
 
     // -- private -- //
 
+    /// gather the senders of the agents of `dna_address`, for messages to all of them
+    fn priv_collect_senders(&mut self, dna_address: &Address) {
+        let senders = match self.agents_by_dna.get(dna_address) {
+            Some(agents) => agents
+                .iter()
+                .filter_map(|agent| self.senders.get(&cat_dna_agent(dna_address, agent)))
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        };
+        self.senders_by_dna.insert(dna_address.to_owned(), senders);
+    }
+
     /// send a message to the appropriate channel based on dna_address::agent_id
     fn priv_send_one(
         &mut self,
@@ -207,6 +242,94 @@ impl InMemoryServer {
         Ok(())
     }
 
+    /// send a message to the agents responsible for holding `address`
+    fn priv_send_holders(
+        &mut self,
+        dna_address: &Address,
+        address: &str,
+        data: Protocol,
+    ) -> NetResult<()> {
+        let holders: Vec<String> = match self.agents_by_dna.get(dna_address) {
+            Some(agents) => holders(address, agents.iter().map(String::as_str), self.redundancy)
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            None => Vec::new(),
+        };
+        for holder in holders {
+            self.priv_send_one(dna_address, &holder, data.clone())?;
+        }
+        Ok(())
+    }
+
+    /// the holders of `address` a request of `from_agent_id` should go to, in order
+    fn priv_request_holders(
+        &self,
+        dna_address: &Address,
+        address: &str,
+        from_agent_id: &str,
+    ) -> Vec<String> {
+        match self.agents_by_dna.get(dna_address) {
+            Some(agents) => request_holders(
+                address,
+                agents.iter().map(String::as_str),
+                self.redundancy,
+                from_agent_id,
+            )
+            .into_iter()
+            .map(String::from)
+            .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// send a request to the first of the holders we can reach.
+    /// the ones after it get the request if it misses.
+    /// returns false if we could not reach any of them.
+    fn priv_ask_holders(
+        &mut self,
+        dna_address: &Address,
+        from_agent_id: &str,
+        msg_id: &str,
+        msg: JsonProtocol,
+        holders: Vec<String>,
+    ) -> bool {
+        for (index, holder) in holders.iter().enumerate() {
+            if self
+                .priv_send_one(dna_address, holder, msg.clone().into())
+                .is_ok()
+            {
+                self.pending_requests.insert(
+                    from_agent_id,
+                    msg_id,
+                    msg,
+                    holders[index + 1..].to_vec(),
+                );
+                return true;
+            }
+        }
+        false
+    }
+
+    /// when the holder that got a request missed, the next holder gets asked
+    /// instead of the requester getting the result.
+    /// returns false if the result should go to the requester.
+    fn priv_ask_next_holder(
+        &mut self,
+        dna_address: &Address,
+        requester: &str,
+        msg_id: &str,
+        content: &serde_json::Value,
+    ) -> bool {
+        match self.pending_requests.take(requester, msg_id) {
+            Some((request, holders)) => {
+                is_miss(content)
+                    && self.priv_ask_holders(dna_address, requester, msg_id, request, holders)
+            }
+            None => false,
+        }
+    }
+
     /// we received a SendMessage message...
     /// normally this would travel over the network, then
     /// show up as a HandleSend message, fabricate that message && deliver
@@ -232,21 +355,21 @@ impl InMemoryServer {
     }
 
     /// when someone makes a dht data request,
-    /// this in-memory module routes it to the first node connected on that dna.
-    /// this works because we also send store requests to all connected nodes.
+    /// this in-memory module routes it to the closest node holding that address.
+    /// this works because we send store requests to all holders of an address.
     fn priv_handle_get_dht_data(&mut self, msg: &GetDhtData) -> NetResult<()> {
-        match self.senders_by_dna.entry(msg.dna_address.to_owned()) {
-            Entry::Occupied(mut e) => {
-                if !e.get().is_empty() {
-                    let r = &e.get_mut()[0];
-                    // Debugging code (do not remove)
-                    //println!("<<<< InMemoryServer '{}' send: {:?}", self.name.clone(), msg.clone());
-                    r.send(JsonProtocol::HandleGetDhtData(msg.clone()).into())?;
-                    return Ok(());
-                }
-            }
-            _ => (),
-        };
+        let holders = self.priv_request_holders(&msg.dna_address, &msg.address, &msg.from_agent_id);
+        // Debugging code (do not remove)
+        //println!("<<<< InMemoryServer '{}' send: {:?}", self.name.clone(), msg.clone());
+        if self.priv_ask_holders(
+            &msg.dna_address,
+            &msg.from_agent_id,
+            &msg.msg_id,
+            JsonProtocol::HandleGetDhtData(msg.clone()),
+            holders,
+        ) {
+            return Ok(());
+        }
 
         self.priv_send_one(
             &msg.dna_address,
@@ -263,8 +386,12 @@ impl InMemoryServer {
         Ok(())
     }
 
-    /// send back a response to a request for dht data
+    /// send back a response to a request for dht data,
+    /// unless the holder missed and the next one gets asked
     fn priv_handle_handle_get_dht_data_result(&mut self, msg: &DhtData) -> NetResult<()> {
+        if self.priv_ask_next_holder(&msg.dna_address, &msg.agent_id, &msg.msg_id, &msg.content) {
+            return Ok(());
+        }
         self.priv_send_one(
             &msg.dna_address,
             &msg.agent_id,
@@ -273,29 +400,30 @@ impl InMemoryServer {
         Ok(())
     }
 
-    /// on publish meta, we send store requests to all nodes connected on this dna
+    /// on publish, we send store requests to all nodes holding that address
     fn priv_handle_publish_dht_data(&mut self, msg: &DhtData) -> NetResult<()> {
-        self.priv_send_all(
+        self.priv_send_holders(
             &msg.dna_address,
+            &msg.address,
             JsonProtocol::HandleStoreDhtData(msg.clone()).into(),
         )?;
         Ok(())
     }
 
     /// when someone makes a dht meta data request,
-    /// this in-memory module routes it to the first node connected on that dna.
-    /// this works because we also send store requests to all connected nodes.
+    /// this in-memory module routes it to the closest node holding that address.
+    /// this works because we send store requests to all holders of an address.
     fn priv_handle_get_dht_meta(&mut self, msg: &GetDhtMetaData) -> NetResult<()> {
-        match self.senders_by_dna.entry(msg.dna_address.to_owned()) {
-            Entry::Occupied(mut e) => {
-                if !e.get().is_empty() {
-                    let r = &e.get_mut()[0];
-                    r.send(JsonProtocol::HandleGetDhtMeta(msg.clone()).into())?;
-                    return Ok(());
-                }
-            }
-            _ => (),
-        };
+        let holders = self.priv_request_holders(&msg.dna_address, &msg.address, &msg.from_agent_id);
+        if self.priv_ask_holders(
+            &msg.dna_address,
+            &msg.from_agent_id,
+            &msg.msg_id,
+            JsonProtocol::HandleGetDhtMeta(msg.clone()),
+            holders,
+        ) {
+            return Ok(());
+        }
 
         self.priv_send_one(
             &msg.dna_address,
@@ -312,8 +440,12 @@ impl InMemoryServer {
         Ok(())
     }
 
-    /// send back a response to a request for dht meta data
+    /// send back a response to a request for dht meta data,
+    /// unless the holder missed and the next one gets asked
     fn priv_handle_handle_get_dht_meta_result(&mut self, msg: &DhtMetaData) -> NetResult<()> {
+        if self.priv_ask_next_holder(&msg.dna_address, &msg.agent_id, &msg.msg_id, &msg.content) {
+            return Ok(());
+        }
         self.priv_send_one(
             &msg.dna_address,
             &msg.agent_id,
@@ -322,10 +454,11 @@ impl InMemoryServer {
         Ok(())
     }
 
-    /// on publish, we send store requests to all nodes connected on this dna
+    /// on publish, we send store requests to all nodes holding that address
     fn priv_handle_publish_dht_meta(&mut self, msg: &DhtMetaData) -> NetResult<()> {
-        self.priv_send_all(
+        self.priv_send_holders(
             &msg.dna_address,
+            &msg.address,
             JsonProtocol::HandleStoreDhtMeta(msg.clone()).into(),
         )?;
         Ok(())
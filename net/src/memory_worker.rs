@@ -1,6 +1,6 @@
 //! provides fake in-memory p2p worker for use in scenario testing
 
-use crate::memory_server::*;
+use crate::{memory_server::*, sharding::redundancy};
 use holochain_core_types::{cas::content::Address, json::JsonString};
 use holochain_net_connection::{
     json_protocol::JsonProtocol,
@@ -18,6 +18,8 @@ use std::{
 pub struct InMemoryWorker {
     handler: NetHandler,
     receiver_per_dna: HashMap<Address, mpsc::Receiver<Protocol>>,
+    // the agents we registered, they leave the server with us
+    agents: Vec<(Address, String)>,
     server_name: String,
 }
 
@@ -41,6 +43,8 @@ impl NetWorker for InMemoryWorker {
                     Entry::Vacant(e) => {
                         let (tx, rx) = mpsc::channel();
                         server.register(&track_msg.dna_address, &track_msg.agent_id, tx)?;
+                        self.agents
+                            .push((track_msg.dna_address.clone(), track_msg.agent_id.clone()));
                         e.insert(rx);
                     }
                 };
@@ -74,7 +78,8 @@ impl NetWorker for InMemoryWorker {
 }
 
 impl InMemoryWorker {
-    /// create a new memory worker connected to an in-memory server
+    /// create a new memory worker connected to an in-memory server.
+    /// The first worker of a server sets how many agents hold each address.
     pub fn new(handler: NetHandler, backend_config: &JsonString) -> NetResult<Self> {
         // Get server name from config
         let config: serde_json::Value = serde_json::from_str(backend_config.into())?;
@@ -88,7 +93,10 @@ impl InMemoryWorker {
         if !server_map.contains_key(&server_name) {
             server_map.insert(
                 server_name.clone(),
-                Mutex::new(InMemoryServer::new(server_name.clone())),
+                Mutex::new(InMemoryServer::new(
+                    server_name.clone(),
+                    redundancy(&config),
+                )),
             );
         }
         let mut server = server_map
@@ -101,6 +109,7 @@ impl InMemoryWorker {
         Ok(InMemoryWorker {
             handler,
             receiver_per_dna: HashMap::new(),
+            agents: Vec::new(),
             server_name,
         })
     }
@@ -115,6 +124,9 @@ impl Drop for InMemoryWorker {
             .expect("InMemoryServer should exist")
             .lock()
             .unwrap();
+        for (dna_address, agent_id) in self.agents.iter() {
+            server.unregister(dna_address, agent_id);
+        }
         server.clock_out();
     }
 }
@@ -122,7 +134,7 @@ impl Drop for InMemoryWorker {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::p2p_config::P2pConfig;
+    use crate::{p2p_config::P2pConfig, sharding::holders};
 
     use holochain_core_types::cas::content::Address;
     use holochain_net_connection::json_protocol::{
@@ -184,6 +196,190 @@ mod tests {
         memory_worker_1.tick().unwrap();
     }
 
+    /// workers on a memory network with the given redundancy, one per agent
+    fn sharded_workers(
+        agents: &[&str],
+        redundancy: usize,
+    ) -> Vec<(Box<InMemoryWorker>, mpsc::Receiver<Protocol>)> {
+        let memory_config = &JsonString::from(
+            P2pConfig::new_with_unique_memory_backend()
+                .with_redundancy(redundancy)
+                .backend_config
+                .to_string(),
+        );
+        let mut workers: Vec<(Box<InMemoryWorker>, mpsc::Receiver<Protocol>)> = agents
+            .iter()
+            .map(|agent_id| {
+                let (handler_send, handler_recv) = mpsc::channel::<Protocol>();
+                let mut worker = Box::new(
+                    InMemoryWorker::new(
+                        Box::new(move |r| {
+                            handler_send.send(r?)?;
+                            Ok(())
+                        }),
+                        memory_config,
+                    )
+                    .unwrap(),
+                );
+                worker
+                    .receive(
+                        JsonProtocol::TrackDna(TrackDnaData {
+                            dna_address: example_dna_address(),
+                            agent_id: agent_id.to_string(),
+                        })
+                        .into(),
+                    )
+                    .unwrap();
+                (worker, handler_recv)
+            })
+            .collect();
+        // drain the PeerConnected messages
+        for (worker, receiver) in workers.iter_mut() {
+            while worker.tick().unwrap() {}
+            while receiver.try_recv().is_ok() {}
+        }
+        workers
+    }
+
+    #[test]
+    #[cfg_attr(tarpaulin, skip)]
+    fn memory_network_shards_by_address() {
+        let agents = vec!["agent-1", "agent-2", "agent-3"];
+        let mut workers = sharded_workers(&agents, 1);
+
+        let holder = holders("hello", agents.clone(), 1)[0];
+        let holder_index = agents.iter().position(|agent| *agent == holder).unwrap();
+        let requester_index = (holder_index + 1) % agents.len();
+
+        // only the closest agent gets to store the data
+        let data = DhtData {
+            msg_id: "yada".to_string(),
+            dna_address: example_dna_address(),
+            agent_id: agents[requester_index].to_string(),
+            address: "hello".to_string(),
+            content: json!("test-data"),
+        };
+        workers[requester_index]
+            .0
+            .receive(JsonProtocol::PublishDhtData(data.clone()).into())
+            .unwrap();
+        for (index, (worker, receiver)) in workers.iter_mut().enumerate() {
+            worker.tick().unwrap();
+            if index == holder_index {
+                assert_eq!(
+                    JsonProtocol::try_from(receiver.try_recv().unwrap()).unwrap(),
+                    JsonProtocol::HandleStoreDhtData(data.clone())
+                );
+            } else {
+                assert!(receiver.try_recv().is_err());
+            }
+        }
+
+        // and gets asked for it
+        workers[requester_index]
+            .0
+            .receive(
+                JsonProtocol::GetDhtData(GetDhtData {
+                    msg_id: "yada".to_string(),
+                    dna_address: example_dna_address(),
+                    from_agent_id: agents[requester_index].to_string(),
+                    address: "hello".to_string(),
+                })
+                .into(),
+            )
+            .unwrap();
+        let (worker, receiver) = &mut workers[holder_index];
+        worker.tick().unwrap();
+        match JsonProtocol::try_from(receiver.try_recv().unwrap()).unwrap() {
+            JsonProtocol::HandleGetDhtData(msg) => assert_eq!(msg.address, "hello"),
+            msg => panic!("Did not expect to receive: {:?}", msg),
+        }
+    }
+
+    #[test]
+    #[cfg_attr(tarpaulin, skip)]
+    fn memory_network_forgets_agents_that_left() {
+        let agents = vec!["agent-1", "agent-2"];
+        let mut workers = sharded_workers(&agents, 1);
+        let holder = holders("hello", agents.clone(), 1)[0];
+        let holder_index = agents.iter().position(|agent| *agent == holder).unwrap();
+        drop(workers.remove(holder_index));
+        let remaining = agents[1 - holder_index];
+
+        // the data goes to the agent that is still there
+        let data = DhtData {
+            msg_id: "yada".to_string(),
+            dna_address: example_dna_address(),
+            agent_id: remaining.to_string(),
+            address: "hello".to_string(),
+            content: json!("test-data"),
+        };
+        let (worker, receiver) = &mut workers[0];
+        worker
+            .receive(JsonProtocol::PublishDhtData(data.clone()).into())
+            .unwrap();
+        worker.tick().unwrap();
+        assert_eq!(
+            JsonProtocol::try_from(receiver.try_recv().unwrap()).unwrap(),
+            JsonProtocol::HandleStoreDhtData(data)
+        );
+    }
+
+    #[test]
+    #[cfg_attr(tarpaulin, skip)]
+    fn memory_network_asks_the_next_holder_on_a_miss() {
+        let agents = vec!["agent-1", "agent-2", "agent-3"];
+        let mut workers = sharded_workers(&agents, 2);
+        let holders_of_address = holders("hello", agents.clone(), 2);
+        let index_of = |agent_id: &str| agents.iter().position(|agent| *agent == agent_id).unwrap();
+        let requester_index = (0..agents.len())
+            .find(|index| !holders_of_address.contains(&agents[*index]))
+            .unwrap();
+
+        workers[requester_index]
+            .0
+            .receive(
+                JsonProtocol::GetDhtData(GetDhtData {
+                    msg_id: "yada".to_string(),
+                    dna_address: example_dna_address(),
+                    from_agent_id: agents[requester_index].to_string(),
+                    address: "hello".to_string(),
+                })
+                .into(),
+            )
+            .unwrap();
+        let mut contents = vec![json!(null), json!("test-data")];
+        for holder in holders_of_address.iter() {
+            let (worker, receiver) = &mut workers[index_of(holder)];
+            worker.tick().unwrap();
+            match JsonProtocol::try_from(receiver.try_recv().unwrap()).unwrap() {
+                JsonProtocol::HandleGetDhtData(msg) => worker
+                    .receive(
+                        JsonProtocol::HandleGetDhtDataResult(DhtData {
+                            msg_id: msg.msg_id,
+                            dna_address: msg.dna_address,
+                            agent_id: msg.from_agent_id,
+                            address: msg.address,
+                            content: contents.remove(0),
+                        })
+                        .into(),
+                    )
+                    .unwrap(),
+                msg => panic!("Did not expect to receive: {:?}", msg),
+            }
+        }
+
+        // the requester only gets the result of the holder that had the data
+        let (worker, receiver) = &mut workers[requester_index];
+        worker.tick().unwrap();
+        match JsonProtocol::try_from(receiver.try_recv().unwrap()).unwrap() {
+            JsonProtocol::GetDhtDataResult(msg) => assert_eq!(msg.content, json!("test-data")),
+            msg => panic!("Did not expect to receive: {:?}", msg),
+        }
+        worker.tick().unwrap();
+        assert!(receiver.try_recv().is_err());
+    }
+
     #[test]
     #[cfg_attr(tarpaulin, skip)]
     fn can_memory_network_flow() {
@@ -396,6 +592,7 @@ mod tests {
                     from_agent_id: AGENT_ID_2.to_string(),
                     address: "hello".to_string(),
                     attribute: "link:test".to_string(),
+                    tag_match: Default::default(),
                 })
                 .into(),
             )
@@ -10,6 +10,7 @@ use std::{fs::File, str::FromStr};
 pub enum P2pBackendKind {
     MEMORY,
     IPC,
+    TCP,
 }
 
 impl FromStr for P2pBackendKind {
@@ -18,6 +19,7 @@ impl FromStr for P2pBackendKind {
         match s {
             "MEMORY" => Ok(P2pBackendKind::MEMORY),
             "IPC" => Ok(P2pBackendKind::IPC),
+            "TCP" => Ok(P2pBackendKind::TCP),
             _ => Err(()),
         }
     }
@@ -28,6 +30,7 @@ impl From<P2pBackendKind> for String {
         String::from(match kind {
             P2pBackendKind::MEMORY => "MEMORY",
             P2pBackendKind::IPC => "IPC",
+            P2pBackendKind::TCP => "TCP",
         })
     }
 }
@@ -79,6 +82,12 @@ impl P2pConfig {
         }
     }
 
+    /// Set how many agents hold each address, 0 making every agent hold everything
+    pub fn with_redundancy(mut self, redundancy: usize) -> Self {
+        self.backend_config["redundancy"] = json!(redundancy);
+        self
+    }
+
     pub fn from_file(filepath: &str) -> Self {
         let config_file =
             File::open(filepath).expect("Failed to open filepath on P2pConfig creation.");
@@ -140,6 +149,23 @@ impl P2pConfig {
             server_name
         )
     }
+
+    /// Config for a node listening on `bind_address` that connects to other nodes
+    /// directly over TCP, starting with `bootstrap_nodes`
+    pub fn new_with_tcp_backend(bind_address: &str, bootstrap_nodes: &[String]) -> Self {
+        P2pConfig::new(
+            P2pBackendKind::TCP,
+            &Self::tcp_backend_string(bind_address, bootstrap_nodes),
+        )
+    }
+
+    pub fn tcp_backend_string(bind_address: &str, bootstrap_nodes: &[String]) -> String {
+        json!({
+            "bindAddress": bind_address,
+            "bootstrapNodes": bootstrap_nodes
+        })
+        .to_string()
+    }
 }
 
 // statics
@@ -185,6 +211,31 @@ mod tests {
         assert_eq!(p2p_config, P2pConfig::new_with_memory_backend(server_name));
     }
 
+    #[test]
+    fn it_can_json_round_trip_tcp() {
+        let p2p_config =
+            P2pConfig::new_with_tcp_backend("0.0.0.0:4000", &["127.0.0.1:4001".to_string()]);
+        assert_eq!(p2p_config.backend_kind, P2pBackendKind::TCP);
+        assert_eq!(p2p_config.backend_config["bindAddress"], "0.0.0.0:4000");
+        assert_eq!(
+            P2pConfig::from_str(&p2p_config.as_str()).unwrap(),
+            p2p_config
+        );
+    }
+
+    #[test]
+    fn it_sets_the_redundancy() {
+        use crate::sharding::{redundancy, DEFAULT_REDUNDANCY};
+        let p2p_config = P2pConfig::new_with_memory_backend("redundancy_test");
+        assert_eq!(redundancy(&p2p_config.backend_config), DEFAULT_REDUNDANCY);
+        let p2p_config = p2p_config.with_redundancy(3);
+        assert_eq!(redundancy(&p2p_config.backend_config), 3);
+        assert_eq!(
+            P2pConfig::from_str(&p2p_config.as_str()).unwrap(),
+            p2p_config
+        );
+    }
+
     #[test]
     fn it_should_fail_bad_backend_kind() {
         let res = P2pConfig::from_str(
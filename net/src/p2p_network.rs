@@ -9,7 +9,10 @@ use holochain_net_connection::{
     NetResult,
 };
 
-use super::{ipc_net_worker::IpcNetWorker, memory_worker::InMemoryWorker, p2p_config::*};
+use super::{
+    ipc_net_worker::IpcNetWorker, memory_worker::InMemoryWorker, p2p_config::*,
+    tcp_worker::TcpWorker,
+};
 
 /// Facade handling a p2p module responsable for the network connection
 /// Holds a NetConnectionThread and implements itself the NetSend Trait
@@ -36,6 +39,10 @@ impl P2pNetwork {
             P2pBackendKind::MEMORY => Box::new(move |h| {
                 Ok(Box::new(InMemoryWorker::new(h, &network_config)?) as Box<NetWorker>)
             }),
+            // Create a TcpWorker talking to other nodes directly
+            P2pBackendKind::TCP => Box::new(move |h| {
+                Ok(Box::new(TcpWorker::new(h, &network_config)?) as Box<NetWorker>)
+            }),
         };
         // Create NetConnectionThread with appropriate worker factory
         let connection = NetConnectionThread::new(handler, worker_factory, None)?;
@@ -92,4 +99,16 @@ mod tests {
         res.send(Protocol::P2pReady).unwrap();
         res.stop().unwrap();
     }
+
+    #[test]
+    fn it_should_create_tcp_network() {
+        let mut res = P2pNetwork::new(
+            Box::new(|_r| Ok(())),
+            &P2pConfig::new_with_tcp_backend("127.0.0.1:0", &[]),
+        )
+        .unwrap();
+        assert!(res.endpoint().starts_with("127.0.0.1:"));
+        res.send(Protocol::P2pReady).unwrap();
+        res.stop().unwrap();
+    }
 }
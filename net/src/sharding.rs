@@ -0,0 +1,219 @@
+//! Neighborhoods of the address space.
+//! Agents and addresses get a location on a ring. Every agent is responsible for the
+//! addresses around its own location, so each address is held by the `redundancy`
+//! agents closest to it.
+
+use holochain_sodium::{
+    hash::{sha256, BYTES256},
+    secbuf::SecBuf,
+};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// How many agents hold an address, unless configured otherwise
+pub const DEFAULT_REDUNDANCY: usize = 5;
+
+/// How long we wait for a holder to answer a request before we forget about it
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Read the redundancy factor from a backend config
+pub fn redundancy(backend_config: &serde_json::Value) -> usize {
+    backend_config["redundancy"]
+        .as_u64()
+        .map(|redundancy| redundancy as usize)
+        .unwrap_or(DEFAULT_REDUNDANCY)
+}
+
+/// Location of an agent id or an address on the ring.
+/// These are the first four bytes of its SHA-256 hash, so every node computes the
+/// same locations and nobody can place an agent next to an address without trying
+/// out keys.
+pub fn location(address: &str) -> u32 {
+    let mut input = SecBuf::with_insecure(address.len());
+    input.write_lock().copy_from_slice(address.as_bytes());
+    let mut hash = SecBuf::with_insecure(BYTES256);
+    sha256(&mut input, &mut hash).expect("hashing into a buffer of the right size");
+    let hash = hash.read_lock();
+    hash[..4]
+        .iter()
+        .fold(0, |location, byte| (location << 8) | u32::from(*byte))
+}
+
+/// Distance between two locations, going around the ring either way
+pub fn distance(a: u32, b: u32) -> u32 {
+    std::cmp::min(a.wrapping_sub(b), b.wrapping_sub(a))
+}
+
+/// The agents responsible for holding `address`, closest first.
+/// A redundancy of 0 makes every agent hold every address.
+pub fn holders<'a, I>(address: &str, agents: I, redundancy: usize) -> Vec<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let address_location = location(address);
+    let mut agents: Vec<(u32, &'a str)> = agents
+        .into_iter()
+        .map(|agent| (distance(address_location, location(agent)), agent))
+        .collect();
+    agents.sort();
+    agents.dedup();
+    if redundancy > 0 {
+        agents.truncate(redundancy);
+    }
+    agents.into_iter().map(|(_, agent)| agent).collect()
+}
+
+/// The holders a request for `address` coming from `requester` goes to, in the order
+/// they get asked, each one only if the ones before it missed.
+/// Requesters that hold the address themselves only ask the network if their copy is
+/// missing, so they come last.
+pub fn request_holders<'a, I>(
+    address: &str,
+    agents: I,
+    redundancy: usize,
+    requester: &str,
+) -> Vec<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let (mut others, requesters): (Vec<&'a str>, Vec<&'a str>) =
+        holders(address, agents, redundancy)
+            .into_iter()
+            .partition(|holder| *holder != requester);
+    others.extend(requesters);
+    others
+}
+
+/// Whether the content of a result means that the holder had nothing at the address:
+/// `null` for entries, no links for links.
+pub fn is_miss(content: &serde_json::Value) -> bool {
+    content.is_null() || content.as_array().map(Vec::is_empty).unwrap_or(false)
+}
+
+struct PendingRequest<T> {
+    request: T,
+    // holders to ask if the one that has the request misses
+    holders: Vec<String>,
+    sent_at: Instant,
+}
+
+/// Requests that went to a holder, by requester and message id,
+/// with the holders that get asked next if it misses
+pub struct PendingRequests<T> {
+    requests: HashMap<(String, String), PendingRequest<T>>,
+}
+
+impl<T> Default for PendingRequests<T> {
+    fn default() -> Self {
+        PendingRequests {
+            requests: HashMap::new(),
+        }
+    }
+}
+
+impl<T> PendingRequests<T> {
+    /// Remembers the holders left to ask for a request that went to a holder.
+    /// Requests that did not get answered in time are forgotten.
+    pub fn insert(&mut self, requester: &str, msg_id: &str, request: T, holders: Vec<String>) {
+        self.requests
+            .retain(|_, pending| pending.sent_at.elapsed() < REQUEST_TIMEOUT);
+        if holders.is_empty() {
+            return;
+        }
+        self.requests.insert(
+            (requester.to_string(), msg_id.to_string()),
+            PendingRequest {
+                request,
+                holders,
+                sent_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Takes out the request and the holders left to ask, once it got answered
+    pub fn take(&mut self, requester: &str, msg_id: &str) -> Option<(T, Vec<String>)> {
+        self.requests
+            .remove(&(requester.to_string(), msg_id.to_string()))
+            .map(|pending| (pending.request, pending.holders))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_measures_distances_around_the_ring() {
+        assert_eq!(distance(10, 3), 7);
+        assert_eq!(distance(3, 10), 7);
+        assert_eq!(distance(std::u32::MAX, 1), 2);
+        assert_eq!(location("agent"), location("agent"));
+        assert_ne!(location("agent"), location("agent2"));
+        // SHA-256 of nothing starts with e3b0c442
+        assert_eq!(location(""), 0xe3b0_c442);
+    }
+
+    #[test]
+    fn it_picks_the_closest_agents_as_holders() {
+        let agents: Vec<String> = (0..10).map(|i| format!("agent-{}", i)).collect();
+        let agent_ids = || agents.iter().map(String::as_str);
+
+        let holders_of_address = holders("QmAddress", agent_ids(), 3);
+        assert_eq!(holders_of_address.len(), 3);
+        let furthest_holder = distance(
+            location("QmAddress"),
+            location(holders_of_address.last().unwrap()),
+        );
+        for agent in agent_ids().filter(|agent| !holders_of_address.contains(agent)) {
+            assert!(distance(location("QmAddress"), location(agent)) >= furthest_holder);
+        }
+
+        assert_eq!(holders("QmAddress", agent_ids(), 0).len(), 10);
+        assert_eq!(holders("QmAddress", agent_ids(), 20).len(), 10);
+        assert_eq!(
+            holders("QmAddress", agent_ids(), 3),
+            holders("QmAddress", agent_ids().rev(), 3)
+        );
+    }
+
+    #[test]
+    fn requests_go_to_other_holders_first() {
+        let agents = vec!["agent-1", "agent-2", "agent-3"];
+        let holders_of_address = holders("QmAddress", agents.clone(), 3);
+        let closest = holders_of_address[0];
+        assert_eq!(
+            request_holders("QmAddress", agents.clone(), 3, closest),
+            vec![holders_of_address[1], holders_of_address[2], closest]
+        );
+        assert_eq!(
+            request_holders("QmAddress", agents.clone(), 3, "agent-4"),
+            holders_of_address
+        );
+        assert_eq!(
+            request_holders("QmAddress", vec!["agent-1"], 2, "agent-1"),
+            vec!["agent-1"]
+        );
+        assert!(request_holders("QmAddress", vec![], 2, "agent-1").is_empty());
+    }
+
+    #[test]
+    fn it_remembers_whom_to_ask_next() {
+        let mut pending = PendingRequests::default();
+        pending.insert("agent-1", "msg-1", "request", vec!["agent-2".to_string()]);
+        pending.insert("agent-1", "msg-2", "request", vec![]);
+        assert_eq!(pending.take("agent-2", "msg-1"), None);
+        assert_eq!(
+            pending.take("agent-1", "msg-1"),
+            Some(("request", vec!["agent-2".to_string()]))
+        );
+        assert_eq!(pending.take("agent-1", "msg-1"), None);
+        assert_eq!(pending.take("agent-1", "msg-2"), None);
+
+        assert!(is_miss(&json!(null)));
+        assert!(is_miss(&json!([])));
+        assert!(!is_miss(&json!("entry")));
+        assert!(!is_miss(&json!(["link"])));
+    }
+}
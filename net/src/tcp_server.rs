@@ -0,0 +1,1224 @@
+//! provides a p2p node routing JsonProtocol messages over direct TCP connections
+
+use crate::sharding::{holders, is_miss, request_holders, PendingRequests};
+use holochain_core_types::{agent::KeyBuffer, cas::content::Address, json::JsonString};
+use holochain_net_connection::{
+    json_protocol::{
+        ConnectData, DhtData, DhtMetaData, FailureResultData, GetDhtData, GetDhtMetaData,
+        JsonProtocol, MessageData, PeerChallengeData, PeerData, SuccessResultData,
+    },
+    protocol::Protocol,
+    NetResult,
+};
+use holochain_sodium::{random::random_secbuf, secbuf::SecBuf, sign};
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    convert::TryFrom,
+    io::{self, ErrorKind, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{mpsc, Mutex, RwLock},
+    thread,
+    time::Duration,
+};
+
+type TcpServerMap = HashMap<String, Mutex<TcpServer>>;
+
+/// the TCP nodes of this process, by the address they got bound to
+lazy_static! {
+    pub(crate) static ref TCP_SERVER_MAP: RwLock<TcpServerMap> = RwLock::new(HashMap::new());
+}
+
+/// how long we wait for a node to accept our connection
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// how many connections to other nodes we try to establish at the same time
+const MAX_PENDING_CONNECTS: usize = 16;
+
+/// how many other nodes we are connected to at most, including the ones that connected to us
+const MAX_PEERS: usize = 64;
+
+/// nodes sending longer lines get disconnected
+const MAX_LINE_LEN: usize = 16 * 1024 * 1024;
+
+/// nodes that leave more than this unread get disconnected
+const MAX_QUEUED_LEN: usize = 64 * 1024 * 1024;
+
+/// random bytes in the challenges the agents of other nodes sign
+const CHALLENGE_LEN: usize = 32;
+
+/// length of the signatures agents make
+const SIGNATURE_LEN: usize = 64;
+
+/// hash connections by dna::agent_id
+fn cat_dna_agent(dna_address: &Address, agent_id: &str) -> String {
+    format!("{}::{}", dna_address, agent_id)
+}
+
+/// Turns a bootstrap node into a socket address.
+/// Besides `host:port` this takes multiaddrs like `/ip4/127.0.0.1/tcp/45737/ipfs/Qm..`,
+/// so the bootstrap nodes of n3h networks can be reused.
+pub(crate) fn socket_address(node: &str) -> String {
+    let parts: Vec<&str> = node.split('/').collect();
+    if parts.len() >= 5 && parts[0].is_empty() && parts[3] == "tcp" {
+        match parts[1] {
+            "ip4" | "dns4" => return format!("{}:{}", parts[2], parts[4]),
+            "ip6" => return format!("[{}]:{}", parts[2], parts[4]),
+            _ => (),
+        }
+    }
+    node.to_string()
+}
+
+fn secbuf_from_bytes(bytes: &[u8]) -> SecBuf {
+    let mut buf = SecBuf::with_insecure(bytes.len());
+    buf.write_lock().copy_from_slice(bytes);
+    buf
+}
+
+/// a new random challenge for the agents of another node
+fn random_challenge() -> String {
+    let mut buf = SecBuf::with_insecure(CHALLENGE_LEN);
+    random_secbuf(&mut buf);
+    let challenge = buf.read_lock();
+    base64::encode(&challenge[..])
+}
+
+/// whether `signature` is a signature of `data` by the agent.
+/// agent ids are the public keys of the agents.
+fn is_signed(agent_id: &str, data: &str, signature: &str) -> bool {
+    let key = match KeyBuffer::with_corrected(agent_id) {
+        Ok(key) => key,
+        Err(_) => return false,
+    };
+    let signature = match base64::decode(signature) {
+        Ok(signature) => signature,
+        Err(_) => return false,
+    };
+    if signature.len() != SIGNATURE_LEN {
+        return false;
+    }
+    let mut signature = secbuf_from_bytes(&signature);
+    let mut message = secbuf_from_bytes(data.as_bytes());
+    let mut public_key = secbuf_from_bytes(key.get_sig());
+    sign::verify(&mut signature, &mut message, &mut public_key) == 0
+}
+
+/// a connection to another node
+struct TcpPeer {
+    stream: TcpStream,
+    // bytes of a line that has not been completely received yet
+    buffer: Vec<u8>,
+    // bytes we could not write yet without blocking
+    queued: Vec<u8>,
+    // the address the other node listens on, once we know it
+    address: Option<String>,
+    // addresses of nodes the other node knows, we connect to them once one of its
+    // agents signed our challenge
+    announced: Vec<String>,
+    // agents of the other node that signed our challenge
+    agents: HashSet<(Address, String)>,
+    // what the agents of the other node have to sign
+    challenge: String,
+    // what our agents have to sign for the other node, once it told us
+    their_challenge: Option<String>,
+    // set when reading or writing failed, the peer gets dropped on the next tick
+    closed: bool,
+}
+
+impl TcpPeer {
+    fn new(stream: TcpStream, address: Option<String>) -> NetResult<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(TcpPeer {
+            stream,
+            buffer: Vec::new(),
+            queued: Vec::new(),
+            address,
+            announced: Vec::new(),
+            agents: HashSet::new(),
+            challenge: random_challenge(),
+            their_challenge: None,
+            closed: false,
+        })
+    }
+
+    /// queue a line for the other node and write what we can without blocking.
+    /// nodes that don't read what we send them get disconnected.
+    fn send(&mut self, msg: &JsonProtocol) {
+        if self.closed {
+            return;
+        }
+        let line = String::from(JsonString::from(msg));
+        if self.queued.len() + line.len() + 1 > MAX_QUEUED_LEN {
+            self.closed = true;
+            return;
+        }
+        self.queued.extend_from_slice(line.as_bytes());
+        self.queued.push(b'\n');
+        self.flush();
+    }
+
+    /// write as much of the queued lines as we can without blocking
+    fn flush(&mut self) {
+        let mut written = 0;
+        while !self.closed && written < self.queued.len() {
+            match self.stream.write(&self.queued[written..]) {
+                Ok(0) => self.closed = true,
+                Ok(n) => written += n,
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => (),
+                Err(_) => self.closed = true,
+            }
+        }
+        self.queued.drain(..written);
+    }
+
+    /// read all complete lines that are available without blocking.
+    /// reading stops once there is more than a line can hold, the rest waits.
+    fn receive(&mut self) -> Vec<JsonProtocol> {
+        let mut chunk = [0; 4096];
+        while self.buffer.len() <= MAX_LINE_LEN {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => {
+                    self.closed = true;
+                    break;
+                }
+                Ok(n) => self.buffer.extend_from_slice(&chunk[..n]),
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => (),
+                Err(_) => {
+                    self.closed = true;
+                    break;
+                }
+            }
+        }
+        let mut messages = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            // lines that are not JsonProtocol messages are ignored
+            if let Ok(line) = String::from_utf8(line) {
+                if let Ok(msg) =
+                    JsonProtocol::try_from(JsonString::from(line.trim_end().to_string()))
+                {
+                    messages.push(msg);
+                }
+            }
+        }
+        if self.buffer.len() > MAX_LINE_LEN {
+            self.closed = true;
+        }
+        messages
+    }
+}
+
+/// A p2p node listening on a TCP socket and connected to other nodes.
+/// Workers register with it like with an InMemoryServer. Messages for agents of other
+/// nodes are written to the connection of that node, as the JsonProtocol message the
+/// receiving node hands to its agent.
+pub(crate) struct TcpServer {
+    listener: TcpListener,
+    // the address other nodes reach us on
+    address: String,
+    // keep track of senders by `dna_address::agent_id`
+    senders: HashMap<String, mpsc::Sender<Protocol>>,
+    // keep track of senders as arrays by dna_address
+    senders_by_dna: HashMap<Address, Vec<mpsc::Sender<Protocol>>>,
+    // agents of this node, announced to every node we connect to
+    tracked: Vec<PeerData>,
+    // connections to other nodes
+    peers: Vec<TcpPeer>,
+    // addresses of nodes we are connecting to in the background
+    connecting: HashSet<String>,
+    // where the background connects hand over their connections
+    connected_sender: mpsc::Sender<(String, io::Result<TcpStream>)>,
+    connected_receiver: mpsc::Receiver<(String, io::Result<TcpStream>)>,
+    // requests that go to the next holder if theirs misses
+    pending_requests: PendingRequests<JsonProtocol>,
+    // how many agents hold each address
+    redundancy: usize,
+    // Keep track of connected clients
+    client_count: usize,
+}
+
+impl TcpServer {
+    /// create a new node listening on `bind_address` and connect it to the bootstrap nodes
+    pub fn new(
+        bind_address: &str,
+        bootstrap_nodes: &[String],
+        redundancy: usize,
+    ) -> NetResult<Self> {
+        let listener = TcpListener::bind(bind_address)?;
+        listener.set_nonblocking(true)?;
+        let address = listener.local_addr()?.to_string();
+        let (connected_sender, connected_receiver) = mpsc::channel();
+        let mut server = Self {
+            listener,
+            address,
+            senders: HashMap::new(),
+            senders_by_dna: HashMap::new(),
+            tracked: Vec::new(),
+            peers: Vec::new(),
+            connecting: HashSet::new(),
+            connected_sender,
+            connected_receiver,
+            pending_requests: PendingRequests::default(),
+            redundancy,
+            client_count: 0,
+        };
+        // nodes that are not up yet will connect to us once they bootstrap
+        for node in bootstrap_nodes {
+            let _ = server.connect(&socket_address(node));
+        }
+        Ok(server)
+    }
+
+    /// the address this node is listening on
+    pub fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// A client clocks in on this node
+    pub fn clock_in(&mut self) {
+        self.client_count += 1;
+    }
+
+    /// A client clocks out of this node.
+    /// Returns true if there are no clients left, so the node can be shut down.
+    pub fn clock_out(&mut self) -> bool {
+        assert!(self.client_count > 0);
+        self.client_count -= 1;
+        self.client_count == 0
+    }
+
+    /// register a data handler with this node (for message routing)
+    pub fn register(
+        &mut self,
+        dna_address: &Address,
+        agent_id: &str,
+        sender: mpsc::Sender<Protocol>,
+    ) -> NetResult<()> {
+        self.senders
+            .insert(cat_dna_agent(dna_address, agent_id), sender.clone());
+        match self.senders_by_dna.entry(dna_address.to_owned()) {
+            Entry::Occupied(mut e) => {
+                e.get_mut().push(sender.clone());
+            }
+            Entry::Vacant(e) => {
+                e.insert(vec![sender.clone()]);
+            }
+        };
+        Ok(())
+    }
+
+    /// connect to the node listening on `address`, unless we already are.
+    /// the connection gets established in the background and picked up by `tick()`.
+    pub fn connect(&mut self, address: &str) -> NetResult<()> {
+        if address == self.address
+            || self.connecting.contains(address)
+            || self.priv_is_connected(address)
+        {
+            return Ok(());
+        }
+        if self.connecting.len() >= MAX_PENDING_CONNECTS {
+            bail!("too many pending connections to connect to {}", address);
+        }
+        if self.peers.len() + self.connecting.len() >= MAX_PEERS {
+            bail!("too many connections to connect to {}", address);
+        }
+        self.connecting.insert(address.to_string());
+        let address = address.to_string();
+        let connected_sender = self.connected_sender.clone();
+        thread::spawn(move || {
+            let stream = address
+                .to_socket_addrs()
+                .and_then(|mut socket_addresses| {
+                    socket_addresses
+                        .next()
+                        .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "could not resolve"))
+                })
+                .and_then(|socket_address| {
+                    TcpStream::connect_timeout(&socket_address, CONNECT_TIMEOUT)
+                });
+            // the node might have been shut down in the meantime
+            let _ = connected_sender.send((address, stream));
+        });
+        Ok(())
+    }
+
+    /// process a message from one of our clients
+    pub fn handle(&mut self, data: Protocol) -> NetResult<()> {
+        if let Ok(json_msg) = JsonProtocol::try_from(&data) {
+            match json_msg {
+                JsonProtocol::TrackDna(msg) => {
+                    let peer_data = PeerData {
+                        dna_address: msg.dna_address,
+                        agent_id: msg.agent_id,
+                    };
+                    self.tracked.push(peer_data.clone());
+                    // other nodes only route to the agent once it signed their challenge
+                    let challenges: Vec<String> = self
+                        .peers
+                        .iter()
+                        .filter_map(|peer| peer.their_challenge.clone())
+                        .collect();
+                    for challenge in challenges {
+                        self.priv_request_signature(&peer_data, &challenge)?;
+                    }
+                    self.priv_deliver(JsonProtocol::PeerConnected(peer_data))?;
+                }
+                JsonProtocol::HandleSignPeerChallengeResult(msg) => {
+                    let result = JsonProtocol::HandleSignPeerChallengeResult(msg.clone());
+                    for peer in self
+                        .peers
+                        .iter_mut()
+                        .filter(|peer| peer.their_challenge.as_ref() == Some(&msg.challenge))
+                    {
+                        peer.send(&result);
+                    }
+                }
+                JsonProtocol::Connect(msg) => {
+                    self.connect(&socket_address(&String::from(msg.address)))?;
+                }
+                JsonProtocol::SendMessage(msg) => {
+                    self.priv_send_one(
+                        &msg.dna_address.clone(),
+                        &msg.to_agent_id.clone(),
+                        JsonProtocol::HandleSendMessage(msg),
+                    )?;
+                }
+                JsonProtocol::HandleSendMessageResult(msg) => {
+                    self.priv_send_one(
+                        &msg.dna_address.clone(),
+                        &msg.to_agent_id.clone(),
+                        JsonProtocol::SendMessageResult(msg),
+                    )?;
+                }
+                JsonProtocol::SuccessResult(msg) => {
+                    self.priv_send_one(
+                        &msg.dna_address.clone(),
+                        &msg.to_agent_id.clone(),
+                        JsonProtocol::SuccessResult(msg),
+                    )?;
+                }
+                JsonProtocol::FailureResult(msg) => {
+                    self.priv_send_one(
+                        &msg.dna_address.clone(),
+                        &msg.to_agent_id.clone(),
+                        JsonProtocol::FailureResult(msg),
+                    )?;
+                }
+                JsonProtocol::GetDhtData(msg) => {
+                    self.priv_handle_get_dht_data(msg)?;
+                }
+                JsonProtocol::HandleGetDhtDataResult(msg) => {
+                    if !self.priv_ask_next_holder(
+                        &msg.dna_address,
+                        &msg.agent_id,
+                        &msg.msg_id,
+                        &msg.content,
+                    ) {
+                        self.priv_send_one(
+                            &msg.dna_address.clone(),
+                            &msg.agent_id.clone(),
+                            JsonProtocol::GetDhtDataResult(msg),
+                        )?;
+                    }
+                }
+                JsonProtocol::PublishDhtData(msg) => {
+                    self.priv_send_holders(
+                        &msg.dna_address.clone(),
+                        &msg.address.clone(),
+                        JsonProtocol::HandleStoreDhtData(msg),
+                    )?;
+                }
+                JsonProtocol::GetDhtMeta(msg) => {
+                    self.priv_handle_get_dht_meta(msg)?;
+                }
+                JsonProtocol::HandleGetDhtMetaResult(msg) => {
+                    if !self.priv_ask_next_holder(
+                        &msg.dna_address,
+                        &msg.agent_id,
+                        &msg.msg_id,
+                        &msg.content,
+                    ) {
+                        self.priv_send_one(
+                            &msg.dna_address.clone(),
+                            &msg.agent_id.clone(),
+                            JsonProtocol::GetDhtMetaResult(msg),
+                        )?;
+                    }
+                }
+                JsonProtocol::PublishDhtMeta(msg) => {
+                    self.priv_send_holders(
+                        &msg.dna_address.clone(),
+                        &msg.address.clone(),
+                        JsonProtocol::HandleStoreDhtMeta(msg),
+                    )?;
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    /// accept new connections, pick up the ones we established
+    /// and process what other nodes sent us.
+    /// Returns true if anything happened.
+    /// Misbehaving nodes only get their messages dropped or their connection closed,
+    /// as do nodes we can't reach or have no room for.
+    pub fn tick(&mut self) -> NetResult<bool> {
+        let mut did_something = false;
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    did_something = true;
+                    self.priv_add_peer(stream, None);
+                }
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => (),
+                // connections we failed to accept are tried again on the next tick
+                Err(_) => break,
+            }
+        }
+        while let Ok((address, stream)) = self.connected_receiver.try_recv() {
+            did_something = true;
+            self.connecting.remove(&address);
+            match stream {
+                // the node might have connected to us in the meantime
+                Ok(_) if self.priv_is_connected(&address) => (),
+                Ok(stream) => self.priv_add_peer(stream, Some(address)),
+                // nodes that are not up yet connect to us once they are
+                Err(_) => (),
+            }
+        }
+        let received: Vec<(usize, Vec<JsonProtocol>)> = self
+            .peers
+            .iter_mut()
+            .map(TcpPeer::receive)
+            .enumerate()
+            .filter(|(_, messages)| !messages.is_empty())
+            .collect();
+        for (index, messages) in received {
+            did_something = true;
+            for msg in messages {
+                // messages we can't handle get dropped
+                let _ = self.priv_handle_remote(index, msg);
+            }
+        }
+        for peer in self.peers.iter_mut() {
+            peer.flush();
+        }
+        let gone: HashSet<(Address, String)> = self
+            .peers
+            .iter()
+            .filter(|peer| peer.closed)
+            .flat_map(|peer| peer.agents.iter().cloned())
+            .collect();
+        self.peers.retain(|peer| !peer.closed);
+        // all our agents get told, even if one of them can't be
+        let mut result = Ok(did_something);
+        for agent in gone {
+            // the agent might still be reachable over another connection to its node
+            if self.peers.iter().any(|peer| peer.agents.contains(&agent)) {
+                continue;
+            }
+            let (dna_address, agent_id) = agent;
+            if let Err(e) = self.priv_deliver(JsonProtocol::PeerDisconnected(PeerData {
+                dna_address,
+                agent_id,
+            })) {
+                result = Err(e);
+            }
+        }
+        result
+    }
+
+    // -- private -- //
+
+    /// whether we are connected to the node listening on `address`
+    fn priv_is_connected(&self, address: &str) -> bool {
+        self.peers
+            .iter()
+            .any(|peer| peer.address.as_ref().map(String::as_str) == Some(address))
+    }
+
+    /// connections we have no room for or can't set up get closed right away
+    fn priv_add_peer(&mut self, stream: TcpStream, address: Option<String>) {
+        if self.peers.len() >= MAX_PEERS {
+            return;
+        }
+        if let Ok(peer) = TcpPeer::new(stream, address) {
+            self.peers.push(peer);
+            let index = self.peers.len() - 1;
+            self.priv_greet(index);
+        }
+    }
+
+    /// tell a node we just connected to who we are and whom we know,
+    /// and challenge it to prove which agents it runs
+    fn priv_greet(&mut self, index: usize) {
+        let mut greeting = vec![JsonProtocol::Connect(ConnectData {
+            address: self.address.clone().into(),
+        })];
+        greeting.extend(
+            self.peers
+                .iter()
+                .filter_map(|peer| peer.address.clone())
+                .map(|address| {
+                    JsonProtocol::Connect(ConnectData {
+                        address: address.into(),
+                    })
+                }),
+        );
+        // our challenge is the same for all agents of the node,
+        // they fill in which agent signs it
+        greeting.push(JsonProtocol::HandleSignPeerChallenge(PeerChallengeData {
+            dna_address: Address::from(""),
+            agent_id: String::new(),
+            challenge: self.peers[index].challenge.clone(),
+            signature: String::new(),
+        }));
+        for msg in greeting.iter() {
+            self.peers[index].send(msg);
+        }
+    }
+
+    /// process a message another node sent us
+    fn priv_handle_remote(&mut self, index: usize, msg: JsonProtocol) -> NetResult<()> {
+        match msg {
+            // the first address a node sends is its own, the others are nodes it knows
+            JsonProtocol::Connect(msg) => {
+                let address = String::from(msg.address);
+                if self.peers[index].address.is_none() {
+                    let address = self.priv_reachable(index, address);
+                    self.peers[index].address = Some(address);
+                } else if self.peers[index].agents.is_empty() {
+                    if self.peers[index].announced.len() < MAX_PEERS {
+                        self.peers[index].announced.push(address);
+                    }
+                } else {
+                    // nodes that went away since are no reason to fail
+                    let _ = self.connect(&address);
+                }
+                Ok(())
+            }
+            JsonProtocol::HandleSignPeerChallenge(msg) => {
+                self.peers[index].their_challenge = Some(msg.challenge.clone());
+                for peer_data in self.tracked.iter() {
+                    self.priv_request_signature(peer_data, &msg.challenge)?;
+                }
+                Ok(())
+            }
+            // agents of other nodes only count once they signed our challenge
+            JsonProtocol::HandleSignPeerChallengeResult(msg) => {
+                if msg.challenge != self.peers[index].challenge
+                    || !is_signed(&msg.agent_id, &msg.signed_data(), &msg.signature)
+                {
+                    bail!("agent {} did not sign our challenge", msg.agent_id);
+                }
+                self.peers[index]
+                    .agents
+                    .insert((msg.dna_address.clone(), msg.agent_id.clone()));
+                // only nodes that proved to run an agent get us to connect to others
+                let announced: Vec<String> = self.peers[index].announced.drain(..).collect();
+                for address in announced {
+                    let _ = self.connect(&address);
+                }
+                self.priv_deliver(JsonProtocol::PeerConnected(PeerData {
+                    dna_address: msg.dna_address,
+                    agent_id: msg.agent_id,
+                }))
+            }
+            JsonProtocol::PeerConnected(_) | JsonProtocol::PeerDisconnected(_) => Ok(()),
+            ref msg if !self.priv_is_from_verified_agent(index, msg) => {
+                bail!("message is not from an agent that signed our challenge")
+            }
+            // results of requests we routed to a holder of the other node
+            JsonProtocol::GetDhtDataResult(msg) => {
+                if self.priv_ask_next_holder(
+                    &msg.dna_address,
+                    &msg.agent_id,
+                    &msg.msg_id,
+                    &msg.content,
+                ) {
+                    return Ok(());
+                }
+                self.priv_deliver(JsonProtocol::GetDhtDataResult(msg))
+            }
+            JsonProtocol::GetDhtMetaResult(msg) => {
+                if self.priv_ask_next_holder(
+                    &msg.dna_address,
+                    &msg.agent_id,
+                    &msg.msg_id,
+                    &msg.content,
+                ) {
+                    return Ok(());
+                }
+                self.priv_deliver(JsonProtocol::GetDhtMetaResult(msg))
+            }
+            msg => self.priv_deliver(msg),
+        }
+    }
+
+    /// whether the agent that sent `msg` over the connection to another node signed our
+    /// challenge. Results don't say which agent sent them, they need an agent of the dna.
+    fn priv_is_from_verified_agent(&self, index: usize, msg: &JsonProtocol) -> bool {
+        let agents = &self.peers[index].agents;
+        let is_verified = |dna_address: &Address, agent_id: &str| {
+            agents.contains(&(dna_address.clone(), agent_id.to_string()))
+        };
+        let has_verified = |dna_address: &Address| agents.iter().any(|(dna, _)| dna == dna_address);
+        match msg {
+            JsonProtocol::HandleSendMessage(msg) | JsonProtocol::SendMessageResult(msg) => {
+                is_verified(&msg.dna_address, &msg.from_agent_id)
+            }
+            JsonProtocol::HandleGetDhtData(msg) => {
+                is_verified(&msg.dna_address, &msg.from_agent_id)
+            }
+            JsonProtocol::HandleGetDhtMeta(msg) => {
+                is_verified(&msg.dna_address, &msg.from_agent_id)
+            }
+            JsonProtocol::HandleStoreDhtData(msg) => is_verified(&msg.dna_address, &msg.agent_id),
+            JsonProtocol::HandleStoreDhtMeta(msg) => is_verified(&msg.dna_address, &msg.agent_id),
+            JsonProtocol::GetDhtDataResult(msg) => has_verified(&msg.dna_address),
+            JsonProtocol::GetDhtMetaResult(msg) => has_verified(&msg.dna_address),
+            JsonProtocol::SuccessResult(msg) => has_verified(&msg.dna_address),
+            JsonProtocol::FailureResult(msg) => has_verified(&msg.dna_address),
+            _ => false,
+        }
+    }
+
+    /// ask an agent of this node to sign the challenge of another node
+    fn priv_request_signature(&self, peer_data: &PeerData, challenge: &str) -> NetResult<()> {
+        self.priv_deliver_one(
+            &peer_data.dna_address,
+            &peer_data.agent_id,
+            JsonProtocol::HandleSignPeerChallenge(PeerChallengeData {
+                dna_address: peer_data.dna_address.clone(),
+                agent_id: peer_data.agent_id.clone(),
+                challenge: challenge.to_string(),
+                signature: String::new(),
+            }),
+        )
+    }
+
+    /// nodes listening on all interfaces announce an unspecified address,
+    /// which we replace with the address their connection comes from
+    fn priv_reachable(&self, index: usize, address: String) -> String {
+        match (
+            address.parse::<SocketAddr>(),
+            self.peers[index].stream.peer_addr(),
+        ) {
+            (Ok(announced), Ok(peer)) if announced.ip().is_unspecified() => {
+                SocketAddr::new(peer.ip(), announced.port()).to_string()
+            }
+            _ => address,
+        }
+    }
+
+    /// hand a message to the agents of this node it is meant for
+    fn priv_deliver(&mut self, msg: JsonProtocol) -> NetResult<()> {
+        match &msg {
+            JsonProtocol::HandleSendMessage(MessageData {
+                dna_address,
+                to_agent_id,
+                ..
+            })
+            | JsonProtocol::SendMessageResult(MessageData {
+                dna_address,
+                to_agent_id,
+                ..
+            })
+            | JsonProtocol::SuccessResult(SuccessResultData {
+                dna_address,
+                to_agent_id,
+                ..
+            })
+            | JsonProtocol::FailureResult(FailureResultData {
+                dna_address,
+                to_agent_id,
+                ..
+            }) => self.priv_deliver_one(dna_address, to_agent_id, msg.clone()),
+            JsonProtocol::GetDhtDataResult(DhtData {
+                dna_address,
+                agent_id,
+                ..
+            })
+            | JsonProtocol::GetDhtMetaResult(DhtMetaData {
+                dna_address,
+                agent_id,
+                ..
+            }) => self.priv_deliver_one(dna_address, agent_id, msg.clone()),
+            // requests and store requests don't say which of our agents they are for,
+            // so they go to our agents holding the address
+            JsonProtocol::HandleGetDhtData(GetDhtData {
+                dna_address,
+                address,
+                ..
+            })
+            | JsonProtocol::HandleGetDhtMeta(GetDhtMetaData {
+                dna_address,
+                address,
+                ..
+            }) => match self.priv_local_holders(dna_address, address).first() {
+                Some(holder) => self.priv_deliver_one(dna_address, holder, msg.clone()),
+                None => Ok(()),
+            },
+            JsonProtocol::HandleStoreDhtData(DhtData {
+                dna_address,
+                address,
+                ..
+            })
+            | JsonProtocol::HandleStoreDhtMeta(DhtMetaData {
+                dna_address,
+                address,
+                ..
+            }) => {
+                for holder in self.priv_local_holders(dna_address, address) {
+                    self.priv_deliver_one(dna_address, &holder, msg.clone())?;
+                }
+                Ok(())
+            }
+            JsonProtocol::PeerConnected(PeerData { dna_address, .. })
+            | JsonProtocol::PeerDisconnected(PeerData { dna_address, .. }) => {
+                if let Some(senders) = self.senders_by_dna.get(dna_address) {
+                    for sender in senders.iter() {
+                        sender.send(msg.clone().into())?;
+                    }
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// hand a message to one of the agents of this node
+    fn priv_deliver_one(
+        &self,
+        dna_address: &Address,
+        agent_id: &str,
+        msg: JsonProtocol,
+    ) -> NetResult<()> {
+        match self.senders.get(&cat_dna_agent(dna_address, agent_id)) {
+            Some(sender) => {
+                sender.send(msg.into())?;
+                Ok(())
+            }
+            None => Err(format_err!(
+                "No sender channel found ({})",
+                self.address.clone()
+            )),
+        }
+    }
+
+    /// send a message to an agent of this or of another node
+    fn priv_send_one(
+        &mut self,
+        dna_address: &Address,
+        agent_id: &str,
+        msg: JsonProtocol,
+    ) -> NetResult<()> {
+        if self
+            .senders
+            .contains_key(&cat_dna_agent(dna_address, agent_id))
+        {
+            return self.priv_deliver_one(dna_address, agent_id, msg);
+        }
+        let agent = (dna_address.clone(), agent_id.to_string());
+        match self
+            .peers
+            .iter_mut()
+            .find(|peer| !peer.closed && peer.agents.contains(&agent))
+        {
+            Some(peer) => {
+                peer.send(&msg);
+                Ok(())
+            }
+            None => Err(format_err!("No node found for agent {}", agent_id)),
+        }
+    }
+
+    /// agents of this node tracking the dna
+    fn priv_local_agents(&self, dna_address: &Address) -> Vec<String> {
+        self.tracked
+            .iter()
+            .filter(|peer_data| {
+                peer_data.dna_address == *dna_address
+                    && self
+                        .senders
+                        .contains_key(&cat_dna_agent(dna_address, &peer_data.agent_id))
+            })
+            .map(|peer_data| peer_data.agent_id.clone())
+            .collect()
+    }
+
+    /// agents of this node and of the nodes we are connected to tracking the dna
+    fn priv_agents(&self, dna_address: &Address) -> Vec<String> {
+        let mut agents = self.priv_local_agents(dna_address);
+        agents.extend(
+            self.peers
+                .iter()
+                .filter(|peer| !peer.closed)
+                .flat_map(|peer| peer.agents.iter())
+                .filter(|(dna, _)| dna == dna_address)
+                .map(|(_, agent_id)| agent_id.clone()),
+        );
+        agents
+    }
+
+    /// the agents of this node responsible for holding `address`.
+    /// if we don't know of any, the node that sent us the address knew better
+    /// and all our agents tracking the dna get it.
+    fn priv_local_holders(&self, dna_address: &Address, address: &str) -> Vec<String> {
+        let agents = self.priv_agents(dna_address);
+        let holders = holders(address, agents.iter().map(String::as_str), self.redundancy);
+        let local_agents = self.priv_local_agents(dna_address);
+        let local_holders: Vec<String> = local_agents
+            .iter()
+            .filter(|agent_id| holders.contains(&agent_id.as_str()))
+            .cloned()
+            .collect();
+        if local_holders.is_empty() {
+            local_agents
+        } else {
+            local_holders
+        }
+    }
+
+    /// send a message to the agents responsible for holding `address`.
+    /// nodes with several of them get the message only once.
+    fn priv_send_holders(
+        &mut self,
+        dna_address: &Address,
+        address: &str,
+        msg: JsonProtocol,
+    ) -> NetResult<()> {
+        let agents = self.priv_agents(dna_address);
+        let holders: Vec<String> =
+            holders(address, agents.iter().map(String::as_str), self.redundancy)
+                .into_iter()
+                .map(String::from)
+                .collect();
+        let mut sent_to = HashSet::new();
+        for holder in holders {
+            if self
+                .senders
+                .contains_key(&cat_dna_agent(dna_address, &holder))
+            {
+                self.priv_deliver_one(dna_address, &holder, msg.clone())?;
+                continue;
+            }
+            let agent = (dna_address.clone(), holder);
+            if let Some(index) = self
+                .peers
+                .iter()
+                .position(|peer| !peer.closed && peer.agents.contains(&agent))
+            {
+                if sent_to.insert(index) {
+                    self.peers[index].send(&msg);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// when someone makes a dht data request, we route it to the closest agent
+    /// holding the address, on this node or another one.
+    /// this works because store requests go to all holders of an address.
+    fn priv_handle_get_dht_data(&mut self, msg: GetDhtData) -> NetResult<()> {
+        let dna_address = msg.dna_address.clone();
+        let address = msg.address.clone();
+        let from_agent_id = msg.from_agent_id.clone();
+        let msg_id = msg.msg_id.clone();
+        self.priv_route_request(
+            &dna_address,
+            &address,
+            &from_agent_id,
+            &msg_id,
+            JsonProtocol::HandleGetDhtData(msg),
+        )
+    }
+
+    /// like `priv_handle_get_dht_data()`, for dht meta data requests
+    fn priv_handle_get_dht_meta(&mut self, msg: GetDhtMetaData) -> NetResult<()> {
+        let dna_address = msg.dna_address.clone();
+        let address = msg.address.clone();
+        let from_agent_id = msg.from_agent_id.clone();
+        let msg_id = msg.msg_id.clone();
+        self.priv_route_request(
+            &dna_address,
+            &address,
+            &from_agent_id,
+            &msg_id,
+            JsonProtocol::HandleGetDhtMeta(msg),
+        )
+    }
+
+    fn priv_route_request(
+        &mut self,
+        dna_address: &Address,
+        address: &str,
+        from_agent_id: &str,
+        msg_id: &str,
+        msg: JsonProtocol,
+    ) -> NetResult<()> {
+        let agents = self.priv_agents(dna_address);
+        let holders: Vec<String> = request_holders(
+            address,
+            agents.iter().map(String::as_str),
+            self.redundancy,
+            from_agent_id,
+        )
+        .into_iter()
+        .map(String::from)
+        .collect();
+        if self.priv_ask_holders(dna_address, from_agent_id, msg_id, msg, holders) {
+            return Ok(());
+        }
+        self.priv_deliver_one(
+            dna_address,
+            from_agent_id,
+            JsonProtocol::FailureResult(FailureResultData {
+                msg_id: msg_id.to_string(),
+                dna_address: dna_address.clone(),
+                to_agent_id: from_agent_id.to_string(),
+                error_info: json!("could not find nodes handling this dnaAddress"),
+            }),
+        )
+    }
+
+    /// send a request to the first of the holders we can reach.
+    /// the ones after it get the request if it misses.
+    /// returns false if we could not reach any of them.
+    fn priv_ask_holders(
+        &mut self,
+        dna_address: &Address,
+        from_agent_id: &str,
+        msg_id: &str,
+        msg: JsonProtocol,
+        holders: Vec<String>,
+    ) -> bool {
+        for (index, holder) in holders.iter().enumerate() {
+            if self.priv_send_one(dna_address, holder, msg.clone()).is_ok() {
+                self.pending_requests.insert(
+                    from_agent_id,
+                    msg_id,
+                    msg,
+                    holders[index + 1..].to_vec(),
+                );
+                return true;
+            }
+        }
+        false
+    }
+
+    /// when the holder that got a request of ours missed, the next holder gets asked
+    /// instead of the requester getting the result.
+    /// returns false if the result should go to the requester.
+    fn priv_ask_next_holder(
+        &mut self,
+        dna_address: &Address,
+        requester: &str,
+        msg_id: &str,
+        content: &serde_json::Value,
+    ) -> bool {
+        match self.pending_requests.take(requester, msg_id) {
+            Some((request, holders)) => {
+                is_miss(content)
+                    && self.priv_ask_holders(dna_address, requester, msg_id, request, holders)
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    /// an agent with real keys, other nodes only believe what it signed
+    pub struct TestAgent {
+        pub agent_id: String,
+        secret_key: SecBuf,
+    }
+
+    impl TestAgent {
+        pub fn generate() -> Self {
+            let mut seed = SecBuf::with_insecure(32);
+            random_secbuf(&mut seed);
+            let mut public_key = SecBuf::with_insecure(32);
+            let mut secret_key = SecBuf::with_insecure(64);
+            sign::seed_keypair(&mut public_key, &mut secret_key, &mut seed).unwrap();
+            // agent ids also carry an encryption key, which is of no use here
+            let mut key = [0; 64];
+            key[..32].copy_from_slice(&public_key.read_lock()[..]);
+            TestAgent {
+                agent_id: KeyBuffer::with_raw(&key).render(),
+                secret_key,
+            }
+        }
+
+        pub fn sign(&mut self, data: &str) -> String {
+            let mut message = secbuf_from_bytes(data.as_bytes());
+            let mut signature = SecBuf::with_insecure(SIGNATURE_LEN);
+            sign::sign(&mut message, &mut self.secret_key, &mut signature).unwrap();
+            let signature = signature.read_lock();
+            base64::encode(&signature[..])
+        }
+
+        /// what the agent answers when asked to sign the challenge of another node
+        pub fn sign_challenge(&mut self, mut msg: PeerChallengeData) -> JsonProtocol {
+            msg.agent_id = self.agent_id.clone();
+            msg.signature = self.sign(&msg.signed_data());
+            JsonProtocol::HandleSignPeerChallengeResult(msg)
+        }
+    }
+
+    #[test]
+    fn it_checks_signatures() {
+        let mut agent = TestAgent::generate();
+        let signature = agent.sign("data");
+        assert!(is_signed(&agent.agent_id, "data", &signature));
+        assert!(!is_signed(&agent.agent_id, "other data", &signature));
+        assert!(!is_signed(
+            &TestAgent::generate().agent_id,
+            "data",
+            &signature
+        ));
+        assert!(!is_signed("agent-hash-test-1", "data", &signature));
+        assert!(!is_signed(&agent.agent_id, "data", "not a signature"));
+    }
+
+    #[test]
+    #[cfg_attr(tarpaulin, skip)]
+    fn it_only_believes_agents_that_signed_its_challenge() {
+        let mut server = TcpServer::new("127.0.0.1:0", &[], 1).unwrap();
+        let mut stream = TcpStream::connect(server.address()).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while server.peers.is_empty() {
+            server.tick().unwrap();
+            assert!(Instant::now() < deadline, "the node did not accept");
+        }
+        let challenge = |agent_id: &str, challenge: &str| PeerChallengeData {
+            dna_address: Address::from("test_dna"),
+            agent_id: agent_id.to_string(),
+            challenge: challenge.to_string(),
+            signature: String::new(),
+        };
+        let our_challenge = server.peers[0].challenge.clone();
+        let mut agent = TestAgent::generate();
+        let impostor = TestAgent::generate().agent_id;
+        let mut forged = challenge(&impostor, &our_challenge);
+        forged.signature = agent.sign(&forged.signed_data());
+        let claims = vec![
+            JsonProtocol::HandleSignPeerChallengeResult(forged),
+            agent.sign_challenge(challenge("", "another challenge")),
+            agent.sign_challenge(challenge("", &our_challenge)),
+        ];
+        for claim in claims.iter() {
+            let line = format!("{}\n", String::from(JsonString::from(claim)));
+            stream.write_all(line.as_bytes()).unwrap();
+        }
+        while server.peers[0].agents.is_empty() {
+            server.tick().unwrap();
+            assert!(Instant::now() < deadline, "the agent did not connect");
+        }
+        let agents: Vec<(Address, String)> = server.peers[0].agents.iter().cloned().collect();
+        assert_eq!(agents, vec![(Address::from("test_dna"), agent.agent_id)]);
+    }
+
+    #[test]
+    #[cfg_attr(tarpaulin, skip)]
+    fn it_drops_messages_of_agents_that_did_not_sign_its_challenge() {
+        let mut server = TcpServer::new("127.0.0.1:0", &[], 1).unwrap();
+        let (sender, receiver) = mpsc::channel();
+        server
+            .register(&Address::from("test_dna"), "local_agent", sender)
+            .unwrap();
+        let mut stream = TcpStream::connect(server.address()).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while server.peers.is_empty() {
+            server.tick().unwrap();
+            assert!(Instant::now() < deadline, "the node did not accept");
+        }
+        let mut agent = TestAgent::generate();
+        let impostor = TestAgent::generate().agent_id;
+        let message = |from_agent_id: &str, data: &str| {
+            JsonProtocol::HandleSendMessage(MessageData {
+                msg_id: data.to_string(),
+                dna_address: Address::from("test_dna"),
+                to_agent_id: "local_agent".to_string(),
+                from_agent_id: from_agent_id.to_string(),
+                data: json!(data),
+            })
+        };
+        let lines = vec![
+            message(&agent.agent_id, "before signing"),
+            agent.sign_challenge(PeerChallengeData {
+                dna_address: Address::from("test_dna"),
+                agent_id: String::new(),
+                challenge: server.peers[0].challenge.clone(),
+                signature: String::new(),
+            }),
+            message(&impostor, "from the impostor"),
+            message(&agent.agent_id, "after signing"),
+        ];
+        for line in lines.iter() {
+            let line = format!("{}\n", String::from(JsonString::from(line)));
+            stream.write_all(line.as_bytes()).unwrap();
+        }
+        // messages get delivered in the order they were sent
+        loop {
+            server.tick().unwrap();
+            if let Ok(protocol) = receiver.try_recv() {
+                if let Ok(JsonProtocol::HandleSendMessage(msg)) = JsonProtocol::try_from(protocol) {
+                    assert_eq!(msg.msg_id, "after signing");
+                    break;
+                }
+            }
+            assert!(Instant::now() < deadline, "the message was not delivered");
+        }
+    }
+
+    #[test]
+    #[cfg_attr(tarpaulin, skip)]
+    fn it_disconnects_nodes_sending_overlong_lines() {
+        let mut server = TcpServer::new("127.0.0.1:0", &[], 1).unwrap();
+        let mut stream = TcpStream::connect(server.address()).unwrap();
+        let (failed_sender, failed_receiver) = mpsc::channel();
+        thread::spawn(move || {
+            // a line of twice the length we accept, without a line break
+            let chunk = vec![b'x'; 1024 * 1024];
+            let failed =
+                (0..2 * MAX_LINE_LEN / chunk.len()).any(|_| stream.write_all(&chunk).is_err());
+            failed_sender.send(failed).unwrap();
+        });
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            server.tick().unwrap();
+            if let Ok(failed) = failed_receiver.try_recv() {
+                assert!(failed, "the node read the whole line");
+                break;
+            }
+            assert!(Instant::now() < deadline, "the node kept reading");
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(server.peers.is_empty());
+    }
+
+    #[test]
+    fn it_reads_bootstrap_nodes() {
+        assert_eq!(socket_address("127.0.0.1:4000"), "127.0.0.1:4000");
+        assert_eq!(
+            socket_address(
+                "/ip4/127.0.0.1/tcp/45737/ipfs/QmYaEMe288imZVHnHeNby75m9V6mwjqu6W71cEuziEBC5i"
+            ),
+            "127.0.0.1:45737"
+        );
+        assert_eq!(socket_address("/ip6/::1/tcp/4000"), "[::1]:4000");
+        assert_eq!(
+            socket_address("/dns4/example.com/tcp/4000"),
+            "example.com:4000"
+        );
+    }
+}
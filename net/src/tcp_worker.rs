@@ -0,0 +1,417 @@
+//! provides a p2p worker connecting to other nodes over TCP, without any external process
+
+use crate::{sharding::redundancy, tcp_server::*};
+use holochain_core_types::{cas::content::Address, json::JsonString};
+use holochain_net_connection::{
+    json_protocol::JsonProtocol,
+    net_connection::{NetHandler, NetWorker},
+    protocol::Protocol,
+    NetResult,
+};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    convert::TryFrom,
+    sync::{mpsc, Mutex},
+};
+
+/// a p2p worker talking to other nodes over direct TCP connections
+pub struct TcpWorker {
+    handler: NetHandler,
+    receiver_per_dna: HashMap<Address, mpsc::Receiver<Protocol>>,
+    // key of our TcpServer in the TCP_SERVER_MAP
+    server_key: String,
+    endpoint: String,
+}
+
+impl NetWorker for TcpWorker {
+    /// we got a message from holochain core
+    /// forward to our TCP node
+    fn receive(&mut self, data: Protocol) -> NetResult<()> {
+        let server_map = TCP_SERVER_MAP.read().unwrap();
+        let mut server = server_map
+            .get(&self.server_key)
+            .expect("TcpServer should have been initialized by now")
+            .lock()
+            .unwrap();
+        if let Ok(json_msg) = JsonProtocol::try_from(&data) {
+            if let JsonProtocol::TrackDna(track_msg) = json_msg {
+                match self
+                    .receiver_per_dna
+                    .entry(track_msg.dna_address.to_owned())
+                {
+                    Entry::Occupied(_) => (),
+                    Entry::Vacant(e) => {
+                        let (tx, rx) = mpsc::channel();
+                        server.register(&track_msg.dna_address, &track_msg.agent_id, tx)?;
+                        e.insert(rx);
+                    }
+                };
+            }
+        }
+        server.handle(data)?;
+        Ok(())
+    }
+
+    /// let our TCP node talk to the other nodes,
+    /// then check for messages it got for us
+    fn tick(&mut self) -> NetResult<bool> {
+        let mut did_something = {
+            let server_map = TCP_SERVER_MAP.read().unwrap();
+            let mut server = server_map
+                .get(&self.server_key)
+                .expect("TcpServer should have been initialized by now")
+                .lock()
+                .unwrap();
+            server.tick()?
+        };
+        for (_, receiver) in self.receiver_per_dna.iter_mut() {
+            if let Ok(data) = receiver.try_recv() {
+                did_something = true;
+                (self.handler)(Ok(data))?;
+            }
+        }
+        Ok(did_something)
+    }
+
+    /// stop the net worker
+    fn stop(self: Box<Self>) -> NetResult<()> {
+        Ok(())
+    }
+
+    /// Set the address our node listens on as worker's endpoint
+    fn endpoint(&self) -> Option<String> {
+        Some(self.endpoint.clone())
+    }
+}
+
+impl TcpWorker {
+    /// create a new TCP worker.
+    /// Workers with the same `bindAddress` share one node, unless the port is 0,
+    /// which gets every worker its own node on a free port.
+    pub fn new(handler: NetHandler, backend_config: &JsonString) -> NetResult<Self> {
+        let config: serde_json::Value = serde_json::from_str(backend_config.into())?;
+        let bind_address = config["bindAddress"]
+            .as_str()
+            .unwrap_or("127.0.0.1:0")
+            .to_string();
+        let bootstrap_nodes: Vec<String> = config["bootstrapNodes"]
+            .as_array()
+            .map(|nodes| {
+                nodes
+                    .iter()
+                    .filter_map(|node| node.as_str())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        // Create a node for that address if it doesn't already exist
+        let mut server_map = TCP_SERVER_MAP.write().unwrap();
+        let shared = !bind_address.ends_with(":0");
+        let server_key = if shared && server_map.contains_key(&bind_address) {
+            bind_address
+        } else {
+            let server = TcpServer::new(&bind_address, &bootstrap_nodes, redundancy(&config))?;
+            let server_key = if shared {
+                bind_address
+            } else {
+                server.address()
+            };
+            server_map.insert(server_key.clone(), Mutex::new(server));
+            server_key
+        };
+        let mut server = server_map
+            .get(&server_key)
+            .expect("TcpServer should exist")
+            .lock()
+            .unwrap();
+        server.clock_in();
+
+        Ok(TcpWorker {
+            handler,
+            receiver_per_dna: HashMap::new(),
+            endpoint: server.address(),
+            server_key,
+        })
+    }
+}
+
+// unregister on Drop, the last worker shuts the node down
+impl Drop for TcpWorker {
+    fn drop(&mut self) {
+        let mut server_map = TCP_SERVER_MAP.write().unwrap();
+        let is_empty = server_map
+            .get(&self.server_key)
+            .expect("TcpServer should exist")
+            .lock()
+            .unwrap()
+            .clock_out();
+        if is_empty {
+            server_map.remove(&self.server_key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{p2p_config::P2pConfig, tcp_server::tests::TestAgent};
+
+    use holochain_core_types::cas::content::Address;
+    use holochain_net_connection::json_protocol::{
+        DhtData, GetDhtData, JsonProtocol, MessageData, TrackDnaData,
+    };
+    use std::{
+        collections::VecDeque,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    fn example_dna_address() -> Address {
+        "blabladnaAddress".into()
+    }
+
+    /// a worker with the agent tracking the dna on it
+    struct TestNode {
+        worker: Box<TcpWorker>,
+        receiver: mpsc::Receiver<Protocol>,
+        agent: TestAgent,
+        // what the worker handed to the agent, besides challenges
+        received: VecDeque<JsonProtocol>,
+    }
+
+    impl TestNode {
+        fn agent_id(&self) -> String {
+            self.agent.agent_id.clone()
+        }
+
+        /// the agent signs the challenges of the other nodes, like core does
+        fn tick(&mut self) {
+            self.worker.tick().unwrap();
+            while let Ok(data) = self.receiver.try_recv() {
+                match JsonProtocol::try_from(data).unwrap() {
+                    JsonProtocol::HandleSignPeerChallenge(msg) => {
+                        let result = self.agent.sign_challenge(msg);
+                        self.worker.receive(result.into()).unwrap();
+                    }
+                    msg => self.received.push_back(msg),
+                }
+            }
+        }
+    }
+
+    fn tcp_node(bootstrap_nodes: &[String]) -> TestNode {
+        let (handler_send, handler_recv) = mpsc::channel::<Protocol>();
+        let config = JsonString::from(P2pConfig::tcp_backend_string(
+            "127.0.0.1:0",
+            bootstrap_nodes,
+        ));
+        let worker = TcpWorker::new(
+            Box::new(move |r| {
+                handler_send.send(r?)?;
+                Ok(())
+            }),
+            &config,
+        )
+        .unwrap();
+        let mut node = TestNode {
+            worker: Box::new(worker),
+            receiver: handler_recv,
+            agent: TestAgent::generate(),
+            received: VecDeque::new(),
+        };
+        let agent_id = node.agent_id();
+        node.worker
+            .receive(
+                JsonProtocol::TrackDna(TrackDnaData {
+                    dna_address: example_dna_address(),
+                    agent_id,
+                })
+                .into(),
+            )
+            .unwrap();
+        node
+    }
+
+    /// tick both nodes until the first one has got a message,
+    /// skipping notifications about connected peers
+    fn next_message(node: &mut TestNode, other: &mut TestNode) -> JsonProtocol {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            node.tick();
+            other.tick();
+            while let Some(msg) = node.received.pop_front() {
+                match msg {
+                    JsonProtocol::PeerConnected(_) => (),
+                    msg => return msg,
+                }
+            }
+            assert!(Instant::now() < deadline, "no message arrived");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// tick both nodes until the first one knows about the agent
+    fn wait_for_peer(node: &mut TestNode, other: &mut TestNode, agent_id: &str) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            node.tick();
+            other.tick();
+            while let Some(msg) = node.received.pop_front() {
+                if let JsonProtocol::PeerConnected(msg) = msg {
+                    if msg.agent_id == agent_id {
+                        return;
+                    }
+                }
+            }
+            assert!(Instant::now() < deadline, "peer did not connect");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    #[cfg_attr(tarpaulin, skip)]
+    fn can_tcp_network_flow() {
+        let mut node_1 = tcp_node(&[]);
+        let mut node_2 = tcp_node(&[node_1.worker.endpoint().unwrap()]);
+        assert_ne!(node_1.worker.endpoint(), node_2.worker.endpoint());
+        let agent_id_1 = node_1.agent_id();
+        let agent_id_2 = node_2.agent_id();
+
+        // both nodes learn about both agents
+        wait_for_peer(&mut node_1, &mut node_2, &agent_id_2);
+        wait_for_peer(&mut node_2, &mut node_1, &agent_id_1);
+
+        // node2node: send & receive
+        node_1
+            .worker
+            .receive(
+                JsonProtocol::SendMessage(MessageData {
+                    dna_address: example_dna_address(),
+                    to_agent_id: agent_id_2.clone(),
+                    from_agent_id: agent_id_1.clone(),
+                    msg_id: "yada".to_string(),
+                    data: json!("hello"),
+                })
+                .into(),
+            )
+            .unwrap();
+        let res = next_message(&mut node_2, &mut node_1);
+        if let JsonProtocol::HandleSendMessage(msg) = res {
+            node_2
+                .worker
+                .receive(
+                    JsonProtocol::HandleSendMessageResult(MessageData {
+                        dna_address: msg.dna_address,
+                        to_agent_id: msg.from_agent_id,
+                        from_agent_id: agent_id_2.clone(),
+                        msg_id: msg.msg_id,
+                        data: json!(format!("echo: {}", msg.data.to_string())),
+                    })
+                    .into(),
+                )
+                .unwrap();
+        } else {
+            panic!("Did not expect to receive: {:?}", res);
+        }
+        let res = next_message(&mut node_1, &mut node_2);
+        if let JsonProtocol::SendMessageResult(msg) = res {
+            assert_eq!("\"echo: \\\"hello\\\"\"".to_string(), msg.data.to_string());
+        } else {
+            panic!("Did not expect to receive: {:?}", res);
+        }
+
+        // -- dht publish / store -- //
+        let data = DhtData {
+            msg_id: "yada".to_string(),
+            dna_address: example_dna_address(),
+            agent_id: agent_id_2.clone(),
+            address: "hello".to_string(),
+            content: json!("test-data"),
+        };
+        node_2
+            .worker
+            .receive(JsonProtocol::PublishDhtData(data.clone()).into())
+            .unwrap();
+        let store = JsonProtocol::HandleStoreDhtData(data);
+        assert_eq!(next_message(&mut node_1, &mut node_2), store);
+        assert_eq!(next_message(&mut node_2, &mut node_1), store);
+
+        // -- dht get -- //
+        node_1
+            .worker
+            .receive(
+                JsonProtocol::GetDhtData(GetDhtData {
+                    msg_id: "yada".to_string(),
+                    dna_address: example_dna_address(),
+                    from_agent_id: agent_id_1.clone(),
+                    address: "hello".to_string(),
+                })
+                .into(),
+            )
+            .unwrap();
+        // both agents hold the address, so the request goes to the other one
+        let res = next_message(&mut node_2, &mut node_1);
+        if let JsonProtocol::HandleGetDhtData(msg) = res {
+            node_2
+                .worker
+                .receive(
+                    JsonProtocol::HandleGetDhtDataResult(DhtData {
+                        msg_id: msg.msg_id.clone(),
+                        dna_address: msg.dna_address.clone(),
+                        agent_id: msg.from_agent_id.clone(),
+                        address: msg.address.clone(),
+                        content: json!(format!("data-for: {}", msg.address)),
+                    })
+                    .into(),
+                )
+                .unwrap();
+        } else {
+            panic!("Did not expect to receive: {:?}", res);
+        }
+        let res = next_message(&mut node_1, &mut node_2);
+        if let JsonProtocol::GetDhtDataResult(msg) = res {
+            assert_eq!("\"data-for: hello\"".to_string(), msg.content.to_string());
+        } else {
+            panic!("Did not expect to receive: {:?}", res);
+        }
+
+        // cleanup
+        node_1.worker.stop().unwrap();
+        node_2.worker.stop().unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(tarpaulin, skip)]
+    fn tcp_nodes_tell_when_peers_go_away() {
+        let mut node_1 = tcp_node(&[]);
+        let mut node_2 = tcp_node(&[node_1.worker.endpoint().unwrap()]);
+        let agent_id_2 = node_2.agent_id();
+        wait_for_peer(&mut node_1, &mut node_2, &agent_id_2);
+        drop(node_2);
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            node_1.tick();
+            if let Some(JsonProtocol::PeerDisconnected(msg)) = node_1.received.pop_front() {
+                assert_eq!(msg.agent_id, agent_id_2);
+                break;
+            }
+            assert!(Instant::now() < deadline, "peer did not disconnect");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    #[cfg_attr(tarpaulin, skip)]
+    fn tcp_workers_share_fixed_addresses() {
+        let node_1 = tcp_node(&[]);
+        let address = node_1.worker.endpoint().unwrap();
+        drop(node_1);
+        // the port got released when the last worker went away
+        let config = JsonString::from(P2pConfig::tcp_backend_string(&address, &[]));
+        let worker_2 = TcpWorker::new(Box::new(|_r| Ok(())), &config).unwrap();
+        let worker_3 = TcpWorker::new(Box::new(|_r| Ok(())), &config).unwrap();
+        assert_eq!(worker_2.endpoint(), Some(address.clone()));
+        assert_eq!(worker_3.endpoint(), Some(address));
+    }
+}
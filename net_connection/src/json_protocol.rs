@@ -5,11 +5,17 @@
 use serde_json;
 
 use failure::Error;
-use holochain_core_types::{cas::content::Address, error::HolochainError, json::JsonString};
+use holochain_core_types::{
+    cas::content::Address, error::HolochainError, json::JsonString, link::LinkTagMatch,
+};
 use std::convert::TryFrom;
 
 use super::protocol::Protocol;
 
+/// Prefix of the data agents sign to prove to another node that the node announcing
+/// them runs them. Agents never sign anything else with this prefix.
+pub const PEER_CHALLENGE_SIGNATURE_DOMAIN: &str = "holochain-peer-challenge:";
+
 fn get_default_state_id() -> String {
     "undefined".to_string()
 }
@@ -46,6 +52,33 @@ pub struct PeerData {
     pub agent_id: String,
 }
 
+/// A challenge a node picked for its connection to another node, which the agents of the
+/// other node sign to prove that they run there
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, DefaultJson)]
+pub struct PeerChallengeData {
+    #[serde(rename = "dnaAddress")]
+    pub dna_address: Address,
+
+    #[serde(rename = "agentId")]
+    pub agent_id: String,
+
+    pub challenge: String,
+
+    /// empty until the agent signed `signed_data()`
+    #[serde(default)]
+    pub signature: String,
+}
+
+impl PeerChallengeData {
+    /// what the agent signs, see PEER_CHALLENGE_SIGNATURE_DOMAIN
+    pub fn signed_data(&self) -> String {
+        format!(
+            "{}{}:{}:{}",
+            PEER_CHALLENGE_SIGNATURE_DOMAIN, self.challenge, self.dna_address, self.agent_id
+        )
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, DefaultJson)]
 pub struct MessageData {
     #[serde(rename = "_id")]
@@ -144,6 +177,10 @@ pub struct GetDhtMetaData {
 
     pub address: String,
     pub attribute: String,
+
+    /// How the tag of a request for links has to match
+    #[serde(default, rename = "tagMatch")]
+    pub tag_match: LinkTagMatch,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, DefaultJson)]
@@ -208,6 +245,16 @@ pub enum JsonProtocol {
     /// Notification of a connection from another peer.
     #[serde(rename = "peerConnected")]
     PeerConnected(PeerData),
+    /// Notification that another peer went away.
+    #[serde(rename = "peerDisconnected")]
+    PeerDisconnected(PeerData),
+    /// Another node wants the agent to sign its challenge before it believes
+    /// that the agent is run by this node.
+    #[serde(rename = "handleSignPeerChallenge")]
+    HandleSignPeerChallenge(PeerChallengeData),
+    /// The agent's signature of a `HandleSignPeerChallenge` challenge.
+    #[serde(rename = "handleSignPeerChallengeResult")]
+    HandleSignPeerChallengeResult(PeerChallengeData),
 
     /// Send a message to another peer on the network
     #[serde(rename = "sendMessage")]
@@ -375,6 +422,26 @@ mod tests {
         }));
     }
 
+    #[test]
+    fn it_can_convert_peer_disconnected() {
+        test_convert!(JsonProtocol::PeerDisconnected(PeerData {
+            dna_address: "test_dna".into(),
+            agent_id: "test_id".to_string(),
+        }));
+    }
+
+    #[test]
+    fn it_can_convert_peer_challenge() {
+        test_convert!(JsonProtocol::HandleSignPeerChallengeResult(
+            PeerChallengeData {
+                dna_address: "test_dna".into(),
+                agent_id: "test_id".to_string(),
+                challenge: "test_challenge".to_string(),
+                signature: "test_signature".to_string(),
+            }
+        ));
+    }
+
     #[test]
     fn it_can_convert_send_message() {
         test_convert!(JsonProtocol::SendMessage(MessageData {
@@ -5,7 +5,7 @@ use holochain_container_api::{
     },
     logger::LogRules,
 };
-use holochain_core_types::agent::AgentId;
+use holochain_core::agent::keys::test_keypair;
 use neon::prelude::*;
 use std::{collections::HashMap, path::PathBuf};
 
@@ -58,11 +58,10 @@ fn make_config(instance_data: Vec<InstanceData>, logger: LoggerConfiguration) ->
         let agent_name = instance.agent.name;
         let mut dna_data = instance.dna;
         let agent_config = agent_configs.entry(agent_name.clone()).or_insert_with(|| {
-            let agent_key = AgentId::generate_fake(&agent_name);
             let config = AgentConfiguration {
                 id: agent_name.clone(),
                 name: agent_name.clone(),
-                public_address: agent_key.key,
+                public_address: test_keypair(&agent_name).address().to_string(),
                 key_file: format!("fake/key/{}", agent_name),
             };
             config
@@ -78,6 +77,9 @@ fn make_config(instance_data: Vec<InstanceData>, logger: LoggerConfiguration) ->
             agent: agent_id,
             dna: dna_id,
             storage: StorageConfiguration::Memory,
+            properties: None,
+            migrated_from: None,
+            dht_quota: None,
         };
         instance_configs.push(instance);
     }
@@ -10,6 +10,7 @@ use std::{
 use holochain_container_api::{
     config::{load_configuration, Configuration},
     container::Container as RustContainer,
+    keystore::test_key_loader,
 };
 use holochain_core::{
     action::Action,
@@ -65,7 +66,8 @@ declare_types! {
             } else {
                 panic!("Invalid type specified for config, must be object or string");
             };
-            let container = RustContainer::from_config(config);
+            // test containers don't have keystores, agents get test keys for their name
+            let container = RustContainer::from_config(config).with_key_loader(test_key_loader());
             let is_running = Arc::new(Mutex::new(false));
 
             Ok(TestContainer { container, sender_tx: None, is_running, is_started: false })
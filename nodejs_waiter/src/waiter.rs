@@ -182,9 +182,12 @@ impl Waiter {
                                 *aw.action() == Action::AddLink(link_add.clone().link().clone())
                             });
                         }
-                        Entry::LinkRemove(_link_remove) => {
+                        Entry::LinkRemove(link_remove) => {
                             checker.add(move |aw| *aw.action() == Action::Hold(entry.clone()));
-                            println!("warn/waiter: LinkRemove not implemented!");
+                            checker.add(move |aw| {
+                                *aw.action()
+                                    == Action::RemoveLink(link_remove.clone().link().clone())
+                            });
                         }
                         _ => (),
                     },
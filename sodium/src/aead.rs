@@ -1,7 +1,7 @@
 //! This module provides access to libsodium
 
 use super::{check_init, secbuf::SecBuf};
-use crate::error::SodiumResult;
+use crate::error::{SodiumError, SodiumResult};
 
 /// Used to set the size of nonce var in the enc fns
 pub const NONCEBYTES: usize =
@@ -10,6 +10,8 @@ pub const NONCEBYTES: usize =
 ///
 /// Note: look at the test cases to see how it is used
 pub const ABYTES: usize = rust_sodium_sys::crypto_aead_xchacha20poly1305_ietf_ABYTES as usize;
+/// Size of the symmetric secret key
+pub const KEYBYTES: usize = rust_sodium_sys::crypto_aead_xchacha20poly1305_ietf_KEYBYTES as usize;
 
 /// Generate symmetric cipher text given a message, secret, and optional auth data
 ///
@@ -79,6 +81,33 @@ pub fn dec(
     nonce: &mut SecBuf,
     cipher: &mut SecBuf,
 ) -> SodiumResult<()> {
+    decrypt(decrypted_message, secret, adata, nonce, cipher);
+    Ok(())
+}
+
+/// Same as `dec`, but fails if the cipher text could not be authenticated,
+/// i.e. if it got tampered with or the secret or auth data don't match.
+/// `dec` leaves the output buffer untouched in that case.
+pub fn dec_verified(
+    decrypted_message: &mut SecBuf,
+    secret: &mut SecBuf,
+    adata: Option<&mut SecBuf>,
+    nonce: &mut SecBuf,
+    cipher: &mut SecBuf,
+) -> SodiumResult<()> {
+    match decrypt(decrypted_message, secret, adata, nonce, cipher) {
+        0 => Ok(()),
+        _ => Err(SodiumError::new("could not authenticate cipher text")),
+    }
+}
+
+fn decrypt(
+    decrypted_message: &mut SecBuf,
+    secret: &mut SecBuf,
+    adata: Option<&mut SecBuf>,
+    nonce: &mut SecBuf,
+    cipher: &mut SecBuf,
+) -> libc::c_int {
     check_init();
     let my_adata_locker;
     let mut my_adata = std::ptr::null();
@@ -106,9 +135,8 @@ pub fn dec(
             my_ad_len,
             raw_ptr_char_immut!(nonce),
             raw_ptr_char_immut!(secret),
-        );
+        )
     }
-    Ok(())
 }
 
 #[cfg(test)]
@@ -237,4 +265,56 @@ mod tests {
             format!("{:?}", *decrypted_message)
         );
     }
+
+    #[test]
+    fn it_should_fail_verified_decryption_with_bad_aead() {
+        let mut message = SecBuf::with_secure(16);
+        random_secbuf(&mut message);
+
+        let mut secret = SecBuf::with_secure(KEYBYTES);
+        random_secbuf(&mut secret);
+
+        let mut adata = SecBuf::with_secure(16);
+        random_secbuf(&mut adata);
+
+        let mut adata1 = SecBuf::with_secure(16);
+        random_secbuf(&mut adata1);
+
+        let mut nonce = SecBuf::with_insecure(NONCEBYTES);
+        random_secbuf(&mut nonce);
+
+        let mut cipher = SecBuf::with_insecure(message.len() + ABYTES);
+        enc(
+            &mut message,
+            &mut secret,
+            Some(&mut adata),
+            &mut nonce,
+            &mut cipher,
+        )
+        .unwrap();
+
+        let mut decrypted_message = SecBuf::with_insecure(message.len());
+        assert!(dec_verified(
+            &mut decrypted_message,
+            &mut secret,
+            Some(&mut adata1),
+            &mut nonce,
+            &mut cipher,
+        )
+        .is_err());
+        dec_verified(
+            &mut decrypted_message,
+            &mut secret,
+            Some(&mut adata),
+            &mut nonce,
+            &mut cipher,
+        )
+        .unwrap();
+        let message = message.read_lock();
+        let decrypted_message = decrypted_message.read_lock();
+        assert_eq!(
+            format!("{:?}", *message),
+            format!("{:?}", *decrypted_message)
+        );
+    }
 }
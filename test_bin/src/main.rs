@@ -281,6 +281,7 @@ fn confirm_published_metadata(
             from_agent_id: BILLY_AGENT_ID.to_string(),
             address: address.to_string(),
             attribute: META_ATTRIBUTE.to_string(),
+            tag_match: Default::default(),
         })
         .into(),
     )?;
@@ -506,6 +507,7 @@ fn meta_test(alex: &mut P2pNode, billy: &mut P2pNode, can_connect: bool) -> NetR
             from_agent_id: BILLY_AGENT_ID.to_string(),
             address: ENTRY_ADDRESS_3.to_string(),
             attribute: META_ATTRIBUTE.to_string(),
+            tag_match: Default::default(),
         })
         .into(),
     )?;
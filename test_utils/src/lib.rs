@@ -10,12 +10,12 @@ extern crate wabt;
 use holochain_container_api::{context_builder::ContextBuilder, error::HolochainResult, Holochain};
 use holochain_core::{
     action::Action,
+    agent::keys::{test_keypair, MemoryRotatedKeyStore},
     context::Context,
     logger::{test_logger, TestLogger},
     signal::Signal,
 };
 use holochain_core_types::{
-    agent::AgentId,
     cas::content::Address,
     dna::{
         capabilities::{Capability, CapabilityCall, CapabilityType},
@@ -174,12 +174,15 @@ pub fn test_context_and_logger_with_network_name(
     agent_name: &str,
     network_name: Option<&str>,
 ) -> (Arc<Context>, Arc<Mutex<TestLogger>>) {
-    let agent = AgentId::generate_fake(agent_name);
+    let keys = test_keypair(agent_name);
+    let agent = keys.agent_id(agent_name);
     let logger = test_logger();
     (
         Arc::new({
             let mut builder = ContextBuilder::new()
                 .with_agent(agent)
+                .with_agent_keys(keys)
+                .with_rotated_key_store(Arc::new(MemoryRotatedKeyStore::default()))
                 .with_logger(logger.clone())
                 .with_file_storage(tempdir().unwrap().path().to_str().unwrap())
                 .expect("Tempdir must be accessible");
@@ -188,7 +191,7 @@ pub fn test_context_and_logger_with_network_name(
                     JsonString::from(P2pConfig::new_with_memory_backend(network_name).as_str());
                 builder = builder.with_network_config(config);
             }
-            builder.spawn()
+            builder.spawn().expect("Context should get created")
         }),
         logger,
     )
@@ -240,13 +243,17 @@ pub fn hc_setup_and_call_zome_fn(wasm_path: &str, fn_name: &str) -> HolochainRes
 
 /// create a test context and TestLogger pair so we can use the logger in assertions
 pub fn create_test_context(agent_name: &str) -> Arc<Context> {
-    let agent = AgentId::generate_fake(agent_name);
+    let keys = test_keypair(agent_name);
+    let agent = keys.agent_id(agent_name);
     Arc::new(
         ContextBuilder::new()
             .with_agent(agent)
+            .with_agent_keys(keys)
+            .with_rotated_key_store(Arc::new(MemoryRotatedKeyStore::default()))
             .with_file_storage(tempdir().unwrap().path().to_str().unwrap())
             .expect("Tempdir must be accessible")
-            .spawn(),
+            .spawn()
+            .expect("Context should get created"),
     )
 }
 
@@ -0,0 +1,18 @@
+use holochain_core_types::{error::HolochainError, json::*};
+
+/// Struct for input data received when Zome API function start_bundle() is invoked
+#[derive(Deserialize, Clone, PartialEq, Debug, Serialize, DefaultJson)]
+pub struct StartBundleArgs {
+    /// milliseconds after which the bundle gets discarded if it was not closed
+    pub timeout: usize,
+    pub user_param: serde_json::Value,
+}
+
+/// What to do with the commits of a bundle when it gets closed
+#[derive(Deserialize, Clone, PartialEq, Debug, Serialize, DefaultJson)]
+pub enum BundleOnClose {
+    /// write all commits of the bundle to the source chain and the DHT
+    Commit,
+    /// drop all commits of the bundle
+    Discard,
+}
@@ -0,0 +1,18 @@
+use holochain_core_types::{
+    cas::content::Address, dna::capabilities::CapabilityType, entry::cap_entries::CapTokenGrant,
+    error::HolochainError, json::*,
+};
+
+/// Struct for input data received when Zome API function grant_capability() is invoked
+#[derive(Deserialize, Clone, PartialEq, Debug, Serialize, DefaultJson)]
+pub struct GrantCapabilityArgs {
+    pub cap_name: String,
+    pub cap_type: CapabilityType,
+    pub assignees: Option<Vec<Address>>,
+}
+
+/// Struct for the result of Zome API function list_grants()
+#[derive(Deserialize, Clone, PartialEq, Debug, Serialize, DefaultJson)]
+pub struct ListGrantsResult {
+    pub grants: Vec<CapTokenGrant>,
+}
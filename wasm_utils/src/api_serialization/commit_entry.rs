@@ -0,0 +1,10 @@
+use holochain_core_types::{cas::content::Address, entry::Entry, error::HolochainError, json::*};
+
+/// Struct for input data received when Zome API function commit_entry() is invoked
+/// for an entry that gets encrypted for recipients
+#[derive(Deserialize, Clone, PartialEq, Debug, Serialize, DefaultJson)]
+pub struct CommitEntryArgs {
+    pub entry: Entry,
+    /// Agent addresses the entry gets encrypted for, in addition to its author
+    pub recipients: Vec<Address>,
+}
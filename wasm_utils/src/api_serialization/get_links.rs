@@ -1,4 +1,8 @@
-use holochain_core_types::{cas::content::Address, error::HolochainError, json::*, time::Timeout};
+use holochain_core_types::{
+    cas::content::Address, error::HolochainError, json::*, link::Link, time::Timeout,
+};
+
+pub use holochain_core_types::link::LinkTagMatch;
 
 #[derive(Deserialize, Default, Debug, Serialize, Clone, PartialEq, Eq, Hash, DefaultJson)]
 pub struct GetLinksArgs {
@@ -24,6 +28,8 @@ pub struct GetLinksOptions {
     pub status_request: LinksStatusRequestKind,
     pub sources: bool,
     pub timeout: Timeout,
+    #[serde(default)]
+    pub tag_match: LinkTagMatch,
 }
 impl Default for GetLinksOptions {
     fn default() -> Self {
@@ -31,6 +37,7 @@ impl Default for GetLinksOptions {
             status_request: LinksStatusRequestKind::default(),
             sources: false,
             timeout: Default::default(),
+            tag_match: LinkTagMatch::default(),
         }
     }
 }
@@ -38,14 +45,31 @@ impl Default for GetLinksOptions {
 #[derive(Deserialize, Serialize, Debug, DefaultJson)]
 pub struct GetLinksResult {
     addresses: Vec<Address>,
+    /// The links themselves, with their actual tags and payloads
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    links: Vec<Link>,
 }
 
 impl GetLinksResult {
     pub fn new(addresses: Vec<Address>) -> GetLinksResult {
-        GetLinksResult { addresses }
+        GetLinksResult {
+            addresses,
+            links: Vec::new(),
+        }
+    }
+
+    pub fn from_links(links: Vec<Link>) -> GetLinksResult {
+        GetLinksResult {
+            addresses: links.iter().map(|link| link.target().clone()).collect(),
+            links,
+        }
     }
 
     pub fn addresses(&self) -> &Vec<Address> {
         &self.addresses
     }
+
+    pub fn links(&self) -> &Vec<Link> {
+        &self.links
+    }
 }
@@ -5,10 +5,21 @@ pub struct LinkEntriesArgs {
     pub base: Address,
     pub target: Address,
     pub tag: String,
+    /// JSON to put on the link itself
+    #[serde(default)]
+    pub payload: Option<String>,
 }
 
 impl LinkEntriesArgs {
     pub fn to_link(&self) -> Link {
-        Link::new(&self.base, &self.target, &self.tag)
+        match &self.payload {
+            Some(payload) => Link::new_with_payload(
+                &self.base,
+                &self.target,
+                &self.tag,
+                JsonString::from(payload.clone()),
+            ),
+            None => Link::new(&self.base, &self.target, &self.tag),
+        }
     }
 }
@@ -9,13 +9,20 @@ mod call;
 ///
 /// For the case of HDK-rust we can use the exact same types by
 /// importing this module.
+pub mod bundle;
+pub mod capabilities;
+mod commit_entry;
 pub mod get_entry;
 pub mod get_links;
 pub mod link_entries;
+pub mod peers;
+pub mod property;
 pub mod query;
 pub mod send;
+pub mod sign;
 mod update_entry;
 pub mod validation;
+pub mod validation_receipt;
 mod zome_api_globals;
 
-pub use self::{call::*, query::*, update_entry::*, zome_api_globals::*};
+pub use self::{call::*, commit_entry::*, query::*, update_entry::*, zome_api_globals::*};
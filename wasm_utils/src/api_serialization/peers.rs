@@ -0,0 +1,20 @@
+use holochain_core_types::{error::HolochainError, json::*};
+
+/// A peer this node knows about, as tracked in the peer store of the network state
+#[derive(Deserialize, Clone, PartialEq, Debug, Serialize, DefaultJson)]
+pub struct PeerInfo {
+    pub agent_id: String,
+    /// Location of the agent on the ring of the address space
+    pub location: u32,
+    /// How far around its location the agent holds addresses, as far as we can tell
+    /// from the agents we know. u32::MAX means the agent holds everything.
+    pub arc_radius: u32,
+    /// When we last heard from the agent, in milliseconds since the Unix epoch
+    pub last_seen: i64,
+}
+
+/// Struct for the result of Zome API function get_peers()
+#[derive(Deserialize, Clone, PartialEq, Debug, Serialize, DefaultJson)]
+pub struct GetPeersResult {
+    pub peers: Vec<PeerInfo>,
+}
@@ -0,0 +1,8 @@
+use holochain_core_types::{error::HolochainError, json::*};
+
+/// Struct for input data received when Zome API function property() is invoked.
+/// Without a name the whole properties object of the DNA gets returned.
+#[derive(Deserialize, Clone, PartialEq, Debug, Serialize, DefaultJson)]
+pub struct PropertyArgs {
+    pub name: Option<String>,
+}
@@ -0,0 +1,17 @@
+use holochain_core_types::{
+    cas::content::Address, error::HolochainError, json::*, signature::Signature,
+};
+
+/// Struct for input data received when Zome API function sign() is invoked
+#[derive(Deserialize, Clone, PartialEq, Debug, Serialize, DefaultJson)]
+pub struct SignArgs {
+    pub payload: String,
+}
+
+/// Struct for input data received when Zome API function verify_signature() is invoked
+#[derive(Deserialize, Clone, PartialEq, Debug, Serialize, DefaultJson)]
+pub struct VerifySignatureArgs {
+    pub pub_key: Address,
+    pub payload: String,
+    pub signature: Signature,
+}
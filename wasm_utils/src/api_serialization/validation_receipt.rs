@@ -0,0 +1,62 @@
+use holochain_core_types::{
+    cas::content::{Address, AddressableContent, Content},
+    error::HolochainError,
+    json::*,
+    signature::Signature,
+};
+use std::convert::TryFrom;
+
+/// Prefix of what holders sign when they send a validation receipt.
+/// Agents never sign anything else with this prefix.
+pub const VALIDATION_RECEIPT_SIGNATURE_DOMAIN: &str = "holochain-validation-receipt:";
+
+/// Statement of a holder about an entry that got published to it, signed by the holder.
+/// Holders send it back to the author after validating the entry.
+#[derive(Deserialize, Clone, PartialEq, Debug, Serialize, DefaultJson)]
+pub struct ValidationReceipt {
+    pub entry_address: Address,
+    /// The agent that validated the entry
+    pub holder: Address,
+    /// Ok if the entry is valid, the reason why it is not otherwise
+    pub result: Result<(), String>,
+    /// When the holder signed the receipt, in milliseconds since the epoch.
+    /// Only the latest receipt of a holder counts.
+    pub signed_at: i64,
+    /// Signature of the holder over the data returned by signed_data()
+    pub signature: Signature,
+}
+
+impl ValidationReceipt {
+    /// What the holder signs, see VALIDATION_RECEIPT_SIGNATURE_DOMAIN
+    pub fn signed_data(
+        entry_address: &Address,
+        holder: &Address,
+        signed_at: i64,
+        result: &Result<(), String>,
+    ) -> String {
+        let result = match result {
+            Ok(()) => "valid".to_string(),
+            Err(reason) => format!("invalid:{}", reason),
+        };
+        format!(
+            "{}{}:{}:{}:{}",
+            VALIDATION_RECEIPT_SIGNATURE_DOMAIN, entry_address, holder, signed_at, result
+        )
+    }
+}
+
+impl AddressableContent for ValidationReceipt {
+    fn content(&self) -> Content {
+        self.to_owned().into()
+    }
+
+    fn try_from_content(content: &Content) -> Result<Self, HolochainError> {
+        ValidationReceipt::try_from(content.to_owned())
+    }
+}
+
+/// Struct for the result of Zome API function get_validation_receipts()
+#[derive(Deserialize, Clone, PartialEq, Debug, Serialize, DefaultJson)]
+pub struct GetValidationReceiptsResult {
+    pub receipts: Vec<ValidationReceipt>,
+}